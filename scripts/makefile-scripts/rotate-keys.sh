@@ -0,0 +1,32 @@
+#!/bin/bash
+
+# Colors for output
+GREEN='\033[0;32m'
+YELLOW='\033[1;33m'
+RED='\033[0;31m'
+NC='\033[0m' # No Color
+
+API_URL="${API_URL:-http://localhost:4000}"
+
+if [ -z "$ADMIN_TOKEN" ]; then
+    echo -e "${RED}ADMIN_TOKEN is not set${NC}"
+    exit 1
+fi
+
+echo -e "${YELLOW}Starting key rotation...${NC}"
+curl -s -X POST -H "X-Admin-Token: $ADMIN_TOKEN" "$API_URL/keys/rotate"
+echo ""
+
+sleep 1
+
+# Postęp zapisywany jest po każdej partii; przerwaną rotację wznawia ponowne uruchomienie
+while true; do
+    STATUS=$(curl -s -H "X-Admin-Token: $ADMIN_TOKEN" "$API_URL/keys/rotation")
+    echo "$STATUS"
+    if echo "$STATUS" | grep -q '"running":false'; then
+        break
+    fi
+    sleep 2
+done
+
+echo -e "${GREEN}✓ Key rotation finished - check failed counts above before removing ENCRYPTION_KEY_PREVIOUS${NC}"
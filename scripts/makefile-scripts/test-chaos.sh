@@ -0,0 +1,4 @@
+#!/bin/bash
+
+echo "$(tput setaf 3)🧪 Running tests with fault injection hooks enabled...$(tput sgr0)"
+cd src-tauri && FAULT_INJECTION="${FAULT_INJECTION:-db:fail=0.3,redis:fail=0.5,llm:fail=0.5,spawn:delay=0.2@200ms}" cargo test --features fault_injection --verbose
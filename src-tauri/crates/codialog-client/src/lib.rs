@@ -0,0 +1,597 @@
+//! Klient HTTP serwera automatyzacji Codialog z typowanymi metodami dla tras API.
+//! Obsługuje tylko transport TCP (API_TRANSPORT=tcp); trasy powiązane z oknem aplikacji
+//! wymagają nonce instancji (`with_instance_nonce`), administracyjne - tokenu (`with_admin_token`).
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+pub use codialog_types as types;
+
+use codialog_types::automation::{
+    AutofillAcceptRequest, AutofillScriptRequest, BudgetLimits, CancelRunRequest, DebugBreakpointsRequest, DebugCommandRequest, DslRequest, DslResponse, FieldPolicies, InspectSelectorRequest,
+    JobDebugRequest, LintRequest, PageAnalyzeRequest, PageClickRequest, PageEvaluateRequest, PageFillRequest, PageNavigateRequest, PageScreenshotRequest, PromptTemplateRequest, RememberChoiceRequest, ReplayRunRequest, RunScriptRequest, ScheduleRequest, TextDslRequest, TextDslResponse,
+};
+use codialog_types::session::{SessionRequest, SessionResponse};
+use codialog_types::system::{
+    ArchiveRestoreRequest, ArtifactGcRequest, HealthResponse, LogResponse, MaintenanceWindowRequest, SystemConfigRequest, TaguiInstallRequest,
+};
+use codialog_types::vault::{BitwardenLoginRequest, BitwardenUnlockRequest, CredentialImportRequest, CredentialsResponse, VaultExportRequest};
+use codialog_types::{ADMIN_TOKEN_HEADER, INSTANCE_NONCE_HEADER};
+
+/// Domyślny adres serwera (API_HOST/API_PORT)
+pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:4000";
+
+/// Format raportu `/rpa/runs/:id/report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Pdf,
+}
+
+#[derive(Debug, Clone)]
+pub struct CodialogClient {
+    base_url: String,
+    http: reqwest::Client,
+    admin_token: Option<String>,
+    instance_nonce: Option<String>,
+}
+
+impl Default for CodialogClient {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE_URL)
+    }
+}
+
+impl CodialogClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            admin_token: None,
+            instance_nonce: None,
+        }
+    }
+
+    /// Własny klient reqwest (timeouty, proxy)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    pub fn with_instance_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.instance_nonce = Some(nonce.into());
+        self
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self.http.request(method, format!("{}{}", self.base_url, path));
+        if let Some(token) = &self.admin_token {
+            request = request.header(ADMIN_TOKEN_HEADER, token);
+        }
+        if let Some(nonce) = &self.instance_nonce {
+            request = request.header(INSTANCE_NONCE_HEADER, nonce);
+        }
+        request
+    }
+
+    /// Wysyła zapytanie; odpowiedź spoza 2xx zamienia na błąd z polem `error` serwera
+    async fn send_raw(&self, request: RequestBuilder) -> Result<reqwest::Response> {
+        let response = request.send().await.context("Request to the Codialog API failed")?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|value| value.get("error").and_then(Value::as_str).map(str::to_string))
+            .unwrap_or(body);
+        bail!("Codialog API returned {}: {}", status, message)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        self.send_raw(request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Unexpected response from the Codialog API: {}", e))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        self.send(self.request(Method::GET, path).query(query)).await
+    }
+
+    async fn post<T: DeserializeOwned, B: Serialize + ?Sized>(&self, path: &str, body: &B) -> Result<T> {
+        self.send(self.request(Method::POST, path).json(body)).await
+    }
+
+    async fn post_empty<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.request(Method::POST, path)).await
+    }
+
+    async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.request(Method::DELETE, path)).await
+    }
+
+    async fn bytes(&self, path: &str, query: &[(&str, &str)]) -> Result<Vec<u8>> {
+        let response = self.send_raw(self.request(Method::GET, path).query(query)).await?;
+        Ok(response.bytes().await.context("Failed to read response body")?.to_vec())
+    }
+
+    // System
+
+    pub async fn health(&self) -> Result<HealthResponse> {
+        self.get("/health", &[]).await
+    }
+
+    pub async fn health_live(&self) -> Result<Value> {
+        self.get("/health/live", &[]).await
+    }
+
+    pub async fn health_ready(&self) -> Result<Value> {
+        self.get("/health/ready", &[]).await
+    }
+
+    pub async fn shutdown(&self) -> Result<Value> {
+        self.post_empty("/shutdown").await
+    }
+
+    pub async fn selftest(&self) -> Result<Value> {
+        self.post_empty("/selftest").await
+    }
+
+    pub async fn rotate_keys(&self) -> Result<Value> {
+        self.post_empty("/keys/rotate").await
+    }
+
+    pub async fn key_rotation_status(&self) -> Result<Value> {
+        self.get("/keys/rotation", &[]).await
+    }
+
+    pub async fn tagui_install_status(&self) -> Result<Value> {
+        self.get("/system/tagui/install", &[]).await
+    }
+
+    pub async fn install_tagui(&self, request: &TaguiInstallRequest) -> Result<Value> {
+        self.post("/system/tagui/install", request).await
+    }
+
+    pub async fn system_config(&self) -> Result<Value> {
+        self.get("/system/config", &[]).await
+    }
+
+    pub async fn update_system_config(&self, request: &SystemConfigRequest) -> Result<Value> {
+        self.post("/system/config", request).await
+    }
+
+    pub async fn postman_collection(&self) -> Result<Value> {
+        self.get("/system/postman", &[]).await
+    }
+
+    pub async fn archive_files(&self) -> Result<Value> {
+        self.get("/system/archive", &[]).await
+    }
+
+    pub async fn export_archive(&self) -> Result<Value> {
+        self.post_empty("/system/archive/export").await
+    }
+
+    pub async fn restore_archive(&self, request: &ArchiveRestoreRequest) -> Result<Value> {
+        self.post("/system/archive/restore", request).await
+    }
+
+    // Scheduler
+
+    pub async fn maintenance_windows(&self, include_past: bool) -> Result<Value> {
+        self.get("/scheduler/maintenance", &[("include_past", if include_past { "true" } else { "false" })]).await
+    }
+
+    pub async fn create_maintenance_window(&self, request: &MaintenanceWindowRequest) -> Result<Value> {
+        self.post("/scheduler/maintenance", request).await
+    }
+
+    pub async fn delete_maintenance_window(&self, window_id: &str) -> Result<Value> {
+        self.delete(&format!("/scheduler/maintenance/{}", window_id)).await
+    }
+
+    pub async fn schedules(&self) -> Result<Value> {
+        self.get("/scheduler/schedules", &[]).await
+    }
+
+    pub async fn create_schedule(&self, request: &ScheduleRequest) -> Result<Value> {
+        self.post("/scheduler/schedules", request).await
+    }
+
+    pub async fn delete_schedule(&self, schedule_id: &str) -> Result<Value> {
+        self.delete(&format!("/scheduler/schedules/{}", schedule_id)).await
+    }
+
+    pub async fn budgets(&self) -> Result<Value> {
+        self.get("/scheduler/budgets", &[]).await
+    }
+
+    /// `owner_type`: "schedule" albo "user"
+    pub async fn set_budget_limits(&self, owner_type: &str, owner_id: &str, limits: &BudgetLimits) -> Result<Value> {
+        self.post(&format!("/scheduler/budgets/{}/{}", owner_type, owner_id), limits).await
+    }
+
+    pub async fn resume_budget(&self, owner_type: &str, owner_id: &str) -> Result<Value> {
+        self.post_empty(&format!("/scheduler/budgets/{}/{}/resume", owner_type, owner_id)).await
+    }
+
+    /// `group_by`: "day" albo "session"
+    pub async fn llm_usage(&self, group_by: &str, days: u32) -> Result<Value> {
+        self.get("/llm/usage", &[("group_by", group_by), ("days", &days.to_string())]).await
+    }
+
+    // DSL
+
+    pub async fn generate_dsl(&self, request: &DslRequest) -> Result<DslResponse> {
+        self.post("/dsl/generate", request).await
+    }
+
+    pub async fn generate_dsl_from_text(&self, request: &TextDslRequest) -> Result<TextDslResponse> {
+        self.post("/dsl/from-text", request).await
+    }
+
+    pub async fn prompt_templates(&self) -> Result<Value> {
+        self.get("/dsl/prompts", &[]).await
+    }
+
+    /// `form_type`: "job_application", "registration", "checkout" albo "generic"; `language`: "pl" albo "en"
+    pub async fn save_prompt_template(&self, form_type: &str, language: &str, request: &PromptTemplateRequest) -> Result<Value> {
+        self.post(&format!("/dsl/prompts/{}/{}", form_type, language), request).await
+    }
+
+    pub async fn reset_prompt_template(&self, form_type: &str, language: &str) -> Result<Value> {
+        self.delete(&format!("/dsl/prompts/{}/{}", form_type, language)).await
+    }
+
+    pub async fn lint_dsl(&self, request: &LintRequest) -> Result<Value> {
+        self.post("/dsl/lint", request).await
+    }
+
+    // Automation
+
+    pub async fn run_script(&self, request: &RunScriptRequest) -> Result<Value> {
+        self.post("/rpa/run", request).await
+    }
+
+    /// Filtry jak w `/rpa/runs`: session_id, status, script_hash, replay_of, limit, offset
+    pub async fn list_runs(&self, filters: &[(&str, &str)]) -> Result<Value> {
+        self.get("/rpa/runs", filters).await
+    }
+
+    pub async fn get_run(&self, run_id: &str) -> Result<Value> {
+        self.get(&format!("/rpa/runs/{}", run_id), &[]).await
+    }
+
+    pub async fn replay_run(&self, run_id: &str, request: &ReplayRunRequest) -> Result<Value> {
+        self.post(&format!("/rpa/runs/{}/replay", run_id), request).await
+    }
+
+    pub async fn run_report(&self, run_id: &str, format: ReportFormat) -> Result<Vec<u8>> {
+        let format = match format {
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        };
+        self.bytes(&format!("/rpa/runs/{}/report", run_id), &[("format", format)]).await
+    }
+
+    /// Porównanie krok po kroku dwóch przebiegów tego samego skryptu
+    pub async fn compare_runs(&self, run_a: &str, run_b: &str) -> Result<Value> {
+        self.get("/runs/compare", &[("a", run_a), ("b", run_b)]).await
+    }
+
+    pub async fn cancel_run(&self, request: &CancelRunRequest) -> Result<Value> {
+        self.post("/rpa/cancel", request).await
+    }
+
+    pub async fn run_status(&self, run_id: &str) -> Result<Value> {
+        self.get("/rpa/status", &[("run_id", run_id)]).await
+    }
+
+    pub async fn enqueue_job(&self, request: &RunScriptRequest) -> Result<Value> {
+        self.post("/rpa/jobs", request).await
+    }
+
+    pub async fn get_job(&self, job_id: &str) -> Result<Value> {
+        self.get(&format!("/rpa/jobs/{}", job_id), &[]).await
+    }
+
+    pub async fn job_trace(&self, job_id: &str) -> Result<Value> {
+        self.get(&format!("/rpa/jobs/{}/trace", job_id), &[]).await
+    }
+
+    pub async fn run_artifacts(&self, run_id: &str) -> Result<Value> {
+        self.get("/rpa/artifacts", &[("run_id", run_id)]).await
+    }
+
+    pub async fn download_artifact(&self, artifact_id: &str) -> Result<Vec<u8>> {
+        self.bytes(&format!("/rpa/artifacts/{}", artifact_id), &[]).await
+    }
+
+    // Debugger
+
+    pub async fn debug_start(&self, request: &RunScriptRequest) -> Result<Value> {
+        self.post("/rpa/debug/start", request).await
+    }
+
+    pub async fn debug_step(&self, request: &DebugCommandRequest) -> Result<Value> {
+        self.post("/rpa/debug/step", request).await
+    }
+
+    pub async fn debug_continue(&self, request: &DebugCommandRequest) -> Result<Value> {
+        self.post("/rpa/debug/continue", request).await
+    }
+
+    pub async fn debug_status(&self, debug_id: &str) -> Result<Value> {
+        self.get(&format!("/rpa/debug/{}", debug_id), &[]).await
+    }
+
+    pub async fn debug_job(&self, job_id: &str, request: &JobDebugRequest) -> Result<Value> {
+        self.post(&format!("/rpa/jobs/{}/debug", job_id), request).await
+    }
+
+    pub async fn job_debug_status(&self, job_id: &str) -> Result<Value> {
+        self.get(&format!("/rpa/jobs/{}/debug", job_id), &[]).await
+    }
+
+    pub async fn job_debug_pause(&self, job_id: &str) -> Result<Value> {
+        self.post_empty(&format!("/rpa/jobs/{}/debug/pause", job_id)).await
+    }
+
+    pub async fn job_debug_resume(&self, job_id: &str) -> Result<Value> {
+        self.post_empty(&format!("/rpa/jobs/{}/debug/resume", job_id)).await
+    }
+
+    pub async fn job_debug_step(&self, job_id: &str) -> Result<Value> {
+        self.post_empty(&format!("/rpa/jobs/{}/debug/step", job_id)).await
+    }
+
+    pub async fn job_debug_breakpoints(&self, job_id: &str, request: &DebugBreakpointsRequest) -> Result<Value> {
+        self.post(&format!("/rpa/jobs/{}/debug/breakpoints", job_id), request).await
+    }
+
+    pub async fn job_debug_inspect(&self, job_id: &str, request: &InspectSelectorRequest) -> Result<Value> {
+        self.post(&format!("/rpa/jobs/{}/debug/inspect", job_id), request).await
+    }
+
+    // Artifacts, page analysis and replay bundles
+
+    pub async fn list_artifacts(&self, owner_type: &str, owner_id: &str) -> Result<Value> {
+        self.get("/artifacts", &[("owner_type", owner_type), ("owner_id", owner_id)]).await
+    }
+
+    pub async fn artifacts_gc(&self, request: &ArtifactGcRequest) -> Result<Value> {
+        self.post("/artifacts/gc", request).await
+    }
+
+    pub async fn analyze_page(&self) -> Result<Value> {
+        self.get("/page/analyze", &[]).await
+    }
+
+    pub async fn analyze_page_with_auth(&self, request: &PageAnalyzeRequest) -> Result<Value> {
+        self.post("/page/analyze", request).await
+    }
+
+    pub async fn capture_page(&self, request: &PageScreenshotRequest) -> Result<Value> {
+        self.post("/page/screenshot", request).await
+    }
+
+    // Sterowanie przeglądarką analizy (wymaga nonce instancji)
+
+    pub async fn page_navigate(&self, request: &PageNavigateRequest) -> Result<Value> {
+        self.post("/page/navigate", request).await
+    }
+
+    pub async fn page_click(&self, request: &PageClickRequest) -> Result<Value> {
+        self.post("/page/click", request).await
+    }
+
+    pub async fn page_fill(&self, request: &PageFillRequest) -> Result<Value> {
+        self.post("/page/fill", request).await
+    }
+
+    pub async fn page_evaluate(&self, request: &PageEvaluateRequest) -> Result<Value> {
+        self.post("/page/evaluate", request).await
+    }
+
+    pub async fn close_page_session(&self) -> Result<Value> {
+        self.delete("/page/session").await
+    }
+
+    pub async fn replay_bundle(&self, replay_id: &str) -> Result<Value> {
+        self.get(&format!("/replay/{}", replay_id), &[]).await
+    }
+
+    pub async fn run_replay_bundle(&self, replay_id: &str) -> Result<Value> {
+        self.post_empty(&format!("/replay/{}/run", replay_id)).await
+    }
+
+    // Logs (`/logs/tagui/tail` to strumień SSE - poza zakresem klienta)
+
+    pub async fn logs(&self, log_type: &str, lines: usize) -> Result<LogResponse> {
+        self.get("/logs", &[("log_type", log_type), ("lines", &lines.to_string())]).await
+    }
+
+    pub async fn log_stats(&self) -> Result<LogResponse> {
+        self.get("/logs/stats", &[]).await
+    }
+
+    /// Strona logu; `cursor` z poprzedniej odpowiedzi przesuwa się wstecz
+    pub async fn log_page(&self, log_type: &str, limit: usize, cursor: Option<&str>) -> Result<Value> {
+        let limit = limit.to_string();
+        let mut query = vec![("log_type", log_type), ("limit", limit.as_str())];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor));
+        }
+        self.get("/logs/page", &query).await
+    }
+
+    pub async fn clear_logs(&self) -> Result<LogResponse> {
+        self.post_empty("/logs/clear").await
+    }
+
+    // Bitwarden and sessions
+
+    pub async fn bitwarden_login(&self, request: &BitwardenLoginRequest) -> Result<SessionResponse> {
+        self.post("/bitwarden/login", request).await
+    }
+
+    pub async fn bitwarden_unlock(&self, request: &BitwardenUnlockRequest) -> Result<Value> {
+        self.post("/bitwarden/unlock", request).await
+    }
+
+    pub async fn credentials(&self) -> Result<CredentialsResponse> {
+        self.get("/bitwarden/credentials", &[]).await
+    }
+
+    pub async fn credentials_for_url(&self, url: &str) -> Result<CredentialsResponse> {
+        self.get("/bitwarden/credentials/url", &[("url", url)]).await
+    }
+
+    /// Podgląd (bez `confirm`) albo import eksportu CSV do vault
+    pub async fn import_credentials(&self, request: &CredentialImportRequest) -> Result<Value> {
+        self.post("/bitwarden/import", request).await
+    }
+
+    pub async fn export_vault(&self, request: &VaultExportRequest) -> Result<Value> {
+        self.post("/bitwarden/export", request).await
+    }
+
+    pub async fn biometric_status(&self) -> Result<Value> {
+        self.get("/bitwarden/biometric", &[]).await
+    }
+
+    /// Wymaga vault odblokowanego hasłem głównym; zapisuje jego sesję do odblokowania biometrycznego
+    pub async fn enable_biometric_unlock(&self) -> Result<Value> {
+        self.post_empty("/bitwarden/biometric/enable").await
+    }
+
+    /// Pokazuje okno Windows Hello / Touch ID na komputerze z aplikacją
+    pub async fn biometric_unlock(&self) -> Result<Value> {
+        self.post_empty("/bitwarden/biometric/unlock").await
+    }
+
+    pub async fn disable_biometric_unlock(&self) -> Result<Value> {
+        self.delete("/bitwarden/biometric").await
+    }
+
+    pub async fn autofill_offers(&self) -> Result<Value> {
+        self.get("/autofill/offers", &[]).await
+    }
+
+    /// Uruchamia wybrany skrypt albo logowanie z vault na stronie, dla której powstała oferta
+    pub async fn accept_autofill_offer(&self, offer_id: &str, request: &AutofillAcceptRequest) -> Result<Value> {
+        self.post(&format!("/autofill/offers/{}/accept", offer_id), request).await
+    }
+
+    pub async fn dismiss_autofill_offer(&self, offer_id: &str) -> Result<Value> {
+        self.post_empty(&format!("/autofill/offers/{}/dismiss", offer_id)).await
+    }
+
+    pub async fn autofill_scripts(&self) -> Result<Value> {
+        self.get("/autofill/scripts", &[]).await
+    }
+
+    pub async fn create_autofill_script(&self, request: &AutofillScriptRequest) -> Result<Value> {
+        self.post("/autofill/scripts", request).await
+    }
+
+    pub async fn delete_autofill_script(&self, script_id: &str) -> Result<Value> {
+        self.delete(&format!("/autofill/scripts/{}", script_id)).await
+    }
+
+    /// Zapamiętane decyzje; `site` jako URL albo host
+    pub async fn choices(&self, site: Option<&str>) -> Result<Value> {
+        match site {
+            Some(site) => self.get("/choices", &[("site", site)]).await,
+            None => self.get("/choices", &[]).await,
+        }
+    }
+
+    pub async fn remember_choice(&self, request: &RememberChoiceRequest) -> Result<Value> {
+        self.post("/choices", request).await
+    }
+
+    /// Usuwa decyzje pasujące do filtrów, np. `[("site", "portal.example.com"), ("key", "phone")]`
+    pub async fn clear_choices(&self, filters: &[(&str, &str)]) -> Result<Value> {
+        self.send(self.request(Method::DELETE, "/choices").query(filters)).await
+    }
+
+    pub async fn field_policies(&self) -> Result<Value> {
+        self.get("/policies", &[]).await
+    }
+
+    pub async fn save_field_policies(&self, policies: &FieldPolicies) -> Result<Value> {
+        self.post("/policies", policies).await
+    }
+
+    pub async fn clear_field_policies(&self) -> Result<Value> {
+        self.send(self.request(Method::DELETE, "/policies")).await
+    }
+
+    pub async fn create_session(&self, request: &SessionRequest) -> Result<SessionResponse> {
+        self.post("/session/create", request).await
+    }
+
+    pub async fn get_session(&self, session_id: &str) -> Result<SessionResponse> {
+        self.get("/session/get", &[("session_id", session_id)]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Jednorazowy serwer HTTP: zwraca odebrane zapytanie i odpowiada `status` z ciałem `body`
+    async fn serve_once(status: &'static str, body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0u8; 8192];
+            let read = socket.read(&mut buffer).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_string()
+        });
+        (base_url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_typed_request_and_error_mapping() {
+        let (base_url, server) = serve_once("200 OK", r##"{"script": "click \"#submit\""}"##).await;
+        let client = CodialogClient::new(format!("{}/", base_url)).with_instance_nonce("nonce-1");
+        let request = DslRequest { html: "<form></form>".to_string(), user_data: serde_json::json!({}), session_id: None, form_type: None, language: None, locale: None, url: None, strategies: None, policies: None, llm: Default::default() };
+        let response = client.generate_dsl(&request).await.unwrap();
+        assert_eq!(response.script, "click \"#submit\"");
+        assert_eq!(response.replay_id, None);
+
+        let received = server.await.unwrap().to_lowercase();
+        assert!(received.starts_with("post /dsl/generate http/1.1"));
+        assert!(received.contains("x-instance-nonce: nonce-1"));
+        assert!(!received.contains(ADMIN_TOKEN_HEADER));
+
+        let (base_url, server) = serve_once("404 Not Found", r#"{"success": false, "error": "Run not found"}"#).await;
+        let error = CodialogClient::new(base_url).get_run("missing").await.unwrap_err();
+        assert!(error.to_string().contains("Run not found"));
+        assert!(server.await.unwrap().starts_with("GET /rpa/runs/missing"));
+    }
+}
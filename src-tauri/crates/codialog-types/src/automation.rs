@@ -0,0 +1,884 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::vault::SecretRef;
+
+/// Przeglądarka, w której TagUI wykonuje skrypt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserMode {
+    Headless,
+    /// Widoczne okno Chrome - do debugowania skryptów
+    #[default]
+    Headed,
+    Edge,
+    Firefox,
+}
+
+impl BrowserMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "headless" => Some(BrowserMode::Headless),
+            "headed" | "chrome" => Some(BrowserMode::Headed),
+            "edge" => Some(BrowserMode::Edge),
+            "firefox" => Some(BrowserMode::Firefox),
+            _ => None,
+        }
+    }
+
+    /// TagUI invocation flag passed after the script path
+    pub fn tagui_flag(&self) -> &'static str {
+        match self {
+            BrowserMode::Headless => "headless",
+            BrowserMode::Headed => "chrome",
+            BrowserMode::Edge => "edge",
+            BrowserMode::Firefox => "firefox",
+        }
+    }
+}
+
+/// Silnik wykonujący skrypty DSL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionBackend {
+    /// Zewnętrzny proces TagUI
+    #[default]
+    Tagui,
+    /// Native interpreter over CDP; Chrome only, no TagUI/npm installation needed
+    #[serde(alias = "native")]
+    Cdp,
+}
+
+impl ExecutionBackend {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "tagui" => Some(ExecutionBackend::Tagui),
+            "cdp" | "native" => Some(ExecutionBackend::Cdp),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionBackend::Tagui => "tagui",
+            ExecutionBackend::Cdp => "cdp",
+        }
+    }
+}
+
+/// Tempo wykonania: przerwy między krokami i sposób wpisywania tekstu
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacingProfile {
+    /// Bez przerw; TagUI w trybie `turbo` - dla przebiegów wsadowych
+    Fast,
+    #[default]
+    Normal,
+    /// Losowe przerwy między krokami i wpisywanie znak po znaku - dla stron walidujących pisanie
+    #[serde(alias = "human-like", alias = "human_like")]
+    Human,
+}
+
+impl PacingProfile {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "fast" | "turbo" => Some(PacingProfile::Fast),
+            "normal" => Some(PacingProfile::Normal),
+            "human" | "human-like" | "human_like" => Some(PacingProfile::Human),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PacingProfile::Fast => "fast",
+            PacingProfile::Normal => "normal",
+            PacingProfile::Human => "human",
+        }
+    }
+
+    /// Dodatkowa flaga TagUI przekazywana po trybie przeglądarki
+    pub fn tagui_flag(&self) -> Option<&'static str> {
+        match self {
+            PacingProfile::Fast => Some("turbo"),
+            PacingProfile::Normal | PacingProfile::Human => None,
+        }
+    }
+}
+
+/// Rodzaj formularza - wybiera szablon promptu generowania DSL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormType {
+    JobApplication,
+    Registration,
+    Checkout,
+    #[default]
+    Generic,
+}
+
+impl FormType {
+    pub const ALL: [FormType; 4] = [FormType::JobApplication, FormType::Registration, FormType::Checkout, FormType::Generic];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "job_application" | "job" => Some(FormType::JobApplication),
+            "registration" | "signup" => Some(FormType::Registration),
+            "checkout" | "payment" => Some(FormType::Checkout),
+            "generic" => Some(FormType::Generic),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FormType::JobApplication => "job_application",
+            FormType::Registration => "registration",
+            FormType::Checkout => "checkout",
+            FormType::Generic => "generic",
+        }
+    }
+}
+
+/// Język promptu generowania DSL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptLanguage {
+    #[default]
+    Pl,
+    En,
+}
+
+impl PromptLanguage {
+    pub const ALL: [PromptLanguage; 2] = [PromptLanguage::Pl, PromptLanguage::En];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "pl" | "polish" => Some(PromptLanguage::Pl),
+            "en" | "english" => Some(PromptLanguage::En),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromptLanguage::Pl => "pl",
+            PromptLanguage::En => "en",
+        }
+    }
+}
+
+/// Strategia generowania DSL; `/dsl/generate` próbuje ich po kolei, aż któraś da skrypt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationStrategy {
+    /// Wbudowane mapy selektorów znanych platform rekrutacyjnych (Greenhouse, Lever, Workday, SmartRecruiters)
+    Platform,
+    /// Model językowy skonfigurowanego dostawcy
+    Llm,
+    /// Analiza pól formularza i dopasowanie danych użytkownika
+    Enhanced,
+    /// Kliknięcie przycisku wysłania formularza
+    Simple,
+    /// Akceptacja cookies i logowanie, bez wypełniania pól
+    Basic,
+}
+
+impl GenerationStrategy {
+    /// Kolejność bez GENERATION_FALLBACKS
+    pub const DEFAULT_CHAIN: [GenerationStrategy; 5] = [
+        GenerationStrategy::Platform,
+        GenerationStrategy::Llm,
+        GenerationStrategy::Enhanced,
+        GenerationStrategy::Simple,
+        GenerationStrategy::Basic,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "platform" | "ats" => Some(GenerationStrategy::Platform),
+            "llm" => Some(GenerationStrategy::Llm),
+            "enhanced" | "heuristic" => Some(GenerationStrategy::Enhanced),
+            "simple" => Some(GenerationStrategy::Simple),
+            "basic" => Some(GenerationStrategy::Basic),
+            _ => None,
+        }
+    }
+
+    /// Łańcuch z listy oddzielonej przecinkami, np. "enhanced,basic"; powtórzenia są pomijane
+    pub fn parse_chain(value: &str) -> Result<Vec<Self>, String> {
+        let mut chain = Vec::new();
+        for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let strategy = Self::parse(name).ok_or_else(|| format!("unknown generation strategy '{}'", name))?;
+            if !chain.contains(&strategy) {
+                chain.push(strategy);
+            }
+        }
+        if chain.is_empty() {
+            return Err("must name at least one generation strategy".to_string());
+        }
+        Ok(chain)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GenerationStrategy::Platform => "platform",
+            GenerationStrategy::Llm => "llm",
+            GenerationStrategy::Enhanced => "enhanced",
+            GenerationStrategy::Simple => "simple",
+            GenerationStrategy::Basic => "basic",
+        }
+    }
+}
+
+/// Jak powstał skrypt z `/dsl/generate`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationInfo {
+    /// Strategia, która dała skrypt; brak, gdy żadna z łańcucha nie zadziałała (skrypt awaryjny)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<GenerationStrategy>,
+    /// Skrypt wzięty z cache - `strategy` to strategia, która go wtedy wygenerowała
+    #[serde(default)]
+    pub cached: bool,
+    /// Przeterminowany wpis cache dla niezmienionej struktury strony; nowy skrypt powstaje w tle
+    #[serde(default)]
+    pub stale: bool,
+    /// Łańcuch faktycznie użyty: z żądania lub konfiguracji, bez LLM, gdy jest wyłączony
+    pub chain: Vec<GenerationStrategy>,
+    /// Klucz cache strony - historia wersji skryptu pod `/dsl/cache/:key/history`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_key: Option<String>,
+    /// Niedawny powód, dla którego model nie dał poprawnego skryptu dla tej struktury strony - model jest pomijany
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocker: Option<String>,
+}
+
+/// `/dsl/cache/:key/rollback`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DslRollbackRequest {
+    pub version: i32,
+    /// Przypięty skrypt nie wygasa i nie jest zastępowany nowym generowaniem; `false` zdejmuje przypięcie
+    #[serde(default = "default_pin")]
+    pub pin: bool,
+}
+
+fn default_pin() -> bool {
+    true
+}
+
+/// Podpisana paczka wpisów cache DSL do przeniesienia na inną instalację (`/dsl/cache/export`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DslCacheBundle {
+    pub format: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Domeny, dla których wyeksportowano wpisy (razem z subdomenami)
+    pub domains: Vec<String>,
+    pub entries: Vec<DslCacheEntry>,
+    /// HMAC-SHA256 (hex) treści paczki kluczem DSL_CACHE_SIGNING_KEY
+    #[serde(default)]
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DslCacheEntry {
+    pub cache_key: String,
+    pub site: String,
+    pub script: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<GenerationStrategy>,
+    /// Sama struktura strony (bez wartości pól), do podpowiedzi few-shot na instalacji docelowej
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html_structure: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `/dsl/cache/export`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DslCacheExportRequest {
+    pub domains: Vec<String>,
+}
+
+/// Co zrobić, gdy klucz z paczki ma już inny skrypt na tej instalacji
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheConflictPolicy {
+    /// Zostaw lokalny skrypt
+    #[default]
+    Skip,
+    /// Weź skrypt z paczki, także zamiast przypiętego
+    Overwrite,
+    /// Weź nowszy z dwóch (przypięty lokalny zostaje)
+    Newer,
+}
+
+/// `/dsl/cache/import`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DslCacheImportRequest {
+    pub bundle: DslCacheBundle,
+    #[serde(default)]
+    pub on_conflict: CacheConflictPolicy,
+    /// Przypina zaimportowane skrypty, żeby nie wygasły po godzinie jak zwykłe wpisy cache
+    #[serde(default)]
+    pub pin: bool,
+}
+
+/// Treść szablonu promptu nadpisującego wbudowany (`{{html}}` i `{{user_data}}` jako miejsca na dane)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplateRequest {
+    pub body: String,
+}
+
+/// `/dsl/generate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DslRequest {
+    pub html: String,
+    pub user_data: serde_json::Value,
+    /// Sesja, do której `/llm/usage` przypisuje zużycie tokenów
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Szablon promptu; domyślnie rozpoznawany z pól formularza
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form_type: Option<FormType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<PromptLanguage>,
+    /// Locale użytkownika (np. `pl-PL`); razem z atrybutem `lang` strony ustala język treści i formaty wartości
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Adres strony; prompt dostaje przykłady udanych przebiegów z tej witryny. Domyślnie bieżący adres webview
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Kolejność strategii generowania; domyślnie GENERATION_FALLBACKS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategies: Option<Vec<GenerationStrategy>>,
+    /// Polityki pytań wrażliwych tylko dla tego generowania; nadpisują zapisane w `/policies`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policies: Option<FieldPolicies>,
+    /// Dostawca, model, temperatura i limit tokenów dla tego wywołania
+    #[serde(default, flatten)]
+    pub llm: LlmOptions,
+}
+
+/// Parametry modelu dla jednego wywołania; puste pola - ustawienia z konfiguracji (LLM_PROVIDER, LLM_MODEL)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LlmOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl LlmOptions {
+    /// Bez nadpisań - wywołanie jak z konfiguracji, wynik może trafić do cache
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DslResponse {
+    pub script: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_id: Option<String>,
+    /// Które pole wypełnia każdy klucz danych użytkownika i z jaką pewnością
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<Vec<FieldMapping>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<GenerationInfo>,
+}
+
+/// `/dsl/from-text` - skrypt z polecenia w języku naturalnym
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextDslRequest {
+    /// Np. "zaloguj się do LinkedIn i aplikuj na tę ofertę z moim zapisanym CV"
+    pub instruction: String,
+    /// HTML strony; domyślnie ostatnio analizowana strona webview
+    #[serde(default)]
+    pub html: Option<String>,
+    /// Adres strony; domyślnie bieżący adres webview
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Sesja, której dane skrypt używa jako `{{klucz}}` (np. `{{cv_path}}`)
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Dodatkowe dane; do modelu trafiają tylko nazwy kluczy
+    #[serde(default)]
+    pub user_data: serde_json::Value,
+}
+
+/// `/page/classify` - rodzaj strony i jej pola wymagane
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageClassifyRequest {
+    /// HTML strony; domyślnie ostatnio analizowana strona webview
+    #[serde(default)]
+    pub html: Option<String>,
+    /// Adres strony (rozpoznanie platform rekrutacyjnych po domenie); domyślnie bieżący adres webview
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Przy niepewnym wyniku heurystyk zapytaj model (tylko etykiety pól, bez wartości)
+    #[serde(default)]
+    pub llm: bool,
+    /// Sesja, do której `/llm/usage` przypisuje zużycie tokenów
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// `/page/navigate` - otwiera adres w sesji sterowania stroną
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageNavigateRequest {
+    pub url: String,
+}
+
+/// `/page/click`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageClickRequest {
+    pub selector: String,
+}
+
+/// `/page/fill` - czyści pole i wpisuje wartość
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageFillRequest {
+    pub selector: String,
+    pub value: String,
+}
+
+/// `/page/evaluate` - wyrażenie JavaScript; wynik musi dać się zserializować do JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageEvaluateRequest {
+    pub expression: String,
+}
+
+/// Ciasteczko ustawiane w przeglądarce analizy przed nawigacją; przyjmuje pola w formacie eksportu
+/// Playwright (`httpOnly`, `sameSite`, `expires`) i rozszerzeń przeglądarki (`expirationDate`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectedCookie {
+    pub name: String,
+    pub value: String,
+    /// Bez domeny ciasteczko dotyczy analizowanego adresu
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub secure: Option<bool>,
+    #[serde(default, alias = "httpOnly")]
+    pub http_only: Option<bool>,
+    /// Strict, Lax albo None
+    #[serde(default, alias = "sameSite")]
+    pub same_site: Option<String>,
+    /// Sekundy od epoki; brak albo wartość ujemna - ciasteczko sesyjne
+    #[serde(default, alias = "expirationDate")]
+    pub expires: Option<f64>,
+}
+
+/// Wpis localStorage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageEntry {
+    pub name: String,
+    pub value: String,
+}
+
+/// localStorage jednej witryny, np. `https://portal.example.com`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginStorage {
+    pub origin: String,
+    #[serde(default, alias = "localStorage")]
+    pub local_storage: Vec<StorageEntry>,
+}
+
+/// Stan zalogowania wstrzykiwany do przeglądarki CDP - ten sam kształt co `storageState` z Playwright
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthState {
+    #[serde(default)]
+    pub cookies: Vec<InjectedCookie>,
+    #[serde(default)]
+    pub origins: Vec<OriginStorage>,
+}
+
+impl AuthState {
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty() && self.origins.iter().all(|origin| origin.local_storage.is_empty())
+    }
+}
+
+/// `POST /page/analyze` - analiza strony za logowaniem
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageAnalyzeRequest {
+    /// Adres strony; domyślnie bieżący adres webview
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub auth_state: Option<AuthState>,
+    /// Stan zapisany jako JSON w polu elementu vault (zwykle `notes`), np. `{"item": "portal-cookies", "field": "notes"}`
+    #[serde(default)]
+    pub auth_state_ref: Option<SecretRef>,
+}
+
+/// `/page/screenshot` - zrzut strony (PNG i opcjonalnie PDF) zapisany jako artefakty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageScreenshotRequest {
+    /// Adres strony; domyślnie bieżący adres webview
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Cała strona (domyślnie) albo tylko widoczny obszar
+    #[serde(default = "default_full_page")]
+    pub full_page: bool,
+    #[serde(default)]
+    pub pdf: bool,
+    /// Przebieg, do którego artefaktów dołączyć zrzut; bez niego zrzut dostaje własny identyfikator
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+fn default_full_page() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextDslResponse {
+    pub script: String,
+    /// Zmienne użyte w skrypcie - do przekazania w `/rpa/run` przez `session_id` lub `variables`
+    pub variables: Vec<String>,
+    /// Użyte zmienne, których nie ma w sesji ani w `user_data`
+    #[serde(default)]
+    pub missing_variables: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_id: Option<String>,
+}
+
+/// Poniżej tej pewności UI prosi użytkownika o potwierdzenie dopasowania
+pub const LOW_CONFIDENCE: f32 = 0.7;
+
+/// Na jakiej podstawie klucz danych został przypisany do pola
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchSource {
+    /// Etykieta pola to znana nazwa klucza (także zlokalizowana)
+    Label,
+    /// Etykieta pola jest równa nazwie klucza, np. "postal_code" -> "Postal code"
+    KeyLabel,
+    /// Etykieta tylko zawiera znaną nazwę
+    LabelPartial,
+    /// Nazwa w id/name/klasie pola, bez pasującej etykiety
+    Selector,
+    /// Poprawka użytkownika zapamiętana dla witryny (`/choices`)
+    Remembered,
+}
+
+/// Dopasowanie klucza danych użytkownika do pola formularza
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldMapping {
+    pub key: String,
+    pub selector: String,
+    /// Widoczna etykieta pola
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 0.0-1.0
+    pub confidence: f32,
+    pub matched_by: MatchSource,
+}
+
+impl FieldMapping {
+    pub fn needs_review(&self) -> bool {
+        self.confidence < LOW_CONFIDENCE
+    }
+}
+
+/// `/dsl/lint`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintRequest {
+    pub script: String,
+    /// Zwraca też skrypt z zastosowanymi poprawkami
+    #[serde(default)]
+    pub fix: bool,
+}
+
+/// `/rpa/run`, `/rpa/jobs` i `/rpa/debug/start`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunScriptRequest {
+    pub script: String,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Wartości dla `{{nazwa}}` w skrypcie
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Sekrety rozwiązywane z Bitwarden w chwili wykonania, np. {"password": {"item": "Portal HR"}}
+    #[serde(default)]
+    pub secret_refs: HashMap<String, SecretRef>,
+    /// Sesja, której dane użytkownika są dostępne jako zmienne
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Zrzut ekranu po każdej komendzie, dostępny potem przez /rpa/artifacts?run_id=
+    #[serde(default)]
+    pub capture_screenshots: bool,
+    /// headless | headed | edge | firefox; domyślnie TAGUI_BROWSER_MODE
+    #[serde(default)]
+    pub browser_mode: Option<BrowserMode>,
+    /// Limit czasu tego przebiegu w sekundach; domyślnie TAGUI_RUN_TIMEOUT_SECS
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// tagui | cdp; domyślnie EXECUTION_BACKEND
+    #[serde(default)]
+    pub backend: Option<ExecutionBackend>,
+    /// fast | normal | human; domyślnie EXECUTION_PACING
+    #[serde(default)]
+    pub pacing: Option<PacingProfile>,
+    /// Strona startowa dla silnika CDP; domyślnie bieżący adres webview
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Przed uruchomieniem sprawdza w przeglądarce, czy selektory kroków istnieją na stronie startowej
+    #[serde(default)]
+    pub verify_selectors: bool,
+    /// Po błędzie kroku model pisze od nowa resztę skryptu na podstawie błędu i strony, a przebieg jest powtarzany
+    #[serde(default)]
+    pub self_heal: bool,
+    /// Limit prób naprawy tego przebiegu; domyślnie SELF_HEAL_MAX_ATTEMPTS
+    #[serde(default)]
+    pub max_heal_attempts: Option<u32>,
+    /// UUID przebiegu nadany przez klienta (tylko `/rpa/run`) - pozwala śledzić i anulować przebieg przez
+    /// `/rpa/status` i `/rpa/cancel`, zanim `/rpa/run` odpowie; domyślnie nowy
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// Zapisany przebieg powtarzany przez /rpa/runs/:id/replay; ustawia tylko serwer
+    #[serde(skip)]
+    pub replay_of: Option<String>,
+}
+
+/// `/rpa/runs/:id/replay`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayRunRequest {
+    /// Zastępuje sesję oryginalnego przebiegu (np. wygasłą po restarcie)
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// `/rpa/cancel`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelRunRequest {
+    pub run_id: String,
+}
+
+/// `/rpa/debug/step` i `/rpa/debug/continue`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugCommandRequest {
+    pub debug_id: String,
+}
+
+/// `POST /scheduler/schedules` - zadanie dodawane do kolejki w terminach wyrażenia
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRequest {
+    pub name: String,
+    pub script: String,
+    /// Np. "every weekday at 9", "first Monday of month at 8:30", "last business day of month at 17:00"
+    pub expression: String,
+    /// Strefa IANA, w której liczone są godziny i dni, np. "Europe/Warsaw"
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Dni bez uruchomień; nie liczą się też jako dni robocze
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+    /// Użytkownik, którego limity budżetu obejmują też zadania tego harmonogramu
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Strona startowa zadań; według jej witryny liczony jest odstęp między wysłaniami
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Limity harmonogramu lub użytkownika; brak wartości = bez limitu
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetLimits {
+    /// Zadania dodane do kolejki w ciągu doby (UTC)
+    #[serde(default)]
+    pub max_runs_per_day: Option<u32>,
+    /// Koszt wywołań LLM w ciągu doby (UTC), w USD według cen LLM_PRICE_*_PER_MTOK
+    #[serde(default)]
+    pub max_llm_spend_per_day: Option<f64>,
+    /// Nieudane przebiegi z rzędu, po których automatyzacja jest wstrzymywana do ręcznego wznowienia
+    #[serde(default)]
+    pub max_failures: Option<u32>,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+/// `/rpa/jobs/:id/debug` - uruchomienie skryptu zadania w debuggerze
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobDebugRequest {
+    /// Linie skryptu, przed którymi przebieg się zatrzymuje; bez breakpointów pauza po pierwszej komendzie
+    #[serde(default)]
+    pub breakpoints: Vec<usize>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub secret_refs: HashMap<String, SecretRef>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub browser_mode: Option<BrowserMode>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// `/rpa/jobs/:id/debug/breakpoints`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DebugBreakpointsRequest {
+    pub breakpoints: Vec<usize>,
+}
+
+/// `/rpa/jobs/:id/debug/inspect` - selektor CSS sprawdzany na stronie z bieżącej pauzy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectSelectorRequest {
+    pub selector: String,
+}
+
+/// Rodzaj decyzji zapamiętanej dla witryny
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChoiceKind {
+    /// Klucz danych użytkownika -> selektor pola formularza
+    FieldMapping,
+    /// Klucz -> ID elementu vault, gdy do witryny pasuje kilka danych logowania
+    Credential,
+}
+
+impl ChoiceKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "field_mapping" | "field" => Some(ChoiceKind::FieldMapping),
+            "credential" => Some(ChoiceKind::Credential),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChoiceKind::FieldMapping => "field_mapping",
+            ChoiceKind::Credential => "credential",
+        }
+    }
+}
+
+/// `POST /choices` - np. poprawione pole dla klucza `phone` na stronie `url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RememberChoiceRequest {
+    pub url: String,
+    pub kind: ChoiceKind,
+    pub key: String,
+    pub value: String,
+}
+
+/// Widełki wynagrodzenia w jednej walucie (kwoty miesięczne brutto lub tak, jak podaje je użytkownik)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SalaryRange {
+    /// Kod ISO 4217, np. `PLN`, `EUR`
+    pub currency: String,
+    pub min: u64,
+    pub max: u64,
+}
+
+/// Którą kwotę z widełek wpisywać w pole oczekiwań finansowych
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SalaryQuote {
+    Min,
+    Midpoint,
+    /// Górna granica - od niej zaczynają się negocjacje
+    #[default]
+    Max,
+    /// Całe widełki, np. `15000-18000`; pole liczbowe dostaje dolną granicę
+    Range,
+}
+
+/// Gotowość do przeprowadzki
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relocation {
+    Yes,
+    No,
+    Negotiable,
+}
+
+/// Odpowiedzi na wrażliwe pytania formularzy (oczekiwania finansowe, okres wypowiedzenia, relokacja),
+/// z których korzysta generator zamiast zostawiać pola puste albo wymyślać wartości.
+/// `GET`/`POST /policies`; pole `policies` w `/dsl/generate` nadpisuje je dla jednego generowania
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldPolicies {
+    /// Widełki w kolejnych walutach; wybierana jest waluta strony, a bez niej pierwsza
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub salary: Vec<SalaryRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salary_quote: Option<SalaryQuote>,
+    /// Okres wypowiedzenia w dniach; 0 - dostępny od zaraz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notice_period_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relocation: Option<Relocation>,
+}
+
+impl FieldPolicies {
+    pub fn is_empty(&self) -> bool {
+        self == &FieldPolicies::default()
+    }
+
+    /// Polityki z `overrides` zastępują te pola, które są w nim ustawione
+    pub fn merged(&self, overrides: &FieldPolicies) -> FieldPolicies {
+        FieldPolicies {
+            salary: if overrides.salary.is_empty() { self.salary.clone() } else { overrides.salary.clone() },
+            salary_quote: overrides.salary_quote.or(self.salary_quote),
+            notice_period_days: overrides.notice_period_days.or(self.notice_period_days),
+            relocation: overrides.relocation.or(self.relocation),
+        }
+    }
+}
+
+/// `POST /autofill/scripts` - skrypt proponowany, gdy webview otworzy stronę z tej samej witryny co `url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillScriptRequest {
+    pub name: String,
+    pub url: String,
+    pub script: String,
+}
+
+/// Pozycja oferty wybrana w `/autofill/offers/:id/accept`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AutofillChoice {
+    Script { script_id: String },
+    Credential { credential_id: String },
+}
+
+/// `/autofill/offers/:id/accept`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillAcceptRequest {
+    #[serde(flatten)]
+    pub choice: AutofillChoice,
+    /// Sesja, której dane użytkownika są dostępne jako zmienne skryptu
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_request_defaults() {
+        let request: RunScriptRequest = serde_json::from_str(r#"{"script": "click \".a\"", "backend": "native"}"#).unwrap();
+        assert_eq!(request.backend, Some(ExecutionBackend::Cdp));
+        assert!(!request.dry_run && !request.verify_selectors);
+        assert_eq!(request.pacing, None);
+        let request: RunScriptRequest = serde_json::from_str(r#"{"script": "wait 1", "pacing": "human-like"}"#).unwrap();
+        assert_eq!(request.pacing, Some(PacingProfile::Human));
+        assert_eq!(PacingProfile::parse("Turbo").and_then(|pacing| pacing.tagui_flag()), Some("turbo"));
+
+        // replay_of nie przechodzi przez API w żadną stronę
+        let request = RunScriptRequest { replay_of: Some("run-1".to_string()), ..request };
+        assert!(serde_json::to_value(&request).unwrap().get("replay_of").is_none());
+    }
+}
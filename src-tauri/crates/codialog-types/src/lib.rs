@@ -0,0 +1,16 @@
+//! Typy zapytań i odpowiedzi API serwera automatyzacji, wspólne dla serwera (`codialog`)
+//! i klienta (`codialog-client`)
+
+pub mod automation;
+pub mod secret;
+pub mod session;
+pub mod system;
+pub mod vault;
+
+pub use secret::SecretString;
+
+/// Nagłówek, w którym frontend odsyła nonce bieżącej instancji
+pub const INSTANCE_NONCE_HEADER: &str = "x-instance-nonce";
+
+/// Nagłówek z tokenem administratora (ADMIN_TOKEN) dla tras administracyjnych
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Sekret w pamięci (hasło główne, token sesji Bitwarden, hasło z vault).
+/// The buffer is zeroed on drop and `Debug` never prints the value.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    /// Przejmuje wyjście procesu (np. `bw unlock --raw`) i zeruje oryginalny bufor
+    pub fn from_output(bytes: &mut Vec<u8>) -> Self {
+        let value = String::from_utf8_lossy(bytes).trim().to_string();
+        bytes.zeroize();
+        Self::new(value)
+    }
+
+    /// Jawna wartość - tylko w miejscu faktycznego użycia, nigdy do logów
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Serializes the plain value: only for payloads that must carry it
+/// (API responses with credentials, the Redis session cache)
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose_secret())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_not_printed() {
+        let secret: SecretString = serde_json::from_str("\"hunter2\"").unwrap();
+        assert_eq!(secret.expose_secret(), "hunter2");
+        assert_eq!(format!("{:?}", Some(&secret)), "Some([REDACTED])");
+
+        let mut output = b"session-token\n".to_vec();
+        let token = SecretString::from_output(&mut output);
+        assert_eq!(token.expose_secret(), "session-token");
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::secret::SecretString;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSession {
+    pub session_id: String,
+    pub user_id: String,
+    pub bitwarden_session: Option<SecretString>,
+    pub user_data: UserData,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    /// Skrót nonce instancji aplikacji, która wydała sesję (`instance::InstanceNonce::binding`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_binding: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserData {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub cv_path: Option<String>,
+    pub cover_letter_path: Option<String>,
+    pub preferences: HashMap<String, serde_json::Value>,
+    pub form_data: HashMap<String, serde_json::Value>,
+}
+
+impl UserData {
+    /// Dane użytkownika jako zmienne `{{nazwa}}` dla skryptów DSL
+    pub fn as_variables(&self) -> HashMap<String, String> {
+        let mut variables: HashMap<String, String> = self
+            .form_data
+            .iter()
+            .filter_map(|(key, value)| match value {
+                serde_json::Value::String(text) => Some((key.clone(), text.clone())),
+                serde_json::Value::Number(number) => Some((key.clone(), number.to_string())),
+                serde_json::Value::Bool(flag) => Some((key.clone(), flag.to_string())),
+                _ => None,
+            })
+            .collect();
+
+        let fields = [
+            ("first_name", &self.first_name),
+            ("last_name", &self.last_name),
+            ("email", &self.email),
+            ("phone", &self.phone),
+            ("address", &self.address),
+            ("cv_path", &self.cv_path),
+            ("cover_letter_path", &self.cover_letter_path),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                variables.insert(name.to_string(), value.clone());
+            }
+        }
+
+        variables
+    }
+}
+
+/// `/session/create`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRequest {
+    pub user_id: String,
+    pub user_data: UserData,
+}
+
+/// `/session/create` i `/session/get`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub success: bool,
+    pub session: Option<UserSession>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// `/health`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub services: serde_json::Value,
+}
+
+/// `/logs`, `/logs/stats` i `/logs/clear`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogResponse {
+    pub success: bool,
+    pub logs: Option<Vec<String>>,
+    pub stats: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// `POST /system/config`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemConfigRequest {
+    /// Plik TagUI albo katalog z nim; `null` przywraca automatyczne wyszukiwanie
+    pub tagui_path: Option<String>,
+}
+
+/// `POST /system/tagui/install`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaguiInstallRequest {
+    pub version: Option<String>,
+    pub sha256: Option<String>,
+}
+
+/// `POST /scheduler/maintenance` - okno, w którym zadania z kolejki nie są uruchamiane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindowRequest {
+    pub name: String,
+    /// Np. numer zgłoszenia zamrożenia zmian
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// `/artifacts/gc`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactGcRequest {
+    #[serde(default = "default_true")]
+    pub dry_run: bool,
+    #[serde(default = "default_gc_min_age_hours")]
+    pub min_age_hours: i64,
+}
+
+impl Default for ArtifactGcRequest {
+    fn default() -> Self {
+        Self { dry_run: default_true(), min_age_hours: default_gc_min_age_hours() }
+    }
+}
+
+/// `POST /system/archive/restore` - przywraca rekordy jednego miesiąca z zimnego archiwum
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRestoreRequest {
+    /// `sessions` albo `runs`
+    pub kind: String,
+    /// RRRR-MM
+    pub month: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_gc_min_age_hours() -> i64 {
+    24
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::secret::SecretString;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitwardenCredential {
+    pub id: String,
+    pub name: String,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    pub uri: Option<String>,
+    pub notes: Option<String>,
+    pub folder_id: Option<String>,
+}
+
+/// Odwołanie do sekretu w vault: element (nazwa lub ID) i jego pole
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretRef {
+    pub item: String,
+    #[serde(default = "default_secret_field")]
+    pub field: String,
+}
+
+fn default_secret_field() -> String {
+    "password".to_string()
+}
+
+/// `/bitwarden/login`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitwardenLoginRequest {
+    pub email: String,
+    pub master_password: SecretString,
+}
+
+/// `/bitwarden/unlock`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitwardenUnlockRequest {
+    pub master_password: SecretString,
+}
+
+/// `/bitwarden/credentials` i `/bitwarden/credentials/url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialsResponse {
+    pub success: bool,
+    pub credentials: Option<Vec<BitwardenCredential>>,
+    pub error: Option<String>,
+}
+
+/// Menedżer haseł, z którego pochodzi eksport CSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportFormat {
+    #[serde(rename = "lastpass")]
+    LastPass,
+    #[serde(rename = "chrome")]
+    Chrome,
+    #[serde(rename = "1password", alias = "onepassword")]
+    OnePassword,
+}
+
+/// `/bitwarden/import` - bez `confirm` zwraca tylko podgląd, nic nie zapisuje w vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialImportRequest {
+    /// Zawartość pliku CSV z nagłówkiem
+    pub csv: SecretString,
+    /// Domyślnie rozpoznawany po nagłówku
+    #[serde(default)]
+    pub format: Option<ImportFormat>,
+    #[serde(default)]
+    pub confirm: bool,
+    /// Wiersze z podglądu do zaimportowania (numeracja od 1, bez nagłówka); domyślnie wszystkie nowe.
+    /// Wskazany duplikat zostanie dodany mimo to
+    #[serde(default)]
+    pub rows: Option<Vec<usize>>,
+}
+
+/// Format eksportu vault
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VaultExportFormat {
+    /// JSON Bitwarden (po odszyfrowaniu do ponownego importu w Bitwarden)
+    #[default]
+    Json,
+    Csv,
+}
+
+/// `/bitwarden/export` - zaszyfrowana kopia dostępnych elementów vault jako artefakt sesji
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultExportRequest {
+    pub session_id: String,
+    /// Hasło eksportu (nie hasło główne); potrzebne do odszyfrowania pliku
+    pub passphrase: SecretString,
+    #[serde(default)]
+    pub format: VaultExportFormat,
+}
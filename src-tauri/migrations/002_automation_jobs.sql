@@ -0,0 +1,18 @@
+-- Shared automation job queue consumed by worker processes
+-- Workers claim jobs with SELECT ... FOR UPDATE SKIP LOCKED
+
+CREATE TABLE IF NOT EXISTS automation_jobs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    script TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'queued', -- 'queued', 'running', 'succeeded', 'failed'
+    worker_id VARCHAR(255),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    result JSONB,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    heartbeat_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_automation_jobs_status ON automation_jobs(status, created_at);
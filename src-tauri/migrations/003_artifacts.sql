@@ -0,0 +1,20 @@
+-- Run artifacts (screenshots, PDFs, HAR files) with reference tracking
+-- An artifact is garbage-collected only when no retained owner references it
+
+CREATE TABLE IF NOT EXISTS artifacts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    path VARCHAR(1000) NOT NULL UNIQUE,
+    kind VARCHAR(50) NOT NULL, -- 'screenshot', 'pdf', 'har', 'other'
+    size_bytes BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS artifact_refs (
+    artifact_id UUID NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+    owner_type VARCHAR(20) NOT NULL, -- 'job', 'run'
+    owner_id VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (artifact_id, owner_type, owner_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_artifact_refs_owner ON artifact_refs(owner_type, owner_id);
@@ -0,0 +1,15 @@
+-- DSL script cache keyed by page structure
+-- html_content holds sanitized, structure-only HTML encrypted by the application
+-- (enc:v1: prefix); rows stored before that are rewritten by the app after migrating
+
+CREATE TABLE IF NOT EXISTS dsl_cache (
+    cache_key VARCHAR(255) PRIMARY KEY,
+    script_content TEXT NOT NULL,
+    html_content TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+ALTER TABLE dsl_cache ALTER COLUMN html_content DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_dsl_cache_expires_at ON dsl_cache(expires_at);
@@ -0,0 +1,4 @@
+-- Sessions are bound to the app instance that issued them (SHA-256 of a per-start nonce);
+-- rows from before this migration have no binding and are rejected while binding is enabled
+
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS instance_binding TEXT;
@@ -0,0 +1,21 @@
+-- History of /rpa/run calls: script (before variable substitution, no secret values),
+-- its SHA-256, outcome, error output and the session that started the run
+
+CREATE TABLE IF NOT EXISTS automation_runs (
+    id UUID PRIMARY KEY,
+    script TEXT NOT NULL,
+    script_hash CHAR(64) NOT NULL,
+    status VARCHAR(20) NOT NULL, -- 'succeeded', 'failed', 'timed_out', 'cancelled', 'invalid_script', 'spawn_error', 'rejected'
+    session_id VARCHAR(255),
+    parameters JSONB NOT NULL DEFAULT '{}',
+    exit_code INTEGER,
+    failed_line INTEGER,
+    error_output TEXT,
+    duration_ms BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_automation_runs_created_at ON automation_runs(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_automation_runs_session_id ON automation_runs(session_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_automation_runs_script_hash ON automation_runs(script_hash);
@@ -0,0 +1,6 @@
+-- Links a run started by /rpa/runs/:id/replay to the run it re-executes
+
+ALTER TABLE automation_runs
+    ADD COLUMN IF NOT EXISTS replay_of UUID REFERENCES automation_runs(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_automation_runs_replay_of ON automation_runs(replay_of);
@@ -0,0 +1,4 @@
+-- Generation strategy (llm, enhanced, simple, basic) that produced each cached DSL script;
+-- a cached script is reused only by requests whose fallback chain includes that strategy
+
+ALTER TABLE dsl_cache ADD COLUMN IF NOT EXISTS strategy VARCHAR(16);
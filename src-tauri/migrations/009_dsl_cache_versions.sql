@@ -0,0 +1,14 @@
+-- Every distinct script generated for a DSL cache key, so a site's script can be compared
+-- across redesigns and rolled back; a pinned cache entry does not expire or get overwritten
+
+CREATE TABLE IF NOT EXISTS dsl_cache_versions (
+    cache_key VARCHAR(255) NOT NULL,
+    version INTEGER NOT NULL,
+    script_content TEXT NOT NULL,
+    strategy VARCHAR(16),
+    restored_from INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (cache_key, version)
+);
+
+ALTER TABLE dsl_cache ADD COLUMN IF NOT EXISTS pinned BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,6 @@
+-- Site (host without www.) of the page each cached DSL script was generated for,
+-- so cache entries can be exported per domain and imported into another install
+
+ALTER TABLE dsl_cache ADD COLUMN IF NOT EXISTS site VARCHAR(255);
+
+CREATE INDEX IF NOT EXISTS idx_dsl_cache_site ON dsl_cache(site);
@@ -0,0 +1,4 @@
+-- Fingerprint of the page form skeleton a cached script was generated for (SHA-256 of
+-- dom::structure_signature); expired entries with a matching fingerprint are served while refreshing
+
+ALTER TABLE dsl_cache ADD COLUMN IF NOT EXISTS structure_hash VARCHAR(64);
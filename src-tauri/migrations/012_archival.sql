@@ -0,0 +1,11 @@
+-- Soft delete for sessions and runs: cleanup sets archived_at, /system/archive/export moves the rows to ARCHIVE_DIR
+
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS restored_at TIMESTAMPTZ;
+
+-- Archived sessions stay in the table until exported, so only one active session per user is unique
+ALTER TABLE user_sessions DROP CONSTRAINT IF EXISTS user_sessions_user_id_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_sessions_active_user ON user_sessions(user_id) WHERE archived_at IS NULL;
+
+ALTER TABLE automation_runs ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+ALTER TABLE automation_runs ADD COLUMN IF NOT EXISTS restored_at TIMESTAMPTZ;
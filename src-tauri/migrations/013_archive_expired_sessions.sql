@@ -0,0 +1,39 @@
+-- cleanup_expired_data() archives expired sessions instead of deleting them, so their files, form data
+-- and scripts reach ARCHIVE_DIR with the session on the next /system/archive/export
+
+CREATE OR REPLACE FUNCTION cleanup_expired_data()
+RETURNS INTEGER AS $$
+DECLARE
+    deleted_count INTEGER := 0;
+    temp_count INTEGER;
+BEGIN
+    -- Archive expired sessions (restored ones are held for 7 days, as in SessionManager)
+    UPDATE user_sessions SET archived_at = NOW()
+    WHERE expires_at < NOW() AND archived_at IS NULL
+      AND (restored_at IS NULL OR restored_at < NOW() - INTERVAL '7 days');
+    GET DIAGNOSTICS temp_count = ROW_COUNT;
+    deleted_count := deleted_count + temp_count;
+    
+    -- Clean up expired Bitwarden cache of active sessions; archived sessions keep theirs until export
+    DELETE FROM bitwarden_cache c
+    WHERE c.expires_at < NOW()
+      AND NOT EXISTS (SELECT 1 FROM user_sessions s WHERE s.session_id = c.session_id AND s.archived_at IS NOT NULL);
+    GET DIAGNOSTICS temp_count = ROW_COUNT;
+    deleted_count := deleted_count + temp_count;
+    
+    -- Clean up old application logs (older than 30 days)
+    DELETE FROM application_logs WHERE timestamp < NOW() - INTERVAL '30 days';
+    GET DIAGNOSTICS temp_count = ROW_COUNT;
+    deleted_count := deleted_count + temp_count;
+    
+    -- Clean up old DSL scripts cache of active sessions (older than 7 days and not used recently)
+    DELETE FROM dsl_scripts d
+    WHERE d.created_at < NOW() - INTERVAL '7 days' 
+      AND (d.last_used IS NULL OR d.last_used < NOW() - INTERVAL '3 days')
+      AND NOT EXISTS (SELECT 1 FROM user_sessions s WHERE s.session_id = d.session_id AND s.archived_at IS NOT NULL);
+    GET DIAGNOSTICS temp_count = ROW_COUNT;
+    deleted_count := deleted_count + temp_count;
+    
+    RETURN deleted_count;
+END;
+$$ LANGUAGE plpgsql;
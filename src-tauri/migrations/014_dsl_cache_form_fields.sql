@@ -0,0 +1,6 @@
+-- Form fields of the page a cached script was generated for (few_shot::form_fields); few-shot examples
+-- for similar forms are matched on this column instead of decrypting stored HTML
+
+ALTER TABLE dsl_cache ADD COLUMN IF NOT EXISTS form_fields TEXT[];
+
+CREATE INDEX IF NOT EXISTS idx_dsl_cache_form_fields ON dsl_cache USING GIN (form_fields);
@@ -0,0 +1,395 @@
+//! Raport dostępności pól po dry-run: pola, których automatyzacja nie może pewnie wskazać, bo nie mają
+//! etykiety, id ani name albo selektor skryptu trafia w kilka elementów. Tekst raportu (Markdown, po
+//! angielsku) nadaje się do wysłania właścicielowi strony - te same braki utrudniają korzystanie
+//! z formularza czytnikom ekranu.
+
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+
+use crate::dom::{is_generated_id, normalize};
+use crate::tagui::{self, DslCommand};
+use crate::cdp;
+
+/// Komendy, które działają na konkretnym polu lub przycisku
+const TARGETING_COMMANDS: &[&str] = &["click", "type", "select", "check", "uncheck", "upload", "hover"];
+
+/// Kontrolki formularza sprawdzane w całym dokumencie
+const CONTROLS: &str = "input:not([type=\"hidden\"]), select, textarea, button";
+
+/// Atrybuty na początku opisu elementu, w tej kolejności
+const KEY_ATTRIBUTES: &[&str] = &["id", "name", "type", "class"];
+
+/// Długość opisu elementu w raporcie (znacznik otwierający, w znakach)
+const ELEMENT_PREVIEW_CHARS: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Selektor skryptu nie pasuje do niczego na przeanalizowanej stronie
+    NotFound,
+    /// Selektor pasuje do kilku elementów - skrypt trafi w pierwszy z nich
+    Ambiguous,
+    /// Pole bez etykiety, `aria-label`, `aria-labelledby` ani `title`
+    MissingLabel,
+    /// Jedynym opisem pola jest `placeholder`, który znika po wpisaniu tekstu
+    PlaceholderOnly,
+    /// Przycisk bez tekstu i nazwy dostępnej
+    UnnamedButton,
+    /// Pole bez `id` i `name` - selektor zależy od klas lub położenia
+    MissingIdentifier,
+    /// `id` generowane przy każdym renderze, bez stabilnego `name`
+    UnstableIdentifier,
+    /// To samo `id` na kilku elementach
+    DuplicateId,
+}
+
+impl IssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueKind::NotFound => "not_found",
+            IssueKind::Ambiguous => "ambiguous",
+            IssueKind::MissingLabel => "missing_label",
+            IssueKind::PlaceholderOnly => "placeholder_only",
+            IssueKind::UnnamedButton => "unnamed_button",
+            IssueKind::MissingIdentifier => "missing_identifier",
+            IssueKind::UnstableIdentifier => "unstable_identifier",
+            IssueKind::DuplicateId => "duplicate_id",
+        }
+    }
+
+    fn problem(&self) -> &'static str {
+        match self {
+            IssueKind::NotFound => "The targeted element does not exist on the page",
+            IssueKind::Ambiguous => "Several elements match the same selector",
+            IssueKind::MissingLabel => "The field has no label",
+            IssueKind::PlaceholderOnly => "The field is described only by its placeholder",
+            IssueKind::UnnamedButton => "The button has no text or accessible name",
+            IssueKind::MissingIdentifier => "The field has neither an id nor a name",
+            IssueKind::UnstableIdentifier => "The field id changes on every page load",
+            IssueKind::DuplicateId => "The id is used by more than one element",
+        }
+    }
+
+    fn recommendation(&self) -> &'static str {
+        match self {
+            IssueKind::NotFound => "Check whether the field is rendered late or inside a frame; give it a stable id or name",
+            IssueKind::Ambiguous => "Give each field a unique id or name",
+            IssueKind::MissingLabel => "Add a <label for=\"...\"> or an aria-label describing the field",
+            IssueKind::PlaceholderOnly => "Add a visible <label>; keep the placeholder only as an example value",
+            IssueKind::UnnamedButton => "Add visible text, an aria-label or alt text of the button image",
+            IssueKind::MissingIdentifier => "Add a stable name (and id) to the field",
+            IssueKind::UnstableIdentifier => "Add a stable name or id that does not change between page loads",
+            IssueKind::DuplicateId => "Make ids unique within the page",
+        }
+    }
+
+    /// Kryterium WCAG 2.1, którego dotyczy problem
+    fn wcag(&self) -> Option<&'static str> {
+        match self {
+            IssueKind::MissingLabel | IssueKind::PlaceholderOnly => Some("1.3.1 Info and Relationships, 3.3.2 Labels or Instructions"),
+            IssueKind::UnnamedButton => Some("4.1.2 Name, Role, Value"),
+            IssueKind::DuplicateId => Some("4.1.1 Parsing"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccessibilityIssue {
+    pub kind: IssueKind,
+    /// Linia skryptu, która celuje w element; `None` dla pól znalezionych tylko na stronie
+    pub line: Option<usize>,
+    pub selector: String,
+    /// Znacznik otwierający elementu, np. `<input type="text" class="fld">`
+    pub element: Option<String>,
+    pub frame: Option<String>,
+    pub problem: String,
+    pub recommendation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wcag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessibilityReport {
+    pub page_url: String,
+    pub generated_at: DateTime<Utc>,
+    /// Kontrolki formularzy sprawdzone na stronie
+    pub fields_checked: usize,
+    /// Problemy pól, w które celuje skrypt
+    pub script_issues: usize,
+    pub issues: Vec<AccessibilityIssue>,
+    /// Raport do przekazania właścicielowi strony
+    pub markdown: String,
+}
+
+/// Element strony ze swoją ramką
+struct Located<'a> {
+    element: ElementRef<'a>,
+    document: &'a Html,
+    frame: Option<&'a str>,
+}
+
+/// Raport dla skryptu i HTML ostatnio przeanalizowanej strony
+pub fn report(html: &str, page_url: &str, commands: &[DslCommand]) -> AccessibilityReport {
+    let documents: Vec<(Option<String>, Html)> =
+        cdp::split_frames(html).into_iter().map(|(frame, document)| (frame, Html::parse_document(&document))).collect();
+    let controls = Selector::parse(CONTROLS).expect("valid selector");
+    let mut issues: Vec<AccessibilityIssue> = Vec::new();
+    let mut reported: Vec<(Option<&str>, ElementRef)> = Vec::new();
+
+    // Pola, w które celuje skrypt
+    for command in commands.iter().filter(|command| TARGETING_COMMANDS.contains(&command.name.as_str())) {
+        let Some(selector) = command.selector() else { continue };
+        let Some(parsed) = css_selector(selector) else { continue };
+        let matches: Vec<Located> = documents
+            .iter()
+            .flat_map(|(frame, document)| {
+                document.select(&parsed).map(move |element| Located { element, document, frame: frame.as_deref() })
+            })
+            .collect();
+        let issue = |kind: IssueKind, element: Option<&Located>| AccessibilityIssue {
+            kind,
+            line: Some(command.line),
+            selector: selector.to_string(),
+            element: element.map(|located| opening_tag(located.element)),
+            frame: element.and_then(|located| located.frame.map(str::to_string)),
+            problem: kind.problem().to_string(),
+            recommendation: kind.recommendation().to_string(),
+            wcag: kind.wcag().map(str::to_string),
+        };
+        match matches.as_slice() {
+            [] => issues.push(issue(IssueKind::NotFound, None)),
+            [located] => {
+                for kind in element_issues(located) {
+                    issues.push(issue(kind, Some(located)));
+                }
+                reported.push((located.frame, located.element));
+            }
+            [first, ..] => issues.push(issue(IssueKind::Ambiguous, Some(first))),
+        }
+    }
+    let script_issues = issues.len();
+
+    // Pozostałe pola strony - pełny obraz dla właściciela
+    let mut fields_checked = 0;
+    for (frame, document) in &documents {
+        for element in document.select(&controls) {
+            fields_checked += 1;
+            if reported.iter().any(|(reported_frame, reported)| *reported_frame == frame.as_deref() && reported.id() == element.id()) {
+                continue;
+            }
+            let located = Located { element, document, frame: frame.as_deref() };
+            for kind in element_issues(&located) {
+                issues.push(AccessibilityIssue {
+                    kind,
+                    line: None,
+                    selector: element_selector(element),
+                    element: Some(opening_tag(element)),
+                    frame: frame.clone(),
+                    problem: kind.problem().to_string(),
+                    recommendation: kind.recommendation().to_string(),
+                    wcag: kind.wcag().map(str::to_string),
+                });
+            }
+        }
+    }
+
+    let markdown = render_markdown(page_url, fields_checked, &issues);
+    AccessibilityReport { page_url: page_url.to_string(), generated_at: Utc::now(), fields_checked, script_issues, issues, markdown }
+}
+
+/// Selektor CSS z argumentu komendy; XPath i zwykły tekst (`click "Submit"`) nie są sprawdzane
+fn css_selector(selector: &str) -> Option<Selector> {
+    let selector = selector.trim();
+    if selector.starts_with('/') || selector.starts_with('(') || selector.contains(tagui::FOR_EACH_ITEM) || selector.contains("{{") {
+        return None;
+    }
+    let looks_like_css = selector.starts_with(['#', '.', '['])
+        || selector.contains(['#', '[', '>', ':'])
+        || ["input", "select", "textarea", "button", "form", "a"].iter().any(|tag| selector.split([' ', '.']).next() == Some(*tag));
+    looks_like_css.then(|| Selector::parse(selector).ok()).flatten()
+}
+
+fn element_issues(located: &Located) -> Vec<IssueKind> {
+    let element = located.element;
+    let value = element.value();
+    let attr = |name: &str| value.attr(name).map(str::trim).filter(|value| !value.is_empty());
+    let tag = value.name();
+    let input_type = attr("type").map(str::to_lowercase);
+    let is_button = tag == "button" || tag == "a" || matches!(input_type.as_deref(), Some("submit" | "button" | "reset" | "image"));
+    let mut issues = Vec::new();
+
+    if is_button {
+        if accessible_name(located).is_none() && button_text(element).is_none() {
+            issues.push(IssueKind::UnnamedButton);
+        }
+        return issues;
+    }
+    if !matches!(tag, "input" | "select" | "textarea") {
+        return issues;
+    }
+
+    if accessible_name(located).is_none() {
+        issues.push(if attr("placeholder").is_some() { IssueKind::PlaceholderOnly } else { IssueKind::MissingLabel });
+    }
+    match (attr("id"), attr("name")) {
+        (None, None) => issues.push(IssueKind::MissingIdentifier),
+        (Some(id), None) if is_generated_id(id) => issues.push(IssueKind::UnstableIdentifier),
+        _ => {}
+    }
+    if let Some(id) = attr("id") {
+        let same_id = Selector::parse(&format!("[id=\"{}\"]", id.replace('\\', "\\\\").replace('"', "\\\""))).ok();
+        if same_id.is_some_and(|selector| located.document.select(&selector).count() > 1) {
+            issues.push(IssueKind::DuplicateId);
+        }
+    }
+    issues
+}
+
+/// Nazwa dostępna pola: `aria-labelledby`, `aria-label`, `<label for>`, etykieta obejmująca albo `title`
+fn accessible_name(located: &Located) -> Option<String> {
+    let element = located.element;
+    let value = element.value();
+    let text_of = |element: ElementRef| Some(normalize(&element.text().collect::<String>())).filter(|text| !text.is_empty());
+    let by_ids = value.attr("aria-labelledby").and_then(|ids| {
+        let texts: Vec<String> = ids
+            .split_whitespace()
+            .filter_map(|id| Selector::parse(&format!("[id=\"{}\"]", id.replace('"', "\\\""))).ok())
+            .filter_map(|selector| located.document.select(&selector).next().and_then(text_of))
+            .collect();
+        Some(texts.join(" ")).filter(|text| !text.is_empty())
+    });
+    let by_for = || {
+        let id = value.id()?;
+        let selector = Selector::parse(&format!("label[for=\"{}\"]", id.replace('\\', "\\\\").replace('"', "\\\""))).ok()?;
+        located.document.select(&selector).next().and_then(text_of)
+    };
+    let wrapping = || {
+        element.ancestors().filter_map(ElementRef::wrap).find(|ancestor| ancestor.value().name() == "label").and_then(text_of)
+    };
+
+    by_ids
+        .or_else(|| value.attr("aria-label").map(normalize).filter(|text| !text.is_empty()))
+        .or_else(by_for)
+        .or_else(wrapping)
+        .or_else(|| value.attr("title").map(normalize).filter(|text| !text.is_empty()))
+}
+
+/// Tekst przycisku, `value` przycisków `input` albo `alt` obrazka w przycisku
+fn button_text(element: ElementRef) -> Option<String> {
+    let text = normalize(&element.text().collect::<String>());
+    if !text.is_empty() {
+        return Some(text);
+    }
+    let image = Selector::parse("img[alt]").expect("valid selector");
+    element
+        .value()
+        .attr("value")
+        .or_else(|| element.value().attr("alt"))
+        .or_else(|| element.select(&image).next().and_then(|image| image.value().attr("alt")))
+        .map(normalize)
+        .filter(|text| !text.is_empty())
+}
+
+/// Selektor pola spoza skryptu: id, name, pierwsza klasa albo sam znacznik
+fn element_selector(element: ElementRef) -> String {
+    let value = element.value();
+    let attr = |name: &str| value.attr(name).map(str::trim).filter(|value| !value.is_empty());
+    attr("id")
+        .map(|id| format!("[id=\"{}\"]", id))
+        .or_else(|| attr("name").map(|name| format!("[name=\"{}\"]", name)))
+        .or_else(|| value.classes().next().map(|class| format!("{}.{}", value.name(), class)))
+        .unwrap_or_else(|| value.name().to_string())
+}
+
+fn opening_tag(element: ElementRef) -> String {
+    let value = element.value();
+    let mut tag = format!("<{}", value.name());
+    // Kolejność atrybutów parsera nie jest kolejnością w źródle - najważniejsze dla właściciela strony najpierw
+    let mut attributes: Vec<(&str, &str)> =
+        value.attrs().filter(|(name, _)| *name != "value" && *name != "style" && !name.starts_with("on")).collect();
+    attributes.sort_by_key(|(name, _)| (KEY_ATTRIBUTES.iter().position(|key| key == name).unwrap_or(KEY_ATTRIBUTES.len()), *name));
+    for (name, attribute) in attributes {
+        tag.push_str(&format!(" {}=\"{}\"", name, attribute));
+    }
+    tag.push('>');
+    if tag.chars().count() > ELEMENT_PREVIEW_CHARS {
+        tag = tag.chars().take(ELEMENT_PREVIEW_CHARS - 4).collect::<String>() + "...>";
+    }
+    tag
+}
+
+fn render_markdown(page_url: &str, fields_checked: usize, issues: &[AccessibilityIssue]) -> String {
+    let mut text = format!("# Form accessibility report\n\nPage: {}\n\n", if page_url.is_empty() { "(unknown)" } else { page_url });
+    if issues.is_empty() {
+        text.push_str(&format!("All {} form controls have labels and stable identifiers. No problems found.\n", fields_checked));
+        return text;
+    }
+    text.push_str(&format!(
+        "An automated form-filling tool could not reliably identify some of the {} form controls on this page. \
+         The same problems make the form harder to use with screen readers and other assistive technology.\n\n",
+        fields_checked
+    ));
+    text.push_str("| # | Element | Problem | Suggested fix | WCAG |\n|---|---|---|---|---|\n");
+    for (index, issue) in issues.iter().enumerate() {
+        let element = issue.element.as_deref().unwrap_or(&issue.selector);
+        let element = match &issue.frame {
+            Some(frame) => format!("{} (in frame {})", element, frame),
+            None => element.to_string(),
+        };
+        text.push_str(&format!(
+            "| {} | `{}` | {} | {} | {} |\n",
+            index + 1,
+            element.replace('|', "\\|").replace('`', "'"),
+            issue.problem,
+            issue.recommendation,
+            issue.wcag.as_deref().unwrap_or("-")
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_untargetable_fields() {
+        let html = r#"<form>
+            <label for="email">E-mail</label><input id="email" name="email" type="email">
+            <input class="fld" type="text" placeholder="Phone">
+            <input id="input_1694012345" type="text" aria-labelledby="city-label"><span id="city-label">City</span>
+            <input class="x" type="text"><input class="x" type="text" aria-label="Second">
+            <label>Notes <textarea name="notes"></textarea></label>
+            <button class="go"><svg></svg></button>
+        </form>"#;
+        let script = "type \"#email\" \"a@b.c\"\ntype \".fld\" \"600\"\ntype \"#input_1694012345\" \"Gdańsk\"\ntype \".x\" \"?\"\nclick \".go\"\nclick \"#missing\"\nclick \"Submit\"";
+        let commands = tagui::parse_dsl_script(script).unwrap();
+
+        let report = report(html, "https://jobs.example.com/apply", &commands);
+        let summary: Vec<(Option<usize>, IssueKind)> = report.issues.iter().map(|issue| (issue.line, issue.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some(2), IssueKind::PlaceholderOnly),
+                (Some(2), IssueKind::MissingIdentifier),
+                (Some(3), IssueKind::UnstableIdentifier),
+                (Some(4), IssueKind::Ambiguous),
+                (Some(5), IssueKind::UnnamedButton),
+                (Some(6), IssueKind::NotFound),
+                // Pola strony bez komend skryptu
+                (None, IssueKind::MissingLabel),
+                (None, IssueKind::MissingIdentifier),
+                (None, IssueKind::MissingIdentifier),
+            ]
+        );
+        assert_eq!((report.fields_checked, report.script_issues), (7, 6));
+        assert_eq!(report.issues[0].element.as_deref(), Some("<input type=\"text\" class=\"fld\" placeholder=\"Phone\">"));
+        assert!(report.markdown.contains("| 1 | `<input type=\"text\" class=\"fld\" placeholder=\"Phone\">` | The field is described only by its placeholder |"));
+        assert!(report.markdown.contains("Page: https://jobs.example.com/apply"));
+
+        let clean = super::report("<label>Name <input name=\"n\"></label>", "", &[]);
+        assert!(clean.issues.is_empty() && clean.markdown.contains("No problems found"));
+    }
+}
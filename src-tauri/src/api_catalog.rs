@@ -0,0 +1,427 @@
+use serde_json::{json, Value};
+
+use codialog_types::ADMIN_TOKEN_HEADER;
+
+/// Schemat kolekcji Postman v2.1; Insomnia importuje ten format bez konwersji
+const POSTMAN_SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Jak endpoint jest chroniony
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Auth {
+    None,
+    /// X-Admin-Token z ADMIN_TOKEN
+    Admin,
+    /// Nonce instancji (`instance::INSTANCE_NONCE_HEADER`) dla tras powiązanych z oknem aplikacji
+    InstanceNonce,
+}
+
+/// Opis jednej trasy API z przykładowym zapytaniem
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub folder: &'static str,
+    pub name: &'static str,
+    pub auth: Auth,
+    /// Przykładowe parametry zapytania
+    pub query: Vec<(&'static str, &'static str)>,
+    pub body: Option<Value>,
+}
+
+fn endpoint(method: &'static str, path: &'static str, folder: &'static str, name: &'static str, auth: Auth) -> Endpoint {
+    Endpoint { method, path, folder, name, auth, query: Vec::new(), body: None }
+}
+
+impl Endpoint {
+    fn query(mut self, query: &[(&'static str, &'static str)]) -> Self {
+        self.query = query.to_vec();
+        self
+    }
+
+    fn body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+}
+
+/// Przykładowe ciało /rpa/run, /rpa/jobs i /rpa/debug/start
+fn run_script_example() -> Value {
+    json!({
+        "script": "click \"#login\"\ntype \"#email\" \"{{email}}\"\ntype \"#password\" \"{{password}}\"\nclick \"#submit\"",
+        "dry_run": false,
+        "variables": { "email": "jan.kowalski@example.com" },
+        "secret_refs": { "password": { "item": "Portal HR", "field": "password" } },
+        "session_id": null,
+        "capture_screenshots": true,
+        "verify_selectors": false,
+        "browser_mode": "headless",
+        "timeout_secs": 300,
+        "backend": "tagui",
+        "url": null
+    })
+}
+
+/// Wszystkie trasy serwera API w kolejności z `main`; test pilnuje zgodności z routerem
+pub fn endpoints() -> Vec<Endpoint> {
+    use Auth::{Admin, InstanceNonce};
+    let public = Auth::None;
+
+    vec![
+        endpoint("GET", "/health", "System", "Health check", public),
+        endpoint("GET", "/health/live", "System", "Liveness probe", public),
+        endpoint("GET", "/health/ready", "System", "Readiness probe", public),
+        endpoint("GET", "/setup/status", "System", "Backend startup status", public),
+        endpoint("POST", "/shutdown", "System", "Graceful shutdown", Admin),
+        endpoint("POST", "/selftest", "System", "Run self-test", Admin),
+        endpoint("POST", "/keys/rotate", "System", "Rotate encryption keys", Admin),
+        endpoint("GET", "/keys/rotation", "System", "Key rotation status", Admin),
+        endpoint("GET", "/system/tagui/install", "System", "TagUI install status", public),
+        endpoint("POST", "/system/tagui/install", "System", "Install TagUI release", Admin)
+            .body(json!({ "version": "6.110.0", "sha256": "<sha256 of the release archive>" })),
+        endpoint("GET", "/system/config", "System", "Get system config", public),
+        endpoint("POST", "/system/config", "System", "Update system config", Admin)
+            .body(json!({ "tagui_path": "/opt/tagui" })),
+        endpoint("GET", "/system/postman", "System", "Postman collection", public),
+        endpoint("GET", "/system/watchdog", "System", "Component watchdog status", Admin),
+        endpoint("GET", "/system/archive", "System", "List archive files", Admin),
+        endpoint("POST", "/system/archive/export", "System", "Archive and export old sessions and runs", Admin),
+        endpoint("POST", "/system/archive/restore", "System", "Restore archived month", Admin)
+            .body(json!({ "kind": "runs", "month": "2026-03" })),
+        endpoint("GET", "/scheduler/maintenance", "Scheduler", "List maintenance windows", Admin)
+            .query(&[("include_past", "false")]),
+        endpoint("POST", "/scheduler/maintenance", "Scheduler", "Schedule maintenance window", Admin)
+            .body(json!({
+                "name": "Change freeze",
+                "reason": "CHG-1042",
+                "starts_at": "2026-12-22T18:00:00Z",
+                "ends_at": "2026-12-27T08:00:00Z"
+            })),
+        endpoint("DELETE", "/scheduler/maintenance/:id", "Scheduler", "Cancel maintenance window", Admin),
+        endpoint("GET", "/scheduler/schedules", "Scheduler", "List schedules", Admin),
+        endpoint("POST", "/scheduler/schedules", "Scheduler", "Create schedule", Admin)
+            .body(json!({
+                "name": "Daily timesheet",
+                "script": "click \"#timesheet\"\nclick \"#submit\"",
+                "expression": "every weekday at 9",
+                "timezone": "Europe/Warsaw",
+                "holidays": ["2026-12-24", "2026-12-25"]
+            })),
+        endpoint("DELETE", "/scheduler/schedules/:id", "Scheduler", "Delete schedule", Admin),
+        endpoint("GET", "/scheduler/budgets", "Scheduler", "List budgets", Admin),
+        endpoint("POST", "/scheduler/budgets/:owner_type/:owner_id", "Scheduler", "Set budget limits", Admin)
+            .body(json!({ "max_runs_per_day": 20, "max_llm_spend_per_day": 1.5, "max_failures": 3 })),
+        endpoint("POST", "/scheduler/budgets/:owner_type/:owner_id/resume", "Scheduler", "Resume paused automation", Admin),
+        endpoint("GET", "/scheduler/blocked-sites", "Scheduler", "List blocked sites", Admin),
+        endpoint("DELETE", "/scheduler/blocked-sites/:site", "Scheduler", "Clear site block", Admin),
+        endpoint("GET", "/llm/usage", "System", "LLM token usage and cost", Admin)
+            .query(&[("group_by", "day"), ("days", "30")]),
+        endpoint("POST", "/dsl/generate", "DSL", "Generate DSL from HTML", public)
+            .body(json!({
+                "html": "<form><input id=\"email\" type=\"email\"><button id=\"submit\">Send</button></form>",
+                "user_data": { "email": "jan.kowalski@example.com" },
+                "locale": "pl-PL"
+            })),
+        endpoint("POST", "/dsl/generate/stream", "DSL", "Stream DSL generation (SSE)", public)
+            .body(json!({
+                "html": "<form><input id=\"email\" type=\"email\"><button id=\"submit\">Send</button></form>",
+                "user_data": { "email": "jan.kowalski@example.com" }
+            })),
+        endpoint("POST", "/dsl/from-text", "DSL", "Generate DSL from instruction", public)
+            .body(json!({
+                "instruction": "Log into LinkedIn and apply to this job with my saved CV",
+                "session_id": "{{sessionId}}"
+            })),
+        endpoint("POST", "/dsl/lint", "DSL", "Lint DSL script", public)
+            .body(json!({ "script": "click \"#submit\"\nwait 2", "fix": true })),
+        endpoint("POST", "/dsl/cache/export", "DSL", "Export DSL cache bundle", Admin)
+            .body(json!({ "domains": ["example.com"] })),
+        endpoint("POST", "/dsl/cache/import", "DSL", "Import DSL cache bundle", Admin)
+            .body(json!({ "bundle": { "format": 1, "exported_at": "2026-01-01T00:00:00Z", "domains": ["example.com"], "entries": [], "signature": "<hmac>" }, "on_conflict": "newer", "pin": true })),
+        endpoint("GET", "/dsl/cache/:key/history", "DSL", "DSL script version history", public),
+        endpoint("GET", "/dsl/cache/:key/diff", "DSL", "Diff DSL script versions", public)
+            .query(&[("from", "1"), ("to", "2")]),
+        endpoint("POST", "/dsl/cache/:key/rollback", "DSL", "Roll back DSL script version", Admin)
+            .body(json!({ "version": 1, "pin": true })),
+        endpoint("GET", "/dsl/prompts", "DSL", "List prompt templates", Admin),
+        endpoint("POST", "/dsl/prompts/:form_type/:language", "DSL", "Override prompt template", Admin)
+            .body(json!({ "body": "Fill in the job application form.\n\nHTML: {{html}}\n\nUser data: {{user_data}}" })),
+        endpoint("DELETE", "/dsl/prompts/:form_type/:language", "DSL", "Reset prompt template", Admin),
+        endpoint("POST", "/rpa/cancel", "Automation", "Cancel run", public)
+            .body(json!({ "run_id": "{{runId}}" })),
+        endpoint("GET", "/rpa/status", "Automation", "Run status", public).query(&[("run_id", "{{runId}}")]),
+        endpoint("POST", "/rpa/jobs", "Automation", "Enqueue job", public).body(run_script_example()),
+        endpoint("GET", "/rpa/jobs/:id", "Automation", "Get job", public),
+        endpoint("GET", "/rpa/jobs/:id/trace", "Automation", "Get job trace", public),
+        endpoint("GET", "/rpa/artifacts", "Automation", "List run artifacts", public).query(&[("run_id", "{{runId}}")]),
+        endpoint("GET", "/rpa/artifacts/:id", "Automation", "Download artifact", public),
+        endpoint("GET", "/artifacts", "Artifacts", "List artifacts", public)
+            .query(&[("owner_type", "run"), ("owner_id", "{{runId}}")]),
+        endpoint("POST", "/artifacts/gc", "Artifacts", "Garbage-collect artifacts", Admin)
+            .body(json!({ "dry_run": true, "min_age_hours": 24 })),
+        endpoint("GET", "/page/analyze", "Page", "Analyze current page", public),
+        endpoint("POST", "/page/analyze", "Page", "Analyze page with injected auth state", Admin).body(json!({
+            "url": "https://portal.example.com/applications",
+            "auth_state": {
+                "cookies": [{ "name": "sid", "value": "{{sessionCookie}}", "domain": ".portal.example.com", "httpOnly": true }],
+                "origins": [{ "origin": "https://portal.example.com", "localStorage": [{ "name": "token", "value": "{{authToken}}" }] }]
+            }
+        })),
+        endpoint("POST", "/page/classify", "Page", "Classify page form", public).body(json!({ "llm": false })),
+        endpoint("POST", "/page/screenshot", "Page", "Capture page screenshot and PDF", Admin)
+            .body(json!({ "url": "https://portal.example.com/apply", "full_page": true, "pdf": true })),
+        endpoint("GET", "/replay/:id", "Replay", "Get replay bundle", Admin),
+        endpoint("POST", "/replay/:id/run", "Replay", "Run replay bundle", Admin),
+        endpoint("GET", "/logs", "Logs", "Get logs", public).query(&[("log_type", "app"), ("lines", "100")]),
+        endpoint("GET", "/logs/stats", "Logs", "Log stats", public).query(&[("top", "20"), ("kind", "db_query")]),
+        endpoint("GET", "/logs/page", "Logs", "Page through logs", public).query(&[("log_type", "app"), ("limit", "200")]),
+        endpoint("GET", "/logs/tagui/tail", "Logs", "Tail TagUI output (SSE)", public).query(&[("run_id", "{{runId}}")]),
+        endpoint("POST", "/logs/clear", "Logs", "Rotate logs", public),
+        endpoint("POST", "/rpa/run", "Automation", "Run script", InstanceNonce).body(run_script_example()),
+        endpoint("GET", "/rpa/runs", "Automation", "List runs", InstanceNonce)
+            .query(&[("status", "failed"), ("limit", "50"), ("offset", "0")]),
+        endpoint("GET", "/rpa/runs/:id", "Automation", "Get run", InstanceNonce),
+        endpoint("POST", "/rpa/runs/:id/replay", "Automation", "Replay run", InstanceNonce)
+            .body(json!({ "session_id": null })),
+        endpoint("GET", "/rpa/runs/:id/report", "Automation", "Run report", InstanceNonce).query(&[("format", "html")]),
+        endpoint("GET", "/runs/compare", "Automation", "Compare two runs", InstanceNonce)
+            .query(&[("a", "{{runId}}"), ("b", "{{otherRunId}}")]),
+        endpoint("POST", "/rpa/debug/start", "Debugger", "Start debug run", InstanceNonce).body(run_script_example()),
+        endpoint("POST", "/rpa/debug/step", "Debugger", "Step", InstanceNonce).body(json!({ "debug_id": "{{debugId}}" })),
+        endpoint("POST", "/rpa/debug/continue", "Debugger", "Continue", InstanceNonce)
+            .body(json!({ "debug_id": "{{debugId}}" })),
+        endpoint("GET", "/rpa/debug/:id", "Debugger", "Debug status", InstanceNonce),
+        endpoint("POST", "/rpa/jobs/:id/debug", "Debugger", "Debug job", InstanceNonce)
+            .body(json!({ "breakpoints": [7], "variables": { "email": "jan.kowalski@example.com" } })),
+        endpoint("GET", "/rpa/jobs/:id/debug", "Debugger", "Job debug status", InstanceNonce),
+        endpoint("POST", "/rpa/jobs/:id/debug/pause", "Debugger", "Pause job", InstanceNonce),
+        endpoint("POST", "/rpa/jobs/:id/debug/resume", "Debugger", "Resume job", InstanceNonce),
+        endpoint("POST", "/rpa/jobs/:id/debug/step", "Debugger", "Step over", InstanceNonce),
+        endpoint("POST", "/rpa/jobs/:id/debug/breakpoints", "Debugger", "Set breakpoints", InstanceNonce)
+            .body(json!({ "breakpoints": [7, 12] })),
+        endpoint("POST", "/rpa/jobs/:id/debug/inspect", "Debugger", "Inspect selector", InstanceNonce)
+            .body(json!({ "selector": "#submit" })),
+        endpoint("POST", "/bitwarden/login", "Bitwarden", "Login", InstanceNonce)
+            .body(json!({ "email": "jan.kowalski@example.com", "master_password": "{{bitwardenMasterPassword}}" })),
+        endpoint("POST", "/bitwarden/unlock", "Bitwarden", "Unlock", InstanceNonce)
+            .body(json!({ "master_password": "{{bitwardenMasterPassword}}" })),
+        endpoint("GET", "/bitwarden/credentials", "Bitwarden", "List credentials", InstanceNonce),
+        endpoint("GET", "/bitwarden/credentials/url", "Bitwarden", "Credentials for URL", InstanceNonce)
+            .query(&[("url", "https://portal.example.com/login")]),
+        endpoint("POST", "/bitwarden/import", "Bitwarden", "Import credentials from CSV", InstanceNonce)
+            .body(json!({
+                "csv": "name,url,username,password\nPortal HR,https://portal.example.com,jkowalski,secret",
+                "confirm": false
+            })),
+        endpoint("POST", "/bitwarden/export", "Bitwarden", "Export encrypted vault backup", InstanceNonce)
+            .body(json!({ "session_id": "{{sessionId}}", "passphrase": "backup passphrase", "format": "json" })),
+        endpoint("GET", "/bitwarden/biometric", "Bitwarden", "Biometric unlock status", InstanceNonce),
+        endpoint("POST", "/bitwarden/biometric/enable", "Bitwarden", "Enable biometric unlock", InstanceNonce),
+        endpoint("POST", "/bitwarden/biometric/unlock", "Bitwarden", "Unlock with Windows Hello / Touch ID", InstanceNonce),
+        endpoint("DELETE", "/bitwarden/biometric", "Bitwarden", "Disable biometric unlock", InstanceNonce),
+        endpoint("GET", "/autofill/offers", "Autofill", "Pending autofill offers", InstanceNonce),
+        endpoint("POST", "/autofill/offers/:id/accept", "Autofill", "Accept autofill offer", InstanceNonce)
+            .body(json!({ "kind": "credential", "credential_id": "{{credentialId}}", "session_id": "{{sessionId}}" })),
+        endpoint("POST", "/autofill/offers/:id/dismiss", "Autofill", "Dismiss autofill offer", InstanceNonce),
+        endpoint("GET", "/autofill/scripts", "Autofill", "List site scripts", InstanceNonce),
+        endpoint("POST", "/autofill/scripts", "Autofill", "Save site script", InstanceNonce)
+            .body(json!({
+                "name": "Portal HR login",
+                "url": "https://portal.example.com/login",
+                "script": "type \"#email\" \"{{email}}\"\nclick \"#next\""
+            })),
+        endpoint("DELETE", "/autofill/scripts/:id", "Autofill", "Delete site script", InstanceNonce),
+        endpoint("GET", "/choices", "Choices", "List remembered choices", InstanceNonce).query(&[("site", "portal.example.com")]),
+        endpoint("POST", "/choices", "Choices", "Remember choice for site", InstanceNonce)
+            .body(json!({ "url": "https://portal.example.com/apply", "kind": "field_mapping", "key": "phone", "value": "#mobile" })),
+        endpoint("DELETE", "/choices", "Choices", "Forget remembered choices", InstanceNonce)
+            .query(&[("site", "portal.example.com"), ("kind", "field_mapping"), ("key", "phone")]),
+        endpoint("GET", "/policies", "Choices", "Get sensitive field policies", InstanceNonce),
+        endpoint("POST", "/policies", "Choices", "Save sensitive field policies", InstanceNonce).body(json!({
+            "salary": [{ "currency": "PLN", "min": 15000, "max": 18000 }, { "currency": "EUR", "min": 4000, "max": 5000 }],
+            "salary_quote": "max",
+            "notice_period_days": 30,
+            "relocation": "negotiable"
+        })),
+        endpoint("DELETE", "/policies", "Choices", "Clear sensitive field policies", InstanceNonce),
+        endpoint("POST", "/session/create", "Session", "Create session", InstanceNonce)
+            .body(json!({
+                "user_id": "user-1",
+                "user_data": {
+                    "first_name": "Jan",
+                    "last_name": "Kowalski",
+                    "email": "jan.kowalski@example.com",
+                    "phone": "+48 600 000 000",
+                    "address": null,
+                    "cv_path": null,
+                    "cover_letter_path": null,
+                    "preferences": {},
+                    "form_data": {}
+                }
+            })),
+        endpoint("GET", "/session/get", "Session", "Get session", InstanceNonce).query(&[("session_id", "{{sessionId}}")]),
+        endpoint("POST", "/page/navigate", "Page", "Open URL in page control session", Admin)
+            .body(json!({ "url": "https://portal.example.com/login" })),
+        endpoint("POST", "/page/click", "Page", "Click element", Admin).body(json!({ "selector": "#submit" })),
+        endpoint("POST", "/page/fill", "Page", "Fill field", Admin)
+            .body(json!({ "selector": "#email", "value": "jan.kowalski@example.com" })),
+        endpoint("POST", "/page/evaluate", "Page", "Evaluate JavaScript", Admin)
+            .body(json!({ "expression": "document.querySelectorAll('input').length" })),
+        endpoint("DELETE", "/page/session", "Page", "Close page control session", Admin),
+    ]
+}
+
+/// Placeholder dla parametru ścieżki, np. `:id` w /rpa/runs/:id -> {{runId}}
+fn path_variable_value(path: &str, name: &str) -> &'static str {
+    match name {
+        "owner_type" => return "schedule",
+        "form_type" => return "job_application",
+        "language" => return "pl",
+        "site" => return "example.com",
+        _ => {}
+    }
+    match path.split('/').nth(1).unwrap_or_default() {
+        "replay" => "{{replayId}}",
+        "dsl" => "{{cacheKey}}",
+        "autofill" if path.starts_with("/autofill/offers") => "{{autofillOfferId}}",
+        "autofill" => "{{autofillScriptId}}",
+        "scheduler" if path.starts_with("/scheduler/budgets") => "{{scheduleId}}",
+        "scheduler" if path.starts_with("/scheduler/schedules") => "{{scheduleId}}",
+        "scheduler" => "{{maintenanceWindowId}}",
+        "rpa" if path.starts_with("/rpa/jobs") => "{{jobId}}",
+        "rpa" if path.starts_with("/rpa/artifacts") => "{{artifactId}}",
+        "rpa" if path.starts_with("/rpa/debug") => "{{debugId}}",
+        _ => "{{runId}}",
+    }
+}
+
+fn postman_item(endpoint: &Endpoint, nonce_header: &str) -> Value {
+    let segments: Vec<&str> = endpoint.path.trim_start_matches('/').split('/').collect();
+    let mut url = json!({
+        "raw": format!("{{{{baseUrl}}}}{}", endpoint.path),
+        "host": ["{{baseUrl}}"],
+        "path": segments,
+    });
+    if !endpoint.query.is_empty() {
+        let query: Vec<String> = endpoint.query.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        url["raw"] = json!(format!("{{{{baseUrl}}}}{}?{}", endpoint.path, query.join("&")));
+        url["query"] = json!(endpoint.query.iter().map(|(key, value)| json!({ "key": key, "value": value })).collect::<Vec<_>>());
+    }
+    let variables: Vec<Value> = segments
+        .iter()
+        .filter_map(|segment| segment.strip_prefix(':'))
+        .map(|name| json!({ "key": name, "value": path_variable_value(endpoint.path, name) }))
+        .collect();
+    if !variables.is_empty() {
+        url["variable"] = json!(variables);
+    }
+
+    let mut headers = Vec::new();
+    match endpoint.auth {
+        Auth::Admin => headers.push(json!({ "key": ADMIN_TOKEN_HEADER, "value": "{{adminToken}}" })),
+        Auth::InstanceNonce => headers.push(json!({ "key": nonce_header, "value": "{{instanceNonce}}" })),
+        Auth::None => {}
+    }
+
+    let mut request = json!({ "method": endpoint.method, "header": headers, "url": url });
+    if let Some(body) = &endpoint.body {
+        request["header"].as_array_mut().unwrap().push(json!({ "key": "Content-Type", "value": "application/json" }));
+        request["body"] = json!({
+            "mode": "raw",
+            "raw": serde_json::to_string_pretty(body).unwrap_or_default(),
+            "options": { "raw": { "language": "json" } }
+        });
+    }
+
+    json!({ "name": endpoint.name, "request": request })
+}
+
+/// Kolekcja Postman v2.1 ze wszystkimi trasami pogrupowanymi w foldery; tokeny i identyfikatory
+/// są zmiennymi kolekcji (`{{adminToken}}`, `{{instanceNonce}}`, `{{runId}}`...) do uzupełnienia po imporcie
+pub fn postman_collection(base_url: &str, nonce_header: &str) -> Value {
+    let mut folders: Vec<(&str, Vec<Value>)> = Vec::new();
+    for endpoint in endpoints() {
+        let item = postman_item(&endpoint, nonce_header);
+        match folders.iter_mut().find(|(folder, _)| *folder == endpoint.folder) {
+            Some((_, items)) => items.push(item),
+            None => folders.push((endpoint.folder, vec![item])),
+        }
+    }
+
+    let variables: Vec<Value> = [
+        ("baseUrl", base_url),
+        ("adminToken", ""),
+        ("instanceNonce", ""),
+        ("bitwardenMasterPassword", ""),
+        ("runId", ""),
+        ("otherRunId", ""),
+        ("jobId", ""),
+        ("artifactId", ""),
+        ("debugId", ""),
+        ("replayId", ""),
+        ("maintenanceWindowId", ""),
+        ("scheduleId", ""),
+        ("autofillOfferId", ""),
+        ("autofillScriptId", ""),
+        ("credentialId", ""),
+        ("sessionId", ""),
+        ("cacheKey", ""),
+        ("sessionCookie", ""),
+        ("authToken", ""),
+    ]
+    .iter()
+    .map(|(key, value)| json!({ "key": key, "value": value }))
+    .collect();
+
+    json!({
+        "info": {
+            "name": "Codialog API",
+            "description": "Local Codialog automation API. Fill in adminToken (ADMIN_TOKEN) for admin routes and instanceNonce for routes bound to the app window.",
+            "schema": POSTMAN_SCHEMA,
+        },
+        "variable": variables,
+        "item": folders
+            .into_iter()
+            .map(|(folder, items)| json!({ "name": folder, "item": items }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Trasy zarejestrowane w routerze `main` (metoda, ścieżka)
+    fn router_routes() -> BTreeSet<(String, String)> {
+        let mut routes = BTreeSet::new();
+        for line in include_str!("main.rs").lines().map(str::trim).filter(|line| line.starts_with(".route(\"")) {
+            let path = line.trim_start_matches(".route(\"").split('"').next().unwrap().to_string();
+            for method in ["get", "post", "put", "delete", "patch"] {
+                if line.contains(&format!("{}(", method)) {
+                    routes.insert((method.to_uppercase(), path.clone()));
+                }
+            }
+        }
+        routes
+    }
+
+    #[test]
+    fn test_collection_covers_router() {
+        let catalog: BTreeSet<(String, String)> =
+            endpoints().iter().map(|endpoint| (endpoint.method.to_string(), endpoint.path.to_string())).collect();
+        assert_eq!(catalog, router_routes());
+
+        let collection = postman_collection("http://127.0.0.1:4000", "x-instance-nonce");
+        assert_eq!(collection["info"]["schema"], POSTMAN_SCHEMA);
+        let items: Vec<&Value> = collection["item"].as_array().unwrap().iter().flat_map(|folder| folder["item"].as_array().unwrap()).collect();
+        assert_eq!(items.len(), endpoints().len());
+
+        let run = items.iter().find(|item| item["request"]["url"]["raw"] == "{{baseUrl}}/rpa/run").unwrap();
+        assert_eq!(run["request"]["header"][0]["value"], "{{instanceNonce}}");
+        let body: Value = serde_json::from_str(run["request"]["body"]["raw"].as_str().unwrap()).unwrap();
+        assert!(body["script"].is_string());
+
+        let report = items.iter().find(|item| item["name"] == "Run report").unwrap();
+        assert_eq!(report["request"]["url"]["variable"][0]["value"], "{{runId}}");
+    }
+}
@@ -0,0 +1,372 @@
+//! Zimne archiwum sesji i przebiegów. Sprzątanie nie usuwa rekordów, tylko ustawia `archived_at`; eksport
+//! dopisuje zarchiwizowane rekordy do skompresowanych plików miesięcznych `ARCHIVE_DIR/<rodzaj>-RRRR-MM.jsonl.gz`
+//! (jeden rekord na linię, zaszyfrowany kluczem ENCRYPTION_KEY; każdy eksport to kolejny człon gzip) i dopiero po
+//! zapisaniu pliku usuwa je z bazy. Sesja trafia do archiwum razem z wierszami tabel, które usunęłoby jej
+//! kaskadowe usunięcie. Przywrócenie miesiąca wstawia rekordy z powrotem jako aktywne.
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::crypto::{self, ContentCipher};
+
+/// Przywrócone rekordy nie są archiwizowane ponownie przez tyle dni, choć nadal spełniają warunek (np. wygasła sesja)
+pub const RESTORE_HOLD_DAYS: i32 = 7;
+
+/// Domyślny wiek przebiegu, po którym trafia do archiwum (RUN_RETENTION_DAYS); 0 wyłącza archiwizację przebiegów
+pub const DEFAULT_RUN_RETENTION_DAYS: u64 = 180;
+
+/// Domyślny odstęp eksportu w tle (ARCHIVE_EXPORT_INTERVAL_HOURS); 0 zostawia tylko `/system/archive/export`
+pub const DEFAULT_EXPORT_INTERVAL_HOURS: u64 = 24;
+
+pub const FILE_SUFFIX: &str = ".jsonl.gz";
+
+/// Tabele z `ON DELETE CASCADE` do `user_sessions`, eksportowane w rekordzie sesji pod nazwą tabeli
+const SESSION_CHILD_TABLES: [&str; 4] = ["user_files", "form_data_cache", "dsl_scripts", "bitwarden_cache"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveKind {
+    Sessions,
+    Runs,
+}
+
+impl ArchiveKind {
+    pub const ALL: [ArchiveKind; 2] = [ArchiveKind::Sessions, ArchiveKind::Runs];
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind.trim().to_lowercase().as_str() {
+            "sessions" | "session" => Some(ArchiveKind::Sessions),
+            "runs" | "run" => Some(ArchiveKind::Runs),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveKind::Sessions => "sessions",
+            ArchiveKind::Runs => "runs",
+        }
+    }
+
+    /// Zarchiwizowane rekordy z miesiącem utworzenia; sesja z wierszami `SESSION_CHILD_TABLES`.
+    /// Token Bitwarden sesji nie trafia do archiwum.
+    fn export_query(&self) -> String {
+        match self {
+            ArchiveKind::Sessions => {
+                let children: Vec<String> = SESSION_CHILD_TABLES
+                    .iter()
+                    .map(|table| {
+                        format!(
+                            "'{table}', (SELECT COALESCE(jsonb_agg(to_jsonb(c)), '[]'::jsonb) FROM {table} c WHERE c.session_id = s.session_id)"
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"
+                SELECT session_id::text AS id, to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM') AS month,
+                       (to_jsonb(s) - 'bitwarden_session') || jsonb_build_object({}) AS record
+                FROM user_sessions s
+                WHERE archived_at IS NOT NULL
+                ORDER BY created_at
+                "#,
+                    children.join(", ")
+                )
+            }
+            ArchiveKind::Runs => r#"
+                SELECT id::text AS id, to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM') AS month, to_jsonb(r) AS record
+                FROM automation_runs r
+                WHERE archived_at IS NOT NULL
+                ORDER BY created_at
+                "#
+            .to_string(),
+        }
+    }
+
+    fn purge_query(&self) -> &'static str {
+        match self {
+            ArchiveKind::Sessions => "DELETE FROM user_sessions WHERE session_id::text = ANY($1) AND archived_at IS NOT NULL",
+            ArchiveKind::Runs => "DELETE FROM automation_runs WHERE id::text = ANY($1) AND archived_at IS NOT NULL",
+        }
+    }
+
+    /// Wstawia rekord jako aktywny; istniejący (albo druga aktywna sesja użytkownika) jest pomijany.
+    /// `replay_of` wskazujący usunięty przebieg jest zerowany, jak przy usunięciu.
+    fn restore_query(&self) -> &'static str {
+        match self {
+            ArchiveKind::Sessions => {
+                r#"
+                INSERT INTO user_sessions
+                SELECT * FROM jsonb_populate_record(NULL::user_sessions,
+                    $1::jsonb || jsonb_build_object('archived_at', NULL, 'restored_at', NOW()))
+                ON CONFLICT DO NOTHING
+                "#
+            }
+            ArchiveKind::Runs => {
+                r#"
+                INSERT INTO automation_runs
+                SELECT * FROM jsonb_populate_record(NULL::automation_runs,
+                    $1::jsonb || jsonb_build_object(
+                        'archived_at', NULL,
+                        'restored_at', NOW(),
+                        'replay_of', (SELECT id FROM automation_runs WHERE id::text = $1::jsonb->>'replay_of')))
+                ON CONFLICT DO NOTHING
+                "#
+            }
+        }
+    }
+
+    /// Wiersze tabel zależnych przywracanej sesji (`$1` - rekord z archiwum)
+    fn restore_children_queries(&self) -> Vec<String> {
+        match self {
+            ArchiveKind::Sessions => SESSION_CHILD_TABLES
+                .iter()
+                .map(|table| {
+                    format!(
+                        "INSERT INTO {table} SELECT * FROM jsonb_populate_recordset(NULL::{table}, COALESCE($1::jsonb->'{table}', '[]'::jsonb)) ON CONFLICT DO NOTHING"
+                    )
+                })
+                .collect(),
+            ArchiveKind::Runs => Vec::new(),
+        }
+    }
+
+    fn id_field(&self) -> &'static str {
+        match self {
+            ArchiveKind::Sessions => "session_id",
+            ArchiveKind::Runs => "id",
+        }
+    }
+}
+
+/// Plik miesiąca w archiwum
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveFile {
+    pub kind: ArchiveKind,
+    pub month: String,
+    pub size_bytes: u64,
+}
+
+/// Rekordy dopisane do jednego pliku przy eksporcie
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthExport {
+    pub kind: ArchiveKind,
+    pub month: String,
+    pub records: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub kind: ArchiveKind,
+    pub month: String,
+    pub restored: u64,
+    /// Rekordy, które już są w bazie
+    pub skipped: u64,
+}
+
+pub struct Archiver {
+    db_pool: PgPool,
+    dir: PathBuf,
+}
+
+impl Archiver {
+    pub fn new(db_pool: PgPool, dir: impl Into<PathBuf>) -> Self {
+        Self { db_pool, dir: dir.into() }
+    }
+
+    /// Dopisuje zarchiwizowane rekordy do plików miesięcznych i usuwa je z bazy; bez ENCRYPTION_KEY odmawia
+    pub async fn export(&self) -> Result<Vec<MonthExport>> {
+        let Some(cipher) = crypto::content_cipher() else {
+            bail!("ENCRYPTION_KEY is not set; archives are only written encrypted");
+        };
+        let mut exported = Vec::new();
+        for kind in ArchiveKind::ALL {
+            let rows = sqlx::query(&kind.export_query())
+                .fetch_all(&self.db_pool)
+                .await
+                .with_context(|| format!("Failed to read archived {}", kind.as_str()))?;
+
+            let mut months: BTreeMap<String, (Vec<String>, Vec<serde_json::Value>)> = BTreeMap::new();
+            for row in &rows {
+                let (ids, records) = months.entry(row.get("month")).or_default();
+                ids.push(row.get("id"));
+                records.push(row.get("record"));
+            }
+
+            for (month, (ids, records)) in months {
+                let path = month_file(&self.dir, kind, &month)?;
+                let count = records.len();
+                tokio::task::spawn_blocking(move || append_records(&path, &records, cipher)).await??;
+                sqlx::query(kind.purge_query())
+                    .bind(&ids)
+                    .execute(&self.db_pool)
+                    .await
+                    .with_context(|| format!("Failed to purge exported {}", kind.as_str()))?;
+                info!(kind = kind.as_str(), month = %month, records = count, "Exported archived records");
+                exported.push(MonthExport { kind, month, records: count });
+            }
+        }
+        Ok(exported)
+    }
+
+    /// Wstawia z powrotem rekordy z pliku miesiąca; `None`, gdy dla tego miesiąca nie ma archiwum
+    pub async fn restore(&self, kind: ArchiveKind, month: &str) -> Result<Option<RestoreReport>> {
+        let path = month_file(&self.dir, kind, month)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let id_field = kind.id_field();
+        let records = tokio::task::spawn_blocking(move || read_records(&path, id_field, crypto::decrypt_any)).await??;
+        let mut restored = 0;
+        for record in &records {
+            let mut tx = self.db_pool.begin().await?;
+            let inserted = sqlx::query(kind.restore_query())
+                .bind(record)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to restore archived {}", kind.as_str()))?
+                .rows_affected();
+            // Wiersze zależne tylko razem z nowo wstawioną sesją
+            if inserted > 0 {
+                for query in kind.restore_children_queries() {
+                    sqlx::query(&query)
+                        .bind(record)
+                        .execute(&mut *tx)
+                        .await
+                        .with_context(|| format!("Failed to restore data of archived {}", kind.as_str()))?;
+                }
+            }
+            tx.commit().await?;
+            restored += inserted;
+        }
+
+        info!(kind = kind.as_str(), month, restored, "Restored archived records");
+        Ok(Some(RestoreReport { kind, month: month.to_string(), restored, skipped: records.len() as u64 - restored }))
+    }
+
+    /// Pliki archiwum od najstarszego miesiąca
+    pub fn list(&self) -> Result<Vec<ArchiveFile>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir).with_context(|| format!("Failed to read {}", self.dir.display()))? {
+            let entry = entry?;
+            let Some((kind, month)) = entry.file_name().to_str().and_then(parse_file_name) else {
+                continue;
+            };
+            files.push(ArchiveFile { kind, month, size_bytes: entry.metadata()?.len() });
+        }
+        files.sort_by(|a, b| (&a.month, a.kind).cmp(&(&b.month, b.kind)));
+        Ok(files)
+    }
+}
+
+/// Miesiąc archiwum w formacie RRRR-MM
+pub fn is_valid_month(month: &str) -> bool {
+    month.len() == 7 && NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_ok()
+}
+
+fn month_file(dir: &Path, kind: ArchiveKind, month: &str) -> Result<PathBuf> {
+    if !is_valid_month(month) {
+        bail!("Invalid archive month '{}', expected YYYY-MM", month);
+    }
+    Ok(dir.join(format!("{}-{}{}", kind.as_str(), month, FILE_SUFFIX)))
+}
+
+fn parse_file_name(name: &str) -> Option<(ArchiveKind, String)> {
+    let (kind, month) = name.strip_suffix(FILE_SUFFIX)?.split_once('-')?;
+    let kind = ArchiveKind::parse(kind)?;
+    month_file(Path::new(""), kind, month).ok()?;
+    Some((kind, month.to_string()))
+}
+
+/// Dopisuje zaszyfrowane rekordy jako nowy człon gzip i synchronizuje plik, zanim rekordy znikną z bazy
+fn append_records(path: &Path, records: &[serde_json::Value], cipher: &ContentCipher) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut encoder = GzEncoder::new(file, Compression::best());
+    for record in records {
+        encoder.write_all(cipher.encrypt(&record.to_string())?.as_bytes())?;
+        encoder.write_all(b"\n")?;
+    }
+    encoder.finish()?.sync_all().with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Rekordy z pliku; rekord wyeksportowany kilka razy (po przywróceniu) występuje raz, w ostatniej wersji.
+/// Linie sprzed szyfrowania archiwum są czytane jako jawny JSON.
+fn read_records(path: &Path, id_field: &str, decrypt: impl Fn(&str) -> Result<String>) -> Result<Vec<serde_json::Value>> {
+    let file = fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut latest: Vec<serde_json::Value> = Vec::new();
+    let mut positions: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for line in BufReader::new(MultiGzDecoder::new(file)).lines() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line = if line.starts_with(crypto::ENCRYPTED_PREFIX) {
+            decrypt(&line).with_context(|| format!("Failed to decrypt a record in {}", path.display()))?
+        } else {
+            line
+        };
+        let record: serde_json::Value = serde_json::from_str(&line).with_context(|| format!("Corrupt record in {}", path.display()))?;
+        let id = record.get(id_field).and_then(|id| id.as_str()).unwrap_or_default().to_string();
+        match positions.get(&id) {
+            Some(&index) => latest[index] = record,
+            None => {
+                positions.insert(id, latest.len());
+                latest.push(record);
+            }
+        }
+    }
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_monthly_files_append_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = month_file(dir.path(), ArchiveKind::Runs, "2026-03").unwrap();
+        assert!(path.ends_with("runs-2026-03.jsonl.gz"));
+        assert!(month_file(dir.path(), ArchiveKind::Runs, "2026-13").is_err());
+        assert!(month_file(dir.path(), ArchiveKind::Runs, "../../etc").is_err());
+
+        let cipher = ContentCipher::from_secret("archive-test-key").unwrap();
+        append_records(&path, &[json!({ "id": "a", "status": "failed" }), json!({ "id": "b", "status": "succeeded" })], &cipher).unwrap();
+        // Ten sam przebieg wyeksportowany ponownie po przywróceniu - liczy się ostatnia wersja
+        append_records(&path, &[json!({ "id": "a", "status": "succeeded" })], &cipher).unwrap();
+
+        let mut raw = String::new();
+        std::io::Read::read_to_string(&mut MultiGzDecoder::new(fs::File::open(&path).unwrap()), &mut raw).unwrap();
+        assert!(raw.lines().all(|line| line.starts_with(crypto::ENCRYPTED_PREFIX)) && !raw.contains("succeeded"));
+
+        let records = read_records(&path, "id", |line| cipher.decrypt(line)).unwrap();
+        assert_eq!(records, vec![json!({ "id": "a", "status": "succeeded" }), json!({ "id": "b", "status": "succeeded" })]);
+        let wrong_key = ContentCipher::from_secret("other-key").unwrap();
+        assert!(read_records(&path, "id", |line| wrong_key.decrypt(line)).is_err());
+
+        fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        let archiver = Archiver::new(sqlx::PgPool::connect_lazy("postgres://localhost/unused").unwrap(), dir.path());
+        let files = archiver.list().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].kind, files[0].month.as_str()), (ArchiveKind::Runs, "2026-03"));
+        assert_eq!(parse_file_name("sessions-2025-12.jsonl.gz"), Some((ArchiveKind::Sessions, "2025-12".to_string())));
+    }
+}
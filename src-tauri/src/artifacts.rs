@@ -0,0 +1,529 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context};
+use tracing::{info, warn, debug};
+use chrono::{DateTime, Utc};
+use ring::digest;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use codialog_core::tagui;
+
+/// Podkatalog plików adresowanych treścią: `objects/<2 pierwsze znaki>/<sha256>`
+const OBJECTS_DIR: &str = "objects";
+
+/// Plik artefaktu (zrzut ekranu, PDF, HAR) powiązany z zadaniami/przebiegami
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: String,
+    pub path: String,
+    pub kind: String,
+    pub size_bytes: i64,
+    /// SHA-256 treści; ten sam plik z wielu sesji i zadań to jeden artefakt z wieloma referencjami
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcCandidate {
+    pub artifact_id: Option<String>,
+    pub path: String,
+    pub size_bytes: u64,
+    pub reason: String,
+}
+
+/// Raport z odśmiecania artefaktów
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub dangling_references_removed: u64,
+    pub candidates: Vec<GcCandidate>,
+    pub deleted: usize,
+    pub freed_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    db_pool: PgPool,
+    base_dir: PathBuf,
+}
+
+impl ArtifactStore {
+    pub fn new(db_pool: PgPool, base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            db_pool,
+            base_dir: base_dir.into(),
+        }
+    }
+
+    /// Inicjalizuje tabele artefaktów i referencji
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing artifact store in {}", self.base_dir.display());
+
+        std::fs::create_dir_all(&self.base_dir)
+            .with_context(|| format!("Failed to create artifacts directory {}", self.base_dir.display()))?;
+
+        sqlx::query(
+            r#"
+            CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+            -- Jak w migrations/003_artifacts.sql; `sha256` dochodzi przez ALTER poniżej
+            CREATE TABLE IF NOT EXISTS artifacts (
+                id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                path VARCHAR(1000) NOT NULL UNIQUE,
+                kind VARCHAR(50) NOT NULL, -- 'screenshot', 'pdf', 'har', 'other'
+                size_bytes BIGINT NOT NULL DEFAULT 0,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            );
+
+            CREATE TABLE IF NOT EXISTS artifact_refs (
+                artifact_id UUID NOT NULL REFERENCES artifacts(id) ON DELETE CASCADE,
+                owner_type VARCHAR(20) NOT NULL, -- 'job', 'run'
+                owner_id VARCHAR(255) NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (artifact_id, owner_type, owner_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_artifact_refs_owner ON artifact_refs(owner_type, owner_id);
+
+            ALTER TABLE artifacts ADD COLUMN IF NOT EXISTS sha256 CHAR(64);
+            CREATE INDEX IF NOT EXISTS idx_artifacts_sha256 ON artifacts(sha256);
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create artifact tables")?;
+
+        Ok(())
+    }
+
+    /// Rejestruje plik artefaktu i od razu wiąże go z właścicielem
+    pub async fn register(&self, path: &Path, kind: &str, owner_type: &str, owner_id: &str) -> Result<String> {
+        let size_bytes = std::fs::metadata(path).map(|m| m.len() as i64).unwrap_or(0);
+        let hashed = path.to_path_buf();
+        let sha256 = tokio::task::spawn_blocking(move || hash_file(&hashed))
+            .await
+            .context("Artifact hashing task failed")?
+            .map_err(|e| warn!("Failed to hash artifact {}: {}", path.display(), e))
+            .ok();
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO artifacts (path, kind, size_bytes, sha256)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (path) DO UPDATE SET size_bytes = EXCLUDED.size_bytes, sha256 = EXCLUDED.sha256
+            RETURNING id::text AS id
+            "#,
+        )
+        .bind(path.to_string_lossy().to_string())
+        .bind(kind)
+        .bind(size_bytes)
+        .bind(sha256)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to register artifact")?;
+
+        let artifact_id: String = row.get("id");
+        self.add_reference(&artifact_id, owner_type, owner_id).await?;
+
+        debug!("Registered artifact {} ({}) for {} {}", artifact_id, kind, owner_type, owner_id);
+        Ok(artifact_id)
+    }
+
+    /// Zapisuje kopię pliku adresowaną treścią (SHA-256), bez referencji - właściciel dopisuje ją, gdy zna już
+    /// swój identyfikator (GC nie usunie pliku młodszego niż `min_age`). Identyczny plik (np. to samo CV
+    /// w każdej sesji i zadaniu wsadu) leży na dysku raz.
+    pub async fn store_object(&self, source: &Path, kind: &str) -> Result<Artifact> {
+        // Haszowanie i kopia dużych plików nie blokują wątków runtime
+        let (source, base_dir) = (source.to_path_buf(), self.base_dir.clone());
+        let (sha256, object, size_bytes) = tokio::task::spawn_blocking(move || copy_into_store(&source, &base_dir))
+            .await
+            .context("Artifact copy task failed")??;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO artifacts (path, kind, size_bytes, sha256)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (path) DO UPDATE SET sha256 = EXCLUDED.sha256
+            RETURNING id::text AS id, path, kind, size_bytes, sha256, created_at
+            "#,
+        )
+        .bind(object.to_string_lossy().to_string())
+        .bind(kind)
+        .bind(size_bytes)
+        .bind(&sha256)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to register stored artifact")?;
+
+        Ok(artifact_from_row(&row))
+    }
+
+    pub async fn add_reference(&self, artifact_id: &str, owner_type: &str, owner_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO artifact_refs (artifact_id, owner_type, owner_id)
+            VALUES ($1::uuid, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(artifact_id)
+        .bind(owner_type)
+        .bind(owner_id)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to add artifact reference")?;
+
+        Ok(())
+    }
+
+    /// Registers every file in `dir` (sorted by name) as an artifact of the owner
+    pub async fn register_dir(&self, dir: &Path, kind: &str, owner_type: &str, owner_id: &str) -> Result<Vec<String>> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read artifact directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+
+        let mut ids = Vec::with_capacity(paths.len());
+        for path in paths {
+            ids.push(self.register(&path, kind, owner_type, owner_id).await?);
+        }
+        Ok(ids)
+    }
+
+    pub async fn get(&self, artifact_id: &str) -> Result<Option<Artifact>> {
+        let row = sqlx::query(
+            "SELECT id::text AS id, path, kind, size_bytes, sha256, created_at FROM artifacts WHERE id::text = $1",
+        )
+        .bind(artifact_id)
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to fetch artifact")?;
+
+        Ok(row.as_ref().map(artifact_from_row))
+    }
+
+    /// Czyta plik artefaktu, o ile leży w katalogu artefaktów
+    pub fn read(&self, artifact: &Artifact) -> Result<Vec<u8>> {
+        let base_dir = std::fs::canonicalize(&self.base_dir).context("Artifacts directory is not accessible")?;
+        let path = std::fs::canonicalize(&artifact.path)
+            .with_context(|| format!("Artifact file {} is missing", artifact.path))?;
+        if !path.starts_with(&base_dir) {
+            anyhow::bail!("Artifact {} points outside the artifacts directory", artifact.id);
+        }
+        std::fs::read(&path).with_context(|| format!("Failed to read artifact {}", artifact.id))
+    }
+
+    /// Artefakty przypisane do właściciela
+    pub async fn list_for_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<Artifact>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT a.id::text AS id, a.path, a.kind, a.size_bytes, a.sha256, a.created_at
+            FROM artifacts a
+            JOIN artifact_refs r ON r.artifact_id = a.id
+            WHERE r.owner_type = $1 AND r.owner_id = $2
+            ORDER BY a.created_at
+            "#,
+        )
+        .bind(owner_type)
+        .bind(owner_id)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to list artifacts")?;
+
+        Ok(rows.iter().map(artifact_from_row).collect())
+    }
+
+    /// Removes artifacts that no retained owner references.
+    /// Files younger than `min_age` are kept so in-progress runs aren't raced.
+    pub async fn collect_garbage(&self, dry_run: bool, min_age: chrono::Duration) -> Result<GcReport> {
+        // Ujemny wiek przesunąłby granicę w przyszłość i objął pliki właśnie zapisywane
+        if min_age < chrono::Duration::zero() {
+            anyhow::bail!("Minimum artifact age must not be negative");
+        }
+        info!(dry_run = dry_run, "Starting artifact garbage collection");
+
+        // Referencje do zadań i sesji, które już nie istnieją, nie chronią artefaktów
+        let dangling_references_removed = if dry_run {
+            0
+        } else {
+            self.prune_dangling_references().await?
+        };
+
+        // Rekordy porównujemy z zegarem bazy; pliki na dysku z czasem lokalnym
+        let cutoff = Utc::now() - min_age;
+        let rows = sqlx::query(
+            r#"
+            SELECT a.id::text AS id, a.path, a.size_bytes
+            FROM artifacts a
+            WHERE a.created_at < NOW() - make_interval(secs => $1)
+              AND NOT EXISTS (
+                  SELECT 1 FROM artifact_refs r
+                  WHERE r.artifact_id = a.id
+                    AND NOT (r.owner_type = 'job' AND NOT EXISTS (
+                        SELECT 1 FROM automation_jobs j WHERE j.id::text = r.owner_id
+                    ))
+                    AND NOT (r.owner_type = 'session' AND NOT EXISTS (
+                        SELECT 1 FROM user_sessions s WHERE s.session_id::text = r.owner_id
+                    ))
+              )
+            "#,
+        )
+        .bind(min_age.num_seconds() as f64)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to find orphaned artifacts")?;
+
+        let mut candidates: Vec<GcCandidate> = rows
+            .iter()
+            .map(|row| GcCandidate {
+                artifact_id: Some(row.get("id")),
+                path: row.get("path"),
+                size_bytes: row.get::<i64, _>("size_bytes").max(0) as u64,
+                reason: "unreferenced".to_string(),
+            })
+            .collect();
+
+        // Pliki w katalogu artefaktów, których baza nie zna
+        let known_paths: HashSet<String> = sqlx::query("SELECT path FROM artifacts")
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to list known artifacts")?
+            .iter()
+            .map(|row| row.get("path"))
+            .collect();
+
+        candidates.extend(untracked_files(&self.base_dir, &known_paths, cutoff));
+
+        let mut report = GcReport {
+            dry_run,
+            dangling_references_removed,
+            candidates,
+            deleted: 0,
+            freed_bytes: 0,
+            errors: Vec::new(),
+        };
+
+        if dry_run {
+            info!("Artifact GC dry run: {} candidates", report.candidates.len());
+            return Ok(report);
+        }
+
+        for candidate in &report.candidates {
+            match std::fs::remove_file(&candidate.path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    warn!("Failed to delete artifact {}: {}", candidate.path, e);
+                    report.errors.push(format!("{}: {}", candidate.path, e));
+                    continue;
+                }
+            }
+
+            if let Some(artifact_id) = &candidate.artifact_id {
+                sqlx::query("DELETE FROM artifacts WHERE id = $1::uuid")
+                    .bind(artifact_id)
+                    .execute(&self.db_pool)
+                    .await
+                    .context("Failed to delete artifact record")?;
+            }
+
+            report.deleted += 1;
+            report.freed_bytes += candidate.size_bytes;
+        }
+
+        info!("Artifact GC deleted {} files, freed {} bytes", report.deleted, report.freed_bytes);
+        Ok(report)
+    }
+
+    async fn prune_dangling_references(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM artifact_refs r
+            WHERE (r.owner_type = 'job'
+                   AND NOT EXISTS (SELECT 1 FROM automation_jobs j WHERE j.id::text = r.owner_id))
+               OR (r.owner_type = 'session'
+                   AND NOT EXISTS (SELECT 1 FROM user_sessions s WHERE s.session_id::text = r.owner_id))
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to prune dangling artifact references")?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Kopiuje `source` do magazynu pod nazwą z jego SHA-256, o ile tej treści jeszcze tam nie ma.
+/// Zwraca (sha256, ścieżka obiektu, rozmiar).
+fn copy_into_store(source: &Path, base_dir: &Path) -> Result<(String, PathBuf, i64)> {
+    let sha256 = hash_file(source)?;
+    let object = object_path(base_dir, &sha256);
+
+    if !object.is_file() {
+        let parent = object.parent().expect("object path has a parent");
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        // Kopia pod tymczasową nazwą i rename - współbieżny zapis tej samej treści nie zostawi połowy pliku
+        let partial = parent.join(format!(".{}.{}.partial", sha256, uuid::Uuid::new_v4()));
+        std::fs::copy(source, &partial).with_context(|| format!("Failed to copy {} into the artifact store", source.display()))?;
+        std::fs::rename(&partial, &object).with_context(|| format!("Failed to store artifact {}", sha256))?;
+        info!("Stored new artifact content {} from {}", sha256, source.display());
+    } else {
+        debug!("Artifact content {} already stored, reusing it for {}", sha256, source.display());
+    }
+    let size_bytes = std::fs::metadata(&object).map(|m| m.len() as i64).unwrap_or(0);
+    Ok((sha256, object, size_bytes))
+}
+
+/// Pliki w `base_dir` nieznane bazie i zmodyfikowane przed `cutoff`
+fn untracked_files(base_dir: &Path, known_paths: &HashSet<String>, cutoff: DateTime<Utc>) -> Vec<GcCandidate> {
+    let mut candidates = Vec::new();
+    for entry in WalkDir::new(base_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().to_string_lossy().to_string();
+        if known_paths.contains(&path) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        let old_enough = metadata
+            .modified()
+            .map(|modified| DateTime::<Utc>::from(modified) < cutoff)
+            .unwrap_or(false);
+        if old_enough {
+            candidates.push(GcCandidate {
+                artifact_id: None,
+                path,
+                size_bytes: metadata.len(),
+                reason: "untracked".to_string(),
+            });
+        }
+    }
+    candidates
+}
+
+fn artifact_from_row(row: &sqlx::postgres::PgRow) -> Artifact {
+    Artifact {
+        id: row.get("id"),
+        path: row.get("path"),
+        kind: row.get("kind"),
+        size_bytes: row.get("size_bytes"),
+        sha256: row.get::<Option<String>, _>("sha256").map(|sha| sha.trim().to_string()),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Ścieżka pliku o danej treści w magazynie artefaktów
+pub fn object_path(base_dir: &Path, sha256: &str) -> PathBuf {
+    base_dir.join(OBJECTS_DIR).join(&sha256[..2]).join(sha256)
+}
+
+/// SHA-256 pliku (hex), czytanego kawałkami
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = digest::Context::new(&digest::SHA256);
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).with_context(|| format!("Failed to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finish().as_ref().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Dosłowne ścieżki plików z komend `upload` (bez zmiennych `{{...}}`, rozwiązywanych dopiero przy wykonaniu)
+pub fn upload_paths(script: &str) -> Vec<String> {
+    let Ok(commands) = tagui::parse_dsl_script(script) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<String> = Vec::new();
+    for command in commands.iter().filter(|command| command.name == "upload") {
+        let Some(path) = command.args.get(1).map(|path| path.trim()) else { continue };
+        if !path.is_empty() && !path.contains("{{") && !paths.iter().any(|known| known == path) {
+            paths.push(path.to_string());
+        }
+    }
+    paths
+}
+
+/// Podmienia ścieżkę pliku tylko w komendach `upload`, które go wysyłają; ten sam tekst w innych
+/// komendach (np. `type` z nazwą pliku) zostaje bez zmian
+pub fn replace_upload_path(script: &str, from: &str, to: &str) -> String {
+    let (from_literal, to_literal) = (format!("\"{}\"", tagui::escape_for_dsl(from)), format!("\"{}\"", tagui::escape_for_dsl(to)));
+    script
+        .split('\n')
+        .map(|line| {
+            let uploads_path = tagui::tokenize_dsl_line(line.trim())
+                .is_ok_and(|tokens| tokens.first().map(String::as_str) == Some("upload") && tokens.get(2).map(|path| path.trim()) == Some(from));
+            // Ścieżka jest ostatnim cytowanym argumentem komendy (po selektorze)
+            match line.rfind(&from_literal).filter(|_| uploads_path) {
+                Some(position) => format!("{}{}{}", &line[..position], to_literal, &line[position + from_literal.len()..]),
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_addressing_and_upload_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("cv.pdf");
+        let second = dir.path().join("cv-copy.pdf");
+        std::fs::write(&first, b"%PDF-1.4 cv").unwrap();
+        std::fs::write(&second, b"%PDF-1.4 cv").unwrap();
+
+        let sha256 = hash_file(&first).unwrap();
+        assert_eq!(sha256.len(), 64);
+        assert_eq!(hash_file(&second).unwrap(), sha256);
+        assert!(hash_file(&dir.path().join("missing.pdf")).is_err());
+        assert_eq!(object_path(Path::new("/data/artifacts"), &sha256), PathBuf::from(format!("/data/artifacts/objects/{}/{}", &sha256[..2], sha256)));
+
+        let script = "upload \"#cv\" \"/home/jan/cv.pdf\"\nupload \"#letter\" \"{{cover_letter_path}}\"\nupload \"#cv2\" \"/home/jan/cv.pdf\"\nclick \"#submit\"";
+        assert_eq!(upload_paths(script), vec!["/home/jan/cv.pdf".to_string()]);
+        assert!(upload_paths("upload").is_empty());
+
+        let script = "type \"#note\" \"/home/jan/cv.pdf\"\n  upload \"#cv\" \"/home/jan/cv.pdf\"\nupload \"#other\" \"/home/jan/cv.pdf.bak\"";
+        assert_eq!(
+            replace_upload_path(script, "/home/jan/cv.pdf", "/data/objects/ab/abc"),
+            "type \"#note\" \"/home/jan/cv.pdf\"\n  upload \"#cv\" \"/data/objects/ab/abc\"\nupload \"#other\" \"/home/jan/cv.pdf.bak\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gc_untracked_files_and_min_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let known = dir.path().join("objects").join("ab").join("known.png");
+        std::fs::create_dir_all(known.parent().unwrap()).unwrap();
+        std::fs::write(&known, b"known").unwrap();
+        std::fs::write(dir.path().join("stray.har"), b"stray").unwrap();
+        let known_paths: HashSet<String> = [known.to_string_lossy().to_string()].into_iter().collect();
+
+        // Granica w przyszłości: każdy plik jest dość stary, ale znany zostaje
+        let candidates = untracked_files(dir.path(), &known_paths, Utc::now() + chrono::Duration::hours(1));
+        let paths: Vec<&str> = candidates.iter().map(|candidate| candidate.path.as_str()).collect();
+        assert_eq!(paths, vec![dir.path().join("stray.har").to_string_lossy().as_ref()]);
+        assert_eq!(candidates[0].size_bytes, 5);
+        assert!(candidates[0].artifact_id.is_none());
+
+        // Świeże pliki nie są kandydatami
+        assert!(untracked_files(dir.path(), &known_paths, Utc::now() - chrono::Duration::hours(1)).is_empty());
+
+        let store = ArtifactStore::new(PgPool::connect_lazy("postgres://localhost/unused").unwrap(), dir.path());
+        assert!(store.collect_garbage(true, chrono::Duration::hours(-1)).await.is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use anyhow::Result;
+use tracing::{info, warn};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use crate::session::redis_connection;
+
+/// Limity prób podania hasła głównego
+#[derive(Debug, Clone)]
+pub struct GuardPolicy {
+    /// Failures allowed before any cooldown applies
+    pub free_attempts: u32,
+    /// First cooldown after the free attempts; doubles with every further failure
+    pub base_lockout_secs: i64,
+    pub max_lockout_secs: i64,
+    /// Liczniki wygasają po tym czasie bez kolejnych błędów
+    pub window_secs: i64,
+}
+
+impl Default for GuardPolicy {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            base_lockout_secs: 5,
+            max_lockout_secs: 15 * 60,
+            window_secs: 60 * 60,
+        }
+    }
+}
+
+impl GuardPolicy {
+    /// Czas blokady po `failures` nieudanych próbach; krótkie odczekanie przechodzi w pełną blokadę
+    pub fn lockout_secs(&self, failures: u32) -> Option<i64> {
+        let over = failures.checked_sub(self.free_attempts)?;
+        let lockout = self.base_lockout_secs.saturating_mul(1i64 << over.min(30));
+        Some(lockout.min(self.max_lockout_secs))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttemptState {
+    pub failures: u32,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl AttemptState {
+    pub fn retry_after(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.locked_until
+            .filter(|until| *until > now)
+            .map(|until| (until - now).num_seconds().max(1))
+    }
+
+    pub fn register_failure(&mut self, policy: &GuardPolicy, now: DateTime<Utc>) -> Option<i64> {
+        self.failures += 1;
+        let lockout = policy.lockout_secs(self.failures);
+        self.locked_until = lockout.map(|secs| now + Duration::seconds(secs));
+        lockout
+    }
+}
+
+/// Zablokowana próba: ile sekund do kolejnej
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lockout {
+    pub retry_after_secs: i64,
+}
+
+/// Throttles master-password attempts per user and per client IP.
+/// State lives in Redis when configured (shared by all API instances), otherwise in memory.
+/// Failures are counted with an atomic INCR, so concurrent attempts never overwrite each other's count
+pub struct LoginGuard {
+    db_pool: PgPool,
+    redis_client: Option<redis::Client>,
+    policy: GuardPolicy,
+    memory: Mutex<HashMap<String, (AttemptState, DateTime<Utc>)>>,
+}
+
+impl LoginGuard {
+    pub fn new(db_pool: PgPool, redis_client: Option<redis::Client>) -> Self {
+        Self {
+            db_pool,
+            redis_client,
+            policy: GuardPolicy::default(),
+            memory: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Klucze śledzenia logowania: użytkownik i adres klienta
+    pub fn subjects(user: &str, ip: Option<IpAddr>) -> Vec<String> {
+        let mut subjects = vec![format!("user:{}", user.trim().to_lowercase())];
+        subjects.extend(ip.map(|ip| format!("ip:{}", ip)));
+        subjects
+    }
+
+    /// Klucz odblokowania lokalnego sejfu: tylko klient, żeby obcy adres nie mógł zablokować właściciela.
+    /// Bez adresu (gniazdo Unix, IPC) wszystkie próby liczą się jako jeden lokalny klient
+    pub fn client_subjects(ip: Option<IpAddr>) -> Vec<String> {
+        vec![format!("vault:{}", ip.map(|ip| ip.to_string()).unwrap_or_else(|| "local".to_string()))]
+    }
+
+    /// Returns the lockout when any of the subjects is still cooling down
+    pub async fn check(&self, action: &str, subjects: &[String]) -> Option<Lockout> {
+        let mut retry_after = None;
+        for subject in subjects {
+            retry_after = retry_after.max(self.retry_after(subject).await);
+        }
+
+        let retry_after_secs = retry_after?;
+        self.audit("WARN", "blocked_attempt", action, subjects, json_detail(None, Some(retry_after_secs))).await;
+        Some(Lockout { retry_after_secs })
+    }
+
+    pub async fn record_failure(&self, action: &str, subjects: &[String]) -> Option<Lockout> {
+        let mut failures = 0;
+        let mut lockout = None;
+        for subject in subjects {
+            let (subject_failures, subject_lockout) = self.register_failure(subject).await;
+            failures = failures.max(subject_failures);
+            lockout = lockout.max(subject_lockout);
+        }
+
+        let event = if lockout.is_some() { "locked_out" } else { "failed_attempt" };
+        self.audit("WARN", event, action, subjects, json_detail(Some(failures), lockout)).await;
+        lockout.map(|retry_after_secs| Lockout { retry_after_secs })
+    }
+
+    /// Sukces zeruje licznik użytkownika lub klienta sejfu; licznik IP logowania wygasa sam,
+    /// żeby jedno znane konto go nie czyściło
+    pub async fn record_success(&self, action: &str, subjects: &[String]) {
+        for subject in subjects.iter().filter(|subject| !subject.starts_with("ip:")) {
+            self.reset(subject).await;
+        }
+        self.audit("INFO", "success", action, subjects, json_detail(None, None)).await;
+    }
+
+    async fn retry_after(&self, subject: &str) -> Option<i64> {
+        if let Some(redis_client) = &self.redis_client {
+            match self.retry_after_redis(redis_client, subject).await {
+                Ok(retry_after) => return retry_after,
+                Err(e) => warn!("Login guard Redis unavailable, using in-memory counters: {}", e),
+            }
+        }
+
+        let now = Utc::now();
+        let mut memory = self.memory.lock().unwrap();
+        memory.retain(|_, (_, expires_at)| *expires_at > now);
+        memory.get(subject).and_then(|(state, _)| state.retry_after(now))
+    }
+
+    /// Liczba błędów po tej próbie i ewentualna blokada
+    async fn register_failure(&self, subject: &str) -> (u32, Option<i64>) {
+        if let Some(redis_client) = &self.redis_client {
+            match self.register_failure_redis(redis_client, subject).await {
+                Ok(registered) => return registered,
+                Err(e) => warn!("Login guard Redis unavailable, using in-memory counters: {}", e),
+            }
+        }
+
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(self.policy.window_secs);
+        let mut memory = self.memory.lock().unwrap();
+        memory.retain(|_, (_, expires_at)| *expires_at > now);
+        let (state, entry_expires_at) = memory.entry(subject.to_string()).or_default();
+        let lockout = state.register_failure(&self.policy, now);
+        *entry_expires_at = expires_at;
+        (state.failures, lockout)
+    }
+
+    async fn reset(&self, subject: &str) {
+        if let Some(redis_client) = &self.redis_client {
+            match self.reset_redis(redis_client, subject).await {
+                Ok(()) => return,
+                Err(e) => warn!("Login guard Redis unavailable, using in-memory counters: {}", e),
+            }
+        }
+
+        self.memory.lock().unwrap().remove(subject);
+    }
+
+    async fn retry_after_redis(&self, client: &redis::Client, subject: &str) -> Result<Option<i64>> {
+        let mut redis_conn = redis_connection(client).await?;
+        // TTL zwraca -2 dla brakującego klucza i -1 bez wygaśnięcia
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(locked_key(subject))
+            .query_async(&mut redis_conn)
+            .await?;
+        Ok((ttl > 0).then_some(ttl))
+    }
+
+    async fn register_failure_redis(&self, client: &redis::Client, subject: &str) -> Result<(u32, Option<i64>)> {
+        let mut redis_conn = redis_connection(client).await?;
+        let (failures,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(failures_key(subject), 1)
+            .expire(failures_key(subject), self.policy.window_secs as usize)
+            .ignore()
+            .query_async(&mut redis_conn)
+            .await?;
+
+        let lockout = self.policy.lockout_secs(failures);
+        if let Some(secs) = lockout {
+            let _: () = redis::cmd("SET")
+                .arg(locked_key(subject))
+                .arg(failures)
+                .arg("EX")
+                .arg(secs)
+                .query_async(&mut redis_conn)
+                .await?;
+        }
+        Ok((failures, lockout))
+    }
+
+    async fn reset_redis(&self, client: &redis::Client, subject: &str) -> Result<()> {
+        let mut redis_conn = redis_connection(client).await?;
+        let _: () = redis::cmd("DEL")
+            .arg(failures_key(subject))
+            .arg(locked_key(subject))
+            .query_async(&mut redis_conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Zdarzenie audytowe: log z targetem `audit` oraz wpis w `application_logs`
+    async fn audit(&self, level: &str, event: &str, action: &str, subjects: &[String], detail: serde_json::Value) {
+        let message = format!("bitwarden {} {} ({})", action, event, subjects.join(", "));
+        if level == "WARN" {
+            warn!(target: "audit", "{}", message);
+        } else {
+            info!(target: "audit", "{}", message);
+        }
+
+        let result = sqlx::query(
+            "INSERT INTO application_logs (level, target, module, message, additional_data)
+             VALUES ($1, 'audit', 'auth_guard', $2, $3)",
+        )
+        .bind(level)
+        .bind(&message)
+        .bind(serde_json::json!({
+            "event": event,
+            "action": action,
+            "subjects": subjects,
+            "detail": detail
+        }))
+        .execute(&self.db_pool)
+        .await;
+        if let Err(e) = result {
+            warn!("Failed to store audit event: {}", e);
+        }
+    }
+}
+
+fn failures_key(subject: &str) -> String {
+    format!("login_guard:{}:failures", subject)
+}
+
+fn locked_key(subject: &str) -> String {
+    format!("login_guard:{}:locked", subject)
+}
+
+fn json_detail(failures: Option<u32>, retry_after_secs: Option<i64>) -> serde_json::Value {
+    serde_json::json!({ "failures": failures, "retry_after_secs": retry_after_secs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_lockout() {
+        let policy = GuardPolicy::default();
+        let now = Utc::now();
+        let mut state = AttemptState::default();
+
+        for _ in 0..policy.free_attempts - 1 {
+            assert_eq!(state.register_failure(&policy, now), None);
+        }
+        assert_eq!(state.retry_after(now), None);
+
+        assert_eq!(state.register_failure(&policy, now), Some(5));
+        assert_eq!(state.register_failure(&policy, now), Some(10));
+        assert_eq!(state.register_failure(&policy, now), Some(20));
+        assert_eq!(state.retry_after(now + Duration::seconds(15)), Some(5));
+        assert_eq!(state.retry_after(now + Duration::seconds(20)), None);
+
+        assert_eq!(policy.lockout_secs(40), Some(policy.max_lockout_secs));
+    }
+
+    #[test]
+    fn test_subjects() {
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        assert_eq!(LoginGuard::subjects(" Jan@Example.com", Some(ip)), vec!["user:jan@example.com", "ip:10.0.0.7"]);
+        assert_eq!(LoginGuard::subjects("jan@example.com", None), vec!["user:jan@example.com"]);
+        assert_eq!(LoginGuard::client_subjects(Some(ip)), vec!["vault:10.0.0.7"]);
+        assert_eq!(LoginGuard::client_subjects(None), vec!["vault:local"]);
+    }
+}
@@ -0,0 +1,382 @@
+//! Propozycje wypełnienia strony otwartej w webview. Po zakończeniu nawigacji aplikacja szuka
+//! zapisanych skryptów dla witryny i pasujących danych logowania w odblokowanym vault, a ofertę
+//! wysyła do frontendu zdarzeniem `autofill-offer`. Nic nie jest wypełniane bez akceptacji użytkownika.
+
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context, anyhow};
+use tracing::{info, warn};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use codialog_types::automation::{AutofillChoice, AutofillScriptRequest};
+use codialog_types::vault::SecretRef;
+
+use crate::bitwarden::BitwardenManager;
+use crate::cdp::site_of;
+
+/// Zdarzenie Tauri z nową ofertą dla frontendu
+pub const OFFER_EVENT: &str = "autofill-offer";
+
+/// Oferta bez odpowiedzi wygasa po tym czasie
+const OFFER_TTL_MINUTES: i64 = 15;
+
+/// Po odrzuceniu oferty witryna nie dostaje kolejnej przez ten czas
+const DISMISS_MINUTES: i64 = 60;
+
+/// Pole loginu formularza logowania; pierwsze pasujące w dokumencie
+const USERNAME_SELECTOR: &str =
+    "input[autocomplete='username'], input[type='email'], input[name*='user'], input[name*='login'], input[name*='email']";
+const PASSWORD_SELECTOR: &str = "input[type='password']";
+
+/// Skrypt zapisany dla witryny (`autofill_scripts`)
+#[derive(Debug, Clone, Serialize)]
+pub struct AutofillScript {
+    pub id: String,
+    pub name: String,
+    /// Host bez `www.`, np. `portal.example.com`
+    pub site: String,
+    pub script: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptOffer {
+    pub id: String,
+    pub name: String,
+}
+
+/// Dane logowania z vault - oferta nie zawiera hasła
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialOffer {
+    pub id: String,
+    pub name: String,
+    pub username: Option<String>,
+    /// Konto wybrane wcześniej dla tej witryny (`/choices`)
+    pub remembered: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutofillOffer {
+    pub id: String,
+    pub url: String,
+    pub site: String,
+    pub scripts: Vec<ScriptOffer>,
+    pub credentials: Vec<CredentialOffer>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Czy nawigacja prowadzi na stronę, dla której warto szukać oferty (nie na własny frontend aplikacji)
+pub fn offerable_site(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str() == Some("tauri.localhost") {
+        return None;
+    }
+    site_of(url)
+}
+
+/// Skrypt logowania wpisujący login i hasło z vault; wartości są podstawiane przy wykonaniu
+/// jako sekrety, więc nie trafiają do historii przebiegów
+pub fn login_script(credential: &CredentialOffer) -> (String, HashMap<String, SecretRef>) {
+    let secret = |field: &str| SecretRef { item: credential.id.clone(), field: field.to_string() };
+    let mut lines = Vec::new();
+    let mut refs = HashMap::new();
+    if credential.username.is_some() {
+        lines.push(format!("type \"{}\" \"{{{{username}}}}\"", USERNAME_SELECTOR));
+        refs.insert("username".to_string(), secret("username"));
+    }
+    lines.push(format!("type \"{}\" \"{{{{password}}}}\"", PASSWORD_SELECTOR));
+    refs.insert("password".to_string(), secret("password"));
+    (lines.join("\n"), refs)
+}
+
+/// Pasujące dane logowania; przy zablokowanym vault oferta obejmuje tylko skrypty,
+/// żeby nawigacja nie wywoływała próśb o hasło główne
+pub async fn matching_credentials(bitwarden: &tokio::sync::Mutex<BitwardenManager>, site: &str) -> Vec<CredentialOffer> {
+    let bitwarden = bitwarden.lock().await;
+    if !bitwarden.is_session_valid() {
+        return Vec::new();
+    }
+    match bitwarden.get_all_credentials().await {
+        Ok(credentials) => credentials
+            .into_iter()
+            .filter(|credential| credential.password.is_some())
+            .filter(|credential| credential.uri.as_deref().and_then(site_of).as_deref() == Some(site))
+            .map(|credential| CredentialOffer { id: credential.id, name: credential.name, username: credential.username, remembered: false })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to look up vault credentials for autofill: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Zapamiętane konto witryny na początek listy, oznaczone jako wybrane wcześniej
+pub fn prefer_credential(credentials: &mut [CredentialOffer], remembered_id: Option<&str>) {
+    let Some(remembered_id) = remembered_id else {
+        return;
+    };
+    for credential in credentials.iter_mut() {
+        credential.remembered = credential.id == remembered_id;
+    }
+    credentials.sort_by_key(|credential| !credential.remembered);
+}
+
+#[derive(Debug, Default)]
+struct OfferState {
+    offers: HashMap<String, AutofillOffer>,
+    /// Witryna -> koniec wyciszenia po odrzuceniu
+    dismissed: HashMap<String, DateTime<Utc>>,
+}
+
+impl OfferState {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        self.offers.retain(|_, offer| offer.expires_at > now);
+        self.dismissed.retain(|_, until| *until > now);
+    }
+}
+
+/// Oferty czekające na odpowiedź użytkownika (tylko w pamięci tej instancji)
+#[derive(Debug, Default)]
+pub struct AutofillOffers {
+    state: Mutex<OfferState>,
+}
+
+impl AutofillOffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejestruje ofertę dla strony, zastępując wcześniejszą ofertę tej witryny; `None`, gdy nie ma
+    /// czego zaproponować albo witryna jest wyciszona
+    pub fn offer(
+        &self,
+        url: &str,
+        site: &str,
+        scripts: Vec<ScriptOffer>,
+        credentials: Vec<CredentialOffer>,
+        now: DateTime<Utc>,
+    ) -> Option<AutofillOffer> {
+        if scripts.is_empty() && credentials.is_empty() {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.prune(now);
+        if state.dismissed.contains_key(site) {
+            return None;
+        }
+        state.offers.retain(|_, offer| offer.site != site);
+
+        let offer = AutofillOffer {
+            id: uuid::Uuid::new_v4().to_string(),
+            url: url.to_string(),
+            site: site.to_string(),
+            scripts,
+            credentials,
+            created_at: now,
+            expires_at: now + Duration::minutes(OFFER_TTL_MINUTES),
+        };
+        info!(offer_id = %offer.id, site = %site, scripts = offer.scripts.len(), credentials = offer.credentials.len(), "Autofill offered");
+        state.offers.insert(offer.id.clone(), offer.clone());
+        Some(offer)
+    }
+
+    /// Aktualne oferty, najnowsze pierwsze
+    pub fn pending(&self, now: DateTime<Utc>) -> Vec<AutofillOffer> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.prune(now);
+        let mut offers: Vec<AutofillOffer> = state.offers.values().cloned().collect();
+        offers.sort_by_key(|offer| std::cmp::Reverse(offer.created_at));
+        offers
+    }
+
+    /// Zdejmuje ofertę, jeśli zawiera wybraną pozycję - każdą można przyjąć tylko raz
+    pub fn accept(&self, offer_id: &str, choice: &AutofillChoice, now: DateTime<Utc>) -> Option<AutofillOffer> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.prune(now);
+        let offered = state.offers.get(offer_id).is_some_and(|offer| match choice {
+            AutofillChoice::Script { script_id } => offer.scripts.iter().any(|script| &script.id == script_id),
+            AutofillChoice::Credential { credential_id } => offer.credentials.iter().any(|credential| &credential.id == credential_id),
+        });
+        if !offered {
+            return None;
+        }
+        info!(offer_id = %offer_id, choice = ?choice, "Autofill offer accepted");
+        state.offers.remove(offer_id)
+    }
+
+    /// Odrzuca ofertę i wycisza jej witrynę; `false`, jeśli oferty nie ma
+    pub fn dismiss(&self, offer_id: &str, now: DateTime<Utc>) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.prune(now);
+        match state.offers.remove(offer_id) {
+            Some(offer) => {
+                info!(offer_id = %offer_id, site = %offer.site, "Autofill offer dismissed");
+                state.dismissed.insert(offer.site, now + Duration::minutes(DISMISS_MINUTES));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AutofillScriptStore {
+    db_pool: PgPool,
+}
+
+impl AutofillScriptStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę skryptów proponowanych po nawigacji
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing autofill scripts table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS autofill_scripts (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                name VARCHAR(255) NOT NULL,
+                site VARCHAR(255) NOT NULL,
+                script TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_autofill_scripts_site ON autofill_scripts(site);
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create autofill_scripts table")?;
+
+        Ok(())
+    }
+
+    pub async fn create(&self, request: &AutofillScriptRequest) -> Result<AutofillScript> {
+        let site = site_of(&request.url).ok_or_else(|| anyhow!("Invalid site URL: {}", request.url))?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO autofill_scripts (name, site, script)
+            VALUES ($1, $2, $3)
+            RETURNING id::text AS id, name, site, script, created_at
+            "#,
+        )
+        .bind(request.name.trim())
+        .bind(&site)
+        .bind(&request.script)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to save autofill script")?;
+
+        let script = script_from_row(&row);
+        info!(script_id = %script.id, site = %script.site, "Autofill script saved: {}", script.name);
+        Ok(script)
+    }
+
+    pub async fn list(&self) -> Result<Vec<AutofillScript>> {
+        let rows = sqlx::query("SELECT id::text AS id, name, site, script, created_at FROM autofill_scripts ORDER BY site, name")
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to list autofill scripts")?;
+
+        Ok(rows.iter().map(script_from_row).collect())
+    }
+
+    pub async fn get(&self, script_id: &str) -> Result<Option<AutofillScript>> {
+        let row = sqlx::query("SELECT id::text AS id, name, site, script, created_at FROM autofill_scripts WHERE id = $1::uuid")
+            .bind(script_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to load autofill script")?;
+
+        Ok(row.as_ref().map(script_from_row))
+    }
+
+    pub async fn for_site(&self, site: &str) -> Result<Vec<ScriptOffer>> {
+        let rows = sqlx::query("SELECT id::text AS id, name FROM autofill_scripts WHERE site = $1 ORDER BY name")
+            .bind(site)
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to find autofill scripts of site")?;
+
+        Ok(rows.iter().map(|row| ScriptOffer { id: row.get("id"), name: row.get("name") }).collect())
+    }
+
+    pub async fn delete(&self, script_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM autofill_scripts WHERE id = $1::uuid")
+            .bind(script_id)
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to delete autofill script")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn script_from_row(row: &sqlx::postgres::PgRow) -> AutofillScript {
+    AutofillScript {
+        id: row.get("id"),
+        name: row.get("name"),
+        site: row.get("site"),
+        script: row.get("script"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offers_replace_per_site_and_dismiss_mutes_site() {
+        let offers = AutofillOffers::new();
+        let now = Utc::now();
+        let script = || vec![ScriptOffer { id: "s1".to_string(), name: "Logowanie".to_string() }];
+
+        assert_eq!(offerable_site("tauri://localhost/index.html"), None);
+        assert_eq!(offerable_site("http://tauri.localhost/"), None);
+        let site = offerable_site("https://www.portal.example.com/login?next=/").unwrap();
+        assert_eq!(site, "portal.example.com");
+
+        assert!(offers.offer("https://portal.example.com/", &site, Vec::new(), Vec::new(), now).is_none());
+        let first = offers.offer("https://portal.example.com/", &site, script(), Vec::new(), now).unwrap();
+        let second = offers.offer("https://portal.example.com/login", &site, script(), Vec::new(), now).unwrap();
+        assert_eq!(offers.pending(now).len(), 1);
+        let choice = AutofillChoice::Script { script_id: "s1".to_string() };
+        assert!(offers.accept(&first.id, &choice, now).is_none());
+        assert!(offers.accept(&second.id, &AutofillChoice::Credential { credential_id: "s1".to_string() }, now).is_none());
+
+        assert!(offers.dismiss(&second.id, now));
+        assert!(offers.offer("https://portal.example.com/", &site, script(), Vec::new(), now).is_none());
+        let later = now + Duration::minutes(DISMISS_MINUTES + 1);
+        let third = offers.offer("https://portal.example.com/", &site, script(), Vec::new(), later).unwrap();
+        assert!(offers.accept(&third.id, &choice, later).is_some());
+        assert!(offers.accept(&third.id, &choice, later).is_none());
+        offers.offer("https://portal.example.com/", &site, script(), Vec::new(), later).unwrap();
+        assert!(offers.pending(later + Duration::minutes(OFFER_TTL_MINUTES + 1)).is_empty());
+    }
+
+    #[test]
+    fn test_login_script_uses_secret_refs() {
+        let credential = CredentialOffer { id: "item-1".to_string(), name: "Portal".to_string(), username: Some("jan".to_string()), remembered: false };
+        let (script, refs) = login_script(&credential);
+        assert!(crate::tagui::validate_dsl_script(&script).is_ok());
+        assert!(!script.contains("jan"));
+        assert_eq!(refs["username"].field, "username");
+        assert_eq!(refs["password"].item, "item-1");
+
+        let (script, refs) = login_script(&CredentialOffer { username: None, ..credential.clone() });
+        assert_eq!(script.lines().count(), 1);
+        assert!(!refs.contains_key("username"));
+
+        let other = CredentialOffer { id: "item-2".to_string(), name: "Portal (firma)".to_string(), username: None, remembered: false };
+        let mut credentials = vec![credential.clone(), other];
+        prefer_credential(&mut credentials, Some("item-2"));
+        assert_eq!((credentials[0].id.as_str(), credentials[0].remembered, credentials[1].remembered), ("item-2", true, false));
+    }
+}
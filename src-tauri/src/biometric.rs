@@ -0,0 +1,406 @@
+//! Odblokowanie sesji Bitwarden przez Windows Hello / Touch ID zamiast ponownego wpisywania hasła głównego.
+//! Klucz sesji `bw` chroni sam system: na macOS leży w pęku kluczy z listą dostępu wymagającą biometrii
+//! (`kSecAccessControlBiometryCurrentSet`), na Windows jest zaszyfrowany kluczem wyprowadzonym z podpisu
+//! kluczem Windows Hello (`KeyCredentialManager`), który system wykonuje dopiero po weryfikacji użytkownika.
+//! Skrypty systemu (PowerShell, JXA) obsługują okna - aplikacja nie widzi danych biometrycznych.
+
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context, anyhow};
+use tracing::{info, warn};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::bitwarden::LoginSession;
+use crate::crypto::ContentCipher;
+
+/// Zmienna środowiskowa z tekstem okna systemowego - nie trafia do kodu skryptu
+const REASON_ENV: &str = "CODIALOG_BIOMETRIC_REASON";
+
+/// Zmienna środowiskowa z sesją zapisywaną w pęku kluczy macOS - sekret nie trafia do argumentów procesu
+const SECRET_ENV: &str = "CODIALOG_BIOMETRIC_SECRET";
+
+/// Usługa i konto wpisu w pęku kluczy macOS
+const KEYCHAIN_SERVICE: &str = "codialog";
+const KEYCHAIN_ACCOUNT: &str = "bitwarden-session";
+
+/// Nazwa klucza Windows Hello i podpisywane nim wyzwanie; podpis RSA PKCS#1 jest deterministyczny,
+/// więc ten sam klucz daje zawsze ten sam materiał do szyfrowania pliku sesji
+const HELLO_KEY_NAME: &str = "codialog-bitwarden-session";
+const HELLO_CHALLENGE: &str = "codialog-biometric-session-v1";
+
+/// Zawartość BIOMETRIC_SESSION_FILE na macOS - sama sesja jest w pęku kluczy
+const KEYCHAIN_MARKER: &str = "keychain";
+
+/// Ile czekać na reakcję użytkownika w oknie systemowym
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Sprawdzenie dostępności nie wymaga reakcji użytkownika
+const AVAILABILITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BiometricMethod {
+    WindowsHello,
+    TouchId,
+}
+
+impl BiometricMethod {
+    /// Metoda systemu, na którym działa aplikacja; `None` na Linuksie
+    pub fn for_platform() -> Option<Self> {
+        if cfg!(target_os = "windows") {
+            Some(BiometricMethod::WindowsHello)
+        } else if cfg!(target_os = "macos") {
+            Some(BiometricMethod::TouchId)
+        } else {
+            None
+        }
+    }
+
+    fn command(&self, script: &str) -> tokio::process::Command {
+        let mut command = match self {
+            BiometricMethod::WindowsHello => {
+                let mut command = tokio::process::Command::new("powershell");
+                command.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+                command
+            }
+            BiometricMethod::TouchId => {
+                let mut command = tokio::process::Command::new("osascript");
+                command.args(["-l", "JavaScript", "-e", script]);
+                command
+            }
+        };
+        command.kill_on_drop(true);
+        command
+    }
+
+    fn availability_script(&self) -> String {
+        match self {
+            BiometricMethod::WindowsHello => format!(
+                "{}\nAwait ([Windows.Security.Credentials.UI.UserConsentVerifier]::CheckAvailabilityAsync()) \
+                 ([Windows.Security.Credentials.UI.UserConsentVerifierAvailability])",
+                WINRT_AWAIT
+            ),
+            BiometricMethod::TouchId => "ObjC.import('LocalAuthentication');\n\
+                 $.LAContext.alloc.init.canEvaluatePolicyError(1, null) ? 'Available' : 'Unavailable'"
+                .to_string(),
+        }
+    }
+
+    fn verify_script(&self) -> String {
+        match self {
+            BiometricMethod::WindowsHello => format!(
+                "{}\nAwait ([Windows.Security.Credentials.UI.UserConsentVerifier]::RequestVerificationAsync($env:{})) \
+                 ([Windows.Security.Credentials.UI.UserConsentVerificationResult])",
+                WINRT_AWAIT, REASON_ENV
+            ),
+            // LAPolicyDeviceOwnerAuthenticationWithBiometrics = 1; odpowiedź przychodzi w callbacku na run loop
+            BiometricMethod::TouchId => format!(
+                "ObjC.import('LocalAuthentication');\n\
+                 ObjC.import('Foundation');\n\
+                 const reason = $.NSProcessInfo.processInfo.environment.objectForKey('{}').js;\n\
+                 let result = null;\n\
+                 $.LAContext.alloc.init.evaluatePolicyLocalizedReasonReply(1, reason, (ok, error) => {{ result = ok ? 'Verified' : 'Canceled'; }});\n\
+                 const deadline = Date.now() + {};\n\
+                 while (result === null && Date.now() < deadline) {{\n\
+                     $.NSRunLoop.currentRunLoop.runUntilDate($.NSDate.dateWithTimeIntervalSinceNow(0.1));\n\
+                 }}\n\
+                 result || 'TimedOut'",
+                REASON_ENV,
+                VERIFY_TIMEOUT.as_millis()
+            ),
+        }
+    }
+}
+
+/// `await` dla operacji WinRT w PowerShell 5 (przez `WindowsRuntimeSystemExtensions.AsTask`)
+const WINRT_AWAIT: &str = r#"Add-Type -AssemblyName System.Runtime.WindowsRuntime
+[Windows.Security.Credentials.UI.UserConsentVerifier,Windows.Security.Credentials.UI,ContentType=WindowsRuntime] | Out-Null
+[Windows.Security.Credentials.KeyCredentialManager,Windows.Security.Credentials,ContentType=WindowsRuntime] | Out-Null
+$asTask = [System.WindowsRuntimeSystemExtensions].GetMethods() | Where-Object { $_.Name -eq 'AsTask' -and $_.GetParameters().Count -eq 1 -and $_.GetParameters()[0].ParameterType.Name -eq 'IAsyncOperation`1' } | Select-Object -First 1
+$asActionTask = [System.WindowsRuntimeSystemExtensions].GetMethods() | Where-Object { $_.Name -eq 'AsTask' -and $_.GetParameters().Count -eq 1 -and $_.GetParameters()[0].ParameterType.Name -eq 'IAsyncAction' } | Select-Object -First 1
+function Await($operation, [Type]$type) { $task = $asTask.MakeGenericMethod($type).Invoke($null, @($operation)); $task.Wait() | Out-Null; $task.Result }
+function AwaitAction($action) { $asActionTask.Invoke($null, @($action)).Wait() | Out-Null }"#;
+
+/// Podpis wyzwania kluczem Windows Hello (`create` - nowy klucz); system pokazuje okno weryfikacji.
+/// Wypisuje `Verified` i podpis w base64 albo status `KeyCredentialStatus`
+fn hello_sign_script(create: bool) -> String {
+    let open = if create {
+        format!(
+            "[Windows.Security.Credentials.KeyCredentialManager]::RequestCreateAsync('{}', [Windows.Security.Credentials.KeyCredentialCreationOption]::ReplaceExisting)",
+            HELLO_KEY_NAME
+        )
+    } else {
+        format!("[Windows.Security.Credentials.KeyCredentialManager]::OpenAsync('{}')", HELLO_KEY_NAME)
+    };
+    format!(
+        "{}
+         $key = Await ({}) ([Windows.Security.Credentials.KeyCredentialRetrievalResult])
+         if ($key.Status -ne 'Success') {{ $key.Status; exit }}
+         $challenge = [System.Runtime.InteropServices.WindowsRuntime.WindowsRuntimeBufferExtensions]::AsBuffer([Text.Encoding]::UTF8.GetBytes('{}'))
+         $signed = Await ($key.Credential.RequestSignAsync($challenge)) ([Windows.Security.Credentials.KeyCredentialOperationResult])
+         if ($signed.Status -ne 'Success') {{ $signed.Status; exit }}
+         'Verified'
+         [Convert]::ToBase64String([System.Runtime.InteropServices.WindowsRuntime.WindowsRuntimeBufferExtensions]::ToArray($signed.Result))",
+        WINRT_AWAIT, open, HELLO_CHALLENGE
+    )
+}
+
+fn hello_delete_script() -> String {
+    format!("{}
+AwaitAction ([Windows.Security.Credentials.KeyCredentialManager]::DeleteAsync('{}'))
+'Deleted'", WINRT_AWAIT, HELLO_KEY_NAME)
+}
+
+/// Zapytanie o wpis sesji w pęku kluczy macOS (zmienna `query` w JXA)
+fn keychain_query() -> String {
+    format!(
+        "ObjC.import('Security');
+         ObjC.import('Foundation');
+         const env = $.NSProcessInfo.processInfo.environment;
+         const query = $.NSMutableDictionary.alloc.init;
+         query.setObjectForKey($.kSecClassGenericPassword, $.kSecClass);
+         query.setObjectForKey($('{}'), $.kSecAttrService);
+         query.setObjectForKey($('{}'), $.kSecAttrAccount);
+         query.setObjectForKey($.kCFBooleanTrue, $.kSecUseDataProtectionKeychain);
+",
+        KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT
+    )
+}
+
+/// Zapis sesji z listą dostępu: odczyt tylko po Touch ID aktualnie zarejestrowanych palców (BiometryCurrentSet = 8)
+fn keychain_store_script() -> String {
+    format!(
+        "{}         $.SecItemDelete(query);
+         const access = $.SecAccessControlCreateWithFlags(null, $.kSecAttrAccessibleWhenPasscodeSetThisDeviceOnly, 8, null);
+         query.setObjectForKey(access, $.kSecAttrAccessControl);
+         query.setObjectForKey($(env.objectForKey('{}').js).dataUsingEncoding($.NSUTF8StringEncoding), $.kSecValueData);
+         const status = $.SecItemAdd(query, null);
+         status === 0 ? 'Verified' : 'Error ' + status",
+        keychain_query(),
+        SECRET_ENV
+    )
+}
+
+/// Odczyt sesji - system sam pokazuje okno Touch ID z tekstem `reason`
+fn keychain_load_script() -> String {
+    format!(
+        "{}         query.setObjectForKey($.kCFBooleanTrue, $.kSecReturnData);
+         query.setObjectForKey($.kSecMatchLimitOne, $.kSecMatchLimit);
+         query.setObjectForKey(env.objectForKey('{}'), $.kSecUseOperationPrompt);
+         const data = Ref();
+         const status = $.SecItemCopyMatching(query, data);
+         status === 0 ? 'Verified\n' + $.NSString.alloc.initWithDataEncoding(data[0], $.NSUTF8StringEncoding).js
+             : status === -25300 ? 'NotFound' : status === -128 || status === -25293 ? 'Canceled' : 'Error ' + status",
+        keychain_query(),
+        REASON_ENV
+    )
+}
+
+fn keychain_delete_script() -> String {
+    format!("{}const status = $.SecItemDelete(query);
+status === 0 || status === -25300 ? 'Deleted' : 'Error ' + status", keychain_query())
+}
+
+/// Wynik okna systemowego
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BiometricOutcome {
+    Verified,
+    /// Użytkownik anulował okno albo weryfikacja się nie powiodła
+    Rejected,
+    /// Brak czytnika, nieskonfigurowana biometria albo zablokowana przez zasady systemu
+    Unavailable,
+}
+
+/// Ostatnia linia wyjścia skryptu: `Verified`/`Available` albo nazwa błędu z API systemu
+fn parse_outcome(stdout: &str) -> BiometricOutcome {
+    outcome_of(stdout.lines().map(str::trim).rfind(|line| !line.is_empty()).unwrap_or_default())
+}
+
+fn outcome_of(status: &str) -> BiometricOutcome {
+    match status {
+        "Verified" | "Available" => BiometricOutcome::Verified,
+        "DeviceNotPresent" | "NotConfiguredForUser" | "DisabledByPolicy" | "DeviceBusy" | "Unavailable" | "NotFound" | "SecurityDeviceLocked" => {
+            BiometricOutcome::Unavailable
+        }
+        _ => BiometricOutcome::Rejected,
+    }
+}
+
+/// Pierwsza linia wyjścia to wynik; po `Verified` reszta to sekret (sesja albo podpis)
+fn parse_secret(stdout: &str) -> (BiometricOutcome, Option<String>) {
+    let stdout = stdout.trim_start();
+    let (status, rest) = stdout.split_once('\n').unwrap_or((stdout, ""));
+    let outcome = outcome_of(status.trim());
+    let secret = Some(rest.trim()).filter(|secret| outcome == BiometricOutcome::Verified && !secret.is_empty());
+    (outcome, secret.map(str::to_string))
+}
+
+/// Wyjście skryptu systemu; `None`, gdy skrypt zakończył się błędem
+async fn run_command(method: BiometricMethod, script: &str, env: &[(&str, &str)], limit: Duration) -> Result<Option<String>> {
+    let mut command = method.command(script);
+    command.envs(env.iter().copied());
+    let output = tokio::time::timeout(limit, command.output())
+        .await
+        .map_err(|_| anyhow!("Biometric prompt timed out after {}s", limit.as_secs()))?
+        .context("Failed to start system biometric prompt")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!(method = ?method, "Biometric script failed: {}", stderr.trim());
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+async fn run_script(method: BiometricMethod, script: &str, reason: &str, limit: Duration) -> Result<BiometricOutcome> {
+    let stdout = run_command(method, script, &[(REASON_ENV, reason)], limit).await?;
+    Ok(stdout.map(|stdout| parse_outcome(&stdout)).unwrap_or(BiometricOutcome::Unavailable))
+}
+
+/// Sekret zwrócony przez system po weryfikacji użytkownika
+async fn run_secret_script(method: BiometricMethod, script: &str, env: &[(&str, &str)]) -> Result<(BiometricOutcome, Option<String>)> {
+    let stdout = run_command(method, script, env, VERIFY_TIMEOUT + Duration::from_secs(5)).await?;
+    Ok(stdout.map(|stdout| parse_secret(&stdout)).unwrap_or((BiometricOutcome::Unavailable, None)))
+}
+
+/// Czy system może teraz zweryfikować użytkownika biometrycznie
+pub async fn is_available(method: BiometricMethod) -> bool {
+    match run_script(method, &method.availability_script(), "", AVAILABILITY_TIMEOUT).await {
+        Ok(outcome) => outcome == BiometricOutcome::Verified,
+        Err(e) => {
+            warn!(method = ?method, "Biometric availability check failed: {}", e);
+            false
+        }
+    }
+}
+
+/// Pokazuje systemowe okno Windows Hello / Touch ID z tekstem `reason`
+pub async fn verify(method: BiometricMethod, reason: &str) -> Result<BiometricOutcome> {
+    info!(method = ?method, "Requesting biometric verification");
+    run_script(method, &method.verify_script(), reason, VERIFY_TIMEOUT + Duration::from_secs(5)).await
+}
+
+/// Klucz sesji Bitwarden do odblokowania biometrycznego. BIOMETRIC_SESSION_FILE oznacza włączenie funkcji:
+/// na Windows zawiera sesję zaszyfrowaną kluczem z podpisu Windows Hello, na macOS tylko znacznik pęku kluczy
+#[derive(Debug, Clone)]
+pub struct BiometricSessionStore {
+    path: PathBuf,
+}
+
+impl BiometricSessionStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn is_enrolled(&self) -> bool {
+        self.path.is_file()
+    }
+
+    /// Oddaje sesję pod ochronę systemu; na Windows tworzy klucz Windows Hello i pokazuje okno weryfikacji
+    pub async fn save(&self, method: BiometricMethod, session: &LoginSession, reason: &str) -> Result<BiometricOutcome> {
+        let json = serde_json::to_string(session).context("Failed to serialize Bitwarden session")?;
+        let stored = match method {
+            BiometricMethod::TouchId => {
+                let (outcome, _) = run_secret_script(method, &keychain_store_script(), &[(SECRET_ENV, &json)]).await?;
+                if outcome != BiometricOutcome::Verified {
+                    return Ok(outcome);
+                }
+                KEYCHAIN_MARKER.to_string()
+            }
+            BiometricMethod::WindowsHello => {
+                let (outcome, signature) = run_secret_script(method, &hello_sign_script(true), &[(REASON_ENV, reason)]).await?;
+                let Some(signature) = signature else {
+                    return Ok(outcome);
+                };
+                ContentCipher::from_secret(&signature)?.encrypt(&json)?
+            }
+        };
+
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&self.path, stored).with_context(|| format!("Failed to write {}", self.path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to restrict permissions of {}", self.path.display()))?;
+        }
+        info!(method = ?method, "Bitwarden session stored for biometric unlock");
+        Ok(BiometricOutcome::Verified)
+    }
+
+    /// Sesja wydana przez system po weryfikacji użytkownika (okno z tekstem `reason`); bez sesji,
+    /// gdy weryfikacja została odrzucona lub odblokowanie biometryczne nie zostało włączone
+    pub async fn load(&self, method: BiometricMethod, reason: &str) -> Result<(BiometricOutcome, Option<LoginSession>)> {
+        if !self.is_enrolled() {
+            return Ok((BiometricOutcome::Unavailable, None));
+        }
+        let (outcome, json) = match method {
+            BiometricMethod::TouchId => run_secret_script(method, &keychain_load_script(), &[(REASON_ENV, reason)]).await?,
+            BiometricMethod::WindowsHello => {
+                let (outcome, signature) = run_secret_script(method, &hello_sign_script(false), &[(REASON_ENV, reason)]).await?;
+                let json = match signature {
+                    Some(signature) => {
+                        let stored = std::fs::read_to_string(&self.path).with_context(|| format!("Failed to read {}", self.path.display()))?;
+                        Some(ContentCipher::from_secret(&signature)?.decrypt(stored.trim()).context("Failed to decrypt stored Bitwarden session")?)
+                    }
+                    None => None,
+                };
+                (outcome, json)
+            }
+        };
+        let session = json.map(|json| serde_json::from_str(&json).context("Invalid stored Bitwarden session")).transpose()?;
+        Ok((outcome, session))
+    }
+
+    /// Wyłącza odblokowanie biometryczne i usuwa sesję z pęku kluczy lub klucz Windows Hello;
+    /// `false`, jeśli nie było włączone
+    pub async fn clear(&self) -> Result<bool> {
+        if let Some(method) = BiometricMethod::for_platform() {
+            let script = match method {
+                BiometricMethod::TouchId => keychain_delete_script(),
+                BiometricMethod::WindowsHello => hello_delete_script(),
+            };
+            match run_command(method, &script, &[], AVAILABILITY_TIMEOUT).await {
+                Ok(Some(stdout)) if stdout.trim() == "Deleted" => {}
+                Ok(stdout) => warn!(method = ?method, "Failed to remove the protected Bitwarden session: {}", stdout.unwrap_or_default().trim()),
+                Err(e) => warn!(method = ?method, "Failed to remove the protected Bitwarden session: {}", e),
+            }
+        }
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => {
+                info!("Biometric unlock disabled, stored Bitwarden session removed");
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", self.path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_outcome_and_reason_passed_through_env() {
+        assert_eq!(parse_outcome("Verified\r\n"), BiometricOutcome::Verified);
+        assert_eq!(parse_outcome("warning: slow\nAvailable\n"), BiometricOutcome::Verified);
+        assert_eq!(parse_outcome("NotConfiguredForUser"), BiometricOutcome::Unavailable);
+        assert_eq!(parse_outcome("Canceled"), BiometricOutcome::Rejected);
+        assert_eq!(parse_outcome(""), BiometricOutcome::Rejected);
+
+        for method in [BiometricMethod::WindowsHello, BiometricMethod::TouchId] {
+            assert!(method.verify_script().contains(REASON_ENV));
+        }
+
+        // Sekret tylko po weryfikacji; sesja nie trafia do kodu skryptu
+        assert_eq!(parse_secret("Verified\r\n{\"session\":\"abc\"}\n"), (BiometricOutcome::Verified, Some("{\"session\":\"abc\"}".to_string())));
+        assert_eq!(parse_secret("UserCanceled\n"), (BiometricOutcome::Rejected, None));
+        assert_eq!(parse_secret("NotFound"), (BiometricOutcome::Unavailable, None));
+        assert!(keychain_store_script().contains(SECRET_ENV));
+        assert!(keychain_load_script().contains("kSecUseOperationPrompt"));
+    }
+}
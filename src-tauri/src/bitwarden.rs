@@ -2,33 +2,39 @@ use serde::{Deserialize, Serialize};
 use reqwest::Client;
 use std::process::Command;
 use anyhow::{Result, Context};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
 use tracing::{info, warn, error};
 use tokio::time::{timeout, Duration};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::Zeroize;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BitwardenCredential {
-    pub id: String,
-    pub name: String,
-    pub username: Option<String>,
-    pub password: Option<String>,
-    pub uri: Option<String>,
-    pub notes: Option<String>,
-    pub folder_id: Option<String>,
-}
+use crate::clock::{Clock, SystemClock};
+use crate::crypto;
+use crate::faults::{self, FaultTarget};
+use crate::replay;
+use codialog_types::SecretString;
+
+pub use codialog_types::vault::{BitwardenCredential, SecretRef, VaultExportFormat};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginSession {
-    pub session_token: String,
+    pub session_token: SecretString,
     pub user_id: String,
     pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Zmienna środowiskowa, przez którą hasło główne trafia do `bw --passwordenv`
+const MASTER_PASSWORD_ENV: &str = "BW_MASTER_PASSWORD";
+
 #[derive(Debug, Clone)]
 pub struct BitwardenManager {
     server_url: String,
     cli_server_url: String,
     client: Client,
     session: Option<LoginSession>,
+    clock: Arc<dyn Clock>,
 }
 
 impl BitwardenManager {
@@ -38,9 +44,16 @@ impl BitwardenManager {
             cli_server_url,
             client: Client::new(),
             session: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Ustawia zegar używany do wyznaczania wygaśnięcia sesji
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Inicjalizuje połączenie z serwerem Bitwarden
     pub async fn initialize(&mut self) -> Result<()> {
         info!("Initializing Bitwarden connection to: {}", self.server_url);
@@ -100,22 +113,24 @@ impl BitwardenManager {
     }
 
     /// Zaloguj się do Bitwarden używając master password
-    pub async fn login(&mut self, email: &str, master_password: &str) -> Result<()> {
+    pub async fn login(&mut self, email: &str, master_password: &SecretString) -> Result<()> {
         info!("Attempting login to Bitwarden for user: {}", email);
 
-        // Użyj CLI do zalogowania
-        let output = Command::new("bw")
-            .args(&["login", email, master_password, "--raw"])
+        // Użyj CLI do zalogowania; hasło przez zmienną środowiskową, nie w argumentach widocznych w `ps`
+        faults::inject(FaultTarget::Spawn).await?;
+        let mut output = Command::new("bw")
+            .args(&["login", email, "--passwordenv", MASTER_PASSWORD_ENV, "--raw"])
+            .env(MASTER_PASSWORD_ENV, master_password.expose_secret())
             .output()
             .context("Failed to execute bitwarden CLI login command")?;
 
         if output.status.success() {
-            let session_token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            let session_token = SecretString::from_output(&mut output.stdout);
             
             self.session = Some(LoginSession {
-                session_token: session_token.clone(),
+                session_token,
                 user_id: email.to_string(),
-                expires_at: chrono::Utc::now() + chrono::Duration::hours(24),
+                expires_at: self.clock.now() + chrono::Duration::hours(24),
             });
 
             info!("Successfully logged into Bitwarden");
@@ -128,18 +143,19 @@ impl BitwardenManager {
     }
 
     /// Odblokowuje vault używając master password
-    pub async fn unlock(&mut self, master_password: &str) -> Result<()> {
+    pub async fn unlock(&mut self, master_password: &SecretString) -> Result<()> {
         info!("Unlocking Bitwarden vault");
 
         if let Some(ref session) = self.session {
-            let output = Command::new("bw")
-                .args(&["unlock", master_password, "--raw"])
-                .env("BW_SESSION", &session.session_token)
+            let mut output = Command::new("bw")
+                .args(&["unlock", "--passwordenv", MASTER_PASSWORD_ENV, "--raw"])
+                .env(MASTER_PASSWORD_ENV, master_password.expose_secret())
+                .env("BW_SESSION", session.session_token.expose_secret())
                 .output()
                 .context("Failed to execute bitwarden CLI unlock command")?;
 
             if output.status.success() {
-                let session_token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                let session_token = SecretString::from_output(&mut output.stdout);
                 
                 // Aktualizuj token sesji
                 if let Some(ref mut session) = self.session {
@@ -163,42 +179,54 @@ impl BitwardenManager {
         info!("Retrieving all credentials from Bitwarden vault");
 
         if let Some(ref session) = self.session {
-            let output = Command::new("bw")
-                .args(&["list", "items", "--session", &session.session_token])
-                .output()
-                .context("Failed to execute bitwarden CLI list command")?;
-
-            if output.status.success() {
-                let json_output = String::from_utf8_lossy(&output.stdout);
-                let items: Vec<serde_json::Value> = serde_json::from_str(&json_output)
-                    .context("Failed to parse Bitwarden items JSON")?;
-
-                let credentials: Vec<BitwardenCredential> = items
-                    .into_iter()
-                    .filter_map(|item| {
-                        if item["type"] == 1 { // Type 1 = login item
-                            Some(BitwardenCredential {
-                                id: item["id"].as_str().unwrap_or("").to_string(),
-                                name: item["name"].as_str().unwrap_or("").to_string(),
-                                username: item["login"]["username"].as_str().map(|s| s.to_string()),
-                                password: item["login"]["password"].as_str().map(|s| s.to_string()),
-                                uri: item["login"]["uris"][0]["uri"].as_str().map(|s| s.to_string()),
-                                notes: item["notes"].as_str().map(|s| s.to_string()),
-                                folder_id: item["folderId"].as_str().map(|s| s.to_string()),
-                            })
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                info!("Retrieved {} credentials from Bitwarden", credentials.len());
-                Ok(credentials)
-            } else {
-                let error_msg = String::from_utf8_lossy(&output.stderr);
-                error!("Failed to retrieve credentials: {}", error_msg);
-                Err(anyhow::anyhow!("Failed to retrieve Bitwarden credentials: {}", error_msg))
-            }
+            // Wyjście `bw list items` trafia do paczek replay bez haseł i notatek
+            let items: Vec<serde_json::Value> = replay::intercept_redacted(
+                replay::InteractionKind::BitwardenOutput,
+                "list items",
+                redact_vault_items,
+                || async {
+                    faults::inject(FaultTarget::Spawn).await?;
+                    let mut output = Command::new("bw")
+                        .args(&["list", "items"])
+                        .env("BW_SESSION", session.session_token.expose_secret())
+                        .output()
+                        .context("Failed to execute bitwarden CLI list command")?;
+
+                    if !output.status.success() {
+                        let error_msg = String::from_utf8_lossy(&output.stderr);
+                        error!("Failed to retrieve credentials: {}", error_msg);
+                        return Err(anyhow::anyhow!("Failed to retrieve Bitwarden credentials: {}", error_msg));
+                    }
+
+                    // Wyjście zawiera hasła z vault - bufor zerowany po sparsowaniu
+                    let parsed = serde_json::from_slice(&output.stdout).context("Failed to parse Bitwarden items JSON");
+                    output.stdout.zeroize();
+                    parsed
+                },
+            )
+            .await?;
+
+            let credentials: Vec<BitwardenCredential> = items
+                .into_iter()
+                .filter_map(|item| {
+                    if item["type"] == 1 { // Type 1 = login item
+                        Some(BitwardenCredential {
+                            id: item["id"].as_str().unwrap_or("").to_string(),
+                            name: item["name"].as_str().unwrap_or("").to_string(),
+                            username: item["login"]["username"].as_str().map(|s| s.to_string()),
+                            password: item["login"]["password"].as_str().map(SecretString::from),
+                            uri: item["login"]["uris"][0]["uri"].as_str().map(|s| s.to_string()),
+                            notes: item["notes"].as_str().map(|s| s.to_string()),
+                            folder_id: item["folderId"].as_str().map(|s| s.to_string()),
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            info!("Retrieved {} credentials from Bitwarden", credentials.len());
+            Ok(credentials)
         } else {
             Err(anyhow::anyhow!("No active Bitwarden session. Please login first."))
         }
@@ -225,6 +253,38 @@ impl BitwardenManager {
         Ok(matching_credentials)
     }
 
+    /// Resolves named secret references against the vault in a single `bw list` call.
+    /// Values are returned only to the caller and never logged.
+    pub async fn resolve_secrets(&self, refs: &HashMap<String, SecretRef>) -> Result<HashMap<String, SecretString>> {
+        if refs.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        info!("Resolving {} secret references from Bitwarden", refs.len());
+        let credentials = self.get_all_credentials().await?;
+
+        refs.iter()
+            .map(|(name, secret_ref)| {
+                let credential = credentials
+                    .iter()
+                    .find(|cred| cred.id == secret_ref.item || cred.name == secret_ref.item)
+                    .ok_or_else(|| anyhow::anyhow!("Secret '{}': vault item '{}' not found", name, secret_ref.item))?;
+
+                let value = match secret_ref.field.as_str() {
+                    "password" => credential.password.clone(),
+                    "username" => credential.username.clone().map(SecretString::from),
+                    "uri" => credential.uri.clone().map(SecretString::from),
+                    "notes" => credential.notes.clone().map(SecretString::from),
+                    other => return Err(anyhow::anyhow!("Secret '{}': unsupported field '{}'", name, other)),
+                };
+
+                value
+                    .map(|value| (name.clone(), value))
+                    .ok_or_else(|| anyhow::anyhow!("Secret '{}': item '{}' has no {}", name, secret_ref.item, secret_ref.field))
+            })
+            .collect()
+    }
+
     /// Dodaj nowe dane logowania do vault
     pub async fn add_credential(&self, credential: &BitwardenCredential) -> Result<String> {
         info!("Adding new credential to Bitwarden vault: {}", credential.name);
@@ -249,7 +309,8 @@ impl BitwardenManager {
                 .context("Failed to write temporary Bitwarden item file")?;
 
             let output = Command::new("bw")
-                .args(&["create", "item", &temp_file, "--session", &session.session_token])
+                .args(&["create", "item", &temp_file])
+                .env("BW_SESSION", session.session_token.expose_secret())
                 .output()
                 .context("Failed to execute bitwarden CLI create command")?;
 
@@ -273,10 +334,49 @@ impl BitwardenManager {
         }
     }
 
+    /// Eksportuje dostępne elementy vault do zaszyfrowanego pliku w `dir`. `bw export --raw` pisze na stdout,
+    /// a wynik jest szyfrowany tutaj hasłem eksportu, zanim cokolwiek zostanie zapisane na dysk - hasło
+    /// nie trafia do argumentów procesu, gdzie widzieliby je inni użytkownicy (`ps`, /proc).
+    pub async fn export_vault(&self, format: VaultExportFormat, passphrase: &SecretString, dir: &Path) -> Result<PathBuf> {
+        validate_export_passphrase(passphrase)?;
+        let Some(ref session) = self.session else {
+            return Err(anyhow::anyhow!("No active Bitwarden session. Please login first."));
+        };
+
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create export directory {}", dir.display()))?;
+        let path = dir.join(export_file_name(format, self.clock.now()));
+        info!(format = ?format, "Exporting Bitwarden vault to {}", path.display());
+
+        faults::inject(FaultTarget::Spawn).await?;
+        let bw_format = match format {
+            VaultExportFormat::Json => "json",
+            VaultExportFormat::Csv => "csv",
+        };
+        let mut output = Command::new("bw")
+            .args(["export", "--format", bw_format, "--raw"])
+            .env("BW_SESSION", session.session_token.expose_secret())
+            .output()
+            .context("Failed to execute bitwarden CLI export command")?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            error!("Failed to export Bitwarden vault: {}", error_msg);
+            return Err(anyhow::anyhow!("Bitwarden export failed: {}", error_msg));
+        }
+
+        // Wyjście zawiera hasła z vault - bufor zerowany po zaszyfrowaniu
+        let contents = SecretString::from_output(&mut output.stdout);
+        let encrypted = encrypt_export(contents.expose_secret(), passphrase)?;
+        std::fs::write(&path, encrypted).with_context(|| format!("Failed to write vault export {}", path.display()))?;
+
+        info!("Bitwarden vault exported to {}", path.display());
+        Ok(path)
+    }
+
     /// Sprawdź czy sesja jest nadal aktywna
     pub fn is_session_valid(&self) -> bool {
         if let Some(ref session) = self.session {
-            chrono::Utc::now() < session.expires_at
+            !self.clock.is_expired(session.expires_at)
         } else {
             false
         }
@@ -287,6 +387,11 @@ impl BitwardenManager {
         self.session.as_ref()
     }
 
+    /// Przywraca sesję zapisaną wcześniej (np. przez odblokowanie biometryczne)
+    pub fn restore_session(&mut self, session: LoginSession) {
+        self.session = Some(session);
+    }
+
     /// Wyloguj się z Bitwarden
     pub async fn logout(&mut self) -> Result<()> {
         info!("Logging out from Bitwarden");
@@ -301,3 +406,89 @@ impl BitwardenManager {
         Ok(())
     }
 }
+
+/// Minimalna długość hasła eksportu vault
+const MIN_EXPORT_PASSPHRASE_CHARS: usize = 12;
+
+pub fn validate_export_passphrase(passphrase: &SecretString) -> Result<()> {
+    if passphrase.expose_secret().chars().count() < MIN_EXPORT_PASSPHRASE_CHARS {
+        anyhow::bail!("Export passphrase must have at least {} characters", MIN_EXPORT_PASSPHRASE_CHARS);
+    }
+    Ok(())
+}
+
+/// `vault-export-20261015-093000.json.enc` albo `.csv.enc` - oba zaszyfrowane przez `encrypt_export`
+fn export_file_name(format: VaultExportFormat, at: chrono::DateTime<chrono::Utc>) -> String {
+    let extension = match format {
+        VaultExportFormat::Json => "json.enc",
+        VaultExportFormat::Csv => "csv.enc",
+    };
+    format!("vault-export-{}.{}", at.format("%Y%m%d-%H%M%S"), extension)
+}
+
+/// Nagłówek zaszyfrowanego eksportu: `pbkdf2-sha256$<iteracje>$<sól base64>$` przed `enc:v1:...`
+const EXPORT_KDF: &str = "pbkdf2-sha256";
+const EXPORT_KDF_ITERATIONS: u32 = 600_000;
+
+/// AES-256-GCM z kluczem wyprowadzonym z hasła eksportu przez PBKDF2 z losową solą zapisaną w nagłówku
+fn encrypt_export(contents: &str, passphrase: &SecretString) -> Result<String> {
+    let salt = crypto::ContentCipher::random_salt()?;
+    let cipher = crypto::ContentCipher::from_passphrase(passphrase.expose_secret(), &salt, EXPORT_KDF_ITERATIONS)?;
+    Ok(format!("{}${}${}${}", EXPORT_KDF, EXPORT_KDF_ITERATIONS, STANDARD.encode(salt), cipher.encrypt(contents)?))
+}
+
+/// Usuwa hasła, kody TOTP i notatki z wyjścia `bw list items` przed zapisem do paczki replay
+fn redact_vault_items(items: &mut serde_json::Value) {
+    const REDACTED: &str = "[REDACTED]";
+    if let Some(items) = items.as_array_mut() {
+        for item in items {
+            if let Some(login) = item.get_mut("login").and_then(|login| login.as_object_mut()) {
+                for field in ["password", "totp"] {
+                    if login.get(field).map(|value| !value.is_null()).unwrap_or(false) {
+                        login.insert(field.to_string(), REDACTED.into());
+                    }
+                }
+            }
+            if item.get("notes").map(|notes| !notes.is_null()).unwrap_or(false) {
+                item["notes"] = REDACTED.into();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Odszyfrowuje plik `.json.enc`/`.csv.enc` zapisany przez `encrypt_export`
+    fn decrypt_export(stored: &str, passphrase: &SecretString) -> Result<String> {
+        let mut parts = stored.trim().splitn(4, '$');
+        let (Some(EXPORT_KDF), Some(iterations), Some(salt), Some(encrypted)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            anyhow::bail!("Unsupported vault export format");
+        };
+        let iterations = iterations.parse().context("Invalid PBKDF2 iteration count in vault export")?;
+        let salt = STANDARD.decode(salt).context("Invalid salt in vault export")?;
+        crypto::ContentCipher::from_passphrase(passphrase.expose_secret(), &salt, iterations)?.decrypt(encrypted)
+    }
+
+    #[test]
+    fn test_export_names_and_csv_encryption() {
+        let at = chrono::DateTime::parse_from_rfc3339("2026-10-15T09:30:00Z").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(export_file_name(VaultExportFormat::Json, at), "vault-export-20261015-093000.json.enc");
+        assert_eq!(export_file_name(VaultExportFormat::Csv, at), "vault-export-20261015-093000.csv.enc");
+
+        assert!(validate_export_passphrase(&SecretString::from("short")).is_err());
+        let passphrase = SecretString::from("correct horse battery");
+        assert!(validate_export_passphrase(&passphrase).is_ok());
+
+        let csv = "name,login_uri,login_username,login_password\nMail,https://mail.example.com,jan,s3cret\n";
+        let encrypted = encrypt_export(csv, &passphrase).unwrap();
+        assert!(!encrypted.contains("s3cret"));
+        assert!(encrypted.starts_with("pbkdf2-sha256$600000$"));
+        assert_eq!(decrypt_export(&encrypted, &passphrase).unwrap(), csv);
+        assert!(decrypt_export(&encrypted, &SecretString::from("wrong passphrase!")).is_err());
+        // Losowa sól - ten sam plik i hasło dają inny klucz, a sam skrót hasła nie odszyfruje eksportu
+        assert_ne!(encrypt_export(csv, &passphrase).unwrap().split('$').nth(2), encrypted.split('$').nth(2));
+        assert!(crypto::ContentCipher::from_secret("correct horse battery").unwrap().decrypt(encrypted.rsplit('$').next().unwrap()).is_err());
+    }
+}
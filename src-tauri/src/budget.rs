@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context, bail};
+use tracing::{info, warn};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::future::Future;
+use std::pin::Pin;
+
+use codialog_types::automation::BudgetLimits;
+
+use crate::jobs::JobOwner;
+use crate::{llm_usage, logging};
+
+/// Czyje zużycie liczą limity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetOwner {
+    Schedule,
+    User,
+}
+
+impl BudgetOwner {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "schedule" | "schedules" => Some(BudgetOwner::Schedule),
+            "user" | "users" => Some(BudgetOwner::User),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetOwner::Schedule => "schedule",
+            BudgetOwner::User => "user",
+        }
+    }
+}
+
+/// Limit, który wstrzymał automatyzację
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetCap {
+    RunsPerDay,
+    LlmSpendPerDay,
+    Failures,
+}
+
+impl BudgetCap {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetCap::RunsPerDay => "runs_per_day",
+            BudgetCap::LlmSpendPerDay => "llm_spend_per_day",
+            BudgetCap::Failures => "failures",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "runs_per_day" => Some(BudgetCap::RunsPerDay),
+            "llm_spend_per_day" => Some(BudgetCap::LlmSpendPerDay),
+            "failures" => Some(BudgetCap::Failures),
+            _ => None,
+        }
+    }
+
+    /// Limity dobowe wstrzymują do północy UTC, seria błędów - do ręcznego wznowienia
+    pub fn paused_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            BudgetCap::Failures => None,
+            BudgetCap::RunsPerDay | BudgetCap::LlmSpendPerDay => {
+                let tomorrow = now.date_naive() + Duration::days(1);
+                Some(tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+            }
+        }
+    }
+}
+
+/// Zużycie w dobie `usage_day` (UTC); seria błędów nie zeruje się o północy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub runs_today: u32,
+    pub llm_spend_today: f64,
+    pub consecutive_failures: u32,
+}
+
+/// Pierwszy osiągnięty limit; kolejność: seria błędów, przebiegi, koszt LLM
+pub fn reached_cap(limits: &BudgetLimits, usage: &BudgetUsage) -> Option<BudgetCap> {
+    if limits.max_failures.is_some_and(|max| usage.consecutive_failures >= max) {
+        return Some(BudgetCap::Failures);
+    }
+    if limits.max_runs_per_day.is_some_and(|max| usage.runs_today >= max) {
+        return Some(BudgetCap::RunsPerDay);
+    }
+    if limits.max_llm_spend_per_day.is_some_and(|max| usage.llm_spend_today >= max) {
+        return Some(BudgetCap::LlmSpendPerDay);
+    }
+    None
+}
+
+pub fn validate_limits(limits: &BudgetLimits) -> Result<()> {
+    if let Some(spend) = limits.max_llm_spend_per_day {
+        if !spend.is_finite() || spend < 0.0 {
+            bail!("max_llm_spend_per_day must be a non-negative amount");
+        }
+    }
+    Ok(())
+}
+
+/// Limity i zużycie harmonogramu lub użytkownika
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    pub owner_type: BudgetOwner,
+    pub owner_id: String,
+    pub limits: BudgetLimits,
+    pub usage_day: Option<NaiveDate>,
+    pub usage: BudgetUsage,
+    pub paused_at: Option<DateTime<Utc>>,
+    /// Koniec wstrzymania; `None` przy wstrzymaniu do ręcznego wznowienia
+    pub paused_until: Option<DateTime<Utc>>,
+    pub paused_reason: Option<BudgetCap>,
+}
+
+impl Budget {
+    pub fn is_paused_at(&self, now: DateTime<Utc>) -> bool {
+        self.paused_at.is_some() && self.paused_until.is_none_or(|until| now < until)
+    }
+
+    /// Zużycie w dobie `now` - liczniki z poprzednich dni już nie obowiązują
+    pub fn usage_at(&self, now: DateTime<Utc>) -> BudgetUsage {
+        if self.usage_day == Some(now.date_naive()) {
+            self.usage
+        } else {
+            BudgetUsage { consecutive_failures: self.usage.consecutive_failures, ..BudgetUsage::default() }
+        }
+    }
+
+    /// Limit blokujący nowe przebiegi w chwili `now`
+    pub fn blocking_cap(&self, now: DateTime<Utc>) -> Option<BudgetCap> {
+        if self.is_paused_at(now) {
+            return self.paused_reason.or(Some(BudgetCap::Failures));
+        }
+        reached_cap(&self.limits, &self.usage_at(now))
+    }
+}
+
+/// Automatyzacja zatrzymana przez limit: kto i dlaczego
+#[derive(Debug, Clone)]
+pub struct BudgetBlock {
+    pub owner_type: BudgetOwner,
+    pub owner_id: String,
+    pub cap: BudgetCap,
+}
+
+impl std::fmt::Display for BudgetBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} is paused by its {} limit", self.owner_type.as_str(), self.owner_id, self.cap.as_str())
+    }
+}
+
+const BUDGET_COLUMNS: &str = "owner_type, owner_id, max_runs_per_day, max_llm_spend_per_day, max_failures, usage_day, \
+    runs_today, llm_spend_today, consecutive_failures, paused_at, paused_until, paused_reason";
+
+/// Limity budżetu i dobowe zużycie w PostgreSQL, wspólne dla harmonogramu i kolejki zadań
+#[derive(Debug, Clone)]
+pub struct BudgetStore {
+    db_pool: PgPool,
+}
+
+impl BudgetStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę limitów
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing automation budgets table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS automation_budgets (
+                owner_type VARCHAR(20) NOT NULL, -- 'schedule', 'user'
+                owner_id VARCHAR(255) NOT NULL,
+                max_runs_per_day INTEGER,
+                max_llm_spend_per_day DOUBLE PRECISION,
+                max_failures INTEGER,
+                usage_day DATE,
+                runs_today INTEGER NOT NULL DEFAULT 0,
+                llm_spend_today DOUBLE PRECISION NOT NULL DEFAULT 0,
+                consecutive_failures INTEGER NOT NULL DEFAULT 0,
+                paused_at TIMESTAMPTZ,
+                paused_until TIMESTAMPTZ,
+                paused_reason VARCHAR(32),
+                PRIMARY KEY (owner_type, owner_id)
+            );
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create automation_budgets table")?;
+
+        Ok(())
+    }
+
+    pub async fn set_limits(&self, owner_type: BudgetOwner, owner_id: &str, limits: &BudgetLimits) -> Result<Budget> {
+        validate_limits(limits)?;
+
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO automation_budgets (owner_type, owner_id, max_runs_per_day, max_llm_spend_per_day, max_failures)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (owner_type, owner_id) DO UPDATE SET
+                max_runs_per_day = EXCLUDED.max_runs_per_day,
+                max_llm_spend_per_day = EXCLUDED.max_llm_spend_per_day,
+                max_failures = EXCLUDED.max_failures
+            RETURNING {}
+            "#,
+            BUDGET_COLUMNS
+        ))
+        .bind(owner_type.as_str())
+        .bind(owner_id)
+        .bind(limits.max_runs_per_day.map(|max| max as i32))
+        .bind(limits.max_llm_spend_per_day)
+        .bind(limits.max_failures.map(|max| max as i32))
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to save budget limits")?;
+
+        let budget = budget_from_row(&row);
+        info!(owner_type = owner_type.as_str(), owner_id = %owner_id, limits = ?budget.limits, "Budget limits updated");
+        Ok(budget)
+    }
+
+    pub async fn get(&self, owner_type: BudgetOwner, owner_id: &str) -> Result<Option<Budget>> {
+        let row = sqlx::query(&format!("SELECT {} FROM automation_budgets WHERE owner_type = $1 AND owner_id = $2", BUDGET_COLUMNS))
+            .bind(owner_type.as_str())
+            .bind(owner_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to fetch budget")?;
+
+        Ok(row.map(|row| budget_from_row(&row)))
+    }
+
+    pub async fn list(&self) -> Result<Vec<Budget>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM automation_budgets ORDER BY owner_type, owner_id", BUDGET_COLUMNS))
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to list budgets")?;
+
+        Ok(rows.iter().map(budget_from_row).collect())
+    }
+
+    /// Zdejmuje wstrzymanie i zeruje serię błędów; liczniki dobowe zostają
+    pub async fn resume(&self, owner_type: BudgetOwner, owner_id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE automation_budgets
+            SET paused_at = NULL, paused_until = NULL, paused_reason = NULL, consecutive_failures = 0
+            WHERE owner_type = $1 AND owner_id = $2
+            "#,
+        )
+        .bind(owner_type.as_str())
+        .bind(owner_id)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to resume budget")?;
+
+        if result.rows_affected() > 0 {
+            info!(owner_type = owner_type.as_str(), owner_id = %owner_id, "Automation resumed after budget pause");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Pierwszy limit harmonogramu lub użytkownika zadania, który nie pozwala dodać kolejnego przebiegu
+    pub async fn check(&self, owner: &JobOwner) -> Result<Option<BudgetBlock>> {
+        let now = Utc::now();
+        for (owner_type, owner_id) in owner.budget_owners() {
+            if let Some(cap) = self.get(owner_type, owner_id).await?.and_then(|budget| budget.blocking_cap(now)) {
+                return Ok(Some(BudgetBlock { owner_type, owner_id: owner_id.to_string(), cap }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Liczy przebieg dodany do kolejki
+    pub async fn record_enqueued(&self, owner: &JobOwner) {
+        for (owner_type, owner_id) in owner.budget_owners() {
+            self.record(owner_type, owner_id, 1, 0.0, None).await;
+        }
+    }
+
+    /// Wynik zakończonego przebiegu - podtrzymuje albo zeruje serię błędów
+    pub async fn record_outcome(&self, owner: &JobOwner, success: bool) {
+        for (owner_type, owner_id) in owner.budget_owners() {
+            self.record(owner_type, owner_id, 0, 0.0, Some(success)).await;
+        }
+    }
+
+    pub async fn record_llm_spend(&self, owner_type: BudgetOwner, owner_id: &str, spend: f64) {
+        if spend > 0.0 {
+            self.record(owner_type, owner_id, 0, spend, None).await;
+        }
+    }
+
+    /// Dopisuje zużycie i wstrzymuje automatyzację, gdy przekroczyła limit. Błędy bazy tylko loguje -
+    /// rozliczenie nie może zatrzymać samego przebiegu
+    async fn record(&self, owner_type: BudgetOwner, owner_id: &str, runs: i32, spend: f64, success: Option<bool>) {
+        let updated = match self.add_usage(owner_type, owner_id, runs, spend, success).await {
+            Ok(budget) => budget,
+            Err(e) => {
+                warn!(owner_type = owner_type.as_str(), owner_id = %owner_id, "Failed to record budget usage: {}", e);
+                return;
+            }
+        };
+        let now = Utc::now();
+        if updated.is_paused_at(now) {
+            return;
+        }
+        let Some(cap) = reached_cap(&updated.limits, &updated.usage_at(now)) else {
+            return;
+        };
+        match self.pause(owner_type, owner_id, cap, cap.paused_until(now)).await {
+            Ok(Some(paused)) => self.notify_paused(&paused, cap).await,
+            Ok(None) => {}
+            Err(e) => warn!(owner_type = owner_type.as_str(), owner_id = %owner_id, "Failed to pause automation: {}", e),
+        }
+    }
+
+    async fn add_usage(&self, owner_type: BudgetOwner, owner_id: &str, runs: i32, spend: f64, success: Option<bool>) -> Result<Budget> {
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO automation_budgets AS budget (owner_type, owner_id, usage_day, runs_today, llm_spend_today, consecutive_failures)
+            VALUES ($1, $2, (NOW() AT TIME ZONE 'UTC')::date, $3, $4, CASE WHEN $5::boolean = FALSE THEN 1 ELSE 0 END)
+            ON CONFLICT (owner_type, owner_id) DO UPDATE SET
+                runs_today = CASE WHEN budget.usage_day = EXCLUDED.usage_day THEN budget.runs_today ELSE 0 END + $3,
+                llm_spend_today = CASE WHEN budget.usage_day = EXCLUDED.usage_day THEN budget.llm_spend_today ELSE 0 END + $4,
+                consecutive_failures = CASE
+                    WHEN $5::boolean IS NULL THEN budget.consecutive_failures
+                    WHEN $5::boolean THEN 0
+                    ELSE budget.consecutive_failures + 1
+                END,
+                usage_day = EXCLUDED.usage_day
+            RETURNING {}
+            "#,
+            BUDGET_COLUMNS
+        ))
+        .bind(owner_type.as_str())
+        .bind(owner_id)
+        .bind(runs)
+        .bind(spend)
+        .bind(success)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to update budget usage")?;
+
+        Ok(budget_from_row(&row))
+    }
+
+    /// Wstrzymuje, jeśli nic jeszcze nie wstrzymało - przy wielu workerach powiadomienie wychodzi raz
+    async fn pause(&self, owner_type: BudgetOwner, owner_id: &str, cap: BudgetCap, until: Option<DateTime<Utc>>) -> Result<Option<Budget>> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE automation_budgets
+            SET paused_at = NOW(), paused_until = $3, paused_reason = $4
+            WHERE owner_type = $1 AND owner_id = $2
+              AND (paused_at IS NULL OR paused_until <= NOW())
+            RETURNING {}
+            "#,
+            BUDGET_COLUMNS
+        ))
+        .bind(owner_type.as_str())
+        .bind(owner_id)
+        .bind(until)
+        .bind(cap.as_str())
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to pause automation")?;
+
+        Ok(row.map(|row| budget_from_row(&row)))
+    }
+
+    async fn notify_paused(&self, budget: &Budget, cap: BudgetCap) {
+        warn!(
+            owner_type = budget.owner_type.as_str(),
+            owner_id = %budget.owner_id,
+            cap = cap.as_str(),
+            paused_until = ?budget.paused_until,
+            "Automation paused by budget limit"
+        );
+        let event = serde_json::json!({
+            "operation": "automation_paused",
+            "owner_type": budget.owner_type,
+            "owner_id": budget.owner_id,
+            "cap": cap,
+            "limits": budget.limits,
+            "usage": budget.usage,
+            "paused_until": budget.paused_until,
+        });
+        if let Err(e) = logging::log_system_event(&self.db_pool, "scheduler", "warn", &event).await {
+            warn!("Failed to log budget pause event: {}", e);
+        }
+    }
+}
+
+/// Koszt każdego wywołania modelu (zapis w `llm_usage`) liczy się do `max_llm_spend_per_day`
+impl llm_usage::SpendSink for BudgetStore {
+    fn record_spend<'a>(&'a self, owner_type: &'a str, owner_id: &'a str, cost: f64) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            match BudgetOwner::parse(owner_type) {
+                Some(owner_type) => self.record_llm_spend(owner_type, owner_id, cost).await,
+                None => warn!(owner_type, "Unknown budget owner for LLM spend"),
+            }
+        })
+    }
+}
+
+fn budget_from_row(row: &sqlx::postgres::PgRow) -> Budget {
+    let owner_type: String = row.get("owner_type");
+    let count = |column: &str| row.get::<Option<i32>, _>(column).map(|value| value.max(0) as u32);
+    Budget {
+        owner_type: BudgetOwner::parse(&owner_type).unwrap_or(BudgetOwner::User),
+        owner_id: row.get("owner_id"),
+        limits: BudgetLimits {
+            max_runs_per_day: count("max_runs_per_day"),
+            max_llm_spend_per_day: row.get("max_llm_spend_per_day"),
+            max_failures: count("max_failures"),
+        },
+        usage_day: row.get("usage_day"),
+        usage: BudgetUsage {
+            runs_today: count("runs_today").unwrap_or(0),
+            llm_spend_today: row.get("llm_spend_today"),
+            consecutive_failures: count("consecutive_failures").unwrap_or(0),
+        },
+        paused_at: row.get("paused_at"),
+        paused_until: row.get("paused_until"),
+        paused_reason: row.get::<Option<String>, _>("paused_reason").and_then(|reason| BudgetCap::parse(&reason)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caps_and_daily_pauses() {
+        let limits = BudgetLimits { max_runs_per_day: Some(3), max_llm_spend_per_day: Some(0.5), max_failures: Some(2) };
+        let usage = |runs_today, llm_spend_today, consecutive_failures| BudgetUsage { runs_today, llm_spend_today, consecutive_failures };
+        assert_eq!(reached_cap(&limits, &usage(2, 0.2, 1)), None);
+        assert_eq!(reached_cap(&limits, &usage(3, 0.2, 1)), Some(BudgetCap::RunsPerDay));
+        assert_eq!(reached_cap(&limits, &usage(1, 0.5, 0)), Some(BudgetCap::LlmSpendPerDay));
+        assert_eq!(reached_cap(&limits, &usage(3, 0.9, 2)), Some(BudgetCap::Failures));
+        assert_eq!(reached_cap(&BudgetLimits::default(), &usage(100, 10.0, 50)), None);
+
+        let now: DateTime<Utc> = "2026-10-15T21:30:00Z".parse().unwrap();
+        assert_eq!(BudgetCap::RunsPerDay.paused_until(now), Some("2026-10-16T00:00:00Z".parse().unwrap()));
+        assert_eq!(BudgetCap::Failures.paused_until(now), None);
+
+        // Limit przebiegów z wczoraj nie blokuje dziś, seria błędów przechodzi przez północ
+        let budget = Budget {
+            owner_type: BudgetOwner::Schedule,
+            owner_id: "daily-report".to_string(),
+            limits,
+            usage_day: Some(now.date_naive()),
+            usage: usage(3, 0.1, 1),
+            paused_at: Some(now),
+            paused_until: BudgetCap::RunsPerDay.paused_until(now),
+            paused_reason: Some(BudgetCap::RunsPerDay),
+        };
+        assert_eq!(budget.blocking_cap(now), Some(BudgetCap::RunsPerDay));
+        let tomorrow = now + Duration::hours(3);
+        assert_eq!(budget.usage_at(tomorrow), usage(0, 0.0, 1));
+        assert_eq!(budget.blocking_cap(tomorrow), None);
+
+        let failing = Budget { paused_until: None, paused_reason: Some(BudgetCap::Failures), ..budget };
+        assert_eq!(failing.blocking_cap(tomorrow + Duration::days(30)), Some(BudgetCap::Failures));
+        assert!(validate_limits(&BudgetLimits { max_llm_spend_per_day: Some(-1.0), ..limits }).is_err());
+    }
+}
@@ -1,14 +1,491 @@
-use chromiumoxide::Browser;
+use chromiumoxide::cdp::browser_protocol::network::{
+    CookieParam, CookieSameSite, EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, SetCookiesParams, TimeSinceEpoch,
+};
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, PrintToPdfParams};
+use chromiumoxide::handler::viewport::Viewport;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::cdp::browser_protocol::page::{Frame, FrameId, GetFrameTreeParams};
+use chromiumoxide::cdp::browser_protocol::target::{TargetId, TargetInfo};
+use chromiumoxide::cdp::browser_protocol::accessibility::{AxNode, GetFullAxTreeParams};
+use chromiumoxide::cdp::browser_protocol::dom::{
+    BackendNodeId, DescribeNodeParams, GetDocumentParams, GetFrameOwnerParams, PushNodesByBackendIdsToFrontendParams, QuerySelectorAllParams,
+};
+use chromiumoxide::cdp::js_protocol::runtime::EvaluateParams;
+use chromiumoxide::{Browser, Page};
+use futures::stream::{self, BoxStream};
 use futures::StreamExt;
-use tracing::{info, debug};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tracing::{info, debug, warn};
+
+use codialog_types::automation::AuthState;
+
+use crate::dom::{self, SelectOption};
+use crate::tagui;
+
+/// Znacznik, w którym `PageContent::to_html` dokleja do HTML strony dokumenty jej ramek iframe;
+/// atrybut `name` to odwołanie do ramki dla komendy `frame` (nazwa albo `#id`)
+pub const FRAME_TAG: &str = "codialog-frame";
+
+/// Dokument ramki iframe odczytany przez CDP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameDocument {
+    /// Odwołanie do ramki dla komendy `frame`: nazwa albo `#id` elementu iframe
+    pub reference: String,
+    pub html: String,
+}
+
+/// Strona odczytana przez CDP: dokument główny, dokumenty ramek i elementy drzewa dostępności osobno.
+/// Ramki nie są szukane w treści strony - pochodzą z drzewa ramek przeglądarki
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageContent {
+    pub html: String,
+    pub frames: Vec<FrameDocument>,
+    pub accessible: Vec<AccessibleElement>,
+}
+
+impl PageContent {
+    /// Jeden dokument dla promptu i analizy: strona, po niej ramki w `FRAME_TAG` i blok `ACCESSIBILITY_TAG`.
+    /// Znaczniki występujące w samych dokumentach (np. w skrypcie strony) są zamieniane na tekst, więc
+    /// `split_frames` widzi tylko ramki dopisane tutaj
+    pub fn to_html(&self) -> String {
+        let mut html = neutralize_markers(&self.html);
+        for frame in &self.frames {
+            html.push_str(&format!("\n<{} name=\"{}\">\n{}\n</{}>\n", FRAME_TAG, frame.reference, neutralize_markers(&frame.html), FRAME_TAG));
+        }
+        append_accessibility(&mut html, &self.accessible);
+        html
+    }
+}
+
+/// `<` przed nazwą znacznika ramki lub drzewa dostępności jako `&lt;` - dokument strony nie podrobi ramki
+fn neutralize_markers(document: &str) -> String {
+    let mut neutralized = String::with_capacity(document.len());
+    let mut rest = document;
+    while let Some(position) = rest.find('<') {
+        neutralized.push_str(&rest[..position]);
+        let tail = &rest[position + 1..];
+        let name = tail.strip_prefix('/').unwrap_or(tail);
+        let forged = [FRAME_TAG, ACCESSIBILITY_TAG].iter().any(|tag| name.get(..tag.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(tag)));
+        neutralized.push_str(if forged { "&lt;" } else { "<" });
+        rest = tail;
+    }
+    neutralized.push_str(rest);
+    neutralized
+}
+
+/// Ile razy czekamy 100 ms na podpięcie ramki z innego procesu (iframe z innej domeny)
+const FRAME_ATTACH_ATTEMPTS: u32 = 10;
+
+/// Znacznik bloku z elementami drzewa dostępności (JSON), doklejanego do HTML strony po jej ramkach
+pub const ACCESSIBILITY_TAG: &str = "codialog-ax";
+
+/// Role elementów interaktywnych zbieranych z drzewa dostępności
+const INTERACTIVE_ROLES: &[&str] = &[
+    "button", "link", "textbox", "searchbox", "combobox", "listbox", "checkbox", "radio", "switch", "spinbutton", "slider",
+    "menuitem", "tab",
+];
+
+/// Ile elementów drzewa dostępności odczytujemy
+const MAX_ACCESSIBLE_ELEMENTS: usize = 300;
+
+/// Kontrolki formularza dokumentu w kolejności `dom::form_fields`
+const FORM_CONTROLS_SELECTOR: &str = "input:not([type=\"hidden\" i]), button, select, textarea";
+
+/// Element interaktywny z drzewa dostępności (CDP Accessibility): rola i nazwa dostępna policzone przez
+/// przeglądarkę, także z `aria-labelledby`, `title` czy tekstu ikon. Zastępuje selektor, gdy pole nie ma
+/// `id` ani `name` albo ma wygenerowane id i klasy z hashem (React, CSS-in-JS)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessibleElement {
+    pub role: String,
+    pub name: String,
+    /// Numer kontrolki formularza dokumentu głównego (kolejność `dom::form_fields`); `None` dla linków, zakładek itp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control: Option<usize>,
+    /// Który z kolei (od 1) element o tej samej roli i nazwie, gdy para się powtarza (dwa przyciski "Apply")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub occurrence: Option<usize>,
+}
+
+impl AccessibleElement {
+    /// XPath po roli i nazwie: element o tej roli (jawnej lub wynikającej ze znacznika), którego tekst,
+    /// `aria-label`, `title`, `placeholder`, `value`, etykieta lub `aria-labelledby` daje tę nazwę
+    pub fn xpath(&self) -> String {
+        let element = match self.role.as_str() {
+            "button" => "self::button or self::input[@type='submit' or @type='button' or @type='reset'] or @role='button'".to_string(),
+            "link" => "self::a or @role='link'".to_string(),
+            "textbox" | "searchbox" => "self::textarea or self::input[not(@type) or @type='text' or @type='email' or @type='tel' \
+                or @type='url' or @type='password' or @type='search'] or @role='textbox' or @role='searchbox'"
+                .to_string(),
+            "combobox" | "listbox" => "self::select or self::input or @role='combobox' or @role='listbox'".to_string(),
+            "switch" => "self::input[@type='checkbox'] or @role='switch'".to_string(),
+            "spinbutton" => "self::input[@type='number'] or @role='spinbutton'".to_string(),
+            "slider" => "self::input[@type='range'] or @role='slider'".to_string(),
+            role @ ("checkbox" | "radio") => format!("self::input[@type='{0}'] or @role='{0}'", role),
+            role => format!("@role={}", xpath_literal(role)),
+        };
+        let name = xpath_literal(&self.name);
+        let xpath = format!(
+            "//*[{element}][normalize-space(.)={name} or @aria-label={name} or @title={name} or @placeholder={name} or @value={name} \
+            or @id=//label[normalize-space(.)={name}]/@for or ancestor::label[normalize-space(.)={name}] \
+            or @aria-labelledby=//*[normalize-space(.)={name}]/@id]"
+        );
+        match self.occurrence {
+            Some(occurrence) => format!("({})[{}]", xpath, occurrence),
+            None => xpath,
+        }
+    }
+}
+
+/// Literał XPath 1.0: w apostrofach, w cudzysłowie albo przez `concat()`, gdy tekst zawiera oba
+fn xpath_literal(text: &str) -> String {
+    if !text.contains('\'') {
+        format!("'{}'", text)
+    } else if !text.contains('"') {
+        format!("\"{}\"", text)
+    } else {
+        let parts: Vec<String> = text.split('\'').map(|part| format!("'{}'", part)).collect();
+        format!("concat({})", parts.join(", \"'\", "))
+    }
+}
+
+/// Kiedy `get_page_html` uznaje stronę za gotową do odczytu (PAGE_READY_STRATEGY). Tablice ogłoszeń w React/Vue
+/// po zdarzeniu load mają jeszcze szkielet strony - formularz dociągają i renderują dopiero potem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadyStrategy {
+    /// Tylko zdarzenie load
+    Load,
+    /// Cisza w sieci: najwyżej `NETWORK_IDLE_MAX_IN_FLIGHT` żądań w toku i żadnej zmiany przez `QUIET_PERIOD`
+    NetworkIdle,
+    /// Brak zmian DOM przez `QUIET_PERIOD` i żadnego elementu `aria-busy="true"`
+    DomStable,
+    /// Najpierw cisza w sieci, potem stabilny DOM
+    #[default]
+    Auto,
+}
+
+impl ReadyStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "load" => Some(ReadyStrategy::Load),
+            "network_idle" | "networkidle" => Some(ReadyStrategy::NetworkIdle),
+            "dom_stable" | "dom" => Some(ReadyStrategy::DomStable),
+            "auto" => Some(ReadyStrategy::Auto),
+            _ => None,
+        }
+    }
+
+    fn waits_for_network(&self) -> bool {
+        matches!(self, ReadyStrategy::NetworkIdle | ReadyStrategy::Auto)
+    }
+
+    fn waits_for_dom(&self) -> bool {
+        matches!(self, ReadyStrategy::DomStable | ReadyStrategy::Auto)
+    }
+}
+
+/// Strategia i łączny limit czekania; po limicie strona jest odczytywana w takim stanie, w jakim jest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageReadiness {
+    pub strategy: ReadyStrategy,
+    pub timeout: Duration,
+}
+
+/// Domyślny limit czekania na gotowość strony (PAGE_READY_TIMEOUT_SECS)
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tyle czasu bez żądań sieciowych / zmian DOM oznacza, że strona skończyła się budować
+const QUIET_PERIOD: Duration = Duration::from_millis(500);
+
+/// Żądania, które mogą trwać bez końca (long polling, analityka) - jak `networkidle2` w Puppeteerze
+const NETWORK_IDLE_MAX_IN_FLIGHT: usize = 2;
+
+/// Czeka, aż przez `__QUIET_MS__` ms nie będzie zmian DOM ani elementów oznaczonych jako ładowane
+const DOM_STABLE_SCRIPT: &str = "new Promise(resolve => { \
+    let timer; \
+    const settle = () => { clearTimeout(timer); timer = setTimeout(done, __QUIET_MS__); }; \
+    const done = () => { if (document.querySelector('[aria-busy=\"true\"]')) { settle(); return; } observer.disconnect(); resolve(true); }; \
+    const observer = new MutationObserver(settle); \
+    observer.observe(document, { subtree: true, childList: true, attributes: true, characterData: true }); \
+    settle(); })";
+
+static PAGE_READINESS: RwLock<Option<PageReadiness>> = RwLock::new(None);
+
+/// Strategia czekania na gotowość strony w `get_page_html`; wywoływane raz przy starcie
+pub fn set_page_readiness(readiness: PageReadiness) {
+    info!(strategy = ?readiness.strategy, timeout_ms = readiness.timeout.as_millis() as u64, "Page readiness strategy configured");
+    *PAGE_READINESS.write().unwrap() = Some(readiness);
+}
+
+fn page_readiness() -> PageReadiness {
+    PAGE_READINESS
+        .read()
+        .unwrap()
+        .unwrap_or(PageReadiness { strategy: ReadyStrategy::default(), timeout: DEFAULT_READY_TIMEOUT })
+}
+
+enum NetworkEvent {
+    Started(String),
+    Finished(String),
+}
+
+/// Żądania strony w toku i chwila ostatniej zmiany
+struct NetworkActivity {
+    in_flight: HashSet<String>,
+    last_change: Instant,
+}
+
+impl NetworkActivity {
+    fn new(now: Instant) -> Self {
+        Self { in_flight: HashSet::new(), last_change: now }
+    }
+
+    fn record(&mut self, event: NetworkEvent, now: Instant) {
+        match event {
+            NetworkEvent::Started(id) => self.in_flight.insert(id),
+            NetworkEvent::Finished(id) => self.in_flight.remove(&id),
+        };
+        self.last_change = now;
+    }
+
+    /// Kiedy sieć będzie cicha, jeśli nic się nie zmieni; `None`, gdy w toku jest za dużo żądań
+    fn idle_at(&self) -> Option<Instant> {
+        (self.in_flight.len() <= NETWORK_IDLE_MAX_IN_FLIGHT).then_some(self.last_change + QUIET_PERIOD)
+    }
+}
+
+/// Zdarzenia sieciowe strony; subskrypcja przed nawigacją, żeby widzieć też żądania z jej początku
+async fn network_events(page: &Page) -> chromiumoxide::Result<BoxStream<'static, NetworkEvent>> {
+    let started = page.event_listener::<EventRequestWillBeSent>().await?
+        .map(|event| NetworkEvent::Started(event.request_id.inner().clone()));
+    let finished = page.event_listener::<EventLoadingFinished>().await?
+        .map(|event| NetworkEvent::Finished(event.request_id.inner().clone()));
+    let failed = page.event_listener::<EventLoadingFailed>().await?
+        .map(|event| NetworkEvent::Finished(event.request_id.inner().clone()));
+    Ok(stream::select(started, stream::select(finished, failed)).boxed())
+}
+
+/// Czeka na ciszę w sieci do `deadline`; false, gdy jej nie było
+async fn wait_for_network_idle(mut events: BoxStream<'static, NetworkEvent>, deadline: Instant) -> bool {
+    let mut activity = NetworkActivity::new(Instant::now());
+    loop {
+        let now = Instant::now();
+        let idle_at = activity.idle_at();
+        if idle_at.is_some_and(|idle_at| idle_at <= now) {
+            return true;
+        }
+        if now >= deadline {
+            return false;
+        }
+        let wake = idle_at.unwrap_or(deadline).min(deadline);
+        match tokio::time::timeout_at(wake.into(), events.next()).await {
+            Ok(Some(event)) => activity.record(event, Instant::now()),
+            Ok(None) => return false,
+            Err(_) => {}
+        }
+    }
+}
+
+/// Czeka na gotowość strony według `readiness`; po przekroczeniu limitu strona jest odczytywana i tak
+async fn wait_until_ready(page: &Page, network: Option<BoxStream<'static, NetworkEvent>>, readiness: PageReadiness) {
+    let started = Instant::now();
+    let deadline = started + readiness.timeout;
+    if let Some(events) = network {
+        if !wait_for_network_idle(events, deadline).await {
+            debug!(timeout_ms = readiness.timeout.as_millis() as u64, "Network did not go idle before the page snapshot");
+        }
+    }
+    if readiness.strategy.waits_for_dom() {
+        let script = DOM_STABLE_SCRIPT.replace("__QUIET_MS__", &QUIET_PERIOD.as_millis().to_string());
+        match tokio::time::timeout_at(deadline.into(), page.evaluate(script)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => debug!("Failed to wait for a stable DOM: {}", e),
+            Err(_) => debug!(timeout_ms = readiness.timeout.as_millis() as u64, "DOM was still changing at the page snapshot"),
+        }
+    }
+    debug!(strategy = ?readiness.strategy, waited_ms = started.elapsed().as_millis() as u64, "Page ready for snapshot");
+}
+
+/// Ustawia localStorage z `__ENTRIES__` (origin -> [[klucz, wartość]]) przed skryptami strony,
+/// gdy dokument pochodzi z jednej z tych witryn
+const LOCAL_STORAGE_SCRIPT: &str = "(() => { const items = (__ENTRIES__)[location.origin]; if (!items) return; \
+    for (const [name, value] of items) { try { localStorage.setItem(name, value); } catch (e) {} } })()";
+
+/// Ciasteczka z `auth` dla CDP. Ciasteczko bez domeny dotyczy pierwszego originu zapisanego w stanie,
+/// nigdy adresu z żądania; bez originu jest pomijane
+fn cookie_params(auth: &AuthState) -> Vec<CookieParam> {
+    let origin = auth.origins.first().map(|origin| origin.origin.trim_end_matches('/').to_string());
+    auth.cookies
+        .iter()
+        .filter_map(|cookie| {
+            let mut param = CookieParam::new(cookie.name.clone(), cookie.value.clone());
+            match (&cookie.domain, &origin) {
+                (Some(domain), _) => param.domain = Some(domain.clone()),
+                (None, Some(origin)) => param.url = Some(origin.clone()),
+                (None, None) => {
+                    debug!("Skipping a cookie without domain, the auth state records no origin");
+                    return None;
+                }
+            }
+            param.path = cookie.path.clone();
+            param.secure = cookie.secure;
+            param.http_only = cookie.http_only;
+            param.same_site = cookie.same_site.as_deref().and_then(|same_site| match same_site.to_lowercase().as_str() {
+                "strict" => Some(CookieSameSite::Strict),
+                "lax" => Some(CookieSameSite::Lax),
+                "none" | "no_restriction" => Some(CookieSameSite::None),
+                _ => None,
+            });
+            param.expires = cookie.expires.filter(|expires| *expires > 0.0).map(TimeSinceEpoch::new);
+            Some(param)
+        })
+        .collect()
+}
+
+/// Czy stan zalogowania dotyczy strony `url`: jej host to domena któregoś ciasteczka (lub jej subdomena)
+/// albo host zapisanego originu. Inaczej ciasteczka trafiłyby na obcy serwer
+pub fn auth_state_covers(auth: &AuthState, url: &str) -> bool {
+    let host_of = |uri: &str| reqwest::Url::parse(uri.trim()).ok().and_then(|parsed| parsed.host_str().map(str::to_lowercase));
+    let Some(host) = host_of(url) else {
+        return false;
+    };
+    let cookie_domain = auth.cookies.iter().filter_map(|cookie| cookie.domain.as_deref()).any(|domain| {
+        let domain = domain.trim_start_matches('.').to_lowercase();
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    });
+    cookie_domain || auth.origins.iter().any(|origin| host_of(&origin.origin).as_deref() == Some(host.as_str()))
+}
+
+/// Skrypt ustawiający localStorage z `auth`; `None`, gdy nie ma wpisów
+fn local_storage_script(auth: &AuthState) -> Option<String> {
+    let entries: serde_json::Map<String, serde_json::Value> = auth
+        .origins
+        .iter()
+        .filter(|origin| !origin.local_storage.is_empty())
+        .map(|origin| {
+            let items = origin.local_storage.iter().map(|entry| serde_json::json!([entry.name, entry.value])).collect();
+            (origin.origin.trim_end_matches('/').to_string(), serde_json::Value::Array(items))
+        })
+        .collect();
+    (!entries.is_empty()).then(|| LOCAL_STORAGE_SCRIPT.replace("__ENTRIES__", &serde_json::Value::Object(entries).to_string()))
+}
+
+/// Wstrzykuje stan zalogowania do karty przed nawigacją; wartości nie trafiają do logów
+async fn inject_auth_state(page: &Page, auth: &AuthState) -> chromiumoxide::Result<()> {
+    if !auth.cookies.is_empty() {
+        page.execute(SetCookiesParams::new(cookie_params(auth))).await?;
+    }
+    if let Some(script) = local_storage_script(auth) {
+        page.evaluate_on_new_document(script).await?;
+    }
+    info!(cookies = auth.cookies.len(), origins = auth.origins.len(), "Injected auth state into the analysis browser");
+    Ok(())
+}
+
+/// Otwiera `url` w nowej karcie i czeka na załadowanie, a potem aż aplikacja SPA skończy budować stronę
+async fn open_ready_page(browser: &Browser, url: &str, auth: Option<&AuthState>) -> chromiumoxide::Result<Page> {
+    let readiness = page_readiness();
+    let page = browser.new_page("about:blank").await?;
+    if let Some(auth) = auth.filter(|auth| !auth.is_empty()) {
+        inject_auth_state(&page, auth).await?;
+    }
+    let network = if readiness.strategy.waits_for_network() { Some(network_events(&page).await?) } else { None };
+    page.goto(url).await?;
+    page.wait_for_navigation().await?;
+    wait_until_ready(&page, network, readiness).await;
+    Ok(page)
+}
+
+/// Co zapisać w `capture_screenshot`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureOptions {
+    /// Cała strona zamiast widocznego obszaru
+    pub full_page: bool,
+    /// Dodatkowo wydruk strony do PDF
+    pub pdf: bool,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self { full_page: true, pdf: false, width: 1366, height: 900 }
+    }
+}
+
+/// Zrzut strony do archiwum
+#[derive(Debug, Clone)]
+pub struct PageCapture {
+    /// Adres po przekierowaniach
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub png: Vec<u8>,
+    pub pdf: Option<Vec<u8>>,
+}
+
+/// Zrzut ekranu PNG (cała strona albo widoczny obszar) i opcjonalnie PDF strony załadowanej w nowej przeglądarce,
+/// po tym samym czekaniu na gotowość co `get_page_html`
+pub async fn capture_screenshot(url: &str, options: &CaptureOptions) -> Result<PageCapture, Box<dyn std::error::Error + Send + Sync>> {
+    info!(full_page = options.full_page, pdf = options.pdf, "Capturing page {}", url);
+    if url.is_empty() {
+        return Err("URL cannot be empty".into());
+    }
+    
+    let (mut browser, mut handler) = Browser::launch(
+        chromiumoxide::BrowserConfig::builder()
+            .window_size(options.width, options.height)
+            .viewport(Viewport { width: options.width, height: options.height, ..Default::default() })
+            .build()?
+    ).await?;
+    
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+    
+    let captured = async {
+        let page = open_ready_page(&browser, url, None).await?;
+        let png = page
+            .screenshot(ScreenshotParams::builder().format(CaptureScreenshotFormat::Png).full_page(options.full_page).build())
+            .await?;
+        let pdf = if options.pdf {
+            Some(page.pdf(PrintToPdfParams { print_background: Some(true), ..Default::default() }).await?)
+        } else {
+            None
+        };
+        Ok::<PageCapture, Box<dyn std::error::Error + Send + Sync>>(PageCapture {
+            url: page.url().await.ok().flatten(),
+            title: page.get_title().await.ok().flatten(),
+            png,
+            pdf,
+        })
+    }
+    .await;
+    
+    browser.close().await?;
+    handle.abort();
+    
+    if let Ok(capture) = &captured {
+        debug!(png_bytes = capture.png.len(), pdf_bytes = capture.pdf.as_ref().map(Vec::len), "Page captured");
+    }
+    captured
+}
 
 pub async fn get_page_html(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    get_page_html_with_auth(url, None).await
+}
+
+/// Jak `get_page_html`, ale z ciasteczkami i localStorage z `auth` ustawionymi przed nawigacją (strony za logowaniem)
+pub async fn get_page_html_with_auth(url: &str, auth: Option<&AuthState>) -> Result<String, Box<dyn std::error::Error>> {
     info!("Fetching HTML content from URL: {}", url);
     
     if url.is_empty() {
         return Err("URL cannot be empty".into());
     }
     
+    // Formularze osadzone z innych domen (Greenhouse, Workday) są czytane przez ich własne cele CDP,
+    // bez wyłączania izolacji stron
     let (mut browser, mut handler) = Browser::launch(
         chromiumoxide::BrowserConfig::builder()
             .build()?
@@ -18,13 +495,10 @@ pub async fn get_page_html(url: &str) -> Result<String, Box<dyn std::error::Erro
         while let Some(_) = handler.next().await {}
     });
     
-    let page = browser.new_page(url).await?;
-    
-    // Poczekaj na załadowanie strony
-    page.wait_for_navigation().await?;
+    let page = open_ready_page(&browser, url, auth).await?;
     
     // Pobierz HTML content
-    let html = page.content().await?;
+    let html = content_with_frames(&browser, &page).await?.to_html();
     
     debug!("Retrieved HTML content, length: {} characters", html.len());
     
@@ -34,147 +508,669 @@ pub async fn get_page_html(url: &str) -> Result<String, Box<dyn std::error::Erro
     Ok(html)
 }
 
-pub async fn extract_form_elements(html: &str) -> Vec<FormElement> {
-    debug!("Extracting form elements from HTML");
+/// HTML strony otwartej w webview aplikacji, odczytany przez jego port zdalnego debugowania (WEBVIEW_CDP_PORT).
+/// Strona nie jest ładowana od nowa: widać dokładnie to, co użytkownik, razem z jego zalogowaną sesją.
+pub async fn get_webview_html(port: u16, url: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    info!(port, "Attaching to the webview over CDP");
+    let (mut browser, mut handler) = Browser::connect(format!("http://127.0.0.1:{}", port)).await?;
     
-    let mut elements = Vec::new();
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
     
-    // Proste parsowanie HTML - w produkcji użyj scraper lub html5ever
-    if html.contains("type=\"text\"") || html.contains("type='text'") {
-        elements.push(FormElement {
-            tag: "input".to_string(),
-            element_type: Some("text".to_string()),
-            id: extract_id_from_input(html, "text"),
-            name: extract_name_from_input(html, "text"),
-            selector: generate_selector(html, "text"),
-        });
+    let html = async {
+        let page = attach_webview_page(&mut browser, url, false).await?;
+        Ok::<String, Box<dyn std::error::Error + Send + Sync>>(content_with_frames(&browser, &page).await?.to_html())
     }
+    .await;
     
-    if html.contains("type=\"email\"") || html.contains("type='email'") {
-        elements.push(FormElement {
-            tag: "input".to_string(),
-            element_type: Some("email".to_string()),
-            id: extract_id_from_input(html, "email"),
-            name: extract_name_from_input(html, "email"),
-            selector: generate_selector(html, "email"),
-        });
-    }
+    // Bez `browser.close()` - to przeglądarka użytkownika; zrywamy tylko połączenie
+    handle.abort();
     
-    if html.contains("type=\"password\"") || html.contains("type='password'") {
-        elements.push(FormElement {
-            tag: "input".to_string(),
-            element_type: Some("password".to_string()),
-            id: extract_id_from_input(html, "password"),
-            name: extract_name_from_input(html, "password"),
-            selector: generate_selector(html, "password"),
-        });
+    if let Ok(html) = &html {
+        debug!("Retrieved webview HTML, length: {} characters", html.len());
     }
-    
-    if html.contains("type=\"file\"") || html.contains("type='file'") {
-        elements.push(FormElement {
-            tag: "input".to_string(),
-            element_type: Some("file".to_string()),
-            id: extract_id_from_input(html, "file"),
-            name: extract_name_from_input(html, "file"),
-            selector: generate_selector(html, "file"),
-        });
+    html
+}
+
+/// Strona webview wybrana przez `webview_page`; z `same_site` tylko strona z witryny `url`
+async fn attach_webview_page(browser: &mut Browser, url: &str, same_site: bool) -> Result<Page, Box<dyn std::error::Error + Send + Sync>> {
+    let targets = browser.fetch_targets().await?;
+    let pages: Vec<&TargetInfo> = targets.iter().filter(|target| target.r#type == "page").collect();
+    let urls: Vec<&str> = pages.iter().map(|target| target.url.as_str()).collect();
+    let index = webview_page(&urls, url).ok_or("The webview has no open page besides the application itself")?;
+    if same_site && site_of(urls[index]) != site_of(url) {
+        return Err(format!("The webview does not show {}", url).into());
     }
+    let target = pages[index].target_id.clone();
     
-    if html.contains("<button") || html.contains("type=\"submit\"") {
-        elements.push(FormElement {
-            tag: "button".to_string(),
-            element_type: Some("submit".to_string()),
-            id: extract_button_id(html),
-            name: None,
-            selector: generate_button_selector(html),
-        });
+    // Strony z fetch_targets są podpinane w tle, chwilę po odpowiedzi
+    let mut attempts = 0;
+    loop {
+        match browser.get_page(target.clone()).await {
+            Ok(page) => return Ok(page),
+            Err(_) if attempts < WEBVIEW_ATTACH_ATTEMPTS => {
+                attempts += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
     }
-    
-    debug!("Found {} form elements", elements.len());
-    elements
 }
 
-#[derive(Debug, Clone)]
-pub struct FormElement {
-    pub tag: String,
-    pub element_type: Option<String>,
-    pub id: Option<String>,
-    pub name: Option<String>,
-    pub selector: String,
+/// Ile razy czekamy 100 ms na podpięcie strony webview
+const WEBVIEW_ATTACH_ATTEMPTS: u32 = 20;
+
+/// Strona webview do odczytu: ta pod adresem `url`, potem ta z tej samej witryny, a w ostateczności pierwsza
+/// strona, która nie jest interfejsem aplikacji (`tauri://`, `tauri.localhost`) ani stroną wewnętrzną przeglądarki
+fn webview_page(urls: &[&str], url: &str) -> Option<usize> {
+    let normalize = |uri: &str| uri.split('#').next().unwrap_or_default().trim_end_matches('/').to_string();
+    let is_app_page = |uri: &str| {
+        uri.starts_with("tauri:") || uri.starts_with("about:") || uri.starts_with("devtools:") || uri.starts_with("edge:")
+            || site_of(uri).is_some_and(|site| site == "tauri.localhost")
+    };
+    let candidates: Vec<usize> = (0..urls.len()).filter(|&index| !is_app_page(urls[index])).collect();
+    let wanted = normalize(url);
+    candidates
+        .iter()
+        .find(|&&index| !wanted.is_empty() && normalize(urls[index]) == wanted)
+        .or_else(|| candidates.iter().find(|&&index| !url.is_empty() && site_of(urls[index]) == site_of(url)))
+        .or(candidates.first())
+        .copied()
 }
 
-fn extract_id_from_input(html: &str, input_type: &str) -> Option<String> {
-    // Bardzo prosty parser - w produkcji użyj regex lub właściwego parsera HTML
-    let pattern = format!("type=\"{}\"", input_type);
-    if let Some(pos) = html.find(&pattern) {
-        let before = &html[..pos];
-        if let Some(id_pos) = before.rfind("id=\"") {
-            let id_start = id_pos + 4;
-            if let Some(id_end) = html[id_start..].find('"') {
-                return Some(html[id_start..id_start + id_end].to_string());
-            }
+/// Dokument strony, dokumenty jej ramek iframe i elementy drzewa dostępności
+async fn content_with_frames(browser: &Browser, page: &Page) -> chromiumoxide::Result<PageContent> {
+    let html = page.content().await?;
+    let frames = match frame_documents(browser, page).await {
+        Ok(frames) => frames,
+        Err(e) => {
+            debug!("Failed to read iframe documents: {}", e);
+            Vec::new()
+        }
+    };
+    let accessible = accessible_elements(page).await.unwrap_or_else(|e| {
+        debug!("Failed to read the accessibility tree: {}", e);
+        Vec::new()
+    });
+    Ok(PageContent { html, frames, accessible })
+}
+
+/// Dokumenty ramek iframe dokumentu głównego z drzewa ramek (`Page.getFrameTree`). Ramka z tej samej domeny
+/// jest czytana w swoim kontekście wykonania, ramka z innej domeny - przez własny cel CDP (out-of-process iframe)
+async fn frame_documents(browser: &Browser, page: &Page) -> chromiumoxide::Result<Vec<FrameDocument>> {
+    let tree = page.execute(GetFrameTreeParams::default()).await?.result.frame_tree;
+    let mut frames = Vec::new();
+    for child in tree.child_frames.unwrap_or_default() {
+        let frame = child.frame;
+        let Some(reference) = frame_reference(page, &frame).await else {
+            debug!("Skipping iframe without a usable name or id");
+            continue;
+        };
+        match frame_html(browser, page, &frame.id).await {
+            Some(html) => frames.push(FrameDocument { reference, html }),
+            None => debug!("Skipping iframe {:?}, its document is not accessible", reference),
         }
     }
-    None
+    Ok(frames)
 }
 
-fn extract_name_from_input(html: &str, input_type: &str) -> Option<String> {
-    let pattern = format!("type=\"{}\"", input_type);
-    if let Some(pos) = html.find(&pattern) {
-        let before = &html[..pos];
-        if let Some(name_pos) = before.rfind("name=\"") {
-            let name_start = name_pos + 6;
-            if let Some(name_end) = html[name_start..].find('"') {
-                return Some(html[name_start..name_start + name_end].to_string());
-            }
+/// Odwołanie do ramki dla komendy `frame`: `name` elementu iframe, a bez niej jego `#id`
+async fn frame_reference(page: &Page, frame: &Frame) -> Option<String> {
+    let owner = page.execute(GetFrameOwnerParams::new(frame.id.clone())).await.ok()?.result.backend_node_id;
+    let node = page.execute(DescribeNodeParams::builder().backend_node_id(owner).build()).await.ok()?.result.node;
+    let attributes = node.attributes.unwrap_or_default();
+    let attribute = |wanted: &str| {
+        attributes.chunks(2).find(|pair| pair.len() == 2 && pair[0] == wanted).map(|pair| pair[1].clone()).unwrap_or_default()
+    };
+    let (name, id) = (attribute("name"), attribute("id"));
+    tagui::frame_identifier(&name)
+        .filter(|_| !name.starts_with('#'))
+        .map(|name| name.to_string())
+        .or_else(|| tagui::frame_identifier(&id).map(|id| format!("#{}", id)))
+}
+
+async fn frame_html(browser: &Browser, page: &Page, frame: &FrameId) -> Option<String> {
+    const DOCUMENT_HTML: &str = "document.documentElement ? document.documentElement.outerHTML : null";
+    if let Ok(Some(context)) = page.frame_execution_context(frame.clone()).await {
+        let evaluate = EvaluateParams::builder().expression(DOCUMENT_HTML).context_id(context).return_by_value(true).build().ok()?;
+        if let Some(html) = page.execute(evaluate).await.ok().and_then(|response| response.result.result.value).and_then(|value| value.as_str().map(str::to_string)) {
+            return Some(html);
         }
     }
+    // Ramka w osobnym procesie ma własny cel o identyfikatorze ramki, podpinany w tle
+    let target = TargetId::from(frame.inner().clone());
+    for _ in 0..FRAME_ATTACH_ATTEMPTS {
+        if let Ok(frame_page) = browser.get_page(target.clone()).await {
+            return frame_page.evaluate(DOCUMENT_HTML).await.ok()?.into_value::<Option<String>>().ok().flatten();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
     None
 }
 
-fn generate_selector(html: &str, input_type: &str) -> String {
-    if let Some(id) = extract_id_from_input(html, input_type) {
-        return format!("#{}", id);
+/// Elementy interaktywne dokumentu głównego z drzewa dostępności, z numerami kontrolek formularza
+async fn accessible_elements(page: &Page) -> chromiumoxide::Result<Vec<AccessibleElement>> {
+    let nodes = page.execute(GetFullAxTreeParams::default()).await?.result.nodes;
+    let (backend_ids, mut elements): (Vec<Option<BackendNodeId>>, Vec<AccessibleElement>) =
+        interactive_nodes(&nodes).into_iter().take(MAX_ACCESSIBLE_ELEMENTS).unzip();
+    for (element, control) in elements.iter_mut().zip(control_indices(page, &backend_ids).await) {
+        element.control = control;
+    }
+    debug!("Read {} interactive elements from the accessibility tree", elements.len());
+    Ok(elements)
+}
+
+/// Widoczne węzły o interaktywnej roli i niepustej nazwie, z węzłem DOM, któremu odpowiadają
+fn interactive_nodes(nodes: &[AxNode]) -> Vec<(Option<BackendNodeId>, AccessibleElement)> {
+    let text = |value: &Option<chromiumoxide::cdp::browser_protocol::accessibility::AxValue>| {
+        value.as_ref().and_then(|value| value.value.as_ref()).and_then(|value| value.as_str()).map(dom::normalize).unwrap_or_default()
+    };
+    let mut elements = nodes
+        .iter()
+        .filter(|node| !node.ignored)
+        .filter_map(|node| {
+            let role = text(&node.role);
+            let name = text(&node.name);
+            (INTERACTIVE_ROLES.contains(&role.as_str()) && !name.is_empty())
+                .then_some((node.backend_dom_node_id, AccessibleElement { role, name, control: None, occurrence: None }))
+        })
+        .collect::<Vec<_>>();
+
+    // Powtarzająca się para rola/nazwa dostaje numer, żeby XPath wskazywał jeden element
+    let mut totals: HashMap<(String, String), usize> = HashMap::new();
+    for (_, element) in &elements {
+        *totals.entry((element.role.clone(), element.name.clone())).or_default() += 1;
+    }
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    for (_, element) in elements.iter_mut() {
+        let key = (element.role.clone(), element.name.clone());
+        if totals[&key] > 1 {
+            let occurrence = seen.entry(key).or_default();
+            *occurrence += 1;
+            element.occurrence = Some(*occurrence);
+        }
+    }
+    elements
+}
+
+/// Numery kontrolek formularza (`FORM_CONTROLS_SELECTOR`) odpowiadających węzłom DOM. Trzy wywołania CDP
+/// na całą stronę zamiast dwóch na każdy węzeł; przy błędzie żaden węzeł nie dostaje numeru
+async fn control_indices(page: &Page, nodes: &[Option<BackendNodeId>]) -> Vec<Option<usize>> {
+    let resolve = async {
+        let document = page.execute(GetDocumentParams::default()).await?.result.root.node_id;
+        let controls = page.execute(QuerySelectorAllParams::new(document, FORM_CONTROLS_SELECTOR)).await?.result.node_ids;
+        let known: Vec<BackendNodeId> = nodes.iter().flatten().cloned().collect();
+        let pushed = if known.is_empty() {
+            Vec::new()
+        } else {
+            page.execute(PushNodesByBackendIdsToFrontendParams::new(known)).await?.result.node_ids
+        };
+        Ok::<_, chromiumoxide::error::CdpError>((controls, pushed))
+    };
+    let (controls, pushed) = match resolve.await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            debug!("Failed to map accessibility nodes to form controls: {}", e);
+            return vec![None; nodes.len()];
+        }
+    };
+
+    let mut pushed = pushed.into_iter();
+    nodes
+        .iter()
+        .map(|node| {
+            // Węzły bez backend id nie były wysłane; pozostałe przychodzą w tej samej kolejności
+            node.as_ref()?;
+            let node_id = pushed.next()?;
+            controls.iter().position(|control| *control == node_id)
+        })
+        .collect()
+}
+
+fn append_accessibility(html: &mut String, elements: &[AccessibleElement]) {
+    if elements.is_empty() {
+        return;
+    }
+    match serde_json::to_string(elements) {
+        // `<` w nazwach jako escape JSON, żeby nazwa nie zamknęła bloku
+        Ok(json) => html.push_str(&format!("\n<{}>{}</{}>\n", ACCESSIBILITY_TAG, json.replace('<', "\\u003c"), ACCESSIBILITY_TAG)),
+        Err(e) => debug!("Failed to serialize accessibility elements: {}", e),
+    }
+}
+
+/// Elementy drzewa dostępności z bloku `ACCESSIBILITY_TAG` w HTML z `get_page_html`; pusta lista bez bloku
+pub fn accessible_elements_of(html: &str) -> Vec<AccessibleElement> {
+    let open = format!("<{}>", ACCESSIBILITY_TAG);
+    let close = format!("</{}>", ACCESSIBILITY_TAG);
+    html.rfind(&open)
+        .and_then(|start| html[start + open.len()..].split_once(close.as_str()))
+        .and_then(|(json, _)| serde_json::from_str(json).map_err(|e| debug!("Invalid accessibility block: {}", e)).ok())
+        .unwrap_or_default()
+}
+
+/// HTML bez bloku `ACCESSIBILITY_TAG`
+fn without_accessibility(html: &str) -> String {
+    let open = format!("<{}>", ACCESSIBILITY_TAG);
+    let close = format!("</{}>", ACCESSIBILITY_TAG);
+    match html.rfind(&open) {
+        Some(start) => {
+            let rest = &html[start..];
+            let end = rest.find(&close).map(|end| end + close.len()).unwrap_or(rest.len());
+            format!("{}{}", &html[..start], &rest[end..])
+        }
+        None => html.to_string(),
+    }
+}
+
+/// Filtruje listę `__SELECTORS__` do selektorów bez dopasowania w bieżącym dokumencie. Jak TagUI:
+/// XPath, gdy zaczyna się od `/` lub `(`, w pozostałych przypadkach CSS, a potem id, name i widoczny tekst.
+const MISSING_SELECTORS_SCRIPT: &str = r#"(() => {
+    const exists = (selector) => {
+        if (selector.startsWith('/') || selector.startsWith('(')) {
+            return document.evaluate(selector, document, null, XPathResult.FIRST_ORDERED_NODE_TYPE, null).singleNodeValue !== null;
+        }
+        try {
+            if (document.querySelector(selector)) return true;
+        } catch (e) {}
+        return document.getElementById(selector) !== null
+            || document.getElementsByName(selector).length > 0
+            || (document.body !== null && document.body.innerText.includes(selector));
+    };
+    return __SELECTORS__.filter((selector) => {
+        try { return !exists(selector); } catch (e) { return true; }
+    });
+})()"#;
+
+/// Otwiera `url` i zwraca selektory, których nie ma na stronie (sprawdzenie przed uruchomieniem skryptu)
+pub async fn find_missing_selectors(url: &str, selectors: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    if url.is_empty() {
+        return Err("URL cannot be empty".into());
+    }
+    if selectors.is_empty() {
+        return Ok(Vec::new());
+    }
+    info!("Verifying {} selectors on {}", selectors.len(), url);
+
+    let (mut browser, mut handler) = Browser::launch(
+        chromiumoxide::BrowserConfig::builder()
+            .build()?
+    ).await?;
+
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+
+    let script = MISSING_SELECTORS_SCRIPT.replace("__SELECTORS__", &serde_json::to_string(selectors)?);
+    let missing = async {
+        let page = browser.new_page(url).await?;
+        page.wait_for_navigation().await?;
+        Ok::<Vec<String>, Box<dyn std::error::Error + Send + Sync>>(page.evaluate(script).await?.into_value()?)
+    }
+    .await;
+
+    if let Err(e) = browser.close().await {
+        warn!("Failed to close selector verification browser: {}", e);
+    }
+    handle.abort();
+
+    missing
+}
+
+/// Jak `find_missing_selectors`, ale na stronie otwartej w webview aplikacji (WEBVIEW_CDP_PORT),
+/// czyli z zalogowaną sesją użytkownika; strona musi być z witryny `url`
+pub async fn find_missing_selectors_in_webview(port: u16, url: &str, selectors: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    if selectors.is_empty() {
+        return Ok(Vec::new());
+    }
+    info!(port, "Verifying {} selectors in the webview", selectors.len());
+    let (mut browser, mut handler) = Browser::connect(format!("http://127.0.0.1:{}", port)).await?;
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+
+    let script = MISSING_SELECTORS_SCRIPT.replace("__SELECTORS__", &serde_json::to_string(selectors)?);
+    let missing = async {
+        let page = attach_webview_page(&mut browser, url, true).await?;
+        Ok::<Vec<String>, Box<dyn std::error::Error + Send + Sync>>(page.evaluate(script).await?.into_value()?)
+    }
+    .await;
+
+    // Bez `browser.close()` - to przeglądarka użytkownika; zrywamy tylko połączenie
+    handle.abort();
+    missing
+}
+
+/// Otwiera pustą stronę w przeglądarce przez CDP - test, czy Chromium w ogóle działa
+pub async fn render_blank_page() -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let (mut browser, mut handler) = Browser::launch(
+        chromiumoxide::BrowserConfig::builder()
+            .build()?
+    ).await?;
+    
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+    
+    let rendered = async {
+        let page = browser.new_page("about:blank").await?;
+        let html = page.content().await?;
+        if !html.contains("<html") {
+            return Err::<usize, Box<dyn std::error::Error + Send + Sync>>("Blank page rendered without an <html> element".into());
+        }
+        Ok(html.len())
     }
+    .await;
+    
+    browser.close().await?;
+    handle.abort();
     
-    if let Some(name) = extract_name_from_input(html, input_type) {
-        return format!("[name=\"{}\"]", name);
+    rendered
+}
+
+/// Rozdziela HTML z `PageContent::to_html` na dokument główny (`None`) i dokumenty ramek
+pub fn split_frames(html: &str) -> Vec<(Option<String>, String)> {
+    let open = format!("<{} name=\"", FRAME_TAG);
+    let close = format!("</{}>", FRAME_TAG);
+    let mut main = String::new();
+    let mut frames = Vec::new();
+    let mut rest = html;
+    
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some((name, body)) = after.split_once('"').and_then(|(name, tail)| tail.split_once('>').map(|(_, body)| (name, body))) else {
+            break;
+        };
+        main.push_str(&rest[..start]);
+        let (content, remaining) = body.split_once(close.as_str()).unwrap_or((body, ""));
+        frames.push((Some(name.to_string()), content.to_string()));
+        rest = remaining;
     }
+    main.push_str(rest);
     
-    format!("input[type=\"{}\"]", input_type)
+    let mut documents = vec![(None, without_accessibility(&main))];
+    documents.extend(frames);
+    documents
 }
 
-fn extract_button_id(html: &str) -> Option<String> {
-    // Znajdź pierwszy button lub input[type=submit]
-    if let Some(pos) = html.find("<button") {
-        let button_section = &html[pos..];
-        if let Some(id_pos) = button_section.find("id=\"") {
-            let id_start = id_pos + 4;
-            if let Some(id_end) = button_section[id_start..].find('"') {
-                return Some(button_section[id_start..id_start + id_end].to_string());
+/// Form elements of the page and of its iframes, each tagged with the frame it lives in
+pub async fn extract_form_elements(html: &str) -> Vec<FormElement> {
+    debug!("Extracting form elements from HTML");
+    
+    let elements: Vec<FormElement> = dom::group_radios(dom::form_fields(html))
+        .into_iter()
+        .map(|field| FormElement {
+            selector: field.selector(),
+            tag: field.tag,
+            element_type: field.element_type,
+            id: field.id,
+            name: field.name,
+            label: field.label,
+            options: field.options,
+            frame: field.frame,
+            required: field.required,
+            placeholder: field.placeholder,
+            max_length: field.max_length,
+            pattern: field.pattern,
+            role: field.accessible.map(|accessible| accessible.role),
+        })
+        .collect();
+    
+    debug!("Found {} form elements", elements.len());
+    elements
+}
+
+/// Heuristically checks whether a CSS selector (or visible text) can be found in the HTML.
+/// Used for dry runs, where no live DOM is available.
+pub fn selector_matches(html: &str, selector: &str) -> bool {
+    let selector = selector.trim();
+    if selector.is_empty() {
+        return false;
+    }
+    
+    // XPath z roli i nazwy dostępnej - element z bloku drzewa dostępności
+    if (selector.starts_with("//") || selector.starts_with("(//")) && accessible_elements_of(html).iter().any(|element| element.xpath() == selector) {
+        return true;
+    }
+    
+    let has_attribute = |attr: &str, value: &str| {
+        html.contains(&format!("{}=\"{}\"", attr, value)) || html.contains(&format!("{}='{}'", attr, value))
+    };
+    
+    if let Some(id) = selector.strip_prefix('#') {
+        return has_attribute("id", id);
+    }
+    
+    if let Some(class) = selector.strip_prefix('.') {
+        return html.match_indices("class=").any(|(pos, _)| {
+            let rest = &html[pos + 6..];
+            let quote = rest.chars().next().unwrap_or('"');
+            rest.get(1..)
+                .and_then(|value| value.split(quote).next())
+                .map(|classes| classes.split_whitespace().any(|c| c == class))
+                .unwrap_or(false)
+        });
+    }
+    
+    // [attr="value"] oraz tag[attr="value"]
+    if let (Some(open), true) = (selector.find('['), selector.ends_with(']')) {
+        let tag = &selector[..open];
+        let inner = &selector[open + 1..selector.len() - 1];
+        let attribute_found = match inner.split_once('=') {
+            Some((attr, value)) => has_attribute(attr.trim(), value.trim().trim_matches('"').trim_matches('\'')),
+            None => html.contains(&format!(" {}", inner.trim())),
+        };
+        return attribute_found && (tag.is_empty() || html.contains(&format!("<{}", tag)));
+    }
+    
+    // Zwykły tekst (np. click "Submit") - szukaj w treści strony
+    html.contains(selector)
+}
+
+/// Attributes kept by `sanitize_html`; values, inline handlers, `data-*` and styles are dropped
+const STRUCTURAL_ATTRIBUTES: &[&str] = &[
+    "id", "name", "class", "type", "for", "role", "method", "action", "href",
+    "placeholder", "aria-label", "multiple", "required", "disabled",
+];
+
+/// Elements whose whole content is dropped (inline code and data)
+const DROPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+
+/// Host adresu małymi literami, bez `www.`; adres bez schematu traktowany jak https
+pub fn site_of(uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    let parsed = reqwest::Url::parse(uri).or_else(|_| reqwest::Url::parse(&format!("https://{}", uri))).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+/// Redukuje HTML do samej struktury przed zapisem: bez skryptów, komentarzy, tekstu
+/// i wartości pól (tokeny CSRF, e-maile). Jeden znacznik na linię.
+pub fn sanitize_html(html: &str) -> String {
+    let mut output = String::new();
+    let mut rest = html;
+    
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+            continue;
+        }
+        let Some(end) = find_tag_end(rest) else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        
+        if let Some(closing) = tag.strip_prefix('/') {
+            let name = closing.trim().to_lowercase();
+            if is_tag_name(&name) {
+                output.push_str(&format!("</{}>\n", name));
+            }
+            continue;
+        }
+        
+        let (name, attributes) = parse_tag(tag);
+        if !is_tag_name(&name) {
+            // <!DOCTYPE>, <?xml?> albo przypadkowe "<" w tekście
+            continue;
+        }
+        if DROPPED_ELEMENTS.contains(&name.as_str()) {
+            let closing = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&closing) {
+                Some(pos) => rest[pos..].find('>').map(|end| &rest[pos + end + 1..]).unwrap_or(""),
+                None => "",
+            };
+            continue;
+        }
+        
+        output.push('<');
+        output.push_str(&name);
+        for (attribute, value) in attributes {
+            if !STRUCTURAL_ATTRIBUTES.contains(&attribute.as_str()) {
+                continue;
+            }
+            match value {
+                Some(value) => {
+                    let value = if attribute == "href" || attribute == "action" {
+                        let lowered = value.trim().to_lowercase();
+                        if lowered.starts_with("javascript:") || lowered.starts_with("data:") {
+                            continue;
+                        }
+                        // Parametry zapytania często niosą tokeny
+                        value.split(['?', '#']).next().unwrap_or("").to_string()
+                    } else {
+                        value
+                    };
+                    output.push_str(&format!(" {}=\"{}\"", attribute, value.replace('"', "&quot;")));
+                }
+                None => output.push_str(&format!(" {}", attribute)),
             }
         }
+        if tag.trim_end().ends_with('/') {
+            output.push_str(" /");
+        }
+        output.push_str(">\n");
     }
-    None
+    
+    output
 }
 
-fn generate_button_selector(html: &str) -> String {
-    if let Some(id) = extract_button_id(html) {
-        return format!("#{}", id);
+/// Index of the `>` closing the tag at the start of `html`, ignoring `>` inside quoted values
+fn find_tag_end(html: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (index, c) in html.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '>') => return Some(index),
+            _ => {}
+        }
     }
+    None
+}
+
+fn is_tag_name(name: &str) -> bool {
+    name.chars().next().map(|c| c.is_ascii_alphabetic()).unwrap_or(false)
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Splits the inside of an opening tag into its lowercased name and attributes
+fn parse_tag(tag: &str) -> (String, Vec<(String, Option<String>)>) {
+    let chars: Vec<char> = tag.chars().collect();
+    let mut position = 0;
+    let read_while = |position: &mut usize, predicate: &dyn Fn(char) -> bool| -> String {
+        let start = *position;
+        while *position < chars.len() && predicate(chars[*position]) {
+            *position += 1;
+        }
+        chars[start..*position].iter().collect()
+    };
+    
+    let name = read_while(&mut position, &|c| !c.is_whitespace() && c != '/').to_lowercase();
+    let mut attributes = Vec::new();
     
-    // Fallback selectors
-    if html.contains("type=\"submit\"") {
-        return "input[type=\"submit\"]".to_string();
+    loop {
+        read_while(&mut position, &|c| c.is_whitespace() || c == '/');
+        if position >= chars.len() {
+            break;
+        }
+        let attribute = read_while(&mut position, &|c| !c.is_whitespace() && c != '=' && c != '/').to_lowercase();
+        read_while(&mut position, &|c| c.is_whitespace());
+        
+        let value = if position < chars.len() && chars[position] == '=' {
+            position += 1;
+            read_while(&mut position, &|c| c.is_whitespace());
+            match chars.get(position).copied() {
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    position += 1;
+                    let value = read_while(&mut position, &|c| c != quote);
+                    position += 1;
+                    Some(value)
+                }
+                _ => Some(read_while(&mut position, &|c| !c.is_whitespace())),
+            }
+        } else {
+            None
+        };
+        
+        if !attribute.is_empty() {
+            attributes.push((attribute, value));
+        }
     }
     
-    "button".to_string()
+    (name, attributes)
+}
+
+#[derive(Debug, Clone)]
+pub struct FormElement {
+    pub tag: String,
+    pub element_type: Option<String>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub selector: String,
+    /// Tekst etykiety pola (`<label>`, `aria-label` lub `placeholder`)
+    pub label: Option<String>,
+    /// Opcje listy `<select>` albo wartości grupy radio (jeden element na grupę, selektor `[name]`)
+    pub options: Vec<SelectOption>,
+    /// Ramka iframe (nazwa albo `#id`), w której leży element; `None` dla dokumentu głównego
+    pub frame: Option<String>,
+    pub required: bool,
+    pub placeholder: Option<String>,
+    pub max_length: Option<usize>,
+    pub pattern: Option<String>,
+    /// Rola z drzewa dostępności; gdy pole nie ma `id` ani `name`, `selector` jest XPath z roli i nazwy
+    pub role: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_network_idle_tolerates_long_polling() {
+        let start = Instant::now();
+        let mut activity = NetworkActivity::new(start);
+        assert_eq!(activity.idle_at(), Some(start + QUIET_PERIOD));
+
+        for id in ["doc", "bundle", "api", "poll"] {
+            activity.record(NetworkEvent::Started(id.to_string()), start);
+        }
+        assert_eq!(activity.idle_at(), None);
+
+        // Zostają dwa żądania w toku (long polling, analityka) - po ciszy strona jest gotowa
+        let later = start + Duration::from_millis(300);
+        activity.record(NetworkEvent::Finished("doc".to_string()), later);
+        activity.record(NetworkEvent::Finished("bundle".to_string()), later);
+        assert_eq!(activity.idle_at(), Some(later + QUIET_PERIOD));
+
+        assert_eq!(ReadyStrategy::parse("network-idle"), Some(ReadyStrategy::NetworkIdle));
+        assert_eq!(ReadyStrategy::parse("DOM_STABLE"), Some(ReadyStrategy::DomStable));
+        assert!(ReadyStrategy::parse("idle").is_none());
+        assert!(!ReadyStrategy::Load.waits_for_network() && !ReadyStrategy::Load.waits_for_dom());
+    }
+
     #[tokio::test]
     async fn test_extract_form_elements() {
         let html = r#"
@@ -197,15 +1193,209 @@ mod tests {
         assert_eq!(text_input.id, Some("username".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_extract_form_elements_options_and_constraints() {
+        let html = r#"
+            <form>
+                <input id="phone" name="phone" required maxlength="12" pattern="\+?[0-9 ]+" placeholder="+48 600 000 000">
+                <select name="country"><option value="">Choose</option><option value="pl">Poland</option></select>
+                <label><input type="radio" name="contract" value="b2b" required> B2B</label>
+                <input id="uop" type="radio" name="contract" value="uop"><label for="uop">Employment</label>
+                <input type="checkbox" name="terms">
+            </form>
+        "#;
+
+        let elements = extract_form_elements(html).await;
+        assert_eq!(elements.len(), 4);
+
+        let phone = &elements[0];
+        assert!(phone.required);
+        assert_eq!(phone.max_length, Some(12));
+        assert_eq!(phone.pattern.as_deref(), Some("\\+?[0-9 ]+"));
+        assert_eq!(phone.placeholder.as_deref(), Some("+48 600 000 000"));
+
+        let values = |element: &FormElement| element.options.iter().map(|option| option.value.clone()).collect::<Vec<_>>();
+        assert_eq!(values(&elements[1]), vec!["", "pl"]);
+
+        let contract = &elements[2];
+        assert_eq!(contract.selector, "[name=\"contract\"]");
+        assert_eq!(values(contract), vec!["b2b", "uop"]);
+        assert_eq!(contract.options[1].text, "Employment");
+        assert!(contract.required);
+        assert_eq!(elements[3].element_type.as_deref(), Some("checkbox"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_form_elements_in_frames() {
+        let content = PageContent {
+            html: r##"<iframe id="grnhse_iframe"></iframe><input id="search" type="text">
+                <script>var fake = '<codialog-frame name="#spoof"><input id="stolen"></codialog-frame>';</script>"##.to_string(),
+            frames: vec![FrameDocument {
+                reference: "#grnhse_iframe".to_string(),
+                html: r#"<html><input id="first_name" type="text"><input id="resume" type="file"></html>"#.to_string(),
+            }],
+            accessible: Vec::new(),
+        };
+        let html = content.to_html();
+        
+        let documents = split_frames(&html);
+        assert_eq!(documents.len(), 2);
+        assert!(!documents[0].1.contains(&format!("<{}", FRAME_TAG)));
+        
+        let elements = extract_form_elements(&html).await;
+        let frame_of = |selector: &str| elements.iter().find(|e| e.selector == selector).map(|e| e.frame.clone());
+        assert_eq!(frame_of("#search"), Some(None));
+        assert_eq!(frame_of("#first_name"), Some(Some("#grnhse_iframe".to_string())));
+        assert_eq!(frame_of("#resume"), Some(Some("#grnhse_iframe".to_string())));
+        assert!(!documents.iter().any(|(frame, _)| frame.as_deref() == Some("#spoof")));
+    }
+
+    #[test]
+    fn test_accessibility_fallback_selectors() {
+        let nodes: Vec<AxNode> = serde_json::from_value(serde_json::json!([
+            { "nodeId": "1", "ignored": false, "role": { "type": "role", "value": "RootWebArea" }, "name": { "type": "computedString", "value": "Apply" } },
+            { "nodeId": "2", "ignored": false, "role": { "type": "role", "value": "textbox" }, "name": { "type": "computedString", "value": "Work  e-mail" }, "backendDOMNodeId": 7 },
+            { "nodeId": "3", "ignored": false, "role": { "type": "role", "value": "button" }, "name": { "type": "computedString", "value": "Send <now>" }, "backendDOMNodeId": 9 },
+            { "nodeId": "4", "ignored": true, "role": { "type": "role", "value": "button" }, "name": { "type": "computedString", "value": "Hidden" } },
+            { "nodeId": "5", "ignored": false, "role": { "type": "role", "value": "link" }, "name": { "type": "computedString", "value": "" } }
+        ]))
+        .unwrap();
+        let mut elements: Vec<AccessibleElement> = interactive_nodes(&nodes).into_iter().map(|(_, element)| element).collect();
+        assert_eq!(elements.iter().map(|element| element.name.as_str()).collect::<Vec<_>>(), vec!["Work e-mail", "Send <now>"]);
+        elements[0].control = Some(1);
+        elements[1].control = Some(2);
+
+        let mut html = r#"<form><input id="name"><input class="css-1q2w3e" id=":r5:"><button class="sc-a8f3d">
+            <svg aria-hidden="true"></svg></button></form>"#.to_string();
+        append_accessibility(&mut html, &elements);
+        assert!(!html.contains("Send <now>"));
+        assert_eq!(accessible_elements_of(&html), elements);
+        assert!(!split_frames(&html)[0].1.contains(ACCESSIBILITY_TAG));
+
+        let fields = dom::form_fields(&html);
+        assert_eq!(fields[0].selector(), "#name");
+        assert_eq!(fields[1].label.as_deref(), Some("Work e-mail"));
+        assert!(fields[1].selector().starts_with("//*[self::textarea or self::input"));
+        assert_eq!(fields[1].selectors()[1], "[id=\":r5:\"]");
+        assert_eq!(
+            fields[2].selector(),
+            "//*[self::button or self::input[@type='submit' or @type='button' or @type='reset'] or @role='button'][normalize-space(.)='Send <now>' \
+            or @aria-label='Send <now>' or @title='Send <now>' or @placeholder='Send <now>' or @value='Send <now>' \
+            or @id=//label[normalize-space(.)='Send <now>']/@for or ancestor::label[normalize-space(.)='Send <now>'] \
+            or @aria-labelledby=//*[normalize-space(.)='Send <now>']/@id]"
+        );
+        assert!(selector_matches(&html, &fields[2].selector()));
+        assert!(tagui::validate_dsl_script(&format!("click \"{}\"", fields[2].selector())).is_ok());
+        assert_eq!(xpath_literal("it's \"ok\""), "concat('it', \"'\", 's \"ok\"')");
+
+        // Dwa przyciski "Apply" dostają pozycję, pojedynczy element jej nie ma
+        let repeated: Vec<AxNode> = serde_json::from_value(serde_json::json!([
+            { "nodeId": "1", "ignored": false, "role": { "type": "role", "value": "button" }, "name": { "type": "computedString", "value": "Apply" } },
+            { "nodeId": "2", "ignored": false, "role": { "type": "role", "value": "link" }, "name": { "type": "computedString", "value": "Apply" } },
+            { "nodeId": "3", "ignored": false, "role": { "type": "role", "value": "button" }, "name": { "type": "computedString", "value": "Apply" } }
+        ]))
+        .unwrap();
+        let repeated: Vec<AccessibleElement> = interactive_nodes(&repeated).into_iter().map(|(_, element)| element).collect();
+        assert_eq!(repeated.iter().map(|element| element.occurrence).collect::<Vec<_>>(), vec![Some(1), None, Some(2)]);
+        assert!(repeated[2].xpath().starts_with("(//*[self::button"));
+        assert!(repeated[2].xpath().ends_with(")[2]"));
+        assert!(repeated[1].xpath().starts_with("//*[self::a"));
+    }
+
     #[test]
-    fn test_generate_selector() {
-        let html_with_id = r#"<input id="test" type="text">"#;
-        assert_eq!(generate_selector(html_with_id, "text"), "#test");
+    fn test_selector_matches() {
+        let html = r#"<input id="email" name='user_email' class="form-control wide"><button>Apply now</button>"#;
+        assert!(selector_matches(html, "#email"));
+        assert!(selector_matches(html, "[name=\"user_email\"]"));
+        assert!(selector_matches(html, "input[name=\"user_email\"]"));
+        assert!(selector_matches(html, ".wide"));
+        assert!(selector_matches(html, "Apply now"));
+        assert!(!selector_matches(html, "#password"));
+        assert!(!selector_matches(html, ".form"));
+        assert!(!selector_matches("<div class=", "."));
+        assert!(!selector_matches("<div class=\"a\">", "."));
+    }
+
+    #[test]
+    fn test_sanitize_html_keeps_structure_only() {
+        let html = r#"<!DOCTYPE html><html><head>
+            <meta name="csrf-token" content="s3cr3t-csrf">
+            <script>window.__TOKEN__ = "abc123";</script>
+            <style>.x > .y { color: red }</style>
+        </head><body>
+            <!-- debug: jan@example.com -->
+            <form action="/apply?token=xyz" method="post" onsubmit="track()">
+                <input id="email" name="email" type="email" value="jan@example.com" data-user-id="42">
+                <input type="hidden" name="_csrf" value="s3cr3t-csrf"/>
+                <a href="javascript:alert(1)" class="link">Contact jan@example.com</a>
+                <button id="submit" type="submit" style="color: red">Send</button>
+            </form>
+        </body></html>"#;
         
-        let html_with_name = r#"<input name="test" type="text">"#;
-        assert_eq!(generate_selector(html_with_name, "text"), "[name=\"test\"]");
+        let sanitized = sanitize_html(html);
+        for leaked in ["s3cr3t-csrf", "abc123", "jan@example.com", "token=xyz", "track()", "data-user-id", "color", "javascript"] {
+            assert!(!sanitized.contains(leaked), "sanitized HTML still contains {}", leaked);
+        }
+        assert!(sanitized.contains("<form action=\"/apply\" method=\"post\">"));
+        assert!(sanitized.contains("<input id=\"email\" name=\"email\" type=\"email\">"));
+        assert!(sanitized.contains("<input type=\"hidden\" name=\"_csrf\" />"));
+        assert!(sanitized.contains("<a class=\"link\">"));
+        assert!(selector_matches(&sanitized, "#submit"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_selector() {
+        let selector = |html: &'static str| async move { extract_form_elements(html).await[0].selector.clone() };
         
-        let html_basic = r#"<input type="text">"#;
-        assert_eq!(generate_selector(html_basic, "text"), "input[type=\"text\"]");
+        assert_eq!(selector(r#"<input id="test" type="text">"#).await, "#test");
+        assert_eq!(selector(r#"<input name="test" type="text">"#).await, "[name=\"test\"]");
+        assert_eq!(selector(r#"<input type="text">"#).await, "input[type=\"text\"]");
+    }
+
+    #[test]
+    fn test_webview_page_skips_application_ui() {
+        let urls = ["tauri://localhost/index.html", "https://jobs.example.com/apply#step-2", "https://example.com/login"];
+        assert_eq!(webview_page(&urls, "https://jobs.example.com/apply/"), Some(1));
+        assert_eq!(webview_page(&urls, "https://example.com/account"), Some(2));
+        // Nieznany adres - pierwsza strona, która nie jest interfejsem aplikacji
+        assert_eq!(webview_page(&urls, "https://other.org/"), Some(1));
+        assert_eq!(webview_page(&["http://tauri.localhost/", "about:blank"], "https://example.com/"), None);
+    }
+
+    #[test]
+    fn test_auth_state_cookies_and_local_storage() {
+        // Eksport storageState z Playwright
+        let auth: AuthState = serde_json::from_value(serde_json::json!({
+            "cookies": [
+                { "name": "sid", "value": "abc", "domain": ".portal.example.com", "path": "/", "httpOnly": true, "sameSite": "Lax", "expires": -1 },
+                { "name": "lang", "value": "pl", "expirationDate": 1900000000.0 }
+            ],
+            "origins": [
+                { "origin": "https://portal.example.com/", "localStorage": [{ "name": "token", "value": "x\"y" }] },
+                { "origin": "https://empty.example.com", "localStorage": [] }
+            ]
+        }))
+        .unwrap();
+
+        let cookies = cookie_params(&auth);
+        assert_eq!(cookies[0].domain.as_deref(), Some(".portal.example.com"));
+        assert_eq!(cookies[0].url, None);
+        assert_eq!(cookies[0].http_only, Some(true));
+        assert_eq!(cookies[0].same_site, Some(CookieSameSite::Lax));
+        assert_eq!(cookies[0].expires, None);
+        assert_eq!(cookies[1].url.as_deref(), Some("https://portal.example.com"));
+        assert_eq!(cookies[1].expires.as_ref().map(|expires| *expires.inner()), Some(1900000000.0));
+
+        let script = local_storage_script(&auth).unwrap();
+        assert!(script.contains(r#"{"https://portal.example.com":[["token","x\"y"]]}"#));
+        assert!(!script.contains("empty.example.com"));
+        assert_eq!(local_storage_script(&AuthState::default()), None);
+
+        assert!(auth_state_covers(&auth, "https://portal.example.com/apply"));
+        assert!(auth_state_covers(&auth, "https://jobs.portal.example.com/"));
+        assert!(!auth_state_covers(&auth, "https://attacker.example.net/collect"));
+        assert!(!auth_state_covers(&auth, "https://evilportal.example.com/"));
+        let without_origin = AuthState { origins: Vec::new(), ..auth };
+        assert_eq!(cookie_params(&without_origin).len(), 1);
     }
 }
@@ -0,0 +1,185 @@
+//! Decyzje użytkownika zapamiętane dla witryny: poprawione pola formularza (klucz danych -> selektor)
+//! i dane logowania wybrane spośród kilku pasujących. Kolejne generowanie DSL i oferty autofill
+//! na tej samej witrynie stosują je bez ponownego pytania.
+
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context, anyhow, bail};
+use tracing::info;
+use chrono::{DateTime, Utc};
+
+use codialog_types::automation::{ChoiceKind, RememberChoiceRequest};
+
+use crate::cdp::site_of;
+
+/// Klucz wyboru danych logowania - witryna ma jedno zapamiętane konto
+pub const CREDENTIAL_KEY: &str = "login";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RememberedChoice {
+    pub site: String,
+    pub kind: ChoiceKind,
+    pub key: String,
+    pub value: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Witryna, klucz i wartość z żądania; selektor pola sprawdzany jako CSS, bo trafi do skryptu
+pub fn normalize(request: &RememberChoiceRequest) -> Result<(String, String, String)> {
+    let site = site_of(&request.url).ok_or_else(|| anyhow!("Invalid site URL: {}", request.url))?;
+    let key = match request.kind {
+        ChoiceKind::FieldMapping => request.key.trim().to_string(),
+        ChoiceKind::Credential => CREDENTIAL_KEY.to_string(),
+    };
+    let value = request.value.trim().to_string();
+    if key.is_empty() || value.is_empty() {
+        bail!("Choice key and value must not be empty");
+    }
+    if request.kind == ChoiceKind::FieldMapping {
+        scraper::Selector::parse(&value).map_err(|e| anyhow!("Invalid CSS selector '{}': {}", value, e))?;
+    }
+    Ok((site, key, value))
+}
+
+fn choice_from_row(row: &sqlx::postgres::PgRow) -> RememberedChoice {
+    RememberedChoice {
+        site: row.get("site"),
+        kind: ChoiceKind::parse(&row.get::<String, _>("kind")).unwrap_or(ChoiceKind::FieldMapping),
+        key: row.get("key"),
+        value: row.get("value"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChoiceStore {
+    db_pool: PgPool,
+}
+
+impl ChoiceStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę zapamiętanych decyzji
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing remembered choices table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS remembered_choices (
+                site VARCHAR(255) NOT NULL,
+                kind VARCHAR(32) NOT NULL,
+                key VARCHAR(255) NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (site, kind, key)
+            )
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create remembered_choices table")?;
+
+        Ok(())
+    }
+
+    /// Zapisuje decyzję; nowsza dla tego samego klucza zastępuje poprzednią
+    pub async fn remember(&self, request: &RememberChoiceRequest) -> Result<RememberedChoice> {
+        let (site, key, value) = normalize(request)?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO remembered_choices (site, kind, key, value)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (site, kind, key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()
+            RETURNING site, kind, key, value, updated_at
+            "#,
+        )
+        .bind(&site)
+        .bind(request.kind.as_str())
+        .bind(&key)
+        .bind(&value)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to save remembered choice")?;
+
+        info!(site = %site, kind = request.kind.as_str(), key = %key, "Choice remembered for site");
+        Ok(choice_from_row(&row))
+    }
+
+    /// Decyzje danego rodzaju dla witryny jako pary klucz -> wartość
+    pub async fn for_site(&self, site: &str, kind: ChoiceKind) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query("SELECT key, value FROM remembered_choices WHERE site = $1 AND kind = $2 ORDER BY key")
+            .bind(site)
+            .bind(kind.as_str())
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to load remembered choices of site")?;
+
+        Ok(rows.iter().map(|row| (row.get("key"), row.get("value"))).collect())
+    }
+
+    pub async fn list(&self, site: Option<&str>) -> Result<Vec<RememberedChoice>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT site, kind, key, value, updated_at
+            FROM remembered_choices
+            WHERE $1::text IS NULL OR site = $1
+            ORDER BY site, kind, key
+            "#,
+        )
+        .bind(site)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to list remembered choices")?;
+
+        Ok(rows.iter().map(choice_from_row).collect())
+    }
+
+    /// Usuwa decyzje pasujące do podanych filtrów (brak filtra = wszystkie); zwraca liczbę usuniętych
+    pub async fn clear(&self, site: Option<&str>, kind: Option<ChoiceKind>, key: Option<&str>) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM remembered_choices
+            WHERE ($1::text IS NULL OR site = $1)
+              AND ($2::text IS NULL OR kind = $2)
+              AND ($3::text IS NULL OR key = $3)
+            "#,
+        )
+        .bind(site)
+        .bind(kind.map(|kind| kind.as_str()))
+        .bind(key)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to clear remembered choices")?;
+
+        info!(site = ?site, kind = ?kind, key = ?key, removed = result.rows_affected(), "Remembered choices cleared");
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_keys_choices_by_site() {
+        let request = |kind, key: &str, value: &str| RememberChoiceRequest {
+            url: "https://www.Jobs.example.com/apply?id=7".to_string(),
+            kind,
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+
+        let (site, key, value) = normalize(&request(ChoiceKind::FieldMapping, " phone ", "#mobile")).unwrap();
+        assert_eq!((site.as_str(), key.as_str(), value.as_str()), ("jobs.example.com", "phone", "#mobile"));
+        // Jedno konto na witrynę, niezależnie od klucza z żądania
+        let (_, key, _) = normalize(&request(ChoiceKind::Credential, "", "item-1")).unwrap();
+        assert_eq!(key, CREDENTIAL_KEY);
+
+        assert!(normalize(&request(ChoiceKind::FieldMapping, "phone", "#[broken")).is_err());
+        assert!(normalize(&request(ChoiceKind::FieldMapping, "phone", "  ")).is_err());
+        assert!(normalize(&RememberChoiceRequest { url: "not a url".to_string(), ..request(ChoiceKind::Credential, "", "item-1") }).is_err());
+    }
+}
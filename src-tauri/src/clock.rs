@@ -0,0 +1,128 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use anyhow::{Result, Context};
+use std::sync::atomic::{AtomicI64, Ordering};
+use tracing::{debug, warn};
+
+/// Tolerancja rozjazdu zegarów przy sprawdzaniu wygaśnięcia
+pub const SKEW_TOLERANCE_SECS: i64 = 5;
+
+/// Źródło czasu dla decyzji o wygaśnięciu sesji i cache
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// An entry counts as expired only once it is past `expires_at` by more than the skew tolerance
+    fn is_expired(&self, expires_at: DateTime<Utc>) -> bool {
+        self.now() > expires_at + Duration::seconds(SKEW_TOLERANCE_SECS)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Local clock corrected by the offset to the database's `NOW()`,
+/// so in-process checks agree with SQL-side `expires_at > NOW()` filters.
+#[derive(Debug)]
+pub struct DbClock {
+    db_pool: PgPool,
+    offset_ms: AtomicI64,
+}
+
+impl DbClock {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            offset_ms: AtomicI64::new(0),
+        }
+    }
+
+    pub fn offset(&self) -> Duration {
+        Duration::milliseconds(self.offset_ms.load(Ordering::Relaxed))
+    }
+
+    /// Mierzy przesunięcie względem zegara bazy (z korektą o połowę czasu zapytania)
+    pub async fn sync(&self) -> Result<Duration> {
+        let before = Utc::now();
+        let db_now: DateTime<Utc> = sqlx::query_scalar("SELECT NOW()")
+            .fetch_one(&self.db_pool)
+            .await
+            .context("Failed to read database time")?;
+        let after = Utc::now();
+
+        let local_midpoint = before + (after - before) / 2;
+        let offset = db_now - local_midpoint;
+        self.offset_ms.store(offset.num_milliseconds(), Ordering::Relaxed);
+
+        if offset.num_seconds().abs() > SKEW_TOLERANCE_SECS {
+            warn!("Local clock differs from database clock by {} ms", offset.num_milliseconds());
+        } else {
+            debug!("Database clock offset: {} ms", offset.num_milliseconds());
+        }
+
+        Ok(offset)
+    }
+
+    /// Okresowa synchronizacja z zegarem bazy
+    pub async fn run_sync(&self, interval: std::time::Duration) {
+        loop {
+            if let Err(e) = self.sync().await {
+                warn!("Database clock sync failed: {}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+impl Clock for DbClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset()
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+pub struct MockClock {
+    now: std::sync::Mutex<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: std::sync::Mutex::new(now) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_tolerates_small_skew() {
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let expires_at = start + Duration::seconds(10);
+
+        assert!(!clock.is_expired(expires_at));
+        clock.advance(Duration::seconds(12));
+        assert!(!clock.is_expired(expires_at));
+        clock.advance(Duration::seconds(10));
+        assert!(clock.is_expired(expires_at));
+    }
+}
@@ -0,0 +1,298 @@
+//! Import danych logowania z eksportów CSV innych menedżerów haseł (LastPass, Chrome, 1Password).
+//! Wiersze są mapowane na elementy vault i porównywane z istniejącymi, zanim cokolwiek zostanie zapisane.
+
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use codialog_types::vault::{BitwardenCredential, ImportFormat};
+use codialog_types::SecretString;
+
+use crate::bitwarden::BitwardenManager;
+use crate::cdp::site_of;
+
+const NAME_COLUMNS: &[&str] = &["name", "title"];
+const URL_COLUMNS: &[&str] = &["url", "website", "login_uri", "login url"];
+const USERNAME_COLUMNS: &[&str] = &["username", "login_username", "login name"];
+const PASSWORD_COLUMNS: &[&str] = &["password", "login_password"];
+const NOTES_COLUMNS: &[&str] = &["extra", "notes", "note"];
+
+/// LastPass eksportuje bezpieczne notatki jako wiersze z tym adresem
+const LASTPASS_SECURE_NOTE_URL: &str = "http://sn";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    New,
+    /// Ten sam login dla tej samej strony jest już w vault albo wcześniej w pliku
+    Duplicate,
+    Invalid,
+}
+
+/// Wiersz eksportu po zmapowaniu; w podglądzie bez hasła
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportEntry {
+    /// Numer wiersza danych, od 1 (bez nagłówka)
+    pub row: usize,
+    pub name: String,
+    pub uri: Option<String>,
+    pub username: Option<String>,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Element vault, który duplikuje
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing_item_id: Option<String>,
+    #[serde(skip)]
+    credential: BitwardenCredential,
+}
+
+/// Rozpoznaje eksport po nazwach kolumn nagłówka
+pub fn detect_format(header: &[String]) -> Option<ImportFormat> {
+    let has = |column: &str| header.iter().any(|name| name == column);
+    if has("grouping") || has("extra") {
+        Some(ImportFormat::LastPass)
+    } else if has("title") {
+        Some(ImportFormat::OnePassword)
+    } else if has("name") && has("url") {
+        Some(ImportFormat::Chrome)
+    } else {
+        None
+    }
+}
+
+/// Plan importu: każdy wiersz z oceną względem vault (`existing`) i poprzednich wierszy pliku
+pub fn plan_import(csv: &str, format: Option<ImportFormat>, existing: &[BitwardenCredential]) -> Result<(ImportFormat, Vec<ImportEntry>)> {
+    let mut records = parse_csv(csv)?.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or_else(|| anyhow!("CSV export is empty"))?
+        .iter()
+        .map(|column| column.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+    let format = format.or_else(|| detect_format(&header)).ok_or_else(|| {
+        anyhow!("Unrecognized CSV header - expected a LastPass, Chrome or 1Password export")
+    })?;
+    let column = |aliases: &[&str]| header.iter().position(|name| aliases.contains(&name.as_str()));
+    let password_column = column(PASSWORD_COLUMNS).ok_or_else(|| anyhow!("CSV export has no password column"))?;
+    let (name_column, url_column) = (column(NAME_COLUMNS), column(URL_COLUMNS));
+    if name_column.is_none() && url_column.is_none() {
+        bail!("CSV export has neither a name nor a URL column");
+    }
+    let (username_column, notes_column) = (column(USERNAME_COLUMNS), column(NOTES_COLUMNS));
+
+    let mut known: HashMap<String, Known> = existing
+        .iter()
+        .map(|credential| (login_key(credential), Known::Vault { item_id: credential.id.clone(), password: credential.password.clone() }))
+        .collect();
+    let mut entries = Vec::new();
+    for (index, record) in records.enumerate() {
+        let row = index + 1;
+        let field = |column: Option<usize>| {
+            column.and_then(|column| record.get(column)).map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+        };
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        let uri = field(url_column);
+        let username = field(username_column);
+        let password = field(Some(password_column)).map(SecretString::from);
+        let name = field(name_column)
+            .or_else(|| uri.as_deref().and_then(site_of))
+            .or_else(|| username.clone())
+            .unwrap_or_else(|| format!("Imported login {}", row));
+        let credential = BitwardenCredential {
+            id: String::new(),
+            name: name.clone(),
+            username: username.clone(),
+            password: password.clone(),
+            uri: uri.clone(),
+            notes: field(notes_column),
+            folder_id: None,
+        };
+
+        let (status, reason, existing_item_id) = if uri.as_deref() == Some(LASTPASS_SECURE_NOTE_URL) {
+            (ImportStatus::Invalid, Some("Secure note, not a login".to_string()), None)
+        } else if password.is_none() {
+            (ImportStatus::Invalid, Some("Missing password".to_string()), None)
+        } else {
+            let key = login_key(&credential);
+            match known.get(&key) {
+                Some(Known::Vault { item_id, password: vault_password }) => {
+                    let reason = if password == *vault_password {
+                        "Already in vault"
+                    } else {
+                        "Already in vault with a different password"
+                    };
+                    (ImportStatus::Duplicate, Some(reason.to_string()), Some(item_id.clone()))
+                }
+                Some(Known::Row(earlier)) => (ImportStatus::Duplicate, Some(format!("Same login as row {}", earlier)), None),
+                None => {
+                    known.insert(key, Known::Row(row));
+                    (ImportStatus::New, None, None)
+                }
+            }
+        };
+        entries.push(ImportEntry { row, name, uri, username, status, reason, existing_item_id, credential });
+    }
+
+    Ok((format, entries))
+}
+
+/// Login widziany wcześniej: w vault (z hasłem do porównania) albo we wcześniejszym wierszu pliku
+enum Known {
+    Vault { item_id: String, password: Option<SecretString> },
+    Row(usize),
+}
+
+/// Wiersze do zapisania: nowe (albo tylko wskazane w `rows`) oraz wskazane duplikaty
+pub fn selected<'a>(entries: &'a [ImportEntry], rows: Option<&[usize]>) -> Vec<&'a ImportEntry> {
+    entries
+        .iter()
+        .filter(|entry| match (entry.status, rows) {
+            (ImportStatus::Invalid, _) => false,
+            (ImportStatus::New, None) => true,
+            (_, Some(rows)) => rows.contains(&entry.row),
+            (ImportStatus::Duplicate, None) => false,
+        })
+        .collect()
+}
+
+/// Wynik zapisu jednego wiersza
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub row: usize,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Dodaje wybrane wiersze przez `add_credential`; błąd jednego wiersza nie przerywa pozostałych
+pub async fn import(bitwarden: &BitwardenManager, entries: &[&ImportEntry]) -> Vec<ImportResult> {
+    let mut results = Vec::new();
+    for entry in entries {
+        let result = match bitwarden.add_credential(&entry.credential).await {
+            Ok(item_id) => ImportResult { row: entry.row, name: entry.name.clone(), item_id: Some(item_id), error: None },
+            Err(e) => {
+                warn!(row = entry.row, "Failed to import credential {}: {}", entry.name, e);
+                ImportResult { row: entry.row, name: entry.name.clone(), item_id: None, error: Some(e.to_string()) }
+            }
+        };
+        results.push(result);
+    }
+    info!(
+        imported = results.iter().filter(|result| result.item_id.is_some()).count(),
+        failed = results.iter().filter(|result| result.error.is_some()).count(),
+        "Credential import finished"
+    );
+    results
+}
+
+/// Strona i login wyznaczające duplikat; bez adresu liczy się nazwa elementu
+fn login_key(credential: &BitwardenCredential) -> String {
+    let site = credential
+        .uri
+        .as_deref()
+        .and_then(site_of)
+        .unwrap_or_else(|| credential.name.trim().to_lowercase());
+    format!("{}\n{}", site, credential.username.as_deref().unwrap_or_default().trim().to_lowercase())
+}
+
+/// Rekordy CSV (RFC 4180): pola w cudzysłowach mogą zawierać przecinki, `""` i nowe linie
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (in_quotes, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => in_quotes = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => in_quotes = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') | (false, '\r') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if in_quotes {
+        bail!("CSV export ends inside a quoted field");
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_import_maps_exports_and_flags_duplicates() {
+        let vault = vec![BitwardenCredential {
+            id: "item-1".to_string(),
+            name: "GitHub".to_string(),
+            username: Some("jan@example.com".to_string()),
+            password: Some(SecretString::from("old-secret")),
+            uri: Some("https://github.com/login".to_string()),
+            notes: None,
+            folder_id: None,
+        }];
+
+        let lastpass = "url,username,password,totp,extra,name,grouping,fav\n\
+            https://www.github.com,Jan@example.com,new-secret,,,GitHub,Dev,0\n\
+            https://portal.example.com/hr,jkowalski,\"pa,ss\"\"word\",,\"line 1\nline 2\",Portal HR,Work,0\n\
+            http://sn,,,,note text,Wi-Fi,,0\n\
+            portal.example.com,jkowalski,other,,,Portal copy,,0\n";
+        let (format, entries) = plan_import(lastpass, None, &vault).unwrap();
+        assert_eq!(format, ImportFormat::LastPass);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].status, ImportStatus::Duplicate);
+        assert_eq!(entries[0].existing_item_id.as_deref(), Some("item-1"));
+        assert_eq!(entries[0].reason.as_deref(), Some("Already in vault with a different password"));
+        assert_eq!(entries[1].status, ImportStatus::New);
+        assert_eq!(entries[1].credential.password.as_ref().map(|password| password.expose_secret()), Some("pa,ss\"word"));
+        assert_eq!(entries[1].credential.notes.as_deref(), Some("line 1\nline 2"));
+        assert_eq!(entries[2].status, ImportStatus::Invalid);
+        assert_eq!(entries[3].status, ImportStatus::Duplicate);
+        assert_eq!(entries[3].existing_item_id, None);
+
+        // Podgląd nie zawiera haseł
+        let preview = serde_json::to_string(&entries).unwrap();
+        assert!(!preview.contains("new-secret") && !preview.contains("pa,ss"));
+
+        let rows: Vec<usize> = selected(&entries, None).iter().map(|entry| entry.row).collect();
+        assert_eq!(rows, vec![2]);
+        let rows: Vec<usize> = selected(&entries, Some(&[1, 3])).iter().map(|entry| entry.row).collect();
+        assert_eq!(rows, vec![1]);
+
+        let chrome = "name,url,username,password,note\r\nexample.com,https://example.com/,anna,s3cret,\r\n";
+        let (format, entries) = plan_import(chrome, None, &[]).unwrap();
+        assert_eq!(format, ImportFormat::Chrome);
+        assert_eq!((entries[0].name.as_str(), entries[0].status), ("example.com", ImportStatus::New));
+
+        let one_password = "Title,Url,Username,Password,OTPAuth,Favorite,Archived,Tags,Notes\n\
+            Bank,https://bank.example.pl,,hunter2,,false,false,,\n";
+        let (format, entries) = plan_import(one_password, None, &[]).unwrap();
+        assert_eq!(format, ImportFormat::OnePassword);
+        assert_eq!(entries[0].uri.as_deref(), Some("https://bank.example.pl"));
+
+        assert!(plan_import("id,secret\n1,2\n", None, &[]).is_err());
+        assert!(plan_import("name,url,password\n\"unterminated,x,y\n", None, &[]).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::{digest, pbkdf2};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::num::NonZeroU32;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// Prefiks wersji formatu, pozwala odróżnić zaszyfrowane wartości od starych danych jawnych
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+static CONTENT_CIPHER: OnceLock<ContentCipher> = OnceLock::new();
+static PREVIOUS_CIPHER: OnceLock<ContentCipher> = OnceLock::new();
+
+/// AES-256-GCM for content stored at rest (e.g. `dsl_cache.html_content`)
+pub struct ContentCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+    fingerprint: String,
+}
+
+impl ContentCipher {
+    /// Klucz wyprowadzany jest jako SHA-256 z sekretu ENCRYPTION_KEY
+    pub fn from_secret(secret: &str) -> Result<Self> {
+        if secret.trim().is_empty() {
+            bail!("Encryption key must not be empty");
+        }
+
+        let key_bytes = digest::digest(&digest::SHA256, secret.as_bytes());
+        Self::from_key(key_bytes.as_ref())
+    }
+
+    /// Klucz z hasła podanego przez użytkownika: PBKDF2-HMAC-SHA256 z losową solą zapisywaną obok szyfrogramu
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Self> {
+        if passphrase.is_empty() {
+            bail!("Passphrase must not be empty");
+        }
+        let iterations = NonZeroU32::new(iterations).ok_or_else(|| anyhow!("PBKDF2 iterations must be positive"))?;
+        let mut key_bytes = [0u8; 32];
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key_bytes);
+        Self::from_key(&key_bytes)
+    }
+
+    /// Losowa sól dla `from_passphrase`
+    pub fn random_salt() -> Result<[u8; 16]> {
+        let mut salt = [0u8; 16];
+        SystemRandom::new().fill(&mut salt).map_err(|_| anyhow!("Failed to generate salt"))?;
+        Ok(salt)
+    }
+
+    fn from_key(key_bytes: &[u8]) -> Result<Self> {
+        let unbound = UnboundKey::new(&AES_256_GCM, key_bytes)
+            .map_err(|_| anyhow!("Failed to create AES-256-GCM key"))?;
+
+        // Skrót klucza (nie sekretu) - identyfikuje klucz w postępie rotacji bez ujawniania go
+        let fingerprint: String = digest::digest(&digest::SHA256, key_bytes)
+            .as_ref()
+            .iter()
+            .take(8)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(Self { key: LessSafeKey::new(unbound), rng: SystemRandom::new(), fingerprint })
+    }
+
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Returns `enc:v1:` followed by base64 of nonce || ciphertext || tag
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce_bytes).map_err(|_| anyhow!("Failed to generate nonce"))?;
+
+        let mut in_out = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce_bytes), Aad::empty(), &mut in_out)
+            .map_err(|_| anyhow!("Encryption failed"))?;
+
+        let mut payload = nonce_bytes.to_vec();
+        payload.extend_from_slice(&in_out);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let encoded = stored.strip_prefix(ENCRYPTED_PREFIX).ok_or_else(|| anyhow!("Value is not encrypted"))?;
+        let payload = STANDARD.decode(encoded).map_err(|e| anyhow!("Invalid encrypted payload: {}", e))?;
+        if payload.len() < NONCE_LEN {
+            bail!("Encrypted payload is too short");
+        }
+
+        let (nonce_bytes, ciphertext) = payload.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| anyhow!("Invalid nonce"))?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut in_out)
+            .map_err(|_| anyhow!("Decryption failed (wrong key or corrupted data)"))?;
+
+        Ok(String::from_utf8(plaintext.to_vec())?)
+    }
+}
+
+/// Ustawia szyfr dla danych zapisywanych w bazie; wywoływane raz przy starcie.
+/// `previous` is the key being rotated away from: still readable, never used for writing.
+pub fn install(secret: &str, previous: Option<&str>) -> Result<()> {
+    let cipher = ContentCipher::from_secret(secret)?;
+    let previous = previous.map(ContentCipher::from_secret).transpose()?;
+
+    if CONTENT_CIPHER.set(cipher).is_ok() {
+        info!("Encryption of stored page content enabled");
+    }
+    if let Some(previous) = previous {
+        info!("Previous encryption key {} loaded for key rotation", previous.fingerprint());
+        let _ = PREVIOUS_CIPHER.set(previous);
+    }
+    Ok(())
+}
+
+/// Szyfr skonfigurowany przez `install`; `None`, gdy ENCRYPTION_KEY nie jest ustawiony
+pub fn content_cipher() -> Option<&'static ContentCipher> {
+    CONTENT_CIPHER.get()
+}
+
+/// Klucz sprzed rotacji (ENCRYPTION_KEY_PREVIOUS), jeśli skonfigurowany
+pub fn previous_cipher() -> Option<&'static ContentCipher> {
+    PREVIOUS_CIPHER.get()
+}
+
+/// Decrypts with the current key, falling back to the previous one during a rotation
+pub fn decrypt_any(stored: &str) -> Result<String> {
+    let current = content_cipher().ok_or_else(|| anyhow!("Encryption key is not configured"))?;
+    match (current.decrypt(stored), previous_cipher()) {
+        (Ok(plaintext), _) => Ok(plaintext),
+        (Err(_), Some(previous)) => previous.decrypt(stored),
+        (Err(e), None) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip_and_wrong_key() {
+        let cipher = ContentCipher::from_secret("test-secret").unwrap();
+        let encrypted = cipher.encrypt("<form id=\"apply\"></form>").unwrap();
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        assert!(!encrypted.contains("apply"));
+        // Losowy nonce - ten sam tekst daje różne szyfrogramy
+        assert_ne!(encrypted, cipher.encrypt("<form id=\"apply\"></form>").unwrap());
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "<form id=\"apply\"></form>");
+
+        let other = ContentCipher::from_secret("other-secret").unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+        assert!(cipher.decrypt("<form></form>").is_err());
+        assert!(ContentCipher::from_secret("  ").is_err());
+    }
+}
@@ -0,0 +1,525 @@
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn, debug};
+use chrono::{DateTime, Utc};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use codialog_types::SecretString;
+use crate::dom;
+use crate::tagui::{self, ExecutionResult, RunManager};
+
+/// Plik z numerem linii (i miejscem pauzy), na której TagUI czeka; istnieje tylko w trakcie pauzy
+const PAUSED_FILE: &str = "paused";
+
+/// Bramka zwalniająca bieżącą pauzę - TagUI usuwa ją po przejściu dalej
+const STEP_GATE: &str = "step";
+
+/// Dopóki plik istnieje, TagUI zatrzymuje się po każdej komendzie (tryb krokowy)
+const STEPPING_FILE: &str = "stepping";
+
+/// Jak często TagUI sprawdza bramki w trakcie pauzy (sekundy)
+const GATE_POLL_SECS: f64 = 0.2;
+
+/// Upper bound of the TagUI wait loop (about a day); in practice the run ends by idle watchdog or timeout
+const GATE_MAX_POLLS: u64 = 432_000;
+
+/// Pauza bez żadnego wywołania dłużej niż ten czas przerywa przebieg
+pub const DEBUG_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Domyślny limit czasu przebiegu w trybie krokowym (zamiast TAGUI_RUN_TIMEOUT_SECS)
+pub const DEBUG_RUN_TIMEOUT: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Zakończone sesje debugowania są dostępne jeszcze przez ten czas
+const FINISHED_RETENTION: chrono::Duration = chrono::Duration::hours(1);
+
+/// Miejsce pauzy względem komendy z linii `line`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PausePosition {
+    /// Breakpoint: komenda jeszcze się nie wykonała
+    Before,
+    /// Krok: komenda właśnie się wykonała
+    After,
+}
+
+impl PausePosition {
+    fn as_str(self) -> &'static str {
+        match self {
+            PausePosition::Before => "before",
+            PausePosition::After => "after",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "before" => Some(PausePosition::Before),
+            "after" => Some(PausePosition::After),
+            _ => None,
+        }
+    }
+}
+
+/// Komendy TagUI wstawiane przed komendą z linii `line`: pauza, gdy na linii jest breakpoint
+pub fn breakpoint_steps(dir: &Path, line: usize, loop_variables: &[String]) -> Vec<String> {
+    let breakpoint = tagui::escape_for_dsl(&dir.join(breakpoint_file_name(line)).display().to_string());
+    wait_steps(dir, &format!("fs.exists(\"{}\")", breakpoint), line, PausePosition::Before, loop_variables)
+}
+
+/// Komendy TagUI wstawiane po komendzie z linii `line`: pauza w trybie krokowym
+pub fn pause_steps(dir: &Path, line: usize, loop_variables: &[String]) -> Vec<String> {
+    let stepping = tagui::escape_for_dsl(&dir.join(STEPPING_FILE).display().to_string());
+    wait_steps(dir, &format!("fs.exists(\"{}\")", stepping), line, PausePosition::After, loop_variables)
+}
+
+/// Zapis URL, zrzutu i HTML strony oraz liczników pętli, sygnał pauzy i pętla czekająca na bramkę
+fn wait_steps(dir: &Path, condition: &str, line: usize, position: PausePosition, loop_variables: &[String]) -> Vec<String> {
+    let step_gate = tagui::escape_for_dsl(&dir.join(STEP_GATE).display().to_string());
+    let file = |extension: &str| dir.join(pause_file_name(line, position, extension)).display().to_string();
+
+    let mut steps = vec![
+        format!("if {}", condition),
+        "{".to_string(),
+        format!("  dump `url()` to {}", file("url")),
+        format!("  snap page to {}", file("png")),
+        "  dom return document.documentElement.outerHTML".to_string(),
+        format!("  dump `dom_result` to {}", file("html")),
+    ];
+    for (depth, variable) in loop_variables.iter().enumerate() {
+        steps.push(format!("  dump `{}` to {}", variable, file(&format!("loop{}", depth + 1))));
+    }
+    steps.extend([
+        format!("  dump {} {} to {}", line, position.as_str(), dir.join(PAUSED_FILE).display()),
+        format!("  for codialog_pause from 1 to {}", GATE_MAX_POLLS),
+        "  {".to_string(),
+        format!("    if fs.exists(\"{}\")", step_gate),
+        "    {".to_string(),
+        "      break".to_string(),
+        "    }".to_string(),
+        format!("    wait {}", GATE_POLL_SECS),
+        "  }".to_string(),
+        format!("  js if (fs.exists(\"{0}\")) fs.remove(\"{0}\");", step_gate),
+        "}".to_string(),
+    ]);
+    steps
+}
+
+fn breakpoint_file_name(line: usize) -> String {
+    format!("break-{:03}", line)
+}
+
+/// Pliki pauzy po komendzie mają nazwy jak zrzuty z `capture_screenshots` (`step-007.png`)
+fn pause_file_name(line: usize, position: PausePosition, extension: &str) -> String {
+    match position {
+        PausePosition::Before => format!("before-step-{:03}.{}", line, extension),
+        PausePosition::After => format!("step-{:03}.{}", line, extension),
+    }
+}
+
+/// Stan sesji debugowania widziany przez API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugState {
+    /// Komenda się wykonuje (albo TagUI jeszcze startuje)
+    Running,
+    Paused,
+    Finished,
+}
+
+/// Bieżący stan sesji: komenda, na której przebieg stoi, URL strony i zrzut ekranu
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugSnapshot {
+    pub debug_id: String,
+    /// Zadanie z kolejki, którego skrypt jest debugowany
+    #[serde(default)]
+    pub job_id: Option<String>,
+    pub state: DebugState,
+    /// 1-based line of the command the run is paused at
+    pub line: Option<usize>,
+    /// Przed komendą (breakpoint) albo po niej (krok)
+    #[serde(default)]
+    pub position: Option<PausePosition>,
+    /// Komenda z oryginalnego skryptu, przed podstawieniem zmiennych i sekretów
+    pub command: Option<String>,
+    pub url: Option<String>,
+    /// Zrzut strony w chwili pauzy, PNG w base64
+    pub screenshot: Option<String>,
+    /// Bieżąca iteracja każdej obejmującej pętli `repeat`/`for`, od zewnętrznej
+    #[serde(default)]
+    pub loop_iterations: Vec<u64>,
+    /// Zmienne podstawione w skrypcie; sekrety zamaskowane
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    #[serde(default)]
+    pub breakpoints: Vec<usize>,
+    /// Czy przebieg zatrzyma się po następnej komendzie
+    #[serde(default)]
+    pub stepping: bool,
+    pub result: Option<ExecutionResult>,
+}
+
+/// Wynik sprawdzenia selektora na stronie zapisanej w chwili pauzy
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectorInspection {
+    pub line: usize,
+    pub position: PausePosition,
+    #[serde(flatten)]
+    pub matches: dom::SelectorMatches,
+}
+
+/// Przebieg w trybie krokowym. TagUI and the API talk through marker files in a private
+/// directory: TagUI writes `paused` and waits, the API answers with a gate file. Breakpoints
+/// and step mode are marker files too, so they can change while the run is in progress.
+pub struct DebugSession {
+    id: String,
+    job_id: Option<String>,
+    dir: tempfile::TempDir,
+    commands: HashMap<usize, String>,
+    secrets: Vec<SecretString>,
+    variables: BTreeMap<String, String>,
+    breakpoints: Mutex<BTreeSet<usize>>,
+    started_at: DateTime<Utc>,
+    last_activity: Mutex<Instant>,
+    finished: Mutex<Option<(ExecutionResult, DateTime<Utc>)>>,
+}
+
+impl DebugSession {
+    pub fn new(script: &str, secrets: Vec<SecretString>) -> std::io::Result<Self> {
+        let dir = tempfile::Builder::new().prefix("codialog-debug-").tempdir()?;
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            job_id: None,
+            dir,
+            commands: tagui::script_commands(script).into_iter().collect(),
+            secrets,
+            variables: BTreeMap::new(),
+            breakpoints: Mutex::new(BTreeSet::new()),
+            started_at: Utc::now(),
+            last_activity: Mutex::new(Instant::now()),
+            finished: Mutex::new(None),
+        })
+    }
+
+    /// Sesja dla zadania z kolejki (`/rpa/jobs/:id/debug`)
+    pub fn for_job(mut self, job_id: impl Into<String>) -> Self {
+        self.job_id = Some(job_id.into());
+        self
+    }
+
+    /// Zmienne pokazywane w snapshotach; wartości sekretów są maskowane
+    pub fn with_variables(mut self, variables: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.variables = variables
+            .into_iter()
+            .map(|(name, value)| {
+                let value = tagui::mask_secret_values(&value, &self.secrets);
+                (name, value)
+            })
+            .collect();
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn job_id(&self) -> Option<&str> {
+        self.job_id.as_deref()
+    }
+
+    /// Katalog przekazywany jako `RunOptions::debug_dir`
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    fn paused_at(&self) -> Option<(usize, PausePosition)> {
+        let paused = fs::read_to_string(self.dir.path().join(PAUSED_FILE)).ok()?;
+        let mut parts = paused.split_whitespace();
+        let line = parts.next()?.parse().ok()?;
+        let position = parts.next().and_then(PausePosition::parse).unwrap_or(PausePosition::After);
+        Some((line, position))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.lock().unwrap().is_some()
+    }
+
+    fn is_stepping(&self) -> bool {
+        self.dir.path().join(STEPPING_FILE).exists()
+    }
+
+    fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    pub fn snapshot(&self) -> DebugSnapshot {
+        let mask = |text: String| tagui::mask_secret_values(text.trim(), &self.secrets);
+        let mut snapshot = DebugSnapshot {
+            debug_id: self.id.clone(),
+            job_id: self.job_id.clone(),
+            state: DebugState::Running,
+            line: None,
+            position: None,
+            command: None,
+            url: None,
+            screenshot: None,
+            loop_iterations: Vec::new(),
+            variables: self.variables.clone(),
+            breakpoints: self.breakpoints.lock().unwrap().iter().copied().collect(),
+            stepping: self.is_stepping(),
+            result: None,
+        };
+
+        if let Some((result, _)) = self.finished.lock().unwrap().as_ref() {
+            snapshot.state = DebugState::Finished;
+            snapshot.result = Some(result.clone());
+            return snapshot;
+        }
+
+        if let Some((line, position)) = self.paused_at() {
+            let file = |extension: &str| self.dir.path().join(pause_file_name(line, position, extension));
+            snapshot.state = DebugState::Paused;
+            snapshot.line = Some(line);
+            snapshot.position = Some(position);
+            snapshot.command = self.commands.get(&line).cloned();
+            snapshot.url = fs::read_to_string(file("url")).ok().map(mask);
+            snapshot.screenshot = fs::read(file("png")).ok().map(|png| STANDARD.encode(png));
+            snapshot.loop_iterations = (1..)
+                .map_while(|depth| fs::read_to_string(file(&format!("loop{}", depth))).ok())
+                .filter_map(|iteration| iteration.trim().parse().ok())
+                .collect();
+        }
+        snapshot
+    }
+
+    /// Linie, na których nie można postawić breakpointu (puste, komentarze, nagłówki bloków)
+    pub fn invalid_breakpoints(&self, lines: &[usize]) -> Vec<usize> {
+        lines
+            .iter()
+            .copied()
+            .filter(|line| self.commands.get(line).map(|command| tagui::is_block_keyword(command)).unwrap_or(true))
+            .collect()
+    }
+
+    /// Zastępuje zbiór breakpointów; działa także w trakcie przebiegu
+    pub fn set_breakpoints(&self, lines: &[usize]) -> std::io::Result<()> {
+        self.touch();
+        let mut breakpoints = self.breakpoints.lock().unwrap();
+        for line in breakpoints.iter() {
+            remove_if_exists(&self.dir.path().join(breakpoint_file_name(*line)))?;
+        }
+        breakpoints.clear();
+        for line in lines {
+            fs::write(self.dir.path().join(breakpoint_file_name(*line)), b"")?;
+            breakpoints.insert(*line);
+        }
+        debug!(debug_id = %self.id, breakpoints = ?breakpoints, "Debug breakpoints updated");
+        Ok(())
+    }
+
+    /// Zatrzymuje przebieg po najbliższej wykonanej komendzie
+    pub fn pause(&self) -> std::io::Result<()> {
+        self.touch();
+        fs::write(self.dir.path().join(STEPPING_FILE), b"")?;
+        debug!(debug_id = %self.id, "Debug pause requested");
+        Ok(())
+    }
+
+    /// Wykonuje następną komendę i zatrzymuje się po niej; false, gdy przebieg akurat nie czeka
+    pub fn step(&self) -> std::io::Result<bool> {
+        self.touch();
+        if self.is_finished() || self.paused_at().is_none() {
+            return Ok(false);
+        }
+        fs::write(self.dir.path().join(STEPPING_FILE), b"")?;
+        self.release()?;
+        debug!(debug_id = %self.id, "Debug step requested");
+        Ok(true)
+    }
+
+    /// Wyłącza tryb krokowy i zwalnia bieżącą pauzę - przebieg biegnie do następnego breakpointu
+    pub fn resume(&self) -> std::io::Result<()> {
+        self.touch();
+        remove_if_exists(&self.dir.path().join(STEPPING_FILE))?;
+        if self.paused_at().is_some() {
+            self.release()?;
+        }
+        info!(debug_id = %self.id, "Debug session continues to the next breakpoint");
+        Ok(())
+    }
+
+    fn release(&self) -> std::io::Result<()> {
+        fs::remove_file(self.dir.path().join(PAUSED_FILE))?;
+        fs::write(self.dir.path().join(STEP_GATE), b"")
+    }
+
+    /// Sprawdza selektor CSS na stronie zapisanej w chwili bieżącej pauzy; `None`, gdy przebieg nie czeka
+    pub fn inspect(&self, selector: &str) -> Option<anyhow::Result<SelectorInspection>> {
+        self.touch();
+        let (line, position) = self.paused_at()?;
+        let html = fs::read_to_string(self.dir.path().join(pause_file_name(line, position, "html")));
+        Some(
+            html.map_err(anyhow::Error::from)
+                .and_then(|html| dom::select(&html, selector))
+                .map(|mut matches| {
+                    for element in &mut matches.elements {
+                        element.text = tagui::mask_secret_values(&element.text, &self.secrets);
+                        element.html = tagui::mask_secret_values(&element.html, &self.secrets);
+                    }
+                    SelectorInspection { line, position, matches }
+                }),
+        )
+    }
+
+    pub fn finish(&self, result: ExecutionResult) {
+        *self.finished.lock().unwrap() = Some((result, Utc::now()));
+    }
+
+    /// Czeka, aż przebieg zatrzyma się na kolejnej pauzie lub się zakończy
+    pub async fn wait_for_pause(&self, timeout: Duration) -> DebugSnapshot {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let snapshot = self.snapshot();
+            if snapshot.state != DebugState::Running || Instant::now() >= deadline {
+                return snapshot;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Przerywa przebieg porzucony w pauzie (nikt nie woła step/continue)
+    pub async fn watch_idle(self: Arc<Self>, run_manager: Arc<RunManager>, idle_timeout: Duration) {
+        loop {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            if self.is_finished() {
+                return;
+            }
+            let idle = self.last_activity.lock().unwrap().elapsed();
+            if self.paused_at().is_some() && idle > idle_timeout {
+                warn!(debug_id = %self.id, "Debug session idle for {}s, cancelling run", idle.as_secs());
+                run_manager.cancel(&self.id);
+                return;
+            }
+        }
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Rejestr sesji debugowania
+#[derive(Default)]
+pub struct DebugManager {
+    sessions: Mutex<HashMap<String, Arc<DebugSession>>>,
+}
+
+impl DebugManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, session: Arc<DebugSession>) {
+        let cutoff = Utc::now() - FINISHED_RETENTION;
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| {
+            session.finished.lock().unwrap().as_ref().map(|(_, finished_at)| *finished_at > cutoff).unwrap_or(true)
+        });
+        sessions.insert(session.id.clone(), session);
+    }
+
+    pub fn get(&self, debug_id: &str) -> Option<Arc<DebugSession>> {
+        self.sessions.lock().unwrap().get(debug_id).cloned()
+    }
+
+    /// Najnowsza sesja debugowania zadania
+    pub fn for_job(&self, job_id: &str) -> Option<Arc<DebugSession>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| session.job_id() == Some(job_id))
+            .max_by_key(|session| session.started_at)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_protocol() {
+        let session = DebugSession::new("type \"#user\" \"{{login}}\"\nclick \"#next\"", vec![SecretString::from("s3cret")]).unwrap();
+        let steps = pause_steps(session.dir(), 2, &[]);
+        assert!(steps.iter().any(|step| step.ends_with("step-002.png")));
+        assert!(steps.iter().any(|step| step.starts_with("  dump 2 after to ") && step.ends_with(PAUSED_FILE)));
+
+        // Zanim TagUI zgłosi pauzę, nie ma czego wykonać
+        assert_eq!(session.snapshot().state, DebugState::Running);
+        assert!(!session.step().unwrap());
+
+        fs::write(session.dir().join(pause_file_name(1, PausePosition::After, "url")), "https://example.com/?token=s3cret\n").unwrap();
+        fs::write(session.dir().join(PAUSED_FILE), "1 after").unwrap();
+        let snapshot = session.snapshot();
+        assert_eq!(snapshot.state, DebugState::Paused);
+        assert_eq!(snapshot.position, Some(PausePosition::After));
+        assert_eq!(snapshot.command.as_deref(), Some("type \"#user\" \"{{login}}\""));
+        assert_eq!(snapshot.url.as_deref(), Some("https://example.com/?token=********"));
+
+        assert!(session.step().unwrap());
+        assert!(session.dir().join(STEP_GATE).exists());
+        assert!(session.is_stepping());
+        assert_eq!(session.snapshot().state, DebugState::Running);
+    }
+
+    #[test]
+    fn test_breakpoints_and_inspection() {
+        let script = "open \"https://example.com\"\nrepeat 3\n  type \"#query\" \"{{term}}\"\nend\nclick \".submit\"";
+        let session = DebugSession::new(script, vec![SecretString::from("s3cret")])
+            .unwrap()
+            .for_job("job-1")
+            .with_variables([("term".to_string(), "s3cret".to_string())]);
+
+        let steps = breakpoint_steps(session.dir(), 3, &["codialog_i1".to_string()]);
+        assert!(steps[0].contains("break-003"));
+        assert!(steps.iter().any(|step| step.starts_with("  dump `codialog_i1` to ") && step.ends_with("before-step-003.loop1")));
+
+        // Nagłówki bloków, `end` i linie spoza skryptu nie są komendami
+        assert_eq!(session.invalid_breakpoints(&[2, 3, 4, 9]), vec![2, 4, 9]);
+        session.set_breakpoints(&[3, 5]).unwrap();
+        assert!(session.dir().join(breakpoint_file_name(5)).exists());
+        session.set_breakpoints(&[3]).unwrap();
+        assert!(!session.dir().join(breakpoint_file_name(5)).exists());
+        assert!(session.inspect("#query").is_none());
+
+        let file = |extension: &str| session.dir().join(pause_file_name(3, PausePosition::Before, extension));
+        fs::write(file("html"), "<form><input id=\"query\" value=\"s3cret\"><button class=\"submit\">Go</button></form>").unwrap();
+        fs::write(file("loop1"), "2\n").unwrap();
+        fs::write(session.dir().join(PAUSED_FILE), "3 before\n").unwrap();
+
+        let snapshot = session.snapshot();
+        assert_eq!(snapshot.job_id.as_deref(), Some("job-1"));
+        assert_eq!((snapshot.line, snapshot.position), (Some(3), Some(PausePosition::Before)));
+        assert_eq!(snapshot.loop_iterations, vec![2]);
+        assert_eq!(snapshot.breakpoints, vec![3]);
+        assert_eq!(snapshot.variables["term"], "********");
+
+        let inspection = session.inspect("#query").unwrap().unwrap();
+        assert_eq!(inspection.matches.count, 1);
+        assert!(!inspection.matches.elements[0].html.contains("s3cret"));
+        assert!(session.inspect("//button").unwrap().is_err());
+
+        // Kontynuacja wyłącza tryb krokowy i zwalnia pauzę
+        session.pause().unwrap();
+        session.resume().unwrap();
+        assert!(!session.is_stepping());
+        assert!(session.dir().join(STEP_GATE).exists());
+        assert_eq!(session.snapshot().state, DebugState::Running);
+    }
+}
@@ -0,0 +1,633 @@
+//! Pola formularzy odczytane z drzewa DOM (scraper/html5ever) zamiast wyszukiwania tekstu w liniach HTML.
+//! Handles tags split across lines, single-quoted and unquoted attributes, labels wrapping
+//! their control and the options of `<select>` lists.
+
+use anyhow::{anyhow, Result};
+use ring::digest;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::cdp;
+
+/// Elementy, których tekst nie należy do etykiety obejmującej pole
+const NON_LABEL_TEXT: &[&str] = &["select", "textarea", "option", "button", "script", "style"];
+
+/// Ile dopasowań `select` opisuje szczegółowo
+const MAX_MATCHED_ELEMENTS: usize = 5;
+
+/// Długość tekstu i HTML elementu w wyniku `select` (w znakach)
+const MATCH_PREVIEW_CHARS: usize = 300;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelectOption {
+    pub value: String,
+    pub text: String,
+}
+
+/// Kontrolka formularza: `input` (poza hidden), `button`, `select` lub `textarea`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormField {
+    pub tag: String,
+    /// Typ `input`/`button` małymi literami (domyślnie `text` / `submit`); `None` dla select i textarea
+    pub element_type: Option<String>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub classes: Vec<String>,
+    /// Tekst `<label for>`, etykiety obejmującej pole, `aria-label` albo `placeholder`
+    pub label: Option<String>,
+    /// Tekst przycisku (lub `value` dla `input type="submit"`)
+    pub text: Option<String>,
+    pub options: Vec<SelectOption>,
+    /// Ramka iframe (`cdp::FRAME_TAG`), w której leży pole; `None` dla dokumentu głównego
+    pub frame: Option<String>,
+    /// Numer kroku formularza wieloetapowego (kontener z `data-step` lub klasą kroku), od zera
+    pub step: Option<usize>,
+    /// Atrybut `required`, `aria-required="true"` albo etykieta oznaczona gwiazdką
+    pub required: bool,
+    /// Atrybut `value` pól radio i checkbox - wartość wysyłana po zaznaczeniu
+    pub value: Option<String>,
+    pub placeholder: Option<String>,
+    /// Atrybut `maxlength` (w znakach)
+    pub max_length: Option<usize>,
+    /// Wyrażenie regularne z atrybutu `pattern`
+    pub pattern: Option<String>,
+    /// Rola i nazwa dostępna z drzewa dostępności strony (tylko dokument główny pobrany przez CDP)
+    pub accessible: Option<cdp::AccessibleElement>,
+    /// Pytanie grupy pól: `<legend>` obejmującego `fieldset` albo nazwa (`aria-labelledby`, `aria-label`)
+    /// kontenera `role="radiogroup"` / `role="group"`
+    pub group_label: Option<String>,
+    /// Numer `<form>` w ramce, do którego należy pole (atrybut `form` albo formularz obejmujący), od zera
+    pub form: Option<usize>,
+}
+
+impl FormField {
+    /// Najstabilniejszy selektor pola: id, potem name, XPath z roli i nazwy dostępnej, na końcu tag z typem
+    pub fn selector(&self) -> String {
+        self.selectors().into_iter().next().unwrap_or_else(|| match &self.element_type {
+            Some(element_type) if self.tag == "input" => format!("input[type=\"{}\"]", css_string(element_type)),
+            _ => self.tag.clone(),
+        })
+    }
+
+    /// Selektory po id, name i pierwszej klasie, w tej kolejności. Pole bez `name` i bez id albo
+    /// z wygenerowanym id (`:r3:`, `ember123`) ma przed nimi XPath z drzewa dostępności, jeśli je znamy
+    pub fn selectors(&self) -> Vec<String> {
+        let mut selectors = Vec::new();
+        if self.name.is_none() && self.id.as_deref().is_none_or(is_generated_id) {
+            selectors.extend(self.accessible.as_ref().map(cdp::AccessibleElement::xpath));
+        }
+        if let Some(id) = &self.id {
+            selectors.push(if is_css_identifier(id) { format!("#{}", id) } else { format!("[id=\"{}\"]", css_string(id)) });
+        }
+        if let Some(name) = &self.name {
+            selectors.push(format!("[name=\"{}\"]", css_string(name)));
+        }
+        if let Some(class) = self.classes.first().filter(|class| is_css_identifier(class)) {
+            selectors.push(format!(".{}", class));
+        }
+        selectors
+    }
+}
+
+/// Wartość atrybutu do selektora `[attr="..."]`: `\` i `"` poprzedzone `\`
+fn css_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Pola formularzy strony i jej ramek (HTML z `cdp::get_page_html`), w kolejności dokumentu
+pub fn form_fields(html: &str) -> Vec<FormField> {
+    let controls = Selector::parse("input, button, select, textarea").expect("valid selector");
+    let option = Selector::parse("option").expect("valid selector");
+    let accessible = cdp::accessible_elements_of(html);
+    let mut fields = Vec::new();
+    let mut steps_before = 0;
+
+    for (frame, document) in cdp::split_frames(html) {
+        let document = Html::parse_document(&document);
+        let forms: Vec<ElementRef> = document.select(&Selector::parse("form").expect("valid selector")).collect();
+        let mut step_containers = Vec::new();
+        let mut control = 0;
+        for element in document.select(&controls) {
+            let attr = |name: &str| element.value().attr(name).map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+            let tag = element.value().name().to_string();
+            let element_type = match tag.as_str() {
+                "input" => Some(attr("type").map(|t| t.to_lowercase()).unwrap_or_else(|| "text".to_string())),
+                "button" => Some(attr("type").map(|t| t.to_lowercase()).unwrap_or_else(|| "submit".to_string())),
+                _ => None,
+            };
+            if element_type.as_deref() == Some("hidden") {
+                continue;
+            }
+            let accessible = accessible.iter().find(|accessible| frame.is_none() && accessible.control == Some(control)).cloned();
+            control += 1;
+
+            let text = match tag.as_str() {
+                "button" => Some(normalize(&element.text().collect::<String>())).filter(|text| !text.is_empty()),
+                "input" if matches!(element_type.as_deref(), Some("submit" | "button" | "reset")) => attr("value"),
+                _ => None,
+            };
+            let options = if tag == "select" {
+                element
+                    .select(&option)
+                    .map(|option| {
+                        let text = normalize(&option.text().collect::<String>());
+                        let value = option.value().attr("value").map(str::to_string).unwrap_or_else(|| text.clone());
+                        SelectOption { value, text }
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let step = element.ancestors().filter_map(ElementRef::wrap).find(|ancestor| is_step_container(*ancestor)).map(|container| {
+                match step_containers.iter().position(|id| *id == container.id()) {
+                    Some(index) => steps_before + index,
+                    None => {
+                        step_containers.push(container.id());
+                        steps_before + step_containers.len() - 1
+                    }
+                }
+            });
+
+            let value = match element_type.as_deref() {
+                Some("radio" | "checkbox") => Some(element.value().attr("value").unwrap_or("on").to_string()),
+                _ => None,
+            };
+            let label = label_of(&document, element).or_else(|| accessible.as_ref().map(|accessible| accessible.name.clone()));
+            let required = element.value().attr("required").is_some()
+                || attr("aria-required").is_some_and(|value| value.eq_ignore_ascii_case("true"))
+                || label.as_deref().is_some_and(|label| label.ends_with('*'));
+            fields.push(FormField {
+                label,
+                id: attr("id"),
+                name: attr("name"),
+                classes: element.value().classes().map(str::to_string).collect(),
+                tag,
+                element_type,
+                text,
+                options,
+                frame: frame.clone(),
+                step,
+                required,
+                value,
+                placeholder: element.value().attr("placeholder").map(normalize).filter(|text| !text.is_empty()),
+                max_length: attr("maxlength").and_then(|length| length.parse().ok()),
+                pattern: attr("pattern"),
+                accessible,
+                group_label: group_label_of(&document, element),
+                form: form_of(&forms, element),
+            });
+        }
+        steps_before += step_containers.len();
+    }
+    fields
+}
+
+/// Pola radio o tej samej nazwie (w tej samej ramce i formularzu) scalone w jedno pole z selektorem `[name]`,
+/// którego opcjami są wartości przycisków, a tekstem opcji ich etykiety; etykietą grupy jest jej pytanie
+pub fn group_radios(fields: Vec<FormField>) -> Vec<FormField> {
+    let mut grouped: Vec<FormField> = Vec::with_capacity(fields.len());
+    for field in fields {
+        let is_radio = field.element_type.as_deref() == Some("radio") && field.name.is_some();
+        let option = || {
+            let value = field.value.clone().unwrap_or_default();
+            SelectOption { text: field.label.clone().unwrap_or_else(|| value.clone()), value }
+        };
+        if !is_radio {
+            grouped.push(field);
+            continue;
+        }
+        match grouped.iter_mut().find(|group| {
+            group.element_type.as_deref() == Some("radio")
+                && group.name == field.name
+                && group.frame == field.frame
+                && group.form == field.form
+        }) {
+            Some(group) => {
+                group.options.push(option());
+                group.required |= field.required;
+            }
+            None => {
+                let options = vec![option()];
+                let label = field.group_label.clone();
+                grouped.push(FormField { id: None, classes: Vec::new(), label, value: None, options, ..field });
+            }
+        }
+    }
+    grouped
+}
+
+/// Kontener jednego kroku kreatora: `data-step` albo klasa `step`, `form-step`, `step-2`...
+fn is_step_container(element: ElementRef) -> bool {
+    element.value().attr("data-step").is_some()
+        || element.value().classes().any(|class| {
+            let class = class.to_lowercase();
+            class == "step" || class.ends_with("-step") || (class.starts_with("step-") && class[5..].chars().all(|c| c.is_ascii_digit()))
+        })
+}
+
+/// Czy strona pokazuje postęp formularza wieloetapowego: `role="progressbar"`, stepper albo kilka kontenerów kroków.
+/// Samo `<progress>` czy klasa `progress` to zwykle pasek wysyłania pliku lub ładowania, więc się nie liczy.
+pub fn has_progress_indicator(html: &str) -> bool {
+    let indicators = Selector::parse("[role=\"progressbar\"], [aria-current=\"step\"], [class*=\"stepper\"], [class*=\"wizard-nav\"]")
+        .expect("valid selector");
+    let any = Selector::parse("*").expect("valid selector");
+    cdp::split_frames(html).into_iter().any(|(_, document)| {
+        let document = Html::parse_document(&document);
+        document.select(&indicators).next().is_some() || document.select(&any).filter(|element| is_step_container(*element)).nth(1).is_some()
+    })
+}
+
+/// Elementy tworzące szkielet formularza w `structure_signature`
+const SKELETON_TAGS: &[&str] = &["form", "fieldset", "input", "select", "option", "textarea", "button"];
+
+/// Skrót SHA-256 (hex) szkieletu formularzy razem z id, etykietami i wartościami opcji kontrolek.
+/// Szerszy niż klucz cache (`structure_signature`), więc wykrywa przebudowę strony o tym samym kluczu
+/// - zmienione id lub etykiety, na które wskazują selektory zapisanego skryptu
+pub fn structure_fingerprint(html: &str) -> String {
+    let fingerprint = format!("{}|{}", structure_signature(html), label_signature(html));
+    digest::digest(&digest::SHA256, fingerprint.as_bytes()).as_ref().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Id, etykiety, nazwy dostępne i wartości opcji kontrolek w kolejności dokumentu
+fn label_signature(html: &str) -> String {
+    let labelled = Selector::parse("label, input, select, textarea, button, option").expect("valid selector");
+    let mut signature = String::new();
+    for (_, document) in cdp::split_frames(html) {
+        for element in Html::parse_document(&document).select(&labelled) {
+            let value = element.value();
+            if value.attr("type").is_some_and(|element_type| element_type.eq_ignore_ascii_case("hidden")) {
+                continue;
+            }
+            signature.push_str(value.name());
+            for attr in ["id", "for", "aria-label", "placeholder", "value"] {
+                if let Some(attr_value) = value.attr(attr).map(str::trim).filter(|attr_value| !attr_value.is_empty()) {
+                    // Wartości wpisane w pola tekstowe nie są częścią struktury
+                    if attr == "value" && value.name() == "input" {
+                        continue;
+                    }
+                    if (attr == "id" || attr == "for") && is_generated_id(attr_value) {
+                        continue;
+                    }
+                    signature.push_str(&format!(" {}={}", attr, attr_value));
+                }
+            }
+            if matches!(value.name(), "label" | "button") {
+                signature.push_str(&format!(" \"{}\"", element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")));
+            }
+            signature.push(';');
+        }
+    }
+    signature
+}
+
+/// Szkielet formularzy strony: drzewo tag/type/name/id kontrolek i ich kontenerów, zbudowane parserem.
+/// Pomija tekst, klasy, style, wartości, elementy-opakowania i `id` wyglądające na generowane,
+/// więc kosmetyczne zmiany HTML nie zmieniają wyniku, a zmiana id, na które wskazują selektory - tak.
+pub fn structure_signature(html: &str) -> String {
+    let mut signature = String::new();
+    for (frame, document) in cdp::split_frames(html) {
+        if let Some(frame) = frame {
+            signature.push_str(&format!("frame({})", frame));
+        }
+        signature.push('[');
+        skeleton_of(Html::parse_document(&document).root_element(), &mut signature);
+        signature.push(']');
+    }
+    signature
+}
+
+fn skeleton_of(element: ElementRef, signature: &mut String) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let value = child.value();
+        let tag = value.name();
+        if !SKELETON_TAGS.contains(&tag) {
+            skeleton_of(child, signature);
+            continue;
+        }
+        let attr = |name: &str| value.attr(name).map(str::trim).filter(|value| !value.is_empty());
+        let element_type = attr("type").map(str::to_lowercase);
+        if tag == "input" && element_type.as_deref() == Some("hidden") {
+            continue;
+        }
+
+        signature.push_str(tag);
+        if let Some(element_type) = element_type {
+            signature.push_str(&format!(":{}", element_type));
+        }
+        if let Some(name) = attr("name") {
+            signature.push_str(&format!("@{}", name));
+        }
+        if let Some(id) = attr("id").filter(|id| !is_generated_id(id)) {
+            signature.push_str(&format!("#{}", id));
+        }
+        if tag == "option" {
+            // Wartość opcji decyduje o skrypcie `select`, tekst opcji już nie
+            signature.push_str(&format!("={}", attr("value").unwrap_or_default()));
+        }
+        signature.push('(');
+        skeleton_of(child, signature);
+        signature.push(')');
+    }
+}
+
+/// Id nadawane przez frameworki przy każdym renderze: `:r1:`, `ember123`, `field-8f3a9c2e`, `input_1694012345`
+pub(crate) fn is_generated_id(id: &str) -> bool {
+    let mut digits = 0;
+    let mut longest_digits = 0;
+    for c in id.chars() {
+        digits = if c.is_ascii_digit() { digits + 1 } else { 0 };
+        longest_digits = longest_digits.max(digits);
+    }
+    let hex_segment = id
+        .split(['-', '_', ':'])
+        .any(|part| part.len() >= 8 && part.chars().all(|c| c.is_ascii_hexdigit()) && part.chars().any(|c| c.is_ascii_digit()));
+    id.starts_with(':') || longest_digits >= 3 || hex_segment
+}
+
+/// Element dopasowany przez `select`, z tekstem i HTML skróconymi do `MATCH_PREVIEW_CHARS`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchedElement {
+    pub tag: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    pub text: String,
+    pub html: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelectorMatches {
+    pub selector: String,
+    pub count: usize,
+    /// Pierwsze `MAX_MATCHED_ELEMENTS` dopasowań w kolejności dokumentu
+    pub elements: Vec<MatchedElement>,
+}
+
+/// Dopasowuje selektor CSS do dokumentu HTML (np. strony zapisanej w pauzie debuggera)
+pub fn select(html: &str, selector: &str) -> Result<SelectorMatches> {
+    let parsed = Selector::parse(selector.trim()).map_err(|e| anyhow!("Invalid CSS selector '{}': {}", selector, e))?;
+    let document = Html::parse_document(html);
+    let preview = |text: &str| text.chars().take(MATCH_PREVIEW_CHARS).collect::<String>();
+
+    let mut count = 0;
+    let mut elements = Vec::new();
+    for element in document.select(&parsed) {
+        count += 1;
+        if elements.len() < MAX_MATCHED_ELEMENTS {
+            elements.push(MatchedElement {
+                tag: element.value().name().to_string(),
+                id: element.value().id().map(str::to_string),
+                classes: element.value().classes().map(str::to_string).collect(),
+                text: preview(&normalize(&element.text().collect::<String>())),
+                html: preview(&element.html()),
+            });
+        }
+    }
+    Ok(SelectorMatches { selector: selector.to_string(), count, elements })
+}
+
+/// Pozycja pierwszego elementu każdego selektora CSS w kolejności dokumentu; `None` dla selektora
+/// bez dopasowania lub niepoprawnego (np. XPath)
+pub fn document_positions(html: &str, selectors: &[&str]) -> Vec<Option<usize>> {
+    let document = Html::parse_document(html);
+    let order: HashMap<_, usize> = document.root_element().descendants().enumerate().map(|(position, node)| (node.id(), position)).collect();
+    selectors
+        .iter()
+        .map(|selector| {
+            let parsed = Selector::parse(selector.trim()).ok()?;
+            let element = document.select(&parsed).next()?;
+            order.get(&element.id()).copied()
+        })
+        .collect()
+}
+
+fn label_of(document: &Html, element: ElementRef) -> Option<String> {
+    let by_for = element.value().id().and_then(|id| {
+        let selector = Selector::parse(&format!("label[for=\"{}\"]", id.replace('\\', "\\\\").replace('"', "\\\""))).ok()?;
+        document.select(&selector).next()
+    });
+    let wrapping = || element.ancestors().filter_map(ElementRef::wrap).find(|ancestor| ancestor.value().name() == "label");
+
+    by_for
+        .or_else(wrapping)
+        .map(|label| {
+            let mut text = String::new();
+            label_text(label, &mut text);
+            normalize(&text)
+        })
+        .filter(|text| !text.is_empty())
+        .or_else(|| {
+            ["aria-label", "placeholder"]
+                .iter()
+                .find_map(|name| element.value().attr(name).map(normalize).filter(|text| !text.is_empty()))
+        })
+}
+
+/// Pytanie grupy, w której leży pole: najbliższy `fieldset` z `<legend>` albo kontener grupy z nazwą
+fn group_label_of(document: &Html, element: ElementRef) -> Option<String> {
+    let text_of = |element: ElementRef| {
+        let mut text = String::new();
+        label_text(element, &mut text);
+        Some(normalize(&text)).filter(|text| !text.is_empty())
+    };
+    element.ancestors().filter_map(ElementRef::wrap).find_map(|ancestor| {
+        let value = ancestor.value();
+        if value.name() == "fieldset" {
+            return ancestor
+                .children()
+                .filter_map(ElementRef::wrap)
+                .find(|child| child.value().name() == "legend")
+                .and_then(text_of);
+        }
+        if !matches!(value.attr("role"), Some("radiogroup" | "group")) {
+            return None;
+        }
+        let labelled_by = value.attr("aria-labelledby").and_then(|ids| {
+            let parts: Vec<String> = ids
+                .split_whitespace()
+                .filter_map(|id| {
+                    let selector = Selector::parse(&format!("[id=\"{}\"]", css_string(id))).ok()?;
+                    document.select(&selector).next().and_then(text_of)
+                })
+                .collect();
+            Some(parts.join(" ")).filter(|text| !text.is_empty())
+        });
+        labelled_by.or_else(|| value.attr("aria-label").map(normalize).filter(|text| !text.is_empty()))
+    })
+}
+
+/// Formularz pola: wskazany atrybutem `form` albo obejmujący je `<form>`
+fn form_of(forms: &[ElementRef], element: ElementRef) -> Option<usize> {
+    if let Some(form_id) = element.value().attr("form") {
+        return forms.iter().position(|form| form.value().id() == Some(form_id));
+    }
+    element.ancestors().find_map(|ancestor| forms.iter().position(|form| form.id() == ancestor.id()))
+}
+
+fn label_text(element: ElementRef, text: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(content) => {
+                text.push_str(content);
+                text.push(' ');
+            }
+            Node::Element(child_element) if !NON_LABEL_TEXT.contains(&child_element.name()) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    label_text(child, text);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Identyfikator używalny bez cudzysłowów w `#id` / `.klasa`
+fn is_css_identifier(value: &str) -> bool {
+    value.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_fields_from_real_markup() {
+        let html = r#"
+            <form>
+                <label for='first-name'>First
+                    name</label>
+                <input
+                    id='first-name'
+                    name=first_name
+                    class="form-control wide">
+                <input type="hidden" name="_csrf" value="token">
+                <label>Country
+                    <select id="country"><option value="pl">Poland</option><option>Germany</option></select>
+                </label>
+                <textarea name="cover_letter" placeholder="Why us?"></textarea>
+                <input id="user.email" type="EMAIL" aria-label="E-mail">
+                <button class="btn primary"><span>Apply</span> now</button>
+            </form>
+        "#;
+
+        let fields = form_fields(html);
+        assert_eq!(fields.len(), 5);
+
+        assert_eq!(fields[0].element_type.as_deref(), Some("text"));
+        assert_eq!(fields[0].label.as_deref(), Some("First name"));
+        assert_eq!(fields[0].selectors(), vec!["#first-name", "[name=\"first_name\"]", ".form-control"]);
+
+        assert_eq!(fields[1].label.as_deref(), Some("Country"));
+        assert_eq!(
+            fields[1].options,
+            vec![
+                SelectOption { value: "pl".to_string(), text: "Poland".to_string() },
+                SelectOption { value: "Germany".to_string(), text: "Germany".to_string() },
+            ]
+        );
+
+        assert_eq!(fields[2].tag, "textarea");
+        assert_eq!(fields[2].label.as_deref(), Some("Why us?"));
+        assert_eq!(fields[2].selector(), "[name=\"cover_letter\"]");
+
+        assert_eq!(fields[3].element_type.as_deref(), Some("email"));
+        assert_eq!(fields[3].selector(), "[id=\"user.email\"]");
+
+        assert_eq!(fields[4].element_type.as_deref(), Some("submit"));
+        assert_eq!(fields[4].text.as_deref(), Some("Apply now"));
+        assert_eq!(fields[4].selector(), ".btn");
+        assert!(fields.iter().all(|field| field.step.is_none()));
+        assert!(!has_progress_indicator(html));
+    }
+
+    #[test]
+    fn test_form_fields_record_wizard_steps() {
+        let html = r#"
+            <div class="progress"><div role="progressbar" style="width: 33%"></div></div>
+            <form>
+                <section data-step="personal"><input id="name"><button type="button">Next</button></section>
+                <section data-step="contact" hidden><input id="email"><div class="row"><input id="phone"></div></section>
+                <div class="form-step"><input id="cv" type="file"></div>
+                <div class="step-indicator"></div>
+                <button>Send</button>
+            </form>
+        "#;
+        let steps: Vec<Option<usize>> = form_fields(html).iter().map(|field| field.step).collect();
+        assert_eq!(steps, vec![Some(0), Some(0), Some(1), Some(1), Some(2), None]);
+        assert!(has_progress_indicator(html));
+
+        let upload = r#"<form><input id="cv" type="file"><div class="upload-progress"><progress value="40" max="100"></progress></div></form>"#;
+        assert!(!has_progress_indicator(upload));
+        assert!(has_progress_indicator(&html.replace("role=\"progressbar\"", "")));
+    }
+
+    #[test]
+    fn test_group_radios_per_form_with_question() {
+        let html = r#"
+            <form id="apply">
+                <fieldset><legend>Do you need a visa?</legend>
+                    <label><input type="radio" name="answer" value="yes">Yes</label>
+                    <label><input type="radio" name="answer" value="no">No</label>
+                </fieldset>
+                <span id="relocate-q">Willing to</span><span id="relocate-q2">relocate?</span>
+                <div role="radiogroup" aria-labelledby="relocate-q relocate-q2">
+                    <label><input type="radio" name="relocate" value="y">Yes</label>
+                </div>
+            </form>
+            <form id="newsletter">
+                <label><input type="radio" name="answer" value="weekly">Weekly</label>
+            </form>
+            <input type="radio" name="answer" value="late" form="newsletter">
+        "#;
+        let grouped = group_radios(form_fields(html));
+        let summary: Vec<(Option<&str>, Option<usize>, usize)> =
+            grouped.iter().map(|field| (field.label.as_deref(), field.form, field.options.len())).collect();
+        assert_eq!(
+            summary,
+            vec![(Some("Do you need a visa?"), Some(0), 2), (Some("Willing to relocate?"), Some(0), 1), (None, Some(1), 2)]
+        );
+        assert_eq!(grouped[0].options[1], SelectOption { value: "no".to_string(), text: "No".to_string() });
+    }
+
+    #[test]
+    fn test_select_reports_matches() {
+        let html = "<ul><li class=\"item\">One</li><li class=\"item\">  Two\n </li></ul>";
+        let matches = select(html, "li.item").unwrap();
+        assert_eq!(matches.count, 2);
+        assert_eq!(matches.elements[1].text, "Two");
+        assert_eq!(matches.elements[0].classes, vec!["item"]);
+        assert_eq!(select(html, "#missing").unwrap().count, 0);
+        assert!(select(html, "li[").is_err());
+
+        let quoted = r#"<input name='answers["q1"]'><input id='path\to' class="1st">"#;
+        let selectors: Vec<String> = form_fields(quoted).iter().map(FormField::selector).collect();
+        assert_eq!(selectors, vec![r#"[name="answers[\"q1\"]"]"#, r#"[id="path\\to"]"#]);
+        assert!(selectors.iter().all(|selector| select(quoted, selector).unwrap().count == 1));
+    }
+
+    #[test]
+    fn test_structure_signature_ignores_cosmetic_changes() {
+        let original = r#"<form class="a"><p>Hello</p><input id="user" type="text">
+            <input name="email" id=":r3:" value="x"><select name="country"><option value="pl">Polska</option></select>
+            <input type="hidden" name="_csrf" value="1"><button type="submit">Send</button></form>"#;
+        let restyled = r#"<form class="b" style="margin: 0"><div class="row"><label>Hi</label><input id="user" type="TEXT" class="wide"></div>
+            <input name="email" id=":r9:"><select name="country"><option value="pl">Poland</option></select>
+            <input type="hidden" name="_csrf" value="2"><button type="submit"><b>Apply</b></button></form>"#;
+        assert_eq!(structure_signature(original), structure_signature(restyled));
+        assert_eq!(
+            structure_signature(original),
+            "[form(input:text#user()input@email()select@country(option=pl())button:submit())]"
+        );
+        assert_ne!(structure_signature(original), structure_signature(&original.replace("name=\"email\"", "name=\"mail\"")));
+        // Id pola z `name` też jest częścią szkieletu - selektor `#user-email` przestałby trafiać
+        let with_id = original.replace("id=\":r3:\"", "id=\"user-email\"");
+        assert_ne!(structure_signature(&with_id), structure_signature(&with_id.replace("user-email", "mail")));
+        assert!(is_generated_id("ember123") && is_generated_id("field-8f3a9c2e") && !is_generated_id("first-name"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Przenoszenie cache DSL między instalacjami (np. staging -> produkcja serwera zespołu):
+//! eksport poprawnych skryptów dla wybranych domen do paczki podpisanej HMAC-SHA256
+//! (DSL_CACHE_SIGNING_KEY) i import paczki po sprawdzeniu podpisu, z wyborem, co zrobić
+//! z kluczami, które mają już lokalnie inny skrypt. Paczka zawiera tylko skrypty wpisujące wartości
+//! przez `{{zmienne}}` (`few_shot::is_shareable`), a skrypty i struktury stron są w niej zaszyfrowane
+//! kluczem wyprowadzonym z tego samego sekretu.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tracing::{debug, info, warn};
+
+use crate::crypto::{self, ContentCipher};
+use crate::dsl::history;
+use crate::{dom, few_shot, llm, tagui};
+use codialog_types::automation::{CacheConflictPolicy, DslCacheBundle, DslCacheEntry, GenerationStrategy};
+
+/// Wersja formatu paczki; 2 - zaszyfrowane skrypty i struktury stron
+pub const BUNDLE_FORMAT: u32 = 2;
+
+/// Czy witryna wpisu należy do jednej z domen (sama domena albo jej subdomena)
+pub fn matches_domain(site: &str, domains: &[String]) -> bool {
+    domains.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches("www.").to_lowercase();
+        !domain.is_empty() && (site == domain || site.ends_with(&format!(".{}", domain)))
+    })
+}
+
+/// Podpisywana treść: paczka bez pola `signature`
+fn signed_payload(bundle: &DslCacheBundle) -> Result<Vec<u8>> {
+    let unsigned = DslCacheBundle { signature: String::new(), ..bundle.clone() };
+    serde_json::to_vec(&unsigned).context("Failed to serialize DSL cache bundle")
+}
+
+fn signing_key(secret: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+}
+
+/// Szyfr treści wpisów; klucz inny niż klucz podpisu, choć z tego samego sekretu
+fn bundle_cipher(secret: &str) -> Result<ContentCipher> {
+    ContentCipher::from_secret(&format!("dsl-cache-bundle:{}", secret))
+}
+
+pub fn sign(bundle: &mut DslCacheBundle, secret: &str) -> Result<()> {
+    let tag = hmac::sign(&signing_key(secret), &signed_payload(bundle)?);
+    bundle.signature = tag.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(())
+}
+
+/// Sprawdza format i podpis paczki; zwraca odszyfrowane wpisy do `import`
+pub fn verify(bundle: &DslCacheBundle, secret: &str) -> Result<Vec<DslCacheEntry>> {
+    if bundle.format != BUNDLE_FORMAT {
+        bail!("Unsupported DSL cache bundle format {}", bundle.format);
+    }
+    let signature = bundle.signature.trim();
+    if !signature.len().is_multiple_of(2) || !signature.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("DSL cache bundle signature is missing or malformed");
+    }
+    let tag: Vec<u8> = (0..signature.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&signature[index..index + 2], 16).expect("hex digits checked"))
+        .collect();
+    hmac::verify(&signing_key(secret), &signed_payload(bundle)?, &tag)
+        .map_err(|_| anyhow::anyhow!("DSL cache bundle signature does not match - wrong DSL_CACHE_SIGNING_KEY or modified bundle"))?;
+
+    let cipher = bundle_cipher(secret)?;
+    bundle
+        .entries
+        .iter()
+        .map(|entry| {
+            Ok(DslCacheEntry {
+                script: cipher.decrypt(&entry.script).with_context(|| format!("Failed to decrypt script of {}", entry.cache_key))?,
+                html_structure: entry.html_structure.as_deref().map(|html| cipher.decrypt(html)).transpose()?,
+                ..entry.clone()
+            })
+        })
+        .collect()
+}
+
+/// Eksportuje poprawne skrypty z cache dla domen (także wygasłe - skrypt nadal opisuje stronę).
+/// Skrypty z dosłownymi wartościami pól nie są eksportowane - mogą zawierać dane użytkownika.
+pub async fn export(pool: &PgPool, domains: &[String], secret: &str) -> Result<DslCacheBundle> {
+    let cipher = bundle_cipher(secret)?;
+    let rows = sqlx::query(
+        "SELECT cache_key, site, script_content, strategy, html_content, created_at FROM dsl_cache WHERE site IS NOT NULL ORDER BY site, cache_key",
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read DSL cache")?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let site: String = row.get("site");
+        if !matches_domain(&site, domains) {
+            continue;
+        }
+        let cache_key: String = row.get("cache_key");
+        let script: String = row.get("script_content");
+        if let Err(e) = tagui::validate_dsl_script(&script) {
+            debug!(cache_key = %cache_key, "Cached script is not valid DSL, not exported: {}", e);
+            continue;
+        }
+        if !few_shot::is_shareable(&script) {
+            debug!(cache_key = %cache_key, "Cached script types literal values, not exported");
+            continue;
+        }
+        // HTML jest zaszyfrowany kluczem tej instalacji; do paczki trafia odszyfrowana sama struktura
+        let html_structure = row
+            .get::<Option<String>, _>("html_content")
+            .and_then(|html| if html.starts_with(crypto::ENCRYPTED_PREFIX) { crypto::decrypt_any(&html).ok() } else { None });
+        entries.push(DslCacheEntry {
+            cache_key,
+            site,
+            script: cipher.encrypt(&script)?,
+            strategy: row.get::<Option<String>, _>("strategy").as_deref().and_then(GenerationStrategy::parse),
+            html_structure: html_structure.as_deref().map(|html| cipher.encrypt(html)).transpose()?,
+            created_at: row.get("created_at"),
+        });
+    }
+
+    let mut bundle = DslCacheBundle {
+        format: BUNDLE_FORMAT,
+        exported_at: Utc::now(),
+        domains: domains.to_vec(),
+        entries,
+        signature: String::new(),
+    };
+    sign(&mut bundle, secret)?;
+    info!(domains = ?domains, entries = bundle.entries.len(), "Exported DSL cache bundle");
+    Ok(bundle)
+}
+
+/// Wpis paczki, który nie został zaimportowany
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub cache_key: String,
+    pub site: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    /// Klucze, które miały już lokalnie ten sam skrypt
+    pub unchanged: usize,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Lokalny wpis dla klucza z paczki
+#[derive(Debug, Clone)]
+pub struct LocalEntry {
+    pub script: String,
+    pub created_at: DateTime<Utc>,
+    pub pinned: bool,
+}
+
+/// Decyzja dla wpisu paczki: `Ok(true)` - zapisać, `Ok(false)` - ten sam skrypt, `Err` - powód pominięcia
+pub fn resolve(entry: &DslCacheEntry, local: Option<&LocalEntry>, policy: CacheConflictPolicy) -> Result<bool, String> {
+    if let Err(e) = tagui::validate_dsl_script(&entry.script) {
+        return Err(format!("invalid DSL: {}", e));
+    }
+    if !few_shot::is_shareable(&entry.script) {
+        return Err("script types literal values instead of {{variables}}".to_string());
+    }
+    let Some(local) = local else {
+        return Ok(true);
+    };
+    if local.script == entry.script {
+        return Ok(false);
+    }
+    match policy {
+        CacheConflictPolicy::Overwrite => Ok(true),
+        _ if local.pinned => Err("local script is pinned".to_string()),
+        CacheConflictPolicy::Newer if entry.created_at > local.created_at => Ok(true),
+        CacheConflictPolicy::Newer => Err("local script is newer".to_string()),
+        CacheConflictPolicy::Skip => Err("local script differs".to_string()),
+    }
+}
+
+/// Importuje wpisy paczki sprawdzonej przez `verify`; zapisane skrypty trafiają też do historii wersji klucza
+pub async fn import(pool: &PgPool, entries: &[DslCacheEntry], policy: CacheConflictPolicy, pin: bool) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    for entry in entries {
+        let local = sqlx::query("SELECT script_content, created_at, pinned FROM dsl_cache WHERE cache_key = $1")
+            .bind(&entry.cache_key)
+            .fetch_optional(pool)
+            .await
+            .context("Failed to read DSL cache entry")?
+            .map(|row| LocalEntry { script: row.get("script_content"), created_at: row.get("created_at"), pinned: row.get("pinned") });
+
+        match resolve(entry, local.as_ref(), policy) {
+            Ok(true) => {}
+            Ok(false) => {
+                report.unchanged += 1;
+                continue;
+            }
+            Err(reason) => {
+                report.skipped.push(SkippedEntry { cache_key: entry.cache_key.clone(), site: entry.site.clone(), reason });
+                continue;
+            }
+        }
+
+        let html_content = match entry.html_structure.as_deref().map(llm::stored_html_content).transpose() {
+            Ok(html) => html.flatten(),
+            Err(e) => {
+                warn!(cache_key = %entry.cache_key, "Failed to store page structure of imported script: {}", e);
+                None
+            }
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO dsl_cache (cache_key, script_content, html_content, strategy, site, pinned, created_at, structure_hash, form_fields, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW() + INTERVAL '1 hour')
+            ON CONFLICT (cache_key) DO UPDATE SET
+                script_content = EXCLUDED.script_content,
+                html_content = COALESCE(EXCLUDED.html_content, dsl_cache.html_content),
+                structure_hash = COALESCE(EXCLUDED.structure_hash, dsl_cache.structure_hash),
+                form_fields = COALESCE(EXCLUDED.form_fields, dsl_cache.form_fields),
+                strategy = EXCLUDED.strategy,
+                site = EXCLUDED.site,
+                pinned = EXCLUDED.pinned,
+                created_at = EXCLUDED.created_at,
+                expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(&entry.cache_key)
+        .bind(&entry.script)
+        .bind(html_content)
+        .bind(entry.strategy.map(|strategy| strategy.as_str()))
+        .bind(&entry.site)
+        .bind(pin)
+        .bind(entry.created_at)
+        .bind(entry.html_structure.as_deref().map(dom::structure_fingerprint))
+        .bind(entry.html_structure.as_deref().map(few_shot::form_fields))
+        .execute(pool)
+        .await
+        .context("Failed to import DSL cache entry")?;
+
+        if let Err(e) = history::record(pool, &entry.cache_key, &entry.script, entry.strategy).await {
+            warn!(cache_key = %entry.cache_key, "Failed to record imported script version: {}", e);
+        }
+        report.imported.push(entry.cache_key.clone());
+    }
+
+    info!(
+        imported = report.imported.len(),
+        unchanged = report.unchanged,
+        skipped = report.skipped.len(),
+        "Imported DSL cache bundle"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_bundle_signature_and_conflict_resolution() {
+        let domains = vec!["example.com".to_string()];
+        assert!(matches_domain("example.com", &domains) && matches_domain("jobs.example.com", &domains));
+        assert!(!matches_domain("notexample.com", &domains) && !matches_domain("example.com", &[" ".to_string()]));
+
+        let now = Utc::now();
+        let entry = DslCacheEntry {
+            cache_key: "dsl_1".to_string(),
+            site: "jobs.example.com".to_string(),
+            script: "type \"#email\" \"{{email}}\"\nclick \"#submit\"".to_string(),
+            strategy: Some(GenerationStrategy::Llm),
+            html_structure: None,
+            created_at: now,
+        };
+        let mut bundle = DslCacheBundle { format: BUNDLE_FORMAT, exported_at: now, domains, entries: vec![entry.clone()], signature: String::new() };
+        assert!(verify(&bundle, "team-secret").is_err());
+        sign(&mut bundle, "team-secret").unwrap();
+        assert_eq!(bundle.signature.len(), 64);
+
+        // Podpis przetrwa przesłanie jako JSON, ale nie zmianę treści ani inny klucz
+        let received: DslCacheBundle = serde_json::from_str(&serde_json::to_string(&bundle).unwrap()).unwrap();
+        assert_eq!(verify(&received, "team-secret").unwrap_err().to_string(), "Failed to decrypt script of dsl_1");
+        let cipher = bundle_cipher("team-secret").unwrap();
+        bundle.entries[0].script = cipher.encrypt(&entry.script).unwrap();
+        sign(&mut bundle, "team-secret").unwrap();
+        assert!(!serde_json::to_string(&bundle).unwrap().contains("#email"));
+        let received: DslCacheBundle = serde_json::from_str(&serde_json::to_string(&bundle).unwrap()).unwrap();
+        assert_eq!(verify(&received, "team-secret").unwrap(), vec![entry.clone()]);
+        assert!(verify(&received, "other-secret").is_err());
+        let mut tampered = received.clone();
+        tampered.entries[0].script = "click \"#delete-account\"".to_string();
+        assert!(verify(&tampered, "team-secret").is_err());
+
+        let local = |script: &str, age_hours: i64, pinned: bool| LocalEntry { script: script.to_string(), created_at: now - Duration::hours(age_hours), pinned };
+        assert_eq!(resolve(&entry, None, CacheConflictPolicy::Skip), Ok(true));
+        assert_eq!(resolve(&entry, Some(&local(&entry.script, 1, true)), CacheConflictPolicy::Skip), Ok(false));
+        assert_eq!(resolve(&entry, Some(&local("wait 2", 1, false)), CacheConflictPolicy::Skip), Err("local script differs".to_string()));
+        assert_eq!(resolve(&entry, Some(&local("wait 2", 1, false)), CacheConflictPolicy::Newer), Ok(true));
+        assert_eq!(resolve(&entry, Some(&local("wait 2", -1, false)), CacheConflictPolicy::Newer), Err("local script is newer".to_string()));
+        assert_eq!(resolve(&entry, Some(&local("wait 2", 1, true)), CacheConflictPolicy::Newer), Err("local script is pinned".to_string()));
+        assert_eq!(resolve(&entry, Some(&local("wait 2", -1, true)), CacheConflictPolicy::Overwrite), Ok(true));
+        let invalid = DslCacheEntry { script: "explode \"#x\"".to_string(), ..entry.clone() };
+        assert!(resolve(&invalid, None, CacheConflictPolicy::Overwrite).unwrap_err().starts_with("invalid DSL"));
+        let literal = DslCacheEntry { script: "type \"#email\" \"jan@example.com\"".to_string(), ..entry };
+        assert!(resolve(&literal, None, CacheConflictPolicy::Overwrite).unwrap_err().contains("literal values"));
+    }
+}
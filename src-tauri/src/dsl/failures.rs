@@ -0,0 +1,77 @@
+//! Krótka pamięć nieudanych generowań: klucz cache strony, dla której model nie dał poprawnego skryptu,
+//! razem z powodem. Kolejne żądania przez `DSL_FAILURE_CACHE_SECS` pomijają model zamiast znowu płacić
+//! za tokeny, a UI dostaje znaną przeszkodę w `generation.blocker`.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+/// Jak długo pamiętamy nieudane generowanie dla struktury strony
+pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+static TTL: RwLock<Option<Duration>> = RwLock::new(None);
+
+static FAILURES: Mutex<Option<HashMap<String, Failure>>> = Mutex::new(None);
+
+struct Failure {
+    reason: String,
+    at: Instant,
+}
+
+/// Czas pamiętania porażek (DSL_FAILURE_CACHE_SECS, zero wyłącza); wywoływane raz przy starcie
+pub fn set_ttl(ttl: Duration) {
+    info!(secs = ttl.as_secs(), "DSL generation failure cache configured");
+    *TTL.write().unwrap() = Some(ttl);
+}
+
+fn ttl() -> Duration {
+    TTL.read().unwrap().unwrap_or(DEFAULT_TTL)
+}
+
+/// Zapamiętuje, że model nie dał poprawnego skryptu dla klucza cache
+pub fn remember(cache_key: &str, reason: &str) {
+    if ttl().is_zero() {
+        return;
+    }
+    let mut failures = FAILURES.lock().unwrap();
+    let failures = failures.get_or_insert_with(HashMap::new);
+    failures.retain(|_, failure| failure.at.elapsed() < ttl());
+    failures.insert(cache_key.to_string(), Failure { reason: reason.to_string(), at: Instant::now() });
+}
+
+/// Powód niedawnej porażki dla klucza, jeśli jeszcze nie minął czas pamiętania
+pub fn known(cache_key: &str) -> Option<String> {
+    let failures = FAILURES.lock().unwrap();
+    failures
+        .as_ref()?
+        .get(cache_key)
+        .filter(|failure| failure.at.elapsed() < ttl())
+        .map(|failure| failure.reason.clone())
+}
+
+/// Zapomina porażkę, np. gdy dla klucza powstał poprawny skrypt
+pub fn forget(cache_key: &str) {
+    if let Some(failures) = FAILURES.lock().unwrap().as_mut() {
+        failures.remove(cache_key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_is_remembered_until_forgotten() {
+        let key = "dsl_failures_test";
+        assert_eq!(known(key), None);
+
+        remember(key, "Line 2: Command 'run' is not allowed in generated scripts");
+        assert_eq!(known(key).as_deref(), Some("Line 2: Command 'run' is not allowed in generated scripts"));
+        assert_eq!(known("dsl_other"), None);
+
+        forget(key);
+        assert_eq!(known(key), None);
+    }
+}
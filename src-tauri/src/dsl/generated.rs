@@ -0,0 +1,130 @@
+//! Kontrola skryptów wygenerowanych przez model lub heurystyki, zanim trafią do cache: gramatyka DSL
+//! (`tagui::parse_dsl_script` - cudzysłowy, liczba argumentów, bloki), wiarygodność selektorów oraz
+//! zakaz komend TagUI wykonujących kod i wysyłania plików systemowych.
+
+use scraper::Selector;
+
+use crate::tagui::{self, DslCommand, DslParseError, FOR_EACH_ITEM};
+
+/// Komendy TagUI uruchamiające kod, programy lub zapis plików - model mógłby je dopisać poza gramatyką DSL
+const FORBIDDEN_COMMANDS: &[&str] = &[
+    "run", "js", "dom", "py", "r", "vision", "api", "tagui", "load", "dump", "write", "save", "snap", "echo", "exec",
+    "keyboard", "mouse", "telegram",
+];
+
+/// Fragmenty ścieżek plików, których skrypt nie może wysłać przez `upload`
+const SENSITIVE_UPLOAD_PATHS: &[&str] = &[
+    "/etc/", "/proc/", "/sys/", "/root/", "/var/", ".ssh", ".aws", ".gnupg", ".env", "id_rsa", "id_ed25519",
+    "c:\\windows", "\\system32",
+];
+
+/// Najdłuższe `wait` w skrypcie wygenerowanym (sekundy)
+pub const MAX_WAIT_SECS: f64 = 120.0;
+
+/// Komendy skryptu albo pierwszy problem z numerem linii
+pub fn check(script: &str) -> Result<Vec<DslCommand>, DslParseError> {
+    for (line, command) in tagui::script_commands(script) {
+        let name = command.split_whitespace().next().unwrap_or_default().to_lowercase();
+        if FORBIDDEN_COMMANDS.contains(&name.as_str()) {
+            return Err(DslParseError { line, message: format!("Command '{}' is not allowed in generated scripts", name) });
+        }
+    }
+
+    let commands = tagui::parse_dsl_script(script)?;
+    if commands.is_empty() {
+        return Err(DslParseError { line: 1, message: "Script has no commands".to_string() });
+    }
+
+    for command in &commands {
+        let error = |message: String| DslParseError { line: command.line, message };
+        match command.name.as_str() {
+            "type" | "upload" if command.args.len() != 2 => {
+                return Err(error(format!("Command '{}' requires a selector and one quoted value", command.name)));
+            }
+            "wait" => {
+                let secs: f64 = command.args[0].parse().unwrap_or_default();
+                if !(0.0..=MAX_WAIT_SECS).contains(&secs) {
+                    return Err(error(format!("Wait time must be between 0 and {} seconds", MAX_WAIT_SECS)));
+                }
+            }
+            "upload" => {
+                let path = command.args[1].to_lowercase();
+                if SENSITIVE_UPLOAD_PATHS.iter().any(|fragment| path.contains(fragment)) {
+                    return Err(error(format!("Uploading '{}' is not allowed", command.args[1])));
+                }
+            }
+            _ => {}
+        }
+        if let Some(selector) = command.selector() {
+            check_selector(selector).map_err(error)?;
+        }
+    }
+    Ok(commands)
+}
+
+/// Selektor z domkniętymi nawiasami i cudzysłowami: XPath albo CSS poprawny dla parsera.
+/// `{{zmienne}}` i `@item` są podstawiane dopiero przy wykonaniu, więc ich nie sprawdzamy.
+fn check_selector(selector: &str) -> Result<(), String> {
+    let selector = selector.trim();
+    if selector.is_empty() {
+        return Err("Selector must not be empty".to_string());
+    }
+    if selector.to_lowercase().contains("javascript:") {
+        return Err(format!("Selector '{}' contains a script URL", selector));
+    }
+    if selector.contains("{{") || selector.contains(FOR_EACH_ITEM) {
+        return Ok(());
+    }
+    let xpath = tagui::is_xpath(selector);
+    if !balanced(selector) {
+        return Err(format!("Malformed {} selector '{}'", if xpath { "XPath" } else { "CSS" }, selector));
+    }
+    if xpath {
+        return Ok(());
+    }
+    // Parser CSS domyka nawiasy na końcu sam, dlatego `balanced` sprawdza je wcześniej
+    Selector::parse(selector).map(|_| ()).map_err(|_| format!("Malformed CSS selector '{}'", selector))
+}
+
+fn balanced(selector: &str) -> bool {
+    let mut open = Vec::new();
+    let mut quote: Option<char> = None;
+    for c in selector.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(' | '[') => open.push(c),
+            (None, ')') if open.pop() != Some('(') => return false,
+            (None, ']') if open.pop() != Some('[') => return false,
+            _ => {}
+        }
+    }
+    quote.is_none() && open.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_script_checks() {
+        let valid = "wait 2\ntype \"#email\" \"{{email}}\"\nselect \"select[name='country']\" \"PL\"\nupload \"input[type=file]\" \"/home/jan/cv.pdf\"\n\
+                     for each \"(//li[@class='job'])\"\n  click \"@item//a\"\nend\nclick \"#submit\"";
+        assert_eq!(check(valid).unwrap().len(), 8);
+
+        let line_of = |script: &str| check(script).unwrap_err().line;
+        let message_of = |script: &str| check(script).unwrap_err().message;
+        assert_eq!(line_of("wait 1\ntype \"#email\" \"unclosed\nclick \"#go\""), 2);
+        assert!(message_of("// tylko komentarz").contains("no commands"));
+        assert!(message_of("click \"#a\"\nrun rm -rf /").contains("'run' is not allowed"));
+        assert!(message_of("js document.cookie").contains("not allowed"));
+        assert!(message_of("type \"#name\" Jan Kowalski").contains("one quoted value"));
+        assert!(message_of("wait 900").contains("between 0 and 120"));
+        assert!(message_of("upload \"#cv\" \"/etc/passwd\"").contains("not allowed"));
+        assert!(message_of("click \"input[name=email\"").contains("Malformed CSS"));
+        assert!(message_of("click \"(//button[text()='Go')\"").contains("Malformed XPath"));
+        assert!(message_of("click \"a[href='javascript:void(0)']\"").contains("script URL"));
+        assert!(check("click \"#{{button_id}}\"").is_ok());
+    }
+}
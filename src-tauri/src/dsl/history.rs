@@ -0,0 +1,263 @@
+//! Historia wersji skryptów w `dsl_cache`: każdy inny skrypt wygenerowany dla klucza cache dostaje
+//! kolejny numer wersji (`dsl_cache_versions`), więc po przebudowie strony widać, jak zmienił się
+//! skrypt, a znaną dobrą wersję można przywrócić i przypiąć w cache.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tracing::{debug, info};
+
+use codialog_types::automation::GenerationStrategy;
+
+/// Ile ostatnich wersji zostaje w historii klucza
+pub const MAX_VERSIONS_PER_KEY: i32 = 50;
+
+/// Wersja skryptu zapisana dla klucza cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptVersion {
+    pub version: i32,
+    pub script: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<GenerationStrategy>,
+    /// Wersja przywrócona przez rollback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored_from: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+fn version_from_row(row: &sqlx::postgres::PgRow) -> ScriptVersion {
+    ScriptVersion {
+        version: row.get("version"),
+        script: row.get("script_content"),
+        strategy: row.get::<Option<String>, _>("strategy").as_deref().and_then(GenerationStrategy::parse),
+        restored_from: row.get("restored_from"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Dopisuje wersję, jeśli skrypt różni się od ostatniej; zwraca numer nowej wersji
+pub async fn record(pool: &PgPool, cache_key: &str, script: &str, strategy: Option<GenerationStrategy>) -> Result<Option<i32>> {
+    append(pool, cache_key, script, strategy, None).await
+}
+
+async fn append(
+    pool: &PgPool,
+    cache_key: &str,
+    script: &str,
+    strategy: Option<GenerationStrategy>,
+    restored_from: Option<i32>,
+) -> Result<Option<i32>> {
+    // Blokada na klucz: równoległe zapisy dostają kolejne numery, zamiast gubić się na konflikcie `MAX + 1`
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('dsl_cache_versions:' || $1))")
+        .bind(cache_key)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to lock DSL script history")?;
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO dsl_cache_versions (cache_key, version, script_content, strategy, restored_from)
+        SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4
+        FROM dsl_cache_versions
+        WHERE cache_key = $1
+        HAVING (SELECT script_content FROM dsl_cache_versions WHERE cache_key = $1 ORDER BY version DESC LIMIT 1)
+               IS DISTINCT FROM $2
+        RETURNING version
+        "#,
+    )
+    .bind(cache_key)
+    .bind(script)
+    .bind(strategy.map(|strategy| strategy.as_str()))
+    .bind(restored_from)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to record DSL script version")?;
+
+    let version = row.map(|row| row.get::<i32, _>("version"));
+    if let Some(version) = version {
+        // Starsze wersje poza limitem odpadają; numeracja się nie zmienia
+        let pruned = sqlx::query("DELETE FROM dsl_cache_versions WHERE cache_key = $1 AND version <= $2")
+            .bind(cache_key)
+            .bind(version - MAX_VERSIONS_PER_KEY)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to prune DSL script versions")?
+            .rows_affected();
+        if pruned > 0 {
+            debug!(cache_key = cache_key, pruned = pruned, "Pruned old DSL script versions");
+        }
+    }
+    tx.commit().await.context("Failed to record DSL script version")?;
+
+    match version {
+        Some(version) => debug!(cache_key = cache_key, version = version, "Recorded DSL script version"),
+        None => debug!(cache_key = cache_key, "DSL script unchanged, no new version"),
+    }
+    Ok(version)
+}
+
+/// Wersje dla klucza, od najnowszej
+pub async fn list(pool: &PgPool, cache_key: &str) -> Result<Vec<ScriptVersion>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT version, script_content, strategy, restored_from, created_at
+        FROM dsl_cache_versions
+        WHERE cache_key = $1
+        ORDER BY version DESC
+        "#,
+    )
+    .bind(cache_key)
+    .fetch_all(pool)
+    .await
+    .context("Failed to list DSL script versions")?;
+
+    Ok(rows.iter().map(version_from_row).collect())
+}
+
+pub async fn get(pool: &PgPool, cache_key: &str, version: i32) -> Result<Option<ScriptVersion>> {
+    let row = sqlx::query(
+        r#"
+        SELECT version, script_content, strategy, restored_from, created_at
+        FROM dsl_cache_versions
+        WHERE cache_key = $1 AND version = $2
+        "#,
+    )
+    .bind(cache_key)
+    .bind(version)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch DSL script version")?;
+
+    Ok(row.as_ref().map(version_from_row))
+}
+
+/// Czy wpis cache dla klucza jest przypięty
+pub async fn is_pinned(pool: &PgPool, cache_key: &str) -> Result<bool> {
+    let pinned = sqlx::query_scalar::<_, bool>("SELECT pinned FROM dsl_cache WHERE cache_key = $1")
+        .bind(cache_key)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to read DSL cache entry")?;
+    Ok(pinned.unwrap_or(false))
+}
+
+/// Przywraca wersję jako bieżący skrypt w cache (przypięty, gdy `pin`) i dopisuje ją do historii
+pub async fn rollback(pool: &PgPool, cache_key: &str, version: i32, pin: bool) -> Result<Option<ScriptVersion>> {
+    let Some(target) = get(pool, cache_key, version).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO dsl_cache (cache_key, script_content, strategy, pinned, expires_at)
+        VALUES ($1, $2, $3, $4, NOW() + INTERVAL '1 hour')
+        ON CONFLICT (cache_key) DO UPDATE SET
+            script_content = EXCLUDED.script_content,
+            strategy = EXCLUDED.strategy,
+            pinned = EXCLUDED.pinned,
+            expires_at = EXCLUDED.expires_at
+        "#,
+    )
+    .bind(cache_key)
+    .bind(&target.script)
+    .bind(target.strategy.map(|strategy| strategy.as_str()))
+    .bind(pin)
+    .execute(pool)
+    .await
+    .context("Failed to restore DSL script version")?;
+
+    append(pool, cache_key, &target.script, target.strategy, Some(version)).await?;
+    info!(cache_key = cache_key, version = version, pinned = pin, "DSL cache rolled back to a previous script version");
+    Ok(Some(target))
+}
+
+/// Linia porównania dwóch wersji
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub line: String,
+}
+
+/// Porównanie linii (najdłuższy wspólny podciąg); usunięte linie przed dodanymi w miejscu zmiany
+pub fn diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] - długość wspólnego podciągu old[i..] i new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+
+    let line = |kind, line: &str| DiffLine { kind, line: line.to_string() };
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(line(DiffKind::Same, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(line(DiffKind::Removed, old[i]));
+            i += 1;
+        } else {
+            lines.push(line(DiffKind::Added, new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Porównanie w zwykłym formacie: ` ` bez zmian, `-` usunięta, `+` dodana
+pub fn render(lines: &[DiffLine]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            let marker = match line.kind {
+                DiffKind::Same => ' ',
+                DiffKind::Added => '+',
+                DiffKind::Removed => '-',
+            };
+            format!("{}{}", marker, line.line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_between_script_versions() {
+        let before = "click \"#accept\"\ntype \"#email\" \"{{email}}\"\nclick \"#submit\"";
+        let after = "click \"#accept\"\ntype \"input[name=email]\" \"{{email}}\"\ncheck \"#gdpr\"\nclick \"#submit\"";
+
+        let lines = diff(before, after);
+        let kinds: Vec<DiffKind> = lines.iter().map(|line| line.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![DiffKind::Same, DiffKind::Removed, DiffKind::Added, DiffKind::Added, DiffKind::Same]
+        );
+        assert_eq!(
+            render(&lines),
+            " click \"#accept\"\n-type \"#email\" \"{{email}}\"\n+type \"input[name=email]\" \"{{email}}\"\n+check \"#gdpr\"\n click \"#submit\""
+        );
+
+        assert!(diff(before, before).iter().all(|line| line.kind == DiffKind::Same));
+        assert_eq!(render(&diff("", "wait 2")), "+wait 2");
+        assert_eq!(render(&diff("wait 2", "")), "-wait 2");
+    }
+}
@@ -0,0 +1,488 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::tagui::{escape_for_dsl, parse_dsl_script, DslCommand, DEFAULT_WAITFOR_TIMEOUT_SECS};
+
+/// Fragmenty selektorów kroków, które zwykle przeładowują stronę
+const NAVIGATION_HINTS: &[&str] = &["submit", "login", "log-in", "signin", "sign-in", "next", "continue", "search", "href"];
+
+/// Pola, do których nie powinno się wpisywać wartości wprost ze skryptu, z nazwą sugerowanej zmiennej
+const CREDENTIAL_FIELDS: &[(&str, &str)] = &[
+    ("pass", "password"),
+    ("pwd", "password"),
+    ("secret", "secret"),
+    ("token", "token"),
+    ("api_key", "api_key"),
+    ("api-key", "api_key"),
+    ("apikey", "api_key"),
+    ("otp", "otp"),
+];
+
+/// Komendy operujące na elemencie strony; tylko one czekają na załadowanie po nawigacji
+const ELEMENT_COMMANDS: &[&str] = &["click", "type", "upload", "hover", "select", "check", "uncheck"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Zmiana jednej linii skryptu (numery linii jak w oryginalnym skrypcie, 1-based)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Edit {
+    Replace { line: usize, text: String },
+    InsertAfter { line: usize, text: String },
+    Delete { line: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fix {
+    pub description: String,
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    fn new(line: usize, severity: Severity, code: &'static str, message: String) -> Self {
+        Self { line, severity, code, message, fix: None }
+    }
+
+    fn with_fix(mut self, description: impl Into<String>, edits: Vec<Edit>) -> Self {
+        self.fix = Some(Fix { description: description.into(), edits });
+        self
+    }
+}
+
+/// Lints a DSL script. A syntax error is returned as the only diagnostic, since the
+/// other checks need the parsed commands. `run_timeout` enables the unreachable-step check.
+pub fn lint_script(script: &str, run_timeout: Option<Duration>) -> Vec<Diagnostic> {
+    let commands = match parse_dsl_script(script) {
+        Ok(commands) => commands,
+        Err(e) => return vec![Diagnostic::new(e.line, Severity::Error, "syntax", e.message)],
+    };
+    let lines: Vec<&str> = script.lines().collect();
+
+    let mut diagnostics = Vec::new();
+    check_empty_blocks(&commands, &lines, &mut diagnostics);
+    if let Some(timeout) = run_timeout {
+        check_unreachable(&commands, timeout, &mut diagnostics);
+    }
+    check_duplicate_selectors(&commands, &mut diagnostics);
+    check_navigation_waits(&commands, &lines, &mut diagnostics);
+    check_credentials(&commands, &lines, &mut diagnostics);
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+/// Stosuje poprawki z diagnostyk; przy kilku zmianach tej samej linii wygrywa pierwsza
+pub fn apply_fixes(script: &str, diagnostics: &[Diagnostic]) -> String {
+    apply_edits(script, diagnostics.iter().filter_map(|d| d.fix.as_ref()).flat_map(|fix| &fix.edits))
+}
+
+fn apply_edits<'a>(script: &str, edits: impl IntoIterator<Item = &'a Edit>) -> String {
+    let mut changes: HashMap<usize, Option<String>> = HashMap::new();
+    let mut inserts: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+
+    for edit in edits {
+        match edit {
+            Edit::Replace { line, text } => {
+                changes.entry(*line).or_insert_with(|| Some(text.clone()));
+            }
+            Edit::Delete { line } => {
+                changes.entry(*line).or_insert(None);
+            }
+            Edit::InsertAfter { line, text } => inserts.entry(*line).or_default().push(text.clone()),
+        }
+    }
+
+    let mut output = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let number = index + 1;
+        match changes.get(&number) {
+            Some(Some(text)) => output.push(text.clone()),
+            Some(None) => {}
+            None => output.push(line.to_string()),
+        }
+        if let Some(texts) = inserts.get(&number) {
+            output.extend(texts.iter().cloned());
+        }
+    }
+    output.join("\n")
+}
+
+/// Po każdym kroku nawigacji, po którym skrypt od razu używa elementu nowej strony, wstawia
+/// `waitfor` na ten element zamiast stałych `wait N` pomiędzy nimi. Skrypt z błędem składni zostaje bez zmian.
+pub fn insert_navigation_waits(script: &str) -> String {
+    let Ok(commands) = parse_dsl_script(script) else {
+        return script.to_string();
+    };
+    let lines: Vec<&str> = script.lines().collect();
+    let mut edits = Vec::new();
+
+    for (index, command) in commands.iter().enumerate() {
+        if !is_navigation(command) {
+            continue;
+        }
+        let waits: Vec<&DslCommand> = commands[index + 1..].iter().take_while(|c| c.name == "wait").collect();
+        let Some(next) = commands.get(index + 1 + waits.len()) else {
+            continue;
+        };
+        let Some(selector) = next.selector().filter(|_| ELEMENT_COMMANDS.contains(&next.name.as_str()) && next.retry.is_none()) else {
+            continue;
+        };
+
+        edits.extend(waits.iter().map(|wait| Edit::Delete { line: wait.line }));
+        edits.push(Edit::InsertAfter {
+            line: command.line,
+            text: format!(
+                "{}waitfor \"{}\" timeout {}",
+                indent_of(&lines, command.line),
+                escape_for_dsl(selector),
+                DEFAULT_WAITFOR_TIMEOUT_SECS
+            ),
+        });
+    }
+    apply_edits(script, &edits)
+}
+
+fn indent_of<'a>(lines: &[&'a str], line: usize) -> &'a str {
+    let text = lines.get(line - 1).copied().unwrap_or("");
+    &text[..text.len() - text.trim_start().len()]
+}
+
+/// Bloki bez żadnej komendy w środku
+fn check_empty_blocks(commands: &[DslCommand], lines: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    for pair in commands.windows(2) {
+        let (opener, next) = (&pair[0], &pair[1]);
+        if matches!(opener.name.as_str(), "if" | "repeat" | "for" | "frame") && next.name == "end" {
+            let header = lines.get(opener.line - 1).map(|l| l.trim()).unwrap_or(opener.name.as_str());
+            diagnostics.push(
+                Diagnostic::new(opener.line, Severity::Warning, "empty_block", format!("Block '{}' has no commands", header))
+                    .with_fix("Remove the empty block", vec![Edit::Delete { line: opener.line }, Edit::Delete { line: next.line }]),
+            );
+        }
+    }
+}
+
+/// Steps that can only start after the watchdog has already killed the run.
+/// Only waits that always execute count: `if`/`for each` bodies and retries may be skipped.
+fn check_unreachable(commands: &[DslCommand], timeout: Duration, diagnostics: &mut Vec<Diagnostic>) {
+    struct Frame {
+        repeat: u32,
+        conditional: bool,
+        body_secs: f64,
+    }
+
+    let limit = timeout.as_secs_f64();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut elapsed = 0.0;
+
+    for (index, command) in commands.iter().enumerate() {
+        if elapsed > limit && command.name != "end" {
+            let remaining = commands[index..].iter().filter(|c| c.name != "end").count();
+            diagnostics.push(Diagnostic::new(
+                command.line,
+                Severity::Warning,
+                "unreachable",
+                format!(
+                    "This and {} following command(s) never run: waits before line {} take at least {:.0}s, longer than the {}s run timeout",
+                    remaining - 1,
+                    command.line,
+                    elapsed,
+                    timeout.as_secs()
+                ),
+            ));
+            return;
+        }
+
+        let conditional = frames.last().map(|frame| frame.conditional).unwrap_or(false);
+        match command.name.as_str() {
+            "if" | "for" => frames.push(Frame { repeat: 1, conditional: true, body_secs: 0.0 }),
+            "repeat" => {
+                let repeat = command.args[0].parse().unwrap_or(1);
+                frames.push(Frame { repeat, conditional, body_secs: 0.0 });
+            }
+            "frame" => frames.push(Frame { repeat: 1, conditional, body_secs: 0.0 }),
+            "end" => {
+                if let Some(frame) = frames.pop() {
+                    if !frame.conditional {
+                        // Pierwsza iteracja jest już policzona
+                        elapsed += frame.body_secs * frame.repeat.saturating_sub(1) as f64;
+                        if let Some(parent) = frames.last_mut() {
+                            parent.body_secs += frame.body_secs * frame.repeat as f64;
+                        }
+                    }
+                }
+            }
+            "wait" if !conditional => {
+                let secs = command.args[0].parse::<f64>().unwrap_or(0.0).max(0.0);
+                elapsed += secs;
+                if let Some(frame) = frames.last_mut() {
+                    frame.body_secs += secs;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Powtórzone kroki na tym samym selektorze w obrębie jednej strony
+fn check_duplicate_selectors(commands: &[DslCommand], diagnostics: &mut Vec<Diagnostic>) {
+    // Selektory pól wypełnionych w bieżącym bloku: (komenda, selektor) -> linia
+    let mut filled: Vec<HashMap<(String, String), usize>> = vec![HashMap::new()];
+    let mut previous: Option<&DslCommand> = None;
+
+    for command in commands {
+        match command.name.as_str() {
+            "if" | "repeat" | "for" | "frame" => {
+                filled.push(HashMap::new());
+                previous = None;
+                continue;
+            }
+            "end" => {
+                if filled.len() > 1 {
+                    filled.pop();
+                }
+                previous = None;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let (Some(prev), Some(selector)) = (previous, command.selector()) {
+            if prev.name == command.name && prev.args == command.args && command.name != "type" {
+                diagnostics.push(
+                    Diagnostic::new(
+                        command.line,
+                        Severity::Warning,
+                        "duplicate_command",
+                        format!("'{}' on '{}' repeats line {}", command.name, selector, prev.line),
+                    )
+                    .with_fix("Remove the repeated command", vec![Edit::Delete { line: command.line }]),
+                );
+            }
+        }
+
+        if matches!(command.name.as_str(), "type" | "select" | "upload") {
+            if let (Some(selector), Some(scope)) = (command.selector(), filled.last_mut()) {
+                let key = (command.name.clone(), selector.to_string());
+                if let Some(earlier) = scope.insert(key, command.line) {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            command.line,
+                            Severity::Warning,
+                            "duplicate_selector",
+                            format!("'{}' was already used on '{}' at line {}", command.name, selector, earlier),
+                        )
+                        .with_fix(format!("Remove the earlier '{}' at line {}", command.name, earlier), vec![Edit::Delete { line: earlier }]),
+                    );
+                }
+            }
+        }
+
+        // Po nawigacji te same selektory należą już do nowej strony
+        if is_navigation(command) {
+            if let Some(scope) = filled.last_mut() {
+                scope.clear();
+            }
+        }
+        previous = Some(command);
+    }
+}
+
+fn is_navigation(command: &DslCommand) -> bool {
+    match command.name.as_str() {
+        "press" => command.args.first().map(|key| key.eq_ignore_ascii_case("enter")).unwrap_or(false),
+        "click" => command
+            .selector()
+            .map(|selector| {
+                let selector = selector.to_lowercase();
+                let anchor = selector == "a" || ["a[", "a.", "a#", "//a"].iter().any(|prefix| selector.starts_with(prefix));
+                anchor || NAVIGATION_HINTS.iter().any(|hint| selector.contains(hint))
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Krok po nawigacji bez `wait`, `if present` ani `retry` trafia zwykle w ładującą się stronę
+fn check_navigation_waits(commands: &[DslCommand], lines: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    for (index, command) in commands.iter().enumerate() {
+        if !is_navigation(command) {
+            continue;
+        }
+        let Some(next) = commands[index + 1..].iter().find(|c| c.name != "end") else {
+            continue;
+        };
+        if !ELEMENT_COMMANDS.contains(&next.name.as_str()) || next.retry.is_some() {
+            continue;
+        }
+
+        diagnostics.push(
+            Diagnostic::new(
+                command.line,
+                Severity::Warning,
+                "missing_wait",
+                format!(
+                    "Line {} may navigate away, but line {} uses the page immediately; add a wait or a retry annotation",
+                    command.line, next.line
+                ),
+            )
+            .with_fix(
+                "Wait for the next page to load",
+                vec![Edit::InsertAfter { line: command.line, text: format!("{}wait 2", indent_of(lines, command.line)) }],
+            ),
+        );
+    }
+}
+
+fn credential_variable(selector: &str) -> Option<&'static str> {
+    let selector = selector.to_lowercase();
+    CREDENTIAL_FIELDS
+        .iter()
+        .find(|(hint, _)| selector.contains(hint))
+        .map(|(_, variable)| *variable)
+}
+
+/// Literal values typed into password-like fields end up in stored scripts, replays and logs
+fn check_credentials(commands: &[DslCommand], lines: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    for command in commands.iter().filter(|c| c.name == "type") {
+        let value = command.args[1..].join(" ");
+        if value.is_empty() || value.contains("{{") {
+            continue;
+        }
+        let Some(variable) = command.selector().and_then(credential_variable) else {
+            continue;
+        };
+
+        let original = lines.get(command.line - 1).map(|l| l.trim()).unwrap_or("");
+        let retry_suffix = match (&command.retry, original.rfind(" retry ")) {
+            (Some(_), Some(position)) => &original[position..],
+            _ => "",
+        };
+        let replacement = format!(
+            "{}type \"{}\" \"{{{{{}}}}}\"{}",
+            indent_of(lines, command.line),
+            crate::tagui::escape_for_dsl(&command.args[0]),
+            variable,
+            retry_suffix
+        );
+
+        diagnostics.push(
+            Diagnostic::new(
+                command.line,
+                Severity::Error,
+                "hardcoded_credential",
+                format!(
+                    "Hard-coded value typed into '{}'; pass it as '{{{{{}}}}}' via variables or secret_refs instead",
+                    command.args[0], variable
+                ),
+            )
+            .with_fix(format!("Use the '{}' variable", variable), vec![Edit::Replace { line: command.line, text: replacement }]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(usize, &'static str)> {
+        diagnostics.iter().map(|d| (d.line, d.code)).collect()
+    }
+
+    #[test]
+    fn test_lint_and_fix() {
+        let script = [
+            "type \"#email\" \"jan@example.com\"",
+            "type \"#password\" \"hunter2\"",
+            "click \"#login-button\"",
+            "click \"#profile\"",
+            "click \"#profile\"",
+            "if present \"#banner\"",
+            "end",
+        ]
+        .join("\n");
+
+        let diagnostics = lint_script(&script, None);
+        assert_eq!(
+            codes(&diagnostics),
+            vec![(2, "hardcoded_credential"), (3, "missing_wait"), (5, "duplicate_command"), (6, "empty_block")]
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        let fixed = apply_fixes(&script, &diagnostics);
+        assert_eq!(
+            fixed,
+            [
+                "type \"#email\" \"jan@example.com\"",
+                "type \"#password\" \"{{password}}\"",
+                "click \"#login-button\"",
+                "wait 2",
+                "click \"#profile\"",
+            ]
+            .join("\n")
+        );
+        assert!(lint_script(&fixed, None).is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_and_syntax() {
+        let script = "repeat 5\n  wait 200\nend\nclick \"#save\"\ntype \"#note\" \"a\"";
+        let diagnostics = lint_script(script, Some(Duration::from_secs(600)));
+        assert_eq!(codes(&diagnostics), vec![(4, "unreachable")]);
+        assert!(lint_script(script, Some(Duration::from_secs(1200))).is_empty());
+
+        // Czekanie w bloku warunkowym może się nie wykonać
+        assert!(lint_script("if present \"#x\"\nwait 900\nend\nclick \"#save\"", Some(Duration::from_secs(600))).is_empty());
+
+        assert_eq!(codes(&lint_script("clik \"#x\"", None)), vec![(1, "syntax")]);
+    }
+
+    #[test]
+    fn test_insert_navigation_waits() {
+        let script = [
+            "type \"#user\" \"jan\"",
+            "click \"#login-button\"",
+            "wait 3",
+            "type \"#otp\" \"{{otp}}\"",
+            "click \"#next\"",
+            "if present \"#banner\"",
+            "click \"#close\"",
+            "end",
+        ]
+        .join("\n");
+
+        let waited = insert_navigation_waits(&script);
+        assert_eq!(
+            waited,
+            [
+                "type \"#user\" \"jan\"",
+                "click \"#login-button\"",
+                "waitfor \"#otp\" timeout 10",
+                "type \"#otp\" \"{{otp}}\"",
+                "click \"#next\"",
+                "if present \"#banner\"",
+                "click \"#close\"",
+                "end",
+            ]
+            .join("\n")
+        );
+        // Nawigacja z `waitfor` nie wymaga już ostrzeżenia
+        assert!(lint_script(&waited, None).iter().all(|d| d.code != "missing_wait"));
+        assert_eq!(insert_navigation_waits(&waited), waited);
+    }
+}
@@ -0,0 +1,8 @@
+//! Narzędzia do analizy skryptów DSL ponad samą walidację składni (`tagui::validate_dsl_script`)
+
+pub mod cache_bundle;
+pub mod failures;
+pub mod generated;
+pub mod history;
+pub mod lint;
+pub mod preflight;
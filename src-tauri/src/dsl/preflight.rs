@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tagui::{DslCommand, DSL_BLOCK_KEYWORDS};
+
+/// Selektor kroku sprawdzany przed uruchomieniem skryptu
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectorCheck {
+    pub line: usize,
+    pub command: String,
+    pub selector: String,
+}
+
+/// Selektory kroków wykonywanych zawsze, na stronie startowej.
+/// Pomija bloki `if`/`for` (brak elementu jest tam oczekiwany), ramki `frame` (inny dokument),
+/// niepodstawione `{{zmienne}}` i `@item`, komendy z `retry` (element może dopiero się pojawić)
+/// oraz wszystko po `waitfor`, który zapowiada zmianę strony.
+pub fn unconditional_selectors(commands: &[DslCommand]) -> Vec<SelectorCheck> {
+    let mut checks: Vec<SelectorCheck> = Vec::new();
+    let mut depth = 0usize;
+
+    for command in commands {
+        match command.name.as_str() {
+            "end" => {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            name if DSL_BLOCK_KEYWORDS.contains(&name) => {
+                depth += 1;
+                continue;
+            }
+            // Po `waitfor` skrypt jest już na innej stronie niż ta sprawdzana teraz
+            "waitfor" if depth == 0 => break,
+            _ if depth > 0 => continue,
+            _ => {}
+        }
+
+        if command.retry.is_some() {
+            continue;
+        }
+        let Some(selector) = command.selector() else { continue };
+        if selector.contains("{{") || selector.contains("@item") {
+            continue;
+        }
+        if checks.iter().any(|check| check.selector == selector) {
+            continue;
+        }
+        checks.push(SelectorCheck { line: command.line, command: command.name.clone(), selector: selector.to_string() });
+    }
+    checks
+}
+
+/// Dla każdej komendy: czy jest warunkiem `if present` albo leży w jego bloku.
+/// Brak takiego elementu na stronie jest oczekiwany, więc dry run nie zgłasza go jako nierozwiązanego.
+pub fn guarded_by_if_present(commands: &[DslCommand]) -> Vec<bool> {
+    let mut blocks: Vec<&str> = Vec::new();
+    commands
+        .iter()
+        .map(|command| match command.name.as_str() {
+            "end" => {
+                let closed = blocks.pop();
+                closed == Some("if") || blocks.contains(&"if")
+            }
+            name if DSL_BLOCK_KEYWORDS.contains(&name) => {
+                blocks.push(name);
+                blocks.contains(&"if")
+            }
+            _ => blocks.contains(&"if"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tagui::parse_dsl_script;
+
+    #[test]
+    fn test_unconditional_selectors() {
+        let script = [
+            "click \"#accept\"",
+            "if present \"#banner\"",
+            "  click \"#close-banner\"",
+            "end",
+            "type \"#email\" \"{{email}}\"",
+            "click \"#{{button}}\"",
+            "click \"#accept\"",
+            "frame \"checkout\"",
+            "  click \"#pay\"",
+            "end",
+            "click \"#lazy-apply\" retry 3",
+            "click \"#submit\"",
+            "waitfor \"#dashboard\" timeout 10",
+            "click \"#logout\"",
+        ]
+        .join("\n");
+        let commands = parse_dsl_script(&script).unwrap();
+
+        let checks = unconditional_selectors(&commands);
+        let selectors: Vec<(usize, &str)> = checks.iter().map(|check| (check.line, check.selector.as_str())).collect();
+        assert_eq!(selectors, vec![(1, "#accept"), (5, "#email"), (12, "#submit")]);
+    }
+
+    #[test]
+    fn test_guarded_by_if_present() {
+        let script = "click \"#open\"\nif present \"#modal\"\nrepeat 2\nclick \"#close\"\nend\nend\nclick \"#submit\"";
+        let commands = parse_dsl_script(script).unwrap();
+        assert_eq!(guarded_by_if_present(&commands), vec![false, true, true, true, true, true, false]);
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cdp::{self, FormElement};
+use crate::dsl::lint::{self, Diagnostic, Severity};
+use crate::tagui::{ExecutionResult, RunManager, RunOptions};
+
+/// Strona pobrana przez CDP wraz z wykrytymi polami formularzy
+#[derive(Debug, Clone)]
+pub struct PageAnalysis {
+    pub url: String,
+    pub html: String,
+    pub form_elements: Vec<FormElement>,
+}
+
+/// Diagnostyki skryptu z `dsl::lint`
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    /// Skrypt da się uruchomić: brak błędów (ostrzeżenia nie blokują)
+    pub fn is_valid(&self) -> bool {
+        !self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+/// Wynik całego potoku `Engine::run_pipeline`
+#[derive(Debug, Clone)]
+pub struct PipelineOutcome {
+    pub analysis: PageAnalysis,
+    pub script: String,
+    pub validation: Validation,
+    pub run_id: String,
+    pub result: ExecutionResult,
+}
+
+/// Rdzeń automatyzacji bez serwera HTTP i okna Tauri: analiza strony, generowanie DSL,
+/// walidacja i wykonanie. Serwer `codialog` składa te same kroki w swoich endpointach.
+#[derive(Clone)]
+pub struct Engine {
+    run_manager: Arc<RunManager>,
+    dsl_cache: Option<PgPool>,
+    run_timeout: Option<Duration>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::with_run_manager(Arc::new(RunManager::default()))
+    }
+
+    /// Współdzielony menedżer przebiegów (limit równoległości, przeglądarka, silnik)
+    pub fn with_run_manager(run_manager: Arc<RunManager>) -> Self {
+        Self { run_manager, dsl_cache: None, run_timeout: None }
+    }
+
+    /// Cache wygenerowanych skryptów w tabeli `dsl_cache`
+    pub fn with_dsl_cache(mut self, pool: PgPool) -> Self {
+        self.dsl_cache = Some(pool);
+        self
+    }
+
+    /// Limit czasu przebiegu uwzględniany przy walidacji (kroki, które się w nim nie zmieszczą)
+    pub fn with_run_timeout(mut self, timeout: Duration) -> Self {
+        self.run_timeout = Some(timeout);
+        self
+    }
+
+    pub fn run_manager(&self) -> &Arc<RunManager> {
+        &self.run_manager
+    }
+
+    pub async fn analyze(&self, url: &str) -> Result<PageAnalysis> {
+        let html = cdp::get_page_html(url).await.map_err(|e| anyhow!("Failed to fetch {}: {}", url, e))?;
+        let form_elements = cdp::extract_form_elements(&html).await;
+        info!(url = %url, html_length = html.len(), form_elements = form_elements.len(), "Page analyzed");
+        Ok(PageAnalysis { url: url.to_string(), html, form_elements })
+    }
+
+    /// Skrypt DSL dla strony; bez klucza LLM lub przy błędzie zwraca skrypt zapasowy, nigdy pusty
+    pub async fn generate(&self, html: &str, user_data: &Value) -> String {
+        crate::llm::generate_dsl_script_with_cache(html, user_data, self.dsl_cache.as_ref()).await
+    }
+
+    pub fn validate(&self, script: &str) -> Validation {
+        Validation { diagnostics: lint::lint_script(script, self.run_timeout) }
+    }
+
+    pub async fn run(&self, script: &str, options: &RunOptions) -> (String, ExecutionResult) {
+        self.run_manager.execute_with(script, options).await
+    }
+
+    /// Analiza → generowanie → walidacja → wykonanie. Skrypt z błędami nie jest uruchamiany.
+    pub async fn run_pipeline(&self, url: &str, user_data: &Value, options: &RunOptions) -> Result<PipelineOutcome> {
+        let analysis = self.analyze(url).await?;
+        let script = self.generate(&analysis.html, user_data).await;
+        let validation = self.validate(&script);
+        if !validation.is_valid() {
+            let errors: Vec<String> = validation.errors().map(|error| format!("line {}: {}", error.line, error.message)).collect();
+            warn!(url = %url, "Generated script failed validation: {}", errors.join("; "));
+            bail!("Generated script is invalid: {}", errors.join("; "));
+        }
+
+        let (run_id, result) = self.run(&script, options).await;
+        Ok(PipelineOutcome { analysis, script, validation, run_id, result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_blocks_only_errors() {
+        let engine = Engine::new();
+        let validation = engine.validate("click \"#submit\"\nclick \"#submit\"");
+        assert!(validation.is_valid());
+        assert!(!validation.diagnostics.is_empty());
+
+        let validation = engine.validate("click \"#submit\"\nfly \"#away\"");
+        assert!(!validation.is_valid());
+        assert_eq!(validation.errors().next().map(|error| error.line), Some(2));
+    }
+}
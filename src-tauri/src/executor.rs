@@ -0,0 +1,582 @@
+//! Natywny interpreter DSL działający bezpośrednio na stronie chromiumoxide (CDP).
+//! Alternative to shelling out to TagUI for simple form fills; runs go through the same
+//! `RunManager` queue, cancellation and result format as TagUI runs.
+
+use anyhow::{Result, anyhow, bail};
+use chromiumoxide::cdp::browser_protocol::dom::SetFileInputFilesParams;
+use chromiumoxide::cdp::browser_protocol::input::InsertTextParams;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::{Browser, BrowserConfig, Element, Page};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{info, warn, debug};
+
+use crate::faults::{self, FaultTarget};
+use crate::pacing::{self, Pacer, PacingProfile};
+use crate::tagui::{
+    self, BrowserMode, DslCommand, ExecutionResult, ExecutionStatus, OutputSink, OutputStream, StepResult, StepStatus,
+};
+
+pub use codialog_types::automation::ExecutionBackend;
+
+/// Jak długo komenda czeka na pojawienie się elementu (odpowiednik domyślnego timeoutu TagUI)
+const ELEMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const ELEMENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Komenda lub blok sterujący z ciałem
+enum Node {
+    Command(DslCommand),
+    Block { command: DslCommand, body: Vec<Node> },
+}
+
+/// Nests the flat command list returned by `parse_dsl_script` (nesting is already validated there)
+fn build_tree(commands: Vec<DslCommand>) -> Vec<Node> {
+    let mut stack: Vec<(DslCommand, Vec<Node>)> = Vec::new();
+    let mut root = Vec::new();
+
+    for command in commands {
+        match command.name.as_str() {
+            "if" | "repeat" | "for" => stack.push((command, Vec::new())),
+            "end" => {
+                if let Some((command, body)) = stack.pop() {
+                    let node = Node::Block { command, body };
+                    match stack.last_mut() {
+                        Some((_, parent)) => parent.push(node),
+                        None => root.push(node),
+                    }
+                }
+            }
+            _ => match stack.last_mut() {
+                Some((_, parent)) => parent.push(Node::Command(command)),
+                None => root.push(Node::Command(command)),
+            },
+        }
+    }
+
+    root
+}
+
+/// Błąd komendy przerywający przebieg
+struct StepFailure {
+    line: usize,
+    message: String,
+}
+
+struct Interpreter<'a> {
+    page: &'a Page,
+    screenshot_dir: Option<&'a Path>,
+    output: Option<OutputSink>,
+    pacer: Pacer,
+    stdout: String,
+    statuses: HashMap<usize, StepStatus>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn emit(&mut self, line: &str) {
+        self.stdout.push_str(line);
+        self.stdout.push('\n');
+        if let Some(output) = &self.output {
+            output(OutputStream::Stdout, line);
+        }
+    }
+
+    fn run_block<'s>(
+        &'s mut self,
+        nodes: &'s [Node],
+        item: Option<String>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), StepFailure>> + Send + 's>> {
+        Box::pin(async move {
+            for node in nodes {
+                match node {
+                    Node::Command(command) => self.run_command(command, item.as_deref()).await?,
+                    Node::Block { command, body } => {
+                        match command.name.as_str() {
+                            "if" => {
+                                let selector = with_item(&command.args[1], item.as_deref());
+                                if count_elements(self.page, &selector).await > 0 {
+                                    self.run_block(body, item.clone()).await?;
+                                }
+                            }
+                            "repeat" => {
+                                let times: u32 = command.args[0].parse().unwrap_or(0);
+                                for _ in 0..times {
+                                    self.run_block(body, item.clone()).await?;
+                                }
+                            }
+                            "for" => {
+                                let selector = &command.args[1];
+                                let count = count_elements(self.page, selector).await;
+                                for index in 1..=count {
+                                    self.run_block(body, Some(format!("({})[{}]", selector, index))).await?;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn run_command(&mut self, command: &DslCommand, item: Option<&str>) -> Result<(), StepFailure> {
+        let args: Vec<String> = command.args.iter().map(|arg| with_item(arg, item)).collect();
+        self.emit(&format!("{} {}", command.name, args.iter().map(|arg| format!("\"{}\"", tagui::escape_for_dsl(arg))).collect::<Vec<_>>().join(" ")));
+
+        // Ponawianie: nieudana komenda jest wykonywana ponownie po przerwie z backoffem, do `retry.attempts` prób
+        let attempts = command.retry.as_ref().filter(|_| command.selector().is_some()).map(|retry| retry.attempts).unwrap_or(1);
+        let mut attempt = 1;
+        loop {
+            match perform(self.page, &command.name, &args, Some(&mut self.pacer)).await {
+                Ok(()) => break,
+                Err(e) if attempt < attempts => {
+                    attempt += 1;
+                    let delay = command.retry.as_ref().map(|retry| retry.delay_before(attempt)).unwrap_or_default();
+                    debug!(line = command.line, "Step failed ({}), retry attempt {} in {}s", e, attempt, delay);
+                    tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+                }
+                Err(e) => {
+                    self.statuses.insert(command.line, StepStatus::Failed);
+                    return Err(StepFailure { line: command.line, message: e.to_string() });
+                }
+            }
+        }
+        self.statuses.entry(command.line).or_insert(StepStatus::Succeeded);
+
+        if let Some(dir) = self.screenshot_dir {
+            let path = dir.join(tagui::screenshot_file_name(command.line));
+            if let Err(e) = self.page.save_screenshot(ScreenshotParams::builder().build(), &path).await {
+                warn!(line = command.line, "Failed to save screenshot: {}", e);
+            }
+        }
+        let step_delay = self.pacer.step_delay();
+        if !step_delay.is_zero() {
+            tokio::time::sleep(step_delay).await;
+        }
+        Ok(())
+    }
+}
+
+fn with_item(value: &str, item: Option<&str>) -> String {
+    match item {
+        Some(item) => value.replace(tagui::FOR_EACH_ITEM, item),
+        None => value.to_string(),
+    }
+}
+
+/// Wykonuje pojedynczą komendę na stronie; z `pacer` w profilu `human` tekst jest wpisywany znak po znaku
+async fn perform(page: &Page, name: &str, args: &[String], pacer: Option<&mut Pacer>) -> Result<()> {
+    match name {
+        "click" => {
+            find_element(page, &args[0]).await?.click().await?;
+        }
+        "hover" => {
+            find_element(page, &args[0]).await?.hover().await?;
+        }
+        "type" => {
+            let element = find_element(page, &args[0]).await?;
+            element.click().await?;
+            element.call_js_fn("function() { if ('value' in this) this.value = ''; }", false).await?;
+            let text = args[1..].join(" ");
+            if let Some(pacer) = pacer.filter(|pacer| pacer.types_by_keystroke()) {
+                for key in pacing::keystrokes(&text) {
+                    if key == "[enter]" {
+                        element.press_key("Enter").await?;
+                    } else {
+                        page.execute(InsertTextParams::new(key)).await?;
+                    }
+                    tokio::time::sleep(pacer.keystroke_delay()).await;
+                }
+                return Ok(());
+            }
+            for (index, part) in text.split("[enter]").enumerate() {
+                if index > 0 {
+                    element.press_key("Enter").await?;
+                }
+                if !part.is_empty() {
+                    page.execute(InsertTextParams::new(part)).await?;
+                }
+            }
+        }
+        "upload" => {
+            let element = find_element(page, &args[0]).await?;
+            let files: Vec<String> = args[1..].iter().map(|file| absolute_path(file)).collect();
+            let params = SetFileInputFilesParams::builder()
+                .files(files)
+                .backend_node_id(element.backend_node_id)
+                .build()
+                .map_err(|e| anyhow!(e))?;
+            page.execute(params).await?;
+        }
+        "select" => {
+            let element = find_element(page, &args[0]).await?;
+            let function = format!(
+                "function() {{ const wanted = {}; \
+                 const option = Array.from(this.options || []).find(o => o.value === wanted || o.text.trim() === wanted); \
+                 if (!option) return false; this.value = option.value; \
+                 this.dispatchEvent(new Event('input', {{ bubbles: true }})); \
+                 this.dispatchEvent(new Event('change', {{ bubbles: true }})); return true; }}",
+                serde_json::to_string(&args[1])?
+            );
+            let selected = element.call_js_fn(function, false).await?;
+            if selected.result.value != Some(serde_json::Value::Bool(true)) {
+                bail!("Option '{}' not found in {}", args[1], args[0]);
+            }
+        }
+        "check" | "uncheck" => {
+            let element = find_element(page, &args[0]).await?;
+            let checked = name == "check";
+            element
+                .call_js_fn(format!("function() {{ if (this.checked !== {}) this.click(); }}", checked), false)
+                .await?;
+        }
+        "press" => {
+            let key = cdp_key(&args[0]).ok_or_else(|| anyhow!("Key '{}' is not supported by the CDP backend", args[0]))?;
+            page.find_element("body").await?.press_key(key).await?;
+        }
+        "scroll" => {
+            let pixels: i64 = args.get(1).and_then(|pixels| pixels.parse().ok()).unwrap_or(500);
+            match args[0].as_str() {
+                "down" => { page.evaluate(format!("window.scrollBy(0, {})", pixels)).await?; }
+                "up" => { page.evaluate(format!("window.scrollBy(0, -{})", pixels)).await?; }
+                "top" => { page.evaluate("window.scrollTo(0, 0)").await?; }
+                "bottom" => { page.evaluate("window.scrollTo(0, document.body.scrollHeight)").await?; }
+                selector => { find_element(page, selector).await?.scroll_into_view().await?; }
+            }
+        }
+        "wait" => {
+            let seconds: f64 = args[0].parse().unwrap_or(0.0);
+            tokio::time::sleep(Duration::from_secs_f64(seconds.max(0.0))).await;
+        }
+        "waitfor" => {
+            let seconds: f64 = args.get(2).and_then(|secs| secs.parse().ok()).unwrap_or(tagui::DEFAULT_WAITFOR_TIMEOUT_SECS as f64);
+            find_element_within(page, &args[0], Duration::from_secs_f64(seconds.max(0.0))).await?;
+        }
+        other => bail!("Command '{}' is not supported by the CDP backend", other),
+    }
+    Ok(())
+}
+
+/// Element po selektorze CSS lub XPath, czekając do `ELEMENT_TIMEOUT` na jego pojawienie się
+async fn find_element(page: &Page, selector: &str) -> Result<Element> {
+    find_element_within(page, selector, ELEMENT_TIMEOUT).await
+}
+
+async fn find_element_within(page: &Page, selector: &str, timeout: Duration) -> Result<Element> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let found = if tagui::is_xpath(selector) {
+            page.find_xpath(selector).await
+        } else {
+            page.find_element(selector).await
+        };
+        match found {
+            Ok(element) => return Ok(element),
+            Err(_) if Instant::now() < deadline => tokio::time::sleep(ELEMENT_POLL_INTERVAL).await,
+            Err(e) => bail!("Element '{}' not found after {}s: {}", selector, timeout.as_secs_f64(), e),
+        }
+    }
+}
+
+async fn count_elements(page: &Page, selector: &str) -> usize {
+    let found = if tagui::is_xpath(selector) {
+        page.find_xpaths(selector).await
+    } else {
+        page.find_elements(selector).await
+    };
+    // Brak dopasowań CDP zgłasza jako błąd wyszukiwania
+    found.map(|elements| elements.len()).unwrap_or(0)
+}
+
+fn absolute_path(file: &str) -> String {
+    std::fs::canonicalize(file)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| file.to_string())
+}
+
+/// Nazwa klawisza DSL w układzie klawiatury chromiumoxide; kombinacje z modyfikatorami nie są obsługiwane
+fn cdp_key(key: &str) -> Option<String> {
+    let lowered = key.trim().to_lowercase();
+    let named = match lowered.as_str() {
+        "enter" => "Enter",
+        "tab" => "Tab",
+        "esc" | "escape" => "Escape",
+        "backspace" => "Backspace",
+        "delete" => "Delete",
+        "insert" => "Insert",
+        "space" => " ",
+        "up" => "ArrowUp",
+        "down" => "ArrowDown",
+        "left" => "ArrowLeft",
+        "right" => "ArrowRight",
+        "pageup" => "PageUp",
+        "pagedown" => "PageDown",
+        "home" => "Home",
+        "end" => "End",
+        function if function.len() <= 3 && function.starts_with('f') && function[1..].parse::<u8>().is_ok_and(|n| (1..=12).contains(&n)) => {
+            return Some(function.to_uppercase());
+        }
+        _ => {
+            let mut chars = key.trim().chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c.to_string()),
+                _ => None,
+            };
+        }
+    };
+    Some(named.to_string())
+}
+
+/// Uruchamia Chrome sterowany przez CDP wraz z zadaniem obsługującym jego zdarzenia
+async fn launch_browser(browser_mode: BrowserMode) -> Result<(Browser, JoinHandle<()>)> {
+    let config = match browser_mode {
+        BrowserMode::Headless => BrowserConfig::builder(),
+        BrowserMode::Headed => BrowserConfig::builder().with_head(),
+        BrowserMode::Edge | BrowserMode::Firefox => bail!("The CDP backend only supports Chrome, not {:?}", browser_mode),
+    };
+    faults::inject(FaultTarget::Spawn).await?;
+    let config = config.build().map_err(|e| anyhow!("Failed to launch Chrome: {}", e))?;
+    let (browser, mut handler) = Browser::launch(config).await.map_err(|e| anyhow!("Failed to launch Chrome: {}", e))?;
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+    Ok((browser, handle))
+}
+
+/// Wykonuje skrypt DSL w nowej przeglądarce Chrome sterowanej przez CDP.
+/// The result has the same shape as a TagUI run: echoed commands in `stdout`, per-line step
+/// statuses and an `ERROR` line for the failing command.
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_script(
+    dsl_script: &str,
+    start_url: Option<&str>,
+    cancel: Arc<Notify>,
+    screenshot_dir: Option<&Path>,
+    browser_mode: BrowserMode,
+    pacing: PacingProfile,
+    timeout: Option<Duration>,
+    output: Option<OutputSink>,
+) -> ExecutionResult {
+    info!("Executing DSL script over CDP in {:?} browser mode with {} pacing", browser_mode, pacing.as_str());
+    let started = Instant::now();
+
+    let commands = match tagui::parse_dsl_script(dsl_script) {
+        Ok(commands) => commands,
+        Err(e) => {
+            warn!("Invalid DSL script: {}", e);
+            return ExecutionResult::early_failure(ExecutionStatus::InvalidScript, e.to_string(), Some(e.line), started);
+        }
+    };
+    // Elementy iframe wymagają przełączenia kontekstu, którego ten interpreter jeszcze nie obsługuje
+    if let Some(frame) = commands.iter().find(|command| command.name == "frame") {
+        return ExecutionResult::early_failure(
+            ExecutionStatus::InvalidScript,
+            "'frame' blocks require the TagUI backend".to_string(),
+            Some(frame.line),
+            started,
+        );
+    }
+
+    let (mut browser, handle) = match launch_browser(browser_mode).await {
+        Ok(launched) => launched,
+        Err(e) => {
+            warn!("Failed to start the CDP browser: {}", e);
+            return ExecutionResult::early_failure(ExecutionStatus::SpawnError, e.to_string(), None, started);
+        }
+    };
+
+    let tree = build_tree(commands);
+    let (outcome, stdout, statuses, page_html) = match browser.new_page(start_url.unwrap_or("about:blank")).await {
+        Ok(page) => {
+            let mut interpreter = Interpreter {
+                page: &page,
+                screenshot_dir,
+                output,
+                pacer: Pacer::new(pacing),
+                stdout: String::new(),
+                statuses: HashMap::new(),
+            };
+            let outcome = tokio::select! {
+                result = interpreter.run_block(&tree, None) => match result {
+                    Ok(()) => Ok(()),
+                    Err(failure) => {
+                        interpreter.emit(&format!("ERROR - {}", failure.message));
+                        Err((ExecutionStatus::Failed, Some(failure.line), failure.message))
+                    }
+                },
+                _ = cancel.notified() => {
+                    info!("Cancelling CDP execution");
+                    Err((ExecutionStatus::Cancelled, None, "Execution cancelled".to_string()))
+                }
+                _ = tagui::run_deadline(timeout) => {
+                    warn!("CDP execution exceeded {:?}, closing the browser", timeout.unwrap_or_default());
+                    Err((
+                        ExecutionStatus::TimedOut,
+                        None,
+                        format!("Execution timed out after {}s", timeout.unwrap_or_default().as_secs()),
+                    ))
+                }
+            };
+            // Strona z chwili błędu kroku, zanim przeglądarka zostanie zamknięta
+            let page_html = match &outcome {
+                Err((ExecutionStatus::Failed, Some(_), _)) => page.content().await.ok(),
+                _ => None,
+            };
+            (outcome, interpreter.stdout, interpreter.statuses, page_html)
+        }
+        Err(e) => (
+            Err((ExecutionStatus::Failed, None, format!("Failed to open {}: {}", start_url.unwrap_or("about:blank"), e))),
+            String::new(),
+            HashMap::new(),
+            None,
+        ),
+    };
+
+    if let Err(e) = browser.close().await {
+        debug!("Browser already closed: {}", e);
+    }
+    handle.abort();
+
+    // Kroki w kolejności skryptu; komendy, do których przebieg nie doszedł, są pominięte
+    let steps = tagui::script_commands(dsl_script)
+        .into_iter()
+        .filter(|(_, command)| !command.split_whitespace().next().is_some_and(|word| tagui::DSL_BLOCK_KEYWORDS.contains(&word)))
+        .map(|(line, command)| StepResult { line, command, status: statuses.get(&line).copied().unwrap_or(StepStatus::Skipped) })
+        .collect();
+
+    let (status, failed_line, error) = match outcome {
+        Ok(()) => (ExecutionStatus::Succeeded, None, None),
+        Err((status, failed_line, error)) => (status, failed_line, Some(error)),
+    };
+    if status == ExecutionStatus::Succeeded {
+        info!("CDP script executed successfully");
+    } else {
+        warn!("CDP execution ended with {:?}: {}", status, error.as_deref().unwrap_or_default());
+    }
+
+    ExecutionResult {
+        status,
+        exit_code: None,
+        stdout,
+        stderr: String::new(),
+        steps,
+        duration_ms: started.elapsed().as_millis() as u64,
+        failed_line,
+        error,
+        page_html,
+    }
+}
+
+/// Wynik pojedynczej komendy wykonanej w `LiveSession`
+#[derive(Debug, Clone)]
+pub struct CommandFeedback {
+    pub selector: Option<String>,
+    /// Liczba pasujących elementów tuż przed wykonaniem komendy
+    pub matches: Option<usize>,
+    pub error: Option<String>,
+    pub elapsed: Duration,
+}
+
+/// Jedna przeglądarka z otwartą stroną, na której komendy DSL wykonuje się pojedynczo (`codialog repl`)
+pub struct LiveSession {
+    browser: Browser,
+    page: Page,
+    handle: JoinHandle<()>,
+}
+
+impl LiveSession {
+    pub async fn launch(start_url: Option<&str>, browser_mode: BrowserMode) -> Result<Self> {
+        let (mut browser, handle) = launch_browser(browser_mode).await?;
+        match browser.new_page(start_url.unwrap_or("about:blank")).await {
+            Ok(page) => Ok(Self { browser, page, handle }),
+            Err(e) => {
+                let _ = browser.close().await;
+                handle.abort();
+                bail!("Failed to open {}: {}", start_url.unwrap_or("about:blank"), e)
+            }
+        }
+    }
+
+    /// Wykonuje jedną komendę (bez bloków); `retry` jest pomijane - komendę można po prostu powtórzyć
+    pub async fn execute(&self, command: &DslCommand) -> CommandFeedback {
+        let started = Instant::now();
+        let selector = command.selector().map(str::to_string);
+        let matches = match &selector {
+            Some(selector) => Some(count_elements(&self.page, selector).await),
+            None => None,
+        };
+        let error = if tagui::DSL_BLOCK_KEYWORDS.contains(&command.name.as_str()) {
+            Some(format!("'{}' blocks cannot be run one command at a time", command.name))
+        } else {
+            perform(&self.page, &command.name, &command.args, None).await.err().map(|e| e.to_string())
+        };
+        CommandFeedback { selector, matches, error, elapsed: started.elapsed() }
+    }
+
+    pub async fn open(&self, url: &str) -> Result<()> {
+        self.page.goto(url).await?;
+        Ok(())
+    }
+
+    pub async fn url(&self) -> Option<String> {
+        self.page.url().await.ok().flatten()
+    }
+
+    pub async fn title(&self) -> Option<String> {
+        self.page.get_title().await.ok().flatten()
+    }
+
+    /// Wynik wyrażenia JavaScript na otwartej stronie (obietnice są rozwiązywane); `null` dla `undefined`
+    pub async fn evaluate(&self, expression: &str) -> Result<serde_json::Value> {
+        let result = self.page.evaluate(expression).await?;
+        Ok(result.value().cloned().unwrap_or_default())
+    }
+
+    pub async fn screenshot(&self, path: &Path) -> Result<()> {
+        self.page.save_screenshot(ScreenshotParams::builder().build(), path).await?;
+        Ok(())
+    }
+
+    pub async fn close(mut self) {
+        if let Err(e) = self.browser.close().await {
+            debug!("Browser already closed: {}", e);
+        }
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_tree_and_keys() {
+        let script = "click \"#open\"\nfor each \"//li\"\nif present \"@item\"\nclick \"@item\"\nend\nend\npress enter";
+        let commands = tagui::parse_dsl_script(script);
+        // `@item` w selektorze `if` jest dozwolony tylko wewnątrz `for each` z XPath
+        let tree = build_tree(commands.unwrap());
+        assert_eq!(tree.len(), 3);
+        match &tree[1] {
+            Node::Block { command, body } => {
+                assert_eq!(command.name, "for");
+                assert!(matches!(&body[0], Node::Block { body, .. } if body.len() == 1));
+            }
+            Node::Command(_) => panic!("expected a block"),
+        }
+
+        assert_eq!(cdp_key("enter").as_deref(), Some("Enter"));
+        assert_eq!(cdp_key("F5").as_deref(), Some("F5"));
+        assert_eq!(cdp_key("a").as_deref(), Some("a"));
+        assert_eq!(cdp_key("ctrl+a"), None);
+        assert_eq!(ExecutionBackend::parse("native"), Some(ExecutionBackend::Cdp));
+        assert_eq!(with_item("@item//a", Some("(//li)[2]")), "(//li)[2]//a");
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use tracing::warn;
+
+/// Operacje zewnętrzne, w które można wstrzykiwać awarie
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultTarget {
+    Db,
+    Redis,
+    Llm,
+    Spawn,
+}
+
+impl FaultTarget {
+    #[cfg(feature = "fault_injection")]
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "db" => Some(FaultTarget::Db),
+            "redis" => Some(FaultTarget::Redis),
+            "llm" => Some(FaultTarget::Llm),
+            "spawn" => Some(FaultTarget::Spawn),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "fault_injection")]
+mod injector {
+    use super::FaultTarget;
+    use anyhow::{Result, anyhow, bail};
+    use ring::rand::SystemRandom;
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use tracing::{info, debug};
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct FaultRule {
+        pub fail_probability: f64,
+        pub delay_probability: f64,
+        pub delay: Duration,
+    }
+
+    pub struct FaultInjector {
+        pub(super) rules: HashMap<FaultTarget, FaultRule>,
+        rng: SystemRandom,
+    }
+
+    /// `None` oznacza brak reguł; wypełniane przez `install` albo leniwie z FAULT_INJECTION przy pierwszym `inject`
+    pub static INJECTOR: OnceLock<Option<FaultInjector>> = OnceLock::new();
+
+    impl FaultInjector {
+        /// Parses `target:action=probability[@delay]` entries separated by commas,
+        /// e.g. `db:fail=0.1,redis:delay=0.5@300ms,spawn:fail=1`.
+        pub fn parse(spec: &str) -> Result<Self> {
+            let mut rules: HashMap<FaultTarget, FaultRule> = HashMap::new();
+
+            for entry in spec.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+                let (target, rest) = entry.split_once(':').ok_or_else(|| anyhow!("Missing ':' in fault entry '{}'", entry))?;
+                let (action, value) = rest.split_once('=').ok_or_else(|| anyhow!("Missing '=' in fault entry '{}'", entry))?;
+                let target = FaultTarget::parse(target).ok_or_else(|| anyhow!("Unknown fault target '{}'", target))?;
+
+                let (probability, delay) = match value.split_once('@') {
+                    Some((probability, delay)) => (probability, Some(delay)),
+                    None => (value, None),
+                };
+                let probability: f64 = probability.trim().parse().map_err(|_| anyhow!("Invalid probability in '{}'", entry))?;
+                if !(0.0..=1.0).contains(&probability) {
+                    bail!("Probability must be between 0 and 1 in '{}'", entry);
+                }
+
+                let rule = rules.entry(target).or_default();
+                match action.trim() {
+                    "fail" => rule.fail_probability = probability,
+                    "delay" => {
+                        rule.delay_probability = probability;
+                        rule.delay = match delay {
+                            Some(delay) => parse_delay(delay).ok_or_else(|| anyhow!("Invalid delay in '{}'", entry))?,
+                            None => Duration::from_secs(1),
+                        };
+                    }
+                    other => bail!("Unknown fault action '{}'", other),
+                }
+            }
+
+            Ok(Self { rules, rng: SystemRandom::new() })
+        }
+
+        fn roll(&self, probability: f64) -> bool {
+            if probability <= 0.0 {
+                return false;
+            }
+            let bytes: [u8; 4] = match ring::rand::generate(&self.rng) {
+                Ok(random) => random.expose(),
+                Err(_) => return false,
+            };
+            (u32::from_le_bytes(bytes) as f64 / (u32::MAX as f64 + 1.0)) < probability
+        }
+
+        pub async fn apply(&self, target: FaultTarget) -> Result<()> {
+            let Some(rule) = self.rules.get(&target) else {
+                return Ok(());
+            };
+
+            if self.roll(rule.delay_probability) {
+                debug!("Injecting {:?} delay for {:?}", rule.delay, target);
+                tokio::time::sleep(rule.delay).await;
+            }
+            if self.roll(rule.fail_probability) {
+                info!("Injecting failure for {:?}", target);
+                bail!("Injected fault: {:?} operation failed", target);
+            }
+            Ok(())
+        }
+    }
+
+    fn parse_delay(value: &str) -> Option<Duration> {
+        let value = value.trim();
+        if let Some(ms) = value.strip_suffix("ms") {
+            ms.trim().parse().ok().map(Duration::from_millis)
+        } else if let Some(secs) = value.strip_suffix('s') {
+            secs.trim().parse().ok().map(Duration::from_secs_f64)
+        } else {
+            value.parse().ok().map(Duration::from_millis)
+        }
+    }
+}
+
+/// Włącza wstrzykiwanie awarii według specyfikacji z FAULT_INJECTION (tylko z feature `fault_injection`)
+pub fn install(spec: &str) {
+    #[cfg(feature = "fault_injection")]
+    if let Some(parsed) = parse_spec(spec) {
+        let _ = injector::INJECTOR.set(Some(parsed));
+    }
+
+    #[cfg(not(feature = "fault_injection"))]
+    warn!("FAULT_INJECTION is set ({}) but this build lacks the fault_injection feature; ignoring", spec);
+}
+
+#[cfg(feature = "fault_injection")]
+fn parse_spec(spec: &str) -> Option<injector::FaultInjector> {
+    match injector::FaultInjector::parse(spec) {
+        Ok(parsed) => {
+            warn!("Fault injection enabled: {}", spec);
+            Some(parsed)
+        }
+        Err(e) => {
+            warn!("Ignoring invalid FAULT_INJECTION spec: {}", e);
+            None
+        }
+    }
+}
+
+/// Może opóźnić lub przerwać operację `target`; bez skonfigurowanych reguł nic nie robi.
+/// Bez wcześniejszego `install` (np. w testach z `make test-chaos`) reguły są czytane z FAULT_INJECTION przy pierwszym wywołaniu.
+pub async fn inject(target: FaultTarget) -> Result<()> {
+    #[cfg(feature = "fault_injection")]
+    {
+        let installed = injector::INJECTOR.get_or_init(|| {
+            std::env::var("FAULT_INJECTION").ok().filter(|spec| !spec.trim().is_empty()).and_then(|spec| parse_spec(&spec))
+        });
+        if let Some(injector) = installed {
+            return injector.apply(target).await;
+        }
+    }
+
+    #[cfg(not(feature = "fault_injection"))]
+    let _ = target;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "fault_injection"))]
+mod tests {
+    use super::*;
+    use super::injector::FaultInjector;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_fault_spec() {
+        let injector = FaultInjector::parse("db:fail=0.25, redis:delay=0.5@300ms,db:delay=1").unwrap();
+        let db = &injector.rules[&FaultTarget::Db];
+        assert_eq!(db.fail_probability, 0.25);
+        assert_eq!(db.delay, Duration::from_secs(1));
+        assert_eq!(injector.rules[&FaultTarget::Redis].delay, Duration::from_millis(300));
+
+        assert!(FaultInjector::parse("disk:fail=0.1").is_err());
+        assert!(FaultInjector::parse("db:fail=2").is_err());
+        assert!(FaultInjector::parse("db:explode=0.1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_certain_failure_and_untouched_targets() {
+        let injector = FaultInjector::parse("spawn:fail=1,llm:fail=0").unwrap();
+        assert!(injector.apply(FaultTarget::Spawn).await.is_err());
+        assert!(injector.apply(FaultTarget::Llm).await.is_ok());
+        assert!(injector.apply(FaultTarget::Db).await.is_ok());
+    }
+}
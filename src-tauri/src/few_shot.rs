@@ -0,0 +1,255 @@
+//! Przykłady few-shot do promptu generowania DSL: skrypty, które już zadziałały na tej samej witrynie
+//! (udane przebiegi z `automation_runs`) albo na formularzu o podobnym szkielecie (`dsl_cache`).
+//! Do modelu trafiają tylko skrypty wpisujące dane przez zmienne `{{nazwa}}`, bez wartości użytkowników.
+
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use tracing::{debug, info, warn};
+use std::collections::HashSet;
+
+use codialog_types::automation::PromptLanguage;
+
+use crate::{cdp, crypto, dom, tagui};
+
+/// Ile przykładów dołączyć do promptu
+pub const MAX_EXAMPLES: usize = 2;
+
+/// Minimalne podobieństwo (Jaccard pól formularza) wpisu cache do bieżącej strony
+const MIN_STRUCTURE_SIMILARITY: f64 = 0.5;
+
+/// Dłuższe skrypty zajmowałyby za dużo promptu
+const MAX_EXAMPLE_CHARS: usize = 1500;
+
+/// Ilu kandydatów z każdego źródła sprawdzać
+const CANDIDATE_LIMIT: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleSource {
+    /// Udany przebieg na tej samej witrynie
+    SameSite,
+    /// Skrypt z cache dla formularza o podobnych polach
+    SimilarForm,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FewShotExample {
+    pub source: ExampleSource,
+    /// 1.0 dla tej samej witryny, dla cache - podobieństwo pól
+    pub score: f64,
+    pub script: String,
+}
+
+/// Pola formularza z `dom::structure_signature` (tag, typ, name/id) bez wartości opcji,
+/// które w zapisanym HTML są usuwane
+fn structure_tokens(signature: &str) -> HashSet<String> {
+    signature
+        .split(['(', ')', '[', ']'])
+        .filter(|token| !token.is_empty())
+        .map(|token| token.split('=').next().unwrap_or_default().to_string())
+        .collect()
+}
+
+/// Podobieństwo Jaccarda zbiorów pól dwóch szkieletów formularzy
+pub fn structure_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (structure_tokens(a), structure_tokens(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Czy skrypt może posłużyć za przykład: poprawny, niezbyt długi i wpisujący tekst oraz pliki
+/// wyłącznie przez zmienne - dosłowne wartości mogą być danymi innego użytkownika
+pub fn is_shareable(script: &str) -> bool {
+    !script.trim().is_empty() && script.chars().count() <= MAX_EXAMPLE_CHARS && is_templated(script)
+}
+
+/// Czy skrypt wpisuje tekst i pliki wyłącznie przez `{{zmienne}}`, bez dosłownych wartości
+pub fn is_templated(script: &str) -> bool {
+    let Ok(commands) = tagui::parse_dsl_script(script) else {
+        return false;
+    };
+    commands
+        .iter()
+        .filter(|command| matches!(command.name.as_str(), "type" | "upload"))
+        .all(|command| {
+            command.args[1..].iter().all(|value| {
+                let value = value.trim();
+                value.starts_with("{{") && value.ends_with("}}") && value.matches("{{").count() == 1
+            })
+        })
+}
+
+/// Najlepsze przykłady: najpierw najnowsze udane przebiegi z tej samej witryny, potem skrypty
+/// z cache dla najbardziej podobnych formularzy; bez powtórzeń
+pub fn rank(same_site: Vec<String>, similar_forms: Vec<(f64, String)>) -> Vec<FewShotExample> {
+    let mut similar_forms: Vec<(f64, String)> = similar_forms.into_iter().filter(|(score, _)| *score >= MIN_STRUCTURE_SIMILARITY).collect();
+    similar_forms.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let candidates = same_site
+        .into_iter()
+        .map(|script| FewShotExample { source: ExampleSource::SameSite, score: 1.0, script })
+        .chain(similar_forms.into_iter().map(|(score, script)| FewShotExample { source: ExampleSource::SimilarForm, score, script }));
+
+    let mut seen = HashSet::new();
+    candidates
+        .filter(|example| is_shareable(&example.script))
+        .filter(|example| seen.insert(example.script.trim().to_string()))
+        .take(MAX_EXAMPLES)
+        .collect()
+}
+
+/// Przykłady dla strony; błąd bazy oznacza prompt bez przykładów
+pub async fn find_examples(pool: &PgPool, html: &str, site: Option<&str>) -> Vec<FewShotExample> {
+    let same_site = match site {
+        Some(site) => successful_scripts_for_site(pool, site).await.unwrap_or_else(|e| {
+            warn!("Failed to load successful runs for few-shot examples: {}", e);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let similar_forms = similar_cached_scripts(pool, html).await.unwrap_or_else(|e| {
+        warn!("Failed to load cached scripts for few-shot examples: {}", e);
+        Vec::new()
+    });
+
+    let examples = rank(same_site, similar_forms);
+    debug!(site = ?site, examples = examples.len(), "Selected few-shot examples for DSL prompt");
+    examples
+}
+
+async fn successful_scripts_for_site(pool: &PgPool, site: &str) -> anyhow::Result<Vec<String>> {
+    // Wstępny filtr w SQL, dokładne porównanie hosta niżej
+    let rows = sqlx::query(
+        r#"
+        SELECT script, parameters->>'url' AS url
+        FROM automation_runs
+        WHERE status = 'succeeded' AND script <> '' AND parameters->>'url' ILIKE '%' || $1 || '%'
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(site)
+    .bind(CANDIDATE_LIMIT)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .filter(|row| row.get::<Option<String>, _>("url").as_deref().and_then(cdp::site_of).as_deref() == Some(site))
+        .map(|row| row.get("script"))
+        .collect())
+}
+
+/// Posortowane pola formularza strony, zapisywane z wpisem cache w `dsl_cache.form_fields`
+pub fn form_fields(html: &str) -> Vec<String> {
+    let mut fields: Vec<String> = structure_tokens(&dom::structure_signature(&cdp::sanitize_html(html))).into_iter().collect();
+    fields.sort();
+    fields
+}
+
+/// Skrypty z cache z podobieństwem ich formularza do bieżącej strony, liczonym w SQL na zapisanych polach
+async fn similar_cached_scripts(pool: &PgPool, html: &str) -> anyhow::Result<Vec<(f64, String)>> {
+    let fields = form_fields(html);
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rows = sqlx::query(
+        r#"
+        SELECT script_content, score
+        FROM (
+            SELECT script_content, created_at,
+                (SELECT COUNT(*) FROM (SELECT unnest(form_fields) INTERSECT SELECT unnest($1::text[])) shared)::float8
+                    / (SELECT COUNT(*) FROM (SELECT unnest(form_fields) UNION SELECT unnest($1::text[])) total)::float8 AS score
+            FROM dsl_cache
+            WHERE form_fields && $1::text[]
+        ) candidates
+        WHERE score >= $2
+        ORDER BY score DESC, created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(&fields)
+    .bind(MIN_STRUCTURE_SIMILARITY)
+    .bind(CANDIDATE_LIMIT)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|row| (row.get("score"), row.get("script_content"))).collect())
+}
+
+/// Uzupełnia `form_fields` wpisów zapisanych przed tą kolumną; ich HTML da się odczytać tylko z kluczem
+pub async fn backfill_form_fields(pool: &PgPool) -> anyhow::Result<u64> {
+    if crypto::content_cipher().is_none() {
+        return Ok(0);
+    }
+    let rows = sqlx::query("SELECT cache_key, html_content FROM dsl_cache WHERE form_fields IS NULL AND html_content IS NOT NULL")
+        .fetch_all(pool)
+        .await?;
+
+    let mut filled = 0;
+    for row in rows {
+        let Ok(html) = crypto::decrypt_any(&row.get::<String, _>("html_content")) else {
+            continue;
+        };
+        sqlx::query("UPDATE dsl_cache SET form_fields = $2 WHERE cache_key = $1")
+            .bind(row.get::<String, _>("cache_key"))
+            .bind(form_fields(&html))
+            .execute(pool)
+            .await?;
+        filled += 1;
+    }
+
+    if filled > 0 {
+        info!("Stored form fields of {} cached DSL scripts", filled);
+    }
+    Ok(filled)
+}
+
+/// Sekcja promptu z przykładami; pusta, gdy ich nie ma
+pub fn render_examples(examples: &[FewShotExample], language: PromptLanguage) -> String {
+    if examples.is_empty() {
+        return String::new();
+    }
+    let heading = match language {
+        PromptLanguage::Pl => "Przykłady skryptów, które zadziałały na podobnych stronach (wzoruj się na selektorach i kolejności kroków):",
+        PromptLanguage::En => "Examples of scripts that worked on similar pages (follow their selectors and order of steps):",
+    };
+    let mut section = format!("\n{}\n", heading);
+    for (index, example) in examples.iter().enumerate() {
+        section.push_str(&format!("\n### {}\n{}\n", index + 1, example.script.trim()));
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_prefers_same_site_and_skips_literal_values() {
+        let page = dom::structure_signature(r#"<form><input name="email"><input name="phone"><input type="file" name="cv"></form>"#);
+        let similar = dom::structure_signature(r#"<form><input name="email" value="x"><input type="file" name="cv"></form>"#);
+        let unrelated = dom::structure_signature(r#"<form><input name="q"></form>"#);
+        assert!(structure_similarity(&page, &similar) >= MIN_STRUCTURE_SIMILARITY);
+        assert!(structure_similarity(&page, &unrelated) < MIN_STRUCTURE_SIMILARITY);
+        assert_eq!(form_fields(r#"<form><input name="q"><input name="q"></form>"#), vec!["form".to_string(), "input@q".to_string()]);
+
+        let workday = "type \"#email\" \"{{email}}\"\nclick \"#next\"".to_string();
+        let leaked = "type \"#email\" \"jan@example.com\"".to_string();
+        let cached = "upload \"#cv\" \"{{cv_path}}\"".to_string();
+        let examples = rank(
+            vec![leaked.clone(), workday.clone(), workday.clone()],
+            vec![(0.2, "click \"#other\"".to_string()), (0.8, cached.clone())],
+        );
+        assert_eq!(examples.len(), 2);
+        assert_eq!((examples[0].source, examples[0].script.as_str()), (ExampleSource::SameSite, workday.as_str()));
+        assert_eq!((examples[1].source, examples[1].script.as_str()), (ExampleSource::SimilarForm, cached.as_str()));
+        assert!(!is_shareable(&leaked));
+
+        assert!(render_examples(&[], PromptLanguage::Pl).is_empty());
+        assert!(render_examples(&examples, PromptLanguage::En).contains("### 2\nupload"));
+    }
+}
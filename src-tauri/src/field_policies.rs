@@ -0,0 +1,327 @@
+//! Polityki wrażliwych pytań formularzy: widełki wynagrodzenia w walutach, okres wypowiedzenia i gotowość
+//! do relokacji. Przed generowaniem DSL trafiają do danych użytkownika jako `expected_salary`, `notice_period`,
+//! `available_from` i `relocation`, więc korzystają z nich i model, i skrypt regułowy - zamiast zostawiać
+//! takie pola puste albo zgadywać. Wartości podane wprost w danych użytkownika mają pierwszeństwo.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Days, NaiveDate, Utc};
+use scraper::{Html, Node};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+use tracing::info;
+
+use codialog_types::automation::{FieldPolicies, Relocation, SalaryQuote, SalaryRange};
+
+use crate::locale::LocaleHint;
+
+pub const EXPECTED_SALARY_KEY: &str = "expected_salary";
+pub const NOTICE_PERIOD_KEY: &str = "notice_period";
+pub const AVAILABLE_FROM_KEY: &str = "available_from";
+pub const RELOCATION_KEY: &str = "relocation";
+
+/// Klucze danych użytkownika uzupełniane z polityk; ich wartości są częścią klucza cache DSL
+pub const POLICY_KEYS: &[&str] = &[EXPECTED_SALARY_KEY, NOTICE_PERIOD_KEY, AVAILABLE_FROM_KEY, RELOCATION_KEY];
+
+/// Najdłuższy przyjmowany okres wypowiedzenia (w dniach)
+const MAX_NOTICE_PERIOD_DAYS: u32 = 365;
+
+/// Oznaczenia walut w treści strony; skróty liczą się tylko jako osobne słowa
+const CURRENCY_MARKERS: &[(&str, &[&str])] = &[
+    ("PLN", &["pln", "zł"]),
+    ("EUR", &["eur", "€"]),
+    ("USD", &["usd", "$"]),
+    ("GBP", &["gbp", "£"]),
+    ("CHF", &["chf"]),
+    ("CZK", &["czk", "kč"]),
+];
+
+/// Kody walut wielkimi literami; widełki z `min` <= `max`, każda waluta najwyżej raz
+pub fn normalize(mut policies: FieldPolicies) -> Result<FieldPolicies> {
+    for range in policies.salary.iter_mut() {
+        range.currency = range.currency.trim().to_uppercase();
+        if range.currency.len() != 3 || !range.currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            bail!("Invalid currency code '{}', expected ISO 4217 such as PLN or EUR", range.currency);
+        }
+        if range.min > range.max {
+            bail!("Salary range for {} has min {} above max {}", range.currency, range.min, range.max);
+        }
+    }
+    if let Some((index, range)) = policies.salary.iter().enumerate().find(|(index, range)| policies.salary[..*index].iter().any(|other| other.currency == range.currency)) {
+        bail!("Salary range {} repeats currency {}", index + 1, range.currency);
+    }
+    if policies.notice_period_days.is_some_and(|days| days > MAX_NOTICE_PERIOD_DAYS) {
+        bail!("Notice period must not exceed {} days", MAX_NOTICE_PERIOD_DAYS);
+    }
+    Ok(policies)
+}
+
+/// Dane użytkownika uzupełnione o odpowiedzi z polityk (dostępność liczona od dziś)
+pub fn apply(html: &str, user_data: &Value, policies: &FieldPolicies, locale: &LocaleHint) -> Value {
+    apply_on(html, user_data, policies, locale, Utc::now().date_naive())
+}
+
+fn apply_on(html: &str, user_data: &Value, policies: &FieldPolicies, locale: &LocaleHint, today: NaiveDate) -> Value {
+    let mut data = match user_data {
+        Value::Object(fields) => fields.clone(),
+        _ => serde_json::Map::new(),
+    };
+    let language = locale.effective().map(|tag| tag.split('-').next().unwrap_or(tag).to_string());
+    let language = language.as_deref();
+
+    let mut answers: Vec<(&str, String)> = Vec::new();
+    if let Some(range) = page_currency(html, &policies.salary, locale) {
+        answers.push((EXPECTED_SALARY_KEY, salary_value(range, policies.salary_quote.unwrap_or_default())));
+    }
+    if let Some(days) = policies.notice_period_days {
+        answers.push((NOTICE_PERIOD_KEY, notice_period_text(days, language)));
+        if let Some(date) = today.checked_add_days(Days::new(u64::from(days))) {
+            answers.push((AVAILABLE_FROM_KEY, date.format("%Y-%m-%d").to_string()));
+        }
+    }
+    if let Some(relocation) = policies.relocation {
+        answers.push((RELOCATION_KEY, relocation_text(relocation, language).to_string()));
+    }
+
+    for (key, answer) in answers {
+        let given = data.get(key).is_some_and(|value| !value.is_null() && value.as_str() != Some(""));
+        if !given {
+            data.insert(key.to_string(), json!(answer));
+        }
+    }
+    Value::Object(data)
+}
+
+/// Widełki w walucie strony: najczęściej wymienianej w jej treści, a bez wzmianek - waluty kraju z locale;
+/// w ostateczności pierwsze skonfigurowane
+pub fn page_currency<'a>(html: &str, salary: &'a [SalaryRange], locale: &LocaleHint) -> Option<&'a SalaryRange> {
+    if salary.len() <= 1 {
+        return salary.first();
+    }
+    let text = visible_text(html);
+    let mentions = |currency: &str| {
+        CURRENCY_MARKERS
+            .iter()
+            .find(|(code, _)| *code == currency)
+            .map(|(_, markers)| markers.iter().map(|marker| count_marker(&text, marker)).sum())
+            .unwrap_or_else(|| count_marker(&text, &currency.to_lowercase()))
+    };
+    let counts: Vec<usize> = salary.iter().map(|range| mentions(&range.currency)).collect();
+    let most = counts.iter().copied().max().unwrap_or_default();
+    if most > 0 {
+        return counts.iter().position(|count| *count == most).map(|index| &salary[index]);
+    }
+    let local = locale.effective().and_then(local_currency);
+    salary.iter().find(|range| Some(range.currency.as_str()) == local).or(salary.first())
+}
+
+/// Tekst strony bez skryptów i stylów, małymi literami
+fn visible_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut text = String::new();
+    for node in document.tree.nodes() {
+        let Node::Text(content) = node.value() else { continue };
+        let hidden = node.parent().and_then(|parent| parent.value().as_element()).is_some_and(|parent| matches!(parent.name(), "script" | "style"));
+        if !hidden {
+            text.push_str(&content.to_lowercase());
+            text.push(' ');
+        }
+    }
+    text
+}
+
+/// Wystąpienia oznaczenia; skrót literowy tylko jako osobne słowo (`eur` nie w `europe`)
+fn count_marker(text: &str, marker: &str) -> usize {
+    if !marker.chars().all(|c| c.is_alphabetic()) {
+        return text.matches(marker).count();
+    }
+    text.match_indices(marker)
+        .filter(|(start, _)| {
+            let before = text[..*start].chars().next_back();
+            let after = text[start + marker.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+        .count()
+}
+
+/// Waluta kraju z tagu locale (`de-CH` -> CHF) albo języka używanego głównie w jednym kraju
+fn local_currency(tag: &str) -> Option<&'static str> {
+    let (language, region) = tag.split_once('-').map(|(language, region)| (language, Some(region))).unwrap_or((tag, None));
+    match (language, region) {
+        (_, Some("PL")) | ("pl", None) => Some("PLN"),
+        (_, Some("CH")) => Some("CHF"),
+        (_, Some("US")) => Some("USD"),
+        (_, Some("GB" | "UK")) => Some("GBP"),
+        (_, Some("CZ")) | ("cs", None) => Some("CZK"),
+        (_, Some("DE" | "AT" | "FR" | "ES" | "IT" | "NL" | "IE" | "BE" | "PT")) | ("de" | "fr" | "es" | "it" | "nl", None) => Some("EUR"),
+        _ => None,
+    }
+}
+
+pub fn salary_value(range: &SalaryRange, quote: SalaryQuote) -> String {
+    match quote {
+        SalaryQuote::Min => range.min.to_string(),
+        SalaryQuote::Midpoint => (range.min + (range.max - range.min) / 2).to_string(),
+        SalaryQuote::Max => range.max.to_string(),
+        SalaryQuote::Range if range.min == range.max => range.min.to_string(),
+        SalaryQuote::Range => format!("{}-{}", range.min, range.max),
+    }
+}
+
+/// Okres wypowiedzenia słownie w miesiącach, tygodniach albo dniach; po polsku dla stron `pl`, inaczej po angielsku
+pub fn notice_period_text(days: u32, language: Option<&str>) -> String {
+    let polish = language == Some("pl");
+    if days == 0 {
+        return if polish { "Od zaraz" } else { "Immediately" }.to_string();
+    }
+    let (count, unit) = if days.is_multiple_of(30) {
+        (days / 30, if polish { polish_plural(days / 30, "miesiąc", "miesiące", "miesięcy") } else { "month" })
+    } else if days.is_multiple_of(7) {
+        (days / 7, if polish { polish_plural(days / 7, "tydzień", "tygodnie", "tygodni") } else { "week" })
+    } else {
+        (days, if polish { polish_plural(days, "dzień", "dni", "dni") } else { "day" })
+    };
+    let plural = if !polish && count != 1 { "s" } else { "" };
+    format!("{} {}{}", count, unit, plural)
+}
+
+fn polish_plural(count: u32, one: &'static str, few: &'static str, many: &'static str) -> &'static str {
+    match (count, count % 10, count % 100) {
+        (1, _, _) => one,
+        (_, 2..=4, tens) if !(12..=14).contains(&tens) => few,
+        _ => many,
+    }
+}
+
+/// Odpowiedź na pytanie o relokację w języku strony
+pub fn relocation_text(relocation: Relocation, language: Option<&str>) -> &'static str {
+    match (relocation, language) {
+        (Relocation::Yes, Some("pl")) => "Tak",
+        (Relocation::Yes, Some("de")) => "Ja",
+        (Relocation::Yes, Some("fr")) => "Oui",
+        (Relocation::Yes, Some("es")) => "Sí",
+        (Relocation::Yes, _) => "Yes",
+        (Relocation::No, Some("pl")) => "Nie",
+        (Relocation::No, Some("de")) => "Nein",
+        (Relocation::No, Some("fr")) => "Non",
+        (Relocation::No, _) => "No",
+        (Relocation::Negotiable, Some("pl")) => "Do uzgodnienia",
+        (Relocation::Negotiable, Some("de")) => "Nach Absprache",
+        (Relocation::Negotiable, Some("fr")) => "À discuter",
+        (Relocation::Negotiable, Some("es")) => "A convenir",
+        (Relocation::Negotiable, _) => "Open to discussion",
+    }
+}
+
+/// Zapisane polityki - jeden wiersz tabeli `field_policies`
+pub struct FieldPolicyStore {
+    db_pool: PgPool,
+}
+
+impl FieldPolicyStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę polityk
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing field policies table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS field_policies (
+                id SMALLINT PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+                policies JSONB NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create field_policies table")?;
+
+        Ok(())
+    }
+
+    /// Zapisane polityki; puste, gdy użytkownik żadnych nie ustawił
+    pub async fn load(&self) -> Result<FieldPolicies> {
+        let row = sqlx::query("SELECT policies FROM field_policies WHERE id = 1")
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to load field policies")?;
+
+        match row {
+            Some(row) => serde_json::from_value(row.get("policies")).context("Invalid stored field policies"),
+            None => Ok(FieldPolicies::default()),
+        }
+    }
+
+    /// Zastępuje zapisane polityki
+    pub async fn save(&self, policies: FieldPolicies) -> Result<FieldPolicies> {
+        let policies = normalize(policies)?;
+        sqlx::query(
+            r#"
+            INSERT INTO field_policies (id, policies) VALUES (1, $1)
+            ON CONFLICT (id) DO UPDATE SET policies = EXCLUDED.policies, updated_at = NOW()
+            "#,
+        )
+        .bind(serde_json::to_value(&policies)?)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save field policies")?;
+
+        info!(salary_ranges = policies.salary.len(), notice_period = policies.notice_period_days.is_some(), "Field policies saved");
+        Ok(policies)
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        sqlx::query("DELETE FROM field_policies")
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to clear field policies")?;
+
+        info!("Field policies cleared");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies_fill_sensitive_answers() {
+        let range = |currency: &str, min, max| SalaryRange { currency: currency.to_string(), min, max };
+        let policies = FieldPolicies {
+            salary: vec![range("pln", 15000, 18000), range("EUR", 4000, 5000)],
+            salary_quote: None,
+            notice_period_days: Some(30),
+            relocation: Some(Relocation::Negotiable),
+        };
+        let policies = normalize(policies).unwrap();
+        assert_eq!(policies.salary[0].currency, "PLN");
+        assert!(normalize(FieldPolicies { salary: vec![range("EUR", 5000, 4000)], ..FieldPolicies::default() }).is_err());
+        assert!(normalize(FieldPolicies { salary: vec![range("EUR", 1, 2), range("eur", 3, 4)], ..FieldPolicies::default() }).is_err());
+
+        let today = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let html = r#"<html lang="de"><body><p>Gehalt: 60.000 € brutto (Europe HQ)</p><script>var price = "$";</script></body></html>"#;
+        let locale = LocaleHint::new(Some("pl-PL")).with_page(html);
+        let data = apply_on(html, &json!({ "first_name": "Jan", "notice_period": "" }), &policies, &locale, today);
+        assert_eq!(data["expected_salary"], "5000");
+        assert_eq!(data["notice_period"], "1 month");
+        assert_eq!(data["available_from"], "2026-11-14");
+        assert_eq!(data["relocation"], "Nach Absprache");
+        assert_eq!(data["first_name"], "Jan");
+
+        // Bez wzmianek o walucie - waluta kraju użytkownika; podana wprost kwota zostaje
+        let data = apply_on("<form></form>", &json!({ "expected_salary": "20000" }), &policies, &LocaleHint::new(Some("pl-PL")), today);
+        assert_eq!(data["expected_salary"], "20000");
+        assert_eq!(page_currency("<form></form>", &policies.salary, &LocaleHint::new(Some("de-AT"))).unwrap().currency, "EUR");
+
+        assert_eq!(salary_value(&policies.salary[0], SalaryQuote::Range), "15000-18000");
+        assert_eq!(salary_value(&policies.salary[0], SalaryQuote::Midpoint), "16500");
+        assert_eq!(notice_period_text(90, Some("pl")), "3 miesiące");
+        assert_eq!(notice_period_text(14, None), "2 weeks");
+        assert_eq!(notice_period_text(0, Some("pl")), "Od zaraz");
+        assert_eq!(FieldPolicies::default().merged(&FieldPolicies { notice_period_days: Some(0), ..FieldPolicies::default() }).notice_period_days, Some(0));
+    }
+}
@@ -0,0 +1,131 @@
+//! Mała lokalna strona testowa serwowana przez backend pod `/fixtures/` (FIXTURES_ENABLED):
+//! strona startowa i dwuetapowy formularz zgłoszenia. Wysłane formularze są zapamiętywane
+//! w pamięci, żeby test end-to-end (analiza -> DSL -> przebieg) mógł sprawdzić, co dotarło.
+
+use axum::{
+    extract::{Form, State},
+    response::Html,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Prefiks ścieżek strony testowej w API
+pub const FIXTURES_PATH: &str = "/fixtures";
+
+/// Ile wysłanych formularzy pamiętać (starsze są usuwane)
+const MAX_SUBMISSIONS: usize = 100;
+
+const INDEX_HTML: &str = include_str!("../fixtures/site/index.html");
+const APPLY_HTML: &str = include_str!("../fixtures/site/apply.html");
+const THANKS_HTML: &str = include_str!("../fixtures/site/thanks.html");
+
+/// Formularz wysłany na stronę testową
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FixtureSubmission {
+    pub id: usize,
+    pub fields: BTreeMap<String, String>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Stan strony testowej: wysłane formularze od uruchomienia
+#[derive(Debug, Default)]
+pub struct FixtureSite {
+    submissions: Mutex<Vec<FixtureSubmission>>,
+    next_id: AtomicUsize,
+}
+
+impl FixtureSite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Zapamiętuje wysłany formularz; puste pola są pomijane jak w zwykłym backendzie formularza
+    pub fn record(&self, fields: BTreeMap<String, String>) -> FixtureSubmission {
+        let submission = FixtureSubmission {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            fields: fields.into_iter().filter(|(_, value)| !value.trim().is_empty()).collect(),
+            received_at: Utc::now(),
+        };
+        let mut submissions = self.submissions.lock().unwrap();
+        submissions.push(submission.clone());
+        if submissions.len() > MAX_SUBMISSIONS {
+            let excess = submissions.len() - MAX_SUBMISSIONS;
+            submissions.drain(..excess);
+        }
+        submission
+    }
+
+    pub fn submissions(&self) -> Vec<FixtureSubmission> {
+        self.submissions.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.submissions.lock().unwrap().clear();
+    }
+}
+
+/// Trasy strony testowej do zagnieżdżenia pod `FIXTURES_PATH`
+pub fn router<S: Clone + Send + Sync + 'static>(site: Arc<FixtureSite>) -> Router<S> {
+    Router::new()
+        .route("/", get(index))
+        .route("/apply", get(apply))
+        .route("/submit", post(submit))
+        .route("/submissions", get(list_submissions).delete(clear_submissions))
+        .with_state(site)
+}
+
+async fn index() -> Html<&'static str> {
+    Html(INDEX_HTML)
+}
+
+async fn apply() -> Html<&'static str> {
+    Html(APPLY_HTML)
+}
+
+async fn submit(State(site): State<Arc<FixtureSite>>, Form(fields): Form<BTreeMap<String, String>>) -> Html<&'static str> {
+    let submission = site.record(fields);
+    info!(submission_id = submission.id, fields = ?submission.fields.keys().collect::<Vec<_>>(), "Fixture form submitted");
+    Html(THANKS_HTML)
+}
+
+async fn list_submissions(State(site): State<Arc<FixtureSite>>) -> Json<Vec<FixtureSubmission>> {
+    Json(site.submissions())
+}
+
+async fn clear_submissions(State(site): State<Arc<FixtureSite>>) -> Json<serde_json::Value> {
+    site.clear();
+    Json(serde_json::json!({ "success": true }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_site_records_submissions() {
+        // Formularz ma kroki i przycisk wysłania, na których opiera się test end-to-end
+        assert!(APPLY_HTML.contains("data-step=\"2\"") && APPLY_HTML.contains("id=\"submit\""));
+        assert!(APPLY_HTML.contains("action=\"/fixtures/submit\"") && THANKS_HTML.contains("id=\"thanks\""));
+
+        let site = FixtureSite::new();
+        let fields = |pairs: &[(&str, &str)]| pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        let first = site.record(fields(&[("first_name", "Jan"), ("email", "jan@example.com"), ("phone", " ")]));
+        assert_eq!(first.id, 1);
+        assert_eq!(first.fields.keys().collect::<Vec<_>>(), vec!["email", "first_name"]);
+
+        for _ in 0..MAX_SUBMISSIONS {
+            site.record(fields(&[("country", "pl")]));
+        }
+        let submissions = site.submissions();
+        assert_eq!(submissions.len(), MAX_SUBMISSIONS);
+        assert_eq!(submissions[0].id, 2);
+        site.clear();
+        assert!(site.submissions().is_empty());
+    }
+}
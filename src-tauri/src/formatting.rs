@@ -0,0 +1,351 @@
+//! Wartości z danych użytkownika w formacie, którego oczekuje pole: daty, godziny, telefony i kwoty
+//! (np. oczekiwania finansowe) są przepisywane według typu pola, `placeholder` i `pattern`, np.
+//! `2024-03-01` -> `03/01/2024` dla `placeholder="MM/DD/YYYY"` albo `600123456` -> `+48 600 123 456`.
+
+use chrono::{NaiveDate, NaiveTime, Timelike};
+
+use crate::dom::FormField;
+use crate::locale;
+
+/// Format wartości pola wykryty z jego typu, `placeholder` albo `pattern`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldFormat {
+    /// Data według wzorca z tokenami `DD`, `MM`, `YYYY`, `YY`, np. `DD.MM.YYYY`
+    Date(String),
+    /// Godzina `HH:MM`, w zegarze 12-godzinnym z `AM`/`PM`
+    Time { twelve_hour: bool },
+    /// Telefon: numer kierunkowy i długości grup cyfr z przykładu (np. `+48 600 000 000`);
+    /// bez grup cyfry są wpisywane razem
+    Phone { prefix: Option<String>, groups: Vec<usize>, separator: char },
+    /// Liczba całkowita, np. kwota wynagrodzenia, z separatorem tysięcy lub bez
+    Number { thousands: Option<char> },
+}
+
+/// Format pola albo `None`, gdy nic nie wskazuje na konkretną postać wartości
+pub fn detect(field: &FormField) -> Option<FieldFormat> {
+    let placeholder = field.placeholder.as_deref().map(str::trim).filter(|placeholder| !placeholder.is_empty());
+    match field.element_type.as_deref() {
+        Some("date") => return Some(FieldFormat::Date("YYYY-MM-DD".to_string())),
+        Some("time") => return Some(FieldFormat::Time { twelve_hour: false }),
+        _ => {}
+    }
+    if let Some(format) = placeholder.and_then(|placeholder| date_pattern(placeholder).or_else(|| time_format(placeholder))) {
+        return Some(format);
+    }
+    if let Some(format) = field.pattern.as_deref().and_then(pattern_format) {
+        return Some(format);
+    }
+    match field.element_type.as_deref() {
+        Some("number") => Some(FieldFormat::Number { thousands: None }),
+        _ => placeholder.and_then(|placeholder| phone_format(placeholder).or_else(|| number_format(placeholder))),
+    }
+}
+
+/// Wartość do wpisania w pole: sformatowana, gdy da się ją odczytać jako datę, godzinę, telefon
+/// lub liczbę, w przeciwnym razie bez zmian. Wynik dłuższy niż `maxlength` traci separatory.
+/// `user_locale` rozstrzyga niejednoznaczne daty z ukośnikiem (`03/04/2024` dla `en-US` to 4 marca)
+pub fn format_for_field(raw: &str, format: Option<&FieldFormat>, max_length: Option<usize>, user_locale: Option<&str>) -> String {
+    let Some(formatted) = format.and_then(|format| format_value(raw, format, user_locale)) else {
+        return raw.to_string();
+    };
+    match max_length {
+        Some(max_length) if formatted.chars().count() > max_length => {
+            let compact: String = formatted.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '+').collect();
+            if compact.chars().count() <= max_length { compact } else { formatted }
+        }
+        _ => formatted,
+    }
+}
+
+/// `raw` w formacie `format`; `None`, gdy wartość nie jest datą, godziną, telefonem czy liczbą
+pub fn format_value(raw: &str, format: &FieldFormat, user_locale: Option<&str>) -> Option<String> {
+    match format {
+        FieldFormat::Date(pattern) => parse_date(raw, month_first(user_locale)).map(|date| render_date(date, pattern)),
+        FieldFormat::Time { twelve_hour } => parse_time(raw).map(|time| render_time(time, *twelve_hour)),
+        FieldFormat::Phone { prefix, groups, separator } => format_phone(raw, prefix.as_deref(), groups, *separator),
+        FieldFormat::Number { thousands } => parse_amount(raw).map(|amount| render_number(amount, *thousands)),
+    }
+}
+
+/// `DD.MM.YYYY`, `mm/dd/yyyy`, `RRRR-MM-DD` (pl), `TT.MM.JJJJ` (de), `JJ/MM/AAAA` (fr/es)
+fn date_pattern(placeholder: &str) -> Option<FieldFormat> {
+    let upper = placeholder.to_uppercase();
+    let separator = upper.chars().find(|c| matches!(c, '.' | '/' | '-'))?;
+    let tokens: Vec<&str> = upper
+        .split(separator)
+        .map(|token| match token.trim() {
+            "DD" | "D" | "TT" | "JJ" => Some("DD"),
+            "MM" | "M" => Some("MM"),
+            "YYYY" | "RRRR" | "JJJJ" | "AAAA" => Some("YYYY"),
+            "YY" | "RR" | "AA" => Some("YY"),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let has = |token: &str| tokens.iter().filter(|t| **t == token).count() == 1;
+    (tokens.len() == 3 && has("DD") && has("MM") && (has("YYYY") || has("YY")))
+        .then(|| FieldFormat::Date(tokens.join(&separator.to_string())))
+}
+
+/// `HH:MM`, `hh:mm AM`, `--:-- --`
+fn time_format(placeholder: &str) -> Option<FieldFormat> {
+    let upper = placeholder.to_uppercase();
+    let (clock, suffix) = upper.split_once(' ').map(|(clock, suffix)| (clock, Some(suffix.trim()))).unwrap_or((upper.as_str(), None));
+    if !matches!(clock, "HH:MM" | "H:MM" | "--:--") {
+        return None;
+    }
+    let twelve_hour = matches!(suffix, Some("AM" | "PM" | "AM/PM" | "--"));
+    (suffix.is_none() || twelve_hour).then_some(FieldFormat::Time { twelve_hour })
+}
+
+/// `\d{4}-\d{2}-\d{2}` -> data ISO, `[0-9]+` / `\d*` -> cyfry bez separatorów, `\+48\d{9}` -> telefon
+fn pattern_format(pattern: &str) -> Option<FieldFormat> {
+    let compact = pattern.trim_start_matches('^').trim_end_matches('$').replace("[0-9]", "\\d");
+    if compact == "\\d{4}-\\d{2}-\\d{2}" {
+        return Some(FieldFormat::Date("YYYY-MM-DD".to_string()));
+    }
+    if matches!(compact.as_str(), "\\d+" | "\\d*") {
+        return Some(FieldFormat::Number { thousands: None });
+    }
+    let (prefix, digits) = match compact.strip_prefix("\\+") {
+        Some(rest) => {
+            let code: String = rest.chars().take_while(char::is_ascii_digit).collect();
+            (Some(format!("+{}", code)), rest[code.len()..].to_string())
+        }
+        None => (None, compact),
+    };
+    let count: usize = digits.strip_prefix("\\d{")?.strip_suffix('}')?.parse().ok()?;
+    (count >= 6 && prefix.as_deref() != Some("+")).then_some(FieldFormat::Phone { prefix, groups: vec![count], separator: ' ' })
+}
+
+/// Przykładowy numer, np. `+48 600 000 000`, `(555) 123-4567` → numer kierunkowy i grupy cyfr
+fn phone_format(placeholder: &str) -> Option<FieldFormat> {
+    if !placeholder.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | ' ' | '-' | '(' | ')' | 'X' | 'x')) {
+        return None;
+    }
+    let mut parts = placeholder.split([' ', '-', '(', ')']).filter(|part| !part.is_empty()).peekable();
+    let prefix = parts.next_if(|part| part.starts_with('+')).map(str::to_string);
+    let groups: Vec<usize> = parts.map(str::len).collect();
+    let separator = placeholder
+        .trim_start_matches(|c: char| c == '+' || c.is_ascii_digit())
+        .chars()
+        .find(|c| matches!(c, ' ' | '-'))
+        .unwrap_or(' ');
+    let digits: usize = groups.iter().sum();
+    (prefix.is_some() || (digits >= 7 && groups.len() > 1)).then_some(FieldFormat::Phone { prefix, groups, separator })
+}
+
+/// Przykładowa kwota, np. `10 000` albo `10,000`
+fn number_format(placeholder: &str) -> Option<FieldFormat> {
+    let digits = placeholder.chars().filter(char::is_ascii_digit).count();
+    let separators: Vec<char> = placeholder.chars().filter(|c| !c.is_ascii_digit()).collect();
+    if digits < 4 || separators.is_empty() || separators.iter().any(|c| *c != separators[0] || !matches!(c, ' ' | ',' | '.' | '\u{a0}')) {
+        return None;
+    }
+    let groups: Vec<&str> = placeholder.split(separators[0]).collect();
+    groups[1..].iter().all(|group| group.len() == 3).then_some(FieldFormat::Number { thousands: Some(separators[0]) })
+}
+
+/// Locale użytkownika zapisuje daty z miesiącem na początku (`MM/DD/YYYY`, np. `en-US`)
+fn month_first(user_locale: Option<&str>) -> bool {
+    user_locale.is_some_and(|tag| locale::conventions(tag).date_format.starts_with("MM"))
+}
+
+/// `2024-03-01`, `01.03.2024`, `01/03/2024` (z ukośnikiem dzień najpierw, chyba że drugi człon > 12
+/// albo `month_first` i pierwszy człon <= 12), `20240301`
+fn parse_date(raw: &str, month_first: bool) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    let raw = raw.split(['T', ' ']).next().unwrap_or(raw);
+    if raw.len() == 8 && raw.chars().all(|c| c.is_ascii_digit()) {
+        return NaiveDate::parse_from_str(raw, "%Y%m%d").ok();
+    }
+    let parts: Vec<u32> = raw.split(['-', '.', '/']).map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let [first, second, third] = parts[..] else { return None };
+    let (year, month, day) = if first > 999 {
+        (first, second, third)
+    } else if raw.contains('/') && (second > 12 || (month_first && first <= 12)) {
+        (third, first, second)
+    } else {
+        (third, second, first)
+    };
+    let year = if year < 100 { year + 2000 } else { year };
+    NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month, day)
+}
+
+fn render_date(date: NaiveDate, pattern: &str) -> String {
+    pattern
+        .replace("YYYY", &date.format("%Y").to_string())
+        .replace("YY", &date.format("%y").to_string())
+        .replace("MM", &date.format("%m").to_string())
+        .replace("DD", &date.format("%d").to_string())
+}
+
+/// `14:30`, `14.30`, `2:30 pm`, `2pm`
+fn parse_time(raw: &str) -> Option<NaiveTime> {
+    let lower = raw.trim().to_lowercase().replace('.', ":");
+    let (clock, meridiem) = match lower.strip_suffix("am").or_else(|| lower.strip_suffix("a:m:")) {
+        Some(clock) => (clock.trim().to_string(), Some(false)),
+        None => match lower.strip_suffix("pm").or_else(|| lower.strip_suffix("p:m:")) {
+            Some(clock) => (clock.trim().to_string(), Some(true)),
+            None => (lower, None),
+        },
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock.as_str(), "0"));
+    let (mut hour, minute): (u32, u32) = (hour.trim().parse().ok()?, minute.trim().get(..2).unwrap_or(minute.trim()).parse().ok()?);
+    match meridiem {
+        Some(pm) if (1..=12).contains(&hour) => hour = hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => {}
+    }
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn render_time(time: NaiveTime, twelve_hour: bool) -> String {
+    if !twelve_hour {
+        return time.format("%H:%M").to_string();
+    }
+    let (pm, hour) = time.hour12();
+    format!("{:02}:{:02} {}", hour, time.minute(), if pm { "PM" } else { "AM" })
+}
+
+/// Cyfry numeru bez numeru kierunkowego docelowego formatu, pogrupowane jak w przykładzie. `None` (wpisywana
+/// jest surowa wartość), gdy numer nie pasuje do pola bez zgadywania: ma za dużo cyfr, a nie zaczyna się
+/// znanym numerem kierunkowym, albo jest krajowym numerem innej długości niż w przykładzie z numerem kierunkowym
+fn format_phone(raw: &str, prefix: Option<&str>, groups: &[usize], separator: char) -> Option<String> {
+    let trimmed = raw.trim();
+    if !trimmed.chars().all(|c| c.is_ascii_digit() || matches!(c, '+' | ' ' | '-' | '(' | ')' | '.')) {
+        return None;
+    }
+    let mut digits: String = trimmed.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() < 6 {
+        return None;
+    }
+    let international = trimmed.starts_with('+') || digits.starts_with("00");
+    if digits.starts_with("00") {
+        digits.drain(..2);
+    }
+    let expected: usize = groups.iter().sum();
+    let code = prefix.map(|prefix| prefix.trim_start_matches('+'));
+    match code {
+        Some(code) if digits.starts_with(code) && (international || (expected > 0 && digits.len() == expected + code.len())) => {
+            digits.drain(..code.len());
+        }
+        Some(_) if international => return None,
+        // Numer krajowy innej długości należy do innego kraju - numer kierunkowy pola byłby błędny
+        Some(_) if expected > 0 && digits.len() != expected => return None,
+        None if expected > 0 && digits.len() > expected => {
+            let code = locale::COUNTRY_CALLING_CODES.iter().find(|code| digits.starts_with(**code) && digits.len() == expected + code.len())?;
+            digits.drain(..code.len());
+        }
+        _ => {}
+    }
+
+    let mut grouped: Vec<String> = Vec::new();
+    let mut rest = digits.as_str();
+    for (index, length) in groups.iter().enumerate() {
+        if rest.is_empty() {
+            break;
+        }
+        let take = if index + 1 == groups.len() { rest.len() } else { (*length).min(rest.len()) };
+        grouped.push(rest[..take].to_string());
+        rest = &rest[take..];
+    }
+    if !rest.is_empty() {
+        grouped.push(rest.to_string());
+    }
+    let number = grouped.join(&separator.to_string());
+    Some(match prefix {
+        Some(prefix) => format!("{} {}", prefix, number),
+        None => number,
+    })
+}
+
+/// Kwota jako liczba całkowita: `15 000 PLN`, `15.000`, `15,5k`; z zakresu `15000-18000` dolna granica
+fn parse_amount(raw: &str) -> Option<u64> {
+    let lower = raw.trim().to_lowercase();
+    let start = lower.find(|c: char| c.is_ascii_digit())?;
+    let number: String = lower[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || matches!(c, ' ' | '\u{a0}' | ',' | '.' | '\''))
+        .collect();
+    let number = number.trim_end_matches([' ', '\u{a0}', ',', '.']);
+    let thousands = lower[start + number.len()..].trim_start().starts_with('k');
+
+    // Separator, po którym są dokładnie trzy cyfry, dzieli tysiące; inny - część dziesiętną
+    let parts: Vec<&str> = number.split([' ', '\u{a0}', ',', '.', '\'']).collect();
+    let (integer, fraction) = match parts.split_last() {
+        Some((last, rest)) if !rest.is_empty() && last.len() != 3 => (rest.concat(), *last),
+        _ => (parts.concat(), ""),
+    };
+    let integer: u64 = integer.parse().ok()?;
+    let fraction: f64 = format!("0.{}", if fraction.is_empty() { "0" } else { fraction }).parse().ok()?;
+    Some(if thousands { ((integer as f64 + fraction) * 1000.0).round() as u64 } else { integer })
+}
+
+fn render_number(amount: u64, thousands: Option<char>) -> String {
+    let digits = amount.to_string();
+    let Some(separator) = thousands else {
+        return digits;
+    };
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(element_type: &str, placeholder: Option<&str>, pattern: Option<&str>) -> FormField {
+        let html = format!(
+            "<input type=\"{}\" {} {}>",
+            element_type,
+            placeholder.map(|placeholder| format!("placeholder=\"{}\"", placeholder)).unwrap_or_default(),
+            pattern.map(|pattern| format!("pattern=\"{}\"", pattern)).unwrap_or_default()
+        );
+        crate::dom::form_fields(&html).remove(0)
+    }
+
+    #[test]
+    fn test_format_values_for_fields() {
+        let us_date = detect(&field("text", Some("MM/DD/YYYY"), None));
+        assert_eq!(us_date, Some(FieldFormat::Date("MM/DD/YYYY".to_string())));
+        assert_eq!(format_for_field("2024-03-01", us_date.as_ref(), None, None), "03/01/2024");
+        let pl_date = detect(&field("text", Some("dd.mm.rrrr"), None));
+        assert_eq!(format_for_field("03/25/2024", pl_date.as_ref(), None, None), "25.03.2024");
+        assert_eq!(format_for_field("2024-03-01", detect(&field("date", None, None)).as_ref(), None, None), "2024-03-01");
+        assert_eq!(format_for_field("ASAP", us_date.as_ref(), None, None), "ASAP");
+
+        let time = detect(&field("text", Some("hh:mm AM"), None));
+        assert_eq!(format_for_field("14:30", time.as_ref(), None, None), "02:30 PM");
+        assert_eq!(format_for_field("9am", detect(&field("time", None, None)).as_ref(), None, None), "09:00");
+
+        let phone = detect(&field("tel", Some("+48 600 000 000"), None));
+        assert_eq!(format_for_field("600123456", phone.as_ref(), None, None), "+48 600 123 456");
+        assert_eq!(format_for_field("+48 600-123-456", phone.as_ref(), None, None), "+48 600 123 456");
+        assert_eq!(format_for_field("+49 151 2345678", phone.as_ref(), None, None), "+49 151 2345678");
+        assert_eq!(format_for_field("+48 600 123 456", phone.as_ref(), Some(12), None), "+48600123456");
+        let national = detect(&field("tel", None, Some("[0-9]{9}")));
+        assert_eq!(format_for_field("+48 600 123 456", national.as_ref(), None, None), "600123456");
+        // Numer, którego nie da się dopasować bez zgadywania, zostaje wpisany tak, jak go podano
+        assert_eq!(format_for_field("+1 555 123 4567", national.as_ref(), None, None), "+1 555 123 4567");
+        assert_eq!(format_for_field("07700 900123", phone.as_ref(), None, None), "07700 900123");
+
+        // Niejednoznaczna data z ukośnikiem według locale użytkownika
+        let iso = detect(&field("date", None, None));
+        assert_eq!(format_for_field("03/04/2024", iso.as_ref(), None, None), "2024-04-03");
+        assert_eq!(format_for_field("03/04/2024", iso.as_ref(), None, Some("en-US")), "2024-03-04");
+        assert_eq!(format_for_field("25/04/2024", iso.as_ref(), None, Some("en-US")), "2024-04-25");
+
+        let salary = detect(&field("text", Some("10 000"), None));
+        assert_eq!(salary, Some(FieldFormat::Number { thousands: Some(' ') }));
+        assert_eq!(format_for_field("15000-18000 PLN", salary.as_ref(), None, None), "15 000");
+        assert_eq!(format_for_field("12,5k", detect(&field("number", None, None)).as_ref(), None, None), "12500");
+        assert_eq!(format_for_field("15.000 EUR", detect(&field("text", None, Some("\\d+"))).as_ref(), None, None), "15000");
+        assert_eq!(detect(&field("text", Some("Jan Kowalski"), None)), None);
+    }
+}
@@ -0,0 +1,3 @@
+//! Generatory DSL bez modelu językowego dla stron o znanej, przewidywalnej budowie
+
+pub mod platforms;
@@ -0,0 +1,293 @@
+//! Ręcznie przygotowane mapy selektorów popularnych systemów rekrutacyjnych (ATS): Greenhouse, Lever,
+//! Workday i SmartRecruiters. Formularze tych platform są takie same u każdego pracodawcy, więc skrypt
+//! powstaje z mapy bez wywołania modelu. Platforma jest rozpoznawana po hoście strony albo hoście ramki iframe
+//! z osadzonym formularzem; do skryptu trafiają tylko pola obecne na stronie (także w ramkach). Skrypt
+//! z mapy nie wysyła zgłoszenia, a strona z wymaganym polem spoza mapy trafia do kolejnej strategii.
+
+use scraper::{Html, Selector};
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::cdp;
+use crate::tagui::escape_for_dsl;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Greenhouse,
+    Lever,
+    Workday,
+    SmartRecruiters,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 4] = [Platform::Greenhouse, Platform::Lever, Platform::Workday, Platform::SmartRecruiters];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Greenhouse => "greenhouse",
+            Platform::Lever => "lever",
+            Platform::Workday => "workday",
+            Platform::SmartRecruiters => "smartrecruiters",
+        }
+    }
+
+    /// Domeny ofert platformy (także ich subdomeny, np. `acme.wd5.myworkdayjobs.com`)
+    fn hosts(&self) -> &'static [&'static str] {
+        match self {
+            Platform::Greenhouse => &["greenhouse.io"],
+            Platform::Lever => &["lever.co"],
+            Platform::Workday => &["myworkdayjobs.com", "myworkdaysite.com"],
+            Platform::SmartRecruiters => &["smartrecruiters.com"],
+        }
+    }
+
+    fn rules(&self) -> &'static [FieldRule] {
+        match self {
+            Platform::Greenhouse => GREENHOUSE,
+            Platform::Lever => LEVER,
+            Platform::Workday => WORKDAY,
+            Platform::SmartRecruiters => SMARTRECRUITERS,
+        }
+    }
+}
+
+/// Komenda DSL wypełniająca pole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Type,
+    Upload,
+    Select,
+}
+
+/// Pole platformy: klucz danych użytkownika (pierwszy niepusty z listy) i selektory od najbardziej pewnego
+struct FieldRule {
+    keys: &'static [&'static str],
+    action: Action,
+    selectors: &'static [&'static str],
+}
+
+const fn rule(keys: &'static [&'static str], action: Action, selectors: &'static [&'static str]) -> FieldRule {
+    FieldRule { keys, action, selectors }
+}
+
+const GREENHOUSE: &[FieldRule] = &[
+    rule(&["first_name"], Action::Type, &["#first_name", "input[name=\"job_application[first_name]\"]"]),
+    rule(&["last_name"], Action::Type, &["#last_name", "input[name=\"job_application[last_name]\"]"]),
+    rule(&["email"], Action::Type, &["#email", "input[name=\"job_application[email]\"]"]),
+    rule(&["phone"], Action::Type, &["#phone", "input[name=\"job_application[phone]\"]"]),
+    rule(&["cv_path", "resume_path"], Action::Upload, &["#resume", "input[type=\"file\"][name=\"resume\"]"]),
+    rule(&["cover_letter_path"], Action::Upload, &["#cover_letter", "input[type=\"file\"][name=\"cover_letter\"]"]),
+    rule(&["linkedin", "linkedin_url"], Action::Type, &["input[autocomplete=\"custom-question-linkedin-profile\"]", "input[aria-label=\"LinkedIn Profile\"]"]),
+    rule(&["location", "city"], Action::Type, &["#candidate-location", "#job_application_location"]),
+];
+
+const LEVER: &[FieldRule] = &[
+    rule(&["cv_path", "resume_path"], Action::Upload, &["#resume-upload-input", "input[name=\"resume\"]"]),
+    rule(&["fullname", "full_name", "name"], Action::Type, &["input[name=\"name\"]"]),
+    rule(&["email"], Action::Type, &["input[name=\"email\"]"]),
+    rule(&["phone"], Action::Type, &["input[name=\"phone\"]"]),
+    rule(&["company", "current_company"], Action::Type, &["input[name=\"org\"]"]),
+    rule(&["linkedin", "linkedin_url"], Action::Type, &["input[name=\"urls[LinkedIn]\"]"]),
+    rule(&["github", "github_url"], Action::Type, &["input[name=\"urls[GitHub]\"]"]),
+    rule(&["portfolio", "website"], Action::Type, &["input[name=\"urls[Portfolio]\"]", "input[name=\"urls[Other]\"]"]),
+    rule(&["cover_letter"], Action::Type, &["textarea[name=\"comments\"]"]),
+];
+
+const WORKDAY: &[FieldRule] = &[
+    rule(&["first_name"], Action::Type, &["[data-automation-id=\"legalNameSection_firstName\"]"]),
+    rule(&["last_name"], Action::Type, &["[data-automation-id=\"legalNameSection_lastName\"]"]),
+    rule(&["address"], Action::Type, &["[data-automation-id=\"addressSection_addressLine1\"]"]),
+    rule(&["city"], Action::Type, &["[data-automation-id=\"addressSection_city\"]"]),
+    rule(&["postal_code", "zip"], Action::Type, &["[data-automation-id=\"addressSection_postalCode\"]"]),
+    rule(&["email"], Action::Type, &["[data-automation-id=\"email\"]"]),
+    rule(&["phone"], Action::Type, &["[data-automation-id=\"phone-number\"]"]),
+    rule(&["cv_path", "resume_path"], Action::Upload, &["[data-automation-id=\"file-upload-input-ref\"]"]),
+    rule(&["linkedin", "linkedin_url"], Action::Type, &["[data-automation-id=\"linkedinQuestion\"]"]),
+];
+
+const SMARTRECRUITERS: &[FieldRule] = &[
+    rule(&["first_name"], Action::Type, &["#first-name-input", "input[name=\"firstName\"]"]),
+    rule(&["last_name"], Action::Type, &["#last-name-input", "input[name=\"lastName\"]"]),
+    rule(&["email"], Action::Type, &["#email-input", "input[name=\"email\"]"]),
+    rule(&["email"], Action::Type, &["#confirm-email-input", "input[name=\"confirmEmail\"]"]),
+    rule(&["city", "location"], Action::Type, &["#location-input", "input[name=\"location\"]"]),
+    rule(&["phone"], Action::Type, &["#phone-number-input", "input[name=\"phoneNumber\"]"]),
+    rule(&["linkedin", "linkedin_url"], Action::Type, &["#linkedin-input", "input[name=\"linkedIn\"]"]),
+    rule(&["cv_path", "resume_path"], Action::Upload, &["[data-test=\"resume-upload-input\"]", "input[type=\"file\"][name=\"resume\"]"]),
+    rule(&["cover_letter"], Action::Type, &["#hiring-manager-message-input", "textarea[name=\"message\"]"]),
+    rule(&["country"], Action::Select, &["select[name=\"country\"]"]),
+];
+
+/// Platforma z witryny strony (`cdp::site_of`) albo hostu ramki iframe osadzonej na stronie pracodawcy.
+/// Sam tekst HTML nie wystarcza - dowolna strona może zawierać nazwę platformy
+pub fn detect(html: &str, site: Option<&str>) -> Option<Platform> {
+    let on_host = |host: &str| {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.hosts().iter().any(|platform_host| host == *platform_host || host.ends_with(&format!(".{}", platform_host))))
+    };
+    site.and_then(on_host).or_else(|| {
+        let frames = Selector::parse("iframe[src]").expect("valid selector");
+        let main = cdp::split_frames(html).into_iter().next().map(|(_, document)| document).unwrap_or_default();
+        Html::parse_document(&main)
+            .select(&frames)
+            .filter_map(|frame| frame.value().attr("src").and_then(cdp::site_of))
+            .find_map(|host| on_host(&host))
+    })
+}
+
+/// Dokumenty strony i ramek z funkcją szukania ramki pasującego selektora
+struct Page {
+    documents: Vec<(Option<String>, Html)>,
+}
+
+impl Page {
+    fn new(html: &str) -> Self {
+        Self {
+            documents: cdp::split_frames(html).into_iter().map(|(frame, document)| (frame, Html::parse_document(&document))).collect(),
+        }
+    }
+
+    /// Pierwszy selektor z listy obecny na stronie i ramka, w której leży pole
+    fn find<'a>(&self, selectors: &[&'a str]) -> Option<(&'a str, Option<String>)> {
+        selectors.iter().find_map(|selector| {
+            let parsed = Selector::parse(selector).ok()?;
+            self.documents
+                .iter()
+                .find(|(_, document)| document.select(&parsed).next().is_some())
+                .map(|(frame, _)| (*selector, frame.clone()))
+        })
+    }
+
+    /// Wymagane pola strony i ramek, których nie wypełnia żaden z `filled` (selektor, ramka)
+    fn uncovered_required(&self, filled: &[(&str, Option<String>)]) -> usize {
+        let required = Selector::parse(
+            "input[required]:not([type=\"hidden\"]), select[required], textarea[required], [aria-required=\"true\"]",
+        )
+        .expect("valid selector");
+        self.documents
+            .iter()
+            .map(|(frame, document)| {
+                let covering: Vec<Selector> = filled
+                    .iter()
+                    .filter(|(_, filled_frame)| filled_frame == frame)
+                    .filter_map(|(selector, _)| Selector::parse(selector).ok())
+                    .collect();
+                document
+                    .select(&required)
+                    .filter(|field| !covering.iter().any(|selector| document.select(selector).any(|element| element.id() == field.id())))
+                    .count()
+            })
+            .sum()
+    }
+}
+
+/// Skrypt dla rozpoznanej platformy, bez kliknięcia wysyłającego zgłoszenie; `None`, gdy strona nie należy
+/// do znanej platformy, żadne pole z mapy nie pasuje do danych użytkownika albo wymagane pole zostałoby puste
+pub fn generate(html: &str, site: Option<&str>, user_data: &Value) -> Option<String> {
+    let platform = detect(html, site)?;
+    let page = Page::new(html);
+    let value_of = |keys: &[&str]| {
+        keys.iter().find_map(|key| user_data.get(*key).and_then(Value::as_str).map(str::trim).filter(|value| !value.is_empty()))
+    };
+
+    let mut actions: Vec<(Option<String>, String)> = Vec::new();
+    let mut filled: Vec<(&str, Option<String>)> = Vec::new();
+    for rule in platform.rules() {
+        let Some(value) = value_of(rule.keys) else { continue };
+        let Some((selector, frame)) = page.find(rule.selectors) else {
+            debug!(platform = platform.as_str(), keys = ?rule.keys, "Platform field not on this page");
+            continue;
+        };
+        let command = match rule.action {
+            Action::Type => "type",
+            Action::Upload => "upload",
+            Action::Select => "select",
+        };
+        filled.push((selector, frame.clone()));
+        actions.push((frame, format!("{} \"{}\" \"{}\"", command, escape_for_dsl(selector), escape_for_dsl(value))));
+    }
+    if actions.is_empty() {
+        debug!(platform = platform.as_str(), "No platform field matches the user data");
+        return None;
+    }
+    let uncovered = page.uncovered_required(&filled);
+    if uncovered > 0 {
+        debug!(platform = platform.as_str(), uncovered, "Required fields outside the platform map, leaving the page to the next strategy");
+        return None;
+    }
+
+    // Kolejne komendy z tej samej ramki w jednym bloku `frame`
+    let mut lines = Vec::new();
+    let mut open: Option<&str> = None;
+    for (frame, action) in &actions {
+        if frame.as_deref() != open {
+            if open.is_some() {
+                lines.push("end".to_string());
+            }
+            if let Some(frame) = frame {
+                lines.push(format!("frame \"{}\"", escape_for_dsl(frame)));
+            }
+            open = frame.as_deref();
+        }
+        lines.push(if open.is_some() { format!("  {}", action) } else { action.clone() });
+    }
+    if open.is_some() {
+        lines.push("end".to_string());
+    }
+
+    info!(platform = platform.as_str(), commands = actions.len(), "Generated DSL from the platform selector map");
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_platform_scripts_from_selector_maps() {
+        let user_data = json!({
+            "first_name": "Jan", "last_name": "Kowalski", "fullname": "Jan Kowalski", "email": "jan@example.com",
+            "phone": "600100200", "cv_path": "/home/jan/cv.pdf", "linkedin": "https://linkedin.com/in/jan",
+        });
+
+        assert_eq!(detect("<form></form>", Some("acme.wd5.myworkdayjobs.com")), Some(Platform::Workday));
+        assert_eq!(
+            detect("<iframe id=\"grnhse_iframe\" src=\"https://boards.greenhouse.io/embed/job_app?for=acme\"></iframe>", Some("careers.acme.com")),
+            Some(Platform::Greenhouse)
+        );
+        // Wzmianka o platformie w treści strony to jeszcze nie jej formularz
+        assert_eq!(detect("<p>We moved from jobs.lever.co</p><div id=\"grnhse_app\"></div>", Some("careers.acme.com")), None);
+        assert_eq!(detect("<form></form>", Some("notlever.co")), None);
+        assert_eq!(generate("<input id=\"email\">", Some("example.com"), &user_data), None);
+
+        let lever = r#"<form><input type="file" id="resume-upload-input" name="resume">
+            <input name="name"><input name="email"><input name="phone"><input name="urls[LinkedIn]">
+            <button id="btn-submit" data-qa="btn-submit">Submit application</button></form>"#;
+        assert_eq!(
+            generate(lever, Some("jobs.lever.co"), &user_data).unwrap(),
+            "upload \"#resume-upload-input\" \"/home/jan/cv.pdf\"\ntype \"input[name=\\\"name\\\"]\" \"Jan Kowalski\"\n\
+             type \"input[name=\\\"email\\\"]\" \"jan@example.com\"\ntype \"input[name=\\\"phone\\\"]\" \"600100200\"\n\
+             type \"input[name=\\\"urls[LinkedIn]\\\"]\" \"https://linkedin.com/in/jan\""
+        );
+        // Wymagane pytanie spoza mapy - skrypt z mapy zostawiłby je puste
+        let with_question = lever.replace("<button", "<textarea name=\"cards[q1]\" required></textarea><button");
+        assert_eq!(generate(&with_question, Some("jobs.lever.co"), &user_data), None);
+
+        // Greenhouse osadzony w ramce na stronie pracodawcy
+        let greenhouse = format!(
+            "<div id=\"grnhse_app\"><iframe id=\"grnhse_iframe\" src=\"https://boards.greenhouse.io/embed/job_app?for=acme\"></iframe>\
+             <{tag} name=\"grnhse_iframe\"><form><input id=\"first_name\" required><input id=\"last_name\" required>\
+             <input id=\"email\" required><input type=\"file\" id=\"resume\"><input id=\"submit_app\" type=\"submit\"></form></{tag}></div>",
+            tag = cdp::FRAME_TAG
+        );
+        let script = generate(&greenhouse, Some("careers.acme.com"), &user_data).unwrap();
+        assert_eq!(
+            script,
+            "frame \"grnhse_iframe\"\n  type \"#first_name\" \"Jan\"\n  type \"#last_name\" \"Kowalski\"\n  type \"#email\" \"jan@example.com\"\n  \
+             upload \"#resume\" \"/home/jan/cv.pdf\"\nend"
+        );
+        assert!(crate::tagui::validate_dsl_script(&script).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+//! Samonaprawa przebiegu: gdy krok N zawodzi, model dostaje nieudaną komendę, błąd, końcówkę wyjścia
+//! i pola strony z chwili błędu, i pisze od nowa resztę skryptu. Przeglądarka poprzedniego przebiegu już nie
+//! istnieje, więc poprawiony skrypt powtarza wykonane kroki - ale bez kliknięć i klawiszy, które mogły już coś
+//! wysłać na stronie. Wartości zmiennych w wyjściu i błędzie trafiają do modelu jako `{{nazwa}}`.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::dsl::generated;
+use crate::faults::{self, FaultTarget};
+use crate::perf::{self, OperationKind};
+use crate::tagui::{self, ExecutionResult, ExecutionStatus};
+use crate::{llm, llm_provider, llm_usage, privacy, redaction, replay, throttle};
+
+/// Domyślna liczba prób naprawy po nieudanym przebiegu (SELF_HEAL_MAX_ATTEMPTS)
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 2;
+
+/// Górny limit prób naprawy w jednym żądaniu
+pub const MAX_ATTEMPTS_LIMIT: u32 = 5;
+
+/// Ile ostatnich linii wyjścia przebiegu widzi model
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// Krótsze wartości zmiennych nie są zastępowane w wyjściu - pasowałyby do przypadkowych fragmentów
+const MIN_MASKED_VALUE_CHARS: usize = 3;
+
+/// Poprawiony skrypt; koszt wywołania modelu rozlicza `llm_usage::record`
+#[derive(Debug, Clone)]
+pub struct HealedScript {
+    pub script: String,
+}
+
+/// Skrypt podzielony na instrukcje najwyższego poziomu wykonane przed błędem i resztę od instrukcji,
+/// w której wystąpił błąd (cały blok `if`/`repeat`/`for`/`frame`, jeśli błąd jest w jego środku)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptSplit {
+    pub completed: String,
+    pub remaining: String,
+    /// Pierwsza linia `remaining` w oryginalnym skrypcie
+    pub from_line: usize,
+}
+
+pub fn split_at_failure(script: &str, failed_line: usize) -> Option<ScriptSplit> {
+    let mut depth = 0usize;
+    let mut from_line = None;
+    for (line, command) in tagui::script_commands(script) {
+        if line > failed_line {
+            break;
+        }
+        let keyword = command.split_whitespace().next().unwrap_or_default();
+        if depth == 0 && keyword != "end" {
+            from_line = Some(line);
+        }
+        match keyword {
+            "end" => depth = depth.saturating_sub(1),
+            keyword if tagui::DSL_BLOCK_KEYWORDS.contains(&keyword) => depth += 1,
+            _ => {}
+        }
+    }
+    let from_line = from_line?;
+    let lines: Vec<&str> = script.lines().collect();
+    Some(ScriptSplit {
+        completed: lines[..from_line - 1].join("\n"),
+        remaining: lines[from_line - 1..].join("\n"),
+        from_line,
+    })
+}
+
+/// Wykonane kroki do powtórzenia w nowej przeglądarce: bez instrukcji najwyższego poziomu (całych bloków), które
+/// klikają lub naciskają klawisz - ich ponowne wykonanie mogłoby drugi raz wysłać formularz
+pub fn replayable_steps(completed: &str) -> String {
+    let mut kept = Vec::new();
+    let mut statement = Vec::new();
+    let mut submits = false;
+    let mut depth = 0usize;
+    for line in completed.lines() {
+        statement.push(line);
+        let command = line.trim();
+        if command.is_empty() || command.starts_with("//") {
+            if depth == 0 {
+                kept.append(&mut statement);
+            }
+            continue;
+        }
+        let keyword = command.split_whitespace().next().unwrap_or_default();
+        submits |= throttle::SUBMISSION_COMMANDS.contains(&keyword);
+        match keyword {
+            "end" => depth = depth.saturating_sub(1),
+            keyword if tagui::DSL_BLOCK_KEYWORDS.contains(&keyword) => depth += 1,
+            _ => {}
+        }
+        if depth == 0 {
+            if !submits {
+                kept.append(&mut statement);
+            }
+            statement.clear();
+            submits = false;
+        }
+    }
+    kept.join("\n")
+}
+
+/// Zastępuje wartości zmiennych w tekście znacznikami `{{nazwa}}`; dłuższe wartości najpierw
+fn mask_variable_values(text: &str, values: &HashMap<String, String>) -> String {
+    let mut values: Vec<(&String, &String)> =
+        values.iter().filter(|(_, value)| value.trim().chars().count() >= MIN_MASKED_VALUE_CHARS).collect();
+    values.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+    values.into_iter().fold(text.to_string(), |text, (name, value)| text.replace(value.as_str(), &format!("{{{{{}}}}}", name)))
+}
+
+/// Czy przebieg zawiódł na konkretnym kroku, który model może próbować naprawić
+pub fn can_heal(result: &ExecutionResult) -> bool {
+    result.status == ExecutionStatus::Failed && result.failed_line.is_some()
+}
+
+fn output_tail(result: &ExecutionResult) -> String {
+    let output = format!("{}\n{}", result.stdout, result.stderr);
+    let lines: Vec<&str> = output.lines().filter(|line| !line.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+fn healing_prompt(split: &ScriptSplit, failed_command: &str, error: &str, output: &str, page: &str) -> String {
+    format!(
+        "Skrypt DSL automatyzacji przeglądarki przerwał się na kroku. Napisz od nowa pozostałą część skryptu tak,\n\
+        żeby zadziałała na stronie opisanej poniżej.\n\
+        \n\
+        Zasady:\n\
+        1. Zwróć TYLKO komendy zastępujące pozostałą część - bez kroków już wykonanych i bez komentarzy\n\
+        2. Używaj selektorów pól strony z listy; jeśli element nie istnieje, znajdź jego odpowiednik albo poczekaj na niego (waitfor)\n\
+        3. Zmienne {{{{nazwa}}}} zostaw bez zmian - wartości są podstawiane przy uruchomieniu\n\
+        4. Zachowaj cel pozostałych kroków; nie dodawaj kroków, o które skrypt nie prosił\n\
+        5. Przebieg zacznie się od nowa, a kroki wykonane zostaną powtórzone bez kliknięć i klawiszy (click, press)\n\
+        \n\
+        Kroki wykonane:\n{}\n\
+        \n\
+        Pozostała część (od kroku, który zawiódł):\n{}\n\
+        \n\
+        Krok, który zawiódł: {}\n\
+        Błąd: {}\n\
+        \n\
+        Ostatnie wyjście przebiegu:\n{}\n\
+        \n\
+        Pola strony w chwili błędu:\n{}\n\
+        \n\
+        Wygeneruj poprawioną pozostałą część skryptu:",
+        if split.completed.trim().is_empty() { "(brak)" } else { split.completed.as_str() },
+        split.remaining,
+        failed_command,
+        error,
+        if output.is_empty() { "(brak)" } else { output },
+        page
+    )
+}
+
+/// Poprawiony skrypt po nieudanym przebiegu `result`: wykonane kroki `script` do powtórzenia (`replayable_steps`)
+/// i reszta napisana przez model. `page_html` to strona w chwili błędu, gdy silnik jej nie zachował (np. TagUI);
+/// bez niej model widzi tylko błąd. `values` to wartości podstawione w skrypcie - model ich nie widzi.
+pub async fn heal(script: &str, result: &ExecutionResult, page_html: Option<&str>, values: &HashMap<String, String>) -> Result<HealedScript> {
+    let Some(failed_line) = result.failed_line.filter(|_| can_heal(result)) else {
+        bail!("Run did not fail on a specific step");
+    };
+    if !privacy::allows_llm() {
+        bail!("LLM is disabled in this deployment");
+    }
+    let provider = llm_provider::current();
+    if !provider.is_configured() {
+        bail!("No LLM provider is configured");
+    }
+    let split = split_at_failure(script, failed_line).ok_or_else(|| anyhow!("Line {} is not a command of the script", failed_line))?;
+    let failed_command = script.lines().nth(failed_line - 1).unwrap_or_default().trim();
+
+    // Strona przechodzi przez redakcję jak przy generowaniu - model widzi pola, nie wpisane wartości
+    let page = match result.page_html.as_deref().or(page_html) {
+        Some(html) => llm::page_summary(&redaction::redact(&mask_variable_values(html, values), &Value::Null).html),
+        None => "(niedostępne)".to_string(),
+    };
+    let error = mask_variable_values(result.error.as_deref().unwrap_or_default(), values);
+    let output = mask_variable_values(&output_tail(result), values);
+    let prompt = healing_prompt(&split, failed_command, &error, &output, &page);
+
+    info!(provider = provider.kind().as_str(), failed_line, "Asking LLM to rewrite the remaining steps of a failed run");
+    let replay_key = replay::content_key(&format!("heal:{}", failed_line), script);
+    let response_body: Value = replay::intercept(replay::InteractionKind::LlmResponse, &replay_key, || async {
+        faults::inject(FaultTarget::Llm).await?;
+        let request = provider.complete(&prompt, llm_provider::DEFAULT_MAX_TOKENS);
+        perf::timed(OperationKind::LlmCall, provider.operation(), json!({ "failed_line": failed_line, "heal": true }), request).await
+    })
+    .await?;
+    let completion = provider.response_text(&response_body).unwrap_or_default();
+    let usage = provider.token_usage(&response_body);
+    llm_usage::record(provider.as_ref(), "heal", usage, &prompt, completion);
+
+    let remaining = llm::parse_dsl_from_response(completion);
+    if remaining.trim().is_empty() {
+        bail!("Model returned no DSL commands for the remaining steps");
+    }
+    let completed = replayable_steps(&split.completed);
+    let healed = if completed.trim().is_empty() { remaining } else { format!("{}\n{}", completed, remaining) };
+    if let Err(e) = generated::check(&healed) {
+        warn!("Healed script rejected: {}", e);
+        bail!("Model returned an invalid script: {}", e);
+    }
+    Ok(HealedScript { script: healed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_at_failure_keeps_whole_blocks() {
+        let script = "type \"#email\" \"{{email}}\"\n// zgoda\nif present \"#cookies\"\n  click \"#accept\"\nend\nclick \"#next\"\nclick \"#submit\"";
+
+        let split = split_at_failure(script, 6).unwrap();
+        assert_eq!(split.from_line, 6);
+        assert_eq!(split.remaining, "click \"#next\"\nclick \"#submit\"");
+        assert!(split.completed.ends_with("end"));
+
+        // Błąd w środku bloku - naprawiany jest cały blok
+        let split = split_at_failure(script, 4).unwrap();
+        assert_eq!(split.from_line, 3);
+        assert!(split.remaining.starts_with("if present"));
+        assert_eq!(split.completed, "type \"#email\" \"{{email}}\"\n// zgoda");
+
+        assert_eq!(split_at_failure(script, 1).unwrap().completed, "");
+        assert!(split_at_failure("// tylko komentarz", 1).is_none());
+    }
+
+    #[test]
+    fn test_replayable_steps_drop_submissions() {
+        let completed = "url \"https://example.com/apply\"\ntype \"#email\" \"{{email}}\"\n// zgoda\nif present \"#cookies\"\n  click \"#accept\"\nend\nclick \"#next\"\nselect \"#country\" \"PL\"";
+        assert_eq!(
+            replayable_steps(completed),
+            "url \"https://example.com/apply\"\ntype \"#email\" \"{{email}}\"\n// zgoda\nselect \"#country\" \"PL\""
+        );
+
+        let values = HashMap::from([
+            ("email".to_string(), "jan@example.com".to_string()),
+            ("city".to_string(), "Ło".to_string()),
+        ]);
+        assert_eq!(
+            mask_variable_values("typed jan@example.com into #email, Łódź", &values),
+            "typed {{email}} into #email, Łódź"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Result, anyhow};
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use zeroize::Zeroize;
+
+use codialog_types::SecretString;
+
+pub use codialog_types::INSTANCE_NONCE_HEADER;
+
+/// Losowy nonce generowany przy każdym starcie aplikacji.
+/// Sessions store only its SHA-256 binding, so a session id replayed by another process
+/// or after a restart no longer resolves.
+pub struct InstanceNonce {
+    nonce: SecretString,
+    binding: String,
+}
+
+impl InstanceNonce {
+    pub fn generate() -> Result<Self> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| anyhow!("Failed to generate instance nonce"))?;
+        let nonce = SecretString::new(hex(&bytes));
+        bytes.zeroize();
+        Ok(Self::from_secret(nonce))
+    }
+
+    fn from_secret(nonce: SecretString) -> Self {
+        let binding = hex(digest::digest(&digest::SHA256, nonce.expose_secret().as_bytes()).as_ref());
+        Self { nonce, binding }
+    }
+
+    /// Wartość przekazywana tylko frontendowi tej instancji (komenda Tauri)
+    pub fn expose_secret(&self) -> &str {
+        self.nonce.expose_secret()
+    }
+
+    /// Skrót nonce zapisywany przy sesjach; nie pozwala odtworzyć nagłówka
+    pub fn binding(&self) -> &str {
+        &self.binding
+    }
+
+    pub fn verify(&self, provided: &str) -> bool {
+        ring::constant_time::verify_slices_are_equal(provided.trim().as_bytes(), self.expose_secret().as_bytes()).is_ok()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_verification() {
+        let instance = InstanceNonce::generate().unwrap();
+        assert_eq!(instance.expose_secret().len(), 64);
+        assert!(instance.verify(instance.expose_secret()));
+        assert!(!instance.verify(""));
+
+        let restarted = InstanceNonce::generate().unwrap();
+        assert!(!restarted.verify(instance.expose_secret()));
+        assert_ne!(restarted.binding(), instance.binding());
+        assert_ne!(instance.binding(), instance.expose_secret());
+    }
+}
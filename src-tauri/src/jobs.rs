@@ -0,0 +1,856 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context};
+use tracing::{info, warn, error, debug};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::budget::{BudgetOwner, BudgetStore};
+use crate::lifecycle::Lifecycle;
+use crate::{llm_usage, logging};
+use crate::maintenance::{MaintenanceSchedule, MaintenanceWindow};
+use crate::perf::{self, OperationKind};
+use crate::site_blocks::{self, SiteBlock};
+use crate::tagui::{RunManager, RunOptions};
+use crate::throttle::{self, SubmissionThrottle, ThrottlePolicy};
+use codialog_core::cdp;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationJob {
+    pub id: String,
+    pub script: String,
+    pub status: String,
+    pub worker_id: Option<String>,
+    pub attempts: i32,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Koniec okna serwisowego, na które zadanie czekało (lub czeka) w kolejce
+    #[serde(default)]
+    pub deferred_until: Option<DateTime<Utc>>,
+    /// Nazwa tego okna
+    #[serde(default)]
+    pub deferred_by: Option<String>,
+    /// Harmonogram, który dodał zadanie
+    #[serde(default)]
+    pub schedule_id: Option<String>,
+    /// Użytkownik (z sesji lub harmonogramu), którego limity obejmują zadanie
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Strona startowa przebiegu
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Witryna, na której zadanie wysyła formularz; worker pomija zadania witryn, na których trwa odstęp
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Witryna strony startowej; zadania witryn zablokowanych przez ochronę przed botami czekają w kolejce
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl AutomationJob {
+    pub fn owner(&self) -> JobOwner {
+        JobOwner { schedule_id: self.schedule_id.clone(), user_id: self.user_id.clone() }
+    }
+}
+
+/// Do kogo należy zadanie - według tego liczone są limity budżetu
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JobOwner {
+    pub schedule_id: Option<String>,
+    pub user_id: Option<String>,
+}
+
+impl JobOwner {
+    pub fn budget_owners(&self) -> Vec<(BudgetOwner, &str)> {
+        let schedule = self.schedule_id.as_deref().map(|id| (BudgetOwner::Schedule, id));
+        let user = self.user_id.as_deref().map(|id| (BudgetOwner::User, id));
+        schedule.into_iter().chain(user).collect()
+    }
+
+    /// Przypisanie wywołań modelu do sesji i limitów tego właściciela
+    pub fn usage_attribution(&self, session_id: Option<String>) -> llm_usage::Attribution {
+        let budget_owners = self.budget_owners().into_iter().map(|(owner_type, id)| (owner_type.as_str().to_string(), id.to_string())).collect();
+        llm_usage::Attribution { session_id, budget_owners }
+    }
+}
+
+/// Ile kandydatów różnych witryn sprawdza jedno pobranie, zanim worker poczeka na następny cykl
+const MAX_CLAIM_CANDIDATES: usize = 8;
+
+/// Warunek SQL: od ostatniego startu zadania witryny `site` minął jej odstęp. Odstępy witryn to parametry
+/// `$first` (witryny), `$first+1` (sekundy) i `$first+2` (odstęp domyślny); najdłuższe pasujące dopasowanie wygrywa.
+fn site_interval_elapsed(site: &str, first: usize) -> String {
+    format!(
+        r#"NOT EXISTS (
+            SELECT 1 FROM automation_jobs recent
+            WHERE recent.site = {site}
+              AND recent.started_at > NOW() - make_interval(secs => COALESCE(
+                  (SELECT site_interval.secs FROM unnest(${sites}::text[], ${secs}::float8[]) AS site_interval(site, secs)
+                   WHERE {site} = site_interval.site OR {site} LIKE '%.' || site_interval.site
+                   ORDER BY length(site_interval.site) DESC
+                   LIMIT 1),
+                  ${default}::float8))
+        )"#,
+        site = site,
+        sites = first,
+        secs = first + 1,
+        default = first + 2,
+    )
+}
+
+/// Współdzielona kolejka zadań automatyzacji oparta o PostgreSQL
+#[derive(Debug, Clone)]
+pub struct JobQueue {
+    db_pool: PgPool,
+    /// Odstęp po pierwszej blokadzie witryny; zero wyłącza pamięć blokad
+    block_cooldown: Duration,
+}
+
+impl JobQueue {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool, block_cooldown: site_blocks::DEFAULT_COOLDOWN }
+    }
+
+    pub fn with_block_cooldown(mut self, cooldown: Duration) -> Self {
+        self.block_cooldown = cooldown;
+        self
+    }
+
+    /// Inicjalizuje tabelę kolejki zadań
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing automation job queue table");
+
+        // Kilka poleceń naraz - tylko bez przygotowanego zapytania
+        sqlx::raw_sql(
+            r#"
+            CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
+
+            -- Jak w migrations/002_automation_jobs.sql; nowsze kolumny dochodzą przez ALTER poniżej
+            CREATE TABLE IF NOT EXISTS automation_jobs (
+                id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                script TEXT NOT NULL,
+                status VARCHAR(20) NOT NULL DEFAULT 'queued', -- 'queued', 'running', 'succeeded', 'failed'
+                worker_id VARCHAR(255),
+                attempts INTEGER NOT NULL DEFAULT 0,
+                result JSONB,
+                error TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                started_at TIMESTAMPTZ,
+                heartbeat_at TIMESTAMPTZ,
+                finished_at TIMESTAMPTZ
+            );
+
+            ALTER TABLE automation_jobs
+                ADD COLUMN IF NOT EXISTS deferred_until TIMESTAMPTZ,
+                ADD COLUMN IF NOT EXISTS deferred_by VARCHAR(255),
+                ADD COLUMN IF NOT EXISTS schedule_id UUID,
+                ADD COLUMN IF NOT EXISTS user_id VARCHAR(255),
+                ADD COLUMN IF NOT EXISTS url TEXT,
+                ADD COLUMN IF NOT EXISTS site VARCHAR(255),
+                ADD COLUMN IF NOT EXISTS domain VARCHAR(255);
+
+            CREATE INDEX IF NOT EXISTS idx_automation_jobs_status ON automation_jobs(status, created_at);
+            CREATE INDEX IF NOT EXISTS idx_automation_jobs_site ON automation_jobs(site, started_at) WHERE site IS NOT NULL;
+
+            CREATE TABLE IF NOT EXISTS site_blocks (
+                site VARCHAR(255) PRIMARY KEY,
+                reason TEXT NOT NULL,
+                hits INTEGER NOT NULL DEFAULT 1,
+                job_id VARCHAR(255),
+                detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                blocked_until TIMESTAMPTZ NOT NULL
+            );
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create automation_jobs table")?;
+
+        Ok(())
+    }
+
+    /// Dodaje nowe zadanie do kolejki; `url` to strona startowa, od której zależy odstęp między wysłaniami
+    pub async fn enqueue(&self, script: &str, url: Option<&str>, owner: &JobOwner) -> Result<String> {
+        self.enqueue_with(&self.db_pool, script, url, owner).await
+    }
+
+    /// Jak `enqueue`, ale w transakcji wywołującego (np. razem z przesunięciem terminu harmonogramu)
+    pub async fn enqueue_with<'e>(
+        &self,
+        executor: impl sqlx::PgExecutor<'e>,
+        script: &str,
+        url: Option<&str>,
+        owner: &JobOwner,
+    ) -> Result<String> {
+        let row = sqlx::query(
+            "INSERT INTO automation_jobs (script, schedule_id, user_id, url, site, domain) VALUES ($1, $2::uuid, $3, $4, $5, $6) RETURNING id::text AS id",
+        )
+        .bind(script)
+        .bind(&owner.schedule_id)
+        .bind(&owner.user_id)
+        .bind(url)
+        .bind(throttle::submission_site(script, url))
+        .bind(url.and_then(cdp::site_of))
+        .fetch_one(executor)
+        .await
+        .context("Failed to enqueue automation job")?;
+
+        let job_id: String = row.get("id");
+        info!("Automation job queued: {}", job_id);
+        Ok(job_id)
+    }
+
+    /// Claims the oldest queued job outside `throttled_sites` and `blocked_sites`; SKIP LOCKED lets many workers poll concurrently.
+    /// Odstęp wysłań (`policy`) chroni blokada doradcza transakcji na witrynę kandydata: dopiero pod nią kolejne
+    /// zapytanie (z nowym snapshotem) sprawdza `started_at`, więc dwa workery (także z różnych instancji) nie pobiorą
+    /// naraz zadań tej samej witryny, a zadania różnych witryn są pobierane równolegle
+    pub async fn claim_next(
+        &self,
+        worker_id: &str,
+        throttled_sites: &[String],
+        blocked_sites: &[String],
+        policy: Option<&ThrottlePolicy>,
+    ) -> Result<Option<AutomationJob>> {
+        let default_interval = policy.map(|policy| policy.default_interval).unwrap_or_default();
+        let (interval_sites, interval_secs): (Vec<String>, Vec<f64>) = policy
+            .map(|policy| policy.site_intervals.iter().map(|(site, interval)| (site.clone(), interval.as_secs_f64())).unzip())
+            .unwrap_or_default();
+        let claim = async {
+            let mut tx = self.db_pool.begin().await?;
+            // Witryny pominięte w tej próbie: w odstępie albo właśnie pobierane przez inny worker
+            let mut skipped_sites = throttled_sites.to_vec();
+            for _ in 0..MAX_CLAIM_CANDIDATES {
+                let candidate = sqlx::query(&format!(
+                    r#"
+                    SELECT id::text AS id, site FROM automation_jobs job
+                    WHERE status = 'queued' AND (site IS NULL OR NOT site = ANY($1))
+                      AND (domain IS NULL OR NOT domain = ANY($2))
+                      AND (site IS NULL OR {})
+                    ORDER BY created_at
+                    FOR UPDATE SKIP LOCKED
+                    LIMIT 1
+                    "#,
+                    site_interval_elapsed("job.site", 3),
+                ))
+                .bind(&skipped_sites)
+                .bind(blocked_sites)
+                .bind(&interval_sites)
+                .bind(&interval_secs)
+                .bind(default_interval.as_secs_f64())
+                .fetch_optional(&mut *tx)
+                .await?;
+                let Some(candidate) = candidate else {
+                    break;
+                };
+
+                if let Some(site) = candidate.get::<Option<String>, _>("site") {
+                    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtext('automation_jobs.site:' || $1))")
+                        .bind(&site)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                    // Snapshot tego zapytania powstaje już pod blokadą i widzi zatwierdzone pobrania innych workerów
+                    let elapsed = locked
+                        && sqlx::query_scalar::<_, bool>(&format!("SELECT {}", site_interval_elapsed("$1", 2)))
+                            .bind(&site)
+                            .bind(&interval_sites)
+                            .bind(&interval_secs)
+                            .bind(default_interval.as_secs_f64())
+                            .fetch_one(&mut *tx)
+                            .await?;
+                    if !elapsed {
+                        skipped_sites.push(site);
+                        continue;
+                    }
+                }
+
+                let row = sqlx::query(
+                    r#"
+                    UPDATE automation_jobs
+                    SET status = 'running', worker_id = $1, attempts = attempts + 1,
+                        started_at = NOW(), heartbeat_at = NOW()
+                    WHERE id = $2::uuid
+                    RETURNING id::text AS id, script, status, worker_id, attempts, result, error,
+                              created_at, started_at, finished_at, deferred_until, deferred_by,
+                              schedule_id::text AS schedule_id, user_id, url, site, domain
+                    "#,
+                )
+                .bind(worker_id)
+                .bind(candidate.get::<String, _>("id"))
+                .fetch_one(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                return Ok::<_, sqlx::Error>(Some(row));
+            }
+            tx.rollback().await?;
+            Ok(None)
+        };
+        let row = perf::timed(OperationKind::DbQuery, "automation_jobs.claim", serde_json::json!({ "worker_id": worker_id }), claim)
+            .await
+            .context("Failed to claim automation job")?;
+
+        Ok(row.map(|row| job_from_row(&row)))
+    }
+
+    /// Zapisuje wynik wykonania zadania
+    pub async fn complete(&self, job_id: &str, success: bool, result: &serde_json::Value, error: Option<&str>) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE automation_jobs
+            SET status = $2, result = $3, error = $4, finished_at = NOW()
+            WHERE id = $1::uuid
+            "#,
+        )
+        .bind(job_id)
+        .bind(if success { "succeeded" } else { "failed" })
+        .bind(result)
+        .bind(error)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to complete automation job")?;
+
+        Ok(())
+    }
+
+    pub async fn heartbeat(&self, job_id: &str) -> Result<()> {
+        sqlx::query("UPDATE automation_jobs SET heartbeat_at = NOW() WHERE id = $1::uuid")
+            .bind(job_id)
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to update job heartbeat")?;
+        Ok(())
+    }
+
+    /// Puts jobs of crashed workers (no heartbeat within `stale_after`) back into the queue.
+    /// Zadanie, które przerwało już `max_attempts` przebiegów (np. zawiesza workera), kończy się błędem zamiast wracać.
+    /// Zwraca (przywrócone, porzucone).
+    pub async fn requeue_stale(&self, stale_after: Duration, max_attempts: i32) -> Result<(u64, u64)> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE automation_jobs
+            SET status = CASE WHEN attempts >= $2 THEN 'failed' ELSE 'queued' END,
+                worker_id = NULL,
+                error = CASE WHEN attempts >= $2 THEN 'Abandoned after ' || attempts || ' interrupted attempts' ELSE error END,
+                finished_at = CASE WHEN attempts >= $2 THEN NOW() ELSE finished_at END
+            WHERE status = 'running'
+              AND heartbeat_at < NOW() - make_interval(secs => $1)
+            RETURNING status
+            "#,
+        )
+        .bind(stale_after.as_secs_f64())
+        .bind(max_attempts)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to requeue stale jobs")?;
+
+        let abandoned = rows.iter().filter(|row| row.get::<String, _>("status") == "failed").count() as u64;
+        Ok((rows.len() as u64 - abandoned, abandoned))
+    }
+
+    /// Oznacza zadania czekające w kolejce jako odroczone do końca okna; zwraca tylko nowo odroczone,
+    /// więc każdy worker zgłasza odroczenie danego zadania najwyżej raz na okno
+    pub async fn defer_queued(&self, window: &MaintenanceWindow) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE automation_jobs
+            SET deferred_until = $1, deferred_by = $2
+            WHERE status = 'queued' AND deferred_until IS DISTINCT FROM $1
+            RETURNING id::text AS id
+            "#,
+        )
+        .bind(window.ends_at)
+        .bind(&window.name)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to defer queued jobs")?;
+
+        Ok(rows.iter().map(|row| row.get("id")).collect())
+    }
+
+    /// Ostatni start zadania na każdej witrynie w oknie `within` - także z innych workerów i instancji
+    pub async fn recent_submissions(&self, within: Duration) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT site, MAX(started_at) AS started_at
+            FROM automation_jobs
+            WHERE site IS NOT NULL AND started_at > NOW() - make_interval(secs => $1)
+            GROUP BY site
+            "#,
+        )
+        .bind(within.as_secs_f64())
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to load recent submissions of jobs")?;
+
+        Ok(rows.iter().map(|row| (row.get("site"), row.get("started_at"))).collect())
+    }
+
+    /// Zapisuje blokadę witryny; kolejna w oknie `site_blocks::REPUTATION_WINDOW_HOURS` wydłuża odstęp.
+    /// `None`, gdy pamięć blokad jest wyłączona (SITE_BLOCK_COOLDOWN_SECS=0).
+    pub async fn record_block(&self, site: &str, reason: &str, job_id: &str) -> Result<Option<SiteBlock>> {
+        if self.block_cooldown.is_zero() {
+            return Ok(None);
+        }
+        let hits: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO site_blocks (site, reason, job_id, hits, detected_at, blocked_until)
+            VALUES ($1, $2, $3, 1, NOW(), NOW())
+            ON CONFLICT (site) DO UPDATE SET
+                reason = EXCLUDED.reason,
+                job_id = EXCLUDED.job_id,
+                hits = CASE WHEN site_blocks.detected_at > NOW() - make_interval(hours => $4) THEN site_blocks.hits + 1 ELSE 1 END,
+                detected_at = NOW()
+            RETURNING hits
+            "#,
+        )
+        .bind(site)
+        .bind(reason)
+        .bind(job_id)
+        .bind(site_blocks::REPUTATION_WINDOW_HOURS as i32)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to record site block")?;
+
+        let row = sqlx::query(
+            r#"
+            UPDATE site_blocks SET blocked_until = NOW() + make_interval(secs => $2)
+            WHERE site = $1
+            RETURNING site, reason, hits, job_id, detected_at, blocked_until
+            "#,
+        )
+        .bind(site)
+        .bind(site_blocks::cooldown(self.block_cooldown, hits).as_secs_f64())
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to set site block cool-down")?;
+        Ok(Some(block_from_row(&row)))
+    }
+
+    /// Witryny, na których trwa odstęp po blokadzie
+    pub async fn active_blocks(&self) -> Result<Vec<SiteBlock>> {
+        let rows = sqlx::query(
+            "SELECT site, reason, hits, job_id, detected_at, blocked_until FROM site_blocks WHERE blocked_until > NOW() ORDER BY blocked_until",
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to load site blocks")?;
+
+        Ok(rows.iter().map(block_from_row).collect())
+    }
+
+    /// Trwająca blokada jednej witryny
+    pub async fn block_of(&self, site: &str) -> Result<Option<SiteBlock>> {
+        let row = sqlx::query(
+            "SELECT site, reason, hits, job_id, detected_at, blocked_until FROM site_blocks WHERE site = $1 AND blocked_until > NOW()",
+        )
+        .bind(site)
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to load site block")?;
+
+        Ok(row.as_ref().map(block_from_row))
+    }
+
+    /// Zdejmuje blokadę witryny razem z jej historią i odroczeniem jej zadań; zadania witryny ruszają
+    /// przy następnym pobraniu
+    pub async fn clear_block(&self, site: &str) -> Result<bool> {
+        let mut tx = self.db_pool.begin().await.context("Failed to start site block removal")?;
+        let result = sqlx::query("DELETE FROM site_blocks WHERE site = $1")
+            .bind(site)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear site block")?;
+        sqlx::query(
+            r#"
+            UPDATE automation_jobs SET deferred_until = NULL, deferred_by = NULL
+            WHERE status = 'queued' AND domain = $1 AND deferred_by LIKE 'Site block:%'
+            "#,
+        )
+        .bind(site)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear deferral of jobs of unblocked site")?;
+        tx.commit().await.context("Failed to commit site block removal")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Oznacza zadania witryny czekające w kolejce jako odroczone do końca blokady; zwraca nowo odroczone
+    pub async fn defer_blocked(&self, block: &SiteBlock) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE automation_jobs
+            SET deferred_until = $2, deferred_by = $3
+            WHERE status = 'queued' AND domain = $1 AND deferred_until IS DISTINCT FROM $2
+            RETURNING id::text AS id
+            "#,
+        )
+        .bind(&block.site)
+        .bind(block.blocked_until)
+        .bind(format!("Site block: {}", block.reason))
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to defer jobs of blocked site")?;
+
+        Ok(rows.iter().map(|row| row.get("id")).collect())
+    }
+
+    /// Pobiera zadanie po ID
+    pub async fn get(&self, job_id: &str) -> Result<Option<AutomationJob>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id::text AS id, script, status, worker_id, attempts, result, error,
+                   created_at, started_at, finished_at, deferred_until, deferred_by,
+                   schedule_id::text AS schedule_id, user_id, url, site, domain
+            FROM automation_jobs
+            WHERE id = $1::uuid
+            "#,
+        )
+        .bind(job_id)
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to fetch automation job")?;
+
+        Ok(row.map(|row| job_from_row(&row)))
+    }
+}
+
+fn block_from_row(row: &sqlx::postgres::PgRow) -> SiteBlock {
+    SiteBlock {
+        site: row.get("site"),
+        reason: row.get("reason"),
+        hits: row.get("hits"),
+        job_id: row.get("job_id"),
+        detected_at: row.get("detected_at"),
+        blocked_until: row.get("blocked_until"),
+    }
+}
+
+fn job_from_row(row: &sqlx::postgres::PgRow) -> AutomationJob {
+    AutomationJob {
+        id: row.get("id"),
+        script: row.get("script"),
+        status: row.get("status"),
+        worker_id: row.get("worker_id"),
+        attempts: row.get("attempts"),
+        result: row.get("result"),
+        error: row.get("error"),
+        created_at: row.get("created_at"),
+        started_at: row.get("started_at"),
+        finished_at: row.get("finished_at"),
+        deferred_until: row.get("deferred_until"),
+        deferred_by: row.get("deferred_by"),
+        schedule_id: row.get("schedule_id"),
+        user_id: row.get("user_id"),
+        url: row.get("url"),
+        site: row.get("site"),
+        domain: row.get("domain"),
+    }
+}
+
+/// Po tylu przerwanych przebiegach (worker padł w trakcie) zadanie nie wraca już do kolejki
+pub const MAX_JOB_ATTEMPTS: i32 = 3;
+
+/// Uruchamia pętle workerów pobierające zadania z kolejki.
+/// Kończy się po rozpoczęciu zamknięcia, gdy workery dokończą bieżące zadania.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_worker_pool(
+    queue: Arc<JobQueue>,
+    run_manager: Arc<RunManager>,
+    maintenance: Arc<MaintenanceSchedule>,
+    budgets: Arc<BudgetStore>,
+    lifecycle: Arc<Lifecycle>,
+    worker_id: String,
+    concurrency: usize,
+    poll_interval: Duration,
+) {
+    info!(worker_id = %worker_id, concurrency = concurrency, "Starting automation worker pool");
+
+    let mut handles = Vec::new();
+    for slot in 0..concurrency.max(1) {
+        let queue = queue.clone();
+        let run_manager = run_manager.clone();
+        let maintenance = maintenance.clone();
+        let budgets = budgets.clone();
+        let lifecycle = lifecycle.clone();
+        let slot_id = format!("{}-{}", worker_id, slot);
+        handles.push(tokio::spawn(async move {
+            worker_loop(queue, run_manager, maintenance, budgets, lifecycle, slot_id, poll_interval).await;
+        }));
+    }
+
+    // Okresowo odzyskuj zadania porzucone przez martwe workery
+    let reaper_queue = queue.clone();
+    let reaper = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            match reaper_queue.requeue_stale(Duration::from_secs(300), MAX_JOB_ATTEMPTS).await {
+                Ok((0, 0)) => {}
+                Ok((requeued, abandoned)) => {
+                    warn!("Requeued {} stale automation jobs, failed {} after {} attempts", requeued, abandoned, MAX_JOB_ATTEMPTS)
+                }
+                Err(e) => warn!("Failed to requeue stale jobs: {}", e),
+            }
+        }
+    });
+
+    futures::future::join_all(handles).await;
+    reaper.abort();
+    info!(worker_id = %worker_id, "Automation worker pool drained");
+}
+
+/// Odracza zadania z kolejki na czas trwającego okna serwisowego i zgłasza to w zdarzeniach systemowych;
+/// zwraca `true`, gdy okno trwa i worker nie powinien pobierać zadań
+async fn hold_for_maintenance(queue: &JobQueue, maintenance: &MaintenanceSchedule, worker_id: &str) -> bool {
+    let window = match maintenance.active().await {
+        Ok(Some(window)) => window,
+        Ok(None) => return false,
+        Err(e) => {
+            // Bez dostępu do bazy nie da się też pobrać zadania, więc nic nie ruszy poza oknem
+            warn!("Worker {} failed to check maintenance windows: {}", worker_id, e);
+            return false;
+        }
+    };
+
+    match queue.defer_queued(&window).await {
+        Ok(job_ids) if job_ids.is_empty() => {}
+        Ok(job_ids) => {
+            warn!(
+                window = %window.name,
+                deferred_until = %window.ends_at,
+                jobs = job_ids.len(),
+                "Automation jobs deferred by maintenance window"
+            );
+            let event = serde_json::json!({
+                "operation": "jobs_deferred",
+                "window_id": window.id,
+                "window": window.name,
+                "reason": window.reason,
+                "deferred_until": window.ends_at,
+                "job_ids": job_ids,
+                "worker_id": worker_id,
+            });
+            if let Err(e) = logging::log_system_event(&queue.db_pool, "scheduler", "warn", &event).await {
+                warn!("Failed to log job deferral event: {}", e);
+            }
+        }
+        Err(e) => warn!("Worker {} failed to defer queued jobs: {}", worker_id, e),
+    }
+    true
+}
+
+/// Witryny, na których trwa odstęp - według wysłań w tym procesie i startów zadań zapisanych w bazie
+async fn throttled_sites(queue: &JobQueue, throttle: &SubmissionThrottle, worker_id: &str) -> Vec<String> {
+    if throttle.policy().max_interval().is_zero() {
+        return Vec::new();
+    }
+    match queue.recent_submissions(throttle.policy().max_interval()).await {
+        Ok(submissions) => {
+            for (site, started_at) in submissions {
+                throttle.observe(&site, started_at);
+            }
+        }
+        Err(e) => debug!("Worker {} failed to load recent submissions: {}", worker_id, e),
+    }
+    throttle.throttled_sites(Utc::now())
+}
+
+/// Witryny z trwającą blokadą - błąd bazy nie zatrzymuje kolejki
+async fn blocked_sites(queue: &JobQueue, worker_id: &str) -> Vec<String> {
+    if queue.block_cooldown.is_zero() {
+        return Vec::new();
+    }
+    match queue.active_blocks().await {
+        Ok(blocks) => blocks.into_iter().map(|block| block.site).collect(),
+        Err(e) => {
+            debug!("Worker {} failed to load site blocks: {}", worker_id, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Zapisuje blokadę witryny wykrytą w przebiegu zadania, odracza pozostałe zadania witryny
+/// i ostrzega o tym w zdarzeniach systemowych
+async fn report_block(queue: &JobQueue, job: &AutomationJob, site: &str, reason: &str, worker_id: &str) {
+    let block = match queue.record_block(site, reason, &job.id).await {
+        Ok(Some(block)) => block,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Worker {} failed to record block of {}: {}", worker_id, site, e);
+            return;
+        }
+    };
+    let job_ids = queue.defer_blocked(&block).await.unwrap_or_else(|e| {
+        warn!("Worker {} failed to defer jobs of blocked site {}: {}", worker_id, site, e);
+        Vec::new()
+    });
+    warn!(
+        site = %site,
+        reason = %reason,
+        hits = block.hits,
+        blocked_until = %block.blocked_until,
+        jobs = job_ids.len(),
+        "Site is blocking automation, its jobs are deferred"
+    );
+    let event = serde_json::json!({
+        "operation": "site_blocked",
+        "site": site,
+        "reason": reason,
+        "hits": block.hits,
+        "blocked_until": block.blocked_until,
+        "job_id": job.id,
+        "job_ids": job_ids,
+        "worker_id": worker_id,
+    });
+    if let Err(e) = logging::log_system_event(&queue.db_pool, "scheduler", "warn", &event).await {
+        warn!("Failed to log site block event: {}", e);
+    }
+}
+
+async fn worker_loop(
+    queue: Arc<JobQueue>,
+    run_manager: Arc<RunManager>,
+    maintenance: Arc<MaintenanceSchedule>,
+    budgets: Arc<BudgetStore>,
+    lifecycle: Arc<Lifecycle>,
+    worker_id: String,
+    poll_interval: Duration,
+) {
+    loop {
+        // Przy zamknięciu nie pobieramy nowych zadań; bieżące zadanie zostało już dokończone
+        if lifecycle.is_draining() {
+            debug!("Worker {} stopping for shutdown", worker_id);
+            return;
+        }
+
+        // Zadania już uruchomione kończą się normalnie; okno wstrzymuje tylko pobieranie nowych
+        if hold_for_maintenance(&queue, &maintenance, &worker_id).await {
+            tokio::time::sleep(poll_interval).await;
+            continue;
+        }
+
+        let throttled_sites = match run_manager.throttle() {
+            Some(throttle) => throttled_sites(&queue, throttle, &worker_id).await,
+            None => Vec::new(),
+        };
+        let blocked_sites = blocked_sites(&queue, &worker_id).await;
+        let policy = run_manager.throttle().map(|throttle| throttle.policy());
+        match queue.claim_next(&worker_id, &throttled_sites, &blocked_sites, policy).await {
+            Ok(Some(job)) => {
+                info!(job_id = %job.id, worker_id = %worker_id, "Executing automation job");
+                // Pobranie zadania zajmuje witrynę; przebieg już nie czeka w `RunManager`
+                if let (Some(throttle), Some(site)) = (run_manager.throttle(), &job.site) {
+                    throttle.observe(site, job.started_at.unwrap_or_else(Utc::now));
+                }
+                let start_time = std::time::Instant::now();
+
+                let heartbeat_queue = queue.clone();
+                let heartbeat_job_id = job.id.clone();
+                let heartbeat = tokio::spawn(async move {
+                    loop {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        if let Err(e) = heartbeat_queue.heartbeat(&heartbeat_job_id).await {
+                            debug!("Job heartbeat failed: {}", e);
+                        }
+                    }
+                });
+
+                let options = RunOptions { start_url: job.url.clone(), ..Default::default() };
+                let (run_id, execution) = run_manager.execute_with(&job.script, &options).await;
+                heartbeat.abort();
+                let trace = run_manager.trace(&run_id, &job.script);
+
+                let result = serde_json::json!({
+                    "success": execution.success(),
+                    "execution_time_ms": start_time.elapsed().as_millis(),
+                    "worker_id": worker_id,
+                    "run_id": run_id,
+                    "execution": execution,
+                    "trace": trace,
+                });
+
+                if let Err(e) = queue.complete(&job.id, execution.success(), &result, execution.error.as_deref()).await {
+                    error!("Failed to store result of job {}: {}", job.id, e);
+                }
+                // Seria nieudanych przebiegów wstrzymuje harmonogram lub użytkownika (max_failures)
+                budgets.record_outcome(&job.owner(), execution.success()).await;
+                if let Some((site, reason)) = job.domain.as_deref().zip(site_blocks::detect(&execution)) {
+                    report_block(&queue, &job, site, reason, &worker_id).await;
+                }
+            }
+            Ok(None) => tokio::time::sleep(poll_interval).await,
+            Err(e) => {
+                warn!("Worker {} failed to poll job queue: {}", worker_id, e);
+                tokio::time::sleep(poll_interval * 5).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kolejka w osobnym schemacie bazy TEST_DATABASE_URL; bez tej zmiennej test jest pomijany
+    async fn test_queue() -> Option<JobQueue> {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL is not set, skipping");
+            return None;
+        };
+        let schema = format!("jobs_test_{}", uuid::Uuid::new_v4().simple());
+        let admin = PgPool::connect(&url).await.unwrap();
+        // Rozszerzenie w public, aby widziały je schematy wszystkich testów
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS \"uuid-ossp\" SCHEMA public").execute(&admin).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema)).execute(&admin).await.unwrap();
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(8)
+            .after_connect(move |conn, _| {
+                let schema = schema.clone();
+                Box::pin(async move {
+                    sqlx::Executor::execute(conn, format!("SET search_path TO {}, public", schema).as_str()).await?;
+                    Ok(())
+                })
+            })
+            .connect(&url)
+            .await
+            .unwrap();
+        let queue = JobQueue::new(pool);
+        queue.initialize().await.unwrap();
+        Some(queue)
+    }
+
+    #[tokio::test]
+    async fn test_claim_next_keeps_site_interval_across_workers() {
+        let Some(queue) = test_queue().await else {
+            return;
+        };
+        let submit = "click \"#submit\"";
+        let owner = JobOwner::default();
+        for url in ["https://a.example/1", "https://a.example/2", "https://b.example/1", "https://b.example/2"] {
+            queue.enqueue(submit, Some(url), &owner).await.unwrap();
+        }
+        queue.enqueue("wait 1", Some("https://a.example/read"), &owner).await.unwrap();
+
+        // Sześciu workerów naraz: po jednym zadaniu na witrynę plus zadanie bez wysyłania, reszta czeka na odstęp
+        let policy = ThrottlePolicy { default_interval: Duration::from_secs(60), site_intervals: Vec::new() };
+        let claims = futures::future::join_all((0..6).map(|worker| {
+            let (queue, policy) = (queue.clone(), policy.clone());
+            async move { queue.claim_next(&format!("worker-{}", worker), &[], &[], Some(&policy)).await.unwrap() }
+        }))
+        .await;
+        let mut sites: Vec<Option<String>> = claims.into_iter().flatten().map(|job| job.site).collect();
+        sites.sort();
+        assert_eq!(sites, vec![None, Some("a.example".to_string()), Some("b.example".to_string())]);
+
+        // Witryna w odstępie u tego workera jest pomijana, a krótszy odstęp witryny zwalnia ją od razu
+        let short = ThrottlePolicy { default_interval: Duration::from_secs(60), site_intervals: vec![("b.example".to_string(), Duration::ZERO)] };
+        assert!(queue.claim_next("worker-x", &["b.example".to_string()], &[], Some(&short)).await.unwrap().is_none());
+        let job = queue.claim_next("worker-x", &[], &[], Some(&short)).await.unwrap().unwrap();
+        assert_eq!(job.site.as_deref(), Some("b.example"));
+        assert_eq!(job.worker_id.as_deref(), Some("worker-x"));
+        assert_eq!(job.attempts, 1);
+    }
+}
@@ -0,0 +1,483 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context, bail};
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::crypto::{ContentCipher, ENCRYPTED_PREFIX};
+
+/// Kolumna przechowująca wartości zaszyfrowane przez `crypto::ContentCipher`
+#[derive(Debug, Clone, Copy)]
+pub struct EncryptedColumn {
+    pub table: &'static str,
+    /// Wyrażenie tekstowe klucza wiersza - kursor wznawiania rotacji
+    pub key_expr: &'static str,
+    pub column: &'static str,
+}
+
+impl EncryptedColumn {
+    fn id(&self) -> String {
+        format!("{}.{}", self.table, self.column)
+    }
+}
+
+/// Cached page HTML
+pub const ENCRYPTED_COLUMNS: &[EncryptedColumn] = &[
+    EncryptedColumn { table: "dsl_cache", key_expr: "cache_key", column: "html_content" },
+];
+
+/// Jak zaszyfrowana treść jest ułożona w pliku
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileLayout {
+    /// Cały plik to jedna wartość `enc:v1:` (paczki replay)
+    Whole,
+    /// Człony gzip z wartością `enc:v1:` na linię (archiwa miesięczne)
+    GzipLines,
+}
+
+/// Katalog plików zaszyfrowanych ENCRYPTION_KEY, rotowany razem z kolumnami
+#[derive(Debug, Clone)]
+pub struct EncryptedFiles {
+    pub name: &'static str,
+    pub dir: PathBuf,
+    pub suffix: &'static str,
+    pub layout: FileLayout,
+}
+
+impl EncryptedFiles {
+    fn id(&self) -> String {
+        format!("files:{}", self.name)
+    }
+
+    /// Nazwy plików po `after` w kolejności - kursor wznawiania jak klucz wiersza
+    fn names_after(&self, after: Option<&str>) -> Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to list {}", self.dir.display())),
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|kind| kind.is_file()).unwrap_or(false))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name.ends_with(self.suffix) && after.is_none_or(|after| name.as_str() > after))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+const DEFAULT_BATCH_SIZE: i64 = 200;
+
+/// Postęp rotacji jednej kolumny, zapisywany po każdej partii
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnProgress {
+    pub column: String,
+    pub key_fingerprint: String,
+    pub last_key: Option<String>,
+    pub rotated: i64,
+    pub already_current: i64,
+    pub failed: i64,
+    pub completed: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RotationOutcome {
+    AlreadyCurrent,
+    Rotated(String),
+    Undecryptable,
+}
+
+/// Re-encrypts a single stored value under `current`
+pub fn rotate_value(current: &ContentCipher, previous: &ContentCipher, stored: &str) -> Result<RotationOutcome> {
+    if current.decrypt(stored).is_ok() {
+        return Ok(RotationOutcome::AlreadyCurrent);
+    }
+    match previous.decrypt(stored) {
+        Ok(plaintext) => Ok(RotationOutcome::Rotated(current.encrypt(&plaintext)?)),
+        Err(_) => Ok(RotationOutcome::Undecryptable),
+    }
+}
+
+/// Re-encrypts a file in place. A file with any value neither key can decrypt is left untouched.
+/// The new content is written next to it and renamed over it; if the file grew meanwhile
+/// (an archive export appended to it) the rotation of that file is retried.
+pub fn rotate_file(current: &ContentCipher, previous: &ContentCipher, path: &Path, layout: FileLayout) -> Result<RotationOutcome> {
+    const MAX_ATTEMPTS: usize = 3;
+
+    for _ in 0..MAX_ATTEMPTS {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let rotated = match layout {
+            FileLayout::Whole => {
+                let stored = String::from_utf8(bytes.clone()).with_context(|| format!("{} is not text", path.display()))?;
+                if !stored.starts_with(ENCRYPTED_PREFIX) {
+                    return Ok(RotationOutcome::AlreadyCurrent);
+                }
+                match rotate_value(current, previous, &stored)? {
+                    RotationOutcome::Rotated(encrypted) => encrypted.into_bytes(),
+                    outcome => return Ok(outcome),
+                }
+            }
+            FileLayout::GzipLines => {
+                let mut lines = Vec::new();
+                let mut changed = false;
+                for line in BufReader::new(MultiGzDecoder::new(bytes.as_slice())).lines() {
+                    let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+                    if !line.starts_with(ENCRYPTED_PREFIX) {
+                        lines.push(line);
+                        continue;
+                    }
+                    match rotate_value(current, previous, &line)? {
+                        RotationOutcome::AlreadyCurrent => lines.push(line),
+                        RotationOutcome::Rotated(encrypted) => {
+                            lines.push(encrypted);
+                            changed = true;
+                        }
+                        RotationOutcome::Undecryptable => return Ok(RotationOutcome::Undecryptable),
+                    }
+                }
+                if !changed {
+                    return Ok(RotationOutcome::AlreadyCurrent);
+                }
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                for line in &lines {
+                    encoder.write_all(line.as_bytes())?;
+                    encoder.write_all(b"\n")?;
+                }
+                encoder.finish()?
+            }
+        };
+
+        let staged = path.with_extension("rotating");
+        let mut file = std::fs::File::create(&staged).with_context(|| format!("Failed to write {}", staged.display()))?;
+        file.write_all(&rotated)?;
+        file.sync_all()?;
+        if std::fs::metadata(path)?.len() != bytes.len() as u64 {
+            std::fs::remove_file(&staged)?;
+            continue;
+        }
+        std::fs::rename(&staged, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+        return Ok(RotationOutcome::Rotated(path.display().to_string()));
+    }
+    bail!("{} kept changing during key rotation", path.display())
+}
+
+/// Re-encrypts encrypted columns and files from ENCRYPTION_KEY_PREVIOUS to ENCRYPTION_KEY in batches.
+/// Progress is stored per column (or directory), so an interrupted rotation resumes where it stopped.
+pub struct KeyRotator {
+    db_pool: PgPool,
+    files: Vec<EncryptedFiles>,
+    batch_size: i64,
+    running: AtomicBool,
+}
+
+impl KeyRotator {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            files: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// Dodaje katalogi plików zaszyfrowanych tym samym kluczem (paczki replay, archiwa)
+    pub fn with_files(mut self, files: Vec<EncryptedFiles>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Identyfikatory wszystkiego, co obejmuje rotacja
+    pub fn targets(&self) -> Vec<String> {
+        ENCRYPTED_COLUMNS.iter().map(EncryptedColumn::id).chain(self.files.iter().map(EncryptedFiles::id)).collect()
+    }
+
+    pub async fn initialize(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS key_rotation_progress (
+                column_id VARCHAR(255) PRIMARY KEY,
+                key_fingerprint VARCHAR(64) NOT NULL,
+                last_key TEXT,
+                rotated BIGINT NOT NULL DEFAULT 0,
+                already_current BIGINT NOT NULL DEFAULT 0,
+                failed BIGINT NOT NULL DEFAULT 0,
+                completed BOOLEAN NOT NULL DEFAULT FALSE,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create key rotation progress table")?;
+
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub async fn progress(&self) -> Result<Vec<ColumnProgress>> {
+        let rows = sqlx::query(
+            "SELECT column_id, key_fingerprint, last_key, rotated, already_current, failed, completed, updated_at
+             FROM key_rotation_progress ORDER BY column_id",
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to read key rotation progress")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ColumnProgress {
+                column: row.get("column_id"),
+                key_fingerprint: row.get("key_fingerprint"),
+                last_key: row.get("last_key"),
+                rotated: row.get("rotated"),
+                already_current: row.get("already_current"),
+                failed: row.get("failed"),
+                completed: row.get("completed"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect())
+    }
+
+    /// Rotuje wszystkie kolumny i pliki; zwraca błąd, gdy inna rotacja już trwa
+    pub async fn run(&self, current: &'static ContentCipher, previous: &'static ContentCipher) -> Result<Vec<ColumnProgress>> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            bail!("Key rotation is already running");
+        }
+
+        let result = async {
+            for column in ENCRYPTED_COLUMNS {
+                self.rotate_column(column, current, previous).await?;
+            }
+            for files in &self.files {
+                self.rotate_files(files, current, previous).await?;
+            }
+            self.progress().await
+        }
+        .await;
+
+        self.running.store(false, Ordering::SeqCst);
+        result
+    }
+
+    async fn rotate_files(&self, files: &EncryptedFiles, current: &'static ContentCipher, previous: &'static ContentCipher) -> Result<()> {
+        let files_id = files.id();
+        let mut progress = self.load_or_reset(&files_id, current.fingerprint()).await?;
+        if progress.completed {
+            info!("Key rotation of {} already completed for key {}", files_id, current.fingerprint());
+            return Ok(());
+        }
+
+        info!("Rotating {} in {} to key {} (resuming after {:?})", files_id, files.dir.display(), current.fingerprint(), progress.last_key);
+
+        for name in files.names_after(progress.last_key.as_deref())? {
+            let path = files.dir.join(&name);
+            let layout = files.layout;
+            match tokio::task::spawn_blocking(move || rotate_file(current, previous, &path, layout)).await?? {
+                RotationOutcome::AlreadyCurrent => progress.already_current += 1,
+                RotationOutcome::Rotated(_) => progress.rotated += 1,
+                RotationOutcome::Undecryptable => {
+                    warn!("{} file {} cannot be decrypted with the current or previous key", files_id, name);
+                    progress.failed += 1;
+                }
+            }
+            progress.last_key = Some(name);
+            self.save(&progress).await?;
+        }
+
+        progress.completed = true;
+        self.save(&progress).await?;
+        info!(
+            "Key rotation {}: {} rotated, {} already current, {} failed",
+            files_id, progress.rotated, progress.already_current, progress.failed
+        );
+        Ok(())
+    }
+
+    async fn rotate_column(&self, column: &EncryptedColumn, current: &ContentCipher, previous: &ContentCipher) -> Result<()> {
+        let column_id = column.id();
+        let mut progress = self.load_or_reset(&column_id, current.fingerprint()).await?;
+        if progress.completed {
+            info!("Key rotation of {} already completed for key {}", column_id, current.fingerprint());
+            return Ok(());
+        }
+
+        info!("Rotating {} to key {} (resuming after {:?})", column_id, current.fingerprint(), progress.last_key);
+
+        loop {
+            let rows = sqlx::query(&format!(
+                "SELECT {key} AS row_key, {column} AS value FROM {table}
+                 WHERE {column} LIKE $1 AND ($2::text IS NULL OR {key} > $2)
+                 ORDER BY {key} LIMIT $3",
+                key = column.key_expr,
+                column = column.column,
+                table = column.table,
+            ))
+            .bind(format!("{}%", ENCRYPTED_PREFIX))
+            .bind(&progress.last_key)
+            .bind(self.batch_size)
+            .fetch_all(&self.db_pool)
+            .await
+            .with_context(|| format!("Failed to read batch from {}", column_id))?;
+
+            for row in &rows {
+                let row_key: String = row.get("row_key");
+                let value: String = row.get("value");
+
+                match rotate_value(current, previous, &value)? {
+                    RotationOutcome::AlreadyCurrent => progress.already_current += 1,
+                    RotationOutcome::Rotated(encrypted) => {
+                        // Warunek na starą wartość - nie nadpisujemy równoległego zapisu aplikacji
+                        sqlx::query(&format!(
+                            "UPDATE {table} SET {column} = $1 WHERE {key} = $2 AND {column} = $3",
+                            key = column.key_expr,
+                            column = column.column,
+                            table = column.table,
+                        ))
+                        .bind(encrypted)
+                        .bind(&row_key)
+                        .bind(&value)
+                        .execute(&self.db_pool)
+                        .await
+                        .with_context(|| format!("Failed to update {} row {}", column_id, row_key))?;
+                        progress.rotated += 1;
+                    }
+                    RotationOutcome::Undecryptable => {
+                        warn!("{} row {} cannot be decrypted with the current or previous key", column_id, row_key);
+                        progress.failed += 1;
+                    }
+                }
+                progress.last_key = Some(row_key);
+            }
+
+            progress.completed = (rows.len() as i64) < self.batch_size;
+            self.save(&progress).await?;
+            info!(
+                "Key rotation {}: {} rotated, {} already current, {} failed",
+                column_id, progress.rotated, progress.already_current, progress.failed
+            );
+
+            if progress.completed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Postęp dla tego samego klucza docelowego jest wznawiany; nowy klucz zaczyna od początku
+    async fn load_or_reset(&self, column_id: &str, fingerprint: &str) -> Result<ColumnProgress> {
+        let existing = self.progress().await?.into_iter().find(|progress| progress.column == column_id);
+        match existing {
+            Some(progress) if progress.key_fingerprint == fingerprint => Ok(progress),
+            _ => {
+                let progress = ColumnProgress {
+                    column: column_id.to_string(),
+                    key_fingerprint: fingerprint.to_string(),
+                    last_key: None,
+                    rotated: 0,
+                    already_current: 0,
+                    failed: 0,
+                    completed: false,
+                    updated_at: Utc::now(),
+                };
+                self.save(&progress).await?;
+                Ok(progress)
+            }
+        }
+    }
+
+    async fn save(&self, progress: &ColumnProgress) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO key_rotation_progress (column_id, key_fingerprint, last_key, rotated, already_current, failed, completed, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+            ON CONFLICT (column_id) DO UPDATE SET
+                key_fingerprint = EXCLUDED.key_fingerprint,
+                last_key = EXCLUDED.last_key,
+                rotated = EXCLUDED.rotated,
+                already_current = EXCLUDED.already_current,
+                failed = EXCLUDED.failed,
+                completed = EXCLUDED.completed,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(&progress.column)
+        .bind(&progress.key_fingerprint)
+        .bind(&progress.last_key)
+        .bind(progress.rotated)
+        .bind(progress.already_current)
+        .bind(progress.failed)
+        .bind(progress.completed)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save key rotation progress")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_value() {
+        let previous = ContentCipher::from_secret("old-key").unwrap();
+        let current = ContentCipher::from_secret("new-key").unwrap();
+        assert_ne!(previous.fingerprint(), current.fingerprint());
+
+        let old_value = previous.encrypt("bw-session-token").unwrap();
+        let RotationOutcome::Rotated(rotated) = rotate_value(&current, &previous, &old_value).unwrap() else {
+            panic!("value encrypted with the previous key should be rotated");
+        };
+        assert_eq!(current.decrypt(&rotated).unwrap(), "bw-session-token");
+
+        // Ponowne uruchomienie jest bezpieczne - wartość jest już pod nowym kluczem
+        assert_eq!(rotate_value(&current, &previous, &rotated).unwrap(), RotationOutcome::AlreadyCurrent);
+
+        let foreign = ContentCipher::from_secret("unknown-key").unwrap().encrypt("x").unwrap();
+        assert_eq!(rotate_value(&current, &previous, &foreign).unwrap(), RotationOutcome::Undecryptable);
+    }
+
+    #[test]
+    fn test_rotate_file_layouts() {
+        let previous = ContentCipher::from_secret("old-key").unwrap();
+        let current = ContentCipher::from_secret("new-key").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let bundle = dir.path().join("bundle.json");
+        std::fs::write(&bundle, previous.encrypt("{\"id\":1}").unwrap()).unwrap();
+        assert!(matches!(rotate_file(&current, &previous, &bundle, FileLayout::Whole).unwrap(), RotationOutcome::Rotated(_)));
+        assert_eq!(current.decrypt(&std::fs::read_to_string(&bundle).unwrap()).unwrap(), "{\"id\":1}");
+        assert_eq!(rotate_file(&current, &previous, &bundle, FileLayout::Whole).unwrap(), RotationOutcome::AlreadyCurrent);
+
+        // Dwa człony gzip, jak po dwóch eksportach archiwum
+        let archive = dir.path().join("runs-2026-01.jsonl.gz");
+        let mut bytes = Vec::new();
+        for record in ["{\"id\":\"a\"}", "{\"id\":\"b\"}"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            writeln!(encoder, "{}", previous.encrypt(record).unwrap()).unwrap();
+            bytes.extend(encoder.finish().unwrap());
+        }
+        std::fs::write(&archive, bytes).unwrap();
+        assert!(matches!(rotate_file(&current, &previous, &archive, FileLayout::GzipLines).unwrap(), RotationOutcome::Rotated(_)));
+        let lines: Vec<String> = BufReader::new(MultiGzDecoder::new(std::fs::File::open(&archive).unwrap())).lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines.iter().map(|line| current.decrypt(line).unwrap()).collect::<Vec<_>>(), ["{\"id\":\"a\"}", "{\"id\":\"b\"}"]);
+        assert!(!archive.with_extension("rotating").exists());
+
+        let foreign = dir.path().join("foreign.json");
+        let stored = ContentCipher::from_secret("unknown-key").unwrap().encrypt("x").unwrap();
+        std::fs::write(&foreign, &stored).unwrap();
+        assert_eq!(rotate_file(&current, &previous, &foreign, FileLayout::Whole).unwrap(), RotationOutcome::Undecryptable);
+        assert_eq!(std::fs::read_to_string(&foreign).unwrap(), stored);
+    }
+}
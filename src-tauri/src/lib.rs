@@ -0,0 +1,40 @@
+//! Rdzeń automatyzacji Codialog jako biblioteka (`codialog_core`): analiza stron przez CDP,
+//! generowanie i walidacja skryptów DSL oraz ich wykonanie przez TagUI lub CDP.
+//! Nie zależy od axum ani Tauri - serwer `codialog` i inne aplikacje używają go przez [`Engine`].
+
+pub mod accessibility;
+pub mod cdp;
+pub mod crypto;
+pub mod debugger;
+pub mod dom;
+pub mod dsl;
+pub mod engine;
+pub mod executor;
+pub mod faults;
+pub mod few_shot;
+pub mod field_policies;
+pub mod formatting;
+pub mod generators;
+pub mod healing;
+pub mod llm;
+pub mod llm_provider;
+pub mod llm_usage;
+pub mod locale;
+pub mod logging;
+pub mod pacing;
+pub mod page_classifier;
+pub mod page_session;
+pub mod perf;
+pub mod privacy;
+pub mod prompts;
+pub mod redaction;
+pub mod repl;
+pub mod replay;
+pub mod secure_input;
+pub mod storage;
+pub mod tagui;
+pub mod tagui_path;
+pub mod throttle;
+pub mod trace;
+
+pub use engine::{Engine, PageAnalysis, PipelineOutcome, Validation};
@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Stan cyklu życia serwera: gotowość, drenowanie i żądanie zamknięcia
+pub struct Lifecycle {
+    ready: AtomicBool,
+    draining: AtomicBool,
+    shutdown_tx: watch::Sender<bool>,
+    drained_tx: watch::Sender<bool>,
+    deadline_armed: AtomicBool,
+    drain_timeout: Duration,
+}
+
+impl Lifecycle {
+    pub fn new(drain_timeout: Duration) -> Self {
+        let (shutdown_tx, _) = watch::channel(false);
+        let (drained_tx, _) = watch::channel(false);
+        Self {
+            ready: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
+            shutdown_tx,
+            drained_tx,
+            deadline_armed: AtomicBool::new(false),
+            drain_timeout,
+        }
+    }
+
+    /// Oznacza aplikację jako gotową (baza danych połączona, migracje zakończone)
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::SeqCst);
+        info!("Application marked as ready");
+    }
+
+    /// Ready only while initialized and not draining, so load balancers stop routing first
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst) && !self.is_draining()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Rozpoczyna drenowanie i powiadamia serwer HTTP o zamknięciu
+    pub fn begin_shutdown(&self, reason: &str) {
+        if self.draining.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        info!(reason = reason, drain_timeout_secs = self.drain_timeout.as_secs(), "Shutdown requested, draining");
+        self.shutdown_tx.send_replace(true);
+    }
+
+    /// Serwer i workery zakończyły pracę; odwołuje twardy termin drenowania
+    pub fn mark_drained(&self) {
+        self.drained_tx.send_replace(true);
+        info!("Drain completed");
+    }
+
+    /// Resolves once shutdown was requested; afterwards arms (once) a hard deadline
+    /// that forces an exit unless `mark_drained` is called first
+    pub async fn wait_for_shutdown(&self) {
+        let mut rx = self.shutdown_tx.subscribe();
+        while !*rx.borrow_and_update() {
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+
+        if self.deadline_armed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let drain_timeout = self.drain_timeout;
+        let mut drained = self.drained_tx.subscribe();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = drained.wait_for(|drained| *drained) => {}
+                _ = tokio::time::sleep(drain_timeout) => {
+                    warn!("Drain timeout of {}s exceeded, forcing exit", drain_timeout.as_secs());
+                    std::process::exit(0);
+                }
+            }
+        });
+    }
+
+    /// Nasłuchuje SIGTERM / Ctrl+C i inicjuje łagodne zamknięcie
+    pub async fn listen_for_signals(&self) {
+        let ctrl_c = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                warn!("Failed to listen for Ctrl+C: {}", e);
+                std::future::pending::<()>().await;
+            }
+        };
+
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(e) => {
+                    warn!("Failed to install SIGTERM handler: {}", e);
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => self.begin_shutdown("ctrl_c"),
+            _ = terminate => self.begin_shutdown("sigterm"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readiness_drops_when_draining() {
+        let lifecycle = Lifecycle::new(Duration::from_secs(60));
+        assert!(!lifecycle.is_ready());
+
+        lifecycle.mark_ready();
+        assert!(lifecycle.is_ready());
+
+        lifecycle.begin_shutdown("test");
+        assert!(lifecycle.is_draining());
+        assert!(!lifecycle.is_ready());
+    }
+}
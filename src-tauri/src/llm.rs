@@ -1,10 +1,23 @@
-use serde_json::Value;
-use reqwest;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{self, UnboundedSender};
 use tracing::{info, error, debug, warn};
-use crate::tagui::escape_for_dsl;
+use crate::tagui::{self, escape_for_dsl};
+use crate::replay;
+use crate::{cdp, crypto, dom, few_shot, generators, privacy, redaction};
+use crate::prompts::{self, PromptSelection};
+use crate::faults::{self, FaultTarget};
+use crate::perf::{self, OperationKind};
+use crate::dsl::{failures, generated, history, lint};
+use crate::{llm_provider, llm_usage};
+use crate::locale::{self, Button, LocaleHint};
+use crate::formatting::{self, FieldFormat};
 use sqlx::{PgPool, Row};
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use codialog_types::automation::{FieldMapping, GenerationInfo, GenerationStrategy, MatchSource};
 
 // ---- Lightweight shims expected by tests ----
 #[derive(Debug, Default, Clone)]
@@ -22,6 +35,20 @@ pub struct LLMResponse {
 #[derive(Debug)]
 pub enum LLMError {
     Generic(String),
+    /// Brak klucza API / dostawcy - polecenia tekstowego nie da się przetłumaczyć bez modelu
+    NotConfigured,
+    /// LLM wyłączony w konfiguracji (LLM_ENABLED=false)
+    Disabled,
+}
+
+impl std::fmt::Display for LLMError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LLMError::Generic(message) => write!(f, "{}", message),
+            LLMError::NotConfigured => write!(f, "No LLM provider is configured"),
+            LLMError::Disabled => write!(f, "LLM is disabled in this deployment"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,8 +62,169 @@ pub struct FormField { pub name: String, pub field_type: FieldType }
 
 pub fn analyze_form_structure(_html: &str) -> FormAnalysis { FormAnalysis {} }
 
-pub fn process_natural_language_query(_q: &str) -> std::result::Result<String, LLMError> {
-    Ok(String::new())
+/// Ile pól strony opisuje polecenie dla `/dsl/from-text`
+const MAX_PROMPT_FIELDS: usize = 60;
+
+/// Skrypt z polecenia tekstowego; koszt wywołania modelu rozlicza `llm_usage::record`
+#[derive(Debug, Clone, Serialize)]
+pub struct InstructionScript {
+    pub script: String,
+}
+
+/// Skrypt DSL z polecenia w języku naturalnym, np. "zaloguj się do LinkedIn i aplikuj na tę ofertę
+/// z moim zapisanym CV". Model widzi pola przeanalizowanej strony i tylko nazwy danych sesji -
+/// wartości trafiają do skryptu jako `{{klucz}}` i są podstawiane dopiero przy uruchomieniu.
+pub async fn process_natural_language_query(
+    instruction: &str,
+    html: &str,
+    page_url: Option<&str>,
+    variables: &[String],
+) -> std::result::Result<InstructionScript, LLMError> {
+    let instruction = instruction.trim();
+    if instruction.is_empty() {
+        return Err(LLMError::Generic("Instruction must not be empty".to_string()));
+    }
+    if !privacy::allows_llm() {
+        warn!("LLM is disabled, cannot translate instruction");
+        return Err(LLMError::Disabled);
+    }
+    let provider = llm_provider::current();
+    if !provider.is_configured() {
+        warn!("No API key for LLM provider {}, cannot translate instruction", provider.kind().as_str());
+        return Err(LLMError::NotConfigured);
+    }
+
+    info!(provider = provider.kind().as_str(), model = provider.model(), "Generating DSL from instruction");
+    let redacted = redaction::redact(html, &Value::Null);
+    let prompt = instruction_prompt(instruction, &redacted.html, page_url, variables);
+    let site = page_url.and_then(cdp::site_of);
+    let cache_key = create_cache_key(html, &json!({ "instruction": instruction, "variables": variables }), site.as_deref(), &LocaleHint::default());
+    let replay_key = format!("text:{}", cache_key);
+    let response_body: Value = replay::intercept(replay::InteractionKind::LlmResponse, &replay_key, || async {
+        faults::inject(FaultTarget::Llm).await?;
+        let request = provider.complete(&prompt, llm_provider::DEFAULT_MAX_TOKENS);
+        perf::timed(OperationKind::LlmCall, provider.operation(), json!({ "cache_key": cache_key, "instruction": true }), request).await
+    })
+    .await
+    .map_err(|e| LLMError::Generic(format!("LLM request failed: {}", e)))?;
+    let usage = provider.token_usage(&response_body);
+    llm_usage::record(provider.as_ref(), "instruction", usage, &prompt, provider.response_text(&response_body).unwrap_or_default());
+
+    let script = provider
+        .response_text(&response_body)
+        .map(|content| redacted.redaction.restore(&lint::insert_navigation_waits(&parse_dsl_from_response(content))))
+        .unwrap_or_default();
+    if script.trim().is_empty() {
+        return Err(LLMError::Generic("Model returned no DSL commands for the instruction".to_string()));
+    }
+    tagui::validate_dsl_script(&script).map_err(|e| LLMError::Generic(format!("Model returned an invalid script: {}", e)))?;
+
+    info!("Generated DSL from instruction, {} lines", script.lines().count());
+    Ok(InstructionScript { script })
+}
+
+/// Nazwy `{{zmiennych}}` użytych w skrypcie, w kolejności pierwszego wystąpienia
+pub fn script_variables(script: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = script;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else { break };
+        let name = rest[start + 2..start + 2 + length].trim().to_string();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &rest[start + 2 + length + 2..];
+    }
+    names
+}
+
+/// Pola strony w zwięzłej postaci: selektor, rodzaj, etykieta, opcje, ograniczenia, krok i ramka
+pub(crate) fn page_summary(html: &str) -> String {
+    let fields = dom::group_radios(dom::form_fields(html));
+    if fields.is_empty() {
+        return "(brak pól formularza)".to_string();
+    }
+    fields
+        .iter()
+        .take(MAX_PROMPT_FIELDS)
+        .map(|field| {
+            let kind = field.element_type.as_deref().map(|kind| format!("{}[{}]", field.tag, kind)).unwrap_or_else(|| field.tag.clone());
+            let mut line = format!("- {} {}", field.selector(), kind);
+            if let Some(label) = field.label.as_deref().or(field.text.as_deref()) {
+                line.push_str(&format!(" \"{}\"", label));
+            }
+            if !field.options.is_empty() {
+                let options: Vec<String> = field
+                    .options
+                    .iter()
+                    .map(|option| {
+                        if option.value == option.text || option.text.is_empty() {
+                            option.value.clone()
+                        } else {
+                            format!("{} (\"{}\")", option.value, option.text)
+                        }
+                    })
+                    .collect();
+                line.push_str(&format!(" opcje: {}", options.join(" | ")));
+            }
+            if field.required {
+                line.push_str(" wymagane");
+            }
+            if let Some(placeholder) = field.placeholder.as_deref().filter(|placeholder| field.label.as_deref() != Some(*placeholder)) {
+                line.push_str(&format!(" placeholder \"{}\"", placeholder));
+            }
+            if let Some(max_length) = field.max_length {
+                line.push_str(&format!(" maxlength {}", max_length));
+            }
+            if let Some(pattern) = &field.pattern {
+                line.push_str(&format!(" pattern /{}/", pattern));
+            }
+            if let Some(step) = field.step {
+                line.push_str(&format!(" krok {}", step + 1));
+            }
+            if let Some(frame) = &field.frame {
+                line.push_str(&format!(" ramka \"{}\"", frame));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Polecenie dla modelu przy `/dsl/from-text`
+fn instruction_prompt(instruction: &str, html: &str, page_url: Option<&str>, variables: &[String]) -> String {
+    let variables = if variables.is_empty() {
+        "(brak)".to_string()
+    } else {
+        variables.iter().map(|name| format!("{{{{{}}}}}", name)).collect::<Vec<_>>().join(", ")
+    };
+    format!(
+        "Przetłumacz polecenie użytkownika na skrypt DSL automatyzacji przeglądarki.\n\
+        Dostępne komendy: click, type, upload, hover, wait, waitfor <selektor> timeout <sekundy>, select <selektor> <opcja>, check/uncheck <selektor>, press <klawisz>, scroll <selektor|up|down|top|bottom>\n\
+        Bloki: if present <selektor> ... end, repeat <N> ... end, for each <xpath> ... end (@item = bieżący element)\n\
+        Ramki: pola oznaczone ramką leżą w iframe - obsłuż je w bloku frame \"X\" ... end\n\
+        \n\
+        Zasady:\n\
+        1. Używaj selektorów CSS pól z listy poniżej; dla innych stron użyj selektorów po widocznym tekście lub atrybutach\n\
+        2. Dane użytkownika wstawiaj WYŁĄCZNIE jako zmienne z listy, np. type \"#email\" \"{{{{email}}}}\", upload \"#cv\" \"{{{{cv_path}}}}\" - nigdy nie wpisuj wartości wprost\n\
+        3. Hasła i dane, których nie ma na liście, pomiń - nie zgaduj ich\n\
+        4. Po kliknięciu, które ładuje nową stronę, użyj waitfor na pierwszy element tej strony zamiast wait <sekundy>\n\
+        5. Skrypt startuje na bieżącej stronie (DSL nie ma komendy przejścia pod adres); wykonaj tylko to, o co prosi polecenie\n\
+        6. Zwróć TYLKO komendy DSL, bez komentarzy\n\
+        \n\
+        Polecenie: {}\n\
+        \n\
+        Bieżąca strona: {}\n\
+        Pola strony:\n{}\n\
+        \n\
+        Dostępne zmienne: {}\n\
+        \n\
+        Wygeneruj sekwencję komend DSL:",
+        instruction,
+        page_url.filter(|url| !url.is_empty()).unwrap_or("(nieznana)"),
+        page_summary(html),
+        variables
+    )
 }
 
 pub async fn get_llm_response(_req: &LLMRequest) -> std::result::Result<LLMResponse, LLMError> {
@@ -60,25 +248,101 @@ pub async fn generate_dsl_script(html: &str, user_data: &Value) -> String {
     generate_dsl_script_with_cache(html, user_data, None).await
 }
 
-pub(crate) fn generate_basic_fallback_script(_html: &str, _user_data: &Value) -> String {
-    "// Basic fallback\nwait 3\nclick \"Continue\" if present\nwait 2\n".to_string()
+pub(crate) fn generate_basic_fallback_script(html: &str, _user_data: &Value) -> String {
+    let page_lang = locale::page_language(html);
+    format!("// Basic fallback\nwait 3\n{}wait 2\n", locale::click_if_present(Button::Continue, page_lang.as_deref()))
 }
 
 pub(crate) fn generate_emergency_fallback_script(_html: &str, _user_data: &Value) -> String {
     "wait 5\n// Emergency fallback - manual intervention may be required\n".to_string()
 }
 
+/// Czy skrypt przechodzi `dsl::generated::check` (gramatyka, selektory, zakazane komendy) i może trafić do cache
 pub(crate) fn validate_generated_script(script: &str) -> bool {
-    !script.trim().is_empty() && script.len() > 5
+    match generated::check(script) {
+        Ok(_) => true,
+        Err(e) => {
+            debug!("Generated script rejected: {}", e);
+            false
+        }
+    }
 }
 
 pub async fn generate_dsl_script_with_cache(html: &str, user_data: &Value, db_pool: Option<&PgPool>) -> String {
+    generate_dsl_with_strategies(html, user_data, &PromptSelection::default(), None, db_pool).await.script
+}
+
+/// Skrypt z `/dsl/generate` razem z informacją, która strategia go wygenerowała
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedScript {
+    pub script: String,
+    pub generation: GenerationInfo,
+}
+
+static FALLBACK_CHAIN: RwLock<Option<Vec<GenerationStrategy>>> = RwLock::new(None);
+
+/// Łańcuch strategii z konfiguracji (GENERATION_FALLBACKS); wywoływane raz przy starcie
+pub fn set_fallback_chain(chain: &[GenerationStrategy]) {
+    info!(chain = ?chain.iter().map(GenerationStrategy::as_str).collect::<Vec<_>>(), "DSL generation fallback chain configured");
+    *FALLBACK_CHAIN.write().unwrap() = Some(chain.to_vec());
+}
+
+/// Łańcuch dla żądania: podany w żądaniu albo z konfiguracji, bez powtórzeń i bez LLM, gdy jest wyłączony (LLM_ENABLED)
+pub fn fallback_chain(requested: Option<&[GenerationStrategy]>) -> Vec<GenerationStrategy> {
+    let configured = FALLBACK_CHAIN.read().unwrap().clone().unwrap_or_else(|| GenerationStrategy::DEFAULT_CHAIN.to_vec());
+    resolve_chain(requested, &configured, privacy::allows_llm())
+}
+
+/// Odrzuca żądanie, którego łańcuch po pominięciu niedostępnych strategii jest pusty (np. tylko `llm`
+/// przy LLM_ENABLED=false) - zamiast po cichu zwracać skrypt awaryjny
+pub fn check_chain(requested: Option<&[GenerationStrategy]>) -> Result<()> {
+    if fallback_chain(requested).is_empty() {
+        return Err(anyhow!("None of the requested generation strategies is available (LLM is disabled by LLM_ENABLED=false)"));
+    }
+    Ok(())
+}
+
+fn resolve_chain(requested: Option<&[GenerationStrategy]>, configured: &[GenerationStrategy], llm_allowed: bool) -> Vec<GenerationStrategy> {
+    let source = requested.filter(|requested| !requested.is_empty()).unwrap_or(configured);
+    let mut chain = Vec::new();
+    for &strategy in source {
+        if (strategy != GenerationStrategy::Llm || llm_allowed) && !chain.contains(&strategy) {
+            chain.push(strategy);
+        }
+    }
+    chain
+}
+
+/// Generowanie łańcuchem strategii (`requested` albo GENERATION_FALLBACKS) z cache w `dsl_cache`.
+/// Skrypt z cache jest używany tylko, gdy wygenerowała go strategia z łańcucha; do cache trafiają
+/// wyłącznie skrypty z łańcucha konfiguracji, żeby zawężone żądanie nie zastąpiło lepszego wyniku.
+/// Żądanie z własnym modelem lub parametrami (`selection.llm`) omija cache w obie strony.
+pub async fn generate_dsl_with_strategies(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    requested: Option<&[GenerationStrategy]>,
+    db_pool: Option<&PgPool>,
+) -> GeneratedScript {
+    let cache_results = requested.is_none_or(|requested| requested.is_empty()) && selection.llm.is_empty();
+    generate_with_chain(html, user_data, selection, fallback_chain(requested), cache_results, db_pool).await
+}
+
+async fn generate_with_chain(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    chain: Vec<GenerationStrategy>,
+    cache_results: bool,
+    db_pool: Option<&PgPool>,
+) -> GeneratedScript {
     info!("Generating DSL script from HTML and user data");
     
     // Input validation with error recovery
     if html.trim().is_empty() {
         warn!("Empty HTML provided, generating basic navigation script");
-        return basic_navigation_script();
+        let generation = GenerationInfo { strategy: Some(GenerationStrategy::Basic), cached: false, stale: false, chain, cache_key: None, blocker: None };
+        return GeneratedScript { script: basic_navigation_script(selection.locale.effective()), generation };
     }
     
     // Validate user data structure
@@ -87,41 +351,87 @@ pub async fn generate_dsl_script_with_cache(html: &str, user_data: &Value, db_po
     }
     
     // Create cache key
-    let cache_key = create_cache_key(html, user_data);
+    let cache_key = create_cache_key(html, user_data, selection.site.as_deref(), &selection.locale);
     
     // Try to get cached script first with retry logic
-    if let Some(pool) = db_pool {
-        match get_cached_dsl_script_with_retry(pool, &cache_key, 3).await {
-            Ok(Some(cached_script)) => {
-                info!("Using cached DSL script for key: {}", cache_key);
-                return cached_script;
-            }
-            Ok(None) => debug!("No cached script found for key: {}", cache_key),
-            Err(e) => warn!("Cache retrieval failed: {}", e),
-        }
+    if let Some(cached) = cached_script(html, user_data, selection, &chain, &cache_key, db_pool).await {
+        return cached;
     }
     
-    // Generate new script with comprehensive fallback strategy
-    let script = match generate_script_with_comprehensive_fallbacks(html, user_data).await {
-        Ok(generated_script) => {
-            if generated_script.trim().is_empty() {
-                warn!("Generated script is empty, using basic fallback");
-                generate_basic_fallback_script(html, user_data)
+    let (strategy, script) = generate_fresh(html, user_data, selection, &chain, &cache_key, cache_results, db_pool).await;
+    let blocker = known_failure(&cache_key, selection).filter(|_| chain.contains(&GenerationStrategy::Llm));
+    GeneratedScript { script, generation: GenerationInfo { strategy, cached: false, stale: false, chain, cache_key: Some(cache_key), blocker } }
+}
+
+/// Skrypt z cache dla klucza, gdy pochodzi ze strategii łańcucha; wygasły tylko przy tej samej strukturze
+/// strony, odświeżany wtedy w tle. Żądania z własnymi opcjami modelu nie czytają cache.
+async fn cached_script(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    chain: &[GenerationStrategy],
+    cache_key: &str,
+    db_pool: Option<&PgPool>,
+) -> Option<GeneratedScript> {
+    let pool = db_pool.filter(|_| selection.llm.is_empty())?;
+    let cached = replay::intercept(replay::InteractionKind::DslCache, cache_key, || {
+        get_cached_dsl_script_with_retry(pool, cache_key, 3)
+    })
+    .await;
+    match cached {
+        Ok(Some(cached)) if !cached.strategy.is_none_or(|strategy| chain.contains(&strategy)) => {
+            debug!("Cached script for key {} comes from a strategy outside the chain", cache_key)
+        }
+        Ok(Some(cached)) if cached.stale && cached.structure_hash.as_deref() != Some(dom::structure_fingerprint(html).as_str()) => {
+            debug!("Expired cached script for key {} no longer matches the page structure", cache_key)
+        }
+        Ok(Some(cached)) => {
+            if cached.stale {
+                info!("Serving expired DSL script for key {} while it is regenerated", cache_key);
+                refresh_in_background(html, user_data, selection, cache_key, pool);
             } else {
-                generated_script
+                info!("Using cached DSL script for key: {}", cache_key);
             }
+            let generation =
+                GenerationInfo { strategy: cached.strategy, cached: true, stale: cached.stale, chain: chain.to_vec(), cache_key: Some(cache_key.to_string()), blocker: None };
+            return Some(GeneratedScript { script: cached.script, generation });
         }
+        Ok(None) => debug!("No cached script found for key: {}", cache_key),
+        Err(e) => warn!("Cache retrieval failed: {}", e),
+    }
+    None
+}
+
+/// Nowy skrypt łańcuchem strategii, zapisany w cache, gdy `cache_results`
+async fn generate_fresh(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    chain: &[GenerationStrategy],
+    cache_key: &str,
+    cache_results: bool,
+    db_pool: Option<&PgPool>,
+) -> (Option<GenerationStrategy>, String) {
+    // Generate new script with the configured fallback chain
+    let (strategy, script) = match generate_script_with_comprehensive_fallbacks(html, user_data, selection, chain, db_pool).await {
+        Ok((strategy, script)) => (Some(strategy), script),
         Err(e) => {
             error!("All DSL generation methods failed: {}, using emergency fallback", e);
-            generate_emergency_fallback_script(html, user_data)
+            (None, generate_emergency_fallback_script(html, user_data))
         }
     };
     
-    // Validate generated script before caching
-    if validate_generated_script(&script) {
-        // Cache the generated script with retry logic
-        if let Some(pool) = db_pool {
-            match cache_dsl_script_with_retry(pool, &cache_key, &script, html, 3).await {
+    // Po nawigacji czekaj na element następnej strony zamiast stałego `wait N`
+    let script = lint::insert_navigation_waits(&script);
+    
+    // Validate generated script before caching; skrypt z dosłownymi wartościami trafiłby do innego
+    // użytkownika o tych samych kluczach danych, więc w cache lądują tylko skrypty z `{{zmiennymi}}`
+    if !few_shot::is_templated(&script) {
+        debug!("Generated script types literal values, not caching");
+    } else if validate_generated_script(&script) {
+        // Cache the generated script with retry logic (nie podczas odtwarzania paczki replay)
+        if let Some(pool) = db_pool.filter(|_| cache_results && !replay::is_replaying()) {
+            match cache_dsl_script_with_retry(pool, cache_key, &script, html, strategy, selection.site.as_deref(), 3).await {
                 Ok(_) => debug!("Successfully cached DSL script"),
                 Err(e) => warn!("Failed to cache DSL script after retries: {}", e),
             }
@@ -129,30 +439,71 @@ pub async fn generate_dsl_script_with_cache(html: &str, user_data: &Value, db_po
     } else {
         warn!("Generated script failed validation, not caching");
     }
-    
-    script
+    (strategy, script)
+}
+
+/// Klucze cache, dla których trwa odświeżanie w tle - jedno wywołanie modelu na klucz naraz
+static REFRESHING: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Zwalnia klucz w `REFRESHING` także wtedy, gdy zadanie odświeżania spanikuje
+struct RefreshingKey(String);
+
+impl RefreshingKey {
+    fn acquire(cache_key: &str) -> Option<Self> {
+        let acquired = REFRESHING.lock().unwrap().get_or_insert_with(HashSet::new).insert(cache_key.to_string());
+        acquired.then(|| Self(cache_key.to_string()))
+    }
+}
+
+impl Drop for RefreshingKey {
+    fn drop(&mut self) {
+        let mut refreshing = REFRESHING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        refreshing.get_or_insert_with(HashSet::new).remove(&self.0);
+    }
+}
+
+/// Generuje skrypt dla przeterminowanego wpisu w tle łańcuchem z konfiguracji i zapisuje go w cache
+fn refresh_in_background(html: &str, user_data: &Value, selection: &PromptSelection, cache_key: &str, pool: &PgPool) {
+    if replay::is_replaying() {
+        return;
+    }
+    let Some(refreshing) = RefreshingKey::acquire(cache_key) else {
+        return;
+    };
+    let (html, user_data, selection, pool) = (html.to_string(), user_data.clone(), selection.clone(), pool.clone());
+    llm_usage::spawn(async move {
+        let chain = fallback_chain(None);
+        let (strategy, _) = generate_fresh(&html, &user_data, &selection, &chain, &refreshing.0, true, Some(&pool)).await;
+        info!(cache_key = %refreshing.0, strategy = strategy.map(|strategy| strategy.as_str()), "Expired DSL cache entry refreshed in background");
+    });
 }
 
-pub(crate) fn create_cache_key(html: &str, user_data: &Value) -> String {
+/// Niedawna porażka modelu dla klucza (`dsl::failures`); żądanie z własnym modelem lub parametrami próbuje od nowa
+fn known_failure(cache_key: &str, selection: &PromptSelection) -> Option<String> {
+    failures::known(cache_key).filter(|_| selection.llm.is_empty())
+}
+
+fn remember_failure(cache_key: &str, selection: &PromptSelection, reason: &str) {
+    if selection.llm.is_empty() && !replay::is_replaying() {
+        warn!(cache_key = %cache_key, "LLM could not produce a valid script, remembering the failure: {}", reason);
+        failures::remember(cache_key, reason);
+    }
+}
+
+/// Klucz cache DSL: witryna strony, szkielet formularzy (`dom::structure_signature`) i nazwy kluczy danych
+/// użytkownika. Nie zależy od wartości pól, tekstu, klas ani generowanych id, więc zmiany kosmetyczne strony
+/// trafiają w cache, a ten sam formularz na innej witrynie - nie.
+pub(crate) fn create_cache_key(html: &str, user_data: &Value, site: Option<&str>, locale: &LocaleHint) -> String {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     
     let mut hasher = DefaultHasher::new();
+    site.unwrap_or_default().hash(&mut hasher);
+    dom::structure_signature(html).hash(&mut hasher);
     
-    // Create simplified HTML signature (remove dynamic content)
-    let html_signature = html
-        .lines()
-        .filter(|line| {
-            let trimmed = line.trim();
-            trimmed.contains("<input") || trimmed.contains("<button") || 
-            trimmed.contains("<form") || trimmed.contains("<select") ||
-            trimmed.contains("type=") || trimmed.contains("id=") ||
-            trimmed.contains("name=") || trimmed.contains("class=")
-        })
-        .collect::<Vec<_>>()
-        .join("");
-    
-    html_signature.hash(&mut hasher);
+    // Język strony i locale użytkownika zmieniają prompt (język tekstów, formaty dat i telefonów)
+    let locale = locale.clone().with_page(html);
+    (locale.page_lang, locale.user_locale).hash(&mut hasher);
     
     // Hash user data structure (not values for privacy)
     let user_keys: Vec<String> = user_data.as_object()
@@ -160,19 +511,70 @@ pub(crate) fn create_cache_key(html: &str, user_data: &Value) -> String {
         .unwrap_or_default();
     user_keys.join(",").hash(&mut hasher);
     
+    // Odpowiedzi z polityk pól trafiają do skryptu (np. opcja `select`), więc inne polityki to inny wpis
+    for key in crate::field_policies::POLICY_KEYS {
+        if let Some(value) = user_data.get(*key).and_then(|value| value.as_str()) {
+            (*key, value).hash(&mut hasher);
+        }
+    }
+    
     format!("dsl_{:x}", hasher.finish())
 }
 
-async fn get_cached_dsl_script_with_retry(pool: &PgPool, cache_key: &str, retries: u32) -> Result<Option<String>> {
+/// Wpis `dsl_cache` ze strategią, która go wygenerowała (brak dla wpisów sprzed łańcucha strategii)
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+struct CachedScript {
+    script: String,
+    strategy: Option<GenerationStrategy>,
+    /// Wpis po terminie ważności, w oknie `set_stale_window`
+    #[serde(default)]
+    stale: bool,
+    /// `dom::structure_fingerprint` strony, dla której powstał skrypt
+    #[serde(default)]
+    structure_hash: Option<String>,
+}
+
+/// Jak długo po terminie ważności wpis cache może być podawany, gdy struktura strony się nie zmieniła
+pub const DEFAULT_STALE_WINDOW: Duration = Duration::from_secs(24 * 3600);
+
+static STALE_WINDOW: RwLock<Option<Duration>> = RwLock::new(None);
+
+/// Okno podawania przeterminowanych skryptów (DSL_CACHE_STALE_HOURS, zero wyłącza); wywoływane raz przy starcie
+pub fn set_stale_window(window: Duration) {
+    info!(hours = window.as_secs() / 3600, "DSL cache stale-while-revalidate window configured");
+    *STALE_WINDOW.write().unwrap() = Some(window);
+}
+
+fn stale_window() -> Duration {
+    STALE_WINDOW.read().unwrap().unwrap_or(DEFAULT_STALE_WINDOW)
+}
+
+async fn get_cached_dsl_script_with_retry(pool: &PgPool, cache_key: &str, retries: u32) -> Result<Option<CachedScript>> {
     for attempt in 0..retries {
-        match sqlx::query("SELECT script_content FROM dsl_cache WHERE cache_key = $1 AND expires_at > NOW()")
-            .bind(cache_key)
-            .fetch_optional(pool)
-            .await
-        {
+        let fetched = async {
+            faults::inject(FaultTarget::Db).await?;
+            let query = sqlx::query(
+                "SELECT script_content, strategy, structure_hash, NOT (expires_at > NOW() OR pinned) AS stale
+                 FROM dsl_cache
+                 WHERE cache_key = $1 AND (expires_at > NOW() - make_interval(secs => $2) OR pinned)"
+            )
+                .bind(cache_key)
+                .bind(stale_window().as_secs_f64())
+                .fetch_optional(pool);
+            Ok::<_, anyhow::Error>(perf::timed(OperationKind::DbQuery, "dsl_cache.get", json!({ "cache_key": cache_key }), query).await?)
+        }
+        .await;
+        
+        match fetched {
             Ok(Some(row)) => {
                 let script: String = row.try_get("script_content")?;
-                return Ok(Some(script));
+                let strategy: Option<String> = row.try_get("strategy")?;
+                return Ok(Some(CachedScript {
+                    script,
+                    strategy: strategy.as_deref().and_then(GenerationStrategy::parse),
+                    stale: row.try_get("stale")?,
+                    structure_hash: row.try_get("structure_hash")?,
+                }));
             }
             Ok(None) => return Ok(None),
             Err(e) if attempt < retries - 1 => {
@@ -180,33 +582,73 @@ async fn get_cached_dsl_script_with_retry(pool: &PgPool, cache_key: &str, retrie
                 tokio::time::sleep(tokio::time::Duration::from_millis(100 * (attempt + 1) as u64)).await;
                 continue;
             }
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(e),
         }
     }
     Ok(None)
 }
 
-async fn generate_script_with_comprehensive_fallbacks(html: &str, user_data: &Value) -> Result<String> {
-    // First try: Enhanced form analysis
-    if let Ok(script) = generate_enhanced_form_script(html, user_data).await {
-        if !script.trim().is_empty() {
-            return Ok(script);
+/// Strategie po kolei, aż któraś da niepusty skrypt
+async fn generate_script_with_comprehensive_fallbacks(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    chain: &[GenerationStrategy],
+    db_pool: Option<&PgPool>,
+) -> Result<(GenerationStrategy, String)> {
+    for &strategy in chain {
+        match run_strategy(strategy, html, user_data, selection, db_pool).await {
+            Ok(script) if !script.trim().is_empty() => {
+                info!(strategy = strategy.as_str(), "DSL script generated");
+                return Ok((strategy, script));
+            }
+            Ok(_) => debug!(strategy = strategy.as_str(), "Generation strategy produced no script, trying the next one"),
+            Err(e) => warn!(strategy = strategy.as_str(), "Generation strategy failed: {}", e),
         }
     }
-    
-    // Second try: Simple form parsing
-    if let Ok(script) = generate_simple_form_script(html, user_data).await {
-        if !script.trim().is_empty() {
-            return Ok(script);
+    Err(anyhow!("no strategy in the chain produced a script"))
+}
+
+async fn run_strategy(
+    strategy: GenerationStrategy,
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    db_pool: Option<&PgPool>,
+) -> Result<String> {
+    match strategy {
+        GenerationStrategy::Platform => {
+            Ok(generators::platforms::generate(html, selection.site.as_deref(), user_data).unwrap_or_default())
+        }
+        GenerationStrategy::Llm => {
+            let cache_key = create_cache_key(html, user_data, selection.site.as_deref(), &selection.locale);
+            if let Some(reason) = known_failure(&cache_key, selection) {
+                debug!(cache_key = %cache_key, "Skipping LLM for a page it recently failed on: {}", reason);
+                return Ok(String::new());
+            }
+            let script = generate_dsl_with_llm(html, user_data, selection, db_pool).await.map_err(|e| anyhow!("{}", e))?;
+            if script.trim().is_empty() {
+                return Ok(script);
+            }
+            match generated::check(&script) {
+                Ok(_) => {
+                    failures::forget(&cache_key);
+                    Ok(script)
+                }
+                Err(e) => {
+                    remember_failure(&cache_key, selection, &e.to_string());
+                    Err(anyhow!("model returned an invalid script: {}", e))
+                }
+            }
         }
+        GenerationStrategy::Enhanced => generate_enhanced_form_script(html, user_data, selection.locale.user_locale.as_deref()).await,
+        GenerationStrategy::Simple => generate_simple_form_script(html, user_data).await,
+        GenerationStrategy::Basic => Ok(basic_navigation_script(selection.locale.clone().with_page(html).effective())),
     }
-    
-    // Final fallback
-    Ok(basic_navigation_script())
 }
 
-async fn generate_enhanced_form_script(html: &str, _user_data: &Value) -> Result<String> {
-    let analyzer = FormAnalyzer::new(html);
+async fn generate_enhanced_form_script(html: &str, user_data: &Value, user_locale: Option<&str>) -> Result<String> {
+    let analyzer = FormAnalyzer::new(html).with_user_locale(user_locale);
     let mut script = String::new();
     
     // Add basic navigation commands
@@ -222,52 +664,147 @@ async fn generate_enhanced_form_script(html: &str, _user_data: &Value) -> Result
         }
     }
     
+    let actions = if analyzer.is_multi_step() {
+        debug!(steps = analyzer.step_count(), "Multi-step form detected, generating staged script");
+        generate_staged_sequence(&analyzer, user_data)
+    } else {
+        let mut actions = generate_field_filling_sequence(&analyzer, user_data);
+        actions.extend(generate_select_sequence(&analyzer, user_data));
+        actions
+    };
+    for action in analyzer.scope_to_frames(actions) {
+        script.push_str(&action);
+        script.push('\n');
+    }
+    
     script.push_str("wait 1\n");
     Ok(script)
 }
 
-async fn generate_simple_form_script(_html: &str, _user_data: &Value) -> Result<String> {
-    Ok("wait 3\nclick \"Submit\" if present\nwait 2\n".to_string())
+async fn generate_simple_form_script(html: &str, _user_data: &Value) -> Result<String> {
+    let page_lang = locale::page_language(html);
+    Ok(format!("wait 3\n{}wait 2\n", locale::click_if_present(Button::Submit, page_lang.as_deref())))
 }
 
-fn basic_navigation_script() -> String {
+/// Przyciski zgody i logowania po angielsku i w języku strony (`page_lang`)
+fn basic_navigation_script(page_lang: Option<&str>) -> String {
     debug!("Generating basic navigation script as fallback");
     
     // Basic navigation script for common scenarios
-    let script = r#"
-// Basic navigation script
-wait 3
-click "Accept" if present
-click "Login" if present
-wait 2
-"#;
+    format!(
+        "// Basic navigation script\nwait 3\n{}{}wait 2",
+        locale::click_if_present(Button::Accept, page_lang),
+        locale::click_if_present(Button::Login, page_lang)
+    )
+}
+
+/// HTML zapisywany w `dsl_cache.html_content`: tylko struktura, zaszyfrowana.
+/// Bez skonfigurowanego klucza strona nie jest zapisywana wcale.
+pub(crate) fn stored_html_content(html: &str) -> Result<Option<String>> {
+    if !privacy::stores_page_html() {
+        return Ok(None);
+    }
+    crypto::content_cipher()
+        .map(|cipher| cipher.encrypt(&cdp::sanitize_html(html)))
+        .transpose()
+}
+
+/// Brings rows written before sanitization in line: raw HTML is sanitized and encrypted,
+/// or cleared when no key is configured, it was encrypted with a different key
+/// or page HTML persistence is disabled.
+pub async fn migrate_cached_html(pool: &PgPool) -> Result<u64> {
+    let rows = sqlx::query("SELECT cache_key, html_content FROM dsl_cache WHERE html_content IS NOT NULL")
+        .fetch_all(pool)
+        .await?;
+    
+    let cipher = crypto::content_cipher().filter(|_| privacy::stores_page_html());
+    let mut migrated = 0;
+    for row in rows {
+        let cache_key: String = row.try_get("cache_key")?;
+        let html: String = row.try_get("html_content")?;
+        
+        let replacement = match cipher {
+            Some(_) if html.starts_with(crypto::ENCRYPTED_PREFIX) => {
+                // Wartości pod poprzednim kluczem przepisze rotacja kluczy
+                if crypto::decrypt_any(&html).is_ok() {
+                    continue;
+                }
+                None
+            }
+            Some(_) => stored_html_content(&html)?,
+            None => None,
+        };
+        
+        sqlx::query("UPDATE dsl_cache SET html_content = $2 WHERE cache_key = $1")
+            .bind(&cache_key)
+            .bind(replacement)
+            .execute(pool)
+            .await?;
+        migrated += 1;
+    }
     
-    script.trim().to_string()
+    if migrated > 0 {
+        info!("Sanitized stored HTML of {} cached DSL scripts", migrated);
+    }
+    Ok(migrated)
 }
 
-async fn cache_dsl_script_with_retry(pool: &PgPool, cache_key: &str, script: &str, html: &str, retries: u32) -> Result<()> {
+async fn cache_dsl_script_with_retry(
+    pool: &PgPool,
+    cache_key: &str,
+    script: &str,
+    html: &str,
+    strategy: Option<GenerationStrategy>,
+    site: Option<&str>,
+    retries: u32,
+) -> Result<()> {
+    let html_content = stored_html_content(html)?;
+    let structure_hash = dom::structure_fingerprint(html);
+    let form_fields = few_shot::form_fields(html);
+    
     for attempt in 0..retries {
-        match sqlx::query(
-            "INSERT INTO dsl_cache (cache_key, script_content, html_content, expires_at) 
-             VALUES ($1, $2, $3, NOW() + INTERVAL '1 hour')
-             ON CONFLICT (cache_key) DO UPDATE SET 
-             script_content = EXCLUDED.script_content,
-             html_content = EXCLUDED.html_content,
-             expires_at = EXCLUDED.expires_at"
-        )
-        .bind(cache_key)
-        .bind(script)
-        .bind(html)
-        .execute(pool)
-        .await
-        {
-            Ok(_) => return Ok(()),
+        let stored = async {
+            faults::inject(FaultTarget::Db).await?;
+            let query = sqlx::query(
+                "INSERT INTO dsl_cache (cache_key, script_content, html_content, strategy, site, structure_hash, form_fields, expires_at) 
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + INTERVAL '1 hour')
+                 ON CONFLICT (cache_key) DO UPDATE SET 
+                 script_content = EXCLUDED.script_content,
+                 html_content = EXCLUDED.html_content,
+                 strategy = EXCLUDED.strategy,
+                 site = COALESCE(EXCLUDED.site, dsl_cache.site),
+                 structure_hash = EXCLUDED.structure_hash,
+                 form_fields = EXCLUDED.form_fields,
+                 expires_at = EXCLUDED.expires_at
+                 WHERE NOT dsl_cache.pinned"
+            )
+            .bind(cache_key)
+            .bind(script)
+            .bind(&html_content)
+            .bind(strategy.map(|strategy| strategy.as_str()))
+            .bind(site)
+            .bind(&structure_hash)
+            .bind(&form_fields)
+            .execute(pool);
+            perf::timed(OperationKind::DbQuery, "dsl_cache.put", json!({ "cache_key": cache_key }), query).await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        
+        match stored {
+            Ok(_) => {
+                // Historia wersji jest dodatkiem - jej błąd nie psuje zapisu w cache
+                if let Err(e) = history::record(pool, cache_key, script, strategy).await {
+                    warn!("Failed to record DSL script version for {}: {}", cache_key, e);
+                }
+                return Ok(());
+            }
             Err(e) if attempt < retries - 1 => {
                 warn!("Cache storage attempt {} failed: {}", attempt + 1, e);
                 tokio::time::sleep(tokio::time::Duration::from_millis(100 * (attempt + 1) as u64)).await;
                 continue;
             }
-            Err(e) => return Err(e.into()),
+            Err(e) => return Err(e),
         }
     }
     Ok(())
@@ -276,6 +813,30 @@ async fn cache_dsl_script_with_retry(pool: &PgPool, cache_key: &str, script: &st
 pub(crate) struct FormAnalyzer {
     html: String,
     elements: HashMap<String, Vec<String>>,
+    /// Selektor -> ramka iframe (`cdp::FRAME_TAG`), w której leży element
+    frames: HashMap<String, String>,
+    /// Selektor -> tekst etykiety pola
+    labels: HashMap<String, String>,
+    /// Selektor -> numer pola w kolejności dokumentu (wszystkie selektory jednego pola mają ten sam)
+    field_ids: HashMap<String, usize>,
+    /// Krok kreatora i pierwszy selektor każdego pola, po numerze pola
+    places: Vec<FieldPlace>,
+    /// Pasek postępu lub stepper na stronie
+    has_progress: bool,
+    /// Locale użytkownika, według którego czytane są jego wartości (np. daty `MM/DD/YYYY`)
+    user_locale: Option<String>,
+}
+
+/// Położenie pola w formularzu wieloetapowym
+struct FieldPlace {
+    step: Option<usize>,
+    selector: Option<String>,
+    is_button: bool,
+    /// Opcje listy `<select>`
+    options: Vec<dom::SelectOption>,
+    /// Oczekiwany format wartości (data, telefon, kwota) i `maxlength`
+    format: Option<FieldFormat>,
+    max_length: Option<usize>,
 }
 
 impl FormAnalyzer {
@@ -283,115 +844,163 @@ impl FormAnalyzer {
         let mut analyzer = FormAnalyzer {
             html: html.to_string(),
             elements: HashMap::new(),
+            frames: HashMap::new(),
+            labels: HashMap::new(),
+            field_ids: HashMap::new(),
+            places: Vec::new(),
+            has_progress: dom::has_progress_indicator(html),
+            user_locale: None,
         };
         analyzer.analyze_elements();
         analyzer
     }
+
+    pub(crate) fn with_user_locale(mut self, user_locale: Option<&str>) -> Self {
+        self.user_locale = user_locale.map(str::to_string);
+        self
+    }
     
     fn analyze_elements(&mut self) {
-        // Parse HTML to find form elements (simplified parser)
-        let html_content = self.html.clone();
-        let lines: Vec<&str> = html_content.lines().collect();
-        
-        for line in lines {
-            if line.contains("<input") {
-                self.parse_input_element(line);
-            } else if line.contains("<button") || line.contains("<input") && line.contains("type=\"submit\"") {
-                self.parse_button_element(line);
-            } else if line.contains("<select") {
-                self.parse_select_element(line);
+        // Pola z drzewa DOM strony i jej ramek iframe
+        for (field_id, field) in dom::form_fields(&self.html).into_iter().enumerate() {
+            let element_type = match (field.tag.as_str(), field.element_type.as_deref()) {
+                ("button", _) | ("input", Some("button" | "reset")) => classify_button(field.text.as_deref(), "button"),
+                ("input", Some("submit")) => classify_button(field.text.as_deref(), "submit"),
+                ("input", Some(input_type)) => input_type.to_string(),
+                (tag, _) => tag.to_string(),
+            };
+            let selectors = field.selectors();
+            for selector in &selectors {
+                self.field_ids.insert(selector.clone(), field_id);
+            }
+            self.places.push(FieldPlace {
+                step: field.step,
+                selector: selectors.first().cloned(),
+                is_button: field.tag == "button" || matches!(field.element_type.as_deref(), Some("submit" | "button" | "reset")),
+                format: formatting::detect(&field),
+                max_length: field.max_length,
+                options: field.options,
+            });
+            if let Some(label) = &field.label {
+                for selector in &selectors {
+                    self.labels.insert(selector.clone(), label.clone());
+                }
             }
+            self.record(element_type, selectors, field.frame.as_deref());
         }
     }
     
-    fn parse_input_element(&mut self, line: &str) {
-        let input_type = self.extract_attribute(line, "type").unwrap_or("text".to_string());
-        let id = self.extract_attribute(line, "id");
-        let name = self.extract_attribute(line, "name");
-        let class = self.extract_attribute(line, "class");
-        
-        let mut selectors = Vec::new();
-        if let Some(id) = id {
-            selectors.push(format!("#{}", id));
-        }
-        if let Some(name) = name {
-            selectors.push(format!("[name=\"{}\"]", name));
-        }
-        if let Some(class) = class {
-            selectors.push(format!(".{}", class));
+    fn record(&mut self, element_type: String, selectors: Vec<String>, frame: Option<&str>) {
+        if let Some(frame) = frame {
+            for selector in &selectors {
+                self.frames.insert(selector.clone(), frame.to_string());
+            }
         }
-        
-        self.elements.entry(input_type).or_insert_with(Vec::new).extend(selectors);
+        self.elements.entry(element_type).or_default().extend(selectors);
+    }
+    
+    pub(crate) fn frame_of(&self, selector: &str) -> Option<&str> {
+        self.frames.get(selector).map(|frame| frame.as_str())
     }
     
-    fn parse_button_element(&mut self, line: &str) {
-        let id = self.extract_attribute(line, "id");
-        let class = self.extract_attribute(line, "class");
-        let text_content = self.extract_text_content(line);
+    /// Obejmuje kolejne akcje na elementach tej samej ramki blokiem `frame "..." ... end`
+    pub(crate) fn scope_to_frames(&self, actions: Vec<String>) -> Vec<String> {
+        let mut scoped = Vec::new();
+        let mut open: Option<&str> = None;
         
-        let mut selectors = Vec::new();
-        if let Some(id) = id {
-            selectors.push(format!("#{}", id));
+        for action in actions {
+            let frame = tagui::parse_dsl_script(&action)
+                .ok()
+                .and_then(|commands| commands.first().and_then(|command| command.selector().map(|s| s.to_string())))
+                .and_then(|selector| self.frame_of(&selector));
+            if frame != open {
+                if open.is_some() {
+                    scoped.push("end".to_string());
+                }
+                if let Some(frame) = frame {
+                    scoped.push(format!("frame \"{}\"", escape_for_dsl(frame)));
+                }
+                open = frame;
+            }
+            scoped.push(if open.is_some() { format!("  {}", action) } else { action });
         }
-        if let Some(class) = class {
-            selectors.push(format!(".{}", class));
+        if open.is_some() {
+            scoped.push("end".to_string());
         }
-        
-        // Classify button type based on content
-        let button_type = if let Some(text) = text_content {
-            let text_lower = text.to_lowercase();
-            if text_lower.contains("submit") || text_lower.contains("apply") || text_lower.contains("send") {
-                "submit"
-            } else if text_lower.contains("login") || text_lower.contains("sign in") {
-                "login"
-            } else if text_lower.contains("accept") || text_lower.contains("agree") {
-                "accept"
-            } else {
-                "button"
-            }
-        } else {
-            "button"
+        scoped
+    }
+    
+    /// Pierwsze (najstabilniejsze) selektory pól danego typu, po jednym na pole
+    fn fields_of_type(&self, element_type: &str) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.elements
+            .get(element_type)
+            .into_iter()
+            .flatten()
+            .filter(|selector| seen.insert(self.field_ids.get(*selector).copied()))
+            .map(|selector| selector.as_str())
+            .collect()
+    }
+    
+    /// Pole, którego widoczna etykieta to jedna z `phrases` (`exact`) albo ją zawiera jako ciąg słów
+    fn field_by_label(&self, phrases: &[&str], element_types: &[&str], exact: bool, filled: &HashSet<usize>) -> Option<(usize, String)> {
+        element_types.iter().flat_map(|element_type| self.fields_of_type(element_type)).find_map(|selector| {
+            let field_id = *self.field_ids.get(selector)?;
+            let label = label_words(self.labels.get(selector)?);
+            let matches = phrases.iter().any(|phrase| {
+                let phrase = label_words(phrase);
+                if exact {
+                    label == phrase
+                } else {
+                    !phrase.is_empty() && label.windows(phrase.len()).any(|window| window == phrase.as_slice())
+                }
+            });
+            (matches && !filled.contains(&field_id)).then(|| (field_id, selector.to_string()))
+        })
+    }
+    
+    fn step_of(&self, field_id: usize) -> Option<usize> {
+        self.places.get(field_id).and_then(|place| place.step)
+    }
+    
+    /// Liczba kroków kreatora, w których leżą pola
+    pub(crate) fn step_count(&self) -> usize {
+        self.places.iter().filter_map(|place| place.step).max().map_or(0, |last| last + 1)
+    }
+    
+    /// Przycisk "Dalej" danego kroku; bez własnego - wspólny przycisk spoza kroków
+    fn next_button(&self, step: Option<usize>) -> Option<&str> {
+        let buttons = self.fields_of_type("next");
+        let in_step = |wanted: Option<usize>| {
+            buttons.iter().copied().find(|selector| self.field_ids.get(*selector).map(|id| self.step_of(*id)) == Some(wanted))
         };
-        
-        self.elements.entry(button_type.to_string()).or_insert_with(Vec::new).extend(selectors);
+        in_step(step).or_else(|| step.and_then(|_| in_step(None)))
     }
     
-    fn parse_select_element(&mut self, line: &str) {
-        let id = self.extract_attribute(line, "id");
-        let name = self.extract_attribute(line, "name");
-        
-        let mut selectors = Vec::new();
-        if let Some(id) = id {
-            selectors.push(format!("#{}", id));
-        }
-        if let Some(name) = name {
-            selectors.push(format!("[name=\"{}\"]", name));
-        }
-        
-        self.elements.entry("select".to_string()).or_insert_with(Vec::new).extend(selectors);
+    /// Pierwsze pole (nie przycisk) kroku - na nie `waitfor` czeka po przejściu dalej
+    fn first_field_of_step(&self, step: usize) -> Option<&str> {
+        self.places
+            .iter()
+            .find(|place| place.step == Some(step) && !place.is_button && place.selector.is_some())
+            .and_then(|place| place.selector.as_deref())
     }
     
-    fn extract_attribute(&self, line: &str, attr: &str) -> Option<String> {
-        let pattern = format!("{}=\"", attr);
-        if let Some(start) = line.find(&pattern) {
-            let start = start + pattern.len();
-            if let Some(end) = line[start..].find('"') {
-                return Some(line[start..start + end].to_string());
-            }
+    /// Formularz wieloetapowy: kilka kontenerów kroków, jeden krok z przyciskiem "Dalej"
+    /// albo przycisk "Dalej" przy pasku postępu
+    pub(crate) fn is_multi_step(&self) -> bool {
+        match self.step_count() {
+            0 => self.has_progress && self.next_button(None).is_some(),
+            1 => self.next_button(Some(0)).is_some(),
+            _ => true,
         }
-        None
     }
     
-    fn extract_text_content(&self, line: &str) -> Option<String> {
-        if let Some(start) = line.find('>') {
-            if let Some(end) = line[start + 1..].find('<') {
-                let content = line[start + 1..start + 1 + end].trim();
-                if !content.is_empty() {
-                    return Some(content.to_string());
-                }
-            }
+    /// Selektor z etykietą pola, małymi literami - do dopasowania po nazwach pól
+    fn description_of(&self, selector: &str) -> String {
+        match self.labels.get(selector) {
+            Some(label) => format!("{} {}", selector, label).to_lowercase(),
+            None => selector.to_lowercase(),
         }
-        None
     }
     
     pub(crate) fn find_cookie_consent(&self) -> Option<String> {
@@ -447,6 +1056,26 @@ impl FormAnalyzer {
     }
 }
 
+/// Teksty przycisku przejścia do następnego kroku formularza (także zlokalizowane)
+const NEXT_BUTTON_WORDS: &[&str] = &["next", "continue", "dalej", "kontynuuj", "weiter", "suivant", "continuer", "siguiente", "continuar"];
+
+/// Rodzaj przycisku po jego tekście
+fn classify_button(text: Option<&str>, fallback: &str) -> String {
+    let text_lower = text.unwrap_or_default().to_lowercase();
+    let button_type = if text_lower.contains("submit") || text_lower.contains("apply") || text_lower.contains("send") {
+        "submit"
+    } else if label_words(&text_lower).iter().any(|word| NEXT_BUTTON_WORDS.contains(&word.as_str())) {
+        "next"
+    } else if text_lower.contains("login") || text_lower.contains("log in") || text_lower.contains("sign in") {
+        "login"
+    } else if text_lower.contains("accept") || text_lower.contains("agree") {
+        "accept"
+    } else {
+        fallback
+    };
+    button_type.to_string()
+}
+
 pub(crate) fn generate_login_sequence(analyzer: &FormAnalyzer, user_data: &Value) -> Option<Vec<String>> {
     let mut actions = Vec::new();
     
@@ -465,25 +1094,24 @@ pub(crate) fn generate_login_sequence(analyzer: &FormAnalyzer, user_data: &Value
         // Use email if available, otherwise username
         if let Some(email) = user_data.get("email").and_then(|v| v.as_str()) {
             if !email.is_empty() {
-                actions.push(format!("type \"{}\" \"{}\"", username_sel, escape_for_dsl(email)));
+                actions.push(format!("type \"{}\" \"{}\"", escape_for_dsl(username_sel), escape_for_dsl(email)));
             }
         } else if let Some(username) = user_data.get("username").and_then(|v| v.as_str()) {
             if !username.is_empty() {
-                actions.push(format!("type \"{}\" \"{}\"", username_sel, escape_for_dsl(username)));
+                actions.push(format!("type \"{}\" \"{}\"", escape_for_dsl(username_sel), escape_for_dsl(username)));
             }
         }
         
         if let Some(password) = user_data.get("password").and_then(|v| v.as_str()) {
             if !password.is_empty() {
-                actions.push(format!("type \"{}\" \"{}\"", password_sel, escape_for_dsl(password)));
+                actions.push(format!("type \"{}\" \"{}\"", escape_for_dsl(password_sel), escape_for_dsl(password)));
             }
         }
         
-        // Find and click login button
-        if let Some(login_btn) = analyzer.elements.get("login") {
-            if let Some(selector) = login_btn.first() {
-                actions.push(format!("click \"{}\"", selector));
-            }
+        // Find and click login button; without one submit the form from the password field
+        match analyzer.elements.get("login").and_then(|login_btn| login_btn.first()) {
+            Some(selector) => actions.push(format!("click \"{}\"", escape_for_dsl(selector))),
+            None => actions.push("press \"enter\"".to_string()),
         }
         
         return Some(actions);
@@ -492,40 +1120,373 @@ pub(crate) fn generate_login_sequence(analyzer: &FormAnalyzer, user_data: &Value
     None
 }
 
-pub(crate) fn generate_field_filling_sequence(analyzer: &FormAnalyzer, user_data: &Value) -> Vec<String> {
-    let mut actions = Vec::new();
+/// Klucze danych użytkownika, typy pól i etykiety, po których pola są rozpoznawane (także na stronach
+/// po polsku, niemiecku, francusku i hiszpańsku)
+const FIELD_LABELS: &[(&str, &[&str], &[&str])] = &[
+    ("first_name", &["text"], &["first name", "given name", "imię", "vorname", "prénom", "nombre"]),
+    ("last_name", &["text"], &["last name", "surname", "family name", "nazwisko", "nachname", "nom", "apellido", "apellidos"]),
+    ("fullname", &["text"], &["full name", "name", "imię i nazwisko", "vollständiger name", "nom complet", "nombre completo"]),
+    (
+        "email",
+        &["email", "text"],
+        &["email", "e mail", "email address", "adres email", "adres e mail", "courriel", "correo electrónico"],
+    ),
+    (
+        "phone",
+        &["tel", "text"],
+        &["phone", "phone number", "telephone", "mobile", "telefon", "numer telefonu", "telefonnummer", "téléphone", "teléfono"],
+    ),
+    ("address", &["text", "textarea"], &["address", "adres", "adresse", "anschrift", "dirección"]),
+    (
+        "cover_letter",
+        &["textarea", "text"],
+        &["cover letter", "motivation letter", "list motywacyjny", "anschreiben", "lettre de motivation", "carta de presentación"],
+    ),
+    ("username", &["text"], &["username", "user name", "login", "nazwa użytkownika", "benutzername"]),
+    (
+        "expected_salary",
+        &["text", "number"],
+        &[
+            "expected salary",
+            "salary expectations",
+            "desired salary",
+            "oczekiwania finansowe",
+            "oczekiwane wynagrodzenie",
+            "gehaltsvorstellung",
+            "prétentions salariales",
+            "pretensiones salariales",
+        ],
+    ),
+    ("notice_period", &["text"], &["notice period", "okres wypowiedzenia", "kündigungsfrist", "préavis", "preaviso"]),
+    (
+        "available_from",
+        &["date", "text"],
+        // Bez samego "start date" - tak nazywają się też pola historii zatrudnienia
+        &[
+            "available from",
+            "earliest start date",
+            "availability date",
+            "when can you start",
+            "dostępny od",
+            "dostępność od",
+            "najwcześniejsza data rozpoczęcia",
+            "verfügbar ab",
+            "frühester eintrittstermin",
+            "disponible à partir de",
+        ],
+    ),
+];
+
+/// Fragmenty etykiet list wyboru dla kluczy z polityk pól (np. "Are you willing to relocate?" -> `relocation`)
+const SELECT_LABELS: &[(&str, &[&str])] = &[
+    (crate::field_policies::RELOCATION_KEY, &["relocat", "relokac", "przeprowadz", "umzug", "déménag", "reubica"]),
+    (crate::field_policies::NOTICE_PERIOD_KEY, &["notice", "wypowiedzeni", "kündigung", "préavis", "preaviso"]),
+    // Tylko oczekiwania - "Current salary" czy "Obecne wynagrodzenie" to inne pytanie
+    (
+        crate::field_policies::EXPECTED_SALARY_KEY,
+        &[
+            "expected salary",
+            "salary expectation",
+            "desired salary",
+            "oczekiwania finansowe",
+            "oczekiwane wynagrodzenie",
+            "gehaltsvorstellung",
+            "gehaltswunsch",
+            "prétentions salariales",
+            "salaire souhaité",
+            "pretensiones salariales",
+            "salario deseado",
+        ],
+    ),
+];
+
+/// Typy pól, do których trafiają pozostałe wartości tekstowe z danych użytkownika
+const TEXT_FIELD_TYPES: &[&str] = &["text", "email", "tel", "number", "url", "date", "time", "textarea"];
+
+/// Pewność dopasowania klucza do pola w zależności od sposobu, w jaki zostało znalezione
+fn match_confidence(source: MatchSource) -> f32 {
+    match source {
+        MatchSource::Label => 0.95,
+        MatchSource::KeyLabel => 0.9,
+        MatchSource::LabelPartial => 0.75,
+        MatchSource::Selector => 0.5,
+        MatchSource::Remembered => 1.0,
+    }
+}
+
+/// Dopasowanie z etykietą pola i pewnością wynikającą z `source`
+fn mapping_for(analyzer: &FormAnalyzer, key: &str, selector: &str, source: MatchSource) -> FieldMapping {
+    FieldMapping {
+        key: key.to_string(),
+        selector: selector.to_string(),
+        label: analyzer.labels.get(selector).cloned(),
+        confidence: match_confidence(source),
+        matched_by: source,
+    }
+}
+
+/// Przypisuje klucze danych użytkownika polom tekstowym najpierw po widocznej etykiecie (dokładnej,
+/// potem zawierającej frazę), na końcu po id/name; każde pole najwyżej raz, w kolejności dokumentu
+fn text_field_mappings(analyzer: &FormAnalyzer, user_data: &Value) -> Vec<(usize, FieldMapping)> {
+    let has_text = |key: &str| user_data.get(key).and_then(|v| v.as_str()).is_some_and(|value| !value.is_empty());
+    let mut filled: HashSet<usize> = HashSet::new();
+    let mut filled_keys: HashSet<&str> = HashSet::new();
+    let mut mappings: Vec<(usize, FieldMapping)> = Vec::new();
     
-    // Enhanced field mappings with smarter detection
-    let field_mappings = [
-        ("fullname", vec!["text"], vec!["fullname", "full-name", "name", "firstname", "first-name"]),
-        ("email", vec!["email", "text"], vec!["email", "e-mail", "mail"]),
-        ("phone", vec!["tel", "text"], vec!["phone", "telephone", "tel", "mobile"]),
-        ("username", vec!["text"], vec!["username", "user", "login"]),
-    ];
+    for (exact, source) in [(true, MatchSource::Label), (false, MatchSource::LabelPartial)] {
+        for (data_key, input_types, phrases) in FIELD_LABELS {
+            if !has_text(data_key) || filled_keys.contains(data_key) {
+                continue;
+            }
+            if let Some((field_id, selector)) = analyzer.field_by_label(phrases, input_types, exact, &filled) {
+                filled.insert(field_id);
+                filled_keys.insert(*data_key);
+                mappings.push((field_id, mapping_for(analyzer, data_key, &selector, source)));
+            }
+        }
+    }
     
-    for (data_key, input_types, field_names) in &field_mappings {
-        if let Some(value) = user_data.get(*data_key).and_then(|v| v.as_str()) {
-            if !value.is_empty() {
-                // Try to find matching field
-                for input_type in input_types {
-                    if let Some(selectors) = analyzer.elements.get(*input_type) {
-                        for selector in selectors {
-                            // Check if selector matches field names
-                            let selector_lower = selector.to_lowercase();
-                            let matches = field_names.iter().any(|name| selector_lower.contains(name));
-                            
-                            if matches {
-                                actions.push(format!("type \"{}\" \"{}\"", selector, escape_for_dsl(value)));
-                                break;
-                            }
-                        }
-                    }
-                }
+    // Pozostałe klucze (np. z form_data) po etykiecie równej nazwie klucza: "postal_code" -> "Postal code"
+    if let Some(fields) = user_data.as_object() {
+        for key in fields.keys() {
+            if !has_text(key) || FIELD_LABELS.iter().any(|(data_key, _, _)| data_key == key) || key.ends_with("_path") || key == "password" {
+                continue;
+            }
+            let phrase = key.replace(['_', '-'], " ");
+            if let Some((field_id, selector)) = analyzer.field_by_label(&[phrase.as_str()], TEXT_FIELD_TYPES, true, &filled) {
+                filled.insert(field_id);
+                mappings.push((field_id, mapping_for(analyzer, key, &selector, MatchSource::KeyLabel)));
             }
         }
     }
     
-    actions
+    // Pola bez pasującej etykiety: nazwy w selektorze (id/name/klasa)
+    let field_mappings: [(&str, &[&str], &[&str]); 4] = [
+        ("fullname", &["text"], &["fullname", "full-name", "name", "firstname", "first-name"]),
+        ("email", &["email", "text"], &["email", "e-mail", "mail"]),
+        ("phone", &["tel", "text"], &["phone", "telephone", "tel", "mobile"]),
+        ("username", &["text"], &["username", "user", "login"]),
+    ];
+    for (data_key, input_types, field_names) in field_mappings {
+        if !has_text(data_key) || filled_keys.contains(data_key) {
+            continue;
+        }
+        let found = input_types.iter().flat_map(|input_type| analyzer.fields_of_type(input_type)).find_map(|selector| {
+            let field_id = *analyzer.field_ids.get(selector)?;
+            let description = analyzer.description_of(selector);
+            (!filled.contains(&field_id) && field_names.iter().any(|name| description.contains(name))).then_some((field_id, selector))
+        });
+        if let Some((field_id, selector)) = found {
+            filled.insert(field_id);
+            mappings.push((field_id, mapping_for(analyzer, data_key, selector, MatchSource::Selector)));
+        }
+    }
+    
+    mappings.sort_by_key(|(field_id, _)| *field_id);
+    mappings
+}
+
+/// Przypisuje listom rozwijanym klucze danych użytkownika występujące w ich selektorze lub etykiecie
+fn select_field_mappings(analyzer: &FormAnalyzer, user_data: &Value) -> Vec<(usize, FieldMapping)> {
+    let mut mappings: Vec<(usize, FieldMapping)> = Vec::new();
+    let Some(fields) = user_data.as_object() else {
+        return mappings;
+    };
+    
+    for selector in analyzer.get_elements_by_type("select") {
+        let Some(&field_id) = analyzer.field_ids.get(&selector) else { continue };
+        if mappings.iter().any(|(mapped, _)| *mapped == field_id) {
+            continue;
+        }
+        let description = analyzer.description_of(&selector);
+        let key = fields
+            .iter()
+            .filter(|(key, _)| description.contains(&key.to_lowercase()))
+            .find(|(_, value)| value.as_str().is_some_and(|v| !v.is_empty()))
+            .map(|(key, _)| key)
+            .or_else(|| {
+                SELECT_LABELS
+                    .iter()
+                    .find(|(_, phrases)| phrases.iter().any(|phrase| description.contains(phrase)))
+                    .and_then(|(data_key, _)| fields.get_key_value(*data_key))
+                    .filter(|(_, value)| value.as_str().is_some_and(|v| !v.is_empty()))
+                    .map(|(key, _)| key)
+            });
+        
+        if let Some(key) = key {
+            let label = analyzer.labels.get(&selector).map(|label| label_words(label)).unwrap_or_default();
+            let key_words = label_words(&key.replace(['_', '-'], " "));
+            let source = if label == key_words {
+                MatchSource::KeyLabel
+            } else if !key_words.is_empty() && label.windows(key_words.len()).any(|window| window == key_words.as_slice()) {
+                MatchSource::LabelPartial
+            } else {
+                MatchSource::Selector
+            };
+            mappings.push((field_id, mapping_for(analyzer, key, &selector, source)));
+        }
+    }
+    
+    mappings
+}
+
+/// Które pola formularza wypełnia `script`: klucz danych -> selektor -> pewność. Liczone z HTML
+/// niezależnie od cache i modelu; zostają tylko pola, na które skrypt wpisuje lub wybiera wartość (`type`/`select`)
+pub fn field_mapping(html: &str, user_data: &Value, script: &str) -> Vec<FieldMapping> {
+    let analyzer = FormAnalyzer::new(html);
+    let mut mappings = text_field_mappings(&analyzer, user_data);
+    mappings.extend(select_field_mappings(&analyzer, user_data));
+    let commands = match tagui::parse_dsl_script(script) {
+        Ok(commands) => commands,
+        Err(e) => {
+            debug!("Field mapping skipped, script does not parse: {}", e);
+            return Vec::new();
+        }
+    };
+    let used: HashSet<usize> = commands
+        .iter()
+        .filter(|command| matches!(command.name.as_str(), "type" | "select"))
+        .filter_map(|command| command.selector())
+        .filter_map(|selector| analyzer.field_ids.get(selector).copied())
+        .collect();
+    mappings.into_iter().filter(|(field_id, _)| used.contains(field_id)).map(|(_, mapping)| mapping).collect()
+}
+
+/// Komenda wypełniająca element `selector` na stronie; `None`, jeśli go tam nie ma
+fn fill_command(html: &str, selector: &str) -> Option<&'static str> {
+    let element = dom::select(html, selector).ok()?.elements.into_iter().next()?;
+    Some(match element.tag.as_str() {
+        "select" => "select",
+        "input" if element.html.contains("type=\"file\"") => "upload",
+        _ => "type",
+    })
+}
+
+/// Miejsce w skrypcie dla nowej komendy na polu `selector` (indeks wstawienia i linia, od której brać wcięcie):
+/// po kroku na najbliższym wcześniejszym w dokumencie elemencie (i czekaniu po nim), więc w kreatorze
+/// trafia na krok z tym polem; bez takiego kroku przed pierwszym późniejszym, a gdy nic nie pasuje -
+/// przed ostatnim `click`
+fn field_insertion_point(html: &str, lines: &[String], selector: &str) -> Option<(usize, usize)> {
+    let last_click = || lines.iter().rposition(|line| line.trim_start().starts_with("click ")).map(|index| (index, index));
+    let steps: Vec<(usize, String)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let tokens = tagui::tokenize_dsl_line(line.trim()).ok()?;
+            let [name, target, ..] = tokens.as_slice() else { return None };
+            matches!(name.as_str(), "type" | "select" | "upload" | "click" | "check" | "uncheck").then(|| (index, target.clone()))
+        })
+        .collect();
+    let mut selectors: Vec<&str> = steps.iter().map(|(_, target)| target.as_str()).collect();
+    selectors.push(selector);
+    let positions = dom::document_positions(html, &selectors);
+    let Some(target) = positions[steps.len()] else {
+        return last_click();
+    };
+    let located = || steps.iter().zip(&positions).filter_map(|((index, _), position)| Some((*index, (*position)?)));
+
+    if let Some((index, _)) = located().filter(|(_, position)| *position < target).max_by_key(|(_, position)| *position) {
+        let waits = lines[index + 1..]
+            .iter()
+            .take_while(|line| matches!(line.split_whitespace().next(), Some("wait" | "waitfor")))
+            .count();
+        return Some((index + 1 + waits, index));
+    }
+    located()
+        .filter(|(_, position)| *position > target)
+        .min_by_key(|(_, position)| *position)
+        .map(|(index, _)| (index, index))
+        .or_else(last_click)
+}
+
+/// Zapamiętane dla witryny poprawki pól (klucz danych -> selektor): komendy wpisujące wartość klucza
+/// trafiają na wskazane pole, a brakująca jest dopisywana w kroku z tym polem (`field_insertion_point`).
+/// Poprawki, których pola nie ma na stronie, są pomijane
+pub fn apply_field_choices(html: &str, user_data: &Value, script: &str, choices: &[(String, String)]) -> String {
+    if choices.is_empty() {
+        return script.to_string();
+    }
+    let uses_variables = !script_variables(script).is_empty();
+    let mut lines: Vec<String> = script.lines().map(str::to_string).collect();
+    
+    for (key, selector) in choices {
+        let Some(command) = fill_command(html, selector) else {
+            debug!(key = %key, selector = %selector, "Remembered field is not on this page");
+            continue;
+        };
+        let placeholder = format!("{{{{{}}}}}", key);
+        let literal = user_data.get(key).and_then(|v| v.as_str()).filter(|value| !value.is_empty());
+        let mut found = false;
+        for line in lines.iter_mut() {
+            let Ok(tokens) = tagui::tokenize_dsl_line(line.trim()) else { continue };
+            let [name, current, value, ..] = tokens.as_slice() else { continue };
+            if !matches!(name.as_str(), "type" | "select" | "upload") || (*value != placeholder && Some(value.as_str()) != literal) {
+                continue;
+            }
+            found = true;
+            if current != selector {
+                *line = line.replacen(&format!("\"{}\"", escape_for_dsl(current)), &format!("\"{}\"", escape_for_dsl(selector)), 1);
+            }
+        }
+        if found {
+            continue;
+        }
+        let value = match (uses_variables, literal) {
+            (true, _) => placeholder,
+            (false, Some(literal)) => escape_for_dsl(literal),
+            (false, None) => continue,
+        };
+        let position = field_insertion_point(html, &lines, selector);
+        let indent = position.map(|(_, anchor)| lines[anchor].len() - lines[anchor].trim_start().len()).unwrap_or(0);
+        let action = format!("{}{} \"{}\" \"{}\"", " ".repeat(indent), command, escape_for_dsl(selector), value);
+        match position {
+            Some((index, _)) => lines.insert(index, action),
+            None => lines.push(action),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Mapowanie pól z zapamiętanymi poprawkami: pole wskazane przez użytkownika zastępuje dopasowanie klucza
+pub fn with_remembered_fields(html: &str, mut mapping: Vec<FieldMapping>, choices: &[(String, String)]) -> Vec<FieldMapping> {
+    if choices.is_empty() {
+        return mapping;
+    }
+    let analyzer = FormAnalyzer::new(html);
+    for (key, selector) in choices {
+        if fill_command(html, selector).is_none() {
+            continue;
+        }
+        mapping.retain(|field| field.key != *key && field.selector != *selector);
+        mapping.push(mapping_for(&analyzer, key, selector, MatchSource::Remembered));
+    }
+    mapping
+}
+
+/// Komendy `type` dla pól dopasowanych przez `text_field_mappings`; daty, godziny, telefony i kwoty
+/// w formacie pola (`formatting::detect`)
+pub(crate) fn generate_field_filling_sequence(analyzer: &FormAnalyzer, user_data: &Value) -> Vec<String> {
+    text_field_mappings(analyzer, user_data)
+        .into_iter()
+        .filter_map(|(field_id, mapping)| {
+            let value = user_data.get(&mapping.key)?.as_str()?;
+            let value = match analyzer.places.get(field_id) {
+                Some(place) => formatting::format_for_field(value, place.format.as_ref(), place.max_length, analyzer.user_locale.as_deref()),
+                None => value.to_string(),
+            };
+            Some(format!("type \"{}\" \"{}\"", escape_for_dsl(&mapping.selector), escape_for_dsl(&value)))
+        })
+        .collect()
+}
+
+/// Etykieta jako słowa małymi literami, bez interpunkcji i gwiazdek pól wymaganych
+fn label_words(label: &str) -> Vec<String> {
+    label
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
 pub(crate) fn generate_upload_sequence(analyzer: &FormAnalyzer, user_data: &Value) -> Option<Vec<String>> {
@@ -534,7 +1495,7 @@ pub(crate) fn generate_upload_sequence(analyzer: &FormAnalyzer, user_data: &Valu
             // Find file input
             if let Some(file_selectors) = analyzer.elements.get("file") {
                 if let Some(selector) = file_selectors.first() {
-                    return Some(vec![format!("upload \"{}\" \"{}\"", selector, escape_for_dsl(cv_path))]);
+                    return Some(vec![format!("upload \"{}\" \"{}\"", escape_for_dsl(selector), escape_for_dsl(cv_path))]);
                 }
             }
         }
@@ -553,7 +1514,7 @@ pub(crate) fn generate_checkbox_sequence(analyzer: &FormAnalyzer) -> Vec<String>
                selector_lower.contains("agree") || 
                selector_lower.contains("consent") ||
                selector_lower.contains("gdpr") {
-                actions.push(format!("click \"{}\"", selector));
+                actions.push(format!("check \"{}\"", escape_for_dsl(selector)));
             }
         }
     }
@@ -561,6 +1522,84 @@ pub(crate) fn generate_checkbox_sequence(analyzer: &FormAnalyzer) -> Vec<String>
     actions
 }
 
+/// Skrypt formularza wieloetapowego: pola kroku, "Dalej", `waitfor` na pierwsze pole następnego kroku
+/// i tak do ostatniego kroku. Krok, który jest w DOM dopiero po kliknięciu (przycisk "Dalej" w ostatnim
+/// znanym kroku), kończy skrypt komentarzem - stronę trzeba wtedy przeanalizować ponownie
+pub(crate) fn generate_staged_sequence(analyzer: &FormAnalyzer, user_data: &Value) -> Vec<String> {
+    let mut fills: Vec<(usize, String)> = Vec::new();
+    for (field_id, mapping) in text_field_mappings(analyzer, user_data) {
+        if let Some(value) = user_data.get(&mapping.key).and_then(|v| v.as_str()) {
+            fills.push((field_id, format!("type \"{}\" \"{}\"", escape_for_dsl(&mapping.selector), escape_for_dsl(value))));
+        }
+    }
+    for (field_id, mapping) in select_field_mappings(analyzer, user_data) {
+        if let Some(value) = user_data.get(&mapping.key).and_then(|v| v.as_str()) {
+            fills.push((field_id, format!("select \"{}\" \"{}\"", escape_for_dsl(&mapping.selector), escape_for_dsl(value))));
+        }
+    }
+    fills.sort_by_key(|(field_id, _)| *field_id);
+    let fills_of = |step: Option<usize>| {
+        fills.iter().filter(move |(field_id, _)| analyzer.step_of(*field_id) == step).map(|(_, action)| action.clone())
+    };
+    
+    // Pola poza krokami (np. wspólne dla całego kreatora) przed pierwszym krokiem
+    let mut actions: Vec<String> = fills_of(None).collect();
+    let steps = analyzer.step_count();
+    for step in 0..steps {
+        actions.extend(fills_of(Some(step)));
+        if step + 1 == steps {
+            break;
+        }
+        match analyzer.next_button(Some(step)) {
+            Some(next) => actions.push(format!("click \"{}\"", escape_for_dsl(next))),
+            None => warn!(step = step + 1, "No Next button found for form step"),
+        }
+        if let Some(first) = analyzer.first_field_of_step(step + 1) {
+            actions.push(format!("waitfor \"{}\" timeout {}", escape_for_dsl(first), tagui::DEFAULT_WAITFOR_TIMEOUT_SECS));
+        }
+    }
+    
+    // Wspólny przycisk "Dalej" w kreatorze z kilkoma krokami na ostatnim ustępuje miejsca wysłaniu formularza
+    let last_step = steps.checked_sub(1);
+    let in_last_step = |next: &&str| analyzer.field_ids.get(*next).and_then(|id| analyzer.step_of(*id)) == last_step;
+    if let Some(next) = analyzer.next_button(last_step).filter(|next| steps <= 1 || in_last_step(next)) {
+        actions.push(format!("click \"{}\"", escape_for_dsl(next)));
+        actions.push("// Next form step is rendered after this click - analyze the page again to continue".to_string());
+    }
+    actions
+}
+
+/// Wybiera opcje list rozwijanych, których selektor lub etykieta pasuje do klucza w danych użytkownika.
+/// Wartość z danych jest zamieniana na `value` opcji o tej samej wartości lub tekście; listy bez takiej
+/// opcji są pomijane zamiast wpisywać do nich dowolny tekst
+pub(crate) fn generate_select_sequence(analyzer: &FormAnalyzer, user_data: &Value) -> Vec<String> {
+    select_field_mappings(analyzer, user_data)
+        .into_iter()
+        .filter_map(|(field_id, mapping)| {
+            let value = user_data.get(&mapping.key)?.as_str()?;
+            let options = analyzer.places.get(field_id).map(|place| place.options.as_slice()).unwrap_or_default();
+            let value = match matching_option(options, value) {
+                Some(option) => option.value.as_str(),
+                None if options.is_empty() => value,
+                None => {
+                    debug!(selector = %mapping.selector, key = %mapping.key, "No select option matches user value");
+                    return None;
+                }
+            };
+            Some(format!("select \"{}\" \"{}\"", escape_for_dsl(&mapping.selector), escape_for_dsl(value)))
+        })
+        .collect()
+}
+
+/// Opcja o wartości albo tekście równym `value` (bez rozróżniania wielkości liter)
+fn matching_option<'a>(options: &'a [dom::SelectOption], value: &str) -> Option<&'a dom::SelectOption> {
+    let value = value.trim();
+    options.iter().find(|option| option.value.eq_ignore_ascii_case(value)).or_else(|| {
+        let value = value.to_lowercase();
+        options.iter().find(|option| option.text.to_lowercase() == value)
+    })
+}
+
 pub(crate) fn is_complex_form(html: &str) -> bool {
     // Określ czy formularz jest złożony na podstawie różnych kryteriów
     let complexity_indicators = vec![
@@ -576,62 +1615,197 @@ pub(crate) fn is_complex_form(html: &str) -> bool {
     complexity_indicators.iter().filter(|&&x| x).count() >= 2
 }
 
-// Funkcja do wywołania rzeczywistego LLM (np. Claude API)
-pub async fn generate_dsl_with_llm(html: &str, user_data: &Value) -> Result<String, Box<dyn std::error::Error>> {
-    info!("Attempting to generate DSL using LLM API");
+/// Zdarzenie strumieniowego generowania (`/dsl/generate/stream`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GenerationEvent {
+    /// Surowy fragment odpowiedzi modelu
+    Token { text: String },
+    /// Kolejna pełna komenda DSL rozpoznana w odpowiedzi
+    Line { line: String },
+}
+
+/// Składa fragmenty odpowiedzi w linie i przepuszcza tylko te, które są komendami DSL
+#[derive(Debug, Default)]
+pub struct DslLineParser {
+    buffer: String,
+}
+
+impl DslLineParser {
+    pub fn push(&mut self, text: &str) -> Vec<String> {
+        self.buffer.push_str(text);
+        let Some(end) = self.buffer.rfind('\n') else {
+            return Vec::new();
+        };
+        let complete: String = self.buffer.drain(..=end).collect();
+        dsl_lines(&complete)
+    }
+
+    pub fn finish(self) -> Vec<String> {
+        dsl_lines(&self.buffer)
+    }
+}
+
+fn dsl_lines(text: &str) -> Vec<String> {
+    parse_dsl_from_response(text).lines().map(str::to_string).collect()
+}
+
+/// Generowanie z odpowiedzią modelu przesyłaną na bieżąco do `events`, gdy łańcuch zaczyna się od LLM.
+/// Zwraca gotowy skrypt (z `waitfor` po nawigacji); bez dostawcy lub przy błędzie - skrypt z dalszych strategii.
+pub async fn generate_dsl_streaming(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    requested: Option<&[GenerationStrategy]>,
+    db_pool: Option<&PgPool>,
+    events: UnboundedSender<GenerationEvent>,
+) -> GeneratedScript {
+    let chain = fallback_chain(requested);
+    let cache_results = requested.is_none_or(|requested| requested.is_empty()) && selection.llm.is_empty();
+    let provider = match llm_provider::for_options(&selection.llm) {
+        Ok(provider) => Some(provider).filter(|provider| provider.is_configured()),
+        Err(e) => {
+            warn!("Invalid LLM options for streaming: {}", e);
+            None
+        }
+    };
+    // Strona znanej platformy ATS dostaje skrypt z mapy selektorów; inaczej strumień zaczyna się od następnej strategii
+    let skip = usize::from(
+        chain.first() == Some(&GenerationStrategy::Platform)
+            && generators::platforms::generate(html, selection.site.as_deref(), user_data).is_none(),
+    );
+    let cache_key = create_cache_key(html, user_data, selection.site.as_deref(), &selection.locale);
+    if !html.trim().is_empty() {
+        if let Some(cached) = cached_script(html, user_data, selection, &chain, &cache_key, db_pool).await {
+            send_lines(&events, &cached.script);
+            return cached;
+        }
+    }
+    let Some(provider) = provider.filter(|_| {
+        !html.trim().is_empty() && chain.get(skip) == Some(&GenerationStrategy::Llm) && known_failure(&cache_key, selection).is_none()
+    }) else {
+        info!("LLM streaming unavailable, generating DSL without a model");
+        let generated = generate_with_chain(html, user_data, selection, chain, cache_results, db_pool).await;
+        send_lines(&events, &generated.script);
+        return generated;
+    };
+
+    info!(provider = provider.kind().as_str(), model = provider.model(), "Streaming DSL generation");
+    let redacted = redaction::redact(html, user_data);
+    let prompt = prompts::prompt_for(&redacted.html, &redacted.user_data, selection, db_pool).await;
+    let replay_key = format!("stream:{}", cache_key);
+    let (tokens, mut received) = mpsc::unbounded_channel::<String>();
+
+    let generation = replay::intercept(replay::InteractionKind::LlmResponse, &replay_key, || async {
+        faults::inject(FaultTarget::Llm).await?;
+        let request = provider.stream(&prompt, llm_provider::max_tokens(&selection.llm), tokens);
+        perf::timed(OperationKind::LlmCall, provider.operation(), json!({ "cache_key": cache_key, "stream": true }), request).await
+    });
+    let forward = async {
+        let mut parser = DslLineParser::default();
+        let mut emitted = 0usize;
+        while let Some(text) = received.recv().await {
+            let lines = parser.push(&text);
+            let _ = events.send(GenerationEvent::Token { text });
+            for line in lines {
+                emitted += 1;
+                let _ = events.send(GenerationEvent::Line { line: redacted.redaction.restore(&line) });
+            }
+        }
+        for line in parser.finish() {
+            emitted += 1;
+            let _ = events.send(GenerationEvent::Line { line: redacted.redaction.restore(&line) });
+        }
+        emitted
+    };
+    let (generated, emitted) = tokio::join!(generation, forward);
+
+    let script = match generated {
+        Ok(text) => {
+            // Strumień nie podaje liczby tokenów - zużycie jest szacowane z długości tekstu
+            llm_usage::record(provider.as_ref(), "stream", None, &prompt, &text);
+            redacted.redaction.restore(&lint::insert_navigation_waits(&parse_dsl_from_response(&text)))
+        }
+        Err(e) => {
+            error!("Streaming DSL generation failed: {}", e);
+            String::new()
+        }
+    };
+    if script.trim().is_empty() {
+        warn!("Model returned no DSL commands, falling back to the next generation strategies");
+        let mut generated = generate_with_chain(html, user_data, selection, chain[skip + 1..].to_vec(), cache_results, db_pool).await;
+        generated.generation.chain = chain;
+        send_lines(&events, &generated.script);
+        return generated;
+    }
+    // Odtworzona paczka replay nie przesyła fragmentów - linie wysyłamy z gotowego skryptu
+    if emitted == 0 {
+        for line in script.lines() {
+            let _ = events.send(GenerationEvent::Line { line: line.to_string() });
+        }
+    }
+
+    let blocker = match generated::check(&script) {
+        Ok(_) => None,
+        Err(e) => {
+            remember_failure(&cache_key, selection, &e.to_string());
+            known_failure(&cache_key, selection)
+        }
+    };
+    if let Some(pool) = db_pool.filter(|_| cache_results && blocker.is_none() && !replay::is_replaying()) {
+        if let Err(e) = cache_dsl_script_with_retry(pool, &cache_key, &script, html, Some(GenerationStrategy::Llm), selection.site.as_deref(), 3).await {
+            warn!("Failed to cache streamed DSL script: {}", e);
+        }
+    }
+    info!("Streamed DSL generation finished, {} lines", script.lines().count());
+    GeneratedScript { script, generation: GenerationInfo { strategy: Some(GenerationStrategy::Llm), cached: false, stale: false, chain, cache_key: Some(cache_key), blocker } }
+}
+
+fn send_lines(events: &UnboundedSender<GenerationEvent>, script: &str) {
+    for line in script.lines().filter(|line| !line.trim().is_empty()) {
+        let _ = events.send(GenerationEvent::Line { line: line.to_string() });
+    }
+}
+
+// Funkcja do wywołania rzeczywistego LLM przez skonfigurowanego dostawcę (LLM_PROVIDER);
+// prompt z szablonu dla rodzaju formularza i języka (`prompts`), nadpisanego w bazie, jeśli podano `db_pool`
+pub async fn generate_dsl_with_llm(
+    html: &str,
+    user_data: &Value,
+    selection: &PromptSelection,
+    db_pool: Option<&PgPool>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if !privacy::allows_llm() {
+        debug!("LLM is disabled, skipping model generation");
+        return Ok(String::new());
+    }
+    let provider = llm_provider::for_options(&selection.llm)?;
+    info!(provider = provider.kind().as_str(), model = provider.model(), "Attempting to generate DSL using LLM API");
     
-    // Sprawdź czy mamy klucz API (w prawdziwej implementacji)
-    let api_key = std::env::var("CLAUDE_API_KEY").unwrap_or_default();
-    if api_key.is_empty() {
-        warn!("No CLAUDE_API_KEY found, falling back to simple generation");
+    if !provider.is_configured() {
+        warn!("No API key for LLM provider {}, falling back to simple generation", provider.kind().as_str());
         return Ok(String::new());
     }
     
-    let prompt = format!(
-        "Przeanalizuj formularz HTML i wygeneruj skrypt DSL do jego wypełnienia.\n\
-        Dostępne komendy: click, type, upload, hover, wait\n\
-        \n\
-        Zasady:\n\
-        1. Używaj selektorów CSS (#id, .class, [attribute])\n\
-        2. Najpierw zaloguj się jeśli to konieczne\n\
-        3. Wypełnij wszystkie wymagane pola\n\
-        4. Na końcu kliknij przycisk submit/apply\n\
-        5. Zwróć TYLKO komendy DSL, bez komentarzy\n\
-        \n\
-        HTML: {}\n\
-        \n\
-        Dane użytkownika: {}\n\
-        \n\
-        Wygeneruj optymalną sekwencję komend DSL:",
-        html, 
-        serde_json::to_string_pretty(user_data).unwrap_or_default()
-    );
+    // Hasła, karty i inne wrażliwe wartości nie opuszczają maszyny - model widzi znaczniki
+    let redacted = redaction::redact(html, user_data);
+    let prompt = prompts::prompt_for(&redacted.html, &redacted.user_data, selection, db_pool).await;
     
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .header("Content-Type", "application/json")
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .json(&serde_json::json!({
-            "model": "claude-3-sonnet-20240229",
-            "max_tokens": 1000,
-            "messages": [
-                {"role": "user", "content": prompt}
-            ]
-        }))
-        .send()
-        .await?;
+    let cache_key = create_cache_key(html, user_data, selection.site.as_deref(), &selection.locale);
+    let response_body: Value = replay::intercept(replay::InteractionKind::LlmResponse, &cache_key, || async {
+        faults::inject(FaultTarget::Llm).await?;
+        let request = provider.complete(&prompt, llm_provider::max_tokens(&selection.llm));
+        perf::timed(OperationKind::LlmCall, provider.operation(), json!({ "cache_key": cache_key }), request).await
+    })
+    .await?;
     
-    if !response.status().is_success() {
-        error!("LLM API request failed with status: {}", response.status());
+    if response_body.is_null() {
         return Ok(String::new());
     }
+    llm_usage::record(provider.as_ref(), "generate", provider.token_usage(&response_body), &prompt, provider.response_text(&response_body).unwrap_or_default());
     
-    let response_body: Value = response.json().await?;
-    
-    if let Some(content) = response_body["content"][0]["text"].as_str() {
-        let cleaned_script = parse_dsl_from_response(content);
+    if let Some(content) = provider.response_text(&response_body) {
+        let cleaned_script = redacted.redaction.restore(&lint::insert_navigation_waits(&parse_dsl_from_response(content)));
         info!("Successfully generated DSL using LLM, {} lines", cleaned_script.lines().count());
         Ok(cleaned_script)
     } else {
@@ -640,7 +1814,7 @@ pub async fn generate_dsl_with_llm(html: &str, user_data: &Value) -> Result<Stri
     }
 }
 
-fn parse_dsl_from_response(response: &str) -> String {
+pub(crate) fn parse_dsl_from_response(response: &str) -> String {
     debug!("Parsing DSL from LLM response");
     
     // Wyczyść odpowiedź z niepotrzebnych znaków i komentarzy
@@ -651,11 +1825,12 @@ fn parse_dsl_from_response(response: &str) -> String {
             !line.is_empty() && 
             !line.starts_with("//") &&
             !line.starts_with("#") &&
-            (line.starts_with("click") || 
-             line.starts_with("type") || 
-             line.starts_with("upload") || 
-             line.starts_with("hover") ||
-             line.starts_with("wait"))
+            (tagui::DSL_COMMANDS.iter().any(|command| line.starts_with(command)) ||
+             line.starts_with("if present") ||
+             line.starts_with("repeat") ||
+             line.starts_with("for each") ||
+             line.starts_with("frame ") ||
+             *line == "end")
         })
         .collect::<Vec<_>>()
         .join("\n")
@@ -663,6 +1838,8 @@ fn parse_dsl_from_response(response: &str) -> String {
 
 // Funkcje pomocnicze do różnych typów formularzy
 pub mod templates {
+    use crate::dsl::lint::insert_navigation_waits;
+
     pub fn job_application_template(user_data: &serde_json::Value) -> String {
         let first_name = user_data.get("first_name").and_then(|v| v.as_str()).unwrap_or("");
         let last_name = user_data.get("last_name").and_then(|v| v.as_str()).unwrap_or("");
@@ -670,7 +1847,7 @@ pub mod templates {
         let phone = user_data.get("phone").and_then(|v| v.as_str()).unwrap_or("");
         let cv_path = user_data.get("cv_path").and_then(|v| v.as_str()).unwrap_or("");
         
-        format!("click \"#accept-cookies\"\nhover \"#careers-link\"\nclick \"#careers-link\"\nclick \"#apply-now\"\ntype \"#first-name\" \"{}\"\ntype \"#last-name\" \"{}\"\ntype \"#email\" \"{}\"\ntype \"#phone\" \"{}\"\nupload \"#resume\" \"{}\"\nclick \"#gdpr-consent\"\nclick \"#submit-application\"", first_name, last_name, email, phone, cv_path)
+        insert_navigation_waits(&format!("click \"#accept-cookies\"\nhover \"#careers-link\"\nclick \"#careers-link\"\nclick \"#apply-now\"\ntype \"#first-name\" \"{}\"\ntype \"#last-name\" \"{}\"\ntype \"#email\" \"{}\"\ntype \"#phone\" \"{}\"\nupload \"#resume\" \"{}\"\ncheck \"#gdpr-consent\"\nclick \"#submit-application\"", first_name, last_name, email, phone, cv_path))
     }
 
     pub fn registration_template(user_data: &serde_json::Value) -> String {
@@ -678,7 +1855,7 @@ pub mod templates {
         let email = user_data.get("email").and_then(|v| v.as_str()).unwrap_or("");
         let password = user_data.get("password").and_then(|v| v.as_str()).unwrap_or("");
         
-        format!("click \"#register\"\ntype \"#username\" \"{}\"\ntype \"#email\" \"{}\"\ntype \"#password\" \"{}\"\ntype \"#confirm-password\" \"{}\"\nclick \"#terms-checkbox\"\nclick \"#create-account\"", username, email, password, password)
+        insert_navigation_waits(&format!("click \"#register\"\ntype \"#username\" \"{}\"\ntype \"#email\" \"{}\"\ntype \"#password\" \"{}\"\ntype \"#confirm-password\" \"{}\"\ncheck \"#terms-checkbox\"\nclick \"#create-account\"", username, email, password, password))
     }
 
     pub fn linkedin_apply_template(user_data: &serde_json::Value) -> String {
@@ -687,7 +1864,7 @@ pub mod templates {
         let phone = user_data.get("phone").and_then(|v| v.as_str()).unwrap_or("");
         let cv_path = user_data.get("cv_path").and_then(|v| v.as_str()).unwrap_or("");
         
-        format!("click \"#sign-in\"\ntype \"#username\" \"{}\"\ntype \"#password\" \"{}\"\nclick \"#sign-in-submit\"\nclick \".jobs-apply-button\"\nupload \"#resume-upload\" \"{}\"\ntype \"#phone\" \"{}\"\nclick \"#follow-company\"\nclick \"#submit-application\"", email, password, cv_path, phone)
+        insert_navigation_waits(&format!("click \"#sign-in\"\ntype \"#username\" \"{}\"\ntype \"#password\" \"{}\"\nclick \"#sign-in-submit\"\nclick \".jobs-apply-button\"\nupload \"#resume-upload\" \"{}\"\ntype \"#phone\" \"{}\"\nclick \"#follow-company\"\nclick \"#submit-application\"", email, password, cv_path, phone))
     }
 }
 
@@ -715,6 +1892,335 @@ mod tests {
         assert!(dsl.contains("click \"#submit\""));
     }
 
+    #[tokio::test]
+    async fn test_fallback_chain_order_and_reported_strategy() {
+        use GenerationStrategy::*;
+        assert_eq!(GenerationStrategy::parse_chain(" Simple,heuristic,simple "), Ok(vec![Simple, Enhanced]));
+        assert_eq!(GenerationStrategy::parse_chain("ats,llm,platform"), Ok(vec![Platform, Llm]));
+        assert!(GenerationStrategy::parse_chain("enhanced,gpt").is_err());
+        assert!(GenerationStrategy::parse_chain(" , ").is_err());
+
+        // Żądanie zastępuje konfigurację; wyłączony LLM znika także z łańcucha żądania
+        assert_eq!(resolve_chain(None, &GenerationStrategy::DEFAULT_CHAIN, true), GenerationStrategy::DEFAULT_CHAIN.to_vec());
+        assert_eq!(resolve_chain(Some(&[]), &[Basic], true), vec![Basic]);
+        assert_eq!(resolve_chain(Some(&[Llm, Basic, Llm]), &[Enhanced], true), vec![Llm, Basic]);
+        assert_eq!(resolve_chain(Some(&[Llm, Basic]), &[Enhanced], false), vec![Basic]);
+        assert!(resolve_chain(Some(&[Llm]), &[Enhanced], false).is_empty());
+
+        let html = r#"<form><input id="email" name="email"><button id="submit">Send</button></form>"#;
+        let user_data = json!({ "email": "jan@example.com" });
+        let selection = PromptSelection::default();
+        let generated = generate_with_chain(html, &user_data, &selection, vec![Simple, Enhanced], false, None).await;
+        let cache_key = Some(create_cache_key(html, &user_data, None, &LocaleHint::default()));
+        assert_eq!(generated.generation, GenerationInfo { strategy: Some(Simple), cached: false, stale: false, chain: vec![Simple, Enhanced], cache_key, blocker: None });
+        assert!(generated.script.contains("click \"Submit\""));
+        let generated = generate_with_chain(html, &user_data, &selection, vec![Enhanced], false, None).await;
+        assert_eq!(generated.generation.strategy, Some(Enhanced));
+        assert!(generated.script.contains("jan@example.com"));
+
+        // Bez żadnej strategii - skrypt awaryjny bez strategii
+        let generated = generate_with_chain(html, &user_data, &selection, Vec::new(), false, None).await;
+        assert_eq!(generated.generation.strategy, None);
+        assert!(generated.script.contains("Emergency fallback"));
+    }
+
+    #[test]
+    fn test_cache_key_ignores_values_and_cosmetic_markup() {
+        let user_data = serde_json::json!({ "email": "" });
+        let first = r#"<input id="email-1187" name="email" value="jan@example.com">"#;
+        let second = r#"<div class="wrapper"><input id="email-4821" class="wide" name="email" value="anna@example.com"></div>"#;
+        let locale = LocaleHint::default();
+        
+        assert_eq!(create_cache_key(first, &user_data, Some("jobs.example.com"), &locale), create_cache_key(second, &user_data, Some("jobs.example.com"), &locale));
+        assert_ne!(
+            create_cache_key(first, &user_data, None, &locale),
+            create_cache_key(r#"<input id="phone" name="phone">"#, &user_data, None, &locale)
+        );
+        // Ten sam formularz na innej witrynie to inny wpis
+        assert_ne!(create_cache_key(first, &user_data, Some("jobs.example.com"), &locale), create_cache_key(first, &user_data, Some("careers.other.com"), &locale));
+        // Inne locale użytkownika albo język strony to inny prompt, więc inny wpis
+        assert_ne!(create_cache_key(first, &user_data, None, &locale), create_cache_key(first, &user_data, None, &LocaleHint::new(Some("de-DE"))));
+        let german = r#"<html lang="de"><body><input id="email-1187" name="email"></body></html>"#;
+        let polish = r#"<html lang="pl"><body><input id="email-1187" name="email"></body></html>"#;
+        assert_ne!(create_cache_key(german, &user_data, None, &locale), create_cache_key(polish, &user_data, None, &locale));
+        assert!(few_shot::is_templated("type \"#email\" \"{{email}}\"\nclick \"#send\""));
+        assert!(!few_shot::is_templated("type \"#email\" \"jan@example.com\""));
+    }
+    
+    #[test]
+    fn test_stale_cache_entry_needs_matching_structure() {
+        let user_data = json!({ "email": "" });
+        let page = r#"<form><label for="email">E-mail</label><input id="email" name="email"><button>Send</button></form>"#;
+        let restyled = r#"<form class="v2"><div class="row"><label for="email" class="bold">E-mail</label><input id="email" name="email" class="wide"></div><button>Send</button></form>"#;
+        let rebuilt = r#"<form><input name="email"><input name="phone"><button>Send</button></form>"#;
+        // Ten sam klucz cache, ale inna etykieta - zapisany selektor tekstowy `E-mail` już nie trafia
+        let renamed = r#"<form><label for="email">Adres poczty</label><input id="email" name="email"><button>Send</button></form>"#;
+        assert_eq!(dom::structure_fingerprint(page), dom::structure_fingerprint(restyled));
+        assert_ne!(dom::structure_fingerprint(page), dom::structure_fingerprint(rebuilt));
+        assert_eq!(create_cache_key(page, &user_data, None, &LocaleHint::default()), create_cache_key(renamed, &user_data, None, &LocaleHint::default()));
+        assert_ne!(dom::structure_fingerprint(page), dom::structure_fingerprint(renamed));
+        assert_eq!(dom::structure_fingerprint(page).len(), 64);
+        
+        // Paczki replay sprzed okna stale-while-revalidate odtwarzają się jako świeże wpisy
+        let recorded: CachedScript = serde_json::from_value(json!({ "script": "click \"#go\"", "strategy": "llm" })).unwrap();
+        assert!(!recorded.stale && recorded.structure_hash.is_none());
+        assert_eq!(stale_window(), DEFAULT_STALE_WINDOW);
+    }
+    
+    #[test]
+    fn test_generate_select_sequence() {
+        let html = r#"<select id="country"></select>
+<select id="title"></select>"#;
+        let analyzer = FormAnalyzer::new(html);
+        let user_data = serde_json::json!({ "country": "Poland" });
+        
+        let actions = generate_select_sequence(&analyzer, &user_data);
+        assert_eq!(actions, vec!["select \"#country\" \"Poland\"".to_string()]);
+
+        let html = r#"<select id="country"><option value="">--</option><option value="pl">Polska</option></select>
+<select id="title"><option>Mr</option><option>Ms</option></select>"#;
+        let analyzer = FormAnalyzer::new(html);
+        let user_data = serde_json::json!({ "country": "polska", "title": "Dr" });
+        assert_eq!(generate_select_sequence(&analyzer, &user_data), vec!["select \"#country\" \"pl\"".to_string()]);
+    }
+
+    #[test]
+    fn test_form_analyzer_reads_multiline_markup_and_labels() {
+        let html = r#"<form>
+            <label>E-mail address
+                <input
+                    type='email'
+                    id='f1'>
+            </label>
+            <input type="submit"
+                   id="go" value="Send">
+        </form>"#;
+        let analyzer = FormAnalyzer::new(html);
+        assert_eq!(analyzer.get_elements_by_type("email"), vec!["#f1".to_string()]);
+        assert_eq!(analyzer.get_elements_by_type("submit"), vec!["#go".to_string()]);
+        
+        // Pole bez "email" w selektorze znajdowane po etykiecie
+        let actions = generate_field_filling_sequence(&analyzer, &serde_json::json!({ "email": "jan@example.com" }));
+        assert_eq!(actions, vec!["type \"#f1\" \"jan@example.com\"".to_string()]);
+    }
+
+    #[test]
+    fn test_field_filling_matches_localized_labels() {
+        let html = r#"<form>
+            <label for="f1">Imię</label><input id="f1">
+            <label for="f2">Nazwisko *</label><input id="f2">
+            <label>Numer telefonu: <input id="f3" type="tel"></label>
+            <label for="f4">E-mail</label><input id="f4" name="contact">
+            <label for="f5">List motywacyjny</label><textarea id="f5"></textarea>
+            <label for="f6">Kod pocztowy</label><input id="f6">
+            <input id="name" placeholder="Nickname">
+        </form>"#;
+        let analyzer = FormAnalyzer::new(html);
+        let user_data = serde_json::json!({
+            "first_name": "Jan",
+            "last_name": "Kowalski",
+            "phone": "+48 600 000 000",
+            "email": "jan@example.com",
+            "cover_letter": "Dzień dobry",
+            "kod_pocztowy": "00-001",
+            "fullname": "Jan Kowalski"
+        });
+        
+        let actions = generate_field_filling_sequence(&analyzer, &user_data);
+        assert_eq!(
+            actions,
+            vec![
+                "type \"#f1\" \"Jan\"",
+                "type \"#f2\" \"Kowalski\"",
+                "type \"#f3\" \"+48 600 000 000\"",
+                "type \"#f4\" \"jan@example.com\"",
+                "type \"#f5\" \"Dzień dobry\"",
+                "type \"#f6\" \"00-001\"",
+                // Bez etykiety "name" pole znalezione po id, a nie wpisane drugi raz w "Imię"
+                "type \"#name\" \"Jan Kowalski\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_filling_formats_values() {
+        let html = r#"<form>
+            <label for="phone">Phone</label><input id="phone" type="tel" placeholder="+48 600 000 000">
+            <label for="start">Start date</label><input id="start" placeholder="MM/DD/YYYY">
+            <label for="salary">Salary expectation</label><input id="salary" type="number">
+        </form>"#;
+        let analyzer = FormAnalyzer::new(html);
+        let user_data = serde_json::json!({ "phone": "600123456", "start_date": "2024-09-01", "salary_expectation": "15 000 PLN" });
+        assert_eq!(
+            generate_field_filling_sequence(&analyzer, &user_data),
+            vec!["type \"#phone\" \"+48 600 123 456\"", "type \"#start\" \"09/01/2024\"", "type \"#salary\" \"15000\""]
+        );
+    }
+
+    #[test]
+    fn test_field_mapping_scores_matches() {
+        let html = r#"<form>
+            <label for="f1">E-mail</label><input id="f1">
+            <label for="f2">Kod pocztowy</label><input id="f2">
+            <input id="phone-number">
+            <label for="f4">Kraj zamieszkania</label><select id="f4" name="country"></select>
+        </form>"#;
+        let user_data = serde_json::json!({
+            "email": "jan@example.com",
+            "kod_pocztowy": "00-001",
+            "phone": "600000000",
+            "country": "Polska"
+        });
+        let analyzer = FormAnalyzer::new(html);
+        let mut actions = generate_field_filling_sequence(&analyzer, &user_data);
+        actions.extend(generate_select_sequence(&analyzer, &user_data));
+        let script = actions.join("\n");
+        
+        let mapping = field_mapping(html, &user_data, &script);
+        let summary: Vec<(&str, &str, MatchSource, bool)> = mapping
+            .iter()
+            .map(|field| (field.key.as_str(), field.selector.as_str(), field.matched_by, field.needs_review()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("email", "#f1", MatchSource::Label, false),
+                ("kod_pocztowy", "#f2", MatchSource::KeyLabel, false),
+                ("phone", "#phone-number", MatchSource::Selector, true),
+                ("country", "[name=\"country\"]", MatchSource::Selector, true),
+            ]
+        );
+        assert_eq!(mapping[0].label.as_deref(), Some("E-mail"));
+        
+        // Pola, których skrypt nie wypełnia (np. inny skrypt z modelu), nie trafiają do mapowania
+        let mapping = field_mapping(html, &user_data, "type \"#f1\" \"jan@example.com\"");
+        assert_eq!(mapping.len(), 1);
+        
+        // Selektor w innej komendzie (klik, warunek) nie oznacza wypełnienia pola
+        let mapping = field_mapping(html, &user_data, "if present \"#f2\"\nclick \"#f2\"\nend\ntype \"#f1\" \"jan@example.com\"");
+        assert_eq!(mapping.iter().map(|field| field.selector.as_str()).collect::<Vec<_>>(), vec!["#f1"]);
+        
+        // Obecne wynagrodzenie i data rozpoczęcia poprzedniej pracy nie dostają odpowiedzi z polityk
+        let history = r#"<form>
+            <label for="h1">Start date</label><input id="h1" type="date">
+            <label for="h2">Current salary</label><select id="h2"><option>5000</option></select>
+            <label for="h3">Salary expectations</label><select id="h3"><option>6000</option></select>
+        </form>"#;
+        let answers = serde_json::json!({ "available_from": "2026-11-01", "expected_salary": "6000" });
+        let analyzer = FormAnalyzer::new(history);
+        assert!(text_field_mappings(&analyzer, &answers).is_empty());
+        let selects: Vec<String> = select_field_mappings(&analyzer, &answers).into_iter().map(|(_, mapping)| mapping.selector).collect();
+        assert_eq!(selects, vec!["#h3"]);
+        assert_ne!(
+            create_cache_key(history, &answers, None, &LocaleHint::default()),
+            create_cache_key(history, &serde_json::json!({ "available_from": "2026-11-01", "expected_salary": "7000" }), None, &LocaleHint::default())
+        );
+    }
+
+    #[test]
+    fn test_remembered_field_choices_override_mapping() {
+        let html = r#"<form>
+            <label for="fax">Fax</label><input id="fax">
+            <label for="mobile">Telefon komórkowy</label><input id="mobile">
+            <input type="file" id="cv">
+            <button id="send">Wyślij</button>
+        </form>"#;
+        let user_data = serde_json::json!({ "phone": "600000000", "cv_path": "/tmp/cv.pdf" });
+        let choices = vec![
+            ("phone".to_string(), "#mobile".to_string()),
+            ("cv_path".to_string(), "#cv".to_string()),
+            ("email".to_string(), "#missing".to_string()),
+        ];
+        
+        let script = "type \"#fax\" \"600000000\" retry 2\nclick \"#send\"";
+        assert_eq!(
+            apply_field_choices(html, &user_data, script, &choices),
+            "type \"#mobile\" \"600000000\" retry 2\nupload \"#cv\" \"/tmp/cv.pdf\"\nclick \"#send\""
+        );
+        let templated = apply_field_choices(html, &user_data, "type \"#fax\" \"{{phone}}\"", &choices);
+        assert_eq!(templated, "type \"#mobile\" \"{{phone}}\"\nupload \"#cv\" \"{{cv_path}}\"");
+        
+        let mapping = with_remembered_fields(html, field_mapping(html, &user_data, &templated), &choices);
+        let phone = mapping.iter().find(|field| field.key == "phone").unwrap();
+        assert_eq!((phone.selector.as_str(), phone.matched_by, phone.label.as_deref()), ("#mobile", MatchSource::Remembered, Some("Telefon komórkowy")));
+        assert!(!phone.needs_review());
+        assert!(mapping.iter().all(|field| field.key != "email"));
+
+        // W kreatorze brakujące pole trafia na swój krok, nie przed ostatnie kliknięcie
+        let wizard = r#"<form>
+            <section><input id="name"><button id="next">Next</button></section>
+            <section><input id="mail"><input id="tel"><button id="send">Send</button></section>
+        </form>"#;
+        let choices = vec![("phone".to_string(), "#tel".to_string()), ("email".to_string(), "#mail".to_string())];
+        let user_data = serde_json::json!({ "phone": "600000000", "email": "jan@example.com", "name": "Jan" });
+        let script = "type \"#name\" \"Jan\"\nclick \"#next\"\nwaitfor \"#mail\"\nclick \"#send\"";
+        assert_eq!(
+            apply_field_choices(wizard, &user_data, script, &choices),
+            "type \"#name\" \"Jan\"\nclick \"#next\"\nwaitfor \"#mail\"\ntype \"#mail\" \"jan@example.com\"\ntype \"#tel\" \"600000000\"\nclick \"#send\""
+        );
+    }
+
+    #[test]
+    fn test_staged_script_for_wizard_forms() {
+        let html = r#"<form>
+            <div class="progress-bar" role="progressbar"></div>
+            <section data-step="1">
+                <label for="fn">First name</label><input id="fn">
+                <button type="button" id="next-1">Next</button>
+            </section>
+            <section data-step="2" hidden>
+                <label for="mail">E-mail</label><input id="mail" type="email">
+                <label for="country">Country</label><select id="country"></select>
+                <button type="button" id="next-2">Dalej</button>
+            </section>
+            <section data-step="3" hidden>
+                <label for="tel">Phone</label><input id="tel" type="tel">
+                <button id="send">Submit application</button>
+            </section>
+        </form>"#;
+        let user_data = serde_json::json!({ "first_name": "Jan", "email": "jan@example.com", "phone": "600", "country": "Poland" });
+        let analyzer = FormAnalyzer::new(html);
+        assert!(analyzer.is_multi_step());
+        assert_eq!(analyzer.step_count(), 3);
+        
+        let script = generate_staged_sequence(&analyzer, &user_data).join("\n");
+        assert_eq!(
+            script,
+            "type \"#fn\" \"Jan\"\nclick \"#next-1\"\nwaitfor \"#mail\" timeout 10\n\
+             type \"#mail\" \"jan@example.com\"\nselect \"#country\" \"Poland\"\nclick \"#next-2\"\nwaitfor \"#tel\" timeout 10\n\
+             type \"#tel\" \"600\""
+        );
+        assert!(tagui::validate_dsl_script(&script).is_ok());
+        
+        // Następny krok pojawia się w DOM dopiero po kliknięciu
+        let html = r#"<ol class="stepper"><li aria-current="step">1</li><li>2</li></ol>
+            <form><label for="fn">First name</label><input id="fn"><button id="continue" type="button">Continue</button></form>"#;
+        let analyzer = FormAnalyzer::new(html);
+        assert!(analyzer.is_multi_step());
+        let actions = generate_staged_sequence(&analyzer, &user_data);
+        assert_eq!(actions[..2], ["type \"#fn\" \"Jan\"", "click \"#continue\""]);
+        assert!(actions[2].starts_with("//"));
+        
+        // Zwykły formularz bez kroków
+        assert!(!FormAnalyzer::new("<form><input id=\"fn\"><button>Continue</button></form>").is_multi_step());
+    }
+
+    #[test]
+    fn test_scope_actions_to_frames() {
+        let html = "<select id=\"title\"></select>\n<codialog-frame name=\"#grnhse_iframe\">\n<select id=\"country\"></select>\n<select id=\"city\"></select>\n</codialog-frame>";
+        let analyzer = FormAnalyzer::new(html);
+        assert_eq!(analyzer.frame_of("#country"), Some("#grnhse_iframe"));
+        assert_eq!(analyzer.frame_of("#title"), None);
+        
+        let user_data = serde_json::json!({ "title": "Mr", "country": "Poland", "city": "Gdańsk" });
+        let script = analyzer.scope_to_frames(generate_select_sequence(&analyzer, &user_data)).join("\n");
+        assert_eq!(
+            script,
+            "select \"#title\" \"Mr\"\nframe \"#grnhse_iframe\"\n  select \"#country\" \"Poland\"\n  select \"#city\" \"Gdańsk\"\nend"
+        );
+        assert!(tagui::validate_dsl_script(&script).is_ok());
+    }
+    
     #[test]
     fn test_is_complex_form() {
         let simple_html = "<input type='text'><button>Submit</button>";
@@ -757,6 +2263,44 @@ mod tests {
         assert!(lines[2].starts_with("type"));
         assert!(lines[3].starts_with("click"));
     }
+
+    #[test]
+    fn test_dsl_line_parser_emits_complete_commands() {
+        let mut parser = DslLineParser::default();
+        assert!(parser.push("Here is the script:\ncli").is_empty());
+        assert_eq!(parser.push("ck \"#login\"\ntype \"#email\" "), vec!["click \"#login\"".to_string()]);
+        assert!(parser.push("\"jan@example.com\"").is_empty());
+        assert_eq!(parser.finish(), vec!["type \"#email\" \"jan@example.com\"".to_string()]);
+    }
+
+    #[test]
+    fn test_instruction_prompt_describes_page_and_variables() {
+        let html = r#"
+            <form>
+              <label for="email">E-mail</label><input id="email" type="email">
+              <input type="file" name="resume" aria-label="Upload CV">
+              <select id="source"><option>LinkedIn</option><option>Other</option></select>
+              <select id="notice"><option value="1m">1 month</option><option value="3m">3 months</option></select>
+              <input id="salary" required maxlength="6" pattern="[0-9]+">
+              <button id="apply">Easy Apply</button>
+            </form>
+        "#;
+        let variables = vec!["email".to_string(), "cv_path".to_string()];
+        let prompt = instruction_prompt("apply to this job with my saved CV", html, Some("https://www.linkedin.com/jobs/view/1"), &variables);
+        assert!(prompt.contains("Polecenie: apply to this job with my saved CV"));
+        assert!(prompt.contains("https://www.linkedin.com/jobs/view/1"));
+        assert!(prompt.contains("- #email input[email] \"E-mail\""));
+        assert!(prompt.contains("- [name=\"resume\"] input[file] \"Upload CV\""));
+        assert!(prompt.contains("opcje: LinkedIn | Other"));
+        assert!(prompt.contains("- #notice select opcje: 1m (\"1 month\") | 3m (\"3 months\")"));
+        assert!(prompt.contains("- #salary input[text] wymagane maxlength 6 pattern /[0-9]+/"));
+        assert!(prompt.contains("#apply button[submit] \"Easy Apply\""));
+        assert!(prompt.contains("Dostępne zmienne: {{email}}, {{cv_path}}"));
+        assert!(instruction_prompt("log in", "", None, &[]).contains("Bieżąca strona: (nieznana)\nPola strony:\n(brak pól formularza)"));
+
+        let script = "type \"#email\" \"{{email}}\"\nupload \"[name=\\\"resume\\\"]\" \"{{ cv_path }}\"\ntype \"#note\" \"{{email}}\"";
+        assert_eq!(script_variables(script), vec!["email".to_string(), "cv_path".to_string()]);
+    }
 }
 
 // Simple DSL generator used by unit tests in this module
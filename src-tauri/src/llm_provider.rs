@@ -0,0 +1,671 @@
+use anyhow::{bail, Result};
+use codialog_types::automation::LlmOptions;
+use codialog_types::SecretString;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{error, info};
+
+/// Dostawca modelu generującego skrypty DSL (LLM_PROVIDER)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LlmProviderKind {
+    #[default]
+    Anthropic,
+    OpenAi,
+    /// Lokalny lub self-hosted serwer Ollama
+    Ollama,
+}
+
+impl LlmProviderKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "anthropic" | "claude" => Some(LlmProviderKind::Anthropic),
+            "openai" => Some(LlmProviderKind::OpenAi),
+            "ollama" => Some(LlmProviderKind::Ollama),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmProviderKind::Anthropic => "anthropic",
+            LlmProviderKind::OpenAi => "openai",
+            LlmProviderKind::Ollama => "ollama",
+        }
+    }
+
+    fn default_model(&self) -> &'static str {
+        match self {
+            LlmProviderKind::Anthropic => "claude-3-sonnet-20240229",
+            LlmProviderKind::OpenAi => "gpt-4o-mini",
+            LlmProviderKind::Ollama => "llama3.1",
+        }
+    }
+
+    /// Najwyższa temperatura przyjmowana przez API dostawcy
+    pub fn max_temperature(&self) -> f32 {
+        match self {
+            LlmProviderKind::Anthropic => 1.0,
+            LlmProviderKind::OpenAi | LlmProviderKind::Ollama => 2.0,
+        }
+    }
+
+    fn default_base_url(&self) -> &'static str {
+        match self {
+            LlmProviderKind::Anthropic => "https://api.anthropic.com",
+            LlmProviderKind::OpenAi => "https://api.openai.com",
+            LlmProviderKind::Ollama => "http://localhost:11434",
+        }
+    }
+}
+
+/// Limit tokenów odpowiedzi przy generowaniu DSL, gdy żądanie go nie podaje
+pub const DEFAULT_MAX_TOKENS: u32 = 1000;
+
+/// Największy `max_tokens` przyjmowany w żądaniu
+pub const MAX_TOKENS_LIMIT: u32 = 16_000;
+
+/// Zakres temperatury przyjmowany w żądaniu (OpenAI i Ollama do 2, Anthropic do 1)
+pub const TEMPERATURE_RANGE: std::ops::RangeInclusive<f32> = 0.0..=2.0;
+
+/// Tokeny zużyte przez jedno wywołanie modelu
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+}
+
+/// Ceny modelu w USD za milion tokenów (LLM_PRICE_INPUT_PER_MTOK, LLM_PRICE_OUTPUT_PER_MTOK);
+/// domyślnie zero, np. dla lokalnej Ollamy
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LlmPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl LlmPrice {
+    pub fn cost(&self, usage: TokenUsage) -> f64 {
+        (usage.input as f64 * self.input_per_mtok + usage.output as f64 * self.output_per_mtok) / 1_000_000.0
+    }
+}
+
+/// LLM_MODEL_PRICES: `model=wejście/wyjście` (USD za milion tokenów) oddzielone przecinkami. Modele z tej listy
+/// (poza skonfigurowanym) są jedynymi, które żądanie może wybrać, i mają własne ceny
+pub fn parse_model_prices(value: &str) -> std::result::Result<HashMap<String, LlmPrice>, String> {
+    let mut prices = HashMap::new();
+    for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let invalid = || format!("must be a comma-separated list of model=input/output prices per million tokens, got '{}'", entry);
+        let (model, price) = entry.split_once('=').ok_or_else(invalid)?;
+        let (input, output) = price.split_once('/').ok_or_else(invalid)?;
+        let parse = |price: &str| price.trim().parse::<f64>().ok().filter(|price| price.is_finite() && *price >= 0.0);
+        let (Some(input_per_mtok), Some(output_per_mtok)) = (parse(input), parse(output)) else {
+            return Err(invalid());
+        };
+        if model.trim().is_empty() {
+            return Err(invalid());
+        }
+        prices.insert(model.trim().to_string(), LlmPrice { input_per_mtok, output_per_mtok });
+    }
+    Ok(prices)
+}
+
+/// Wybór dostawcy i modelu ze zmiennych LLM_PROVIDER, LLM_MODEL, LLM_BASE_URL
+/// oraz klucza CLAUDE_API_KEY / OPENAI_API_KEY
+#[derive(Debug, Clone)]
+pub struct LlmSettings {
+    pub provider: LlmProviderKind,
+    pub model: String,
+    pub base_url: String,
+    pub api_key: Option<SecretString>,
+    pub price: LlmPrice,
+    /// Modele, które żądanie może wybrać zamiast skonfigurowanego, z ich cenami (LLM_MODEL_PRICES)
+    pub model_prices: HashMap<String, LlmPrice>,
+    /// Temperatura próbkowania; bez niej - domyślna dostawcy
+    pub temperature: Option<f32>,
+}
+
+impl LlmSettings {
+    pub fn new(provider: LlmProviderKind) -> Self {
+        Self {
+            provider,
+            model: provider.default_model().to_string(),
+            base_url: provider.default_base_url().to_string(),
+            api_key: None,
+            price: LlmPrice::default(),
+            model_prices: HashMap::new(),
+            temperature: None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let value = |key: &str| lookup(key).filter(|value| !value.trim().is_empty());
+        let provider = value("LLM_PROVIDER").and_then(|provider| LlmProviderKind::parse(&provider)).unwrap_or_default();
+        let defaults = Self::new(provider);
+
+        Self {
+            provider,
+            model: value("LLM_MODEL").unwrap_or(defaults.model),
+            base_url: value("LLM_BASE_URL").map(|url| url.trim_end_matches('/').to_string()).unwrap_or(defaults.base_url),
+            api_key: api_key_for(provider, value),
+            price: LlmPrice {
+                input_per_mtok: value("LLM_PRICE_INPUT_PER_MTOK").and_then(|price| price.trim().parse().ok()).unwrap_or(0.0),
+                output_per_mtok: value("LLM_PRICE_OUTPUT_PER_MTOK").and_then(|price| price.trim().parse().ok()).unwrap_or(0.0),
+            },
+            model_prices: value("LLM_MODEL_PRICES").and_then(|prices| parse_model_prices(&prices).ok()).unwrap_or_default(),
+            temperature: None,
+        }
+    }
+
+    /// Ustawienia z nadpisaniami z żądania. Inny dostawca niż skonfigurowany dostaje swój domyślny
+    /// adres i model oraz klucz ze zmiennych środowiskowych (`lookup`). Model inny niż skonfigurowany
+    /// musi być w LLM_MODEL_PRICES i jest liczony według swoich cen
+    fn with_options(&self, options: &LlmOptions, lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        check_fields(options)?;
+        let mut settings = match options.provider.as_deref().and_then(LlmProviderKind::parse) {
+            Some(provider) if provider != self.provider => {
+                let value = |key: &str| lookup(key).filter(|value| !value.trim().is_empty());
+                Self { api_key: api_key_for(provider, value), model_prices: self.model_prices.clone(), ..Self::new(provider) }
+            }
+            _ => self.clone(),
+        };
+        if let Some(model) = &options.model {
+            settings.model = model.trim().to_string();
+        }
+        if settings.provider != self.provider || settings.model != self.model {
+            settings.price = *self.model_prices.get(&settings.model).ok_or_else(|| {
+                anyhow::anyhow!("Model '{}' is not allowed; add it with its prices to LLM_MODEL_PRICES", settings.model)
+            })?;
+        }
+        if let Some(temperature) = options.temperature {
+            let max = settings.provider.max_temperature();
+            if temperature > max {
+                bail!("Temperature for {} must be between 0 and {}", settings.provider.as_str(), max);
+            }
+        }
+        settings.temperature = options.temperature.or(settings.temperature);
+        Ok(settings)
+    }
+
+    pub fn build(&self) -> Arc<dyn LlmProvider> {
+        let api_key = self.api_key.clone().unwrap_or_default();
+        let (model, base_url, price, temperature) = (self.model.clone(), self.base_url.clone(), self.price, self.temperature);
+        match self.provider {
+            LlmProviderKind::Anthropic => Arc::new(AnthropicProvider { api_key, model, base_url, price, temperature }),
+            LlmProviderKind::OpenAi => Arc::new(OpenAiProvider { api_key, model, base_url, price, temperature }),
+            LlmProviderKind::Ollama => Arc::new(OllamaProvider { model, base_url, price, temperature }),
+        }
+    }
+}
+
+fn api_key_for(provider: LlmProviderKind, value: impl Fn(&str) -> Option<String>) -> Option<SecretString> {
+    let key = match provider {
+        LlmProviderKind::Anthropic => value("CLAUDE_API_KEY"),
+        LlmProviderKind::OpenAi => value("OPENAI_API_KEY"),
+        LlmProviderKind::Ollama => None,
+    };
+    key.map(SecretString::from)
+}
+
+/// Sprawdza parametry modelu z żądania względem konfiguracji (dozwolone modele, temperatura dostawcy);
+/// błąd opisuje pierwsze niepoprawne pole
+pub fn validate(options: &LlmOptions) -> Result<()> {
+    if options.provider.is_none() && options.model.is_none() && options.temperature.is_none() {
+        return check_fields(options);
+    }
+    configured().with_options(options, |key| std::env::var(key).ok()).map(|_| ())
+}
+
+fn check_fields(options: &LlmOptions) -> Result<()> {
+    if let Some(provider) = &options.provider {
+        if LlmProviderKind::parse(provider).is_none() {
+            bail!("Unknown LLM provider '{}', expected anthropic, openai or ollama", provider);
+        }
+    }
+    if options.model.as_ref().is_some_and(|model| model.trim().is_empty()) {
+        bail!("Model name must not be empty");
+    }
+    if let Some(temperature) = options.temperature {
+        if !TEMPERATURE_RANGE.contains(&temperature) {
+            bail!("Temperature must be between {} and {}", TEMPERATURE_RANGE.start(), TEMPERATURE_RANGE.end());
+        }
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        if max_tokens == 0 || max_tokens > MAX_TOKENS_LIMIT {
+            bail!("max_tokens must be between 1 and {}", MAX_TOKENS_LIMIT);
+        }
+    }
+    Ok(())
+}
+
+/// Treść zapytania z temperaturą, jeśli ją ustawiono
+fn with_temperature(mut body: Value, temperature: Option<f32>) -> Value {
+    if let Some(temperature) = temperature {
+        body["temperature"] = json!(temperature);
+    }
+    body
+}
+
+pub type CompletionFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Wywołanie modelu rozbite na zapytanie i odczyt tekstu, żeby `replay` mógł nagrywać
+/// surową odpowiedź niezależnie od dostawcy
+pub trait LlmProvider: Send + Sync {
+    fn kind(&self) -> LlmProviderKind;
+
+    fn model(&self) -> &str;
+
+    /// Ceny modelu, według których liczony jest koszt wywołania
+    fn price(&self) -> LlmPrice;
+
+    /// Etykieta wywołania w statystykach wolnych operacji (`perf`)
+    fn operation(&self) -> &'static str;
+
+    /// Bez klucza API generator od razu przechodzi do skryptu zapasowego
+    fn is_configured(&self) -> bool;
+
+    /// Zapytanie z treścią i nagłówkami dostawcy; `stream` włącza odpowiedź przyrostową
+    fn request(&self, prompt: &str, max_tokens: u32, stream: bool) -> reqwest::RequestBuilder;
+
+    fn response_text<'v>(&self, body: &'v Value) -> Option<&'v str>;
+
+    /// Tokeny z odpowiedzi (bez strumienia); `None` gdy dostawca ich nie podał
+    fn token_usage(&self, body: &Value) -> Option<TokenUsage>;
+
+    /// Fragment tekstu z jednej linii strumienia (zdarzenie SSE `data:` albo linia NDJSON)
+    fn stream_delta(&self, line: &str) -> Option<String>;
+
+    /// Treść odpowiedzi jako JSON; `Value::Null` gdy dostawca zwrócił status błędu
+    fn complete<'a>(&'a self, prompt: &'a str, max_tokens: u32) -> CompletionFuture<'a, Value> {
+        Box::pin(async move {
+            let response = self.request(prompt, max_tokens, false).send().await?;
+            if !response.status().is_success() {
+                error!(provider = self.kind().as_str(), "LLM API request failed with status: {}", response.status());
+                return Ok(Value::Null);
+            }
+            Ok(response.json::<Value>().await?)
+        })
+    }
+
+    /// Odpowiedź przyrostowa: każdy fragment trafia do `tokens`, wynikiem jest pełny tekst
+    fn stream<'a>(&'a self, prompt: &'a str, max_tokens: u32, tokens: UnboundedSender<String>) -> CompletionFuture<'a, String> {
+        Box::pin(async move {
+            let mut response = self.request(prompt, max_tokens, true).send().await?;
+            if !response.status().is_success() {
+                bail!("LLM API request failed with status: {}", response.status());
+            }
+
+            let mut text = String::new();
+            let mut pending: Vec<u8> = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                pending.extend_from_slice(&chunk);
+                while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    if let Some(delta) = self.stream_delta(String::from_utf8_lossy(&line).trim()) {
+                        text.push_str(&delta);
+                        // Odbiorca mógł się rozłączyć; tekst i tak jest potrzebny do wyniku
+                        let _ = tokens.send(delta);
+                    }
+                }
+            }
+            if let Some(delta) = self.stream_delta(String::from_utf8_lossy(&pending).trim()) {
+                text.push_str(&delta);
+                let _ = tokens.send(delta);
+            }
+            Ok(text)
+        })
+    }
+}
+
+/// Dane zdarzenia SSE (`data: {...}`) jako JSON
+fn sse_data(line: &str) -> Option<Value> {
+    let data = line.strip_prefix("data:")?.trim();
+    serde_json::from_str(data).ok()
+}
+
+/// Claude Messages API
+struct AnthropicProvider {
+    api_key: SecretString,
+    model: String,
+    base_url: String,
+    price: LlmPrice,
+    temperature: Option<f32>,
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn kind(&self) -> LlmProviderKind {
+        LlmProviderKind::Anthropic
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn price(&self) -> LlmPrice {
+        self.price
+    }
+
+    fn operation(&self) -> &'static str {
+        "anthropic.messages"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    fn request(&self, prompt: &str, max_tokens: u32, stream: bool) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", self.api_key.expose_secret())
+            .header("anthropic-version", "2023-06-01")
+            .json(&with_temperature(
+                json!({
+                    "model": self.model,
+                    "max_tokens": max_tokens,
+                    "stream": stream,
+                    "messages": [{"role": "user", "content": prompt}]
+                }),
+                self.temperature,
+            ))
+    }
+
+    fn response_text<'v>(&self, body: &'v Value) -> Option<&'v str> {
+        body["content"][0]["text"].as_str()
+    }
+
+    fn token_usage(&self, body: &Value) -> Option<TokenUsage> {
+        Some(TokenUsage { input: body["usage"]["input_tokens"].as_u64()?, output: body["usage"]["output_tokens"].as_u64()? })
+    }
+
+    fn stream_delta(&self, line: &str) -> Option<String> {
+        let event = sse_data(line)?;
+        (event["type"] == "content_block_delta").then(|| event["delta"]["text"].as_str().map(str::to_string)).flatten()
+    }
+}
+
+/// OpenAI Chat Completions; działa też z serwerami zgodnymi z tym API przez LLM_BASE_URL
+struct OpenAiProvider {
+    api_key: SecretString,
+    model: String,
+    base_url: String,
+    price: LlmPrice,
+    temperature: Option<f32>,
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn kind(&self) -> LlmProviderKind {
+        LlmProviderKind::OpenAi
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn price(&self) -> LlmPrice {
+        self.price
+    }
+
+    fn operation(&self) -> &'static str {
+        "openai.chat_completions"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    fn request(&self, prompt: &str, max_tokens: u32, stream: bool) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .bearer_auth(self.api_key.expose_secret())
+            .json(&with_temperature(
+                json!({
+                    "model": self.model,
+                    "max_tokens": max_tokens,
+                    "stream": stream,
+                    "messages": [{"role": "user", "content": prompt}]
+                }),
+                self.temperature,
+            ))
+    }
+
+    fn response_text<'v>(&self, body: &'v Value) -> Option<&'v str> {
+        body["choices"][0]["message"]["content"].as_str()
+    }
+
+    fn token_usage(&self, body: &Value) -> Option<TokenUsage> {
+        Some(TokenUsage { input: body["usage"]["prompt_tokens"].as_u64()?, output: body["usage"]["completion_tokens"].as_u64()? })
+    }
+
+    fn stream_delta(&self, line: &str) -> Option<String> {
+        // `data: [DONE]` nie jest JSON-em i kończy strumień
+        sse_data(line)?["choices"][0]["delta"]["content"].as_str().map(str::to_string)
+    }
+}
+
+/// Ollama `/api/chat` (strumień jako NDJSON); nie wymaga klucza
+struct OllamaProvider {
+    model: String,
+    base_url: String,
+    price: LlmPrice,
+    temperature: Option<f32>,
+}
+
+impl LlmProvider for OllamaProvider {
+    fn kind(&self) -> LlmProviderKind {
+        LlmProviderKind::Ollama
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn price(&self) -> LlmPrice {
+        self.price
+    }
+
+    fn operation(&self) -> &'static str {
+        "ollama.chat"
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    fn request(&self, prompt: &str, max_tokens: u32, stream: bool) -> reqwest::RequestBuilder {
+        let mut options = json!({"num_predict": max_tokens});
+        if let Some(temperature) = self.temperature {
+            options["temperature"] = json!(temperature);
+        }
+        reqwest::Client::new().post(format!("{}/api/chat", self.base_url)).json(&json!({
+            "model": self.model,
+            "stream": stream,
+            "options": options,
+            "messages": [{"role": "user", "content": prompt}]
+        }))
+    }
+
+    fn response_text<'v>(&self, body: &'v Value) -> Option<&'v str> {
+        body["message"]["content"].as_str()
+    }
+
+    fn token_usage(&self, body: &Value) -> Option<TokenUsage> {
+        Some(TokenUsage { input: body["prompt_eval_count"].as_u64()?, output: body["eval_count"].as_u64()? })
+    }
+
+    fn stream_delta(&self, line: &str) -> Option<String> {
+        let chunk: Value = serde_json::from_str(line).ok()?;
+        chunk["message"]["content"].as_str().filter(|content| !content.is_empty()).map(str::to_string)
+    }
+}
+
+static PROVIDER: RwLock<Option<Arc<dyn LlmProvider>>> = RwLock::new(None);
+
+static SETTINGS: RwLock<Option<LlmSettings>> = RwLock::new(None);
+
+/// Dostawca z konfiguracji; wywoływane raz przy starcie
+pub fn configure(settings: &LlmSettings) {
+    info!(provider = settings.provider.as_str(), model = %settings.model, base_url = %settings.base_url, "LLM provider configured");
+    *PROVIDER.write().unwrap() = Some(settings.build());
+    *SETTINGS.write().unwrap() = Some(settings.clone());
+}
+
+fn configured() -> LlmSettings {
+    SETTINGS.read().unwrap().clone().unwrap_or_else(LlmSettings::from_env)
+}
+
+/// Skonfigurowany dostawca, a bez `configure` (np. w osadzonym `Engine`) - wybrany ze zmiennych środowiskowych
+pub fn current() -> Arc<dyn LlmProvider> {
+    if let Some(provider) = PROVIDER.read().unwrap().as_ref() {
+        return provider.clone();
+    }
+    LlmSettings::from_env().build()
+}
+
+/// Dostawca dla jednego wywołania: skonfigurowany albo z nadpisanym dostawcą, modelem i temperaturą
+pub fn for_options(options: &LlmOptions) -> Result<Arc<dyn LlmProvider>> {
+    if options.provider.is_none() && options.model.is_none() && options.temperature.is_none() {
+        check_fields(options)?;
+        return Ok(current());
+    }
+    let settings = configured().with_options(options, |key| std::env::var(key).ok())?;
+    Ok(settings.build())
+}
+
+/// Limit tokenów odpowiedzi z żądania albo domyślny
+pub fn max_tokens(options: &LlmOptions) -> u32 {
+    options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+}
+
+/// Stan dostawcy dla `/health`
+pub fn status() -> Value {
+    let provider = current();
+    json!({
+        "provider": provider.kind().as_str(),
+        "model": provider.model(),
+        "configured": provider.is_configured(),
+        "enabled": crate::privacy::allows_llm()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_settings_select_provider_and_key() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("LLM_PROVIDER", "OpenAI"),
+            ("LLM_BASE_URL", "http://llm.internal:8000/"),
+            ("CLAUDE_API_KEY", "sk-ant"),
+            ("OPENAI_API_KEY", "sk-openai"),
+        ]);
+        let settings = LlmSettings::from_lookup(|key| env.get(key).map(|value| value.to_string()));
+        assert_eq!(settings.provider, LlmProviderKind::OpenAi);
+        assert_eq!(settings.model, "gpt-4o-mini");
+        assert_eq!(settings.base_url, "http://llm.internal:8000");
+        assert_eq!(settings.api_key.as_ref().map(|key| key.expose_secret()), Some("sk-openai"));
+
+        let provider = settings.build();
+        assert!(provider.is_configured());
+        let body = json!({"choices": [{"message": {"content": "click \"#apply\""}}]});
+        assert_eq!(provider.response_text(&body), Some("click \"#apply\""));
+
+        // Ollama nie potrzebuje klucza; Anthropic bez klucza nie jest wywoływany
+        let ollama = LlmSettings::from_lookup(|key| (key == "LLM_PROVIDER").then(|| "ollama".to_string())).build();
+        assert!(ollama.is_configured());
+        assert_eq!(ollama.response_text(&json!({"message": {"content": "wait 1"}})), Some("wait 1"));
+        assert_eq!(ollama.stream_delta(r#"{"message": {"content": "wait"}, "done": false}"#).as_deref(), Some("wait"));
+
+        // Strumienie: fragmenty tekstu z SSE, pozostałe zdarzenia pomijane
+        assert_eq!(provider.stream_delta(r#"data: {"choices": [{"delta": {"content": "click"}}]}"#).as_deref(), Some("click"));
+        assert_eq!(provider.stream_delta("data: [DONE]"), None);
+        let anthropic = LlmSettings::new(LlmProviderKind::Anthropic).build();
+        assert_eq!(
+            anthropic.stream_delta(r##"data: {"type": "content_block_delta", "delta": {"type": "text_delta", "text": " \"#go\""}}"##).as_deref(),
+            Some(" \"#go\"")
+        );
+        assert_eq!(anthropic.stream_delta("event: content_block_delta"), None);
+        assert!(!LlmSettings::from_lookup(|_| None).build().is_configured());
+
+        // Koszt z tokenów odpowiedzi i cen za milion tokenów
+        let usage = provider.token_usage(&json!({"usage": {"prompt_tokens": 2000, "completion_tokens": 500}})).unwrap();
+        assert_eq!(usage, TokenUsage { input: 2000, output: 500 });
+        let price = LlmSettings::from_lookup(|key| match key {
+            "LLM_PRICE_INPUT_PER_MTOK" => Some("3".to_string()),
+            "LLM_PRICE_OUTPUT_PER_MTOK" => Some("15".to_string()),
+            _ => None,
+        })
+        .price;
+        assert!((price.cost(usage) - 0.0135).abs() < 1e-9);
+        assert_eq!(ollama.token_usage(&json!({"message": {"content": "wait 1"}})), None);
+    }
+
+    fn request_body(provider: &Arc<dyn LlmProvider>, max_tokens: u32) -> Value {
+        let request = provider.request("prompt", max_tokens, false).build().unwrap();
+        serde_json::from_slice(request.body().and_then(|body| body.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_request_options_override_model_and_sampling() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("LLM_PROVIDER", "anthropic"),
+            ("CLAUDE_API_KEY", "sk-ant"),
+            ("OPENAI_API_KEY", "sk-openai"),
+            ("LLM_PRICE_INPUT_PER_MTOK", "3"),
+            ("LLM_PRICE_OUTPUT_PER_MTOK", "15"),
+            ("LLM_MODEL_PRICES", "claude-3-haiku-20240307=0.25/1.25, gpt-4o-mini=0.15/0.6, llama3.1=0/0"),
+        ]);
+        let lookup = |key: &str| env.get(key).map(|value| value.to_string());
+        let configured = LlmSettings::from_lookup(lookup);
+
+        // Bez temperatury zapytanie jest takie jak dotąd
+        let body = request_body(&configured.build(), DEFAULT_MAX_TOKENS);
+        assert_eq!(body["max_tokens"], 1000);
+        assert!(body.get("temperature").is_none());
+
+        let options = LlmOptions { model: Some(" claude-3-haiku-20240307 ".to_string()), temperature: Some(0.2), max_tokens: Some(400), ..Default::default() };
+        let provider = configured.with_options(&options, lookup).unwrap().build();
+        let body = request_body(&provider, max_tokens(&options));
+        assert_eq!((provider.kind(), body["model"].as_str()), (LlmProviderKind::Anthropic, Some("claude-3-haiku-20240307")));
+        assert_eq!(body["max_tokens"], 400);
+        assert!((body["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+        // Koszt według cen wybranego modelu, nie skonfigurowanego
+        assert_eq!(provider.price(), LlmPrice { input_per_mtok: 0.25, output_per_mtok: 1.25 });
+        assert_eq!(configured.build().price(), LlmPrice { input_per_mtok: 3.0, output_per_mtok: 15.0 });
+
+        // Inny dostawca: jego domyślny model i klucz ze środowiska
+        let switched = configured.with_options(&LlmOptions { provider: Some("OpenAI".to_string()), ..Default::default() }, lookup).unwrap();
+        assert_eq!((switched.provider, switched.model.as_str()), (LlmProviderKind::OpenAi, "gpt-4o-mini"));
+        assert_eq!(switched.base_url, "https://api.openai.com");
+        assert_eq!(switched.api_key.as_ref().map(|key| key.expose_secret()), Some("sk-openai"));
+        assert_eq!(switched.price, LlmPrice { input_per_mtok: 0.15, output_per_mtok: 0.6 });
+
+        // Modele spoza LLM_MODEL_PRICES i temperatura ponad limit dostawcy są odrzucane
+        assert!(configured.with_options(&LlmOptions { model: Some("claude-3-opus-20240229".to_string()), ..Default::default() }, lookup).is_err());
+        assert!(configured.with_options(&LlmOptions { temperature: Some(1.5), ..Default::default() }, lookup).is_err());
+        assert!(parse_model_prices("gpt-4o=2.5").is_err());
+
+        let ollama = LlmOptions { provider: Some("ollama".to_string()), temperature: Some(1.5), ..Default::default() };
+        let body = request_body(&configured.with_options(&ollama, lookup).unwrap().build(), 300);
+        assert_eq!(body["options"]["num_predict"], 300);
+        assert_eq!(body["options"]["temperature"], 1.5);
+
+        for invalid in [
+            LlmOptions { provider: Some("gemini".to_string()), ..Default::default() },
+            LlmOptions { model: Some("  ".to_string()), ..Default::default() },
+            LlmOptions { temperature: Some(2.5), ..Default::default() },
+            LlmOptions { max_tokens: Some(0), ..Default::default() },
+            LlmOptions { max_tokens: Some(MAX_TOKENS_LIMIT + 1), ..Default::default() },
+        ] {
+            assert!(validate(&invalid).is_err(), "{:?} should be rejected", invalid);
+        }
+        assert_eq!(max_tokens(&LlmOptions::default()), DEFAULT_MAX_TOKENS);
+    }
+}
@@ -0,0 +1,354 @@
+//! Zużycie tokenów i szacowany koszt każdego wywołania modelu, zapisywane w tabeli `llm_usage`.
+//! Wywołania z `llm` trafiają kanałem do zapisu w tle, żeby generowanie nie czekało na bazę.
+//! Ten sam zapis rozlicza koszt w dziennych limitach właścicieli (`SpendSink`), więc limit działa
+//! dla każdego wywołania modelu, nie tylko dla wybranych endpointów.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::llm_provider::{LlmProvider, TokenUsage};
+use crate::replay;
+
+/// Domyślny zakres `/llm/usage` w dniach
+pub const DEFAULT_USAGE_DAYS: u32 = 30;
+
+/// Najdłuższy zakres `/llm/usage` w dniach; większe wartości nie mieściłyby się w interwale bazy
+pub const MAX_USAGE_DAYS: u32 = 36_500;
+
+/// Jedno wywołanie modelu
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageRecord {
+    pub provider: String,
+    pub model: String,
+    /// `generate`, `stream` albo `instruction`
+    pub operation: String,
+    pub session_id: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// USD według LLM_PRICE_*_PER_MTOK
+    pub cost: f64,
+    /// Dostawca nie podał liczby tokenów (np. przy strumieniowaniu) - policzone z długości tekstu
+    pub estimated: bool,
+    /// Właściciele limitów, którym liczy się koszt: `("user", id)`, `("schedule", id)`
+    #[serde(skip)]
+    pub budget_owners: Vec<(String, String)>,
+}
+
+/// Do kogo należą wywołania modelu: sesja w statystykach i właściciele dziennych limitów kosztu
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attribution {
+    pub session_id: Option<String>,
+    pub budget_owners: Vec<(String, String)>,
+}
+
+/// Rozliczenie kosztu wywołania w limicie właściciela; implementują je budżety aplikacji
+pub trait SpendSink: Send + Sync {
+    fn record_spend<'a>(&'a self, owner_type: &'a str, owner_id: &'a str, cost: f64) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+}
+
+tokio::task_local! {
+    static ATTRIBUTION: Attribution;
+}
+
+/// Ile wywołań czeka na zapis, zanim baza danych wystartuje; nadmiarowe są pomijane
+const MAX_PENDING_RECORDS: usize = 10_000;
+
+/// Kanał zapisu tworzony przy pierwszym wywołaniu modelu, żeby wywołania sprzed startu bazy czekały
+/// w buforze; odbiornik zabiera `start_recording`
+static SINK: OnceLock<UsageChannel> = OnceLock::new();
+
+struct UsageChannel {
+    sender: Sender<UsageRecord>,
+    receiver: Mutex<Option<Receiver<UsageRecord>>>,
+}
+
+fn sink() -> &'static UsageChannel {
+    SINK.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel(MAX_PENDING_RECORDS);
+        UsageChannel { sender, receiver: Mutex::new(Some(receiver)) }
+    })
+}
+
+/// Przypisuje wywołania modelu wykonane w `future` do sesji i limitów jej właścicieli
+pub async fn attributed<F: Future>(attribution: Attribution, future: F) -> F::Output {
+    ATTRIBUTION.scope(attribution, future).await
+}
+
+/// `tokio::spawn`, który zabiera do zadania przypisanie bieżącego zadania; wywołania modelu
+/// w zwykłym `tokio::spawn` (np. odświeżanie cache w tle) nie miałyby sesji ani właścicieli limitów
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match ATTRIBUTION.try_with(Attribution::clone) {
+        Ok(attribution) => tokio::spawn(ATTRIBUTION.scope(attribution, future)),
+        Err(_) => tokio::spawn(future),
+    }
+}
+
+/// Przybliżona liczba tokenów tekstu (około 4 znaki na token)
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Zapisuje wywołanie modelu; odtwarzane z paczki replay nie kosztują i nie są liczone
+pub fn record(provider: &dyn LlmProvider, operation: &str, usage: Option<TokenUsage>, prompt: &str, completion: &str) {
+    if replay::is_replaying() {
+        return;
+    }
+    let (usage, estimated) = match usage {
+        Some(usage) => (usage, false),
+        None => (TokenUsage { input: estimate_tokens(prompt), output: estimate_tokens(completion) }, true),
+    };
+    let attribution = ATTRIBUTION.try_with(Attribution::clone).unwrap_or_default();
+    let record = UsageRecord {
+        provider: provider.kind().as_str().to_string(),
+        model: provider.model().to_string(),
+        operation: operation.to_string(),
+        session_id: attribution.session_id,
+        input_tokens: usage.input,
+        output_tokens: usage.output,
+        cost: provider.price().cost(usage),
+        estimated,
+        budget_owners: attribution.budget_owners,
+    };
+    debug!(operation, input_tokens = record.input_tokens, output_tokens = record.output_tokens, cost = record.cost, estimated, "LLM usage");
+    if sink().sender.try_send(record).is_err() {
+        debug!("LLM usage buffer is full, dropping the record");
+    }
+}
+
+/// Zapisuje kolejne wywołania, także zbuforowane przed startem bazy, do `store` i dolicza ich koszt
+/// w `spend`; wywoływane raz po starcie bazy, wewnątrz runtime tokio
+pub fn start_recording(store: Arc<LlmUsageStore>, spend: Arc<dyn SpendSink>) {
+    let Some(mut records) = sink().receiver.lock().unwrap().take() else {
+        warn!("LLM usage recording already started");
+        return;
+    };
+    tokio::spawn(async move {
+        while let Some(record) = records.recv().await {
+            if let Err(e) = store.insert(&record).await {
+                warn!("Failed to record LLM usage: {}", e);
+            }
+            for (owner_type, owner_id) in &record.budget_owners {
+                spend.record_spend(owner_type, owner_id, record.cost).await;
+            }
+        }
+    });
+}
+
+/// Po czym grupowane jest zużycie w `/llm/usage`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsageGrouping {
+    #[default]
+    Day,
+    Session,
+}
+
+impl UsageGrouping {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "day" => Some(UsageGrouping::Day),
+            "session" => Some(UsageGrouping::Session),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageGrouping::Day => "day",
+            UsageGrouping::Session => "session",
+        }
+    }
+
+    fn key_expression(&self) -> &'static str {
+        match self {
+            UsageGrouping::Day => "TO_CHAR(created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD')",
+            UsageGrouping::Session => "COALESCE(session_id, '')",
+        }
+    }
+}
+
+/// Zużycie jednego dnia lub jednej sesji u jednego dostawcy i modelu
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageBucket {
+    /// Dzień `YYYY-MM-DD` (UTC) albo identyfikator sesji; pusty dla wywołań bez sesji
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+    /// Ile wywołań ma liczbę tokenów oszacowaną
+    pub estimated_calls: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+}
+
+pub fn totals(buckets: &[UsageBucket]) -> UsageTotals {
+    buckets.iter().fold(UsageTotals::default(), |mut totals, bucket| {
+        totals.calls += bucket.calls;
+        totals.input_tokens += bucket.input_tokens;
+        totals.output_tokens += bucket.output_tokens;
+        totals.cost += bucket.cost;
+        totals
+    })
+}
+
+pub struct LlmUsageStore {
+    db_pool: PgPool,
+}
+
+impl LlmUsageStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę zużycia
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing LLM usage table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_usage (
+                id BIGSERIAL PRIMARY KEY,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                provider VARCHAR(32) NOT NULL,
+                model VARCHAR(255) NOT NULL,
+                operation VARCHAR(32) NOT NULL,
+                session_id VARCHAR(255),
+                input_tokens BIGINT NOT NULL,
+                output_tokens BIGINT NOT NULL,
+                cost DOUBLE PRECISION NOT NULL,
+                estimated BOOLEAN NOT NULL DEFAULT FALSE
+            );
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create llm_usage table")?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_llm_usage_created_at ON llm_usage(created_at)")
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to create llm_usage index")?;
+
+        Ok(())
+    }
+
+    pub async fn insert(&self, record: &UsageRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO llm_usage (provider, model, operation, session_id, input_tokens, output_tokens, cost, estimated)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(&record.provider)
+        .bind(&record.model)
+        .bind(&record.operation)
+        .bind(&record.session_id)
+        .bind(record.input_tokens as i64)
+        .bind(record.output_tokens as i64)
+        .bind(record.cost)
+        .bind(record.estimated)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to insert LLM usage")?;
+
+        Ok(())
+    }
+
+    /// Zużycie z ostatnich `days` dni, od najnowszego dnia lub najdroższej sesji
+    pub async fn summary(&self, grouping: UsageGrouping, days: u32, session_id: Option<&str>) -> Result<Vec<UsageBucket>> {
+        let order = match grouping {
+            UsageGrouping::Day => "key DESC, cost DESC",
+            UsageGrouping::Session => "cost DESC, key",
+        };
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} AS key, provider, model, COUNT(*) AS calls,
+                   COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                   COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                   COALESCE(SUM(cost), 0) AS cost,
+                   COUNT(*) FILTER (WHERE estimated) AS estimated_calls
+            FROM llm_usage
+            WHERE created_at >= NOW() - make_interval(days => $1)
+              AND ($2::VARCHAR IS NULL OR session_id = $2)
+            GROUP BY 1, provider, model
+            ORDER BY {}
+            "#,
+            grouping.key_expression(),
+            order
+        ))
+        .bind(days.min(MAX_USAGE_DAYS) as i32)
+        .bind(session_id)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to aggregate LLM usage")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| UsageBucket {
+                key: row.get("key"),
+                provider: row.get("provider"),
+                model: row.get("model"),
+                calls: row.get::<i64, _>("calls") as u64,
+                input_tokens: row.get::<i64, _>("input_tokens") as u64,
+                output_tokens: row.get::<i64, _>("output_tokens") as u64,
+                cost: row.get("cost"),
+                estimated_calls: row.get::<i64, _>("estimated_calls") as u64,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grouping_estimates_and_totals() {
+        assert_eq!(UsageGrouping::parse(" Session"), Some(UsageGrouping::Session));
+        assert_eq!(UsageGrouping::parse("week"), None);
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("click \"#a\""), 3);
+
+        let bucket = |key: &str, calls, cost| UsageBucket {
+            key: key.to_string(),
+            provider: "anthropic".to_string(),
+            model: "claude".to_string(),
+            calls,
+            input_tokens: calls * 1000,
+            output_tokens: calls * 100,
+            cost,
+            estimated_calls: 0,
+        };
+        let totals = totals(&[bucket("2026-10-14", 3, 0.25), bucket("2026-10-15", 1, 0.5)]);
+        assert_eq!((totals.calls, totals.input_tokens, totals.output_tokens), (4, 4000, 400));
+        assert!((totals.cost - 0.75).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_keeps_attribution() {
+        let attribution = Attribution { session_id: Some("session-1".to_string()), budget_owners: vec![("user".to_string(), "u1".to_string())] };
+        let spawned = attributed(attribution.clone(), async { spawn(async { ATTRIBUTION.try_with(Attribution::clone).ok() }).await.unwrap() }).await;
+        assert_eq!(spawned, Some(attribution));
+        assert_eq!(spawn(async { ATTRIBUTION.try_with(Attribution::clone).ok() }).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,228 @@
+//! Język i konwencje regionalne treści generowanych dla strony: atrybut `lang` strony i locale użytkownika
+//! (`locale` w `/dsl/generate`) trafiają do promptu, żeby list motywacyjny, odpowiedzi w polach tekstowych
+//! i formaty dat czy telefonów pasowały do witryny. Skrypty awaryjne klikają przyciski w języku strony.
+
+use scraper::{Html, Selector};
+
+use codialog_types::automation::PromptLanguage;
+
+/// Język strony i locale użytkownika (tagi BCP 47, np. `de-DE`, `pl`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocaleHint {
+    pub page_lang: Option<String>,
+    pub user_locale: Option<String>,
+}
+
+impl LocaleHint {
+    pub fn new(user_locale: Option<&str>) -> Self {
+        Self { page_lang: None, user_locale: user_locale.and_then(normalize_tag) }
+    }
+
+    /// Uzupełnia język strony z atrybutu `lang` jej HTML, jeśli nie był znany
+    pub fn with_page(mut self, html: &str) -> Self {
+        if self.page_lang.is_none() {
+            self.page_lang = page_language(html);
+        }
+        self
+    }
+
+    /// Locale, którego konwencji używa wypełniany formularz: strony, a bez `lang` - użytkownika
+    pub fn effective(&self) -> Option<&str> {
+        self.page_lang.as_deref().or(self.user_locale.as_deref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.page_lang.is_none() && self.user_locale.is_none()
+    }
+}
+
+/// Formaty wartości w danym regionie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conventions {
+    pub date_format: &'static str,
+    /// Numer kierunkowy kraju; brak, gdy region jest nieznany (np. samo `en`)
+    pub phone_prefix: Option<&'static str>,
+    pub decimal_separator: char,
+}
+
+/// `lang` elementu `<html>` (albo `xml:lang`), znormalizowany do postaci `de-DE`
+pub fn page_language(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("html").expect("valid selector");
+    let root = document.select(&selector).next()?;
+    root.value().attr("lang").or_else(|| root.value().attr("xml:lang")).and_then(normalize_tag)
+}
+
+/// `pl_pl.UTF-8` -> `pl-PL`; `None` dla pustych i niepoprawnych wartości
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().split('.').next().unwrap_or_default().replace('_', "-");
+    let mut parts = tag.split('-').filter(|part| !part.is_empty());
+    let language = parts.next().filter(|language| (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic()))?;
+    match parts.next().filter(|region| region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic())) {
+        Some(region) => Some(format!("{}-{}", language.to_lowercase(), region.to_uppercase())),
+        None => Some(language.to_lowercase()),
+    }
+}
+
+fn language_of(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// Region z tagu albo domyślny dla języka używanego głównie w jednym kraju
+fn region_of(tag: &str) -> Option<&str> {
+    tag.split_once('-').map(|(_, region)| region).or(match language_of(tag) {
+        "pl" => Some("PL"),
+        "de" => Some("DE"),
+        "fr" => Some("FR"),
+        "es" => Some("ES"),
+        "it" => Some("IT"),
+        "nl" => Some("NL"),
+        "cs" => Some("CZ"),
+        _ => None,
+    })
+}
+
+/// Numery kierunkowe krajów z `conventions` (bez `+`)
+pub const COUNTRY_CALLING_CODES: &[&str] = &["1", "31", "33", "34", "39", "41", "43", "44", "48", "49", "353", "420"];
+
+pub fn conventions(tag: &str) -> Conventions {
+    let region = region_of(tag);
+    let date_format = match (language_of(tag), region) {
+        (_, Some("US")) => "MM/DD/YYYY",
+        ("pl" | "de" | "cs", _) | (_, Some("CH" | "AT")) => "DD.MM.YYYY",
+        ("fr" | "es" | "it" | "en", _) => "DD/MM/YYYY",
+        ("nl", _) => "DD-MM-YYYY",
+        _ => "YYYY-MM-DD",
+    };
+    let phone_prefix = region.and_then(|region| match region {
+        "PL" => Some("+48"),
+        "DE" => Some("+49"),
+        "AT" => Some("+43"),
+        "CH" => Some("+41"),
+        "FR" => Some("+33"),
+        "ES" => Some("+34"),
+        "IT" => Some("+39"),
+        "NL" => Some("+31"),
+        "CZ" => Some("+420"),
+        "GB" | "UK" => Some("+44"),
+        "IE" => Some("+353"),
+        "US" | "CA" => Some("+1"),
+        _ => None,
+    });
+    let decimal_separator = match (language_of(tag), region) {
+        (_, Some("CH")) | ("en", _) => '.',
+        ("pl" | "de" | "fr" | "es" | "it" | "nl" | "cs", _) => ',',
+        _ => '.',
+    };
+    Conventions { date_format, phone_prefix, decimal_separator }
+}
+
+/// Sekcja promptu o języku treści i formatach wartości; pusta, gdy nic nie wiadomo o języku
+pub fn prompt_section(hint: &LocaleHint, language: PromptLanguage) -> String {
+    let Some(tag) = hint.effective() else {
+        return String::new();
+    };
+    let conventions = conventions(tag);
+    let phone = conventions.phone_prefix.map(|prefix| match language {
+        PromptLanguage::Pl => format!(", telefon z numerem kierunkowym {}", prefix),
+        PromptLanguage::En => format!(", phone numbers with the {} country code", prefix),
+    });
+    let user = hint.user_locale.as_deref().filter(|user| hint.page_lang.is_some() && language_of(user) != language_of(tag));
+    match language {
+        PromptLanguage::Pl => format!(
+            "\nJęzyk: strona jest w języku {}{}. Teksty wpisywane w pola (list motywacyjny, wiadomości, odpowiedzi na pytania otwarte) pisz w tym języku, a wartości formatuj według konwencji regionu: daty {}, separator dziesiętny \"{}\"{}.\n",
+            tag,
+            user.map(|user| format!(" (użytkownik: {})", user)).unwrap_or_default(),
+            conventions.date_format,
+            conventions.decimal_separator,
+            phone.unwrap_or_default()
+        ),
+        PromptLanguage::En => format!(
+            "\nLanguage: the page is in {}{}. Write text typed into fields (cover letter, messages, answers to open questions) in this language and format values by the regional conventions: dates {}, decimal separator \"{}\"{}.\n",
+            tag,
+            user.map(|user| format!(" (user: {})", user)).unwrap_or_default(),
+            conventions.date_format,
+            conventions.decimal_separator,
+            phone.unwrap_or_default()
+        ),
+    }
+}
+
+/// Przycisk, który klikają skrypty awaryjne
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Continue,
+    Accept,
+    Login,
+    Submit,
+}
+
+/// Teksty przycisku: angielski i, gdy strona jest w innym znanym języku, jego odpowiednik
+pub fn button_labels(button: Button, page_lang: Option<&str>) -> Vec<&'static str> {
+    let english = match button {
+        Button::Continue => "Continue",
+        Button::Accept => "Accept",
+        Button::Login => "Login",
+        Button::Submit => "Submit",
+    };
+    let localized = match (page_lang.map(language_of), button) {
+        (Some("pl"), Button::Continue) => Some("Dalej"),
+        (Some("pl"), Button::Accept) => Some("Akceptuję"),
+        (Some("pl"), Button::Login) => Some("Zaloguj"),
+        (Some("pl"), Button::Submit) => Some("Wyślij"),
+        (Some("de"), Button::Continue) => Some("Weiter"),
+        (Some("de"), Button::Accept) => Some("Akzeptieren"),
+        (Some("de"), Button::Login) => Some("Anmelden"),
+        (Some("de"), Button::Submit) => Some("Absenden"),
+        (Some("fr"), Button::Continue) => Some("Continuer"),
+        (Some("fr"), Button::Accept) => Some("Accepter"),
+        (Some("fr"), Button::Login) => Some("Se connecter"),
+        (Some("fr"), Button::Submit) => Some("Envoyer"),
+        (Some("es"), Button::Continue) => Some("Continuar"),
+        (Some("es"), Button::Accept) => Some("Aceptar"),
+        (Some("es"), Button::Login) => Some("Iniciar sesión"),
+        (Some("es"), Button::Submit) => Some("Enviar"),
+        _ => None,
+    };
+    let mut labels = vec![english];
+    labels.extend(localized);
+    labels
+}
+
+/// Bloki `if present "X"` / `click "X"` dla tekstów przycisku
+pub fn click_if_present(button: Button, page_lang: Option<&str>) -> String {
+    button_labels(button, page_lang)
+        .into_iter()
+        .map(|label| format!("if present \"{label}\"\nclick \"{label}\"\nend\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_hint_and_conventions() {
+        assert_eq!(page_language(r#"<html lang="de_de"><body></body></html>"#).as_deref(), Some("de-DE"));
+        assert_eq!(page_language("<html><body></body></html>"), None);
+        assert_eq!(normalize_tag("pl_PL.UTF-8").as_deref(), Some("pl-PL"));
+        assert_eq!(normalize_tag("  "), None);
+
+        let hint = LocaleHint::new(Some("pl-PL")).with_page(r#"<html lang="en-US"></html>"#);
+        assert_eq!(hint.effective(), Some("en-US"));
+        assert_eq!(conventions("en-US").date_format, "MM/DD/YYYY");
+        assert_eq!(conventions("de").phone_prefix, Some("+49"));
+        assert_eq!(conventions("en").phone_prefix, None);
+        assert_eq!(conventions("pl").decimal_separator, ',');
+
+        let section = prompt_section(&hint, PromptLanguage::En);
+        assert!(section.contains("the page is in en-US (user: pl-PL)"));
+        assert!(section.contains("dates MM/DD/YYYY"));
+        assert!(section.contains("+1 country code"));
+        assert_eq!(prompt_section(&LocaleHint::default(), PromptLanguage::Pl), "");
+
+        assert_eq!(button_labels(Button::Continue, Some("de-AT")), vec!["Continue", "Weiter"]);
+        assert_eq!(button_labels(Button::Submit, None), vec!["Submit"]);
+        assert!(click_if_present(Button::Login, Some("pl")).contains("if present \"Zaloguj\"\nclick \"Zaloguj\"\nend\n"));
+    }
+}
@@ -1,12 +1,18 @@
 use std::path::Path;
 use tracing::{info, error, debug};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+use tracing_subscriber::filter::{filter_fn, FilterExt, LevelFilter};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fs;
-use std::io::Result as IoResult;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use sqlx::PgPool;
 use anyhow::Result;
 
@@ -21,13 +27,244 @@ pub struct LogEntry {
 
 pub struct LogManager {
     log_dir: String,
+    component_logs: Vec<(&'static ComponentLog, ComponentLogSettings)>,
+    /// Liczniki linii z poprzednich statystyk; po dopisaniu do pliku liczone są tylko nowe bajty
+    line_counts: Mutex<HashMap<String, LineCount>>,
+    /// Lista plików katalogu logów, ważna do zmiany mtime katalogu
+    dir_listing: Mutex<Option<(SystemTime, Vec<String>)>>,
+}
+
+/// Liczba linii pliku logu przy danym rozmiarze i czasie modyfikacji
+#[derive(Debug, Clone, Copy)]
+struct LineCount {
+    size: u64,
+    modified: Option<SystemTime>,
+    newlines: u64,
+    last_byte: Option<u8>,
+}
+
+impl LineCount {
+    /// Niedokończona ostatnia linia też się liczy, jak w `str::lines`
+    fn lines(&self) -> u64 {
+        self.newlines + u64::from(self.last_byte.is_some_and(|byte| byte != b'\n'))
+    }
+}
+
+/// Komponent z własnym plikiem logu, wydzielony po prefiksach targetów `tracing`
+#[derive(Debug)]
+pub struct ComponentLog {
+    /// Typ logu w `read_logs`/`get_log_stats` i prefiks zmiennych `<NAME>_LOG_LEVEL`, `<NAME>_LOG_RETENTION_DAYS`
+    pub name: &'static str,
+    pub targets: &'static [&'static str],
+    pub defaults: ComponentLogSettings,
+}
+
+/// Poziom i retencja (liczba dziennych plików) logu komponentu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentLogSettings {
+    pub level: LevelFilter,
+    pub retention_days: usize,
+}
+
+/// Logi komponentów nie trafiają do app.log/debug.log; ostrzeżenia i błędy nadal lądują w error.log.
+/// Targety to ścieżki modułów: `codialog::` dla modułów binarki, `codialog_core::` dla biblioteki.
+pub const COMPONENT_LOGS: &[ComponentLog] = &[
+    ComponentLog {
+        name: "credentials",
+        targets: &[
+            "codialog::bitwarden",
+            "codialog::auth_guard",
+            "codialog::biometric",
+            "codialog::credential_import",
+            "codialog::key_rotation",
+            "codialog_core::secure_input",
+            "audit",
+        ],
+        defaults: ComponentLogSettings { level: LevelFilter::INFO, retention_days: 30 },
+    },
+    ComponentLog {
+        name: "browser",
+        targets: &["codialog_core::cdp", "codialog_core::page_session", "chromiumoxide"],
+        defaults: ComponentLogSettings { level: LevelFilter::INFO, retention_days: 7 },
+    },
+    ComponentLog {
+        name: "llm",
+        targets: &["codialog_core::llm", "codialog_core::llm_provider", "codialog_core::llm_usage"],
+        defaults: ComponentLogSettings { level: LevelFilter::INFO, retention_days: 7 },
+    },
+];
+
+fn matches_targets(target: &str, targets: &[&str]) -> bool {
+    targets.iter().any(|prefix| {
+        target == *prefix || target.strip_prefix(prefix).is_some_and(|rest| rest.starts_with("::"))
+    })
+}
+
+fn is_component_target(target: &str) -> bool {
+    COMPONENT_LOGS.iter().any(|component| matches_targets(target, component.targets))
+}
+
+/// Rolled-over appenders write `<name>.<date>`; prefer the plain file, else the newest rotation
+fn current_log_file(log_dir: &str, file_names: &[String], file_name: &str) -> String {
+    let plain = format!("{}/{}", log_dir, file_name);
+    if Path::new(&plain).exists() {
+        return plain;
+    }
+
+    let prefix = format!("{}.", file_name);
+    file_names
+        .iter()
+        .filter(|name| name.starts_with(&prefix) && !name.ends_with(".gz"))
+        .max()
+        .map(|name| format!("{}/{}", log_dir, name))
+        .unwrap_or(plain)
+}
+
+/// Liczy '\n' od `offset` do końca blokami `PAGE_CHUNK_BYTES`, bez wczytywania całego pliku
+fn count_newlines<R: Read + Seek>(reader: &mut R, offset: u64) -> IoResult<(u64, Option<u8>)> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut block = vec![0u8; PAGE_CHUNK_BYTES as usize];
+    let (mut newlines, mut last_byte) = (0u64, None);
+    loop {
+        let read = reader.read(&mut block)?;
+        if read == 0 {
+            return Ok((newlines, last_byte));
+        }
+        newlines += block[..read].iter().filter(|byte| **byte == b'\n').count() as u64;
+        last_byte = Some(block[read - 1]);
+    }
+}
+
+/// Rozmiar bloku czytanego od końca pliku przy stronicowaniu
+const PAGE_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Górny limit linii na stronę, niezależnie od parametru `limit`
+pub const MAX_PAGE_LINES: usize = 5000;
+
+/// Strona linii logu wraz z pozycjami bajtowymi w pliku
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogPage {
+    pub lines: Vec<String>,
+    /// Offset pierwszej zwróconej linii
+    pub start_offset: u64,
+    /// Offset tuż za ostatnią zwróconą linią; `after` dla kolejnego odczytu nowych wpisów
+    pub end_offset: u64,
+    pub file_size: u64,
+    /// Token następnej (starszej) strony; brak na początku pliku
+    pub next_cursor: Option<String>,
+}
+
+/// Kierunek odczytu strony logu
+#[derive(Debug, Clone)]
+pub enum PageRequest {
+    /// Ostatnie linie pliku
+    Latest,
+    /// Starsze linie przed pozycją zakodowaną w tokenie `next_cursor`
+    Before(String),
+    /// Nowe linie od offsetu (np. `end_offset` poprzedniej strony)
+    After(u64),
+}
+
+fn encode_cursor(log_type: &str, offset: u64) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", log_type, offset))
+}
+
+fn decode_cursor(log_type: &str, cursor: &str) -> IoResult<u64> {
+    let invalid = || IoError::new(ErrorKind::InvalidInput, "Invalid log cursor");
+    let decoded = URL_SAFE_NO_PAD.decode(cursor.trim()).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    match decoded.rsplit_once(':') {
+        Some((cursor_type, offset)) if cursor_type == log_type => offset.parse().map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Reads up to `limit` complete lines ending at byte `end`, seeking backwards in `chunk`-sized blocks.
+/// Returns the offset of the first returned line and the lines in file order.
+fn read_lines_before<R: Read + Seek>(reader: &mut R, end: u64, limit: usize, chunk: u64) -> IoResult<(u64, Vec<String>)> {
+    let mut start = end;
+    let mut buffer: Vec<u8> = Vec::new();
+    // Początki linii w buforze: po każdym '\n' (oraz 0, gdy bufor zaczyna się na początku pliku)
+    let line_starts = |buffer: &[u8], start: u64| -> Vec<usize> {
+        let mut starts: Vec<usize> = (start == 0).then_some(0).into_iter().collect();
+        starts.extend(
+            buffer.iter().enumerate()
+                .filter(|(index, byte)| **byte == b'\n' && index + 1 < buffer.len())
+                .map(|(index, _)| index + 1),
+        );
+        starts
+    };
+
+    while start > 0 && line_starts(&buffer, start).len() < limit {
+        let size = chunk.min(start);
+        start -= size;
+        reader.seek(SeekFrom::Start(start))?;
+        let mut block = vec![0u8; size as usize];
+        reader.read_exact(&mut block)?;
+        block.extend_from_slice(&buffer);
+        buffer = block;
+    }
+
+    let starts = line_starts(&buffer, start);
+    let Some(&first) = starts.get(starts.len().saturating_sub(limit)) else {
+        return Ok((end, Vec::new()));
+    };
+    let lines = buffer[first..]
+        .split(|byte| *byte == b'\n')
+        .map(|line| String::from_utf8_lossy(line).trim_end_matches('\r').to_string())
+        .collect::<Vec<_>>();
+    // Ostatni element po końcowym '\n' jest pusty
+    let lines = match lines.split_last() {
+        Some((last, rest)) if last.is_empty() => rest.to_vec(),
+        _ => lines,
+    };
+    Ok((start + first as u64, lines))
+}
+
+/// Czyta pełne linie od `offset`; niedokończona ostatnia linia zostanie zwrócona przy kolejnym odczycie
+fn read_lines_after<R: Read + Seek>(reader: &mut R, offset: u64, limit: usize) -> IoResult<(u64, Vec<String>)> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(reader);
+    let mut end = offset;
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+
+    while lines.len() < limit {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        end += read as u64;
+        lines.push(String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']).to_string());
+    }
+    Ok((end, lines))
 }
 
 impl LogManager {
     pub fn new(log_dir: &str) -> Self {
         Self {
             log_dir: log_dir.to_string(),
+            component_logs: COMPONENT_LOGS.iter().map(|component| (component, component.defaults)).collect(),
+            line_counts: Mutex::new(HashMap::new()),
+            dir_listing: Mutex::new(None),
+        }
+    }
+
+    /// Nadpisuje poziom/retencję komponentów (zwykle z `AppConfig::component_logs`)
+    pub fn with_component_settings(mut self, settings: &[(&str, ComponentLogSettings)]) -> Self {
+        for (component, current) in self.component_logs.iter_mut() {
+            if let Some((_, configured)) = settings.iter().find(|(name, _)| *name == component.name) {
+                *current = *configured;
+            }
         }
+        self
+    }
+
+    fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = ["app.log", "error.log", "debug.log", "tagui.log"].iter().map(|name| name.to_string()).collect();
+        names.extend(COMPONENT_LOGS.iter().map(|component| format!("{}.log", component.name)));
+        names
     }
 
     /// Inicjalizacja systemu logowania z zapisem do plików
@@ -54,9 +291,9 @@ impl LogManager {
             "debug.log"
         );
 
-        // Filtry dla różnych poziomów
-        let env_filter = EnvFilter::try_from_default_env()
-            .unwrap_or_else(|_| EnvFilter::new("info"));
+        // Filtry dla różnych poziomów; RUST_LOG obejmuje app/debug/konsolę, komponenty mają własne poziomy
+        let base_filter = || EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        let not_component = || filter_fn(|metadata| !is_component_target(metadata.target()));
 
         // Konfiguracja layerów
         let app_layer = tracing_subscriber::fmt::layer()
@@ -65,69 +302,181 @@ impl LogManager {
             .with_target(true)
             .with_thread_ids(true)
             .with_file(true)
-            .with_line_number(true);
+            .with_line_number(true)
+            .with_filter(base_filter().and(not_component()));
 
         let error_layer = tracing_subscriber::fmt::layer()
             .with_writer(error_file)
             .with_ansi(false)
-            .with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
+            .with_filter(filter_fn(|metadata| {
                 metadata.level() <= &tracing::Level::WARN
             }));
 
         let debug_layer = tracing_subscriber::fmt::layer()
             .with_writer(debug_file)
             .with_ansi(false)
-            .with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
-                metadata.level() <= &tracing::Level::DEBUG
-            }));
+            .with_filter(base_filter().and(LevelFilter::DEBUG).and(not_component()));
 
         let console_layer = tracing_subscriber::fmt::layer()
             .with_ansi(true)
-            .with_target(true);
+            .with_target(true)
+            .with_filter(base_filter());
+
+        let mut component_layers = Vec::new();
+        for (component, settings) in &self.component_logs {
+            let file = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(format!("{}.log", component.name))
+                .max_log_files(settings.retention_days.max(1))
+                .build(&self.log_dir)
+                .map_err(IoError::other)?;
+            let targets = component.targets;
+            let layer = tracing_subscriber::fmt::layer()
+                .with_writer(file)
+                .with_ansi(false)
+                .with_target(true)
+                .with_filter(settings.level.and(filter_fn(move |metadata| matches_targets(metadata.target(), targets))))
+                .boxed();
+            component_layers.push(layer);
+        }
 
         // Inicjalizacja subscriber
         tracing_subscriber::registry()
-            .with(env_filter)
             .with(app_layer)
             .with(error_layer)
             .with(debug_layer)
             .with(console_layer)
+            .with(component_layers)
             .init();
 
         info!("Sistema logowania został zainicjalizowany");
         info!("Logi zapisywane do katalogu: {}", self.log_dir);
+        for (component, settings) in &self.component_logs {
+            info!("Log komponentu {}: poziom {}, retencja {} dni", component.name, settings.level, settings.retention_days);
+        }
         
         Ok(())
     }
 
+    /// Nazwy plików katalogu logów; `read_dir` tylko wtedy, gdy zmienił się mtime katalogu (nowy plik po rotacji)
+    fn dir_entries(&self) -> Vec<String> {
+        let modified = fs::metadata(&self.log_dir).and_then(|metadata| metadata.modified()).ok();
+        let mut listing = self.dir_listing.lock().unwrap();
+        if let (Some(modified), Some((cached_at, names))) = (modified, listing.as_ref()) {
+            if *cached_at == modified {
+                return names.clone();
+            }
+        }
+        let names: Vec<String> = fs::read_dir(&self.log_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        *listing = modified.map(|modified| (modified, names.clone()));
+        names
+    }
+
+    fn current_log_file(&self, file_name: &str) -> String {
+        current_log_file(&self.log_dir, &self.dir_entries(), file_name)
+    }
+
+    fn log_file_path(&self, log_type: &str) -> Option<String> {
+        let file_name = format!("{}.log", log_type);
+        self.file_names()
+            .contains(&file_name)
+            .then(|| self.current_log_file(&file_name))
+    }
+
+    /// Liczba linii pliku: bez zmian rozmiaru i mtime - z pamięci, po dopisaniu - tylko nowe bajty,
+    /// a plik mniejszy niż ostatnio (nowy plik pod tą samą nazwą) liczony jest od początku
+    fn line_count(&self, path: &str, metadata: &fs::Metadata) -> IoResult<u64> {
+        let (size, modified) = (metadata.len(), metadata.modified().ok());
+        let mut counts = self.line_counts.lock().unwrap();
+        let count = match counts.get(path).copied() {
+            Some(cached) if cached.size == size && cached.modified == modified => cached,
+            Some(cached) if cached.size < size => {
+                let (newlines, last_byte) = count_newlines(&mut fs::File::open(path)?, cached.size)?;
+                LineCount { size, modified, newlines: cached.newlines + newlines, last_byte: last_byte.or(cached.last_byte) }
+            }
+            _ => {
+                let (newlines, last_byte) = count_newlines(&mut fs::File::open(path)?, 0)?;
+                LineCount { size, modified, newlines, last_byte }
+            }
+        };
+        counts.insert(path.to_string(), count);
+        Ok(count.lines())
+    }
+
     /// Odczyt logów z pliku
     pub fn read_logs(&self, log_type: &str, lines: Option<usize>) -> IoResult<Vec<String>> {
-        let file_path = match log_type {
-            "app" => format!("{}/app.log", self.log_dir),
-            "error" => format!("{}/error.log", self.log_dir),
-            "debug" => format!("{}/debug.log", self.log_dir),
-            "tagui" => format!("{}/tagui.log", self.log_dir),
-            _ => return Ok(vec!["Nieznany typ logu".to_string()]),
+        let Some(file_path) = self.log_file_path(log_type) else {
+            return Ok(vec!["Nieznany typ logu".to_string()]);
         };
 
         if !Path::new(&file_path).exists() {
             return Ok(vec![format!("Plik logu {} nie istnieje", file_path)]);
         }
 
+        // Ostatnie N linii bez wczytywania całego pliku
+        if let Some(n) = lines {
+            let mut file = fs::File::open(&file_path)?;
+            let end = file.metadata()?.len();
+            return read_lines_before(&mut file, end, n, PAGE_CHUNK_BYTES).map(|(_, lines)| lines);
+        }
+
         let content = fs::read_to_string(&file_path)?;
-        let mut log_lines: Vec<String> = content
-            .lines()
-            .map(|line| line.to_string())
-            .collect();
+        Ok(content.lines().map(|line| line.to_string()).collect())
+    }
 
-        // Zwróć ostatnie N linii jeśli określono
-        if let Some(n) = lines {
-            if log_lines.len() > n {
-                log_lines = log_lines.split_off(log_lines.len() - n);
+    /// Stronicowanie dużych plików logów po offsetach bajtowych.
+    /// Pages stay stable while the file grows; a cursor past the end (after truncation) is rejected.
+    pub fn read_log_page(&self, log_type: &str, request: &PageRequest, limit: usize) -> IoResult<LogPage> {
+        let file_path = self
+            .log_file_path(log_type)
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("Unknown log type: {}", log_type)))?;
+        let limit = limit.clamp(1, MAX_PAGE_LINES);
+
+        let mut file = match fs::File::open(&file_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok(LogPage { lines: Vec::new(), start_offset: 0, end_offset: 0, file_size: 0, next_cursor: None });
             }
-        }
+            Err(e) => return Err(e),
+        };
+        let file_size = file.metadata()?.len();
 
-        Ok(log_lines)
+        let (start_offset, end_offset, lines) = match request {
+            PageRequest::After(offset) if *offset > file_size => {
+                return Err(IoError::new(ErrorKind::InvalidInput, "Offset is past the end of the log (file was rotated?)"));
+            }
+            PageRequest::After(offset) => {
+                let (end, lines) = read_lines_after(&mut file, *offset, limit)?;
+                (*offset, end, lines)
+            }
+            PageRequest::Latest | PageRequest::Before(_) => {
+                let end = match request {
+                    PageRequest::Before(cursor) => decode_cursor(log_type, cursor)?,
+                    _ => file_size,
+                };
+                if end > file_size {
+                    return Err(IoError::new(ErrorKind::InvalidInput, "Cursor is past the end of the log (file was rotated?)"));
+                }
+                let (start, lines) = read_lines_before(&mut file, end, limit, PAGE_CHUNK_BYTES)?;
+                (start, end, lines)
+            }
+        };
+
+        debug!("Log page {} [{}..{}) of {} bytes", log_type, start_offset, end_offset, file_size);
+        Ok(LogPage {
+            lines,
+            start_offset,
+            end_offset,
+            file_size,
+            next_cursor: (start_offset > 0).then(|| encode_cursor(log_type, start_offset)),
+        })
     }
 
     /// Wyczyść stare logi
@@ -170,17 +519,13 @@ impl LogManager {
     pub fn get_log_stats(&self) -> IoResult<serde_json::Value> {
         let mut stats = serde_json::Map::new();
         
-        let log_files = ["app.log", "error.log", "debug.log", "tagui.log"];
-        
-        for file in &log_files {
-            let path = format!("{}/{}", self.log_dir, file);
+        for file in &self.file_names() {
+            let path = self.current_log_file(file);
             
             if Path::new(&path).exists() {
                 if let Ok(metadata) = fs::metadata(&path) {
                     let size = metadata.len();
-                    let lines = fs::read_to_string(&path)
-                        .map(|content| content.lines().count())
-                        .unwrap_or(0);
+                    let lines = self.line_count(&path, &metadata).unwrap_or(0);
                     
                     let mut file_stats = serde_json::Map::new();
                     file_stats.insert("size_bytes".to_string(), serde_json::Value::from(size));
@@ -278,3 +623,116 @@ pub async fn get_logs_by_component(
 
     Ok(rows.into_iter().map(|row| row.0).collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_component_targets_match_module_paths() {
+        let component = |target: &str| COMPONENT_LOGS.iter().find(|log| matches_targets(target, log.targets)).map(|log| log.name);
+        // Ścieżki modułów biblioteki biorą nazwę z module_path!, binarki - z nazwy pakietu
+        let core = module_path!().split("::").next().unwrap();
+        let bin = env!("CARGO_PKG_NAME");
+        assert_eq!(component(&format!("{}::cdp", core)), Some("browser"));
+        assert_eq!(component(&format!("{}::llm_provider", core)), Some("llm"));
+        assert_eq!(component(&format!("{}::secure_input", core)), Some("credentials"));
+        assert_eq!(component(&format!("{}::bitwarden", bin)), Some("credentials"));
+        assert_eq!(component(&format!("{}::tagui", core)), None);
+
+        for log in COMPONENT_LOGS {
+            for target in log.targets.iter().filter(|target| target.contains("::")) {
+                let krate = target.split("::").next().unwrap();
+                assert!(krate == core || krate == bin, "{} does not name a module of this package", target);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_paging_with_offsets() {
+        let content = "one\ntwo\nthree\nfour\nfive\n";
+        let mut reader = Cursor::new(content.as_bytes().to_vec());
+        let end = content.len() as u64;
+
+        // Mały blok wymusza kilka odczytów przez granice linii
+        let (start, lines) = read_lines_before(&mut reader, end, 2, 3).unwrap();
+        assert_eq!(lines, vec!["four", "five"]);
+        assert_eq!(&content[start as usize..], "four\nfive\n");
+
+        let (older, lines) = read_lines_before(&mut reader, start, 10, 3).unwrap();
+        assert_eq!(lines, vec!["one", "two", "three"]);
+        assert_eq!(older, 0);
+
+        // Niedokończona linia czeka na kolejny odczyt
+        let mut growing = Cursor::new(format!("{}six\nsev", content).into_bytes());
+        let (next, lines) = read_lines_after(&mut growing, end, 10).unwrap();
+        assert_eq!(lines, vec!["six"]);
+        assert_eq!(next, end + 4);
+
+        let cursor = encode_cursor("debug", start);
+        assert_eq!(decode_cursor("debug", &cursor).unwrap(), start);
+        assert!(decode_cursor("app", &cursor).is_err());
+    }
+
+    #[test]
+    fn test_read_log_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LogManager::new(dir.path().to_str().unwrap());
+        let content: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        fs::write(dir.path().join("debug.log"), &content).unwrap();
+
+        let page = manager.read_log_page("debug", &PageRequest::Latest, 4).unwrap();
+        assert_eq!(page.lines.first().map(String::as_str), Some("line 7"));
+        assert_eq!(page.end_offset, content.len() as u64);
+
+        let older = manager.read_log_page("debug", &PageRequest::Before(page.next_cursor.unwrap()), 100).unwrap();
+        assert_eq!(older.lines.len(), 6);
+        assert!(older.next_cursor.is_none());
+
+        assert!(manager.read_log_page("debug", &PageRequest::After(content.len() as u64 + 1), 10).is_err());
+        assert_eq!(manager.read_logs("debug", Some(2)).unwrap(), vec!["line 9", "line 10"]);
+    }
+
+    #[test]
+    fn test_component_log_files() {
+        assert!(matches_targets("codialog::bitwarden", &["codialog::bitwarden"]));
+        assert!(matches_targets("chromiumoxide::handler", &["chromiumoxide"]));
+        assert!(!matches_targets("codialog::llm_cache", &["codialog::llm"]));
+        assert!(is_component_target("audit"));
+        assert!(!is_component_target("codialog::session"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LogManager::new(dir.path().to_str().unwrap());
+        fs::write(dir.path().join("llm.log.2026-01-01"), "old\n").unwrap();
+        fs::write(dir.path().join("llm.log.2026-01-02"), "request\nresponse\n").unwrap();
+
+        assert_eq!(manager.read_logs("llm", Some(1)).unwrap(), vec!["response"]);
+        assert!(manager.log_file_path("unknown").is_none());
+
+        let stats = manager.get_log_stats().unwrap();
+        assert_eq!(stats["llm"]["lines"], 2);
+        assert!(stats.get("credentials").is_none());
+    }
+
+    #[test]
+    fn test_log_stats_count_only_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = LogManager::new(dir.path().to_str().unwrap());
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\ntwo\nthr").unwrap();
+        assert_eq!(manager.get_log_stats().unwrap()["app"]["lines"], 3);
+
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"ee\nfour\n").unwrap();
+        let stats = manager.get_log_stats().unwrap();
+        assert_eq!(stats["app"]["lines"], 4);
+        assert_eq!(stats["app"]["size_bytes"], 19);
+
+        // Nowy, krótszy plik pod tą samą nazwą jest liczony od początku
+        fs::write(&path, "fresh\n").unwrap();
+        assert_eq!(manager.get_log_stats().unwrap()["app"]["lines"], 1);
+
+        let mut reader = Cursor::new(b"a\nb\nc".to_vec());
+        assert_eq!(count_newlines(&mut reader, 2).unwrap(), (1, Some(b'c')));
+    }
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context, bail};
+use tracing::info;
+use chrono::{DateTime, Duration, Utc};
+
+use codialog_types::system::MaintenanceWindowRequest;
+
+/// Najdłuższe dopuszczalne okno - zamrożenie zmian musi mieć koniec
+pub const MAX_WINDOW_DAYS: i64 = 14;
+
+/// Okno serwisowe, w którym workery nie pobierają zadań z kolejki (zamrożenie zmian, prace na stronie)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub id: String,
+    pub name: String,
+    pub reason: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+}
+
+/// Okno obejmujące `now`; z nakładających się to, które kończy się najpóźniej
+pub fn active_window(windows: &[MaintenanceWindow], now: DateTime<Utc>) -> Option<&MaintenanceWindow> {
+    windows.iter().filter(|window| window.is_active_at(now)).max_by_key(|window| window.ends_at)
+}
+
+/// Sprawdza nazwę i granice okna przed zapisaniem
+pub fn validate(request: &MaintenanceWindowRequest, now: DateTime<Utc>) -> Result<()> {
+    if request.name.trim().is_empty() {
+        bail!("Maintenance window name must not be empty");
+    }
+    if request.ends_at <= request.starts_at {
+        bail!("Maintenance window must end after it starts");
+    }
+    if request.ends_at <= now {
+        bail!("Maintenance window has already ended");
+    }
+    if request.ends_at - request.starts_at > Duration::days(MAX_WINDOW_DAYS) {
+        bail!("Maintenance window must not be longer than {} days", MAX_WINDOW_DAYS);
+    }
+    Ok(())
+}
+
+/// Okna serwisowe zapisane w PostgreSQL, wspólne dla wszystkich workerów
+#[derive(Debug, Clone)]
+pub struct MaintenanceSchedule {
+    db_pool: PgPool,
+}
+
+impl MaintenanceSchedule {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę okien serwisowych
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing maintenance windows table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS maintenance_windows (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                name VARCHAR(255) NOT NULL,
+                reason TEXT,
+                starts_at TIMESTAMPTZ NOT NULL,
+                ends_at TIMESTAMPTZ NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                CHECK (ends_at > starts_at)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_maintenance_windows_ends_at ON maintenance_windows(ends_at);
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create maintenance_windows table")?;
+
+        Ok(())
+    }
+
+    pub async fn create(&self, request: &MaintenanceWindowRequest) -> Result<MaintenanceWindow> {
+        validate(request, Utc::now())?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO maintenance_windows (name, reason, starts_at, ends_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id::text AS id, name, reason, starts_at, ends_at, created_at
+            "#,
+        )
+        .bind(request.name.trim())
+        .bind(&request.reason)
+        .bind(request.starts_at)
+        .bind(request.ends_at)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to create maintenance window")?;
+
+        let window = window_from_row(&row);
+        info!(window_id = %window.id, starts_at = %window.starts_at, ends_at = %window.ends_at, "Maintenance window scheduled: {}", window.name);
+        Ok(window)
+    }
+
+    /// Trwające i przyszłe okna (z `include_past` także zakończone), od najwcześniejszego
+    pub async fn list(&self, include_past: bool) -> Result<Vec<MaintenanceWindow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id::text AS id, name, reason, starts_at, ends_at, created_at
+            FROM maintenance_windows
+            WHERE $1 OR ends_at > NOW()
+            ORDER BY starts_at
+            "#,
+        )
+        .bind(include_past)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to list maintenance windows")?;
+
+        Ok(rows.iter().map(window_from_row).collect())
+    }
+
+    /// Okno trwające teraz według zegara bazy, żeby wszystkie workery zgadzały się co do granic
+    pub async fn active(&self) -> Result<Option<MaintenanceWindow>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id::text AS id, name, reason, starts_at, ends_at, created_at
+            FROM maintenance_windows
+            WHERE starts_at <= NOW() AND ends_at > NOW()
+            ORDER BY ends_at DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to check maintenance windows")?;
+
+        Ok(row.map(|row| window_from_row(&row)))
+    }
+
+    /// Usuwa okno, także trwające - odroczone zadania ruszają przy następnym odpytaniu kolejki
+    pub async fn delete(&self, window_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM maintenance_windows WHERE id = $1::uuid")
+            .bind(window_id)
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to delete maintenance window")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn window_from_row(row: &sqlx::postgres::PgRow) -> MaintenanceWindow {
+    MaintenanceWindow {
+        id: row.get("id"),
+        name: row.get("name"),
+        reason: row.get("reason"),
+        starts_at: row.get("starts_at"),
+        ends_at: row.get("ends_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(name: &str, starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> MaintenanceWindow {
+        MaintenanceWindow { id: name.to_string(), name: name.to_string(), reason: None, starts_at, ends_at, created_at: starts_at }
+    }
+
+    #[test]
+    fn test_active_window_and_validation() {
+        let now = Utc::now();
+        let windows = vec![
+            window("freeze", now - Duration::hours(1), now + Duration::hours(2)),
+            window("site", now - Duration::minutes(5), now + Duration::hours(5)),
+            window("later", now + Duration::hours(6), now + Duration::hours(7)),
+        ];
+        assert_eq!(active_window(&windows, now).map(|window| window.name.as_str()), Some("site"));
+        assert!(active_window(&windows, now + Duration::minutes(330)).is_none());
+        // Koniec okna jest wyłączny
+        assert!(!windows[2].is_active_at(now + Duration::hours(7)));
+
+        let request = |starts_at, ends_at| MaintenanceWindowRequest { name: "freeze".to_string(), reason: None, starts_at, ends_at };
+        assert!(validate(&request(now, now + Duration::hours(8)), now).is_ok());
+        assert!(validate(&request(now + Duration::hours(1), now), now).is_err());
+        assert!(validate(&request(now - Duration::hours(2), now - Duration::hours(1)), now).is_err());
+        assert!(validate(&request(now, now + Duration::days(MAX_WINDOW_DAYS + 1)), now).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Profile tempa wykonania (`fast` / `normal` / `human`) wspólne dla TagUI i silnika CDP.
+//! Some sites only run their validation on realistic typing, so the `human` profile types one
+//! keystroke at a time with jittered delays and waits a random while between steps.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub use codialog_types::automation::PacingProfile;
+
+/// Przerwa między krokami w profilu `human` (milisekundy, od-do)
+const HUMAN_STEP_DELAY_MS: (u64, u64) = (600, 1800);
+
+/// Przerwa między znakami w profilu `human` (milisekundy, od-do)
+const HUMAN_KEYSTROKE_DELAY_MS: (u64, u64) = (60, 220);
+
+/// Źródło opóźnień jednego przebiegu. Jitter comes from a small xorshift generator so that
+/// tests can fix the seed; it does not need to be cryptographically random.
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    profile: PacingProfile,
+    state: u64,
+}
+
+impl Pacer {
+    pub fn new(profile: PacingProfile) -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_nanos() as u64).unwrap_or_default();
+        Self::with_seed(profile, seed)
+    }
+
+    pub fn with_seed(profile: PacingProfile, seed: u64) -> Self {
+        // xorshift nie może startować od zera
+        Self { profile, state: seed | 1 }
+    }
+
+    pub fn profile(&self) -> PacingProfile {
+        self.profile
+    }
+
+    /// Czy tekst komendy `type` jest wpisywany znak po znaku
+    pub fn types_by_keystroke(&self) -> bool {
+        self.profile == PacingProfile::Human
+    }
+
+    /// Przerwa po wykonanym kroku; zero poza profilem `human`
+    pub fn step_delay(&mut self) -> Duration {
+        match self.profile {
+            PacingProfile::Human => self.between(HUMAN_STEP_DELAY_MS),
+            PacingProfile::Fast | PacingProfile::Normal => Duration::ZERO,
+        }
+    }
+
+    /// Przerwa po wpisanym znaku; zero poza profilem `human`
+    pub fn keystroke_delay(&mut self) -> Duration {
+        match self.profile {
+            PacingProfile::Human => self.between(HUMAN_KEYSTROKE_DELAY_MS),
+            PacingProfile::Fast | PacingProfile::Normal => Duration::ZERO,
+        }
+    }
+
+    fn between(&mut self, (low, high): (u64, u64)) -> Duration {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        Duration::from_millis(low + self.state % (high - low + 1))
+    }
+}
+
+/// Dzieli tekst na pojedyncze znaki, zostawiając klawisze TagUI (`[enter]`, `[tab]`...) w całości
+pub fn keystrokes(text: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let key_length = match (first, rest.find(']')) {
+            ('[', Some(end)) if end > 1 && rest[1..end].chars().all(|c| c.is_ascii_alphanumeric()) => end + 1,
+            _ => first.len_utf8(),
+        };
+        keys.push(rest[..key_length].to_string());
+        rest = &rest[key_length..];
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_pacing_is_jittered_within_bounds() {
+        let mut pacer = Pacer::with_seed(PacingProfile::Human, 42);
+        let delays: Vec<Duration> = (0..50).map(|_| pacer.keystroke_delay()).collect();
+        assert!(delays.iter().all(|delay| (60..=220).contains(&(delay.as_millis() as u64))));
+        assert!(delays.windows(2).any(|pair| pair[0] != pair[1]));
+        assert!((600..=1800).contains(&(pacer.step_delay().as_millis() as u64)));
+
+        let mut fast = Pacer::with_seed(PacingProfile::Fast, 42);
+        assert_eq!(fast.step_delay(), Duration::ZERO);
+        assert!(!fast.types_by_keystroke());
+
+        assert_eq!(keystrokes("Jó[enter]"), vec!["J", "ó", "[enter]"]);
+        assert_eq!(keystrokes("[a b]"), vec!["[", "a", " ", "b", "]"]);
+    }
+}
@@ -0,0 +1,335 @@
+//! Rozpoznawanie rodzaju strony (logowanie, rejestracja, aplikacja o pracę, płatność, ankieta) z pól
+//! formularza, żeby UI mógł sam wybrać szablon danych. Heurystyki korzystają z `FormAnalyzer`; gdy wynik
+//! jest niepewny, a żądanie na to pozwala, rodzaj wskazuje model na podstawie samych etykiet pól (bez wartości).
+
+use scraper::{Html, Selector};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use tracing::{debug, info, warn};
+
+use codialog_types::automation::FormType;
+
+use crate::dom::{self, FormField};
+use crate::faults::{self, FaultTarget};
+use crate::generators::platforms;
+use crate::llm::FormAnalyzer;
+use crate::perf::{self, OperationKind};
+use crate::{llm_provider, llm_usage, privacy, replay};
+
+/// Poniżej tej pewności heurystyki żądanie z `llm` pyta model
+pub const LLM_ASSIST_BELOW: f32 = 0.6;
+
+/// Najmniejszy wynik, przy którym strona dostaje rodzaj inny niż `other`
+const MIN_SCORE: f32 = 1.0;
+
+/// Wynik, od którego przewaga nad innymi rodzajami daje pełną pewność
+const CONFIDENT_SCORE: f32 = 3.0;
+
+/// Limit tokenów odpowiedzi modelu - wystarcza na jedną etykietę
+const LLM_MAX_TOKENS: u32 = 16;
+
+/// Pola opisane w prompcie modelu
+const LLM_MAX_FIELDS: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageKind {
+    Login,
+    Registration,
+    JobApplication,
+    Checkout,
+    Survey,
+    Other,
+}
+
+impl PageKind {
+    pub const ALL: [PageKind; 6] =
+        [PageKind::Login, PageKind::Registration, PageKind::JobApplication, PageKind::Checkout, PageKind::Survey, PageKind::Other];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "login" | "sign_in" => Some(PageKind::Login),
+            "registration" | "register" | "signup" | "sign_up" => Some(PageKind::Registration),
+            "job_application" | "job" | "application" => Some(PageKind::JobApplication),
+            "checkout" | "payment" => Some(PageKind::Checkout),
+            "survey" | "questionnaire" => Some(PageKind::Survey),
+            "other" => Some(PageKind::Other),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PageKind::Login => "login",
+            PageKind::Registration => "registration",
+            PageKind::JobApplication => "job_application",
+            PageKind::Checkout => "checkout",
+            PageKind::Survey => "survey",
+            PageKind::Other => "other",
+        }
+    }
+
+    /// Szablon promptu i danych dla rodzaju strony
+    pub fn form_type(&self) -> FormType {
+        match self {
+            PageKind::Registration => FormType::Registration,
+            PageKind::JobApplication => FormType::JobApplication,
+            PageKind::Checkout => FormType::Checkout,
+            PageKind::Login | PageKind::Survey | PageKind::Other => FormType::Generic,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassificationSource {
+    Heuristic,
+    Llm,
+}
+
+/// Pole wymagane przez formularz
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RequiredField {
+    pub selector: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Typ `input` albo tag (`select`, `textarea`)
+    pub field_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KindScore {
+    pub kind: PageKind,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageClassification {
+    pub kind: PageKind,
+    /// Pewność heurystyki (0-1); przy `source: llm` rodzaj wskazał model
+    pub confidence: f32,
+    pub source: ClassificationSource,
+    /// Szablon promptu i danych do `/dsl/generate`
+    pub form_type: FormType,
+    pub required_fields: Vec<RequiredField>,
+    pub multi_step: bool,
+    /// Rozpoznana platforma rekrutacyjna (ATS)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<&'static str>,
+    /// Wyniki heurystyk dla rodzajów z niezerowym wynikiem, od najwyższego
+    pub scores: Vec<KindScore>,
+}
+
+/// Tekst pola do dopasowania słów: id, name, etykieta i tekst przycisku
+fn describe(field: &FormField) -> String {
+    [&field.id, &field.name, &field.label, &field.text]
+        .iter()
+        .filter_map(|value| value.as_deref())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Tytuł, nagłówki i legendy strony małymi literami
+fn headings(html: &str) -> String {
+    let selector = Selector::parse("title, h1, h2, h3, legend").expect("valid selector");
+    let document = Html::parse_document(html);
+    document.select(&selector).map(|element| dom::normalize(&element.text().collect::<String>())).collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Wyniki heurystyk dla każdego rodzaju strony
+fn score(html: &str, site: Option<&str>, fields: &[FormField], analyzer: &FormAnalyzer) -> Vec<KindScore> {
+    let descriptions: Vec<String> = fields.iter().map(describe).collect();
+    let headings = headings(html);
+    let in_fields = |words: &[&str]| descriptions.iter().any(|text| words.iter().any(|word| text.contains(word)));
+    let in_page = |words: &[&str]| in_fields(words) || words.iter().any(|word| headings.contains(word));
+    let bonus = |matched: bool, points: f32| if matched { points } else { 0.0 };
+
+    let passwords = fields.iter().filter(|field| field.element_type.as_deref() == Some("password")).count();
+    let has_file = fields.iter().any(|field| field.element_type.as_deref() == Some("file"));
+    let radio_groups: HashSet<&str> = fields
+        .iter()
+        .filter(|field| field.element_type.as_deref() == Some("radio"))
+        .filter_map(|field| field.name.as_deref())
+        .collect();
+    let inputs = fields.iter().filter(|field| !matches!(field.element_type.as_deref(), Some("submit" | "button" | "reset"))).count();
+
+    let login = bonus(analyzer.is_login_form() && passwords == 1, 2.0)
+        + bonus(in_page(&["log in", "login", "sign in", "zaloguj", "forgot", "nie pamiętasz"]), 1.0)
+        - bonus(inputs > 5, 1.0);
+    let registration = bonus(passwords >= 2, 2.0)
+        + bonus(in_page(&["register", "sign up", "create account", "confirm", "repeat", "rejestr", "załóż konto", "powtórz"]), 1.5)
+        + bonus(in_fields(&["terms", "regulamin"]), 0.5);
+    let job_application = bonus(platforms::detect(html, site).is_some(), 3.0)
+        + bonus(in_fields(&["resume", "cv", "cover_letter", "cover-letter", "cover letter", "list motywacyjny"]), 2.0)
+        + bonus(has_file, 1.0)
+        + bonus(in_page(&["apply", "aplikuj", "linkedin", "job application"]), 1.0);
+    let checkout = bonus(in_fields(&["card", "cc-number", "cvv", "cvc", "karty"]), 2.5)
+        + bonus(in_fields(&["billing", "shipping", "postal", "zip", "dostaw"]), 1.0)
+        + bonus(in_page(&["payment", "checkout", "place order", "płatno", "zamówienie"]), 0.5);
+    let survey = bonus(radio_groups.len() >= 3, 1.5)
+        + bonus(in_page(&["survey", "questionnaire", "feedback", "satisf", "ankiet", "opinia"]), 1.5)
+        + bonus(fields.iter().any(|field| field.tag == "textarea") && !radio_groups.is_empty(), 0.5);
+
+    let mut scores: Vec<KindScore> = [
+        (PageKind::Login, login),
+        (PageKind::Registration, registration),
+        (PageKind::JobApplication, job_application),
+        (PageKind::Checkout, checkout),
+        (PageKind::Survey, survey),
+    ]
+    .into_iter()
+    .filter(|(_, score)| *score > 0.0)
+    .map(|(kind, score)| KindScore { kind, score })
+    .collect();
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scores
+}
+
+/// Rodzaj i pewność z wyników: przewaga najlepszego nad sumą, osłabiona przy niskim wyniku
+fn pick(scores: &[KindScore]) -> (PageKind, f32) {
+    let Some(best) = scores.first().filter(|best| best.score >= MIN_SCORE) else {
+        return (PageKind::Other, 0.0);
+    };
+    let total: f32 = scores.iter().map(|score| score.score).sum();
+    let confidence = best.score / total * (best.score / CONFIDENT_SCORE).min(1.0);
+    (best.kind, (confidence * 100.0).round() / 100.0)
+}
+
+/// Klasyfikacja samymi heurystykami
+pub fn classify(html: &str, site: Option<&str>) -> PageClassification {
+    let fields = dom::form_fields(html);
+    let analyzer = FormAnalyzer::new(html);
+    let scores = score(html, site, &fields, &analyzer);
+    let (kind, confidence) = pick(&scores);
+
+    let required_fields = fields
+        .iter()
+        .filter(|field| field.required && !matches!(field.element_type.as_deref(), Some("submit" | "button" | "reset")))
+        .map(|field| RequiredField {
+            selector: field.selector(),
+            label: field.label.as_ref().map(|label| label.trim_end_matches('*').trim().to_string()),
+            field_type: field.element_type.clone().unwrap_or_else(|| field.tag.clone()),
+            frame: field.frame.clone(),
+            step: field.step,
+        })
+        .collect();
+
+    debug!(kind = kind.as_str(), confidence, "Page classified by heuristics");
+    PageClassification {
+        kind,
+        confidence,
+        source: ClassificationSource::Heuristic,
+        form_type: kind.form_type(),
+        required_fields,
+        multi_step: analyzer.is_multi_step(),
+        platform: platforms::detect(html, site).map(|platform| platform.as_str()),
+        scores,
+    }
+}
+
+/// Klasyfikacja heurystykami, a przy pewności poniżej `LLM_ASSIST_BELOW` i `use_llm` - modelem.
+/// Błąd lub nieczytelna odpowiedź modelu zostawia wynik heurystyki.
+pub async fn classify_with_assist(html: &str, site: Option<&str>, use_llm: bool) -> PageClassification {
+    let mut classification = classify(html, site);
+    if !use_llm || classification.confidence >= LLM_ASSIST_BELOW || !privacy::allows_llm() {
+        return classification;
+    }
+    match ask_model(html).await {
+        Ok(Some(kind)) => {
+            info!(heuristic = classification.kind.as_str(), llm = kind.as_str(), "Page classified with LLM assist");
+            classification.kind = kind;
+            classification.form_type = kind.form_type();
+            classification.source = ClassificationSource::Llm;
+        }
+        Ok(None) => debug!("LLM gave no usable page classification"),
+        Err(e) => warn!("LLM page classification failed: {}", e),
+    }
+    classification
+}
+
+/// Prompt z nagłówków i opisów pól - bez wartości wpisanych w formularz
+fn classification_prompt(html: &str) -> String {
+    let fields: Vec<String> = dom::form_fields(html)
+        .iter()
+        .take(LLM_MAX_FIELDS)
+        .map(|field| {
+            let kind = field.element_type.as_deref().unwrap_or(&field.tag);
+            let required = if field.required { " (required)" } else { "" };
+            format!("- {} {}{}", kind, describe(field), required)
+        })
+        .collect();
+    let labels: Vec<&str> = PageKind::ALL.iter().map(|kind| kind.as_str()).collect();
+    format!(
+        "Classify the web page by its form. Answer with exactly one label: {}.\n\nHeadings: {}\nFields:\n{}",
+        labels.join(", "),
+        headings(html),
+        fields.join("\n")
+    )
+}
+
+async fn ask_model(html: &str) -> anyhow::Result<Option<PageKind>> {
+    let provider = llm_provider::current();
+    if !provider.is_configured() {
+        return Ok(None);
+    }
+    let prompt = classification_prompt(html);
+    let replay_key = replay::content_key("classify", &prompt);
+    let body: Value = replay::intercept(replay::InteractionKind::LlmResponse, &replay_key, || async {
+        faults::inject(FaultTarget::Llm).await?;
+        let request = provider.complete(&prompt, LLM_MAX_TOKENS);
+        perf::timed(OperationKind::LlmCall, provider.operation(), json!({ "classify": true }), request).await
+    })
+    .await?;
+    let answer = provider.response_text(&body).unwrap_or_default();
+    llm_usage::record(provider.as_ref(), "classify", provider.token_usage(&body), &prompt, answer);
+    Ok(answer.split(|c: char| !c.is_alphanumeric() && c != '_').find_map(PageKind::parse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_common_page_kinds() {
+        let login = r#"<h1>Sign in</h1><form>
+            <input id="email" type="email" required><input id="password" type="password" required>
+            <button type="submit">Log in</button></form>"#;
+        let page = classify(login, None);
+        assert_eq!((page.kind, page.form_type, page.source), (PageKind::Login, FormType::Generic, ClassificationSource::Heuristic));
+        assert_eq!(page.required_fields.iter().map(|field| field.selector.as_str()).collect::<Vec<_>>(), vec!["#email", "#password"]);
+
+        let registration = r#"<form><label for="user">E-mail *</label><input id="user" type="email">
+            <input id="pass" type="password"><input id="pass2" type="password" aria-required="true">
+            <input type="checkbox" name="terms"><button>Create account</button></form>"#;
+        let page = classify(registration, None);
+        assert_eq!(page.kind, PageKind::Registration);
+        assert_eq!(page.required_fields[0].label.as_deref(), Some("E-mail"));
+        assert_eq!(page.required_fields.len(), 2);
+
+        let job = r#"<form><input name="first_name" required><label for="cv">Resume/CV</label>
+            <input id="cv" type="file" required><button>Apply</button></form>"#;
+        let page = classify(job, Some("boards.greenhouse.io"));
+        assert_eq!((page.kind, page.form_type, page.platform), (PageKind::JobApplication, FormType::JobApplication, Some("greenhouse")));
+        assert!(page.confidence >= LLM_ASSIST_BELOW);
+
+        let checkout = r#"<h2>Payment</h2><form><input name="cardnumber"><input name="cvc"><input name="billing_zip"></form>"#;
+        assert_eq!(classify(checkout, None).kind, PageKind::Checkout);
+
+        let survey = r#"<h1>Customer satisfaction survey</h1><form>
+            <input type="radio" name="q1" value="1"><input type="radio" name="q1" value="2">
+            <input type="radio" name="q2" value="1"><input type="radio" name="q3" value="1">
+            <textarea name="comments"></textarea></form>"#;
+        assert_eq!(classify(survey, None).kind, PageKind::Survey);
+
+        let other = classify("<form><input name=\"q\" type=\"search\"></form>", None);
+        assert_eq!((other.kind, other.confidence), (PageKind::Other, 0.0));
+        assert!(classification_prompt(login).contains("- password"));
+        assert_eq!(PageKind::parse("Job application"), Some(PageKind::JobApplication));
+    }
+}
@@ -0,0 +1,253 @@
+//! Trwała sesja przeglądarki analizy sterowana z API (`/page/navigate`, `/page/click`, `/page/fill`,
+//! `/page/evaluate`): selektory można sprawdzać krok po kroku na żywej stronie, bez generowania całego skryptu DSL.
+//! Przeglądarka startuje przy pierwszym żądaniu i jest zamykana po `IDLE_TIMEOUT` bezczynności.
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::info;
+
+use crate::executor::LiveSession;
+use crate::tagui::{BrowserMode, DslCommand};
+
+/// Bezczynna sesja jest zamykana po tym czasie
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Jak często sprawdzamy bezczynność
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Strona po nawigacji
+#[derive(Debug, Clone, Serialize)]
+pub struct PageState {
+    pub url: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Wynik kliknięcia lub wypełnienia pola
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionFeedback {
+    pub selector: String,
+    /// Liczba pasujących elementów tuż przed akcją
+    pub matches: usize,
+    pub success: bool,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+    /// Adres strony po akcji (kliknięcie mogło przejść dalej)
+    pub url: Option<String>,
+}
+
+/// Błędny argument żądania (pusty selektor, adres spoza http/https) - w odróżnieniu od błędu przeglądarki
+#[derive(Debug)]
+pub struct InvalidInput(pub String);
+
+impl std::fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+struct Live {
+    session: LiveSession,
+    last_used: Instant,
+}
+
+pub struct PageSession {
+    browser_mode: BrowserMode,
+    live: Mutex<Option<Live>>,
+}
+
+impl PageSession {
+    pub fn new(browser_mode: BrowserMode) -> Self {
+        Self { browser_mode, live: Mutex::new(None) }
+    }
+
+    /// Otwarta sesja; pierwsze wywołanie uruchamia przeglądarkę
+    async fn session(&self) -> Result<MutexGuard<'_, Option<Live>>> {
+        let mut live = self.live.lock().await;
+        if live.is_none() {
+            info!(browser_mode = ?self.browser_mode, "Launching the page control browser");
+            *live = Some(Live { session: LiveSession::launch(None, self.browser_mode).await?, last_used: Instant::now() });
+        }
+        if let Some(live) = live.as_mut() {
+            live.last_used = Instant::now();
+        }
+        Ok(live)
+    }
+
+    pub async fn navigate(&self, url: &str) -> Result<PageState> {
+        let url = public_url(url).await?;
+        let live = self.session().await?;
+        let session = &live.as_ref().expect("session was just opened").session;
+        session.open(&url).await?;
+        // Przekierowanie mogło wyprowadzić stronę do sieci lokalnej
+        let landed = session.url().await;
+        if let Some(landed) = landed.as_deref().filter(|landed| !is_public_landing(landed)) {
+            session.open("about:blank").await?;
+            bail!(InvalidInput(format!("The page redirected to an internal address: {}", landed)));
+        }
+        Ok(PageState { url: landed, title: session.title().await })
+    }
+
+    pub async fn click(&self, selector: &str) -> Result<ActionFeedback> {
+        self.act("click", vec![selector.to_string()]).await
+    }
+
+    /// Czyści pole i wpisuje `value` (jak komenda `type`; `[enter]` naciska Enter)
+    pub async fn fill(&self, selector: &str, value: &str) -> Result<ActionFeedback> {
+        self.act("type", vec![selector.to_string(), value.to_string()]).await
+    }
+
+    async fn act(&self, name: &str, args: Vec<String>) -> Result<ActionFeedback> {
+        if args[0].trim().is_empty() {
+            bail!(InvalidInput("Selector must not be empty".to_string()));
+        }
+        let live = self.session().await?;
+        let session = &live.as_ref().expect("session was just opened").session;
+        let command = DslCommand { line: 1, name: name.to_string(), args, retry: None };
+        let feedback = session.execute(&command).await;
+        Ok(ActionFeedback {
+            selector: command.args[0].clone(),
+            matches: feedback.matches.unwrap_or(0),
+            success: feedback.error.is_none(),
+            error: feedback.error,
+            elapsed_ms: feedback.elapsed.as_millis() as u64,
+            url: session.url().await,
+        })
+    }
+
+    pub async fn evaluate(&self, expression: &str) -> Result<serde_json::Value> {
+        if expression.trim().is_empty() {
+            bail!(InvalidInput("Expression must not be empty".to_string()));
+        }
+        let live = self.session().await?;
+        live.as_ref().expect("session was just opened").session.evaluate(expression).await
+    }
+
+    /// Zamyka przeglądarkę; false, gdy sesja nie była otwarta
+    pub async fn close(&self) -> bool {
+        let Some(live) = self.live.lock().await.take() else {
+            return false;
+        };
+        live.session.close().await;
+        info!("Page control browser closed");
+        true
+    }
+
+    /// Zamyka sesję nieużywaną dłużej niż `IDLE_TIMEOUT`; działa do końca procesu
+    pub async fn run_idle_reaper(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+            let idle = self.live.lock().await.as_ref().is_some_and(|live| live.last_used.elapsed() >= IDLE_TIMEOUT);
+            if idle {
+                info!(idle_secs = IDLE_TIMEOUT.as_secs(), "Closing idle page control browser");
+                self.close().await;
+            }
+        }
+    }
+}
+
+/// Adres do otwarcia: http(s) albo `about:blank`; bez schematu zakładamy https
+fn normalize_url(url: &str) -> Result<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        bail!(InvalidInput("URL must not be empty".to_string()));
+    }
+    if url == "about:blank" {
+        return Ok(url.to_string());
+    }
+    let candidate = if url.contains("://") { url.to_string() } else { format!("https://{}", url) };
+    match reqwest::Url::parse(&candidate) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some() => {
+            if is_internal_host(&parsed) {
+                bail!(InvalidInput(format!("Internal addresses cannot be opened: {}", parsed)));
+            }
+            Ok(parsed.to_string())
+        }
+        Ok(parsed) => bail!(InvalidInput(format!("Only http and https pages can be opened, got '{}'", parsed.scheme()))),
+        Err(e) => bail!(InvalidInput(format!("Invalid URL '{}': {}", url, e))),
+    }
+}
+
+/// Host `localhost` albo adres IP z sieci wewnętrznej zapisany wprost w adresie
+fn is_internal_host(url: &reqwest::Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => is_internal_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+    }
+}
+
+/// Loopback, sieci prywatne, link-local (z metadanymi chmury pod 169.254.169.254), CGNAT i adresy nieokreślone
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast()
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_internal_ip(IpAddr::V4(mapped)),
+            None => ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local(),
+        },
+    }
+}
+
+/// Znormalizowany adres strony otwieranej na żądanie API (sterowanie stroną, zrzuty); przeglądarka nie może sięgać
+/// do usług hosta ani sieci lokalnej, więc adresy wewnętrzne - wprost albo po rozwiązaniu nazwy - są odrzucane
+pub async fn public_url(url: &str) -> Result<String> {
+    let url = normalize_url(url)?;
+    ensure_public_target(&url).await?;
+    Ok(url)
+}
+
+/// Czy strona po przekierowaniach nadal jest poza siecią wewnętrzną
+pub fn is_public_landing(url: &str) -> bool {
+    normalize_url(url).is_ok()
+}
+
+/// Nierozwiązywalny host przepuszczamy, nawigacja i tak się nie powiedzie
+async fn ensure_public_target(url: &str) -> Result<()> {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return Ok(());
+    };
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return Ok(());
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(addrs) = tokio::net::lookup_host((host, port)).await {
+        for addr in addrs {
+            if is_internal_ip(addr.ip()) {
+                bail!(InvalidInput(format!("{} resolves to an internal address", host)));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(normalize_url(" example.com/apply ").unwrap(), "https://example.com/apply");
+        assert_eq!(normalize_url("http://93.184.215.14:8080/").unwrap(), "http://93.184.215.14:8080/");
+        for internal in ["http://localhost:4000/fixtures/", "127.0.0.1", "http://10.0.0.5/", "http://169.254.169.254/latest/",
+            "http://192.168.1.1", "http://[::1]:4000/", "http://[fe80::1]/", "http://[::ffff:127.0.0.1]/", "http://0.0.0.0/"] {
+            assert!(normalize_url(internal).unwrap_err().downcast_ref::<InvalidInput>().is_some(), "{}", internal);
+        }
+        assert_eq!(normalize_url("about:blank").unwrap(), "about:blank");
+        assert!(normalize_url("file:///etc/passwd").unwrap_err().to_string().contains("http and https"));
+        assert!(normalize_url("").unwrap_err().downcast_ref::<InvalidInput>().is_some());
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// Ile różnych operacji śledzić naraz; przy przepełnieniu wypada ta o najmniejszym łącznym czasie
+const MAX_TRACKED_OPERATIONS: usize = 500;
+
+/// Rodzaj mierzonej operacji
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    DbQuery,
+    LlmCall,
+    DslStep,
+}
+
+/// Progi, powyżej których operacja jest uznawana za wolną
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowThresholds {
+    pub db_query: Duration,
+    pub llm_call: Duration,
+    pub dsl_step: Duration,
+}
+
+impl Default for SlowThresholds {
+    fn default() -> Self {
+        Self {
+            db_query: Duration::from_millis(500),
+            llm_call: Duration::from_secs(15),
+            dsl_step: Duration::from_secs(10),
+        }
+    }
+}
+
+impl SlowThresholds {
+    pub fn for_kind(&self, kind: OperationKind) -> Duration {
+        match kind {
+            OperationKind::DbQuery => self.db_query,
+            OperationKind::LlmCall => self.llm_call,
+            OperationKind::DslStep => self.dsl_step,
+        }
+    }
+}
+
+/// Zagregowane przekroczenia progu dla jednej operacji (rodzaj + etykieta)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowOperation {
+    pub kind: OperationKind,
+    pub label: String,
+    pub count: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    pub last_ms: u64,
+    pub threshold_ms: u64,
+    pub last_seen: DateTime<Utc>,
+    /// Kontekst ostatniego przekroczenia (np. run_id i linia kroku)
+    pub last_context: Value,
+}
+
+static THRESHOLDS: RwLock<Option<SlowThresholds>> = RwLock::new(None);
+static OFFENDERS: OnceLock<Mutex<HashMap<(OperationKind, String), SlowOperation>>> = OnceLock::new();
+
+/// Progi z konfiguracji (SLOW_QUERY_MS, SLOW_LLM_MS, SLOW_STEP_MS); wywoływane raz przy starcie
+pub fn set_thresholds(thresholds: SlowThresholds) {
+    *THRESHOLDS.write().unwrap() = Some(thresholds);
+}
+
+pub fn thresholds() -> SlowThresholds {
+    THRESHOLDS.read().unwrap().unwrap_or_default()
+}
+
+fn offenders() -> &'static Mutex<HashMap<(OperationKind, String), SlowOperation>> {
+    OFFENDERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Records a finished operation; slow ones emit a structured warning and count towards
+/// the top offenders. Returns whether the threshold was exceeded.
+pub fn observe(kind: OperationKind, label: &str, elapsed: Duration, context: Value) -> bool {
+    let threshold = thresholds().for_kind(kind);
+    if elapsed <= threshold {
+        return false;
+    }
+
+    let elapsed_ms = elapsed.as_millis() as u64;
+    let threshold_ms = threshold.as_millis() as u64;
+    warn!(
+        kind = ?kind,
+        label = label,
+        elapsed_ms = elapsed_ms,
+        threshold_ms = threshold_ms,
+        context = %context,
+        "Slow operation: {} took {}ms (threshold {}ms)", label, elapsed_ms, threshold_ms
+    );
+
+    let mut offenders = offenders().lock().unwrap();
+    let key = (kind, label.to_string());
+    if !offenders.contains_key(&key) && offenders.len() >= MAX_TRACKED_OPERATIONS {
+        let smallest = offenders.iter().min_by_key(|(_, operation)| operation.total_ms).map(|(key, _)| key.clone());
+        if let Some(smallest) = smallest {
+            offenders.remove(&smallest);
+        }
+    }
+    let entry = offenders.entry(key).or_insert_with(|| SlowOperation {
+        kind,
+        label: label.to_string(),
+        count: 0,
+        total_ms: 0,
+        max_ms: 0,
+        last_ms: 0,
+        threshold_ms,
+        last_seen: Utc::now(),
+        last_context: Value::Null,
+    });
+    entry.count += 1;
+    entry.total_ms += elapsed_ms;
+    entry.max_ms = entry.max_ms.max(elapsed_ms);
+    entry.last_ms = elapsed_ms;
+    entry.threshold_ms = threshold_ms;
+    entry.last_seen = Utc::now();
+    entry.last_context = context;
+    true
+}
+
+/// Mierzy czas `future` i przekazuje go do `observe`
+pub async fn timed<F: Future>(kind: OperationKind, label: &str, context: Value, future: F) -> F::Output {
+    let started = Instant::now();
+    let output = future.await;
+    observe(kind, label, started.elapsed(), context);
+    output
+}
+
+/// Najwolniejsze operacje wg łącznego czasu ponad progiem
+pub fn top_offenders(kind: Option<OperationKind>, limit: usize) -> Vec<SlowOperation> {
+    let mut operations: Vec<SlowOperation> = offenders()
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|operation| kind.map(|kind| operation.kind == kind).unwrap_or(true))
+        .cloned()
+        .collect();
+    operations.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then(b.max_ms.cmp(&a.max_ms)));
+    operations.truncate(limit);
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_operations_aggregate() {
+        let step = thresholds().dsl_step;
+        let label = "click \"#perf-test-submit\"";
+        assert!(!observe(OperationKind::DslStep, label, step, Value::Null));
+        assert!(observe(OperationKind::DslStep, label, step * 2, serde_json::json!({ "line": 1 })));
+        assert!(observe(OperationKind::DslStep, label, step * 3, serde_json::json!({ "line": 2 })));
+
+        let top = top_offenders(Some(OperationKind::DslStep), MAX_TRACKED_OPERATIONS);
+        let entry = top.iter().find(|operation| operation.label == label).unwrap();
+        assert_eq!(entry.count, 2);
+        assert_eq!(entry.max_ms, (step * 3).as_millis() as u64);
+        assert_eq!(entry.last_context["line"], 2);
+        assert!(top_offenders(Some(OperationKind::LlmCall), 10).iter().all(|operation| operation.label != label));
+    }
+}
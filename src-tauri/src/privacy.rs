@@ -0,0 +1,32 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+
+static STORE_PAGE_HTML: AtomicBool = AtomicBool::new(true);
+
+static LLM_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Ustawienie prywatności z konfiguracji (STORE_PAGE_HTML); wywoływane raz przy starcie
+pub fn set_page_html_storage(enabled: bool) {
+    STORE_PAGE_HTML.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        info!("Page HTML persistence disabled: no HTML in the DSL cache, replay bundles or run screenshots");
+    }
+}
+
+/// Whether page content (HTML, or screenshots of it) may be written to the database or disk
+pub fn stores_page_html() -> bool {
+    STORE_PAGE_HTML.load(Ordering::SeqCst)
+}
+
+/// Ustawienie prywatności z konfiguracji (LLM_ENABLED); wywoływane raz przy starcie
+pub fn set_llm_enabled(enabled: bool) {
+    LLM_ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        info!("LLM disabled: page content and instructions are never sent to a model provider");
+    }
+}
+
+/// Whether page content may be sent to the configured LLM provider
+pub fn allows_llm() -> bool {
+    LLM_ENABLED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,377 @@
+//! Szablony promptów generowania DSL per rodzaj formularza i język. Wbudowane szablony można
+//! nadpisać w tabeli `prompt_templates`; przy renderowaniu `{{html}}` i `{{user_data}}` są zastępowane
+//! HTML strony i danymi użytkownika, `{{examples}}` - przykładami udanych skryptów (`few_shot`), a `{{locale}}` -
+//! językiem strony i formatami wartości jej regionu (`locale`).
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use tracing::{debug, info, warn};
+
+use codialog_types::automation::{FormType, LlmOptions, PromptLanguage};
+
+use crate::locale::{self, LocaleHint};
+use crate::{cdp, dom, few_shot};
+
+pub const HTML_PLACEHOLDER: &str = "{{html}}";
+pub const USER_DATA_PLACEHOLDER: &str = "{{user_data}}";
+/// Opcjonalne miejsce na przykłady; w szablonie bez niego przykłady trafiają na koniec
+pub const EXAMPLES_PLACEHOLDER: &str = "{{examples}}";
+/// Opcjonalne miejsce na język i konwencje regionalne; w szablonie bez niego trafiają przed przykłady
+pub const LOCALE_PLACEHOLDER: &str = "{{locale}}";
+
+/// Maksymalna długość nadpisanego szablonu (w znakach)
+const MAX_TEMPLATE_CHARS: usize = 20_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptTemplate {
+    pub form_type: FormType,
+    pub language: PromptLanguage,
+    pub body: String,
+    /// Szablon z bazy zamiast wbudowanego
+    pub overridden: bool,
+}
+
+impl PromptTemplate {
+    /// `examples` to gotowa sekcja z `few_shot::render_examples`, a `locale` z `locale::prompt_section` (mogą być puste)
+    pub fn render(&self, html: &str, user_data: &Value, examples: &str, locale: &str) -> String {
+        let (body, examples) = if self.body.contains(LOCALE_PLACEHOLDER) {
+            (self.body.replace(LOCALE_PLACEHOLDER, locale), examples.to_string())
+        } else {
+            (self.body.clone(), format!("{}{}", locale, examples))
+        };
+        let body = if body.contains(EXAMPLES_PLACEHOLDER) {
+            body.replace(EXAMPLES_PLACEHOLDER, &examples)
+        } else {
+            format!("{}{}", body, examples)
+        };
+        body.replace(USER_DATA_PLACEHOLDER, &serde_json::to_string_pretty(user_data).unwrap_or_default())
+            .replace(HTML_PLACEHOLDER, html)
+    }
+}
+
+/// Wybór szablonu z zapytania; bez `form_type` rodzaj jest rozpoznawany z pól formularza
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptSelection {
+    pub form_type: Option<FormType>,
+    pub language: PromptLanguage,
+    /// Witryna strony (host bez `www.`) - przykłady z udanych przebiegów na niej
+    pub site: Option<String>,
+    /// Dostawca, model i parametry próbkowania z żądania
+    pub llm: LlmOptions,
+    /// Język strony (z jej HTML) i locale użytkownika z żądania
+    pub locale: LocaleHint,
+}
+
+impl PromptSelection {
+    pub fn new(form_type: Option<FormType>, language: Option<PromptLanguage>) -> Self {
+        Self { form_type, language: language.unwrap_or_default(), site: None, llm: LlmOptions::default(), locale: LocaleHint::default() }
+    }
+
+    pub fn with_page_url(mut self, url: Option<&str>) -> Self {
+        self.site = url.and_then(cdp::site_of);
+        self
+    }
+
+    pub fn with_llm_options(mut self, options: LlmOptions) -> Self {
+        self.llm = options;
+        self
+    }
+
+    /// Locale użytkownika (np. `pl-PL`); język strony jest odczytywany z jej HTML przy generowaniu
+    pub fn with_user_locale(mut self, locale: Option<&str>) -> Self {
+        self.locale = LocaleHint::new(locale);
+        self
+    }
+}
+
+/// Prompt dla strony: szablon nadpisany w bazie (jeśli jest `db_pool`) albo wbudowany, z przykładami
+/// udanych skryptów dla tej witryny lub podobnych formularzy
+pub async fn prompt_for(html: &str, user_data: &Value, selection: &PromptSelection, db_pool: Option<&PgPool>) -> String {
+    let form_type = selection.form_type.unwrap_or_else(|| detect_form_type(html));
+    let locale = selection.locale.clone().with_page(html);
+    let (template, examples) = match db_pool {
+        Some(pool) => (
+            PromptTemplateStore::new(pool.clone()).resolve(form_type, selection.language).await,
+            few_shot::find_examples(pool, html, selection.site.as_deref()).await,
+        ),
+        None => (builtin(form_type, selection.language), Vec::new()),
+    };
+    debug!(
+        form_type = form_type.as_str(),
+        language = selection.language.as_str(),
+        overridden = template.overridden,
+        examples = examples.len(),
+        locale = locale.effective(),
+        "Selected DSL prompt template"
+    );
+    template.render(
+        html,
+        user_data,
+        &few_shot::render_examples(&examples, selection.language),
+        &locale::prompt_section(&locale, selection.language),
+    )
+}
+
+/// Rodzaj formularza z jego pól: płatność (karta, CVV, adres rozliczeniowy), aplikacja o pracę (CV),
+/// rejestracja (powtórzone hasło) albo ogólny
+pub fn detect_form_type(html: &str) -> FormType {
+    let fields = dom::form_fields(html);
+    let describe = |field: &dom::FormField| {
+        [&field.id, &field.name, &field.label, &field.text]
+            .iter()
+            .filter_map(|value| value.as_deref())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    let mentions = |words: &[&str]| fields.iter().any(|field| {
+        let text = describe(field);
+        words.iter().any(|word| text.contains(word))
+    });
+
+    let passwords = fields.iter().filter(|field| field.element_type.as_deref() == Some("password")).count();
+    let has_file = fields.iter().any(|field| field.element_type.as_deref() == Some("file"));
+
+    if mentions(&["card", "cc-number", "cvv", "cvc", "billing", "payment", "karty", "płatno"]) {
+        FormType::Checkout
+    } else if mentions(&["resume", "cv", "cover_letter", "cover-letter", "list motywacyjny"]) && (has_file || mentions(&["apply", "aplikuj"])) {
+        FormType::JobApplication
+    } else if passwords >= 2 || (passwords == 1 && mentions(&["confirm", "repeat", "register", "sign up", "powtórz", "rejestr"])) {
+        FormType::Registration
+    } else {
+        FormType::Generic
+    }
+}
+
+/// Szablon dostarczany z aplikacją
+pub fn builtin(form_type: FormType, language: PromptLanguage) -> PromptTemplate {
+    let body = match language {
+        PromptLanguage::Pl => format!(
+            "Przeanalizuj formularz HTML i wygeneruj skrypt DSL do jego wypełnienia.\n\
+            Dostępne komendy: click, type, upload, hover, wait, waitfor <selektor> timeout <sekundy>, select <selektor> <opcja>, check/uncheck <selektor>, press <klawisz>, scroll <selektor|up|down|top|bottom>\n\
+            Bloki: if present <selektor> ... end, repeat <N> ... end, for each <xpath> ... end (@item = bieżący element)\n\
+            Ramki: elementy wewnątrz <codialog-frame name=\"X\"> leżą w iframe - obsłuż je w bloku frame \"X\" ... end\n\
+            Niestabilne elementy: dopisz na końcu komendy retry <N> interval <sekundy>, np. click \"#submit\" retry 3 interval 2\n\
+            \n\
+            Zasady:\n\
+            1. Używaj selektorów CSS (#id, .class, [attribute])\n\
+            2. Najpierw zaloguj się jeśli to konieczne\n\
+            3. Wypełnij wszystkie wymagane pola; w listach <select> i grupach radio wybierz jedną z istniejących opcji (select <selektor> <value opcji>, check [name=\"X\"][value=\"Y\"]) zamiast wpisywać tekst, a wpisywane wartości dopasuj do maxlength i pattern pola. Oczekiwania finansowe, okres wypowiedzenia, datę rozpoczęcia i gotowość do relokacji podawaj tylko z danych użytkownika (expected_salary, notice_period, available_from, relocation) - nigdy ich nie wymyślaj\n\
+            4. Na końcu kliknij przycisk submit/apply\n\
+            5. Po kliknięciu, które ładuje nową stronę, użyj waitfor na pierwszy element tej strony zamiast wait <sekundy>\n\
+            6. Formularz wieloetapowy (kontenery data-step, przyciski Next/Dalej, pasek postępu): wypełnij pola bieżącego kroku, kliknij Next, użyj waitfor na pierwsze pole następnego kroku i kontynuuj; submit dopiero w ostatnim kroku\n\
+            7. Zwróć TYLKO komendy DSL, bez komentarzy\n\
+            {}{}{}\n\
+            HTML: {}\n\
+            \n\
+            Dane użytkownika: {}\n\
+            \n\
+            Wygeneruj optymalną sekwencję komend DSL:",
+            form_hint(form_type, language),
+            LOCALE_PLACEHOLDER,
+            EXAMPLES_PLACEHOLDER,
+            HTML_PLACEHOLDER,
+            USER_DATA_PLACEHOLDER
+        ),
+        PromptLanguage::En => format!(
+            "Analyze the HTML form and generate a DSL script that fills it in.\n\
+            Available commands: click, type, upload, hover, wait, waitfor <selector> timeout <seconds>, select <selector> <option>, check/uncheck <selector>, press <key>, scroll <selector|up|down|top|bottom>\n\
+            Blocks: if present <selector> ... end, repeat <N> ... end, for each <xpath> ... end (@item = current element)\n\
+            Frames: elements inside <codialog-frame name=\"X\"> live in an iframe - handle them in a frame \"X\" ... end block\n\
+            Flaky elements: append retry <N> interval <seconds> to the command, e.g. click \"#submit\" retry 3 interval 2\n\
+            \n\
+            Rules:\n\
+            1. Use CSS selectors (#id, .class, [attribute])\n\
+            2. Log in first if required\n\
+            3. Fill in all required fields; for <select> lists and radio groups pick one of the existing options (select <selector> <option value>, check [name=\"X\"][value=\"Y\"]) instead of typing text, and keep typed values within the field's maxlength and pattern. Answer expected salary, notice period, start date and relocation questions only from user data (expected_salary, notice_period, available_from, relocation) - never invent them\n\
+            4. Click the submit/apply button at the end\n\
+            5. After a click that loads a new page, use waitfor on the first element of that page instead of wait <seconds>\n\
+            6. Multi-step form (data-step containers, Next buttons, progress bar): fill the current step, click Next, waitfor the first field of the next step and continue; submit only in the last step\n\
+            7. Return ONLY DSL commands, no comments\n\
+            {}{}{}\n\
+            HTML: {}\n\
+            \n\
+            User data: {}\n\
+            \n\
+            Generate the optimal sequence of DSL commands:",
+            form_hint(form_type, language),
+            LOCALE_PLACEHOLDER,
+            EXAMPLES_PLACEHOLDER,
+            HTML_PLACEHOLDER,
+            USER_DATA_PLACEHOLDER
+        ),
+    };
+    PromptTemplate { form_type, language, body, overridden: false }
+}
+
+/// Wskazówki dla rodzaju formularza, wstawiane po zasadach ogólnych
+fn form_hint(form_type: FormType, language: PromptLanguage) -> &'static str {
+    match (form_type, language) {
+        (FormType::Generic, _) => "",
+        (FormType::JobApplication, PromptLanguage::Pl) => "\nFormularz aplikacji o pracę: CV (cv_path) i list motywacyjny (cover_letter_path) dołącz komendą upload do pól plików, zaznacz zgody wymagane do rekrutacji (RODO), a zgód marketingowych nie zaznaczaj\n",
+        (FormType::JobApplication, PromptLanguage::En) => "\nJob application form: attach the CV (cv_path) and cover letter (cover_letter_path) with upload to the file inputs, check consents required for recruitment (GDPR) and leave marketing consents unchecked\n",
+        (FormType::Registration, PromptLanguage::Pl) => "\nFormularz rejestracji: to samo hasło wpisz w polu hasła i jego powtórzeniu, zaznacz akceptację regulaminu, newslettera nie zaznaczaj\n",
+        (FormType::Registration, PromptLanguage::En) => "\nRegistration form: type the same password into the password and confirmation fields, accept the terms of service and leave newsletter sign-ups unchecked\n",
+        (FormType::Checkout, PromptLanguage::Pl) => "\nFormularz płatności: wypełnij adres dostawy i rozliczeniowy, dane karty wpisuj tylko z danych użytkownika i nigdy ich nie zgaduj; przycisk zamówienia kliknij dopiero po wypełnieniu wszystkich wymaganych pól\n",
+        (FormType::Checkout, PromptLanguage::En) => "\nCheckout form: fill in the shipping and billing address, take card details only from the user data and never guess them; click the order button only after all required fields are filled\n",
+    }
+}
+
+/// Szablon musi zawierać miejsce na HTML strony
+pub fn validate_template(body: &str) -> Result<()> {
+    if !body.contains(HTML_PLACEHOLDER) {
+        bail!("Prompt template must contain the {} placeholder", HTML_PLACEHOLDER);
+    }
+    if body.chars().count() > MAX_TEMPLATE_CHARS {
+        bail!("Prompt template must not exceed {} characters", MAX_TEMPLATE_CHARS);
+    }
+    Ok(())
+}
+
+pub struct PromptTemplateStore {
+    db_pool: PgPool,
+}
+
+impl PromptTemplateStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę nadpisanych szablonów
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing prompt templates table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS prompt_templates (
+                form_type VARCHAR(32) NOT NULL,
+                language VARCHAR(8) NOT NULL,
+                body TEXT NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (form_type, language)
+            );
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create prompt_templates table")?;
+
+        Ok(())
+    }
+
+    /// Szablony dla każdego rodzaju formularza i języka, nadpisane albo wbudowane
+    pub async fn list(&self) -> Result<Vec<PromptTemplate>> {
+        let rows = sqlx::query("SELECT form_type, language, body FROM prompt_templates")
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to list prompt templates")?;
+
+        let mut templates = Vec::new();
+        for form_type in FormType::ALL {
+            for language in PromptLanguage::ALL {
+                let overridden = rows.iter().find(|row| {
+                    row.get::<String, _>("form_type") == form_type.as_str() && row.get::<String, _>("language") == language.as_str()
+                });
+                templates.push(match overridden {
+                    Some(row) => PromptTemplate { form_type, language, body: row.get("body"), overridden: true },
+                    None => builtin(form_type, language),
+                });
+            }
+        }
+        Ok(templates)
+    }
+
+    pub async fn get(&self, form_type: FormType, language: PromptLanguage) -> Result<Option<PromptTemplate>> {
+        let row = sqlx::query("SELECT body FROM prompt_templates WHERE form_type = $1 AND language = $2")
+            .bind(form_type.as_str())
+            .bind(language.as_str())
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to fetch prompt template")?;
+
+        Ok(row.map(|row| PromptTemplate { form_type, language, body: row.get("body"), overridden: true }))
+    }
+
+    /// Nadpisany szablon, a przy jego braku lub błędzie bazy - wbudowany
+    pub async fn resolve(&self, form_type: FormType, language: PromptLanguage) -> PromptTemplate {
+        match self.get(form_type, language).await {
+            Ok(Some(template)) => template,
+            Ok(None) => builtin(form_type, language),
+            Err(e) => {
+                warn!("Failed to load prompt template override, using built-in: {}", e);
+                builtin(form_type, language)
+            }
+        }
+    }
+
+    pub async fn save(&self, form_type: FormType, language: PromptLanguage, body: &str) -> Result<PromptTemplate> {
+        validate_template(body)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO prompt_templates (form_type, language, body)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (form_type, language) DO UPDATE SET body = EXCLUDED.body, updated_at = NOW()
+            "#,
+        )
+        .bind(form_type.as_str())
+        .bind(language.as_str())
+        .bind(body)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save prompt template")?;
+
+        info!(form_type = form_type.as_str(), language = language.as_str(), "Prompt template overridden");
+        Ok(PromptTemplate { form_type, language, body: body.to_string(), overridden: true })
+    }
+
+    /// Przywraca wbudowany szablon; `false`, jeśli nie był nadpisany
+    pub async fn reset(&self, form_type: FormType, language: PromptLanguage) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM prompt_templates WHERE form_type = $1 AND language = $2")
+            .bind(form_type.as_str())
+            .bind(language.as_str())
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to reset prompt template")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_form_type_and_renders_builtin_templates() {
+        let job = r#"<form><input name="email"><input type="file" id="resume"><button>Apply</button></form>"#;
+        let registration = r#"<form><input name="login"><input type="password" name="password"><input type="password" name="password2"></form>"#;
+        let checkout = r#"<form><input name="address"><input autocomplete="cc-number" id="card-number"><input name="cvv"></form>"#;
+        assert_eq!(detect_form_type(job), FormType::JobApplication);
+        assert_eq!(detect_form_type(registration), FormType::Registration);
+        assert_eq!(detect_form_type(checkout), FormType::Checkout);
+        assert_eq!(detect_form_type(r#"<form><input name="q"></form>"#), FormType::Generic);
+
+        let user_data = serde_json::json!({ "email": "jan@example.com" });
+        let generic = builtin(FormType::Generic, PromptLanguage::Pl).render("<form></form>", &user_data, "", "");
+        assert!(generic.contains("7. Zwróć TYLKO komendy DSL, bez komentarzy\n\nHTML: <form></form>\n"));
+        assert!(generic.contains("\"email\": \"jan@example.com\""));
+        let english = builtin(FormType::JobApplication, PromptLanguage::En).render("<form></form>", &user_data, "\nExamples:\n", "");
+        assert!(english.starts_with("Analyze the HTML form") && english.contains("Job application form"));
+        assert!(english.contains("unchecked\n\nExamples:\n\nHTML: <form></form>"));
+
+        // Język strony przed przykładami - także w nadpisanym szablonie bez {{locale}}
+        let hint = LocaleHint::new(Some("pl-PL")).with_page(r#"<html lang="de-DE"></html>"#);
+        let section = locale::prompt_section(&hint, PromptLanguage::En);
+        let localized = builtin(FormType::Generic, PromptLanguage::En).render("<form></form>", &user_data, "\nExamples:\n", &section);
+        assert!(localized.contains("comments\n\nLanguage: the page is in de-DE (user: pl-PL)."));
+        assert!(localized.contains(".\n\nExamples:\n\nHTML:"));
+        let custom = PromptTemplate { body: "Fill {{html}}".to_string(), ..builtin(FormType::Generic, PromptLanguage::En) };
+        assert!(custom.render("<form></form>", &user_data, "", &section).starts_with("Fill <form></form>\nLanguage:"));
+
+        assert!(validate_template("Fill {{user_data}}").is_err());
+        assert!(validate_template("Fill {{html}} with {{user_data}}").is_ok());
+    }
+}
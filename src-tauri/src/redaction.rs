@@ -0,0 +1,242 @@
+//! Maskowanie wrażliwych wartości przed wysłaniem HTML i danych użytkownika do LLM.
+//! Hasła, numery kart, SSN i wartości kluczy z LLM_REDACT_KEYS są zastępowane znacznikami
+//! `__REDACTED_<RODZAJ>_<N>__`; po wygenerowaniu skryptu znaczniki wracają do oryginalnych wartości,
+//! więc dostawca modelu nigdy ich nie widzi, a wynik jest taki sam jak bez maskowania.
+
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::sync::RwLock;
+use tracing::{debug, info};
+
+use crate::tagui::escape_for_dsl;
+
+/// Fragmenty nazw kluczy danych i pól formularza uznawanych za wrażliwe
+pub const DEFAULT_SENSITIVE_KEYS: &[&str] = &[
+    "password", "passwd", "pwd", "secret", "token", "api_key", "apikey", "card_number", "cardnumber", "cc_number",
+    "ccnumber", "cvv", "cvc", "ssn", "pin", "otp", "totp", "iban", "pesel",
+];
+
+/// Krótsze wartości nie są szukane w HTML - zamiana zniszczyłaby przypadkowe fragmenty strony
+const MIN_HTML_VALUE_CHARS: usize = 4;
+
+static EXTRA_KEYS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Dodatkowe wrażliwe klucze z konfiguracji (LLM_REDACT_KEYS); wywoływane raz przy starcie
+pub fn set_extra_keys(keys: &[String]) {
+    let keys: Vec<String> = keys.iter().map(|key| normalize_key(key)).filter(|key| !key.is_empty()).collect();
+    if !keys.is_empty() {
+        info!(keys = ?keys, "Additional sensitive keys redacted from LLM prompts");
+    }
+    *EXTRA_KEYS.write().unwrap() = keys;
+}
+
+fn normalize_key(key: &str) -> String {
+    key.trim().to_lowercase().replace('-', "_")
+}
+
+/// Czy nazwa klucza lub pola (`user_password`, `creditCardNumber`, `cvv`) wskazuje wartość wrażliwą.
+/// Krótkie fragmenty (`pin`, `ssn`) muszą być osobnym słowem, dłuższe mogą być częścią nazwy.
+pub fn is_sensitive_key(key: &str) -> bool {
+    let key = normalize_key(key);
+    let words: Vec<&str> = key.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+    let compact: String = words.concat();
+    let matches = |fragment: &str| {
+        let fragment_compact = fragment.replace('_', "");
+        key == fragment || words.contains(&fragment) || (fragment_compact.len() >= 5 && compact.contains(&fragment_compact))
+    };
+    DEFAULT_SENSITIVE_KEYS.iter().any(|fragment| matches(fragment))
+        || EXTRA_KEYS.read().unwrap().iter().any(|fragment| matches(fragment))
+}
+
+/// Numer karty: 13-19 cyfr (ze spacjami lub myślnikami) z poprawną sumą Luhna
+pub fn looks_like_card_number(value: &str) -> bool {
+    let value = value.trim();
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit() || c == ' ' || c == '-') {
+        return false;
+    }
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| match index % 2 {
+            0 => *digit,
+            _ if *digit * 2 > 9 => *digit * 2 - 9,
+            _ => *digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Amerykański SSN w postaci `123-45-6789`
+pub fn looks_like_ssn(value: &str) -> bool {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    parts.len() == 3
+        && parts.iter().zip([3, 2, 4]).all(|(part, len)| part.len() == len && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Rodzaj wartości do znacznika; `None` - wartość nie jest wrażliwa
+fn sensitive_kind(key: Option<&str>, value: &str) -> Option<String> {
+    if value.trim().is_empty() {
+        return None;
+    }
+    if looks_like_card_number(value) {
+        return Some("CARD".to_string());
+    }
+    if looks_like_ssn(value) {
+        return Some("SSN".to_string());
+    }
+    let key = key.filter(|key| is_sensitive_key(key))?;
+    let kind: String = normalize_key(key).chars().map(|c| if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    Some(if kind.is_empty() { "VALUE".to_string() } else { kind })
+}
+
+/// Zamaskowane wejście promptu i znaczniki do przywrócenia w wygenerowanym skrypcie
+#[derive(Debug, Clone)]
+pub struct Redacted {
+    pub html: String,
+    pub user_data: Value,
+    pub redaction: Redaction,
+}
+
+/// Pary znacznik -> oryginalna wartość
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    placeholders: Vec<(String, String)>,
+}
+
+impl Redaction {
+    pub fn len(&self) -> usize {
+        self.placeholders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placeholders.is_empty()
+    }
+
+    /// Znacznik dla wartości - ta sama wartość dostaje ten sam znacznik
+    fn placeholder_for(&mut self, kind: &str, value: &str) -> String {
+        if let Some((placeholder, _)) = self.placeholders.iter().find(|(_, original)| original == value) {
+            return placeholder.clone();
+        }
+        let placeholder = format!("__REDACTED_{}_{}__", kind, self.placeholders.len() + 1);
+        self.placeholders.push((placeholder.clone(), value.to_string()));
+        placeholder
+    }
+
+    /// Wstawia oryginalne wartości w miejsce znaczników w skrypcie DSL (w cudzysłowach komend)
+    pub fn restore(&self, script: &str) -> String {
+        self.placeholders.iter().fold(script.to_string(), |script, (placeholder, value)| {
+            script.replace(placeholder, &escape_for_dsl(value))
+        })
+    }
+
+    fn redact_value(&mut self, key: Option<&str>, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                if let Some(kind) = sensitive_kind(key, text) {
+                    *text = self.placeholder_for(&kind, text);
+                }
+            }
+            Value::Number(number) if key.is_some_and(is_sensitive_key) => {
+                let kind = sensitive_kind(key, &number.to_string()).unwrap_or_else(|| "VALUE".to_string());
+                *value = Value::String(self.placeholder_for(&kind, &number.to_string()));
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(key, item)),
+            Value::Object(fields) => fields.iter_mut().for_each(|(key, item)| self.redact_value(Some(key), item)),
+            _ => {}
+        }
+    }
+
+    fn redact_html(&mut self, html: &str) -> String {
+        // Wartości wpisane już w polach: hasła, pola o wrażliwych nazwach, karty i SSN
+        let document = Html::parse_document(html);
+        let inputs = Selector::parse("input[value]").expect("valid selector");
+        for input in document.select(&inputs) {
+            let element = input.value();
+            let Some(value) = element.attr("value") else { continue };
+            let key = match element.attr("type").map(str::to_lowercase).as_deref() {
+                Some("password") => Some("password"),
+                _ => element.attr("name").filter(|name| is_sensitive_key(name)).or_else(|| element.attr("id")),
+            };
+            if let Some(kind) = sensitive_kind(key, value) {
+                self.placeholder_for(&kind, value);
+            }
+        }
+
+        let mut masked = html.to_string();
+        for (placeholder, value) in &self.placeholders {
+            if value.chars().count() < MIN_HTML_VALUE_CHARS {
+                continue;
+            }
+            masked = masked.replace(value.as_str(), placeholder);
+            let escaped = value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;");
+            if escaped != *value {
+                masked = masked.replace(&escaped, placeholder);
+            }
+        }
+        masked
+    }
+}
+
+/// Maskuje wrażliwe wartości danych użytkownika i te same wartości oraz wypełnione wrażliwe pola w HTML
+pub fn redact(html: &str, user_data: &Value) -> Redacted {
+    let mut redaction = Redaction::default();
+    let mut user_data = user_data.clone();
+    redaction.redact_value(None, &mut user_data);
+    let html = redaction.redact_html(html);
+    if !redaction.is_empty() {
+        debug!(values = redaction.len(), "Sensitive values redacted from the LLM prompt");
+    }
+    Redacted { html, user_data, redaction }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redaction_masks_secrets_and_restores_script() {
+        assert!(is_sensitive_key("user_password") && is_sensitive_key("creditCardNumber") && is_sensitive_key("CVV"));
+        assert!(!is_sensitive_key("shipping_address") && !is_sensitive_key("first_name"));
+        assert!(looks_like_card_number("4111 1111 1111 1111") && !looks_like_card_number("4111 1111 1111 1112"));
+        assert!(looks_like_ssn("123-45-6789") && !looks_like_ssn("123-456-789"));
+
+        let user_data = json!({
+            "email": "jan@example.com",
+            "password": "hunter\"2",
+            "payment": { "card": "4111-1111-1111-1111", "cvv": 123 },
+            "notes": ["SSN 123-45-6789 is not a whole value", "123-45-6789"],
+        });
+        let html = r#"<form>
+            <input name="login" value="jan@example.com">
+            <input type="password" name="pass" value="hunter&quot;2">
+            <input name="api_token" value="tok-abc-123">
+            <input name="card" value="4111-1111-1111-1111">
+        </form>"#;
+
+        let redacted = redact(html, &user_data);
+        assert_eq!(redacted.user_data["email"], "jan@example.com");
+        assert_eq!(redacted.user_data["password"], "__REDACTED_PASSWORD_1__");
+        assert_eq!(redacted.user_data["payment"]["card"], "__REDACTED_CARD_2__");
+        assert_eq!(redacted.user_data["payment"]["cvv"], "__REDACTED_CVV_3__");
+        assert_eq!(redacted.user_data["notes"][1], "__REDACTED_SSN_4__");
+        assert!(redacted.user_data["notes"][0].as_str().unwrap().starts_with("SSN 123-45-6789"));
+
+        let prompt = format!("{} {}", redacted.html, redacted.user_data);
+        for secret in ["hunter", "4111", "tok-abc-123", "\"cvv\":123"] {
+            assert!(!prompt.contains(secret), "{} leaked into the prompt", secret);
+        }
+        assert!(redacted.html.contains("value=\"__REDACTED_PASSWORD_1__\"") && redacted.html.contains("jan@example.com"));
+
+        let script = "type \"#pass\" \"__REDACTED_PASSWORD_1__\"\ntype \"#card\" \"__REDACTED_CARD_2__\"\ntype \"#token\" \"__REDACTED_API_TOKEN_5__\"";
+        assert_eq!(
+            redacted.redaction.restore(script),
+            "type \"#pass\" \"hunter\\\"2\"\ntype \"#card\" \"4111-1111-1111-1111\"\ntype \"#token\" \"tok-abc-123\""
+        );
+    }
+}
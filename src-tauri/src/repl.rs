@@ -0,0 +1,276 @@
+//! Tryb interaktywny `codialog repl`: jedna otwarta przeglądarka, komendy DSL wpisywane
+//! pojedynczo z natychmiastową informacją, czy element istnieje, i zrzutem ekranu po każdym kroku.
+
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::executor::LiveSession;
+use crate::tagui::{self, BrowserMode, DslCommand};
+
+pub const USAGE: &str = "Usage: codialog repl [URL] [--headless] [--screenshots DIR | --no-screenshots]";
+
+const HELP: &str = "\
+Type a DSL command (click, type, select, waitfor, ...) to run it on the open page.
+  :open URL      navigate to URL
+  :shot [FILE]   save a screenshot of the current page
+  :history       list commands that succeeded so far
+  :save FILE     write the successful commands as a DSL script, typed values as {{variables}}
+  :help          show this help
+  :quit          close the browser and exit";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplOptions {
+    pub start_url: Option<String>,
+    /// Domyślnie widoczne okno - REPL służy do ręcznego pisania skryptów
+    pub browser_mode: BrowserMode,
+    /// Katalog na zrzut ekranu po każdej komendzie; `None` wyłącza zrzuty
+    pub screenshot_dir: Option<PathBuf>,
+}
+
+impl ReplOptions {
+    /// Argumenty po `codialog repl`
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut options = Self {
+            start_url: None,
+            browser_mode: BrowserMode::Headed,
+            screenshot_dir: Some(PathBuf::from("repl-screenshots")),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.browser_mode = BrowserMode::Headless,
+                "--no-screenshots" => options.screenshot_dir = None,
+                "--screenshots" => {
+                    let dir = args.next().context("--screenshots needs a directory")?;
+                    options.screenshot_dir = Some(PathBuf::from(dir));
+                }
+                flag if flag.starts_with("--") => bail!("Unknown option {}", flag),
+                url if options.start_url.is_none() => options.start_url = Some(url.to_string()),
+                extra => bail!("Unexpected argument {}", extra),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Jedna linia wpisana w REPL
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplInput {
+    Command(DslCommand),
+    Open(String),
+    Screenshot(Option<PathBuf>),
+    History,
+    Save(PathBuf),
+    Help,
+    Quit,
+    Empty,
+}
+
+pub fn parse_input(line: &str) -> Result<ReplInput> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("//") {
+        return Ok(ReplInput::Empty);
+    }
+
+    if let Some(meta) = line.strip_prefix(':') {
+        let (name, argument) = meta.split_once(char::is_whitespace).unwrap_or((meta, ""));
+        let argument = argument.trim();
+        return match (name, argument) {
+            ("open", "") => bail!(":open needs a URL"),
+            ("open", url) => Ok(ReplInput::Open(url.to_string())),
+            ("shot", "") => Ok(ReplInput::Screenshot(None)),
+            ("shot", file) => Ok(ReplInput::Screenshot(Some(PathBuf::from(file)))),
+            ("history", _) => Ok(ReplInput::History),
+            ("save", "") => bail!(":save needs a file name"),
+            ("save", file) => Ok(ReplInput::Save(PathBuf::from(file))),
+            ("help", _) => Ok(ReplInput::Help),
+            ("quit" | "exit" | "q", _) => Ok(ReplInput::Quit),
+            (other, _) => bail!("Unknown REPL command :{} (see :help)", other),
+        };
+    }
+
+    if tagui::is_block_keyword(line) {
+        bail!("Blocks (if/repeat/for/frame) cannot be run one command at a time; run their body commands instead");
+    }
+    let mut commands = tagui::parse_dsl_script(line).map_err(|e| anyhow::anyhow!("{}", e))?;
+    match commands.len() {
+        1 => Ok(ReplInput::Command(commands.remove(0))),
+        _ => bail!("Enter exactly one DSL command"),
+    }
+}
+
+/// Skrypt z udanych komend do zapisania: wartości wpisane przez `type` i `upload` zastępują zmienne
+/// `{{nazwa}}` od selektora pola, żeby dane (także hasła) nie trafiły do pliku. Zwraca też nazwy zmiennych
+pub fn templated_script(history: &[String]) -> (String, Vec<String>) {
+    let mut variables: Vec<String> = Vec::new();
+    let lines = history
+        .iter()
+        .map(|line| {
+            let Ok(commands) = tagui::parse_dsl_script(line) else { return line.clone() };
+            let Some(command) = commands.first().filter(|command| matches!(command.name.as_str(), "type" | "upload")) else {
+                return line.clone();
+            };
+            let value = &command.args[1];
+            if value.trim().starts_with("{{") {
+                return line.clone();
+            }
+            let quoted = format!("\"{}\"", tagui::escape_for_dsl(value));
+            let Some(start) = line.rfind(&quoted) else { return line.clone() };
+
+            let base = variable_name(&command.args[0]);
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while variables.contains(&name) {
+                suffix += 1;
+                name = format!("{}_{}", base, suffix);
+            }
+            let templated = format!("{}\"{{{{{}}}}}\"{}", &line[..start], name, &line[start + quoted.len()..]);
+            variables.push(name);
+            templated
+        })
+        .collect::<Vec<_>>();
+    (format!("{}\n", lines.join("\n")), variables)
+}
+
+/// Nazwa zmiennej z ostatniego członu selektora, np. `#email` -> `email`, `input[name="first-name"]` -> `first_name`
+fn variable_name(selector: &str) -> String {
+    let words: Vec<String> = selector
+        .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .map(|word| word.to_lowercase().replace('-', "_"))
+        .collect();
+    words.last().cloned().unwrap_or_else(|| "value".to_string())
+}
+
+/// Pętla REPL na stdin/stdout; kończy się po `:quit` lub końcu wejścia
+pub async fn run(options: ReplOptions) -> Result<()> {
+    if let Some(dir) = &options.screenshot_dir {
+        std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    }
+    println!("Starting Chrome ({:?})...", options.browser_mode);
+    let session = LiveSession::launch(options.start_url.as_deref(), options.browser_mode).await?;
+    println!("Ready on {}. Type :help for REPL commands.", session.url().await.unwrap_or_else(|| "about:blank".to_string()));
+
+    let mut history: Vec<String> = Vec::new();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut step = 0usize;
+    loop {
+        print!("dsl> ");
+        std::io::stdout().flush().ok();
+        let Some(line) = lines.next_line().await? else { break };
+
+        let input = match parse_input(&line) {
+            Ok(input) => input,
+            Err(e) => {
+                println!("✗ {}", e);
+                continue;
+            }
+        };
+        match input {
+            ReplInput::Empty => {}
+            ReplInput::Help => println!("{}", HELP),
+            ReplInput::Quit => break,
+            ReplInput::History => history.iter().for_each(|command| println!("{}", command)),
+            ReplInput::Save(path) => {
+                let (script, variables) = templated_script(&history);
+                match std::fs::write(&path, script) {
+                    Ok(()) if variables.is_empty() => println!("Saved {} command(s) to {}", history.len(), path.display()),
+                    Ok(()) => println!(
+                        "Saved {} command(s) to {}; typed values were replaced with {}",
+                        history.len(),
+                        path.display(),
+                        variables.iter().map(|name| format!("{{{{{}}}}}", name)).collect::<Vec<_>>().join(", ")
+                    ),
+                    Err(e) => println!("✗ Cannot write {}: {}", path.display(), e),
+                }
+            }
+            ReplInput::Open(url) => match session.open(&url).await {
+                Ok(()) => println!("✓ Opened {}", session.url().await.unwrap_or(url)),
+                Err(e) => println!("✗ Cannot open {}: {}", url, e),
+            },
+            ReplInput::Screenshot(path) => {
+                let path = path.unwrap_or_else(|| {
+                    options.screenshot_dir.clone().unwrap_or_default().join(format!("shot-{}.png", chrono::Utc::now().format("%Y%m%d-%H%M%S")))
+                });
+                match session.screenshot(&path).await {
+                    Ok(()) => println!("✓ Screenshot saved to {}", path.display()),
+                    Err(e) => println!("✗ Screenshot failed: {}", e),
+                }
+            }
+            ReplInput::Command(command) => {
+                let feedback = session.execute(&command).await;
+                let found = match (&feedback.selector, feedback.matches) {
+                    (Some(selector), Some(0)) => format!(" - {} not found", selector),
+                    (Some(selector), Some(count)) => format!(" - {} found ({} match{})", selector, count, if count == 1 { "" } else { "es" }),
+                    _ => String::new(),
+                };
+                match &feedback.error {
+                    None => {
+                        history.push(line.trim().to_string());
+                        println!("✓ {}{} [{} ms]", command.name, found, feedback.elapsed.as_millis());
+                    }
+                    Some(error) => println!("✗ {}{}: {}", command.name, found, error),
+                }
+
+                if let Some(dir) = &options.screenshot_dir {
+                    step += 1;
+                    let path = dir.join(tagui::screenshot_file_name(step));
+                    match session.screenshot(&path).await {
+                        Ok(()) => println!("  screenshot: {}", path.display()),
+                        Err(e) => println!("  screenshot failed: {}", e),
+                    }
+                }
+            }
+        }
+    }
+
+    session.close().await;
+    println!("Bye");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repl_input() {
+        match parse_input("  click \"#submit\"  ").unwrap() {
+            ReplInput::Command(command) => assert_eq!(command.selector(), Some("#submit")),
+            other => panic!("expected a command, got {:?}", other),
+        }
+        assert_eq!(parse_input(":open https://example.com").unwrap(), ReplInput::Open("https://example.com".to_string()));
+        assert_eq!(parse_input(":shot").unwrap(), ReplInput::Screenshot(None));
+        assert_eq!(parse_input(":q").unwrap(), ReplInput::Quit);
+        assert_eq!(parse_input("").unwrap(), ReplInput::Empty);
+        assert!(parse_input("if present \"#banner\"").is_err());
+        assert!(parse_input(":save").is_err());
+        assert!(parse_input("fly \"#away\"").is_err());
+
+        let options = ReplOptions::from_args(&["https://example.com".to_string(), "--headless".to_string(), "--no-screenshots".to_string()]).unwrap();
+        assert_eq!(options.start_url.as_deref(), Some("https://example.com"));
+        assert_eq!(options.browser_mode, BrowserMode::Headless);
+        assert!(options.screenshot_dir.is_none());
+        assert!(ReplOptions::from_args(&["--verbose".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_save_templates_typed_values() {
+        let history = vec![
+            "type \"#email\" \"jan@example.com\"".to_string(),
+            "type \"input[name='first-name']\" \"Jan \\\"JJ\\\"\" retry 2".to_string(),
+            "type \"#email\" \"{{email}}\"".to_string(),
+            "type \"//input[@id='email']\" \"hunter2\"".to_string(),
+            "click \"#send\"".to_string(),
+        ];
+        let (script, variables) = templated_script(&history);
+        assert_eq!(variables, vec!["email", "first_name", "email_2"]);
+        assert_eq!(
+            script,
+            "type \"#email\" \"{{email}}\"\ntype \"input[name='first-name']\" \"{{first_name}}\" retry 2\ntype \"#email\" \"{{email}}\"\ntype \"//input[@id='email']\" \"{{email_2}}\"\nclick \"#send\"\n"
+        );
+        assert!(!script.contains("hunter2") && !script.contains("jan@example.com"));
+    }
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use anyhow::{Result, Context, anyhow, bail};
+use tracing::{info, warn, debug};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use crate::crypto::{self, ContentCipher};
+
+/// Rodzaj zewnętrznej interakcji zapisywanej w paczce replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionKind {
+    PageHtml,
+    DslCache,
+    LlmResponse,
+    BitwardenOutput,
+    TaguiRun,
+}
+
+impl std::fmt::Display for InteractionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InteractionKind::PageHtml => "page_html",
+            InteractionKind::DslCache => "dsl_cache",
+            InteractionKind::LlmResponse => "llm_response",
+            InteractionKind::BitwardenOutput => "bitwarden_output",
+            InteractionKind::TaguiRun => "tagui_run",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub kind: InteractionKind,
+    pub key: String,
+    pub outcome: Outcome,
+}
+
+/// Wszystko, czego potrzeba do odtworzenia przebiegu offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayBundle {
+    pub id: String,
+    pub pipeline: String,
+    pub recorded_at: DateTime<Utc>,
+    pub input: serde_json::Value,
+    pub interactions: Vec<Interaction>,
+    pub output: serde_json::Value,
+}
+
+enum Mode {
+    Record(Mutex<Vec<Interaction>>),
+    Replay(Mutex<VecDeque<Interaction>>),
+}
+
+struct ReplayContext {
+    mode: Mode,
+}
+
+tokio::task_local! {
+    static CONTEXT: Arc<ReplayContext>;
+}
+
+/// Runs `live` normally, unless the current task is recording or replaying.
+/// While recording the outcome is appended to the bundle; while replaying the
+/// next recorded outcome is returned instead and `live` is never called.
+pub async fn intercept<T, F, Fut>(kind: InteractionKind, key: &str, live: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    intercept_redacted(kind, key, |_| {}, live).await
+}
+
+/// Jak `intercept`, ale pozwala usunąć dane wrażliwe przed zapisaniem do paczki
+pub async fn intercept_redacted<T, F, Fut, R>(kind: InteractionKind, key: &str, redact: R, live: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    R: Fn(&mut serde_json::Value),
+{
+    let Ok(context) = CONTEXT.try_with(|context| context.clone()) else {
+        return live().await;
+    };
+
+    match &context.mode {
+        Mode::Record(log) => {
+            let result = live().await;
+            let outcome = match &result {
+                Ok(value) => {
+                    let mut value = serde_json::to_value(value).context("Failed to serialize interaction for replay")?;
+                    redact(&mut value);
+                    Outcome::Ok(value)
+                }
+                Err(e) => Outcome::Error(e.to_string()),
+            };
+            debug!("Recorded {} interaction: {}", kind, key);
+            log.lock().unwrap().push(Interaction { kind, key: key.to_string(), outcome });
+            result
+        }
+        Mode::Replay(queue) => {
+            let next = queue.lock().unwrap().pop_front();
+            let Some(interaction) = next else {
+                bail!("Replay bundle has no recorded {} interaction left for '{}'", kind, key);
+            };
+            if interaction.kind != kind {
+                bail!("Replay diverged: expected {} for '{}', bundle has {}", kind, key, interaction.kind);
+            }
+            if interaction.key != key {
+                warn!("Replay key mismatch for {}: recorded '{}', requested '{}'", kind, interaction.key, key);
+            }
+            match interaction.outcome {
+                Outcome::Ok(value) => serde_json::from_value(value).context("Recorded interaction has an unexpected shape"),
+                Outcome::Error(message) => Err(anyhow!(message)),
+            }
+        }
+    }
+}
+
+/// Klucz interakcji z długiej treści (skrypt, prompt): SHA-256 hex, stały między wersjami Rusta i buildami,
+/// więc paczki nagrane wcześniej nadal pasują
+pub fn content_key(prefix: &str, content: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, content.as_bytes());
+    let hex: String = digest.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}:{}", prefix, hex)
+}
+
+/// Czy bieżące zadanie odtwarza paczkę (efekty uboczne, np. zapis cache, należy pominąć)
+pub fn is_replaying() -> bool {
+    CONTEXT
+        .try_with(|context| matches!(context.mode, Mode::Replay(_)))
+        .unwrap_or(false)
+}
+
+/// `tokio::spawn`, który zabiera do zadania nagrywanie lub odtwarzanie bieżącego zadania;
+/// zwykły `tokio::spawn` gubi kontekst i interakcje w nowym zadaniu nie byłyby przechwycone
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match CONTEXT.try_with(|context| context.clone()) {
+        Ok(context) => tokio::spawn(CONTEXT.scope(context, future)),
+        Err(_) => tokio::spawn(future),
+    }
+}
+
+/// Wykonuje `future` rejestrując wszystkie przechwycone interakcje
+pub async fn record<F: Future>(future: F) -> (F::Output, Vec<Interaction>) {
+    let context = Arc::new(ReplayContext { mode: Mode::Record(Mutex::new(Vec::new())) });
+    let output = CONTEXT.scope(context.clone(), future).await;
+
+    let interactions = match &context.mode {
+        Mode::Record(log) => std::mem::take(&mut *log.lock().unwrap()),
+        Mode::Replay(_) => Vec::new(),
+    };
+    (output, interactions)
+}
+
+/// Executes `future` against recorded interactions; also returns how many were left unused
+pub async fn replay<F: Future>(interactions: Vec<Interaction>, future: F) -> (F::Output, usize) {
+    let context = Arc::new(ReplayContext { mode: Mode::Replay(Mutex::new(interactions.into())) });
+    let output = CONTEXT.scope(context.clone(), future).await;
+
+    let unused = match &context.mode {
+        Mode::Replay(queue) => queue.lock().unwrap().len(),
+        Mode::Record(_) => 0,
+    };
+    (output, unused)
+}
+
+/// Removes fields that legitimately differ between runs (timings, ids) before comparing outputs
+pub fn strip_volatile(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .filter(|(key, _)| !(key.ends_with("_ms") || key.ends_with("_id") || key.as_str() == "timestamp"))
+            .map(|(key, value)| (key.clone(), strip_volatile(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        serde_json::Value::Array(items) => items.iter().map(strip_volatile).collect::<Vec<_>>().into(),
+        other => other.clone(),
+    }
+}
+
+/// Paczki replay przechowywane jako zaszyfrowane pliki JSON w katalogu `REPLAY_DIR` (zawierają dane użytkownika i HTML stron).
+/// Bundles from the field are reproduced by copying them into this directory; it needs the same ENCRYPTION_KEY.
+#[derive(Debug, Clone)]
+pub struct ReplayStore {
+    dir: PathBuf,
+}
+
+impl ReplayStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path_for(&self, id: &str) -> Result<PathBuf> {
+        // Tylko UUID, aby identyfikator nie mógł wskazać pliku poza katalogiem
+        let id = uuid::Uuid::parse_str(id).context("Invalid replay id")?;
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    /// Zapisuje paczkę zaszyfrowaną kluczem ENCRYPTION_KEY; bez klucza odmawia
+    pub fn save(&self, bundle: &ReplayBundle) -> Result<PathBuf> {
+        let Some(cipher) = crypto::content_cipher() else {
+            bail!("ENCRYPTION_KEY is not set; replay bundles are only written encrypted");
+        };
+        self.save_with(bundle, cipher)
+    }
+
+    fn save_with(&self, bundle: &ReplayBundle, cipher: &ContentCipher) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create replay directory {}", self.dir.display()))?;
+
+        let path = self.path_for(&bundle.id)?;
+        let contents = serde_json::to_string(bundle).context("Failed to serialize replay bundle")?;
+        std::fs::write(&path, cipher.encrypt(&contents)?)
+            .map_err(|e| anyhow!(crate::storage::describe_io_error("Failed to write replay bundle", &e)))?;
+
+        info!("Replay bundle {} saved ({} interactions)", bundle.id, bundle.interactions.len());
+        Ok(path)
+    }
+
+    /// Paczki zapisane przed szyfrowaniem są czytane jako jawny JSON
+    pub fn load(&self, id: &str) -> Result<ReplayBundle> {
+        self.load_with(id, crypto::decrypt_any)
+    }
+
+    fn load_with(&self, id: &str, decrypt: impl Fn(&str) -> Result<String>) -> Result<ReplayBundle> {
+        let path = self.path_for(id)?;
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Replay bundle {} not found", id))?;
+        let contents = if contents.starts_with(crypto::ENCRYPTED_PREFIX) {
+            decrypt(&contents).context("Failed to decrypt replay bundle")?
+        } else {
+            contents
+        };
+        serde_json::from_str(&contents).context("Failed to parse replay bundle")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn pipeline(calls: Arc<Mutex<u32>>) -> Result<String> {
+        let html: String = intercept(InteractionKind::PageHtml, "https://example.com", || async {
+            *calls.lock().unwrap() += 1;
+            Ok("<form id=\"apply\"></form>".to_string())
+        })
+        .await?;
+        Ok(html.to_uppercase())
+    }
+
+    #[tokio::test]
+    async fn test_replay_returns_recorded_responses() {
+        let calls = Arc::new(Mutex::new(0));
+        let (recorded, interactions) = record(pipeline(calls.clone())).await;
+        assert_eq!(interactions.len(), 1);
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        let (replayed, unused) = replay(interactions, pipeline(calls.clone())).await;
+        assert_eq!(replayed.unwrap(), recorded.unwrap());
+        assert_eq!(unused, 0);
+        // Odtworzenie nie wykonuje prawdziwej interakcji
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        let (exhausted, _) = replay(Vec::new(), pipeline(calls)).await;
+        assert!(exhausted.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spawned_tasks_keep_the_replay_context() {
+        let calls = Arc::new(Mutex::new(0));
+        let spawned = |calls: Arc<Mutex<u32>>| async move { spawn(pipeline(calls)).await.unwrap() };
+        let (recorded, interactions) = record(spawned(calls.clone())).await;
+        assert_eq!(interactions.len(), 1);
+
+        let (replayed, unused) = replay(interactions, spawned(calls.clone())).await;
+        assert_eq!(replayed.unwrap(), recorded.unwrap());
+        assert_eq!((unused, *calls.lock().unwrap()), (0, 1));
+    }
+
+    #[test]
+    fn test_content_key_is_stable() {
+        // Stała wartość: klucz nie może zależeć od wersji kompilatora
+        assert_eq!(content_key("heal:3", "abc"), "heal:3:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_bundles_are_stored_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ReplayStore::new(dir.path());
+        let cipher = ContentCipher::from_secret("replay-test-key").unwrap();
+        let bundle = ReplayBundle {
+            id: uuid::Uuid::new_v4().to_string(),
+            pipeline: "dsl_generate".to_string(),
+            recorded_at: Utc::now(),
+            input: serde_json::json!({ "user_data": { "email": "jan@example.com" } }),
+            interactions: Vec::new(),
+            output: serde_json::Value::Null,
+        };
+
+        let path = store.save_with(&bundle, &cipher).unwrap();
+        let raw = std::fs::read_to_string(path).unwrap();
+        assert!(raw.starts_with(crypto::ENCRYPTED_PREFIX) && !raw.contains("jan@example.com"));
+
+        let loaded = store.load_with(&bundle.id, |stored| cipher.decrypt(stored)).unwrap();
+        assert_eq!(loaded.input, bundle.input);
+    }
+}
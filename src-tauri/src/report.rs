@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use chromiumoxide::Browser;
+use chromiumoxide::cdp::browser_protocol::page::PrintToPdfParams;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::run_history::AutomationRun;
+use crate::tagui::{self, StepResult, StepStatus};
+use crate::trace::{ExecutionTrace, TraceKind};
+
+/// Krok przebiegu w raporcie
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportStep {
+    pub line: usize,
+    pub command: String,
+    pub status: StepStatus,
+    /// Łączny czas kroku z osi czasu przebiegu (pętle sumują iteracje)
+    pub duration_ms: Option<i64>,
+    /// Zrzut ekranu po kroku jako data URI
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<String>,
+}
+
+/// Dane raportu przebiegu: zapis z historii, kroki, czasy i zrzuty ekranu
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub run: AutomationRun,
+    pub steps: Vec<ReportStep>,
+    /// Kroki odtworzone z `failed_line`, bo wynik przebiegu nie jest już w pamięci
+    pub steps_inferred: bool,
+    /// Czas wg rodzaju odcinka (kolejka, start, kroki...) gdy ślad był dostępny
+    pub totals_ms: Option<BTreeMap<TraceKind, i64>>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Numer linii ze zrzutu `step-NNN.png` zapisanego przez `tagui::screenshot_file_name`
+pub fn screenshot_line(path: &str) -> Option<usize> {
+    Path::new(path)
+        .file_name()?
+        .to_str()?
+        .strip_prefix("step-")?
+        .strip_suffix(".png")?
+        .parse()
+        .ok()
+}
+
+/// Odtwarza statusy kroków z historii: kroki przed `failed_line` przeszły, ona sama nie, reszta pominięta.
+/// Bez `failed_line` nieudany przebieg nie pozwala nic ustalić - wszystkie kroki są pominięte.
+fn infer_steps(script: &str, succeeded: bool, failed_line: Option<usize>) -> Vec<StepResult> {
+    tagui::script_commands(script)
+        .into_iter()
+        .filter(|(_, command)| !tagui::is_block_keyword(command))
+        .map(|(line, command)| {
+            let status = match failed_line {
+                _ if succeeded => StepStatus::Succeeded,
+                Some(failed) if line < failed => StepStatus::Succeeded,
+                Some(failed) if line == failed => StepStatus::Failed,
+                _ => StepStatus::Skipped,
+            };
+            StepResult { line, command, status }
+        })
+        .collect()
+}
+
+/// Składa raport; `steps` to wynik z `RunManager`, jeśli przebieg jest jeszcze zapamiętany
+pub fn build_report(
+    run: AutomationRun,
+    steps: Option<Vec<StepResult>>,
+    trace: Option<&ExecutionTrace>,
+    screenshots: HashMap<usize, Vec<u8>>,
+) -> RunReport {
+    let steps_inferred = steps.is_none();
+    let steps = steps.unwrap_or_else(|| {
+        let succeeded = run.status == "succeeded";
+        infer_steps(run.script.as_deref().unwrap_or_default(), succeeded, run.failed_line.map(|line| line as usize))
+    });
+
+    let mut durations: HashMap<usize, i64> = HashMap::new();
+    for event in trace.map(|trace| trace.events.as_slice()).unwrap_or_default() {
+        if let (Some(line), TraceKind::Step | TraceKind::Wait) = (event.line, event.kind) {
+            *durations.entry(line).or_insert(0) += event.end_ms - event.start_ms;
+        }
+    }
+
+    let steps = steps
+        .into_iter()
+        .map(|step| ReportStep {
+            duration_ms: durations.get(&step.line).copied(),
+            screenshot: screenshots.get(&step.line).map(|png| format!("data:image/png;base64,{}", STANDARD.encode(png))),
+            line: step.line,
+            command: step.command,
+            status: step.status,
+        })
+        .collect();
+
+    RunReport {
+        run,
+        steps,
+        steps_inferred,
+        totals_ms: trace.map(|trace| trace.totals_ms.clone()),
+        generated_at: Utc::now(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn status_label(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Succeeded => "succeeded",
+        StepStatus::Failed => "failed",
+        StepStatus::Skipped => "skipped",
+    }
+}
+
+fn format_ms(ms: i64) -> String {
+    if ms >= 1000 {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    } else {
+        format!("{} ms", ms)
+    }
+}
+
+const REPORT_STYLE: &str = "body{font-family:-apple-system,Segoe UI,Roboto,sans-serif;margin:2em;color:#222}\
+h1{font-size:1.4em}h2{font-size:1.1em;margin-top:2em}\
+table{border-collapse:collapse;width:100%}th,td{border:1px solid #ddd;padding:6px 8px;text-align:left;vertical-align:top}\
+th{background:#f5f5f5}code,pre{font-family:Menlo,Consolas,monospace;font-size:0.9em}\
+pre{background:#f7f7f7;padding:1em;white-space:pre-wrap;word-break:break-word}\
+.succeeded{color:#1a7f37}.failed{color:#cf222e;font-weight:bold}.skipped{color:#888}\
+img{max-width:480px;border:1px solid #ccc}.note{color:#666;font-size:0.9em}";
+
+/// Samodzielna strona HTML (style i zrzuty osadzone), do pobrania albo wydruku do PDF
+pub fn render_html(report: &RunReport) -> String {
+    let run = &report.run;
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+    html.push_str(&format!("<title>Run report {}</title>", escape_html(&run.id)));
+    html.push_str(&format!("<style>{}</style></head><body>\n", REPORT_STYLE));
+    html.push_str(&format!("<h1>Run report <code>{}</code></h1>\n", escape_html(&run.id)));
+
+    html.push_str("<table>\n");
+    let mut summary = vec![
+        ("Status", format!("<span class=\"{0}\">{0}</span>", escape_html(&run.status))),
+        ("Started", run.created_at.to_rfc3339()),
+        ("Finished", run.finished_at.to_rfc3339()),
+        ("Duration", format_ms(run.duration_ms)),
+    ];
+    if let Some(exit_code) = run.exit_code {
+        summary.push(("Exit code", exit_code.to_string()));
+    }
+    if let Some(failed_line) = run.failed_line {
+        summary.push(("Failed line", failed_line.to_string()));
+    }
+    if let Some(replay_of) = &run.replay_of {
+        summary.push(("Replay of", format!("<code>{}</code>", escape_html(replay_of))));
+    }
+    for (label, value) in summary {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", label, value));
+    }
+    html.push_str("</table>\n");
+
+    if let Some(totals) = &report.totals_ms {
+        html.push_str("<h2>Timing</h2>\n<table><tr><th>Phase</th><th>Time</th></tr>\n");
+        for (kind, ms) in totals {
+            let kind = serde_json::to_value(kind).ok().and_then(|value| value.as_str().map(|name| name.to_string())).unwrap_or_default();
+            html.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", escape_html(&kind), format_ms(*ms)));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Steps</h2>\n");
+    if report.steps_inferred {
+        html.push_str("<p class=\"note\">Step results are no longer in memory; statuses are derived from the failed line.</p>\n");
+    }
+    html.push_str("<table><tr><th>Line</th><th>Command</th><th>Status</th><th>Time</th><th>Screenshot</th></tr>\n");
+    for step in &report.steps {
+        let status = status_label(step.status);
+        html.push_str(&format!(
+            "<tr><td>{}</td><td><code>{}</code></td><td class=\"{}\">{}</td><td>{}</td><td>{}</td></tr>\n",
+            step.line,
+            escape_html(&step.command),
+            status,
+            status,
+            step.duration_ms.map(format_ms).unwrap_or_default(),
+            step.screenshot
+                .as_deref()
+                .map(|uri| format!("<img src=\"{}\" alt=\"Screenshot after line {}\">", uri, step.line))
+                .unwrap_or_default(),
+        ));
+    }
+    html.push_str("</table>\n");
+
+    if let Some(error) = run.error_output.as_deref().filter(|error| !error.trim().is_empty()) {
+        html.push_str(&format!("<h2>Error</h2>\n<pre>{}</pre>\n", escape_html(error)));
+    }
+    if let Some(script) = &run.script {
+        html.push_str(&format!("<h2>Script</h2>\n<pre>{}</pre>\n", escape_html(script)));
+    }
+
+    html.push_str(&format!(
+        "<p class=\"note\">Generated {}</p>\n</body></html>\n",
+        report.generated_at.to_rfc3339()
+    ));
+    html
+}
+
+/// Drukuje stronę raportu do PDF w przeglądarce bez interfejsu
+pub async fn render_pdf(html: &str) -> Result<Vec<u8>> {
+    let (mut browser, mut handler) = Browser::launch(
+        chromiumoxide::BrowserConfig::builder()
+            .build()
+            .map_err(|e| anyhow::anyhow!(e))?
+    ).await.context("Failed to launch browser for the PDF report")?;
+
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+
+    let printed = async {
+        let page = browser.new_page("about:blank").await?;
+        page.set_content(html).await?;
+        let params = PrintToPdfParams { print_background: Some(true), ..Default::default() };
+        page.pdf(params).await
+    }
+    .await
+    .context("Failed to print the report to PDF");
+
+    if let Err(e) = browser.close().await {
+        tracing::warn!("Failed to close report browser: {}", e);
+    }
+    handle.abort();
+
+    printed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tagui::{ExecutionResult, ExecutionStatus};
+
+    #[test]
+    fn test_report_infers_steps_and_escapes() {
+        let script = "click \"#open\"\nif present(\"#banner\")\n  click \"#close\"\nend\ntype \"#q\" \"<b>\"";
+        let result = ExecutionResult {
+            status: ExecutionStatus::Failed,
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: String::new(),
+            steps: Vec::new(),
+            duration_ms: 2_500,
+            failed_line: Some(3),
+            error: Some("Element <#close> not found".to_string()),
+            page_html: None,
+        };
+        let run = AutomationRun::from_result("run-1", script, None, serde_json::json!({}), &result);
+
+        assert_eq!(screenshot_line(&format!("/data/runs/run-1/{}", tagui::screenshot_file_name(3))), Some(3));
+        assert_eq!(screenshot_line("/data/runs/run-1/page.png"), None);
+
+        let screenshots = HashMap::from([(1, vec![0x89, b'P', b'N', b'G'])]);
+        let report = build_report(run, None, None, screenshots);
+        let statuses: Vec<(usize, StepStatus)> = report.steps.iter().map(|step| (step.line, step.status)).collect();
+        assert_eq!(statuses, vec![(1, StepStatus::Succeeded), (3, StepStatus::Failed), (5, StepStatus::Skipped)]);
+        assert!(report.steps_inferred);
+        assert!(report.steps[0].screenshot.as_deref().unwrap().starts_with("data:image/png;base64,"));
+
+        let html = render_html(&report);
+        assert!(html.contains("type &quot;#q&quot; &quot;&lt;b&gt;&quot;"));
+        assert!(html.contains("Element &lt;#close&gt; not found"));
+        assert!(!html.contains("<b>"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Porównanie dwóch przebiegów tego samego skryptu krok po kroku: status, czas i zrzut ekranu każdej linii
+//! obu przebiegów, z zaznaczeniem pierwszego kroku, od którego przebiegi się rozeszły.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use codialog_core::tagui::StepStatus;
+
+use crate::report::{ReportStep, RunReport};
+
+/// Krok jest wyraźnie wolniejszy/szybszy, gdy czas zmienił się co najmniej o tyle...
+const TIMING_MIN_DELTA_MS: i64 = 500;
+
+/// ...i co najmniej o tę część czasu z przebiegu `a`
+const TIMING_MIN_RATIO: f64 = 0.5;
+
+/// Zrzut ekranu kroku zapisany jako artefakt przebiegu
+#[derive(Debug, Clone, Serialize)]
+pub struct StepScreenshot {
+    pub artifact_id: String,
+    #[serde(skip)]
+    pub sha256: Option<String>,
+}
+
+/// Krok w jednym z porównywanych przebiegów
+#[derive(Debug, Clone, Serialize)]
+pub struct StepSide {
+    pub status: StepStatus,
+    pub duration_ms: Option<i64>,
+    pub screenshot: Option<StepScreenshot>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepChange {
+    Unchanged,
+    /// Krok przeszedł w jednym przebiegu, a w drugim zawiódł albo został pominięty
+    StatusChanged,
+    Slower,
+    Faster,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepComparison {
+    pub line: usize,
+    pub command: String,
+    pub a: Option<StepSide>,
+    pub b: Option<StepSide>,
+    pub change: StepChange,
+    /// `b - a`, gdy oba czasy są znane
+    pub duration_delta_ms: Option<i64>,
+    /// Treść zrzutów się różni; brak, gdy któregoś zrzutu nie ma
+    pub screenshot_changed: Option<bool>,
+}
+
+/// Przebieg w nagłówku porównania
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub id: String,
+    pub status: String,
+    pub failed_line: Option<i32>,
+    pub duration_ms: i64,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    /// Statusy kroków odtworzone z historii, bo wynik przebiegu nie jest już w pamięci (bez czasów kroków)
+    pub steps_inferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunComparison {
+    pub a: RunSummary,
+    pub b: RunSummary,
+    pub duration_delta_ms: i64,
+    /// Pierwsza linia, której status się różni - zwykle tu szukać przyczyny
+    pub first_divergence: Option<usize>,
+    pub steps: Vec<StepComparison>,
+}
+
+fn summary(report: &RunReport) -> RunSummary {
+    RunSummary {
+        id: report.run.id.clone(),
+        status: report.run.status.clone(),
+        failed_line: report.run.failed_line,
+        duration_ms: report.run.duration_ms,
+        finished_at: report.run.finished_at,
+        steps_inferred: report.steps_inferred,
+    }
+}
+
+fn timing_change(a: i64, b: i64) -> StepChange {
+    let delta = b - a;
+    if delta.abs() < TIMING_MIN_DELTA_MS || (delta.abs() as f64) < a as f64 * TIMING_MIN_RATIO {
+        StepChange::Unchanged
+    } else if delta > 0 {
+        StepChange::Slower
+    } else {
+        StepChange::Faster
+    }
+}
+
+/// Zestawia kroki dwóch raportów tego samego skryptu po numerach linii; `screenshots_*` to zrzuty wg linii
+pub fn compare(
+    a: &RunReport,
+    b: &RunReport,
+    mut screenshots_a: HashMap<usize, StepScreenshot>,
+    mut screenshots_b: HashMap<usize, StepScreenshot>,
+) -> RunComparison {
+    let steps_a: HashMap<usize, &ReportStep> = a.steps.iter().map(|step| (step.line, step)).collect();
+    let steps_b: HashMap<usize, &ReportStep> = b.steps.iter().map(|step| (step.line, step)).collect();
+    // Ten sam skrypt ma te same linie; krok obecny tylko po jednej stronie (np. w pominiętym bloku) też jest pokazany
+    let mut lines: Vec<(usize, String)> = a.steps.iter().chain(&b.steps).map(|step| (step.line, step.command.clone())).collect();
+    lines.sort_by_key(|(line, _)| *line);
+    lines.dedup_by_key(|(line, _)| *line);
+
+    let steps: Vec<StepComparison> = lines
+        .into_iter()
+        .map(|(line, command)| {
+            let side = |step: Option<&&ReportStep>, screenshots: &mut HashMap<usize, StepScreenshot>| {
+                step.map(|step| StepSide { status: step.status, duration_ms: step.duration_ms, screenshot: screenshots.remove(&line) })
+            };
+            let step_a = side(steps_a.get(&line), &mut screenshots_a);
+            let step_b = side(steps_b.get(&line), &mut screenshots_b);
+
+            let duration_delta_ms = match (&step_a, &step_b) {
+                (Some(a), Some(b)) => a.duration_ms.zip(b.duration_ms).map(|(a, b)| b - a),
+                _ => None,
+            };
+            let change = match (&step_a, &step_b) {
+                (Some(a), Some(b)) if a.status != b.status => StepChange::StatusChanged,
+                (Some(a), Some(b)) => a.duration_ms.zip(b.duration_ms).map(|(a, b)| timing_change(a, b)).unwrap_or(StepChange::Unchanged),
+                _ => StepChange::StatusChanged,
+            };
+            let screenshot_changed = match (&step_a, &step_b) {
+                (Some(StepSide { screenshot: Some(a), .. }), Some(StepSide { screenshot: Some(b), .. })) => {
+                    Some(a.artifact_id != b.artifact_id && (a.sha256.is_none() || a.sha256 != b.sha256))
+                }
+                _ => None,
+            };
+            StepComparison { line, command, a: step_a, b: step_b, change, duration_delta_ms, screenshot_changed }
+        })
+        .collect();
+
+    RunComparison {
+        a: summary(a),
+        b: summary(b),
+        duration_delta_ms: b.run.duration_ms - a.run.duration_ms,
+        first_divergence: steps.iter().find(|step| step.change == StepChange::StatusChanged).map(|step| step.line),
+        steps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_history::AutomationRun;
+
+    fn report(id: &str, steps: &[(usize, StepStatus, Option<i64>)]) -> RunReport {
+        let mut run = AutomationRun::rejected("click \"#a\"\nclick \"#b\"\nclick \"#c\"", None, serde_json::Value::Null, "");
+        run.id = id.to_string();
+        RunReport {
+            run,
+            steps: steps
+                .iter()
+                .map(|(line, status, duration_ms)| ReportStep {
+                    line: *line,
+                    command: format!("click \"#{}\"", line),
+                    status: *status,
+                    duration_ms: *duration_ms,
+                    screenshot: None,
+                })
+                .collect(),
+            steps_inferred: false,
+            totals_ms: None,
+            generated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_compare_marks_first_divergence_and_slow_steps() {
+        let yesterday = report("a", &[(1, StepStatus::Succeeded, Some(300)), (2, StepStatus::Succeeded, Some(1000)), (3, StepStatus::Succeeded, Some(200))]);
+        let today = report("b", &[(1, StepStatus::Succeeded, Some(350)), (2, StepStatus::Succeeded, Some(4000)), (3, StepStatus::Failed, Some(30000))]);
+        let shot = |id: &str, sha: &str| StepScreenshot { artifact_id: id.to_string(), sha256: Some(sha.to_string()) };
+
+        let comparison = compare(
+            &yesterday,
+            &today,
+            HashMap::from([(2, shot("s1", "aaa")), (3, shot("s2", "bbb"))]),
+            HashMap::from([(2, shot("s3", "aaa")), (3, shot("s4", "ccc"))]),
+        );
+        let changes: Vec<StepChange> = comparison.steps.iter().map(|step| step.change).collect();
+        assert_eq!(changes, vec![StepChange::Unchanged, StepChange::Slower, StepChange::StatusChanged]);
+        assert_eq!(comparison.first_divergence, Some(3));
+        assert_eq!(comparison.steps[1].duration_delta_ms, Some(3000));
+        assert_eq!(comparison.steps[1].screenshot_changed, Some(false));
+        assert_eq!(comparison.steps[2].screenshot_changed, Some(true));
+        assert_eq!(comparison.steps[0].screenshot_changed, None);
+
+        // Krok obecny tylko w jednym przebiegu też jest rozbieżnością
+        let shorter = report("c", &[(1, StepStatus::Succeeded, None)]);
+        let comparison = compare(&shorter, &yesterday, HashMap::new(), HashMap::new());
+        assert_eq!(comparison.first_divergence, Some(2));
+        assert!(comparison.steps[1].a.is_none());
+    }
+}
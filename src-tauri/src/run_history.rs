@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use anyhow::{Result, Context};
+use tracing::{info, debug};
+use chrono::{DateTime, Duration, Utc};
+use ring::digest;
+
+use crate::archive::RESTORE_HOLD_DAYS;
+use crate::perf::{self, OperationKind};
+use crate::tagui::{ExecutionResult, ExecutionStatus};
+
+/// Ile znaków stderr zachować w historii (ostatnie linie są najbardziej przydatne)
+const MAX_ERROR_OUTPUT_CHARS: usize = 16 * 1024;
+
+/// Status przebiegu odrzuconego przed uruchomieniem TagUI (np. brak zmiennej lub sekretu)
+pub const STATUS_REJECTED: &str = "rejected";
+
+/// Zapis pojedynczego wywołania /rpa/run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationRun {
+    pub id: String,
+    pub script_hash: String,
+    pub status: String,
+    pub session_id: Option<String>,
+    pub exit_code: Option<i32>,
+    pub failed_line: Option<i32>,
+    pub error_output: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Przebieg, który ten przebieg powtarza (`/rpa/runs/:id/replay`)
+    #[serde(default)]
+    pub replay_of: Option<String>,
+    /// Skrypt przed podstawieniem zmiennych (bez wartości sekretów); tylko w widoku szczegółów
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// Variables, secret references and run options of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+impl AutomationRun {
+    /// Builds the history entry from a finished run; the result is already secret-masked
+    pub fn from_result(
+        run_id: &str,
+        script: &str,
+        session_id: Option<String>,
+        parameters: serde_json::Value,
+        result: &ExecutionResult,
+    ) -> Self {
+        let error_output = (!result.success()).then(|| error_output(result.error.as_deref(), &result.stderr));
+        let finished_at = Utc::now();
+        Self {
+            id: run_id.to_string(),
+            script_hash: script_hash(script),
+            status: status_name(result.status),
+            session_id,
+            exit_code: result.exit_code,
+            failed_line: result.failed_line.map(|line| line as i32),
+            error_output,
+            duration_ms: result.duration_ms as i64,
+            created_at: finished_at - Duration::milliseconds(result.duration_ms as i64),
+            finished_at,
+            replay_of: None,
+            script: Some(script.to_string()),
+            parameters: Some(parameters),
+        }
+    }
+
+    /// Przebieg, który nie doszedł do TagUI
+    pub fn rejected(script: &str, session_id: Option<String>, parameters: serde_json::Value, error: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            script_hash: script_hash(script),
+            status: STATUS_REJECTED.to_string(),
+            session_id,
+            exit_code: None,
+            failed_line: None,
+            error_output: Some(error.to_string()),
+            duration_ms: 0,
+            created_at: now,
+            finished_at: now,
+            replay_of: None,
+            script: Some(script.to_string()),
+            parameters: Some(parameters),
+        }
+    }
+
+    pub fn with_replay_of(mut self, replay_of: Option<String>) -> Self {
+        self.replay_of = replay_of;
+        self
+    }
+}
+
+/// Filtry listy przebiegów
+#[derive(Debug, Clone, Default)]
+pub struct RunFilter {
+    pub session_id: Option<String>,
+    pub status: Option<String>,
+    pub script_hash: Option<String>,
+    pub replay_of: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+pub fn script_hash(script: &str) -> String {
+    digest::digest(&digest::SHA256, script.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn status_name(status: ExecutionStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(|name| name.to_string()))
+        .unwrap_or_else(|| format!("{:?}", status).to_lowercase())
+}
+
+/// Komunikat błędu i końcówka stderr
+fn error_output(error: Option<&str>, stderr: &str) -> String {
+    let stderr = stderr.trim();
+    let skip = stderr.chars().count().saturating_sub(MAX_ERROR_OUTPUT_CHARS);
+    let tail: String = stderr.chars().skip(skip).collect();
+    match (error, tail.is_empty()) {
+        (Some(error), true) => error.to_string(),
+        (Some(error), false) => format!("{}\n{}", error, tail),
+        (None, _) => tail,
+    }
+}
+
+/// Historia przebiegów automatyzacji w tabeli `automation_runs`
+#[derive(Debug, Clone)]
+pub struct RunHistory {
+    db_pool: PgPool,
+}
+
+impl RunHistory {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę historii przebiegów
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing automation run history table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS automation_runs (
+                id UUID PRIMARY KEY,
+                script TEXT NOT NULL,
+                script_hash CHAR(64) NOT NULL,
+                status VARCHAR(20) NOT NULL, -- 'succeeded', 'failed', 'timed_out', 'cancelled', 'invalid_script', 'spawn_error', 'rejected'
+                session_id VARCHAR(255),
+                parameters JSONB NOT NULL DEFAULT '{}',
+                exit_code INTEGER,
+                failed_line INTEGER,
+                error_output TEXT,
+                duration_ms BIGINT NOT NULL DEFAULT 0,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                finished_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            );
+
+            ALTER TABLE automation_runs
+                ADD COLUMN IF NOT EXISTS replay_of UUID REFERENCES automation_runs(id) ON DELETE SET NULL;
+
+            -- Przebiegi starsze niż RUN_RETENTION_DAYS są archiwizowane, a potem eksportowane do ARCHIVE_DIR
+            ALTER TABLE automation_runs ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+            ALTER TABLE automation_runs ADD COLUMN IF NOT EXISTS restored_at TIMESTAMPTZ;
+
+            CREATE INDEX IF NOT EXISTS idx_automation_runs_replay_of ON automation_runs(replay_of);
+            CREATE INDEX IF NOT EXISTS idx_automation_runs_created_at ON automation_runs(created_at DESC);
+            CREATE INDEX IF NOT EXISTS idx_automation_runs_session_id ON automation_runs(session_id, created_at DESC);
+            CREATE INDEX IF NOT EXISTS idx_automation_runs_script_hash ON automation_runs(script_hash);
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create automation_runs table")?;
+
+        Ok(())
+    }
+
+    pub async fn record(&self, run: &AutomationRun) -> Result<()> {
+        let query = sqlx::query(
+            r#"
+            INSERT INTO automation_runs
+                (id, script, script_hash, status, session_id, parameters, exit_code,
+                 failed_line, error_output, duration_ms, created_at, finished_at, replay_of)
+            VALUES ($1::uuid, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13::uuid)
+            "#,
+        )
+        .bind(&run.id)
+        .bind(run.script.as_deref().unwrap_or_default())
+        .bind(&run.script_hash)
+        .bind(&run.status)
+        .bind(&run.session_id)
+        .bind(run.parameters.clone().unwrap_or_else(|| serde_json::json!({})))
+        .bind(run.exit_code)
+        .bind(run.failed_line)
+        .bind(&run.error_output)
+        .bind(run.duration_ms)
+        .bind(run.created_at)
+        .bind(run.finished_at)
+        .bind(&run.replay_of)
+        .execute(&self.db_pool);
+        perf::timed(OperationKind::DbQuery, "automation_runs.record", serde_json::json!({ "run_id": run.id }), query)
+            .await
+            .context("Failed to record automation run")?;
+
+        debug!("Automation run recorded: {} ({})", run.id, run.status);
+        Ok(())
+    }
+
+    /// Lista przebiegów od najnowszych, bez skryptu i parametrów
+    pub async fn list(&self, filter: &RunFilter) -> Result<Vec<AutomationRun>> {
+        // Porównanie na kolumnie uuid, żeby filtr korzystał z indeksu; tekst spoza UUID nie wskazuje żadnego przebiegu
+        let Ok(replay_of) = filter.replay_of.as_deref().map(uuid::Uuid::parse_str).transpose() else {
+            return Ok(Vec::new());
+        };
+        let query = sqlx::query(
+            r#"
+            SELECT id::text AS id, script_hash, status, session_id, exit_code, failed_line,
+                   error_output, duration_ms, created_at, finished_at, replay_of::text AS replay_of
+            FROM automation_runs
+            WHERE archived_at IS NULL
+              AND ($1::text IS NULL OR session_id = $1)
+              AND ($2::text IS NULL OR status = $2)
+              AND ($3::text IS NULL OR script_hash = $3)
+              AND ($4::uuid IS NULL OR replay_of = $4)
+            ORDER BY created_at DESC
+            LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(&filter.session_id)
+        .bind(&filter.status)
+        .bind(&filter.script_hash)
+        .bind(replay_of)
+        .bind(filter.limit)
+        .bind(filter.offset)
+        .fetch_all(&self.db_pool);
+        let context = serde_json::json!({
+            "status": filter.status,
+            "script_hash": filter.script_hash,
+            "limit": filter.limit,
+            "offset": filter.offset,
+        });
+        let rows = perf::timed(OperationKind::DbQuery, "automation_runs.list", context, query)
+            .await
+            .context("Failed to list automation runs")?;
+
+        Ok(rows.iter().map(|row| run_from_row(row, false)).collect())
+    }
+
+    /// Pobiera przebieg po ID wraz ze skryptem i parametrami
+    pub async fn get(&self, run_id: &str) -> Result<Option<AutomationRun>> {
+        if uuid::Uuid::parse_str(run_id).is_err() {
+            return Ok(None);
+        }
+
+        let row = sqlx::query(
+            r#"
+            SELECT id::text AS id, script, script_hash, status, session_id, parameters, exit_code,
+                   failed_line, error_output, duration_ms, created_at, finished_at, replay_of::text AS replay_of
+            FROM automation_runs
+            WHERE id = $1::uuid AND archived_at IS NULL
+            "#,
+        )
+        .bind(run_id)
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to fetch automation run")?;
+
+        Ok(row.map(|row| run_from_row(&row, true)))
+    }
+
+    /// Archiwizuje (soft delete) przebiegi zakończone ponad `days` dni temu; przywrócone z archiwum
+    /// czekają jeszcze `RESTORE_HOLD_DAYS`
+    pub async fn archive_older_than(&self, days: u64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE automation_runs SET archived_at = NOW()
+            WHERE archived_at IS NULL
+              AND finished_at < NOW() - make_interval(days => $1)
+              AND (restored_at IS NULL OR restored_at < NOW() - make_interval(days => $2))
+            "#,
+        )
+        .bind(days.min(i32::MAX as u64) as i32)
+        .bind(RESTORE_HOLD_DAYS)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to archive old automation runs")?;
+
+        if result.rows_affected() > 0 {
+            info!("Archived {} automation runs older than {} days", result.rows_affected(), days);
+        }
+        Ok(result.rows_affected())
+    }
+}
+
+fn run_from_row(row: &sqlx::postgres::PgRow, detail: bool) -> AutomationRun {
+    AutomationRun {
+        id: row.get("id"),
+        script_hash: row.get::<String, _>("script_hash").trim().to_string(),
+        status: row.get("status"),
+        session_id: row.get("session_id"),
+        exit_code: row.get("exit_code"),
+        failed_line: row.get("failed_line"),
+        error_output: row.get("error_output"),
+        duration_ms: row.get("duration_ms"),
+        created_at: row.get("created_at"),
+        finished_at: row.get("finished_at"),
+        replay_of: row.get("replay_of"),
+        script: detail.then(|| row.get("script")),
+        parameters: detail.then(|| row.get("parameters")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_from_result() {
+        let result = ExecutionResult {
+            status: ExecutionStatus::TimedOut,
+            exit_code: None,
+            stdout: "click #submit".to_string(),
+            stderr: "x".repeat(MAX_ERROR_OUTPUT_CHARS + 10),
+            steps: Vec::new(),
+            duration_ms: 1500,
+            failed_line: Some(3),
+            error: Some("Run timed out".to_string()),
+            page_html: None,
+        };
+        let run = AutomationRun::from_result("run-1", "click \"#submit\"", None, serde_json::json!({}), &result);
+
+        assert_eq!(run.status, "timed_out");
+        assert_eq!(run.script_hash, script_hash("click \"#submit\""));
+        assert_eq!(run.script_hash.len(), 64);
+        assert_eq!(run.failed_line, Some(3));
+        assert_eq!((run.finished_at - run.created_at).num_milliseconds(), 1500);
+
+        let error_output = run.error_output.unwrap();
+        assert!(error_output.starts_with("Run timed out\n"));
+        assert_eq!(error_output.len(), "Run timed out\n".len() + MAX_ERROR_OUTPUT_CHARS);
+    }
+}
@@ -0,0 +1,519 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use anyhow::{Result, Context, anyhow, bail};
+use tracing::{info, warn, debug};
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use codialog_types::automation::ScheduleRequest;
+
+use crate::budget::BudgetStore;
+use crate::jobs::{JobOwner, JobQueue};
+
+/// Jak daleko szukać następnego terminu; wyrażenie bez terminu w tym czasie jest odrzucane
+const LOOKAHEAD_DAYS: i64 = 400;
+
+/// Ile kolejnych terminów pokazać po utworzeniu harmonogramu
+pub const UPCOMING_RUNS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ordinal {
+    Nth(u32),
+    Last,
+}
+
+/// Dni, w które harmonogram się uruchamia
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DaySpec {
+    EveryDay,
+    /// Poniedziałek-piątek poza świętami harmonogramu
+    BusinessDays,
+    Weekdays(Vec<Weekday>),
+    /// Np. pierwszy poniedziałek miesiąca
+    NthWeekday(Ordinal, Weekday),
+    /// Np. ostatni dzień roboczy miesiąca
+    NthBusinessDay(Ordinal),
+}
+
+/// Wyrażenie harmonogramu w języku naturalnym, np. "every weekday at 9", "first Monday of month at 8:30",
+/// "last business day of month at 17:00"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleExpression {
+    days: DaySpec,
+    time: NaiveTime,
+}
+
+impl ScheduleExpression {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let normalized = expression.trim().to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ");
+        let (days, time) = normalized
+            .rsplit_once(" at ")
+            .ok_or_else(|| anyhow!("Schedule expression needs a time, e.g. \"every weekday at 9\""))?;
+        Ok(Self { days: parse_days(days)?, time: parse_time(time)? })
+    }
+
+    /// Czy harmonogram uruchamia się danego dnia (w strefie harmonogramu); święta są zawsze pomijane
+    fn matches(&self, date: NaiveDate, holidays: &BTreeSet<NaiveDate>) -> bool {
+        if holidays.contains(&date) {
+            return false;
+        }
+        match &self.days {
+            DaySpec::EveryDay => true,
+            DaySpec::BusinessDays => is_business_day(date, holidays),
+            DaySpec::Weekdays(weekdays) => weekdays.contains(&date.weekday()),
+            DaySpec::NthWeekday(ordinal, weekday) => {
+                date.weekday() == *weekday
+                    && match ordinal {
+                        Ordinal::Nth(n) => (date.day() - 1) / 7 + 1 == *n,
+                        Ordinal::Last => (date + Duration::days(7)).month() != date.month(),
+                    }
+            }
+            DaySpec::NthBusinessDay(ordinal) => {
+                if !is_business_day(date, holidays) {
+                    return false;
+                }
+                match ordinal {
+                    Ordinal::Nth(n) => {
+                        let earlier = (1..date.day())
+                            .filter_map(|day| date.with_day(day))
+                            .filter(|day| is_business_day(*day, holidays))
+                            .count() as u32;
+                        earlier + 1 == *n
+                    }
+                    Ordinal::Last => date
+                        .iter_days()
+                        .skip(1)
+                        .take_while(|day| day.month() == date.month())
+                        .all(|day| !is_business_day(day, holidays)),
+                }
+            }
+        }
+    }
+
+    /// Pierwszy termin po `after`; godzina liczona w strefie `timezone`, więc zmiana czasu jej nie przesuwa
+    pub fn next_after(&self, after: DateTime<Utc>, timezone: Tz, holidays: &BTreeSet<NaiveDate>) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&timezone).date_naive();
+        start
+            .iter_days()
+            .take(LOOKAHEAD_DAYS as usize)
+            .filter(|date| self.matches(*date, holidays))
+            .filter_map(|date| local_to_utc(timezone, date, self.time))
+            .find(|run_at| *run_at > after)
+    }
+
+    /// Kolejne `count` terminy po `after`
+    pub fn upcoming(&self, after: DateTime<Utc>, timezone: Tz, holidays: &BTreeSet<NaiveDate>, count: usize) -> Vec<DateTime<Utc>> {
+        let mut runs = Vec::new();
+        let mut cursor = after;
+        while runs.len() < count {
+            let Some(run_at) = self.next_after(cursor, timezone, holidays) else { break };
+            runs.push(run_at);
+            cursor = run_at;
+        }
+        runs
+    }
+}
+
+fn is_business_day(date: NaiveDate, holidays: &BTreeSet<NaiveDate>) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !holidays.contains(&date)
+}
+
+/// Lokalny czas na UTC; w godzinie pominiętej przy zmianie czasu termin przesuwa się o godzinę,
+/// w powtórzonej - bierze pierwsze wystąpienie
+fn local_to_utc(timezone: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    let local = date.and_time(time);
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(run_at) | LocalResult::Ambiguous(run_at, _) => Some(run_at.with_timezone(&Utc)),
+        LocalResult::None => timezone.from_local_datetime(&(local + Duration::hours(1))).earliest().map(|run_at| run_at.with_timezone(&Utc)),
+    }
+}
+
+fn parse_days(days: &str) -> Result<DaySpec> {
+    let days = days.strip_prefix("every ").unwrap_or(days);
+    if matches!(days, "day" | "daily") {
+        return Ok(DaySpec::EveryDay);
+    }
+    if matches!(days, "weekday" | "weekdays" | "business day" | "business days" | "working day" | "working days") {
+        return Ok(DaySpec::BusinessDays);
+    }
+    if let Some(rest) = days.strip_suffix(" of month").or_else(|| days.strip_suffix(" of the month")) {
+        let (ordinal, day) = rest.split_once(' ').ok_or_else(|| anyhow!("Unknown schedule days: {}", days))?;
+        let ordinal = parse_ordinal(ordinal)?;
+        if matches!(day, "business day" | "working day" | "weekday") {
+            return Ok(DaySpec::NthBusinessDay(ordinal));
+        }
+        return Ok(DaySpec::NthWeekday(ordinal, parse_weekday(day)?));
+    }
+    let weekdays = days
+        .split([',', ' '])
+        .filter(|word| !word.is_empty() && *word != "and")
+        .map(parse_weekday)
+        .collect::<Result<Vec<_>>>()?;
+    if weekdays.is_empty() {
+        bail!("Unknown schedule days: {}", days);
+    }
+    Ok(DaySpec::Weekdays(weekdays))
+}
+
+fn parse_ordinal(ordinal: &str) -> Result<Ordinal> {
+    match ordinal {
+        "first" | "1st" => Ok(Ordinal::Nth(1)),
+        "second" | "2nd" => Ok(Ordinal::Nth(2)),
+        "third" | "3rd" => Ok(Ordinal::Nth(3)),
+        "fourth" | "4th" => Ok(Ordinal::Nth(4)),
+        "last" => Ok(Ordinal::Last),
+        other => bail!("Unknown ordinal in schedule: {}", other),
+    }
+}
+
+fn parse_weekday(day: &str) -> Result<Weekday> {
+    let day = day.strip_suffix('s').filter(|day| day.ends_with("day")).unwrap_or(day);
+    day.parse::<Weekday>().map_err(|_| anyhow!("Unknown weekday in schedule: {}", day))
+}
+
+/// "9", "9:30", "09:30", "9am", "5:15 pm", "17:00"
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    let compact = time.replace(' ', "");
+    let (clock, offset) = match (compact.strip_suffix("am"), compact.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(0)),
+        (_, Some(clock)) => (clock, Some(12)),
+        _ => (compact.as_str(), None),
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let (Ok(mut hour), Ok(minute)) = (hour.parse::<u32>(), minute.parse::<u32>()) else {
+        bail!("Invalid schedule time: {}", time);
+    };
+    if let Some(offset) = offset {
+        if !(1..=12).contains(&hour) {
+            bail!("Invalid schedule time: {}", time);
+        }
+        hour = hour % 12 + offset;
+    }
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(|| anyhow!("Invalid schedule time: {}", time))
+}
+
+pub fn parse_timezone(timezone: &str) -> Result<Tz> {
+    timezone.parse::<Tz>().map_err(|_| anyhow!("Unknown time zone: {}", timezone))
+}
+
+/// Harmonogram zadania: skrypt trafia do kolejki zadań w każdym terminie wyrażenia
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub name: String,
+    pub script: String,
+    pub expression: String,
+    pub timezone: String,
+    /// Dni (w strefie harmonogramu), w które harmonogram się nie uruchamia
+    pub holidays: Vec<NaiveDate>,
+    pub enabled: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_job_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Użytkownik, którego limity budżetu obejmują zadania harmonogramu
+    pub user_id: Option<String>,
+    /// Strona startowa zadań harmonogramu
+    pub url: Option<String>,
+}
+
+impl Schedule {
+    pub fn owner(&self) -> JobOwner {
+        JobOwner { schedule_id: Some(self.id.clone()), user_id: self.user_id.clone() }
+    }
+
+    /// Termin po `after` według zapisanego wyrażenia
+    pub fn next_after(&self, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+        let expression = ScheduleExpression::parse(&self.expression)?;
+        let timezone = parse_timezone(&self.timezone)?;
+        Ok(expression.next_after(after, timezone, &self.holidays.iter().copied().collect()))
+    }
+}
+
+/// Sprawdza wyrażenie i strefę; zwraca najbliższe terminy
+pub fn preview(request: &ScheduleRequest, after: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+    if request.name.trim().is_empty() {
+        bail!("Schedule name must not be empty");
+    }
+    let expression = ScheduleExpression::parse(&request.expression)?;
+    let timezone = parse_timezone(&request.timezone)?;
+    let holidays: BTreeSet<NaiveDate> = request.holidays.iter().copied().collect();
+    let upcoming = expression.upcoming(after, timezone, &holidays, UPCOMING_RUNS);
+    if upcoming.is_empty() {
+        bail!("Schedule \"{}\" has no run within {} days", request.expression, LOOKAHEAD_DAYS);
+    }
+    Ok(upcoming)
+}
+
+/// Harmonogramy zapisane w PostgreSQL
+#[derive(Debug, Clone)]
+pub struct ScheduleStore {
+    db_pool: PgPool,
+}
+
+impl ScheduleStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Inicjalizuje tabelę harmonogramów
+    pub async fn initialize(&self) -> Result<()> {
+        info!("Initializing automation schedules table");
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS automation_schedules (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                name VARCHAR(255) NOT NULL,
+                script TEXT NOT NULL,
+                expression TEXT NOT NULL,
+                timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+                holidays DATE[] NOT NULL DEFAULT '{}',
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                next_run_at TIMESTAMPTZ,
+                last_run_at TIMESTAMPTZ,
+                last_job_id UUID,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            );
+
+            ALTER TABLE automation_schedules ADD COLUMN IF NOT EXISTS user_id VARCHAR(255);
+            ALTER TABLE automation_schedules ADD COLUMN IF NOT EXISTS url TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_automation_schedules_next_run ON automation_schedules(next_run_at) WHERE enabled;
+            "#,
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to create automation_schedules table")?;
+
+        Ok(())
+    }
+
+    pub async fn create(&self, request: &ScheduleRequest) -> Result<Schedule> {
+        let next_run_at = preview(request, Utc::now())?.first().copied();
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO automation_schedules (name, script, expression, timezone, holidays, next_run_at, user_id, url)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id::text AS id, name, script, expression, timezone, holidays, enabled,
+                      next_run_at, last_run_at, last_job_id::text AS last_job_id, created_at, user_id, url
+            "#,
+        )
+        .bind(request.name.trim())
+        .bind(&request.script)
+        .bind(request.expression.trim())
+        .bind(&request.timezone)
+        .bind(&request.holidays)
+        .bind(next_run_at)
+        .bind(&request.user_id)
+        .bind(&request.url)
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to create automation schedule")?;
+
+        let schedule = schedule_from_row(&row);
+        info!(schedule_id = %schedule.id, next_run_at = ?schedule.next_run_at, "Automation schedule created: {}", schedule.name);
+        Ok(schedule)
+    }
+
+    pub async fn list(&self) -> Result<Vec<Schedule>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id::text AS id, name, script, expression, timezone, holidays, enabled,
+                   next_run_at, last_run_at, last_job_id::text AS last_job_id, created_at, user_id, url
+            FROM automation_schedules
+            ORDER BY next_run_at NULLS LAST, created_at
+            "#,
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to list automation schedules")?;
+
+        Ok(rows.iter().map(schedule_from_row).collect())
+    }
+
+    pub async fn delete(&self, schedule_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM automation_schedules WHERE id = $1::uuid")
+            .bind(schedule_id)
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to delete automation schedule")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn due(&self) -> Result<Vec<Schedule>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id::text AS id, name, script, expression, timezone, holidays, enabled,
+                   next_run_at, last_run_at, last_job_id::text AS last_job_id, created_at, user_id, url
+            FROM automation_schedules
+            WHERE enabled AND next_run_at <= NOW()
+            ORDER BY next_run_at
+            "#,
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch due automation schedules")?;
+
+        Ok(rows.iter().map(schedule_from_row).collect())
+    }
+
+    /// Przesuwa termin harmonogramu, jeśli nikt inny tego jeszcze nie zrobił - tylko jedna instancja
+    /// dostaje dany termin i dodaje zadanie. Zmiana obowiązuje dopiero po zatwierdzeniu zwróconej transakcji,
+    /// a do tego czasu wiersz jest zablokowany dla innych instancji.
+    async fn claim(&self, schedule: &Schedule, next_run_at: Option<DateTime<Utc>>) -> Result<Option<Transaction<'static, Postgres>>> {
+        let mut tx = self.db_pool.begin().await.context("Failed to start schedule transaction")?;
+        let result = sqlx::query(
+            r#"
+            UPDATE automation_schedules
+            SET next_run_at = $2, last_run_at = NOW()
+            WHERE id = $1::uuid AND next_run_at = $3
+            "#,
+        )
+        .bind(&schedule.id)
+        .bind(next_run_at)
+        .bind(schedule.next_run_at)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to advance automation schedule")?;
+
+        Ok((result.rows_affected() == 1).then_some(tx))
+    }
+
+    async fn record_job(tx: &mut Transaction<'static, Postgres>, schedule_id: &str, job_id: &str) -> Result<()> {
+        sqlx::query("UPDATE automation_schedules SET last_job_id = $2::uuid WHERE id = $1::uuid")
+            .bind(schedule_id)
+            .bind(job_id)
+            .execute(&mut **tx)
+            .await
+            .context("Failed to record scheduled job")?;
+        Ok(())
+    }
+}
+
+fn schedule_from_row(row: &sqlx::postgres::PgRow) -> Schedule {
+    Schedule {
+        id: row.get("id"),
+        name: row.get("name"),
+        script: row.get("script"),
+        expression: row.get("expression"),
+        timezone: row.get("timezone"),
+        holidays: row.get("holidays"),
+        enabled: row.get("enabled"),
+        next_run_at: row.get("next_run_at"),
+        last_run_at: row.get("last_run_at"),
+        last_job_id: row.get("last_job_id"),
+        created_at: row.get("created_at"),
+        user_id: row.get("user_id"),
+        url: row.get("url"),
+    }
+}
+
+/// Dodaje do kolejki zadania harmonogramów, których termin minął. Terminy przegapione podczas przestoju
+/// nie są nadrabiane - harmonogram uruchamia się raz i przechodzi do następnego terminu. Termin
+/// harmonogramu wstrzymanego przez limit budżetu jest pomijany
+pub async fn run_scheduler(store: Arc<ScheduleStore>, queue: Arc<JobQueue>, budgets: Arc<BudgetStore>, poll_interval: std::time::Duration) {
+    info!("Starting automation scheduler");
+    loop {
+        match store.due().await {
+            Ok(schedules) => {
+                for schedule in schedules {
+                    let next_run_at = match schedule.next_after(Utc::now()) {
+                        Ok(next_run_at) => next_run_at,
+                        Err(e) => {
+                            warn!(schedule_id = %schedule.id, "Invalid stored schedule, disabling its next run: {}", e);
+                            None
+                        }
+                    };
+                    let mut claim = match store.claim(&schedule, next_run_at).await {
+                        Ok(Some(claim)) => claim,
+                        Ok(None) => {
+                            debug!(schedule_id = %schedule.id, "Schedule run already taken by another instance");
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!(schedule_id = %schedule.id, "Failed to advance schedule: {}", e);
+                            continue;
+                        }
+                    };
+                    let owner = schedule.owner();
+                    match budgets.check(&owner).await {
+                        Ok(None) => {}
+                        Ok(Some(block)) => {
+                            info!(schedule_id = %schedule.id, next_run_at = ?next_run_at, "Scheduled run skipped: {}", block);
+                            if let Err(e) = claim.commit().await {
+                                warn!(schedule_id = %schedule.id, "Failed to advance schedule: {}", e);
+                            }
+                            continue;
+                        }
+                        // Limity nie mogą zatrzymać harmonogramu przy chwilowym błędzie bazy
+                        Err(e) => warn!(schedule_id = %schedule.id, "Failed to check budget of schedule: {}", e),
+                    }
+                    // Zadanie i nowy termin są zatwierdzane razem; nieudane dodanie zostawia stary termin,
+                    // więc następne odpytanie spróbuje ponownie zamiast pominąć przebieg
+                    let queued = async {
+                        let job_id = queue.enqueue_with(&mut *claim, &schedule.script, schedule.url.as_deref(), &owner).await?;
+                        ScheduleStore::record_job(&mut claim, &schedule.id, &job_id).await?;
+                        claim.commit().await.context("Failed to commit scheduled run")?;
+                        Ok::<_, anyhow::Error>(job_id)
+                    }
+                    .await;
+                    match queued {
+                        Ok(job_id) => {
+                            info!(schedule_id = %schedule.id, job_id = %job_id, next_run_at = ?next_run_at, "Scheduled automation queued: {}", schedule.name);
+                            budgets.record_enqueued(&owner).await;
+                        }
+                        Err(e) => warn!(schedule_id = %schedule.id, "Failed to enqueue scheduled automation, retrying at the next poll: {}", e),
+                    }
+                }
+            }
+            Err(e) => warn!("Scheduler failed to fetch due schedules: {}", e),
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_business_day_expressions() {
+        let warsaw: Tz = "Europe/Warsaw".parse().unwrap();
+        let none = BTreeSet::new();
+
+        // Piątek 30.10.2026 po 9:00 -> poniedziałek 2.11 o 9:00 czasu zimowego (8:00 UTC)
+        let weekday = ScheduleExpression::parse("Every weekday at 9").unwrap();
+        assert_eq!(weekday.next_after(utc("2026-10-30T08:30:00Z"), warsaw, &none), Some(utc("2026-11-02T08:00:00Z")));
+        // Święto 2.11 przesuwa na wtorek
+        let holidays: BTreeSet<NaiveDate> = [NaiveDate::from_ymd_opt(2026, 11, 2).unwrap()].into();
+        assert_eq!(weekday.next_after(utc("2026-10-30T08:30:00Z"), warsaw, &holidays), Some(utc("2026-11-03T08:00:00Z")));
+
+        let first_monday = ScheduleExpression::parse("first Monday of month at 8:30am").unwrap();
+        assert_eq!(
+            first_monday.upcoming(utc("2026-10-15T00:00:00Z"), warsaw, &none, 2),
+            vec![utc("2026-11-02T07:30:00Z"), utc("2026-12-07T07:30:00Z")]
+        );
+
+        // 31.10.2026 to sobota, więc ostatni dzień roboczy października to piątek 30.10 (już po zmianie czasu, UTC+1)
+        let last_business_day = ScheduleExpression::parse("last business day of the month at 17:00").unwrap();
+        assert_eq!(last_business_day.next_after(utc("2026-10-15T00:00:00Z"), warsaw, &none), Some(utc("2026-10-30T16:00:00Z")));
+
+        let days = ScheduleExpression::parse("every Tuesday and Thursday at 5pm").unwrap();
+        assert_eq!(days.next_after(utc("2026-10-15T16:00:00Z"), Tz::UTC, &none), Some(utc("2026-10-15T17:00:00Z")));
+
+        assert!(ScheduleExpression::parse("every weekday").is_err());
+        assert!(ScheduleExpression::parse("fifth Monday of month at 9").is_err());
+        assert!(ScheduleExpression::parse("every funday at 9").is_err());
+        assert!(ScheduleExpression::parse("every day at 25:00").is_err());
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+}
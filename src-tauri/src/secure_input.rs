@@ -0,0 +1,291 @@
+//! Tryb hybrydowy ścieżki TagUI: zwykłe kroki wykonuje TagUI, a pola wypełniane sekretem
+//! (`type "selektor" "{{password}}"`, np. hasło albo kod TOTP z vault) wpisuje backend przez CDP
+//! do przeglądarki uruchomionej przez TagUI. Plik skryptu w katalogu przebiegu zawiera w tym
+//! miejscu tylko zgłoszenie kroku - TagUI zapisuje numer linii i czeka na bramkę, jak w `debugger`.
+
+use anyhow::{anyhow, bail, Result};
+use chromiumoxide::cdp::browser_protocol::input::InsertTextParams;
+use chromiumoxide::{Browser, Page};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::{info, debug};
+
+use codialog_types::SecretString;
+use crate::tagui::{self, escape_for_dsl};
+
+/// Domyślny port zdalnego debugowania Chrome uruchamianego przez TagUI (TAGUI_DEBUG_PORT)
+pub const DEFAULT_TAGUI_DEBUG_PORT: u16 = 9222;
+
+/// Plik z numerem linii pola, na którego wpisanie TagUI czeka
+const REQUEST_FILE: &str = "secure-request";
+
+/// Bramka zwalniająca TagUI po wpisaniu sekretu - TagUI usuwa ją po przejściu dalej
+const DONE_GATE: &str = "secure-done";
+
+/// Jak często TagUI sprawdza bramkę (sekundy)
+const GATE_POLL_SECS: f64 = 0.2;
+
+/// Górny limit czekania TagUI (około 5 minut), po którym krok kończy się błędem; błąd wpisywania
+/// przerywa przebieg wcześniej
+const GATE_MAX_POLLS: u64 = 1_500;
+
+/// Jak często backend sprawdza zgłoszenia
+const REQUEST_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Ile czekać na pojawienie się pola i na strony przeglądarki TagUI
+const ELEMENT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Krok `type`, którego całą wartością jest sekret
+#[derive(Debug, Clone)]
+pub struct SecureField {
+    pub line: usize,
+    pub selector: String,
+    /// Ramki iframe z otaczających bloków `frame`, od zewnętrznej
+    pub frames: Vec<String>,
+    pub variable: String,
+    pub value: SecretString,
+}
+
+/// Błąd wpisania pola, który kończy przebieg
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecureInputError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Kroki `type "selektor" "{{nazwa}}"` z sekretem `nazwa`, poza pętlami `for each` i selektorami ze zmiennymi
+pub fn secure_fields(script: &str, secrets: &HashMap<String, SecretString>) -> Vec<SecureField> {
+    let Ok(commands) = tagui::parse_dsl_script(script) else {
+        return Vec::new();
+    };
+    // Otwarte bloki: ramka dla `frame`, None dla pozostałych
+    let mut blocks: Vec<Option<String>> = Vec::new();
+    let mut fields = Vec::new();
+
+    for command in commands {
+        match command.name.as_str() {
+            "frame" => blocks.push(tagui::frame_identifier(&command.args[0]).map(str::to_string)),
+            "if" | "repeat" | "for" => blocks.push(None),
+            "end" => {
+                blocks.pop();
+            }
+            "type" if command.args.len() == 2 => {
+                let selector = &command.args[0];
+                let Some(variable) = command.args[1].trim().strip_prefix("{{").and_then(|rest| rest.strip_suffix("}}")).map(str::trim) else {
+                    continue;
+                };
+                let Some(value) = secrets.get(variable) else { continue };
+                if selector.contains("{{") || selector.contains(tagui::FOR_EACH_ITEM) {
+                    continue;
+                }
+                fields.push(SecureField {
+                    line: command.line,
+                    selector: selector.clone(),
+                    frames: blocks.iter().flatten().cloned().collect(),
+                    variable: variable.to_string(),
+                    value: value.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+    fields
+}
+
+/// Wstawia sekrety z powrotem w linie pól - dla silnika, który nie obsługuje trybu hybrydowego
+pub fn inline(script: &str, fields: &[SecureField]) -> String {
+    script
+        .lines()
+        .enumerate()
+        .map(|(index, line)| match fields.iter().find(|field| field.line == index + 1) {
+            Some(field) => {
+                let value = HashMap::from([(field.variable.clone(), field.value.expose_secret().to_string())]);
+                tagui::interpolate_variables(line, &value).unwrap_or_else(|_| line.to_string())
+            }
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Pierwszy krok zgłoszenia - TagUI wypisuje go przy wykonaniu zamiast komendy `type`
+pub fn request_step(dir: &Path, line: usize) -> String {
+    format!("dump {} to {}", line, dir.join(REQUEST_FILE).display())
+}
+
+/// Komendy TagUI zamiast `type` z linii `line`: zgłoszenie i pętla czekająca na bramkę; bez bramki
+/// po limicie krok zgłasza błąd i kończy TagUI, zamiast iść dalej z pustym polem
+pub fn handoff_steps(dir: &Path, line: usize) -> Vec<String> {
+    let done = escape_for_dsl(&dir.join(DONE_GATE).display().to_string());
+    vec![
+        request_step(dir, line),
+        format!("for codialog_secure from 1 to {}", GATE_MAX_POLLS),
+        "{".to_string(),
+        format!("  if fs.exists(\"{}\")", done),
+        "  {".to_string(),
+        "    break".to_string(),
+        "  }".to_string(),
+        format!("  wait {}", GATE_POLL_SECS),
+        "}".to_string(),
+        format!(
+            "js if (fs.exists(\"{0}\")) fs.remove(\"{0}\"); else {{ casper.echo(\"ERROR - secure input for line {1} was not typed within {2}s\"); casper.exit(1); }}",
+            done,
+            line,
+            (GATE_MAX_POLLS as f64 * GATE_POLL_SECS) as u64
+        ),
+    ]
+}
+
+/// Obsługuje zgłoszenia TagUI z katalogu przebiegu do pierwszego błędu; bez pól czeka w nieskończoność
+pub async fn serve(dir: &Path, fields: &[SecureField], port: u16) -> SecureInputError {
+    let request = dir.join(REQUEST_FILE);
+    loop {
+        tokio::time::sleep(REQUEST_POLL_INTERVAL).await;
+        // Plik w trakcie zapisu przez TagUI jest pusty - wtedy następna próba
+        let Some(line) = tokio::fs::read_to_string(&request).await.ok().and_then(|content| content.trim().parse::<usize>().ok()) else {
+            continue;
+        };
+        let _ = tokio::fs::remove_file(&request).await;
+        let failure = |message: String| SecureInputError { line, message };
+
+        let Some(field) = fields.iter().find(|field| field.line == line) else {
+            return failure(format!("Unexpected secure input request for line {}", line));
+        };
+        if let Err(e) = type_secret(field, port).await {
+            return failure(format!("Failed to type secret '{}' into {}: {}", field.variable, field.selector, e));
+        }
+        if let Err(e) = tokio::fs::write(dir.join(DONE_GATE), "").await {
+            return failure(format!("Failed to release TagUI after secure input: {}", e));
+        }
+        debug!(line, selector = %field.selector, "Secret typed through CDP");
+    }
+}
+
+/// Łączy się z przeglądarką TagUI i wpisuje sekret w pole; przeglądarka zostaje otwarta
+async fn type_secret(field: &SecureField, port: u16) -> Result<()> {
+    let (mut browser, mut handler) = Browser::connect(format!("http://127.0.0.1:{}", port))
+        .await
+        .map_err(|e| anyhow!("TagUI browser is not reachable on port {}: {}", port, e))?;
+    let handle = tokio::spawn(async move {
+        while handler.next().await.is_some() {}
+    });
+
+    let typed = async {
+        browser.fetch_targets().await?;
+        let page = focus_field(&browser, field).await?;
+        page.execute(InsertTextParams::new(field.value.expose_secret())).await?;
+        Ok(())
+    }
+    .await;
+    handle.abort();
+    if typed.is_ok() {
+        info!(line = field.line, "Secure field typed by backend");
+    }
+    typed
+}
+
+/// Zwykłe strony przeglądarki TagUI, najpierw widoczne - TagUI mógł otworzyć kilka kart (np. `popup`)
+async fn candidate_pages(browser: &Browser) -> Result<Vec<Page>> {
+    let mut visible = Vec::new();
+    let mut hidden = Vec::new();
+    for page in browser.pages().await? {
+        let url = page.url().await.ok().flatten().unwrap_or_default();
+        if url.starts_with("devtools://") || url.starts_with("chrome-extension://") {
+            continue;
+        }
+        let is_visible = page
+            .evaluate("document.visibilityState === 'visible'")
+            .await
+            .ok()
+            .and_then(|result| result.into_value::<bool>().ok())
+            .unwrap_or(false);
+        if is_visible { visible.push(page) } else { hidden.push(page) }
+    }
+    visible.extend(hidden);
+    Ok(visible)
+}
+
+/// Ustawia fokus na polu (także w ramkach iframe) i czyści jego wartość na karcie, na której pole jest;
+/// sekret nie trafia do kodu JS
+async fn focus_field(browser: &Browser, field: &SecureField) -> Result<Page> {
+    let script = format!(
+        "(() => {{ let doc = document; \
+         for (const frame of {frames}) {{ \
+             const element = doc.getElementsByName(frame)[0] || doc.getElementById(frame); \
+             doc = element && element.contentDocument; if (!doc) return false; }} \
+         const selector = {selector}; \
+         const element = selector.startsWith('/') || selector.startsWith('(') \
+             ? doc.evaluate(selector, doc, null, XPathResult.FIRST_ORDERED_NODE_TYPE, null).singleNodeValue \
+             : doc.querySelector(selector); \
+         if (!element) return false; \
+         element.focus(); if ('value' in element) element.value = ''; return true; }})()",
+        frames = serde_json::to_string(&field.frames)?,
+        selector = serde_json::to_string(&field.selector)?,
+    );
+    let deadline = Instant::now() + ELEMENT_TIMEOUT;
+    loop {
+        let pages = candidate_pages(browser).await?;
+        for page in &pages {
+            if page.evaluate(script.as_str()).await.ok().and_then(|result| result.into_value::<bool>().ok()).unwrap_or(false) {
+                return Ok(page.clone());
+            }
+        }
+        if Instant::now() >= deadline {
+            if pages.is_empty() {
+                bail!("No page open in the TagUI browser");
+            }
+            bail!("Element not found after {}s", ELEMENT_TIMEOUT.as_secs());
+        }
+        tokio::time::sleep(REQUEST_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secure_fields_keep_secrets_out_of_script() {
+        let secrets = HashMap::from([
+            ("password".to_string(), SecretString::from("hunter\"2")),
+            ("totp".to_string(), SecretString::from("123456")),
+        ]);
+        let script = "type \"#user\" \"{{username}}\"\n\
+                      type \"#pass\" \"{{password}}\"\n\
+                      frame \"#login-frame\"\n  type \"#otp\" \"{{ totp }}\"\nend\n\
+                      type \"#note\" \"code {{totp}}\"\n\
+                      for each \"//tr\"\n  type \"@item//input\" \"{{password}}\"\nend";
+
+        let fields = secure_fields(script, &secrets);
+        let summary: Vec<(usize, &str, Vec<String>, &str)> = fields
+            .iter()
+            .map(|field| (field.line, field.selector.as_str(), field.frames.clone(), field.variable.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(2, "#pass", vec![], "password"), (4, "#otp", vec!["login-frame".to_string()], "totp")]
+        );
+
+        let inlined = inline(script, &fields);
+        assert_eq!(inlined.lines().nth(1), Some("type \"#pass\" \"hunter\\\"2\""));
+        assert_eq!(inlined.lines().nth(3), Some("  type \"#otp\" \"123456\""));
+        let steps = handoff_steps(Path::new("/tmp/run"), 2);
+        assert_eq!(steps[0], request_step(Path::new("/tmp/run"), 2));
+        assert!(steps.iter().all(|step| !step.contains("hunter")));
+        assert!(steps.last().unwrap().contains("ERROR - secure input for line 2") && steps.last().unwrap().contains("casper.exit(1)"));
+
+        let compiled = tagui::compile_dsl_script_with(
+            script,
+            None,
+            None,
+            Some((Path::new("/tmp/run"), &fields)),
+            &[],
+            &mut crate::pacing::Pacer::new(crate::pacing::PacingProfile::Fast),
+        )
+        .unwrap();
+        assert!(compiled.contains("dump 2 to /tmp/run/secure-request") && compiled.contains("  dump 4 to"));
+        assert!(!compiled.contains("#pass"));
+    }
+}
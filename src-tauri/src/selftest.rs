@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use tracing::{info, warn};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Limit czasu pojedynczego testu, aby zawieszona usługa nie blokowała raportu
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub duration_ms: u64,
+    /// Szczegóły sukcesu, powód pominięcia albo komunikat błędu
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    pub fn skipped(name: &str, reason: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Skipped,
+            duration_ms: 0,
+            detail: Some(reason.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub ran_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfTestReport {
+    /// Raport jest pozytywny, gdy żaden test nie zakończył się błędem (pominięte nie psują wyniku)
+    pub fn new(ran_at: DateTime<Utc>, started: Instant, checks: Vec<CheckResult>) -> Self {
+        Self {
+            passed: checks.iter().all(|check| check.status != CheckStatus::Failed),
+            ran_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            checks,
+        }
+    }
+
+    pub fn log_summary(&self) {
+        for check in &self.checks {
+            let detail = check.detail.as_deref().unwrap_or("");
+            match check.status {
+                CheckStatus::Passed => info!("Self-test {} passed in {}ms {}", check.name, check.duration_ms, detail),
+                CheckStatus::Skipped => info!("Self-test {} skipped: {}", check.name, detail),
+                CheckStatus::Failed => warn!("Self-test {} FAILED after {}ms: {}", check.name, check.duration_ms, detail),
+            }
+        }
+
+        if self.passed {
+            info!("✅ Self-test suite passed ({} checks, {}ms)", self.checks.len(), self.duration_ms);
+        } else {
+            warn!("❌ Self-test suite failed - the environment is not fully functional, see checks above");
+        }
+    }
+}
+
+/// Runs a single check with a timeout; the `Ok` value becomes the check's detail
+pub async fn run_check<F, Fut>(name: &str, timeout: Duration, check: F) -> CheckResult
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, check()).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let (status, detail) = match outcome {
+        Ok(Ok(detail)) => (CheckStatus::Passed, detail),
+        Ok(Err(e)) => (CheckStatus::Failed, format!("{:#}", e)),
+        Err(_) => (CheckStatus::Failed, format!("Timed out after {}s", timeout.as_secs())),
+    };
+
+    CheckResult {
+        name: name.to_string(),
+        status,
+        duration_ms,
+        detail: Some(detail).filter(|detail| !detail.is_empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_report_fails_only_on_failed_checks() {
+        let started = Instant::now();
+        let passed = run_check("ok", CHECK_TIMEOUT, || async { Ok("fine".to_string()) }).await;
+        let failed = run_check("broken", CHECK_TIMEOUT, || async { Err(anyhow::anyhow!("boom")) }).await;
+        let slow = run_check("slow", Duration::from_millis(10), || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(String::new())
+        })
+        .await;
+
+        assert_eq!(passed.status, CheckStatus::Passed);
+        assert_eq!(failed.detail.as_deref(), Some("boom"));
+        assert_eq!(slow.status, CheckStatus::Failed);
+
+        let report = SelfTestReport::new(Utc::now(), started, vec![passed.clone(), CheckResult::skipped("redis", "not configured")]);
+        assert!(report.passed);
+        let report = SelfTestReport::new(Utc::now(), started, vec![passed, failed]);
+        assert!(!report.passed);
+    }
+}
@@ -1,56 +1,25 @@
-use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Row};
 use redis::AsyncCommands;
 use anyhow::{Result, Context};
-use tracing::{info, debug};
-use chrono::{DateTime, Utc, Duration};
+use tracing::{info, debug, warn};
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use std::collections::HashMap;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UserSession {
-    pub session_id: String,
-    pub user_id: String,
-    pub bitwarden_session: Option<String>,
-    pub user_data: UserData,
-    pub created_at: DateTime<Utc>,
-    pub expires_at: DateTime<Utc>,
-    pub last_activity: DateTime<Utc>,
-}
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UserData {
-    pub first_name: Option<String>,
-    pub last_name: Option<String>,
-    pub email: Option<String>,
-    pub phone: Option<String>,
-    pub address: Option<String>,
-    pub cv_path: Option<String>,
-    pub cover_letter_path: Option<String>,
-    pub preferences: HashMap<String, serde_json::Value>,
-    pub form_data: HashMap<String, serde_json::Value>,
-}
+use crate::archive::RESTORE_HOLD_DAYS;
+use crate::clock::{Clock, SystemClock};
+use crate::faults::{self, FaultTarget};
+use crate::perf::{self, OperationKind};
+use codialog_types::SecretString;
 
-impl Default for UserData {
-    fn default() -> Self {
-        Self {
-            first_name: None,
-            last_name: None,
-            email: None,
-            phone: None,
-            address: None,
-            cv_path: None,
-            cover_letter_path: None,
-            preferences: HashMap::new(),
-            form_data: HashMap::new(),
-        }
-    }
-}
+pub use codialog_types::session::{UserData, UserSession};
 
 #[derive(Debug, Clone)]
 pub struct SessionManager {
     db_pool: PgPool,
     redis_client: Option<redis::Client>,
+    clock: Arc<dyn Clock>,
+    instance_binding: Option<String>,
 }
 
 impl SessionManager {
@@ -58,6 +27,8 @@ impl SessionManager {
         Self {
             db_pool,
             redis_client: None,
+            clock: Arc::new(SystemClock),
+            instance_binding: None,
         }
     }
 
@@ -65,6 +36,28 @@ impl SessionManager {
         Self {
             db_pool,
             redis_client: Some(redis_client),
+            clock: Arc::new(SystemClock),
+            instance_binding: None,
+        }
+    }
+
+    /// Ustawia zegar używany do sprawdzania wygaśnięcia sesji z cache
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Wiąże nowe sesje z bieżącą instancją; sesje innej instancji nie są wtedy zwracane
+    pub fn with_instance_binding(mut self, binding: impl Into<String>) -> Self {
+        self.instance_binding = Some(binding.into());
+        self
+    }
+
+    /// Sessions issued before a restart (or by another process) carry a different binding
+    fn is_bound_here(&self, session: &UserSession) -> bool {
+        match &self.instance_binding {
+            Some(binding) => session.instance_binding.as_deref() == Some(binding.as_str()),
+            None => true,
         }
     }
 
@@ -86,6 +79,14 @@ impl SessionManager {
                 UNIQUE(user_id)
             );
 
+            ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS instance_binding TEXT;
+            ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+            ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS restored_at TIMESTAMPTZ;
+
+            -- Zarchiwizowane sesje zostają w tabeli do eksportu, więc jedna aktywna sesja na użytkownika
+            ALTER TABLE user_sessions DROP CONSTRAINT IF EXISTS user_sessions_user_id_key;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_user_sessions_active_user ON user_sessions(user_id) WHERE archived_at IS NULL;
+
             CREATE INDEX IF NOT EXISTS idx_user_sessions_user_id ON user_sessions(user_id);
             CREATE INDEX IF NOT EXISTS idx_user_sessions_expires_at ON user_sessions(expires_at);
             "#,
@@ -147,42 +148,45 @@ impl SessionManager {
         info!("Creating new session for user: {}", user_id);
 
         let session_id = Uuid::new_v4().to_string();
-        let now = Utc::now();
-        let expires_at = now + Duration::hours(24); // Sesja wygasa po 24 godzinach
 
-        let session = UserSession {
-            session_id: session_id.clone(),
-            user_id: user_id.to_string(),
-            bitwarden_session: None,
-            user_data,
-            created_at: now,
-            expires_at,
-            last_activity: now,
-        };
+        faults::inject(FaultTarget::Db).await?;
 
-        // Zapisz sesję w bazie danych
-        sqlx::query(
+        // Zapisz sesję w bazie danych; czas wygaśnięcia liczy baza (24h), nie lokalny zegar
+        let row = sqlx::query(
             r#"
-            INSERT INTO user_sessions (session_id, user_id, user_data, expires_at)
-            VALUES ($1, $2, $3, $4)
-            ON CONFLICT (user_id) DO UPDATE SET
+            INSERT INTO user_sessions (session_id, user_id, user_data, expires_at, instance_binding)
+            VALUES ($1, $2, $3, NOW() + INTERVAL '24 hours', $4)
+            ON CONFLICT (user_id) WHERE archived_at IS NULL DO UPDATE SET
                 session_id = EXCLUDED.session_id,
                 user_data = EXCLUDED.user_data,
+                instance_binding = EXCLUDED.instance_binding,
                 expires_at = EXCLUDED.expires_at,
                 last_activity = NOW()
+            RETURNING created_at, expires_at, last_activity
             "#,
         )
         .bind(&session_id)
         .bind(user_id)
-        .bind(serde_json::to_value(&session.user_data)?)
-        .bind(&expires_at)
-        .execute(&self.db_pool)
+        .bind(serde_json::to_value(&user_data)?)
+        .bind(&self.instance_binding)
+        .fetch_one(&self.db_pool)
         .await
         .context("Failed to create session in database")?;
 
+        let session = UserSession {
+            session_id: session_id.clone(),
+            user_id: user_id.to_string(),
+            bitwarden_session: None,
+            user_data,
+            created_at: row.get("created_at"),
+            expires_at: row.get("expires_at"),
+            last_activity: row.get("last_activity"),
+            instance_binding: self.instance_binding.clone(),
+        };
+
         // Cache w Redis dla szybkiego dostępu
         if let Some(redis_client) = &self.redis_client {
-            let mut redis_conn = redis_client.get_async_connection().await?;
+            let mut redis_conn = redis_connection(redis_client).await?;
             let session_json = serde_json::to_string(&session)?;
             let _: () = redis::cmd("SETEX")
                 .arg(&format!("session:{}", session_id))
@@ -202,34 +206,43 @@ impl SessionManager {
 
         // Najpierw sprawdź Redis cache
         if let Some(redis_client) = &self.redis_client {
-            let mut redis_conn = redis_client.get_async_connection().await?;
-            
-            if let Ok(cached_session) = redis_conn
-                .get::<&str, String>(&format!("session:{}", session_id))
-                .await
-            {
-                if let Ok(session) = serde_json::from_str::<UserSession>(&cached_session) {
-                    if session.expires_at > Utc::now() {
-                        debug!("Session found in Redis cache: {}", session_id);
-                        return Ok(Some(session));
+            match redis_connection(redis_client).await {
+                Ok(mut redis_conn) => {
+                    if let Ok(cached_session) = redis_conn
+                        .get::<&str, String>(&format!("session:{}", session_id))
+                        .await
+                    {
+                        if let Ok(session) = serde_json::from_str::<UserSession>(&cached_session) {
+                            if !self.is_bound_here(&session) {
+                                warn!("Rejecting session {} issued by another app instance", session_id);
+                                return Ok(None);
+                            }
+                            if !self.clock.is_expired(session.expires_at) {
+                                debug!("Session found in Redis cache: {}", session_id);
+                                return Ok(Some(session));
+                            }
+                        }
                     }
                 }
+                Err(e) => warn!("Redis unavailable, falling back to database: {}", e),
             }
         }
 
         // Jeśli nie ma w cache, sprawdź bazę danych
-        let row = sqlx::query(
+        faults::inject(FaultTarget::Db).await?;
+        let query = sqlx::query(
             r#"
             SELECT session_id, user_id, bitwarden_session, user_data, 
-                   created_at, expires_at, last_activity
+                   created_at, expires_at, last_activity, instance_binding
             FROM user_sessions 
-            WHERE session_id = $1 AND expires_at > NOW()
+            WHERE session_id = $1 AND expires_at > NOW() AND archived_at IS NULL
             "#,
         )
         .bind(session_id)
-        .fetch_optional(&self.db_pool)
-        .await
-        .context("Failed to fetch session from database")?;
+        .fetch_optional(&self.db_pool);
+        let row = perf::timed(OperationKind::DbQuery, "user_sessions.get", serde_json::Value::Null, query)
+            .await
+            .context("Failed to fetch session from database")?;
 
         if let Some(row) = row {
             let user_data: UserData = serde_json::from_value(row.get("user_data"))?;
@@ -237,16 +250,21 @@ impl SessionManager {
             let session = UserSession {
                 session_id: row.get("session_id"),
                 user_id: row.get("user_id"),
-                bitwarden_session: row.get("bitwarden_session"),
+                bitwarden_session: row.get::<Option<String>, _>("bitwarden_session").map(SecretString::from),
                 user_data,
                 created_at: row.get("created_at"),
                 expires_at: row.get("expires_at"),
                 last_activity: row.get("last_activity"),
+                instance_binding: row.get("instance_binding"),
             };
+            if !self.is_bound_here(&session) {
+                warn!("Rejecting session {} issued by another app instance", session_id);
+                return Ok(None);
+            }
 
             // Odśwież cache w Redis
             if let Some(redis_client) = &self.redis_client {
-                let mut redis_conn = redis_client.get_async_connection().await?;
+                let mut redis_conn = redis_connection(redis_client).await?;
                 let session_json = serde_json::to_string(&session)?;
                 let _: () = redis::cmd("SETEX")
                     .arg(&format!("session:{}", session_id))
@@ -269,6 +287,7 @@ impl SessionManager {
         debug!("Updating session: {}", session.session_id);
 
         // Aktualizuj w bazie danych
+        faults::inject(FaultTarget::Db).await?;
         sqlx::query(
             r#"
             UPDATE user_sessions 
@@ -276,7 +295,7 @@ impl SessionManager {
             WHERE session_id = $3
             "#,
         )
-        .bind(&session.bitwarden_session)
+        .bind(session.bitwarden_session.as_ref().map(|token| token.expose_secret()))
         .bind(serde_json::to_value(&session.user_data)?)
         .bind(&session.session_id)
         .execute(&self.db_pool)
@@ -285,7 +304,7 @@ impl SessionManager {
 
         // Aktualizuj cache w Redis
         if let Some(redis_client) = &self.redis_client {
-            let mut redis_conn = redis_client.get_async_connection().await?;
+            let mut redis_conn = redis_connection(redis_client).await?;
             let session_json = serde_json::to_string(session)?;
             let _: () = redis::cmd("SETEX")
                 .arg(&format!("session:{}", session.session_id))
@@ -299,23 +318,75 @@ impl SessionManager {
         Ok(())
     }
 
-    /// Usuwa wygasłe sesje
-    pub async fn cleanup_expired_sessions(&self) -> Result<u64> {
-        info!("Cleaning up expired sessions");
+    /// Usuwa sesję z bazy danych i cache Redis
+    pub async fn delete_session(&self, session_id: &str) -> Result<()> {
+        faults::inject(FaultTarget::Db).await?;
 
-        // Usuń z bazy danych
-        let result = sqlx::query("DELETE FROM user_sessions WHERE expires_at < NOW()")
+        sqlx::query("DELETE FROM user_sessions WHERE session_id = $1")
+            .bind(session_id)
             .execute(&self.db_pool)
             .await
-            .context("Failed to delete expired sessions")?;
+            .context("Failed to delete session")?;
 
-        let deleted_count = result.rows_affected();
-        
-        if deleted_count > 0 {
-            info!("Cleaned up {} expired sessions", deleted_count);
+        if let Some(redis_client) = &self.redis_client {
+            let mut redis_conn = redis_connection(redis_client).await?;
+            let _: () = redis_conn.del(format!("session:{}", session_id)).await?;
         }
 
-        Ok(deleted_count)
+        debug!("Session deleted: {}", session_id);
+        Ok(())
+    }
+
+    pub fn has_redis(&self) -> bool {
+        self.redis_client.is_some()
+    }
+
+    /// Writes, reads back and removes a short-lived key to verify the Redis cache works
+    pub async fn redis_roundtrip(&self) -> Result<()> {
+        let redis_client = self.redis_client.as_ref().context("Redis cache is not configured")?;
+        let mut redis_conn = redis_connection(redis_client).await?;
+
+        let key = format!("selftest:{}", Uuid::new_v4());
+        let value = Uuid::new_v4().to_string();
+        let _: () = redis::cmd("SETEX")
+            .arg(&key)
+            .arg(30)
+            .arg(&value)
+            .query_async::<_, ()>(&mut redis_conn)
+            .await?;
+        let read_back: Option<String> = redis_conn.get(&key).await?;
+        let _: () = redis_conn.del(&key).await?;
+
+        if read_back.as_deref() != Some(value.as_str()) {
+            anyhow::bail!("Redis returned {:?} instead of the value just written", read_back);
+        }
+        Ok(())
+    }
+
+    /// Archiwizuje wygasłe sesje (soft delete); z bazy usuwa je dopiero eksport archiwum.
+    /// Sesje przywrócone z archiwum nie są archiwizowane ponownie przez `RESTORE_HOLD_DAYS`.
+    pub async fn cleanup_expired_sessions(&self) -> Result<u64> {
+        info!("Archiving expired sessions");
+
+        let result = sqlx::query(
+            r#"
+            UPDATE user_sessions SET archived_at = NOW()
+            WHERE expires_at < NOW() AND archived_at IS NULL
+              AND (restored_at IS NULL OR restored_at < NOW() - make_interval(days => $1))
+            "#,
+        )
+        .bind(RESTORE_HOLD_DAYS)
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to archive expired sessions")?;
+
+        let archived_count = result.rows_affected();
+
+        if archived_count > 0 {
+            info!("Archived {} expired sessions", archived_count);
+        }
+
+        Ok(archived_count)
     }
 
     /// Zapisuje plik dla sesji
@@ -455,3 +526,9 @@ impl SessionManager {
         }
     }
 }
+
+/// Połączenie z Redis; miejsce wstrzykiwania awarii Redis w testach odporności
+pub(crate) async fn redis_connection(client: &redis::Client) -> Result<redis::aio::Connection> {
+    faults::inject(FaultTarget::Redis).await?;
+    Ok(client.get_async_connection().await?)
+}
@@ -0,0 +1,131 @@
+//! Pamięć blokad witryn: zadanie, które trafiło na ochronę przed botami (403, 429, captcha, strona
+//! "Just a moment..."), wyłącza witrynę z kolejki na czas odstępu. Kolejna blokada tej samej witryny
+//! w ciągu doby podwaja odstęp, żeby wsad zadań nie wypalał się na stronie, która nas odrzuca.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use codialog_core::tagui::ExecutionResult;
+
+/// Domyślny odstęp po pierwszej blokadzie (SITE_BLOCK_COOLDOWN_SECS)
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30 * 60);
+
+/// Najdłuższy odstęp niezależnie od liczby blokad
+pub const MAX_COOLDOWN: Duration = Duration::from_secs(24 * 3600);
+
+/// Blokady starsze niż tyle godzin nie podwajają kolejnego odstępu
+pub const REPUTATION_WINDOW_HOURS: i64 = 24;
+
+/// Fragmenty tytułu strony z chwili błędu (małymi literami) i powód blokady pokazywany użytkownikowi
+const TITLE_MARKERS: &[(&str, &str)] = &[
+    ("403 forbidden", "HTTP 403 Forbidden"),
+    ("429 too many requests", "HTTP 429 Too Many Requests"),
+    ("just a moment", "Cloudflare challenge page"),
+    ("attention required", "Cloudflare challenge page"),
+    ("captcha", "CAPTCHA challenge"),
+    ("are you a robot", "Bot check page"),
+    ("unusual traffic", "Unusual traffic check"),
+    ("access denied", "Access denied page"),
+    ("request blocked", "Request blocked by the site"),
+];
+
+/// Statusy HTTP w linii błędu przebiegu. Tylko statusy - linia błędu powtarza selektor kroku
+/// (np. `#g-recaptcha-response`), więc słowa z treści strony dawałyby fałszywe blokady
+const ERROR_MARKERS: &[(&str, &str)] = &[
+    ("403 forbidden", "HTTP 403 Forbidden"),
+    ("status 403", "HTTP 403 Forbidden"),
+    ("status code 403", "HTTP 403 Forbidden"),
+    ("429 too many requests", "HTTP 429 Too Many Requests"),
+    ("status 429", "HTTP 429 Too Many Requests"),
+    ("status code 429", "HTTP 429 Too Many Requests"),
+];
+
+/// Witryna wyłączona z kolejki po wykryciu blokady
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteBlock {
+    pub site: String,
+    pub reason: String,
+    /// Blokady z rzędu w oknie `REPUTATION_WINDOW_HOURS`
+    pub hits: i32,
+    /// Zadanie, które trafiło na blokadę
+    pub job_id: Option<String>,
+    pub detected_at: DateTime<Utc>,
+    pub blocked_until: DateTime<Utc>,
+}
+
+/// Powód blokady, jeśli nieudany przebieg trafił na ochronę przed botami: z tytułu strony w chwili błędu
+/// (silnik CDP) albo ze statusu HTTP w linii błędu TagUI - nie z całego wyjścia, które powtarza selektory
+/// i wpisywany tekst
+pub fn detect(execution: &ExecutionResult) -> Option<&'static str> {
+    if execution.success() {
+        return None;
+    }
+    let title = execution.page_html.as_deref().and_then(page_title).map(|title| title.to_lowercase());
+    if let Some(reason) = title.and_then(|title| TITLE_MARKERS.iter().find(|(marker, _)| title.contains(marker))) {
+        return Some(reason.1);
+    }
+    error_lines(execution).find_map(|line| {
+        let line = line.to_lowercase();
+        ERROR_MARKERS.iter().find(|(marker, _)| line.contains(marker)).map(|(_, reason)| *reason)
+    })
+}
+
+/// Tekst `<title>` dokumentu
+fn page_title(html: &str) -> Option<String> {
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("title").ok()?;
+    let title = document.select(&selector).next()?.text().collect::<String>();
+    Some(title.trim().to_string()).filter(|title| !title.is_empty())
+}
+
+/// Komunikat błędu przebiegu i linie `ERROR` wypisane przez TagUI
+fn error_lines(execution: &ExecutionResult) -> impl Iterator<Item = &str> {
+    let reported = execution.error.as_deref().into_iter();
+    let logged = execution.stdout.lines().chain(execution.stderr.lines()).filter(|line| line.trim_start().starts_with("ERROR"));
+    reported.chain(logged)
+}
+
+/// Odstęp po `hits`-tej blokadzie z rzędu: podwajany od `base`, najwyżej `MAX_COOLDOWN`
+pub fn cooldown(base: Duration, hits: i32) -> Duration {
+    let doublings = hits.clamp(1, 16) as u32 - 1;
+    base.saturating_mul(1 << doublings).min(MAX_COOLDOWN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codialog_core::tagui::ExecutionStatus;
+
+    fn execution(status: ExecutionStatus, stdout: &str) -> ExecutionResult {
+        ExecutionResult {
+            status,
+            exit_code: Some(1),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            steps: Vec::new(),
+            duration_ms: 0,
+            failed_line: None,
+            error: None,
+            page_html: None,
+        }
+    }
+
+    #[test]
+    fn test_block_detection_and_cooldown() {
+        let mut blocked = execution(ExecutionStatus::Failed, "ERROR - cannot find #email");
+        blocked.page_html = Some("<html><head><title>Just a moment...</title></head><body></body></html>".to_string());
+        assert_eq!(detect(&blocked), Some("Cloudflare challenge page"));
+        assert_eq!(detect(&execution(ExecutionStatus::Failed, "ERROR - navigation failed with status 403")), Some("HTTP 403 Forbidden"));
+        assert_eq!(detect(&execution(ExecutionStatus::Failed, "ERROR - cannot find #email")), None);
+        // Selektor kroku i wpisany tekst w wyjściu nie są blokadą
+        assert_eq!(detect(&execution(ExecutionStatus::Failed, "type #note as access denied\nERROR - cannot find #g-recaptcha-response")), None);
+        // Udany przebieg nie blokuje witryny, nawet jeśli strona wspomina captchę
+        assert_eq!(detect(&execution(ExecutionStatus::Succeeded, "solved captcha")), None);
+
+        let base = Duration::from_secs(600);
+        assert_eq!(cooldown(base, 1), base);
+        assert_eq!(cooldown(base, 3), Duration::from_secs(2400));
+        assert_eq!(cooldown(base, 40), MAX_COOLDOWN);
+    }
+}
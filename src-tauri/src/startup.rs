@@ -0,0 +1,207 @@
+//! Leniwy start zależności: okno i serwer API wstają od razu, a baza danych, Redis i Bitwarden łączą się w tle
+//! (z ponawianiem). `/setup/status` pokazuje, co już działa, a endpointy zależne od backendu, który jeszcze
+//! startuje, odpowiadają 503 zamiast czekać na połączenie.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Pierwsza przerwa przed ponowną próbą połączenia; kolejne są podwajane
+pub const RETRY_BASE: Duration = Duration::from_secs(2);
+
+/// Najdłuższa przerwa między próbami
+pub const RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// Ścieżki działające bez bazy danych (prefiksy)
+const DATABASE_FREE_PATHS: &[&str] = &[
+    "/health", "/setup/status", "/shutdown", "/system/", "/logs", "/fixtures", "/dsl/lint", "/page/",
+];
+
+/// Wyjątki od `DATABASE_FREE_PATHS` (prefiksy), które korzystają z bazy (np. rejestracja artefaktów, archiwum)
+const DATABASE_PATHS: &[&str] = &["/page/screenshot", "/system/archive"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Database,
+    Redis,
+    Bitwarden,
+}
+
+impl Backend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Database => "database",
+            Backend::Redis => "redis",
+            Backend::Bitwarden => "bitwarden",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendState {
+    /// Pierwsza próba jeszcze trwa
+    Starting,
+    Ready,
+    /// Ostatnia próba się nie udała; baza danych i Redis są ponawiane
+    Failed,
+    /// Nieskonfigurowany (np. bez REDIS_URL)
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStatus {
+    pub backend: Backend,
+    pub state: BackendState,
+    pub attempts: u32,
+    /// Tylko do logu - `/setup/status` jest publiczny, a błąd może zawierać adresy i nazwy hostów
+    #[serde(skip)]
+    pub error: Option<String>,
+    pub ready_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupReport {
+    pub started_at: DateTime<Utc>,
+    /// Każdy skonfigurowany backend zakończył start (gotowy albo z błędem), a baza danych działa
+    pub complete: bool,
+    pub backends: Vec<BackendStatus>,
+}
+
+/// Stan startu backendów współdzielony przez zadanie inicjalizacji, middleware i `/setup/status`
+pub struct StartupStatus {
+    started_at: DateTime<Utc>,
+    backends: Mutex<HashMap<Backend, BackendStatus>>,
+    changed: watch::Sender<()>,
+}
+
+impl StartupStatus {
+    /// `enabled` - backendy skonfigurowane w tym wdrożeniu; pozostałe są od razu `Disabled`
+    pub fn new(enabled: &[Backend]) -> Self {
+        let backends = [Backend::Database, Backend::Redis, Backend::Bitwarden]
+            .into_iter()
+            .map(|backend| {
+                let state = if enabled.contains(&backend) { BackendState::Starting } else { BackendState::Disabled };
+                (backend, BackendStatus { backend, state, attempts: 0, error: None, ready_at: None })
+            })
+            .collect();
+        Self { started_at: Utc::now(), backends: Mutex::new(backends), changed: watch::channel(()).0 }
+    }
+
+    fn update(&self, backend: Backend, change: impl FnOnce(&mut BackendStatus)) {
+        if let Some(status) = self.backends.lock().unwrap().get_mut(&backend) {
+            change(status);
+        }
+        self.changed.send_replace(());
+    }
+
+    pub fn begin_attempt(&self, backend: Backend) {
+        self.update(backend, |status| status.attempts += 1);
+    }
+
+    pub fn mark_ready(&self, backend: Backend) {
+        info!(backend = backend.as_str(), elapsed_ms = (Utc::now() - self.started_at).num_milliseconds(), "Backend is ready");
+        self.update(backend, |status| {
+            status.state = BackendState::Ready;
+            status.error = None;
+            status.ready_at = Some(Utc::now());
+        });
+    }
+
+    pub fn mark_failed(&self, backend: Backend, error: String) {
+        warn!(backend = backend.as_str(), "Backend failed to start: {}", error);
+        self.update(backend, |status| {
+            status.state = BackendState::Failed;
+            status.error = Some(error);
+        });
+    }
+
+    pub fn state(&self, backend: Backend) -> BackendState {
+        self.backends.lock().unwrap().get(&backend).map(|status| status.state).unwrap_or(BackendState::Disabled)
+    }
+
+    pub fn is_ready(&self, backend: Backend) -> bool {
+        self.state(backend) == BackendState::Ready
+    }
+
+    /// Czeka, aż backend będzie gotowy (np. workery i harmonogram na bazę danych)
+    pub async fn wait_ready(&self, backend: Backend) {
+        let mut changed = self.changed.subscribe();
+        while !self.is_ready(backend) {
+            if changed.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Backend, który blokuje żądanie pod `path`, jeśli jeszcze nie wystartował
+    pub fn blocking(&self, path: &str) -> Option<Backend> {
+        // Błąd startu Bitwarden nigdy nie blokował endpointów - czekamy tylko na pierwszą próbę
+        if path.starts_with("/bitwarden/") && self.state(Backend::Bitwarden) == BackendState::Starting {
+            return Some(Backend::Bitwarden);
+        }
+        let exempt = DATABASE_FREE_PATHS.iter().any(|prefix| path.starts_with(prefix)) && !DATABASE_PATHS.iter().any(|prefix| path.starts_with(prefix));
+        (!exempt && !self.is_ready(Backend::Database)).then_some(Backend::Database)
+    }
+
+    pub fn report(&self) -> StartupReport {
+        let mut backends: Vec<BackendStatus> = self.backends.lock().unwrap().values().cloned().collect();
+        backends.sort_by_key(|status| status.backend.as_str());
+        let complete = self.is_ready(Backend::Database)
+            && backends.iter().all(|status| status.state != BackendState::Starting);
+        StartupReport { started_at: self.started_at, complete, backends }
+    }
+}
+
+/// Przerwa przed próbą numer `attempt + 1`: podwajana od `RETRY_BASE`, najwyżej `RETRY_MAX`
+pub fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE.saturating_mul(1 << attempt.clamp(1, 16).saturating_sub(1)).min(RETRY_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_endpoints_unblock_as_backends_start() {
+        let startup = StartupStatus::new(&[Backend::Database, Backend::Bitwarden]);
+        assert_eq!(startup.state(Backend::Redis), BackendState::Disabled);
+        assert_eq!(startup.blocking("/rpa/jobs"), Some(Backend::Database));
+        assert_eq!(startup.blocking("/health/ready"), None);
+        assert_eq!(startup.blocking("/page/analyze"), None);
+        assert_eq!(startup.blocking("/page/screenshot"), Some(Backend::Database));
+        assert_eq!(startup.blocking("/system/archive/restore"), Some(Backend::Database));
+        assert_eq!(startup.blocking("/system/watchdog"), None);
+        assert_eq!(startup.blocking("/bitwarden/login"), Some(Backend::Bitwarden));
+
+        startup.begin_attempt(Backend::Database);
+        startup.mark_failed(Backend::Database, "connection refused".to_string());
+        assert_eq!(startup.blocking("/rpa/jobs"), Some(Backend::Database));
+        assert!(!startup.report().complete);
+
+        // Bitwarden z błędem startu działa jak wcześniej, ale nadal potrzebuje bazy danych
+        startup.mark_failed(Backend::Bitwarden, "bw not installed".to_string());
+        assert_eq!(startup.blocking("/bitwarden/login"), Some(Backend::Database));
+
+        startup.begin_attempt(Backend::Database);
+        startup.mark_ready(Backend::Database);
+        startup.wait_ready(Backend::Database).await;
+        assert_eq!(startup.blocking("/rpa/jobs"), None);
+        assert_eq!(startup.blocking("/bitwarden/login"), None);
+        let report = startup.report();
+        assert!(report.complete);
+        assert_eq!(report.backends[0].attempts, 0);
+        assert_eq!(report.backends[1].attempts, 2);
+        startup.mark_failed(Backend::Bitwarden, "bw at /home/user/.local/bin failed".to_string());
+        assert!(!serde_json::to_string(&startup.report()).unwrap().contains("/home/user"));
+
+        assert_eq!(retry_delay(1), RETRY_BASE);
+        assert_eq!(retry_delay(3), RETRY_BASE * 4);
+        assert_eq!(retry_delay(30), RETRY_MAX);
+    }
+}
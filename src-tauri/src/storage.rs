@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::{info, warn, error, debug};
+
+/// Poziom wolnego miejsca na dysku
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskLevel {
+    Ok,
+    Low,
+    Critical,
+}
+
+impl DiskLevel {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => DiskLevel::Low,
+            2 => DiskLevel::Critical,
+            _ => DiskLevel::Ok,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryUsage {
+    pub path: String,
+    pub available_bytes: Option<u64>,
+    pub level: DiskLevel,
+}
+
+/// Monitoruje wolne miejsce w katalogach logów i artefaktów
+pub struct DiskMonitor {
+    directories: Vec<PathBuf>,
+    warn_free_bytes: u64,
+    critical_free_bytes: u64,
+    level: AtomicU8,
+    last_report: Mutex<Vec<DirectoryUsage>>,
+}
+
+impl DiskMonitor {
+    pub fn new(directories: Vec<PathBuf>, warn_free_mb: u64, critical_free_mb: u64) -> Self {
+        Self {
+            directories,
+            warn_free_bytes: warn_free_mb * 1024 * 1024,
+            critical_free_bytes: critical_free_mb * 1024 * 1024,
+            level: AtomicU8::new(0),
+            last_report: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn level(&self) -> DiskLevel {
+        DiskLevel::from_u8(self.level.load(Ordering::SeqCst))
+    }
+
+    /// Screenshots and HAR files are shed as soon as space runs low
+    pub fn allows_artifacts(&self) -> bool {
+        self.level() == DiskLevel::Ok
+    }
+
+    pub fn report(&self) -> Vec<DirectoryUsage> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    fn classify(&self, available_bytes: u64) -> DiskLevel {
+        if available_bytes < self.critical_free_bytes {
+            DiskLevel::Critical
+        } else if available_bytes < self.warn_free_bytes {
+            DiskLevel::Low
+        } else {
+            DiskLevel::Ok
+        }
+    }
+
+    /// Sprawdza wszystkie katalogi i zwraca najgorszy poziom
+    pub fn check(&self) -> DiskLevel {
+        let usage: Vec<DirectoryUsage> = self
+            .directories
+            .iter()
+            .map(|dir| {
+                let available_bytes = fs2::available_space(dir).ok();
+                DirectoryUsage {
+                    path: dir.display().to_string(),
+                    available_bytes,
+                    level: available_bytes.map(|bytes| self.classify(bytes)).unwrap_or(DiskLevel::Ok),
+                }
+            })
+            .collect();
+
+        let worst = usage
+            .iter()
+            .map(|u| u.level)
+            .max_by_key(|level| *level as u8)
+            .unwrap_or(DiskLevel::Ok);
+
+        let previous = DiskLevel::from_u8(self.level.swap(worst as u8, Ordering::SeqCst));
+        if previous != worst {
+            match worst {
+                DiskLevel::Ok => info!("Disk space recovered, artifact capture re-enabled"),
+                DiskLevel::Low => warn!("Disk space low, shedding screenshots/HAR capture"),
+                DiskLevel::Critical => error!("Disk space critical, compressing logs aggressively"),
+            }
+        }
+
+        *self.last_report.lock().unwrap() = usage;
+        worst
+    }
+
+    /// Okresowe sprawdzanie miejsca; przy niskim poziomie kompresuje stare logi
+    pub async fn run(&self, log_dir: PathBuf, interval: Duration) {
+        loop {
+            let level = self.check();
+            if level != DiskLevel::Ok {
+                // W stanie krytycznym kompresuj wszystko poza plikami aktualnie zapisywanymi
+                let min_age = if level == DiskLevel::Critical {
+                    Duration::from_secs(5 * 60)
+                } else {
+                    Duration::from_secs(24 * 60 * 60)
+                };
+                match compress_rotated_logs(&log_dir, min_age) {
+                    Ok(0) => {}
+                    Ok(count) => info!("Compressed {} rotated log files", count),
+                    Err(e) => warn!("Failed to compress rotated logs: {}", e),
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+/// Kompresuje gzipem pliki logów niemodyfikowane od `min_age`; bieżący plik każdego appendera zostaje
+pub fn compress_rotated_logs(log_dir: &Path, min_age: Duration) -> io::Result<usize> {
+    let mut compressed = 0;
+    let now = SystemTime::now();
+    let today = chrono::Utc::now();
+
+    for entry in fs::read_dir(log_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_file() || path.extension().map(|ext| ext == "gz").unwrap_or(false) {
+            continue;
+        }
+        if is_current_log(&entry.file_name().to_string_lossy(), today) {
+            continue;
+        }
+
+        let modified = entry.metadata()?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() < min_age {
+            continue;
+        }
+
+        let gz_path = PathBuf::from(format!("{}.gz", path.display()));
+        let mut input = fs::File::open(&path)?;
+        let mut encoder = GzEncoder::new(fs::File::create(&gz_path)?, Compression::best());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?;
+        fs::remove_file(&path)?;
+
+        debug!("Compressed {}", path.display());
+        compressed += 1;
+    }
+
+    Ok(compressed)
+}
+
+/// Plik, do którego appender nadal pisze: bez rotacji (`tagui.log`) albo z sufiksem bieżącego dnia
+/// lub godziny (UTC, jak w `tracing_appender`)
+fn is_current_log(name: &str, now: chrono::DateTime<chrono::Utc>) -> bool {
+    name.ends_with(".log")
+        || name.ends_with(&format!(".log.{}", now.format("%Y-%m-%d")))
+        || name.ends_with(&format!(".log.{}", now.format("%Y-%m-%d-%H")))
+}
+
+/// Zamienia błąd braku miejsca na czytelny komunikat
+pub fn describe_io_error(context: &str, error: &io::Error) -> String {
+    if error.kind() == io::ErrorKind::StorageFull {
+        format!("{}: disk is full, free up space in the logs/artifacts directories", context)
+    } else {
+        format!("{}: {}", context, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_thresholds() {
+        let monitor = DiskMonitor::new(Vec::new(), 100, 10);
+        assert_eq!(monitor.classify(200 * 1024 * 1024), DiskLevel::Ok);
+        assert_eq!(monitor.classify(50 * 1024 * 1024), DiskLevel::Low);
+        assert_eq!(monitor.classify(1024), DiskLevel::Critical);
+    }
+
+    #[test]
+    fn test_compress_rotated_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("app.log.2024-01-01"), "old log line\n").unwrap();
+
+        let count = compress_rotated_logs(dir.path(), Duration::from_secs(0)).unwrap();
+        assert_eq!(count, 1);
+        assert!(dir.path().join("app.log.2024-01-01.gz").exists());
+        assert!(!dir.path().join("app.log.2024-01-01").exists());
+    }
+
+    #[test]
+    fn test_compress_skips_current_log_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = chrono::Utc::now();
+        let current = [
+            format!("app.log.{}", now.format("%Y-%m-%d")),
+            format!("debug.log.{}", now.format("%Y-%m-%d-%H")),
+            "tagui.log".to_string(),
+        ];
+        for name in current.iter().chain(["error.log.2024-01-01".to_string()].iter()) {
+            fs::write(dir.path().join(name), "log line\n").unwrap();
+        }
+
+        assert_eq!(compress_rotated_logs(dir.path(), Duration::from_secs(0)).unwrap(), 1);
+        assert!(current.iter().all(|name| dir.path().join(name).exists()));
+    }
+}
@@ -1,161 +1,1659 @@
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::fs;
-use std::path::Path;
-use tracing::{info, error, debug};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::{broadcast, Notify, Semaphore};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use tracing::{info, error, debug, warn};
 
-pub async fn execute_script(dsl_script: &str) -> bool {
-    info!("Executing TagUI script");
-    
-    // Validate script first
-    if let Err(e) = validate_dsl_script(dsl_script) {
-        error!("Invalid DSL script: {}", e);
-        return false;
+use crate::debugger;
+use crate::executor::{self, ExecutionBackend};
+use crate::faults::{self, FaultTarget};
+use crate::logging::LogManager;
+use crate::pacing::{self, Pacer, PacingProfile};
+use crate::perf;
+use crate::replay;
+use crate::secure_input::{self, SecureField, SecureInputError};
+use crate::storage;
+use crate::tagui_path::{self, ResolvedTagui};
+use crate::throttle::SubmissionThrottle;
+use crate::trace::{self, ExecutionTrace};
+use codialog_types::SecretString;
+
+pub use codialog_types::automation::BrowserMode;
+
+/// Komendy obsługiwane przez DSL
+pub const DSL_COMMANDS: &[&str] = &[
+    "click", "type", "upload", "hover", "wait", "waitfor", "select", "check", "uncheck", "press", "scroll",
+];
+
+/// Domyślny limit `waitfor` bez `timeout N`; to także domyślny timeout elementów TagUI, przywracany po komendzie
+pub const DEFAULT_WAITFOR_TIMEOUT_SECS: u64 = 10;
+
+/// Zmienna TagUI, do której `waitfor` odczytuje tekst elementu (wartość nie jest używana)
+const WAITFOR_VARIABLE: &str = "codialog_waitfor";
+
+/// Kierunki dla `scroll`; każdy inny argument traktowany jest jako selektor
+const SCROLL_DIRECTIONS: &[&str] = &["up", "down", "top", "bottom"];
+
+/// Słowa kluczowe bloków sterujących: `if present`, `repeat N`, `for each`, `frame`, zamykane przez `end`
+pub const DSL_BLOCK_KEYWORDS: &[&str] = &["if", "repeat", "for", "frame", "end"];
+
+/// Górny limit prób dla adnotacji `retry`, aby backoff nie rozciągał przebiegu w nieskończoność
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// Placeholder for the element currently visited by a `for each` loop
+pub const FOR_EACH_ITEM: &str = "@item";
+
+/// Tekst wstawiany w miejsce sekretów w wynikach przebiegów
+pub const SECRET_MASK: &str = "********";
+
+/// Status całego wykonania skryptu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Succeeded,
+    Failed,
+    InvalidScript,
+    SpawnError,
+    Cancelled,
+    /// Przekroczono limit czasu przebiegu; TagUI i przeglądarka zostały zabite
+    TimedOut,
+}
+
+/// Status pojedynczej komendy DSL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    pub line: usize,
+    pub command: String,
+    pub status: StepStatus,
+}
+
+/// Wynik wykonania skryptu TagUI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionResult {
+    pub status: ExecutionStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub steps: Vec<StepResult>,
+    pub duration_ms: u64,
+    /// 1-based line of the DSL command that broke the run, if it could be determined
+    pub failed_line: Option<usize>,
+    pub error: Option<String>,
+    /// HTML strony w chwili błędu (silnik CDP) - kontekst dla `healing`; nie jest zapisywany ani zwracany
+    #[serde(skip)]
+    pub page_html: Option<String>,
+}
+
+impl ExecutionResult {
+    pub fn success(&self) -> bool {
+        self.status == ExecutionStatus::Succeeded
+    }
+
+    /// Zastępuje wartości sekretów w wyjściu TagUI, które echo-uje wpisywany tekst
+    pub fn mask_secrets(&mut self, secrets: &[SecretString]) {
+        let mask = |text: &str| mask_secret_values(text, secrets);
+        self.stdout = mask(&self.stdout);
+        self.stderr = mask(&self.stderr);
+        self.error = self.error.as_deref().map(mask);
+        for step in &mut self.steps {
+            step.command = mask(&step.command);
+        }
+    }
+
+    pub(crate) fn early_failure(status: ExecutionStatus, error: String, failed_line: Option<usize>, started: Instant) -> Self {
+        Self {
+            status,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            steps: Vec::new(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            failed_line,
+            error: Some(error),
+            page_html: None,
+        }
     }
+}
+
+pub async fn execute_script(dsl_script: &str) -> ExecutionResult {
+    execute_script_cancellable(
+        dsl_script,
+        Arc::new(Notify::new()),
+        None,
+        None,
+        BrowserMode::default(),
+        PacingProfile::default(),
+        Some(DEFAULT_RUN_TIMEOUT),
+        None,
+        &[],
+        &[],
+        secure_input::DEFAULT_TAGUI_DEBUG_PORT,
+    )
+    .await
+}
+
+/// Wykonuje skrypt TagUI; proces potomny jest zabijany po sygnale `cancel`.
+/// With `screenshot_dir` set, a screenshot is saved there after every command;
+/// with `debug_dir` set, the script pauses on breakpoints and in step mode (see `debugger`).
+/// Pola z `secure_fields` wpisuje backend przez CDP (`secure_input`) w przeglądarce TagUI na porcie
+/// `debug_port`, a nie TagUI; linii z wartością
+/// z `secrets` tempo `human` nie rozbija na znaki.
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_script_cancellable(
+    dsl_script: &str,
+    cancel: Arc<Notify>,
+    screenshot_dir: Option<&Path>,
+    debug_dir: Option<&Path>,
+    browser_mode: BrowserMode,
+    pacing: PacingProfile,
+    timeout: Option<Duration>,
+    output: Option<OutputSink>,
+    secure_fields: &[SecureField],
+    secrets: &[SecretString],
+    debug_port: u16,
+) -> ExecutionResult {
+    info!("Executing TagUI script in {:?} browser mode with {} pacing", browser_mode, pacing.as_str());
+    let started = Instant::now();
+    
+    // Każdy przebieg dostaje własny katalog, bo TagUI zapisuje pliki obok skryptu
+    let run_dir = match tempfile::Builder::new().prefix("codialog-run-").tempdir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            error!("Failed to create run directory: {}", e);
+            return ExecutionResult::early_failure(
+                ExecutionStatus::SpawnError,
+                storage::describe_io_error("Failed to create run directory", &e),
+                None,
+                started,
+            );
+        }
+    };
     
-    // Zapisz skrypt do pliku tymczasowego
-    let script_path = "temp_script.codialog";
-    match fs::write(script_path, dsl_script) {
-        Ok(_) => debug!("Script written to {}", script_path),
+    // Validate script first and lower control blocks to TagUI flow syntax
+    let secure = (!secure_fields.is_empty()).then_some((run_dir.path(), secure_fields));
+    let compiled_script = match compile_dsl_script_with(dsl_script, screenshot_dir, debug_dir, secure, secrets, &mut Pacer::new(pacing)) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            error!("Invalid DSL script: {}", e);
+            return ExecutionResult::early_failure(ExecutionStatus::InvalidScript, e.to_string(), Some(e.line), started);
+        }
+    };
+    let script_path = run_dir.path().join("script.codialog");
+    match fs::write(&script_path, &compiled_script) {
+        Ok(_) => debug!("Script written to {}", script_path.display()),
         Err(e) => {
             error!("Failed to write script file: {}", e);
-            return false;
+            return ExecutionResult::early_failure(
+                ExecutionStatus::SpawnError,
+                storage::describe_io_error("Failed to write script file", &e),
+                None,
+                started,
+            );
         }
     }
     
     // Uruchom TagUI
-    let output = Command::new("tagui")
-        .arg(script_path)
-        .arg("chrome")
-        .output();
+    if let Err(e) = faults::inject(FaultTarget::Spawn).await {
+        return ExecutionResult::early_failure(ExecutionStatus::SpawnError, e.to_string(), None, started);
+    }
+    let mut command = tokio::process::Command::new(tagui_executable());
+    command
+        .arg(&script_path)
+        .arg(browser_mode.tagui_flag())
+        .args(pacing.tagui_flag())
+        .current_dir(run_dir.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Własna grupa procesów, żeby watchdog mógł zabić całe drzewo
+    #[cfg(unix)]
+    command.process_group(0);
+    let spawned = command.spawn();
     
-    // Usuń plik tymczasowy
-    fs::remove_file(script_path).ok();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            error!("Failed to execute TagUI: {}", e);
+            return ExecutionResult::early_failure(
+                ExecutionStatus::SpawnError,
+                format!("Failed to execute TagUI: {}", e),
+                None,
+                started,
+            );
+        }
+    };
+    
+    let stdout_reader = tokio::spawn(read_pipe(child.stdout.take(), OutputStream::Stdout, output.clone()));
+    let stderr_reader = tokio::spawn(read_pipe(child.stderr.take(), OutputStream::Stderr, output));
+    
+    let outcome = tokio::select! {
+        status = child.wait() => ProcessOutcome::Exited(status),
+        _ = cancel.notified() => {
+            info!("Cancelling TagUI execution");
+            kill_process_tree(&mut child).await;
+            ProcessOutcome::Cancelled
+        }
+        _ = run_deadline(timeout) => {
+            warn!("TagUI execution exceeded {:?}, killing TagUI and its browser", timeout.unwrap_or_default());
+            kill_process_tree(&mut child).await;
+            ProcessOutcome::TimedOut
+        }
+        failure = secure_input::serve(run_dir.path(), secure_fields, debug_port), if secure.is_some() => {
+            warn!(line = failure.line, "Secure input failed, stopping TagUI: {}", failure.message);
+            kill_process_tree(&mut child).await;
+            ProcessOutcome::SecureInputFailed(failure)
+        }
+    };
     
-    match output {
-        Ok(result) => {
-            if result.status.success() {
+    let stdout = stdout_reader.await.unwrap_or_default();
+    let stderr = stderr_reader.await.unwrap_or_default();
+    // Pola wpisywane przez backend TagUI zgłasza krokiem `dump` zamiast komendy `type`
+    let echoes: HashMap<usize, String> = secure_fields
+        .iter()
+        .map(|field| (field.line, secure_input::request_step(run_dir.path(), field.line)))
+        .collect();
+    
+    match outcome {
+        ProcessOutcome::Exited(Ok(status)) => {
+            let succeeded = status.success();
+            let (steps, failed_line) = build_step_results(dsl_script, &stdout, succeeded, &echoes);
+            
+            if succeeded {
                 info!("TagUI script executed successfully");
-                true
             } else {
-                error!("TagUI execution failed: {}", String::from_utf8_lossy(&result.stderr));
-                false
+                error!("TagUI execution failed: {}", stderr);
+            }
+            
+            ExecutionResult {
+                status: if succeeded { ExecutionStatus::Succeeded } else { ExecutionStatus::Failed },
+                exit_code: status.code(),
+                error: if succeeded { None } else { Some(first_error_line(&stdout, &stderr)) },
+                stdout,
+                stderr,
+                steps,
+                duration_ms: started.elapsed().as_millis() as u64,
+                failed_line,
+                page_html: None,
             }
         }
-        Err(e) => {
-            error!("Failed to execute TagUI: {}", e);
-            false
+        ProcessOutcome::Exited(Err(e)) => {
+            error!("Failed to wait for TagUI: {}", e);
+            ExecutionResult::early_failure(
+                ExecutionStatus::SpawnError,
+                format!("Failed to wait for TagUI: {}", e),
+                None,
+                started,
+            )
+        }
+        ProcessOutcome::SecureInputFailed(failure) => {
+            let (mut steps, _) = build_step_results(dsl_script, &stdout, false, &echoes);
+            for step in steps.iter_mut() {
+                step.status = match step.line.cmp(&failure.line) {
+                    std::cmp::Ordering::Less => StepStatus::Succeeded,
+                    std::cmp::Ordering::Equal => StepStatus::Failed,
+                    std::cmp::Ordering::Greater => StepStatus::Skipped,
+                };
+            }
+            ExecutionResult {
+                status: ExecutionStatus::Failed,
+                exit_code: None,
+                stdout,
+                stderr,
+                steps,
+                duration_ms: started.elapsed().as_millis() as u64,
+                failed_line: Some(failure.line),
+                error: Some(failure.message),
+                page_html: None,
+            }
+        }
+        ProcessOutcome::Cancelled | ProcessOutcome::TimedOut => {
+            let (steps, _) = build_step_results(dsl_script, &stdout, false, &echoes);
+            let (status, error) = match outcome {
+                ProcessOutcome::TimedOut => (
+                    ExecutionStatus::TimedOut,
+                    format!("Execution timed out after {}s", timeout.unwrap_or_default().as_secs()),
+                ),
+                _ => (ExecutionStatus::Cancelled, "Execution cancelled".to_string()),
+            };
+            ExecutionResult {
+                status,
+                exit_code: None,
+                stdout,
+                stderr,
+                steps,
+                duration_ms: started.elapsed().as_millis() as u64,
+                failed_line: None,
+                error: Some(error),
+                page_html: None,
+            }
         }
     }
 }
 
-pub fn install_tagui() -> bool {
-    info!("Installing TagUI...");
-    
-    // Sprawdź czy TagUI jest zainstalowane
-    if Path::new("tagui").exists() {
-        info!("TagUI directory already exists");
-        return true;
+enum ProcessOutcome {
+    Exited(std::io::Result<std::process::ExitStatus>),
+    Cancelled,
+    TimedOut,
+    SecureInputFailed(SecureInputError),
+}
+
+pub(crate) async fn run_deadline(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
     }
-    
-    // Pobierz i zainstaluj TagUI
-    let output = Command::new("git")
-        .args(&["clone", "https://github.com/aisingapore/tagui"])
-        .output();
-    
-    match output {
-        Ok(result) => {
-            if result.status.success() {
-                info!("TagUI cloned successfully");
-                
-                // Zainstaluj zależności npm w folderze tagui
-                let npm_install = Command::new("npm")
-                    .arg("install")
-                    .current_dir("tagui")
-                    .output();
-                
-                match npm_install {
-                    Ok(npm_result) => {
-                        if npm_result.status.success() {
-                            info!("TagUI dependencies installed");
-                            true
-                        } else {
-                            error!("Failed to install TagUI npm dependencies: {}", 
-                                   String::from_utf8_lossy(&npm_result.stderr));
-                            false
-                        }
+}
+
+/// Zabija TagUI razem z procesami potomnymi (przeglądarka, PhantomJS/CasperJS),
+/// które inaczej trzymałyby otwarte potoki stdout/stderr
+async fn kill_process_tree(child: &mut tokio::process::Child) {
+    if let Some(pid) = child.id() {
+        #[cfg(unix)]
+        let killed = tokio::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", pid)])
+            .status()
+            .await;
+        #[cfg(windows)]
+        let killed = tokio::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .status()
+            .await;
+        if let Err(e) = killed {
+            warn!("Failed to kill TagUI process tree: {}", e);
+        }
+    }
+    if let Err(e) = child.kill().await {
+        debug!("TagUI process already gone: {}", e);
+    }
+}
+
+/// Zastępuje wartości sekretów (także w postaci escapowanej dla DSL) maską
+pub(crate) fn mask_secret_values(text: &str, secrets: &[SecretString]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            let escaped = escape_for_dsl(secret.expose_secret());
+            text.replace(escaped.as_str(), SECRET_MASK).replace(secret.expose_secret(), SECRET_MASK)
+        })
+}
+
+/// Czy tekst zawiera wartość któregoś sekretu (także w postaci escapowanej dla DSL)
+fn contains_secret(text: &str, secrets: &[SecretString]) -> bool {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .any(|secret| text.contains(secret.expose_secret()) || text.contains(escape_for_dsl(secret.expose_secret()).as_str()))
+}
+
+/// Strumień wyjścia procesu TagUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Linia wyjścia TagUI publikowana na żywo w trakcie przebiegu
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputLine {
+    pub run_id: String,
+    pub stream: OutputStream,
+    pub line: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Receives every line of TagUI output as soon as it is printed
+pub type OutputSink = Arc<dyn Fn(OutputStream, &str) + Send + Sync>;
+
+/// Liczba ostatnich linii przechowywanych dla klientów dołączających w trakcie przebiegu
+const OUTPUT_HISTORY_LINES: usize = 500;
+
+/// Reads the pipe line by line, forwarding each line to `sink`, and returns the whole output
+async fn read_pipe<R: tokio::io::AsyncRead + Unpin>(pipe: Option<R>, stream: OutputStream, sink: Option<OutputSink>) -> String {
+    let mut buffer = Vec::new();
+    if let Some(pipe) = pipe {
+        let mut reader = tokio::io::BufReader::new(pipe);
+        loop {
+            let start = buffer.len();
+            match reader.read_until(b'\n', &mut buffer).await {
+                Ok(0) => break,
+                Ok(_) => {
+                    if let Some(sink) = &sink {
+                        sink(stream, String::from_utf8_lossy(&buffer[start..]).trim_end());
                     }
-                    Err(e) => {
-                        error!("Failed to run npm install: {}", e);
-                        false
+                }
+                Err(e) => {
+                    debug!("Failed to read TagUI output: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    String::from_utf8_lossy(&buffer).to_string()
+}
+
+/// Stan przebiegu zarządzanego przez `RunManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    Queued,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunInfo {
+    pub run_id: String,
+    pub state: RunState,
+    pub queued_at: DateTime<Utc>,
+    /// 1-based position among waiting runs, only set while queued
+    pub queue_position: Option<usize>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub result: Option<ExecutionResult>,
+    /// Przebieg czeka do tej chwili na odstęp między wysłaniami na witrynie
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttled_until: Option<DateTime<Utc>>,
+}
+
+/// Obciążenie kolejki przebiegów
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunQueueStats {
+    pub max_parallel: usize,
+    pub running: usize,
+    pub queued: usize,
+}
+
+struct RunEntry {
+    info: RunInfo,
+    cancel: Arc<Notify>,
+    output: Arc<RunOutput>,
+}
+
+/// Wyjście przebiegu na żywo: ostatnie linie oraz kanał dla subskrybentów.
+/// The sender is dropped when the run finishes, which ends every subscription.
+struct RunOutput {
+    history: Mutex<VecDeque<OutputLine>>,
+    /// Wszystkie linie (do `MAX_TRACE_LINES`) ze znacznikami czasu dla `RunManager::trace`
+    timeline: Mutex<Vec<OutputLine>>,
+    sender: Mutex<Option<broadcast::Sender<OutputLine>>>,
+}
+
+impl RunOutput {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(OUTPUT_HISTORY_LINES);
+        Self {
+            history: Mutex::new(VecDeque::new()),
+            timeline: Mutex::new(Vec::new()),
+            sender: Mutex::new(Some(sender)),
+        }
+    }
+
+    fn publish(&self, line: OutputLine) {
+        {
+            let mut history = self.history.lock().unwrap();
+            if history.len() == OUTPUT_HISTORY_LINES {
+                history.pop_front();
+            }
+            history.push_back(line.clone());
+        }
+        {
+            let mut timeline = self.timeline.lock().unwrap();
+            if timeline.len() < trace::MAX_TRACE_LINES {
+                timeline.push(line.clone());
+            }
+        }
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(line);
+        }
+    }
+
+    fn close(&self) {
+        self.sender.lock().unwrap().take();
+    }
+}
+
+/// Subskrypcja wyjścia przebiegu: linie do tej pory i odbiornik kolejnych (`None` po zakończeniu)
+pub struct OutputSubscription {
+    pub history: Vec<OutputLine>,
+    pub receiver: Option<broadcast::Receiver<OutputLine>>,
+}
+
+/// Rejestr aktywnych i niedawno zakończonych przebiegów TagUI.
+/// At most `max_parallel` runs execute at once; the rest wait in FIFO order.
+pub struct RunManager {
+    runs: Mutex<HashMap<String, RunEntry>>,
+    pending: Mutex<VecDeque<String>>,
+    slots: Semaphore,
+    max_parallel: usize,
+    retention: chrono::Duration,
+    default_browser_mode: BrowserMode,
+    default_timeout: Option<Duration>,
+    default_backend: ExecutionBackend,
+    default_pacing: PacingProfile,
+    log_manager: Option<Arc<LogManager>>,
+    throttle: Option<Arc<SubmissionThrottle>>,
+    debug_port: u16,
+}
+
+/// Opcje pojedynczego przebiegu
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Wartości sekretów maskowane w zapisanym i zwracanym wyniku
+    pub secrets: Vec<SecretString>,
+    /// Screenshots go to `<root>/<run_id>/step-NNN.png` when set
+    pub screenshots_root: Option<PathBuf>,
+    /// Nadpisuje domyślną przeglądarkę menedżera (TAGUI_BROWSER_MODE)
+    pub browser_mode: Option<BrowserMode>,
+    /// Overrides the manager's run timeout (TAGUI_RUN_TIMEOUT_SECS)
+    pub timeout: Option<Duration>,
+    /// Identyfikator nadany z góry, np. przez sesję debugowania; domyślnie nowy UUID
+    pub run_id: Option<String>,
+    /// Katalog sesji debugowania - przebieg zatrzymuje się na breakpointach i w trybie krokowym
+    pub debug_dir: Option<PathBuf>,
+    /// Nadpisuje domyślny silnik menedżera (EXECUTION_BACKEND)
+    pub backend: Option<ExecutionBackend>,
+    /// Strona otwierana przed pierwszą komendą przez silnik CDP
+    pub start_url: Option<String>,
+    /// Nadpisuje domyślne tempo menedżera (EXECUTION_PACING)
+    pub pacing: Option<PacingProfile>,
+    /// Pola z sekretem pozostawione w skrypcie jako `{{nazwa}}` - TagUI zostawia je backendowi (CDP),
+    /// inne silniki dostają sekret wstawiony w skrypt w pamięci
+    pub secure_fields: Vec<SecureField>,
+    /// Witryna, na której przebieg wysyła formularz (`throttle::submission_site`) - przed startem
+    /// czeka na minimalny odstęp od poprzedniego wysłania
+    pub submission_site: Option<String>,
+}
+
+/// Domyślna liczba równoległych przebiegów TagUI
+pub const DEFAULT_MAX_PARALLEL_RUNS: usize = 2;
+
+/// Domyślny limit czasu jednego przebiegu TagUI
+pub const DEFAULT_RUN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+impl Default for RunManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PARALLEL_RUNS)
+    }
+}
+
+impl RunManager {
+    pub fn new(max_parallel: usize) -> Self {
+        let max_parallel = max_parallel.max(1);
+        Self {
+            runs: Mutex::new(HashMap::new()),
+            pending: Mutex::new(VecDeque::new()),
+            slots: Semaphore::new(max_parallel),
+            max_parallel,
+            retention: chrono::Duration::hours(1),
+            default_browser_mode: BrowserMode::default(),
+            default_timeout: Some(DEFAULT_RUN_TIMEOUT),
+            default_backend: ExecutionBackend::default(),
+            default_pacing: PacingProfile::default(),
+            log_manager: None,
+            throttle: None,
+            debug_port: secure_input::DEFAULT_TAGUI_DEBUG_PORT,
+        }
+    }
+
+    /// Wyjście TagUI zapisywane na bieżąco do `tagui.log`
+    pub fn with_log_manager(mut self, log_manager: Arc<LogManager>) -> Self {
+        self.log_manager = Some(log_manager);
+        self
+    }
+
+    /// Limit czasu przebiegów bez własnego `timeout`; `None` wyłącza watchdog
+    pub fn with_run_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Przeglądarka dla przebiegów bez własnego `browser_mode` (np. zadań z kolejki)
+    pub fn with_browser_mode(mut self, browser_mode: BrowserMode) -> Self {
+        self.default_browser_mode = browser_mode;
+        self
+    }
+
+    /// Silnik dla przebiegów bez własnego `backend`
+    pub fn with_execution_backend(mut self, backend: ExecutionBackend) -> Self {
+        self.default_backend = backend;
+        self
+    }
+
+    /// Port zdalnego debugowania przeglądarki TagUI, w której backend wpisuje sekrety
+    pub fn with_debug_port(mut self, port: u16) -> Self {
+        self.debug_port = port;
+        self
+    }
+
+    /// Tempo przebiegów bez własnego `pacing`
+    pub fn with_pacing(mut self, pacing: PacingProfile) -> Self {
+        self.default_pacing = pacing;
+        self
+    }
+
+    /// Odstępy między wysłaniami na witrynie dla przebiegów z `submission_site`
+    pub fn with_throttle(mut self, throttle: Arc<SubmissionThrottle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    pub fn throttle(&self) -> Option<&Arc<SubmissionThrottle>> {
+        self.throttle.as_ref()
+    }
+
+    pub async fn execute(&self, dsl_script: &str) -> (String, ExecutionResult) {
+        self.execute_with(dsl_script, &RunOptions::default()).await
+    }
+
+    /// Queues the script under a fresh run id and waits until it has run (or was cancelled).
+    pub async fn execute_with(&self, dsl_script: &str, options: &RunOptions) -> (String, ExecutionResult) {
+        let run_id = options.run_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+        let cancel = Arc::new(Notify::new());
+        let output = Arc::new(RunOutput::new());
+        let queued = Instant::now();
+        
+        {
+            let mut runs = self.runs.lock().unwrap();
+            self.prune_finished(&mut runs);
+            runs.insert(run_id.clone(), RunEntry {
+                info: RunInfo {
+                    run_id: run_id.clone(),
+                    state: RunState::Queued,
+                    queued_at: Utc::now(),
+                    queue_position: None,
+                    started_at: None,
+                    finished_at: None,
+                    result: None,
+                    throttled_until: None,
+                },
+                cancel: cancel.clone(),
+                output: output.clone(),
+            });
+        }
+        self.pending.lock().unwrap().push_back(run_id.clone());
+        
+        // Odstęp na witrynie liczy się od startu przebiegu, więc witryna jest zajmowana dopiero z wolnym slotem
+        let throttle = self.throttle.as_ref().zip(options.submission_site.as_deref()).filter(|_| !replay::is_replaying());
+        let mut permit = None;
+        loop {
+            if let Some((throttle, site)) = throttle {
+                let wait = throttle.remaining(site, Utc::now());
+                if !wait.is_zero() {
+                    info!(run_id = %run_id, site = %site, wait_ms = wait.as_millis() as u64, "Run waits for the submission interval of the site");
+                    self.set_throttled_until(&run_id, chrono::Duration::from_std(wait).ok().map(|wait| Utc::now() + wait));
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => continue,
+                        _ = cancel.notified() => return self.cancel_queued(&run_id, queued),
                     }
                 }
+            }
+            // Semaphore tokio jest sprawiedliwy, więc kolejność slotów odpowiada kolejności w `pending`
+            if permit.is_none() {
+                permit = Some(tokio::select! {
+                    permit = self.slots.acquire() => permit.ok(),
+                    _ = cancel.notified() => return self.cancel_queued(&run_id, queued),
+                });
+            }
+            match throttle {
+                // Inny przebieg zajął witrynę w czasie czekania na slot - zajęty slot zostaje, żeby nie stracić kolejki
+                Some((throttle, site)) if !throttle.try_reserve(site, Utc::now()) => continue,
+                _ => break,
+            }
+        }
+        let permit = permit.flatten();
+        self.set_throttled_until(&run_id, None);
+        
+        self.pending.lock().unwrap().retain(|id| id != &run_id);
+        if let Some(entry) = self.runs.lock().unwrap().get_mut(&run_id) {
+            entry.info.state = RunState::Running;
+            entry.info.started_at = Some(Utc::now());
+        }
+        
+        info!(run_id = %run_id, "Starting TagUI run");
+        let screenshot_dir = options.screenshots_root.as_ref().and_then(|root| prepare_screenshot_dir(root, &run_id));
+        let result = replay::intercept(replay::InteractionKind::TaguiRun, "tagui", || async {
+            let browser_mode = options.browser_mode.unwrap_or(self.default_browser_mode);
+            let pacing = options.pacing.unwrap_or(self.default_pacing);
+            let timeout = options.timeout.or(self.default_timeout);
+            let sink = self.output_sink(&run_id, output.clone(), &options.secrets);
+            let backend = options.backend.unwrap_or(self.default_backend);
+            // Firefox w TagUI nie udostępnia CDP, więc sekret trafia do skryptu jak dawniej
+            let hybrid = backend == ExecutionBackend::Tagui && browser_mode != BrowserMode::Firefox;
+            let inlined;
+            let (dsl_script, secure_fields) = if hybrid || options.secure_fields.is_empty() {
+                (dsl_script, options.secure_fields.as_slice())
             } else {
-                error!("Failed to clone TagUI: {}", String::from_utf8_lossy(&result.stderr));
-                false
+                if backend == ExecutionBackend::Tagui {
+                    warn!(run_id = %run_id, "Secure input needs a Chromium browser, secrets are written into the TagUI script");
+                }
+                inlined = secure_input::inline(dsl_script, &options.secure_fields);
+                (inlined.as_str(), &[][..])
+            };
+            let mut result = match backend {
+                ExecutionBackend::Tagui => {
+                    execute_script_cancellable(
+                        dsl_script,
+                        cancel,
+                        screenshot_dir.as_deref(),
+                        options.debug_dir.as_deref(),
+                        browser_mode,
+                        pacing,
+                        timeout,
+                        Some(sink),
+                        secure_fields,
+                        &options.secrets,
+                        self.debug_port,
+                    )
+                    .await
+                }
+                ExecutionBackend::Cdp if options.debug_dir.is_some() => ExecutionResult::early_failure(
+                    ExecutionStatus::InvalidScript,
+                    "Step-through debugging requires the TagUI backend".to_string(),
+                    None,
+                    queued,
+                ),
+                ExecutionBackend::Cdp => {
+                    executor::execute_script(
+                        dsl_script,
+                        options.start_url.as_deref(),
+                        cancel,
+                        screenshot_dir.as_deref(),
+                        browser_mode,
+                        pacing,
+                        timeout,
+                        Some(sink),
+                    )
+                    .await
+                }
+            };
+            result.mask_secrets(&options.secrets);
+            Ok(result)
+        })
+        .await
+        .unwrap_or_else(|e| ExecutionResult::early_failure(ExecutionStatus::SpawnError, e.to_string(), None, queued));
+        drop(permit);
+        
+        self.finish(&run_id, &result);
+        // W trybie krokowym czas kroku obejmuje pauzę, więc nie ma sensu go oceniać
+        if options.debug_dir.is_none() {
+            self.report_slow_steps(&run_id, dsl_script, &options.secrets);
+        }
+        (run_id, result)
+    }
+
+    /// Kroki wolniejsze niż próg SLOW_STEP_MS trafiają do `perf`; etykietą jest komenda z selektorem, bez wpisywanych wartości
+    fn report_slow_steps(&self, run_id: &str, dsl_script: &str, secrets: &[SecretString]) {
+        let Some(trace) = self.trace(run_id, dsl_script) else {
+            return;
+        };
+        let commands = parse_dsl_script(dsl_script).unwrap_or_default();
+        for event in trace.events.iter().filter(|event| event.kind == trace::TraceKind::Step) {
+            let Some(command) = event.line.and_then(|line| commands.iter().find(|command| command.line == line)) else {
+                continue;
+            };
+            let elapsed = Duration::from_millis((event.end_ms - event.start_ms).max(0) as u64);
+            perf::observe(
+                perf::OperationKind::DslStep,
+                &mask_secret_values(&slow_step_label(command), secrets),
+                elapsed,
+                serde_json::json!({ "run_id": run_id, "line": command.line }),
+            );
+        }
+    }
+
+    /// Linie są maskowane, trafiają do `tagui.log` i do subskrybentów `/logs/tagui/tail`
+    fn output_sink(&self, run_id: &str, output: Arc<RunOutput>, secrets: &[SecretString]) -> OutputSink {
+        let run_id = run_id.to_string();
+        let secrets = secrets.to_vec();
+        let log_manager = self.log_manager.clone();
+        Arc::new(move |stream, line| {
+            let line = mask_secret_values(line, &secrets);
+            if let Some(log_manager) = &log_manager {
+                let message = format!("[{}] {}", run_id, line);
+                if let Err(e) = log_manager.log_tagui(&message, stream == OutputStream::Stdout) {
+                    debug!("Failed to write TagUI log: {}", e);
+                }
             }
+            output.publish(OutputLine { run_id: run_id.clone(), stream, line, at: Utc::now() });
+        })
+    }
+
+    /// Subskrybuje wyjście przebiegu; `None`, gdy przebieg nie istnieje
+    pub fn subscribe_output(&self, run_id: &str) -> Option<OutputSubscription> {
+        let output = self.runs.lock().unwrap().get(run_id).map(|entry| entry.output.clone())?;
+        // Odbiornik przed kopią historii - linia opublikowana pomiędzy może się powtórzyć, ale nie zginie
+        let receiver = output.sender.lock().unwrap().as_ref().map(|sender| sender.subscribe());
+        let history = output.history.lock().unwrap().iter().cloned().collect();
+        Some(OutputSubscription { history, receiver })
+    }
+
+    fn cancel_queued(&self, run_id: &str, queued: Instant) -> (String, ExecutionResult) {
+        self.pending.lock().unwrap().retain(|id| id != run_id);
+        info!(run_id = %run_id, "Queued TagUI run cancelled");
+        let result = ExecutionResult::early_failure(
+            ExecutionStatus::Cancelled,
+            "Execution cancelled".to_string(),
+            None,
+            queued,
+        );
+        self.finish(run_id, &result);
+        (run_id.to_string(), result)
+    }
+
+    fn set_throttled_until(&self, run_id: &str, until: Option<DateTime<Utc>>) {
+        if let Some(entry) = self.runs.lock().unwrap().get_mut(run_id) {
+            entry.info.throttled_until = until;
         }
-        Err(e) => {
-            error!("Git command failed: {}", e);
-            false
+    }
+
+    fn finish(&self, run_id: &str, result: &ExecutionResult) {
+        if let Some(entry) = self.runs.lock().unwrap().get_mut(run_id) {
+            entry.output.close();
+            entry.info.state = if result.status == ExecutionStatus::Cancelled {
+                RunState::Cancelled
+            } else {
+                RunState::Finished
+            };
+            entry.info.finished_at = Some(Utc::now());
+            entry.info.result = Some(result.clone());
+        }
+    }
+
+    /// Przerywa oczekujący lub trwający przebieg; zwraca false gdy przebieg nie istnieje lub już się zakończył
+    pub fn cancel(&self, run_id: &str) -> bool {
+        match self.runs.lock().unwrap().get(run_id) {
+            Some(entry) if matches!(entry.info.state, RunState::Queued | RunState::Running) => {
+                info!(run_id = %run_id, "Cancellation requested");
+                entry.cancel.notify_one();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn status(&self, run_id: &str) -> Option<RunInfo> {
+        let mut info = self.runs.lock().unwrap().get(run_id).map(|entry| entry.info.clone())?;
+        if info.state == RunState::Queued {
+            info.queue_position = self
+                .pending
+                .lock()
+                .unwrap()
+                .iter()
+                .position(|id| id == run_id)
+                .map(|index| index + 1);
         }
+        Some(info)
+    }
+
+    /// Oś czasu przebiegu zbudowana z wyjścia TagUI; `None`, gdy przebieg nie istnieje
+    pub fn trace(&self, run_id: &str, script: &str) -> Option<ExecutionTrace> {
+        let runs = self.runs.lock().unwrap();
+        let entry = runs.get(run_id)?;
+        let timeline = entry.output.timeline.lock().unwrap();
+        let steps = entry.info.result.as_ref().map(|result| result.steps.as_slice()).unwrap_or_default();
+        let timing = trace::RunTiming {
+            run_id,
+            queued_at: entry.info.queued_at,
+            started_at: entry.info.started_at,
+            finished_at: entry.info.finished_at,
+            steps,
+            truncated: timeline.len() >= trace::MAX_TRACE_LINES,
+        };
+        Some(trace::build_trace(script, &timing, &timeline))
+    }
+
+    pub fn queue_stats(&self) -> RunQueueStats {
+        let queued = self.pending.lock().unwrap().len();
+        RunQueueStats {
+            max_parallel: self.max_parallel,
+            running: self.max_parallel - self.slots.available_permits(),
+            queued,
+        }
+    }
+
+    fn prune_finished(&self, runs: &mut HashMap<String, RunEntry>) {
+        let cutoff = Utc::now() - self.retention;
+        runs.retain(|_, entry| {
+            entry.info.finished_at.map(|finished| finished > cutoff).unwrap_or(true)
+        });
     }
 }
 
-pub async fn check_tagui_installed() -> bool {
-    // Sprawdź czy TagUI jest dostępne w PATH
-    if let Ok(output) = Command::new("tagui").arg("--version").output() {
-        return output.status.success();
+/// Zwraca komendy skryptu wraz z numerami linii (1-based), bez komentarzy i pustych linii
+pub fn script_commands(script: &str) -> Vec<(usize, String)> {
+    script
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("//"))
+        .map(|(number, line)| (number, line.to_string()))
+        .collect()
+}
+
+/// Maps TagUI's echoed step output back onto DSL lines.
+/// TagUI prints each command as it runs and an `ERROR` line when a step fails;
+/// `echoes` holds the TagUI steps printed instead of the command for given lines.
+fn build_step_results(script: &str, stdout: &str, succeeded: bool, echoes: &HashMap<usize, String>) -> (Vec<StepResult>, Option<usize>) {
+    let output_lines: Vec<&str> = stdout.lines().map(|l| l.trim()).collect();
+    let mut cursor = 0;
+    let mut failed_line = None;
+    let mut steps = Vec::new();
+    
+    // TagUI echo-uje komendy już przetłumaczone na swoją składnię
+    let parsed: HashMap<usize, DslCommand> = parse_dsl_script(script)
+        .map(|commands| commands.into_iter().map(|command| (command.line, command)).collect())
+        .unwrap_or_default();
+    
+    for (line, command) in script_commands(script) {
+        // Nagłówki bloków nie są krokami wykonywanymi przez TagUI
+        if is_block_keyword(&command) {
+            continue;
+        }
+        if failed_line.is_some() {
+            steps.push(StepResult { line, command, status: StepStatus::Skipped });
+            continue;
+        }
+        
+        let expected_echo = match echoes.get(&line) {
+            Some(echo) => echo.clone(),
+            None => parsed
+                .get(&line)
+                .map(|parsed_command| translate_command(parsed_command, &command, None))
+                .unwrap_or_else(|| command.clone()),
+        };
+        let echoed = output_lines[cursor..].iter().position(|out| out.contains(expected_echo.as_str()));
+        let status = match echoed {
+            Some(offset) => {
+                cursor += offset + 1;
+                let step_errored = output_lines[cursor..]
+                    .iter()
+                    .take_while(|out| !is_command_echo(out))
+                    .any(|out| out.starts_with("ERROR"));
+                if step_errored { StepStatus::Failed } else { StepStatus::Succeeded }
+            }
+            None if succeeded => StepStatus::Succeeded,
+            None => StepStatus::Failed,
+        };
+        
+        if status == StepStatus::Failed {
+            failed_line = Some(line);
+        }
+        steps.push(StepResult { line, command, status });
     }
     
-    // Sprawdź czy istnieje lokalna instalacja
-    Path::new("tagui/tagui").exists() || Path::new("tagui/tagui.cmd").exists()
+    (steps, failed_line)
 }
 
-pub fn validate_dsl_script(script: &str) -> Result<(), String> {
-    let valid_commands = ["click", "type", "upload", "hover", "wait"];
+pub(crate) fn is_command_echo(output_line: &str) -> bool {
+    output_line
+        .split_whitespace()
+        .next()
+        .map(|word| DSL_COMMANDS.contains(&word) || matches!(word, "keyboard" | "dom" | "snap" | "read"))
+        .unwrap_or(false)
+}
+
+pub fn is_block_keyword(line: &str) -> bool {
+    line.split_whitespace()
+        .next()
+        .map(|word| DSL_BLOCK_KEYWORDS.contains(&word))
+        .unwrap_or(false)
+}
+
+fn first_error_line(stdout: &str, stderr: &str) -> String {
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(|l| l.trim())
+        .find(|l| l.starts_with("ERROR"))
+        .or_else(|| stderr.lines().map(|l| l.trim()).find(|l| !l.is_empty()))
+        .unwrap_or("TagUI execution failed")
+        .to_string()
+}
+
+/// Ścieżka do TagUI ustawiana przez `tagui_install::InstallManager`
+static TAGUI_EXECUTABLE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn set_tagui_executable(path: PathBuf) {
+    *TAGUI_EXECUTABLE.write().unwrap() = Some(path);
+}
+
+/// Plik TagUI wybrany spośród ścieżki użytkownika, wersji zarządzanej i lokalizacji platformy
+pub fn resolved_tagui() -> Option<ResolvedTagui> {
+    let managed = TAGUI_EXECUTABLE.read().unwrap().clone();
+    tagui_path::resolve(tagui_path::configured_path().as_deref(), managed.as_deref())
+}
+
+/// Domyślnie `tagui` z PATH, gdy nic nie zostało znalezione
+pub fn tagui_executable() -> PathBuf {
+    resolved_tagui()
+        .map(|resolved| resolved.path)
+        .unwrap_or_else(|| PathBuf::from("tagui"))
+}
+
+pub async fn check_tagui_installed() -> bool {
+    let Some(resolved) = resolved_tagui() else {
+        return false;
+    };
+    // Sprawdź czy wybrane TagUI odpowiada; gdy nie da się go uruchomić, wystarczy że plik istnieje
+    match Command::new(&resolved.path).arg("--version").output() {
+        Ok(output) => output.status.success(),
+        Err(_) => resolved.path.exists(),
+    }
+}
+
+/// Sparsowana komenda DSL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DslCommand {
+    pub line: usize,
+    pub name: String,
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// Adnotacja `retry N [interval S]`: do N prób, odstęp S sekund podwajany po każdej
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub interval_secs: f64,
+}
+
+impl RetryPolicy {
+    /// Pause before attempt `attempt` (2-based; the first attempt runs immediately)
+    pub fn delay_before(&self, attempt: u32) -> f64 {
+        self.interval_secs * 2f64.powi(attempt.saturating_sub(2) as i32)
+    }
+}
+
+impl DslCommand {
+    /// Selektor elementu, na którym operuje komenda (jeśli dotyczy)
+    pub fn selector(&self) -> Option<&str> {
+        match self.name.as_str() {
+            "click" | "hover" | "type" | "upload" | "select" | "check" | "uncheck" | "waitfor" => self.args.first().map(|s| s.as_str()),
+            "scroll" => self.args.first().map(|s| s.as_str()).filter(|arg| !SCROLL_DIRECTIONS.contains(arg)),
+            "if" | "for" => self.args.get(1).map(|s| s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Limit `waitfor <selektor> timeout N` w sekundach
+    pub fn waitfor_timeout(&self) -> f64 {
+        self.args
+            .get(2)
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(DEFAULT_WAITFOR_TIMEOUT_SECS as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DslParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for DslParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+/// Splits a DSL line into words, honouring double quotes and `\"` / `\\` escapes
+pub fn tokenize_dsl_line(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    let mut chars = line.chars();
     
-    for line in script.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with("//") {
-            continue;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_quotes => match chars.next() {
+                Some(escaped) => current.push(escaped),
+                None => return Err("Dangling escape character".to_string()),
+            },
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
         }
+    }
+    
+    if in_quotes {
+        return Err("Unterminated quoted string".to_string());
+    }
+    if has_token {
+        tokens.push(current);
+    }
+    
+    Ok(tokens)
+}
+
+/// Parsuje cały skrypt DSL i sprawdza składnię każdej komendy oraz zagnieżdżenie bloków
+pub fn parse_dsl_script(script: &str) -> Result<Vec<DslCommand>, DslParseError> {
+    let mut commands = Vec::new();
+    // Otwarte bloki: (linia, słowo kluczowe, selektor pętli `for each`)
+    let mut open_blocks: Vec<(usize, String, Option<String>)> = Vec::new();
+    
+    for (line_number, line) in script_commands(script) {
+        let error = |message: String| DslParseError { line: line_number, message };
         
-        let parts: Vec<&str> = line.split_whitespace().collect();
+        let mut parts = tokenize_dsl_line(&line).map_err(error)?;
         if parts.is_empty() {
             continue;
         }
         
-        let command = parts[0];
-        if !valid_commands.contains(&command) {
-            return Err(format!("Invalid DSL command: {}", command));
+        let command = parts.remove(0);
+        if !DSL_COMMANDS.contains(&command.as_str()) && !DSL_BLOCK_KEYWORDS.contains(&command.as_str()) {
+            return Err(error(format!("Invalid DSL command: {}", command)));
+        }
+        let retry = take_retry_annotation(&mut parts).map_err(error)?;
+        
+        if parts.iter().any(|arg| arg.contains(FOR_EACH_ITEM)) {
+            // Elementy listy są indeksowane przez XPath, np. (//li)[n]
+            match open_blocks.iter().rev().find_map(|(_, _, selector)| selector.as_ref()) {
+                None => {
+                    return Err(error(format!("'{}' can only be used inside a 'for each' block", FOR_EACH_ITEM)));
+                }
+                Some(selector) if !is_xpath(selector) => {
+                    return Err(error(format!("'{}' requires the 'for each' selector to be an XPath expression", FOR_EACH_ITEM)));
+                }
+                Some(_) => {}
+            }
         }
         
         // Sprawdź poprawność składni dla każdej komendy
-        match command {
-            "click" | "hover" => {
-                if parts.len() != 2 {
-                    return Err(format!("Command '{}' requires exactly one argument", command));
+        match command.as_str() {
+            "if" => {
+                if parts.len() != 2 || parts[0] != "present" {
+                    return Err(error("Expected 'if present <selector>'".to_string()));
+                }
+                open_blocks.push((line_number, command.clone(), None));
+            }
+            "repeat" => {
+                if parts.len() != 1 || !parts[0].parse::<u32>().map(|n| n > 0).unwrap_or(false) {
+                    return Err(error("Expected 'repeat <count>' with a positive whole number".to_string()));
+                }
+                open_blocks.push((line_number, command.clone(), None));
+            }
+            "frame" => {
+                if parts.len() != 1 || frame_identifier(&parts[0]).is_none() {
+                    return Err(error("Expected 'frame <name>' or 'frame #<id>' of an iframe".to_string()));
+                }
+                if open_blocks.iter().any(|(_, keyword, _)| keyword == "frame") {
+                    return Err(error("'frame' blocks cannot be nested".to_string()));
+                }
+                open_blocks.push((line_number, command.clone(), None));
+            }
+            "for" => {
+                if parts.len() != 2 || parts[0] != "each" {
+                    return Err(error("Expected 'for each <selector>'".to_string()));
+                }
+                if parts[1].contains(FOR_EACH_ITEM) {
+                    return Err(error(format!("'{}' cannot be used in a 'for each' selector", FOR_EACH_ITEM)));
+                }
+                open_blocks.push((line_number, command.clone(), Some(parts[1].clone())));
+            }
+            "end" => {
+                if !parts.is_empty() {
+                    return Err(error("Command 'end' takes no arguments".to_string()));
+                }
+                if open_blocks.pop().is_none() {
+                    return Err(error("'end' without a matching block".to_string()));
                 }
             }
+            "click" | "hover" | "check" | "uncheck" => {
+                if parts.len() != 1 {
+                    return Err(error(format!("Command '{}' requires exactly one argument", command)));
+                }
+            }
+            "select" if parts.len() != 2 => {
+                return Err(error("Command 'select' requires a selector and an option".to_string()));
+            }
+            "press" => {
+                if parts.len() != 1 {
+                    return Err(error("Command 'press' requires exactly one key".to_string()));
+                }
+                if tagui_key(&parts[0]).is_none() {
+                    return Err(error(format!("Unsupported key '{}'", parts[0])));
+                }
+            }
+            "scroll" if !is_valid_scroll(&parts) => {
+                return Err(error("Expected 'scroll <selector>', 'scroll up|down [pixels]' or 'scroll top|bottom'".to_string()));
+            }
             "type" | "upload" => {
-                if parts.len() < 3 {
-                    return Err(format!("Command '{}' requires at least two arguments", command));
+                if parts.len() < 2 {
+                    return Err(error(format!("Command '{}' requires at least two arguments", command)));
                 }
             }
             "wait" => {
-                if parts.len() != 2 {
-                    return Err(format!("Command 'wait' requires exactly one argument"));
+                if parts.len() != 1 {
+                    return Err(error(format!("Command 'wait' requires exactly one argument")));
                 }
                 // Sprawdź czy argument jest liczbą
-                if parts[1].parse::<f64>().is_err() {
-                    return Err(format!("Wait time must be a number"));
+                if parts[0].parse::<f64>().is_err() {
+                    return Err(error(format!("Wait time must be a number")));
+                }
+            }
+            "waitfor" => {
+                let valid = match parts.len() {
+                    1 => true,
+                    3 => parts[1] == "timeout" && parts[2].parse::<f64>().map(|secs| secs > 0.0).unwrap_or(false),
+                    _ => false,
+                };
+                if !valid {
+                    return Err(error("Expected 'waitfor <selector>' or 'waitfor <selector> timeout <seconds>' with a positive timeout".to_string()));
                 }
             }
             _ => {}
         }
+        
+        let parsed = DslCommand { line: line_number, name: command, args: parts, retry };
+        if parsed.retry.is_some() && parsed.selector().is_none() {
+            return Err(error(format!("Command '{}' does not target an element and cannot be retried", parsed.name)));
+        }
+        commands.push(parsed);
+    }
+    
+    if let Some((line, keyword, _)) = open_blocks.pop() {
+        return Err(DslParseError { line, message: format!("Block '{}' is missing 'end'", keyword) });
+    }
+    
+    Ok(commands)
+}
+
+/// Identyfikator iframe dla kroku `frame` TagUI: nazwa ramki albo `#id` (TagUI przyjmuje oba bez `#`)
+pub(crate) fn frame_identifier(reference: &str) -> Option<&str> {
+    let identifier = reference.strip_prefix('#').unwrap_or(reference);
+    let valid = !identifier.is_empty()
+        && identifier.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':'));
+    valid.then_some(identifier)
+}
+
+/// Usuwa z końca argumentów `retry N [interval S]` i zwraca politykę ponawiania
+fn take_retry_annotation(parts: &mut Vec<String>) -> Result<Option<RetryPolicy>, String> {
+    let (retry_at, interval) = match parts.len() {
+        len if len >= 4 && parts[len - 4] == "retry" && parts[len - 2] == "interval" => (len - 4, Some(&parts[len - 1])),
+        len if len >= 2 && parts[len - 2] == "retry" => (len - 2, None),
+        _ => return Ok(None),
+    };
+    
+    let attempts = parts[retry_at + 1]
+        .parse::<u32>()
+        .ok()
+        .filter(|attempts| (1..=MAX_RETRY_ATTEMPTS).contains(attempts))
+        .ok_or_else(|| format!("Retry count must be a whole number between 1 and {}", MAX_RETRY_ATTEMPTS))?;
+    let interval_secs = match interval {
+        Some(interval) => interval
+            .parse::<f64>()
+            .ok()
+            .filter(|secs| *secs > 0.0)
+            .ok_or_else(|| "Retry interval must be a positive number of seconds".to_string())?,
+        None => 1.0,
+    };
+    
+    parts.truncate(retry_at);
+    Ok(Some(RetryPolicy { attempts, interval_secs }))
+}
+
+/// Linia komendy bez końcowej adnotacji `retry`
+fn without_retry_annotation<'a>(command: &DslCommand, line: &'a str) -> &'a str {
+    match (&command.retry, line.rfind(" retry ")) {
+        (Some(_), Some(position)) => line[..position].trim_end(),
+        _ => line,
+    }
+}
+
+fn is_valid_scroll(args: &[String]) -> bool {
+    match args.first().map(|s| s.as_str()) {
+        Some("up") | Some("down") => args.len() == 1 || (args.len() == 2 && args[1].parse::<u32>().is_ok()),
+        Some(_) => args.len() == 1,
+        None => false,
+    }
+}
+
+/// Etykieta kroku w statystykach wydajności: nazwa komendy i selektor; tekst z `type` to dane osobowe
+fn slow_step_label(command: &DslCommand) -> String {
+    match command.selector() {
+        Some(selector) => format!("{} {}", command.name, selector),
+        None => command.name.clone(),
+    }
+}
+
+/// Krok TagUI, który TagUI wypisze przy wykonaniu komendy (poza pętlą `for each`)
+pub(crate) fn command_echo(command: &DslCommand, line: &str) -> String {
+    translate_command(command, line, None)
+}
+
+/// Tłumaczy pojedynczą komendę na krok TagUI; podstawowe komendy przechodzą bez zmian
+fn translate_command(command: &DslCommand, line: &str, item: Option<&str>) -> String {
+    let arg = |index: usize| -> String {
+        let value = command.args.get(index).cloned().unwrap_or_default();
+        match item {
+            Some(item) => value.replace(FOR_EACH_ITEM, item),
+            None => value,
+        }
+    };
+    
+    match command.name.as_str() {
+        "select" => format!("select {} as {}", arg(0), arg(1)),
+        "check" => format!("dom var el = {}; if (el && !el.checked) el.click()", element_lookup(&arg(0))),
+        "uncheck" => format!("dom var el = {}; if (el && el.checked) el.click()", element_lookup(&arg(0))),
+        "press" => format!("keyboard {}", tagui_key(&arg(0)).unwrap_or_default()),
+        // Krok z elementem czeka na niego do bieżącego `timeout` TagUI i kończy przebieg błędem, gdy się nie pojawi
+        "waitfor" => format!("read {} to {}", arg(0), WAITFOR_VARIABLE),
+        "scroll" => {
+            let pixels: i64 = command.args.get(1).and_then(|p| p.parse().ok()).unwrap_or(500);
+            match arg(0).as_str() {
+                "down" => format!("dom window.scrollBy(0, {})", pixels),
+                "up" => format!("dom window.scrollBy(0, -{})", pixels),
+                "top" => "dom window.scrollTo(0, 0)".to_string(),
+                "bottom" => "dom window.scrollTo(0, document.body.scrollHeight)".to_string(),
+                selector => format!("dom {}.scrollIntoView({{block: \"center\"}})", element_lookup(selector)),
+            }
+        }
+        _ => {
+            let line = without_retry_annotation(command, line);
+            // Linia jest już w cudzysłowach, więc XPath elementu musi być w niej zescape'owany
+            match item {
+                Some(item) => line.replace(FOR_EACH_ITEM, &escape_for_dsl(item)),
+                None => line.to_string(),
+            }
+        }
+    }
+}
+
+/// Wyrażenie JS zwracające element dla selektora CSS lub XPath
+fn element_lookup(selector: &str) -> String {
+    if is_xpath(selector) {
+        format!(
+            "document.evaluate(\"{}\", document, null, XPathResult.FIRST_ORDERED_NODE_TYPE, null).singleNodeValue",
+            escape_for_dsl(selector)
+        )
+    } else {
+        format!("document.querySelector(\"{}\")", escape_for_dsl(selector))
+    }
+}
+
+/// Maps a key name such as `enter` or `ctrl+a` onto TagUI's `[key]` notation
+fn tagui_key(key: &str) -> Option<String> {
+    const NAMED_KEYS: &[&str] = &[
+        "enter", "tab", "esc", "backspace", "delete", "insert", "space", "up", "down", "left", "right",
+        "pageup", "pagedown", "home", "end", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10", "f11", "f12",
+    ];
+    const MODIFIERS: &[&str] = &["ctrl", "shift", "alt", "cmd", "win"];
+    
+    let lowered = key.trim().to_lowercase();
+    let mut parts: Vec<&str> = lowered.split('+').map(|part| part.trim()).collect();
+    let main = parts.pop()?;
+    
+    let mut translated = String::new();
+    for modifier in parts {
+        if !MODIFIERS.contains(&modifier) {
+            return None;
+        }
+        translated.push_str(&format!("[{}]", modifier));
+    }
+    
+    let main = if main == "escape" { "esc" } else { main };
+    if NAMED_KEYS.contains(&main) || MODIFIERS.contains(&main) {
+        translated.push_str(&format!("[{}]", main));
+    } else if main.chars().count() == 1 {
+        translated.push_str(main);
+    } else {
+        return None;
     }
     
-    Ok(())
+    Some(translated)
+}
+
+pub(crate) fn is_xpath(selector: &str) -> bool {
+    selector.starts_with('/') || selector.starts_with('(')
+}
+
+/// Tworzy katalog zrzutów przebiegu; TagUI działa w katalogu tymczasowym, więc ścieżka musi być absolutna
+fn prepare_screenshot_dir(root: &Path, run_id: &str) -> Option<PathBuf> {
+    let dir = root.join(run_id);
+    match fs::create_dir_all(&dir).and_then(|_| fs::canonicalize(&dir)) {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            warn!(run_id = %run_id, "{}, running without screenshots", storage::describe_io_error("Failed to create screenshot directory", &e));
+            None
+        }
+    }
+}
+
+/// Compiles a validated DSL script into TagUI flow syntax.
+/// Plain commands pass through unchanged; blocks become `if`/`for` with `{ }` bodies.
+pub fn compile_dsl_script(script: &str) -> Result<String, DslParseError> {
+    compile_dsl_script_with_screenshots(script, None)
+}
+
+/// Jak `compile_dsl_script`, ale po każdej komendzie dodaje `snap page` do `screenshot_dir`.
+/// Kroki wewnątrz pętli nadpisują zrzut z poprzedniej iteracji.
+pub fn compile_dsl_script_with_screenshots(script: &str, screenshot_dir: Option<&Path>) -> Result<String, DslParseError> {
+    compile_dsl_script_with(script, screenshot_dir, None, None, &[], &mut Pacer::new(PacingProfile::Normal))
+}
+
+/// Z `debug_dir` przed każdą komendą wstawiany jest breakpoint (`debugger::breakpoint_steps`),
+/// a po niej pauza trybu krokowego (`debugger::pause_steps`). `pacer` dodaje przerwy między krokami
+/// i rozbija `type` na pojedyncze znaki (profil `human`) - poza liniami z wartością któregoś z `secrets`,
+/// bo TagUI wypisuje każdy krok, a maskowanie łapie tylko całe wartości. Komendy z `secure` (katalog
+/// przebiegu i pola) zastępuje zgłoszenie dla backendu (`secure_input::handoff_steps`).
+pub fn compile_dsl_script_with(
+    script: &str,
+    screenshot_dir: Option<&Path>,
+    debug_dir: Option<&Path>,
+    secure: Option<(&Path, &[SecureField])>,
+    secrets: &[SecretString],
+    pacer: &mut Pacer,
+) -> Result<String, DslParseError> {
+    let commands = parse_dsl_script(script)?;
+    let mut output = String::new();
+    // Selektory zagnieżdżonych pętli `for each` (None dla `repeat`/`if`)
+    let mut blocks: Vec<Option<String>> = Vec::new();
+    // Zmienne liczników pętli dla każdego otwartego bloku (None dla `if`/`frame`)
+    let mut loop_variables: Vec<Option<String>> = Vec::new();
+    
+    for (command, (_, line)) in commands.iter().zip(script_commands(script)) {
+        let indent = "  ".repeat(blocks.len());
+        let loop_variable = format!("codialog_i{}", blocks.len() + 1);
+        
+        match command.name.as_str() {
+            "if" => {
+                output.push_str(&format!("{}if present(\"{}\")\n{}{{\n", indent, escape_for_dsl(&command.args[1]), indent));
+                blocks.push(None);
+                loop_variables.push(None);
+            }
+            "repeat" => {
+                output.push_str(&format!("{}for {} from 1 to {}\n{}{{\n", indent, loop_variable, command.args[0], indent));
+                blocks.push(None);
+                loop_variables.push(Some(loop_variable));
+            }
+            "frame" => {
+                let identifier = frame_identifier(&command.args[0]).unwrap_or_default();
+                output.push_str(&format!("{}frame {}\n{}{{\n", indent, identifier, indent));
+                blocks.push(None);
+                loop_variables.push(None);
+            }
+            "for" => {
+                let selector = &command.args[1];
+                output.push_str(&format!(
+                    "{}for {} from 1 to count(\"{}\")\n{}{{\n",
+                    indent, loop_variable, escape_for_dsl(selector), indent
+                ));
+                blocks.push(Some(format!("({})[`{}`]", selector, loop_variable)));
+                loop_variables.push(Some(loop_variable));
+            }
+            "end" => {
+                blocks.pop();
+                loop_variables.pop();
+                output.push_str(&format!("{}}}\n", "  ".repeat(blocks.len())));
+            }
+            _ => {
+                let item = blocks.iter().rev().flatten().next().map(|item| item.as_str());
+                let counters: Vec<String> = loop_variables.iter().flatten().cloned().collect();
+                if let Some(dir) = debug_dir {
+                    for step in debugger::breakpoint_steps(dir, command.line, &counters) {
+                        output.push_str(&format!("{}{}\n", indent, step));
+                    }
+                }
+                if command.name == "waitfor" {
+                    output.push_str(&format!("{}timeout {}\n", indent, command.waitfor_timeout()));
+                }
+                let handoff = secure.filter(|(_, fields)| fields.iter().any(|field| field.line == command.line));
+                let command_steps = |indent: &str, pacer: &mut Pacer| {
+                    let mut steps = String::new();
+                    if let Some((dir, _)) = handoff {
+                        for step in secure_input::handoff_steps(dir, command.line) {
+                            steps.push_str(&format!("{}{}\n", indent, step));
+                        }
+                    } else if command.name == "type" && pacer.types_by_keystroke() && contains_secret(&line, secrets) {
+                        // Sekret wpisywany w jednym kroku, z przerwą jak przy pisaniu przed nim
+                        steps.push_str(&format!("{}wait {:.2}\n", indent, pacer.keystroke_delay().as_secs_f64()));
+                        steps.push_str(&format!("{}{}\n", indent, translate_command(command, &line, item)));
+                    } else if command.name == "type" && pacer.types_by_keystroke() {
+                        let with_item = |value: &str| match item {
+                            Some(item) => value.replace(FOR_EACH_ITEM, item),
+                            None => value.to_string(),
+                        };
+                        let selector = escape_for_dsl(&with_item(&command.args[0]));
+                        for key in pacing::keystrokes(&with_item(&command.args[1..].join(" "))) {
+                            steps.push_str(&format!("{}type \"{}\" \"{}\"\n", indent, selector, escape_for_dsl(&key)));
+                            steps.push_str(&format!("{}wait {:.2}\n", indent, pacer.keystroke_delay().as_secs_f64()));
+                        }
+                    } else {
+                        steps.push_str(&format!("{}{}\n", indent, translate_command(command, &line, item)));
+                    }
+                    steps
+                };
+                // Ponawianie: TagUI przerywa skrypt na błędzie kroku, więc każda próba poza ostatnią
+                // wykonuje komendę tylko przy obecnym elemencie, a między próbami czeka z backoffem.
+                // Ostatnia próba jest bezwarunkowa i zgłasza zwykły błąd TagUI.
+                if let (Some(retry), Some(selector)) = (&command.retry, command.selector()) {
+                    let selector = match item {
+                        Some(item) => selector.replace(FOR_EACH_ITEM, item),
+                        None => selector.to_string(),
+                    };
+                    let done = format!("codialog_retry{}", command.line);
+                    let inner = format!("{}  ", indent);
+                    output.push_str(&format!("{}{} = false\n", indent, done));
+                    for attempt in 1..=retry.attempts {
+                        if attempt > 1 {
+                            output.push_str(&format!("{}if !{}\n{}{{\n{}wait {}\n{}}}\n", indent, done, indent, inner, retry.delay_before(attempt), indent));
+                        }
+                        if attempt < retry.attempts {
+                            output.push_str(&format!("{}if !{} && present(\"{}\")\n{}{{\n", indent, done, escape_for_dsl(&selector), indent));
+                            output.push_str(&command_steps(&inner, pacer));
+                            output.push_str(&format!("{}{} = true\n{}}}\n", inner, done, indent));
+                        } else {
+                            output.push_str(&format!("{}if !{}\n{}{{\n", indent, done, indent));
+                            output.push_str(&command_steps(&inner, pacer));
+                            output.push_str(&format!("{}}}\n", indent));
+                        }
+                    }
+                } else {
+                    output.push_str(&command_steps(&indent, pacer));
+                }
+                if command.name == "waitfor" {
+                    output.push_str(&format!("{}timeout {}\n", indent, DEFAULT_WAITFOR_TIMEOUT_SECS));
+                }
+                if let Some(dir) = screenshot_dir {
+                    output.push_str(&format!("{}snap page to {}\n", indent, dir.join(screenshot_file_name(command.line)).display()));
+                }
+                let step_delay = pacer.step_delay();
+                if !step_delay.is_zero() {
+                    output.push_str(&format!("{}wait {:.2}\n", indent, step_delay.as_secs_f64()));
+                }
+                if let Some(dir) = debug_dir {
+                    for step in debugger::pause_steps(dir, command.line, &counters) {
+                        output.push_str(&format!("{}{}\n", indent, step));
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok(output)
+}
+
+pub fn screenshot_file_name(line: usize) -> String {
+    format!("step-{:03}.png", line)
+}
+
+pub fn validate_dsl_script(script: &str) -> Result<(), String> {
+    parse_dsl_script(script).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Wstawia wartości w miejsce `{{nazwa}}`; brakująca zmienna to błąd z numerem linii
+pub fn interpolate_variables(script: &str, variables: &HashMap<String, String>) -> Result<String, DslParseError> {
+    interpolate_variables_except(script, variables, &HashSet::new())
+}
+
+/// Jak `interpolate_variables`, ale linie z `skip_lines` (od 1) zostają bez zmian
+pub fn interpolate_variables_except(script: &str, variables: &HashMap<String, String>, skip_lines: &HashSet<usize>) -> Result<String, DslParseError> {
+    let mut output = Vec::new();
+    
+    for (index, line) in script.lines().enumerate() {
+        if skip_lines.contains(&(index + 1)) {
+            output.push(line.to_string());
+            continue;
+        }
+        let error = |message: String| DslParseError { line: index + 1, message };
+        let mut rendered = String::new();
+        let mut rest = line;
+        
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                return Err(error("Unterminated '{{' placeholder".to_string()));
+            };
+            let name = rest[start + 2..start + 2 + length].trim();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-') {
+                return Err(error(format!("Invalid variable name '{}'", name)));
+            }
+            let value = variables
+                .get(name)
+                .ok_or_else(|| error(format!("Undefined variable '{}'", name)))?;
+            if value.contains('\n') || value.contains('\r') {
+                return Err(error(format!("Variable '{}' contains a line break", name)));
+            }
+            
+            rendered.push_str(&rest[..start]);
+            rendered.push_str(&escape_for_dsl(value));
+            rest = &rest[start + 2 + length + 2..];
+        }
+        rendered.push_str(rest);
+        output.push(rendered);
+    }
+    
+    Ok(output.join("\n"))
 }
 
 pub fn escape_for_dsl(input: &str) -> String {
@@ -176,9 +1674,332 @@ mod tests {
         assert!(validate_dsl_script(invalid_script).is_err());
     }
     
+    #[test]
+    fn test_parse_dsl_script_handles_quotes() {
+        let commands = parse_dsl_script("type \"#name\" \"Jan \\\"JK\\\" Kowalski\"\nwait 2").unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].args, vec!["#name", "Jan \"JK\" Kowalski"]);
+        assert_eq!(commands[0].selector(), Some("#name"));
+        
+        let error = parse_dsl_script("click \"#ok\"\nclick \"#broken").unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+    
+    #[test]
+    fn test_build_step_results_marks_failed_line() {
+        let script = "// login\nclick \"#login\"\ntype \"#user\" \"john\"\nclick \"#submit\"";
+        let stdout = "click \"#login\"\ntype \"#user\" \"john\"\nERROR - cannot find #user\n";
+        
+        let (steps, failed_line) = build_step_results(script, stdout, false, &HashMap::new());
+        assert_eq!(failed_line, Some(3));
+        assert_eq!(steps[0].status, StepStatus::Succeeded);
+        assert_eq!(steps[1].status, StepStatus::Failed);
+        assert_eq!(steps[2].status, StepStatus::Skipped);
+    }
+    
+    #[test]
+    fn test_cancel_unknown_run() {
+        let manager = RunManager::default();
+        assert!(!manager.cancel("missing"));
+        assert!(manager.status("missing").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_queued_runs_report_position() {
+        let manager = Arc::new(RunManager::new(1));
+        // Zajmij jedyny slot, aby kolejne przebiegi czekały w kolejce
+        let held = manager.slots.try_acquire().unwrap();
+        
+        let first = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.execute("click #a").await }
+        });
+        let second = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.execute("click #b").await }
+        });
+        while manager.pending.lock().unwrap().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        
+        let queued: Vec<String> = manager.pending.lock().unwrap().iter().cloned().collect();
+        assert_eq!(manager.status(&queued[0]).unwrap().queue_position, Some(1));
+        assert_eq!(manager.status(&queued[1]).unwrap().queue_position, Some(2));
+        assert_eq!(manager.queue_stats().queued, 2);
+        
+        assert!(manager.cancel(&queued[0]));
+        let (_, first_result) = first.await.unwrap();
+        assert_eq!(first_result.status, ExecutionStatus::Cancelled);
+        assert_eq!(manager.status(&queued[1]).unwrap().queue_position, Some(1));
+        
+        assert!(manager.cancel(&queued[1]));
+        let (_, second_result) = second.await.unwrap();
+        assert_eq!(second_result.status, ExecutionStatus::Cancelled);
+        drop(held);
+    }
+    
+    /// Podmienia ścieżkę TagUI na czas testu i przywraca poprzednią, także gdy test się wysypie
+    struct ManagedExecutableOverride {
+        previous: Option<PathBuf>,
+    }
+    
+    impl ManagedExecutableOverride {
+        fn set(path: PathBuf) -> Self {
+            let previous = TAGUI_EXECUTABLE.write().unwrap().replace(path);
+            Self { previous }
+        }
+    }
+    
+    impl Drop for ManagedExecutableOverride {
+        fn drop(&mut self) {
+            if let Ok(mut executable) = TAGUI_EXECUTABLE.write() {
+                *executable = self.previous.take();
+            }
+        }
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_watchdog_kills_hanging_run() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        // Atrapa TagUI, która zawiesza się razem z procesem potomnym trzymającym stdout
+        let dir = tempfile::tempdir().unwrap();
+        let fake_tagui = dir.path().join("tagui");
+        fs::write(&fake_tagui, "#!/bin/sh\necho started\nsleep 30 &\nsleep 30\n").unwrap();
+        fs::set_permissions(&fake_tagui, fs::Permissions::from_mode(0o755)).unwrap();
+        let _restore = ManagedExecutableOverride::set(fake_tagui);
+        
+        // Linie docierają na żywo, zanim proces się zakończy
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink: OutputSink = {
+            let lines = lines.clone();
+            Arc::new(move |stream, line| lines.lock().unwrap().push((stream, line.to_string())))
+        };
+        
+        let started = Instant::now();
+        let result = execute_script_cancellable(
+            "click \"#submit\"",
+            Arc::new(Notify::new()),
+            None,
+            None,
+            BrowserMode::Headless,
+            PacingProfile::Normal,
+            Some(Duration::from_millis(300)),
+            Some(sink),
+            &[],
+            &[],
+            secure_input::DEFAULT_TAGUI_DEBUG_PORT,
+        )
+        .await;
+        
+        assert_eq!(result.status, ExecutionStatus::TimedOut);
+        assert_eq!(*lines.lock().unwrap(), vec![(OutputStream::Stdout, "started".to_string())]);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(result.error.unwrap().contains("timed out"));
+    }
+    
+    #[test]
+    fn test_parse_control_blocks() {
+        let script = "if present \"#cookie-modal\"\nclick \"#accept\"\nend\nrepeat 2\nclick \"#more\"\nend";
+        assert!(validate_dsl_script(script).is_ok());
+        
+        let unclosed = parse_dsl_script("wait 1\nrepeat 3\nclick \"#next\"").unwrap_err();
+        assert_eq!(unclosed.line, 2);
+        assert!(unclosed.message.contains("missing 'end'"));
+        
+        assert_eq!(parse_dsl_script("end").unwrap_err().line, 1);
+        assert!(validate_dsl_script("repeat 0\nend").is_err());
+        assert!(validate_dsl_script("click \"@item\"").is_err());
+        assert!(validate_dsl_script("for each \".row\"\nclick \"@item\"\nend").is_err());
+    }
+    
+    #[test]
+    fn test_compile_control_blocks() {
+        let script = "if present \"#modal\"\nclick \"#close\"\nend\nfor each \"//li[@class='job']\"\nrepeat 2\nclick \"@item\"\nend\nend";
+        let compiled = compile_dsl_script(script).unwrap();
+        
+        assert_eq!(
+            compiled,
+            "if present(\"#modal\")\n{\n  click \"#close\"\n}\n\
+             for codialog_i1 from 1 to count(\"//li[@class='job']\")\n{\n\
+             \x20 for codialog_i2 from 1 to 2\n  {\n\
+             \x20   click \"(//li[@class='job'])[`codialog_i1`]\"\n  }\n}\n"
+        );
+    }
+    
+    #[test]
+    fn test_for_each_item_with_quotes_stays_quoted() {
+        let script = "for each \"//li[@data-kind=\\\"job\\\"]\"\nclick \"@item//a\"\nend";
+        let compiled = compile_dsl_script(script).unwrap();
+
+        assert!(compiled.contains("  click \"(//li[@data-kind=\\\"job\\\"])[`codialog_i1`]//a\"\n"), "{}", compiled);
+    }
+    
+    #[test]
+    fn test_slow_step_label_has_no_typed_values() {
+        let commands = parse_dsl_script("type \"#email\" \"jan@example.com\"\nselect \"#country\" \"Polska\"\npress enter").unwrap();
+        let labels: Vec<String> = commands.iter().map(slow_step_label).collect();
+        assert_eq!(labels, vec!["type #email", "select #country", "press"]);
+    }
+    
+    #[test]
+    fn test_frame_blocks() {
+        let script = "click \"#apply\"\nframe \"#grnhse_iframe\"\ntype \"#first_name\" \"Jan\"\nend";
+        assert_eq!(
+            compile_dsl_script(script).unwrap(),
+            "click \"#apply\"\nframe grnhse_iframe\n{\n  type \"#first_name\" \"Jan\"\n}\n"
+        );
+        
+        assert!(validate_dsl_script("frame \"workday\"\nclick \"#next\"\nend").is_ok());
+        assert!(validate_dsl_script("frame \"iframe.embed\"\nend").is_err());
+        assert!(validate_dsl_script("frame \"a\"\nframe \"b\"\nend\nend").is_err());
+        assert!(validate_dsl_script("frame \"a\"\nclick \"#x\"").is_err());
+    }
+
+    #[test]
+    fn test_retry_annotation() {
+        let script = "click \"#submit\" retry 3 interval 2\ntype \"#q\" \"retry\"\nwait 1";
+        let commands = parse_dsl_script(script).unwrap();
+        assert_eq!(commands[0].args, vec!["#submit"]);
+        assert_eq!(commands[0].retry, Some(RetryPolicy { attempts: 3, interval_secs: 2.0 }));
+        assert_eq!(commands[1].retry, None);
+        
+        // Komenda powtórzona w każdej próbie; wykonuje się tylko pierwsza próba z obecnym elementem
+        let compiled = compile_dsl_script(script).unwrap();
+        let lines: Vec<&str> = compiled.lines().collect();
+        assert_eq!(lines[..6], ["codialog_retry1 = false", "if !codialog_retry1 && present(\"#submit\")", "{", "  click \"#submit\"", "  codialog_retry1 = true", "}"]);
+        assert_eq!(lines[8], "  wait 2");
+        assert_eq!(lines[17], "  wait 4");
+        assert_eq!(lines[19..23], ["if !codialog_retry1", "{", "  click \"#submit\"", "}"]);
+        assert_eq!(lines.iter().filter(|line| line.trim() == "click \"#submit\"").count(), 3);
+        assert_eq!(lines[23], "type \"#q\" \"retry\"");
+        
+        assert_eq!(parse_dsl_script("hover \"#menu\" retry 2").unwrap()[0].retry.as_ref().unwrap().interval_secs, 1.0);
+        assert!(validate_dsl_script("click \"#a\" retry 0").is_err());
+        assert!(validate_dsl_script("click \"#a\" retry 3 interval -1").is_err());
+        assert!(validate_dsl_script("wait 2 retry 3").is_err());
+        assert!(validate_dsl_script("click \"#a\" retry 11").is_err());
+    }
+    
+    #[test]
+    fn test_waitfor_command() {
+        let compiled = compile_dsl_script("click \"#login\"\nwaitfor \"#dashboard\" timeout 30\nwaitfor \"#menu\"").unwrap();
+        let lines: Vec<&str> = compiled.lines().collect();
+        assert_eq!(lines[1..4], ["timeout 30", "read #dashboard to codialog_waitfor", "timeout 10"]);
+        assert_eq!(lines[4], "timeout 10");
+        assert!(is_command_echo(lines[2]));
+        
+        assert!(validate_dsl_script("waitfor \"#a\" timeout 0").is_err());
+        assert!(validate_dsl_script("waitfor \"#a\" 5").is_err());
+        assert!(validate_dsl_script("waitfor").is_err());
+    }
+
+    #[test]
+    fn test_compile_with_screenshots() {
+        let script = "click \"#next\"\nrepeat 2\nwait 1\nend";
+        let compiled = compile_dsl_script_with_screenshots(script, Some(Path::new("/tmp/run-1"))).unwrap();
+        let lines: Vec<&str> = compiled.lines().collect();
+        assert_eq!(lines[0], "click \"#next\"");
+        assert_eq!(lines[1], "snap page to /tmp/run-1/step-001.png");
+        assert_eq!(lines[4], "  wait 1");
+        assert_eq!(lines[5], "  snap page to /tmp/run-1/step-003.png");
+        assert_eq!(compiled.matches("snap page").count(), 2);
+        
+        assert!(!compile_dsl_script(script).unwrap().contains("snap"));
+    }
+    
+    #[test]
+    fn test_compile_with_human_pacing() {
+        let script = "type \"#q\" \"ab[enter]\"\nclick \"#go\"";
+        let compiled = compile_dsl_script_with(script, None, None, None, &[], &mut Pacer::with_seed(PacingProfile::Human, 7)).unwrap();
+        let lines: Vec<&str> = compiled.lines().collect();
+        assert_eq!(lines[0], "type \"#q\" \"a\"");
+        assert_eq!(lines[2], "type \"#q\" \"b\"");
+        assert_eq!(lines[4], "type \"#q\" \"[enter]\"");
+        assert!(lines[1].starts_with("wait 0."));
+        // Po każdym kroku: trzy znaki i dwie przerwy między krokami
+        assert_eq!(compiled.matches("wait ").count(), 5);
+        assert_eq!(lines[7], "click \"#go\"");
+        
+        let fast = compile_dsl_script_with(script, None, None, None, &[], &mut Pacer::with_seed(PacingProfile::Fast, 7)).unwrap();
+        assert_eq!(fast, compile_dsl_script(script).unwrap());
+        
+        // Linia z sekretem (także częściowo) idzie jednym krokiem
+        let secrets = [SecretString::from("4821")];
+        let script = "type \"#otp\" \"code 4821\"\ntype \"#name\" \"Jo\"";
+        let compiled = compile_dsl_script_with(script, None, None, None, &secrets, &mut Pacer::with_seed(PacingProfile::Human, 7)).unwrap();
+        assert_eq!(compiled.matches("#otp").count(), 1);
+        assert!(compiled.contains("code 4821"));
+        assert_eq!(compiled.matches("#name").count(), 2);
+    }
+    
+    #[test]
+    fn test_form_control_commands() {
+        let script = "select \"#country\" \"Poland\"\ncheck \"#terms\"\nuncheck \"//input[@name='newsletter']\"\npress \"ctrl+a\"\npress \"enter\"\nscroll down 300\nscroll \"#footer\"";
+        let commands = parse_dsl_script(script).unwrap();
+        assert_eq!(commands[0].selector(), Some("#country"));
+        assert_eq!(commands[5].selector(), None);
+        assert_eq!(commands[6].selector(), Some("#footer"));
+        
+        let compiled = compile_dsl_script(script).unwrap();
+        let lines: Vec<&str> = compiled.lines().collect();
+        assert_eq!(lines[0], "select #country as Poland");
+        assert_eq!(lines[1], "dom var el = document.querySelector(\"#terms\"); if (el && !el.checked) el.click()");
+        assert!(lines[2].starts_with("dom var el = document.evaluate(\"//input[@name='newsletter']\""));
+        assert!(lines[2].ends_with("if (el && el.checked) el.click()"));
+        assert_eq!(lines[3], "keyboard [ctrl]a");
+        assert_eq!(lines[4], "keyboard [enter]");
+        assert_eq!(lines[5], "dom window.scrollBy(0, 300)");
+        assert_eq!(lines[6], "dom document.querySelector(\"#footer\").scrollIntoView({block: \"center\"})");
+        
+        assert!(validate_dsl_script("press \"hyper+q\"").is_err());
+        assert!(validate_dsl_script("select \"#country\"").is_err());
+        assert!(validate_dsl_script("scroll down fast").is_err());
+    }
+    
+    #[test]
+    fn test_interpolate_variables() {
+        let mut variables = HashMap::new();
+        variables.insert("email".to_string(), "jan@example.com".to_string());
+        variables.insert("motto".to_string(), "say \"hi\"".to_string());
+        
+        let script = "click \"#login\"\ntype \"#email\" \"{{ email }}\"\ntype \"#motto\" \"{{motto}}\"";
+        let rendered = interpolate_variables(script, &variables).unwrap();
+        assert_eq!(rendered, "click \"#login\"\ntype \"#email\" \"jan@example.com\"\ntype \"#motto\" \"say \\\"hi\\\"\"");
+        assert!(validate_dsl_script(&rendered).is_ok());
+        
+        let missing = interpolate_variables("wait 1\ntype \"#p\" \"{{password}}\"", &variables).unwrap_err();
+        assert_eq!(missing.line, 2);
+        assert!(interpolate_variables("type \"#a\" \"{{email\"", &variables).is_err());
+    }
+    
+    #[test]
+    fn test_mask_secrets() {
+        let mut result = ExecutionResult::early_failure(ExecutionStatus::Failed, "type failed: hunter2".to_string(), None, Instant::now());
+        result.stdout = "type #password as hunter2".to_string();
+        result.steps.push(StepResult { line: 1, command: "type \"#password\" \"hunter2\"".to_string(), status: StepStatus::Failed });
+        
+        result.mask_secrets(&[SecretString::from("hunter2")]);
+        assert!(!result.stdout.contains("hunter2"));
+        assert!(!result.steps[0].command.contains("hunter2"));
+        assert_eq!(result.error.as_deref(), Some("type failed: ********"));
+    }
+    
     #[test]
     fn test_escape_for_dsl() {
         assert_eq!(escape_for_dsl("test \"quoted\" text"), "test \\\"quoted\\\" text");
         assert_eq!(escape_for_dsl("normal text"), "normal text");
     }
+
+    #[test]
+    fn test_browser_mode_flags() {
+        assert_eq!(BrowserMode::parse(" Headless").map(|mode| mode.tagui_flag()), Some("headless"));
+        assert_eq!(BrowserMode::parse("headed"), Some(BrowserMode::Headed));
+        assert_eq!(BrowserMode::default().tagui_flag(), "chrome");
+        assert_eq!(BrowserMode::parse("safari"), None);
+
+        let mode: BrowserMode = serde_json::from_str("\"edge\"").unwrap();
+        assert_eq!(mode.tagui_flag(), "edge");
+    }
 }
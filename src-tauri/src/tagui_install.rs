@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context, anyhow, bail};
+use tracing::{info, warn, error};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use ring::digest;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncWriteExt;
+
+use crate::tagui;
+
+/// Domyślne źródło archiwów wydań TagUI; `{version}` zastępowane numerem wersji
+pub const DEFAULT_DOWNLOAD_URL: &str = "https://github.com/aisingapore/tagui/archive/refs/tags/v{version}.tar.gz";
+
+/// Plik w katalogu instalacji wskazujący aktywną wersję
+const ACTIVE_VERSION_FILE: &str = "current";
+
+/// Wydanie TagUI przypięte do wersji i sumy SHA-256 archiwum
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaguiRelease {
+    pub version: String,
+    pub sha256: String,
+}
+
+impl TaguiRelease {
+    pub fn new(version: &str, sha256: &str) -> Result<Self> {
+        let version = version.trim().trim_start_matches('v');
+        // Tylko `N(.N)*` - wersja jest nazwą katalogu w TAGUI_HOME, więc `..` nie może przejść
+        if !version.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())) {
+            bail!("Invalid TagUI version '{}'", version);
+        }
+        let sha256 = sha256.trim().to_lowercase();
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("TagUI checksum must be a 64-character SHA-256 hex digest");
+        }
+        Ok(Self { version: version.to_string(), sha256 })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallPhase {
+    Idle,
+    Downloading,
+    Verifying,
+    Extracting,
+    Installing,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallProgress {
+    pub phase: InstallPhase,
+    pub version: Option<String>,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Instaluje przypięte wydania TagUI do `home/<version>` i przełącza aktywną wersję.
+/// Older versions stay on disk, so downgrading to an installed one is just a switch.
+pub struct InstallManager {
+    home: PathBuf,
+    url_template: String,
+    progress: Mutex<InstallProgress>,
+    running: AtomicBool,
+}
+
+impl InstallManager {
+    pub fn new(home: impl Into<PathBuf>, url_template: impl Into<String>) -> Self {
+        Self {
+            home: home.into(),
+            url_template: url_template.into(),
+            progress: Mutex::new(InstallProgress {
+                phase: InstallPhase::Idle,
+                version: None,
+                downloaded_bytes: 0,
+                total_bytes: None,
+                error: None,
+                updated_at: Utc::now(),
+            }),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn progress(&self) -> InstallProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn executable_for(&self, version: &str) -> PathBuf {
+        self.home.join(version).join("src").join("tagui")
+    }
+
+    /// Wersje zainstalowane w katalogu domowym (z plikiem wykonywalnym TagUI)
+    pub fn installed_versions(&self) -> Vec<String> {
+        let mut versions: Vec<String> = std::fs::read_dir(&self.home)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+                    .filter(|name| !name.starts_with('.') && self.executable_for(name).exists())
+                    .collect()
+            })
+            .unwrap_or_default();
+        versions.sort();
+        versions
+    }
+
+    pub fn active_version(&self) -> Option<String> {
+        std::fs::read_to_string(self.home.join(ACTIVE_VERSION_FILE))
+            .ok()
+            .map(|version| version.trim().to_string())
+            .filter(|version| self.executable_for(version).exists())
+    }
+
+    /// Points the runner at the active installed version; false when nothing is installed
+    pub fn activate_installed(&self) -> bool {
+        match self.active_version() {
+            Some(version) => {
+                tagui::set_tagui_executable(self.executable_for(&version));
+                info!("Using TagUI {} from {}", version, self.home.display());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn switch_to(&self, version: &str) -> Result<()> {
+        std::fs::write(self.home.join(ACTIVE_VERSION_FILE), version)
+            .with_context(|| format!("Failed to mark TagUI {} as active", version))?;
+        tagui::set_tagui_executable(self.executable_for(version));
+        info!("TagUI {} is now active", version);
+        Ok(())
+    }
+
+    fn update(&self, change: impl FnOnce(&mut InstallProgress)) {
+        let mut progress = self.progress.lock().unwrap();
+        change(&mut progress);
+        progress.updated_at = Utc::now();
+    }
+
+    /// Instaluje (albo tylko aktywuje, jeśli już jest na dysku) wskazane wydanie
+    pub async fn install(&self, release: &TaguiRelease) -> Result<()> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            bail!("A TagUI installation is already in progress");
+        }
+        self.update(|progress| {
+            *progress = InstallProgress {
+                phase: InstallPhase::Downloading,
+                version: Some(release.version.clone()),
+                downloaded_bytes: 0,
+                total_bytes: None,
+                error: None,
+                updated_at: Utc::now(),
+            };
+        });
+
+        let result = self.install_release(release).await;
+        match &result {
+            Ok(()) => self.update(|progress| progress.phase = InstallPhase::Completed),
+            Err(e) => {
+                error!("TagUI {} installation failed: {:#}", release.version, e);
+                self.update(|progress| {
+                    progress.phase = InstallPhase::Failed;
+                    progress.error = Some(format!("{:#}", e));
+                });
+            }
+        }
+
+        self.running.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// Przy starcie: aktywuje zainstalowaną wersję i doinstalowuje przypiętą, jeśli się różni
+    pub async fn ensure_pinned(&self, pinned: Option<&TaguiRelease>) -> Result<()> {
+        let activated = self.activate_installed();
+        match pinned {
+            Some(release) if self.active_version().as_deref() == Some(release.version.as_str()) => Ok(()),
+            Some(release) => self.install(release).await,
+            None if activated => Ok(()),
+            None => {
+                warn!("No TagUI version pinned (TAGUI_VERSION/TAGUI_SHA256), falling back to tagui from PATH");
+                Ok(())
+            }
+        }
+    }
+
+    async fn install_release(&self, release: &TaguiRelease) -> Result<()> {
+        if self.installed_versions().contains(&release.version) {
+            info!("TagUI {} already installed, switching to it", release.version);
+            return self.switch_to(&release.version);
+        }
+
+        tokio::fs::create_dir_all(&self.home)
+            .await
+            .with_context(|| format!("Failed to create TagUI directory {}", self.home.display()))?;
+
+        let archive = self.home.join(format!(".{}.tar.gz", release.version));
+        let staging = self.home.join(format!(".{}.partial", release.version));
+        let result = async {
+            let checksum = self.download(release, &archive).await?;
+
+            self.update(|progress| progress.phase = InstallPhase::Verifying);
+            if checksum != release.sha256 {
+                bail!("Checksum mismatch for TagUI {}: expected {}, got {}", release.version, release.sha256, checksum);
+            }
+
+            self.update(|progress| progress.phase = InstallPhase::Extracting);
+            extract_archive(&archive, &staging).await?;
+
+            self.update(|progress| progress.phase = InstallPhase::Installing);
+            install_dependencies(&staging).await?;
+
+            let target = self.home.join(&release.version);
+            tokio::fs::rename(&staging, &target)
+                .await
+                .with_context(|| format!("Failed to move TagUI into {}", target.display()))?;
+            if !self.executable_for(&release.version).exists() {
+                bail!("TagUI archive for {} does not contain src/tagui", release.version);
+            }
+            self.switch_to(&release.version)
+        }
+        .await;
+
+        let _ = tokio::fs::remove_file(&archive).await;
+        if staging.exists() {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+        }
+        result
+    }
+
+    /// Pobiera archiwum strumieniowo, licząc SHA-256 i raportując postęp
+    async fn download(&self, release: &TaguiRelease, destination: &Path) -> Result<String> {
+        let url = self.url_template.replace("{version}", &release.version);
+        info!("Downloading TagUI {} from {}", release.version, url);
+
+        let response = reqwest::get(&url)
+            .await
+            .with_context(|| format!("Failed to download {}", url))?
+            .error_for_status()
+            .with_context(|| format!("Download of {} was rejected", url))?;
+        let total_bytes = response.content_length();
+        self.update(|progress| progress.total_bytes = total_bytes);
+
+        let mut file = tokio::fs::File::create(destination)
+            .await
+            .with_context(|| format!("Failed to create {}", destination.display()))?;
+        let mut hasher = digest::Context::new(&digest::SHA256);
+        let mut stream = response.bytes_stream();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("Download interrupted")?;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await.context("Failed to write TagUI archive")?;
+            self.update(|progress| progress.downloaded_bytes += chunk.len() as u64);
+        }
+        file.flush().await?;
+
+        Ok(hex(hasher.finish().as_ref()))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+async fn extract_archive(archive: &Path, destination: &Path) -> Result<()> {
+    if destination.exists() {
+        tokio::fs::remove_dir_all(destination).await?;
+    }
+    tokio::fs::create_dir_all(destination).await?;
+
+    let output = tokio::process::Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(destination)
+        .arg("--strip-components=1")
+        .output()
+        .await
+        .context("Failed to run tar")?;
+    if !output.status.success() {
+        bail!("Failed to extract TagUI archive: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+async fn install_dependencies(dir: &Path) -> Result<()> {
+    if !dir.join("package.json").exists() {
+        return Ok(());
+    }
+
+    let output = tokio::process::Command::new("npm")
+        .args(["install", "--production"])
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run npm install: {}", e))?;
+    if !output.status.success() {
+        warn!("npm install for TagUI reported errors: {}", String::from_utf8_lossy(&output.stderr).trim());
+        bail!("Failed to install TagUI npm dependencies");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_validation() {
+        let checksum = hex(digest::digest(&digest::SHA256, b"archive").as_ref());
+        assert_eq!(checksum.len(), 64);
+
+        let release = TaguiRelease::new("v6.114.0", &checksum.to_uppercase()).unwrap();
+        assert_eq!(release.version, "6.114.0");
+        assert_eq!(release.sha256, checksum);
+
+        assert!(TaguiRelease::new("../etc", &checksum).is_err());
+        for invalid in ["..", ".", "", "6..114", "6.114.", "6.114.0-beta"] {
+            assert!(TaguiRelease::new(invalid, &checksum).is_err(), "{invalid}");
+        }
+        assert_eq!(TaguiRelease::new("6", &checksum).unwrap().version, "6");
+        assert!(TaguiRelease::new("6.114.0", "abc").is_err());
+    }
+
+    #[test]
+    fn test_installed_and_active_versions() {
+        let home = tempfile::tempdir().unwrap();
+        let manager = InstallManager::new(home.path(), DEFAULT_DOWNLOAD_URL);
+        assert!(manager.installed_versions().is_empty());
+        assert!(!manager.activate_installed());
+
+        for version in ["6.100.0", "6.114.0"] {
+            std::fs::create_dir_all(home.path().join(version).join("src")).unwrap();
+            std::fs::write(manager.executable_for(version), "#!/bin/sh\n").unwrap();
+        }
+        std::fs::create_dir_all(home.path().join(".6.120.0.partial")).unwrap();
+
+        assert_eq!(manager.installed_versions(), vec!["6.100.0", "6.114.0"]);
+        manager.switch_to("6.100.0").unwrap();
+        assert_eq!(manager.active_version().as_deref(), Some("6.100.0"));
+    }
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Identyfikator aplikacji z tauri.conf.json - nazwa katalogu danych, tak jak `app_data_dir` Tauri
+const APP_IDENTIFIER: &str = "com.codialog.app";
+
+/// Plik w TAGUI_HOME z zapisaną ścieżką ustawioną przez `/system/config`
+const CONFIGURED_PATH_FILE: &str = "configured_path";
+
+/// Skąd pochodzi wybrany plik TagUI; warianty w kolejności pierwszeństwa
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaguiSource {
+    /// Ścieżka z `/system/config` albo TAGUI_PATH
+    Configured,
+    /// Wersja aktywowana przez `tagui_install::InstallManager`
+    Managed,
+    AppData,
+    Path,
+    /// Standard install locations of the current platform
+    Standard,
+    /// `tagui/` w katalogu roboczym
+    Local,
+}
+
+/// Plik TagUI wybrany przez `resolve`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedTagui {
+    pub path: PathBuf,
+    pub source: TaguiSource,
+}
+
+static CONFIGURED: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn set_configured_path(path: Option<PathBuf>) {
+    *CONFIGURED.write().unwrap() = path;
+}
+
+pub fn configured_path() -> Option<PathBuf> {
+    CONFIGURED.read().unwrap().clone()
+}
+
+/// Ścieżka zapisana wcześniej przez `/system/config`
+pub fn load_configured(home: &Path) -> Option<PathBuf> {
+    fs::read_to_string(home.join(CONFIGURED_PATH_FILE))
+        .ok()
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Zapisuje (albo usuwa przy `None`) ścieżkę ustawioną przez użytkownika, żeby przetrwała restart
+pub fn save_configured(home: &Path, path: Option<&Path>) -> Result<()> {
+    let file = home.join(CONFIGURED_PATH_FILE);
+    match path {
+        Some(path) => {
+            fs::create_dir_all(home).with_context(|| format!("Failed to create {}", home.display()))?;
+            fs::write(&file, path.display().to_string()).with_context(|| format!("Failed to write {}", file.display()))
+        }
+        None => match fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {}", file.display()))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Nazwy pliku startowego TagUI; na Windows TagUI uruchamia się przez `tagui.cmd`
+fn executable_names() -> &'static [&'static str] {
+    if cfg!(windows) {
+        &["tagui.cmd", "tagui.bat", "tagui.exe", "tagui"]
+    } else {
+        &["tagui"]
+    }
+}
+
+/// Plik TagUI pod `candidate`: sam plik albo katalog z `tagui` lub `src/tagui` (układ repozytorium TagUI)
+pub fn executable_in(candidate: &Path) -> Option<PathBuf> {
+    if candidate.is_file() {
+        return Some(candidate.to_path_buf());
+    }
+    if !candidate.is_dir() {
+        return None;
+    }
+    [candidate.to_path_buf(), candidate.join("src")]
+        .iter()
+        .flat_map(|dir| executable_names().iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from)
+}
+
+/// Katalog danych aplikacji, ten sam co `app_data_dir` Tauri
+pub fn app_data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".local").join("share")))
+    };
+    base.map(|base| base.join(APP_IDENTIFIER))
+}
+
+/// Miejsca, w które TagUI trafia przy instalacji według dokumentacji dla danej platformy
+fn standard_locations() -> Vec<PathBuf> {
+    let mut locations = Vec::new();
+    if cfg!(windows) {
+        locations.push(PathBuf::from(r"C:\tagui"));
+        for var in ["LOCALAPPDATA", "APPDATA", "ProgramFiles"] {
+            if let Some(dir) = std::env::var_os(var) {
+                locations.push(PathBuf::from(dir).join("tagui"));
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        locations.push(PathBuf::from("/Applications/tagui"));
+        locations.push(PathBuf::from("/opt/homebrew/bin"));
+        locations.push(PathBuf::from("/usr/local/bin"));
+    } else {
+        locations.push(PathBuf::from("/opt/tagui"));
+        locations.push(PathBuf::from("/usr/local/bin"));
+    }
+    if let Some(home) = home_dir() {
+        locations.push(home.join("tagui"));
+    }
+    locations
+}
+
+fn path_lookup() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        executable_names().iter().map(|name| dir.join(name)).find(|candidate| candidate.is_file())
+    })
+}
+
+/// Wybiera plik TagUI: ścieżka użytkownika, wersja zarządzana, katalog danych aplikacji, PATH,
+/// standardowe lokalizacje i na końcu `tagui/` w katalogu roboczym. Pomija kandydatów bez pliku.
+pub fn resolve(configured: Option<&Path>, managed: Option<&Path>) -> Option<ResolvedTagui> {
+    let found = |source: TaguiSource, candidate: &Path| executable_in(candidate).map(|path| ResolvedTagui { path, source });
+
+    configured
+        .and_then(|path| found(TaguiSource::Configured, path))
+        .or_else(|| managed.and_then(|path| found(TaguiSource::Managed, path)))
+        .or_else(|| app_data_dir().and_then(|dir| found(TaguiSource::AppData, &dir.join("tagui"))))
+        .or_else(|| path_lookup().map(|path| ResolvedTagui { path, source: TaguiSource::Path }))
+        .or_else(|| standard_locations().iter().find_map(|dir| found(TaguiSource::Standard, dir)))
+        .or_else(|| found(TaguiSource::Local, Path::new("tagui")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkout = dir.path().join("checkout");
+        fs::create_dir_all(checkout.join("src")).unwrap();
+        let name = executable_names()[0];
+        fs::write(checkout.join("src").join(name), "").unwrap();
+        let managed = dir.path().join("managed-tagui");
+        fs::write(&managed, "").unwrap();
+
+        // Katalog z kodem TagUI wskazuje na src/tagui
+        assert_eq!(executable_in(&checkout), Some(checkout.join("src").join(name)));
+
+        let resolved = resolve(Some(&checkout), Some(&managed)).unwrap();
+        assert_eq!((resolved.source, resolved.path), (TaguiSource::Configured, checkout.join("src").join(name)));
+
+        // Nieistniejąca ścieżka użytkownika nie blokuje zarządzanej instalacji
+        let resolved = resolve(Some(&dir.path().join("missing")), Some(&managed)).unwrap();
+        assert_eq!((resolved.source, resolved.path), (TaguiSource::Managed, managed));
+
+        let home = dir.path().join("home");
+        assert_eq!(load_configured(&home), None);
+        save_configured(&home, Some(&checkout)).unwrap();
+        assert_eq!(load_configured(&home), Some(checkout.clone()));
+        save_configured(&home, None).unwrap();
+        assert_eq!(load_configured(&home), None);
+    }
+}
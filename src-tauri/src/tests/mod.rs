@@ -19,6 +19,10 @@ pub mod test_database;
 #[cfg(feature = "integration_tests")]
 pub mod integration_tests;
 
+// Full pipeline against the local fixture site (needs Chrome)
+#[cfg(feature = "tests_e2e")]
+pub mod test_e2e;
+
 // Optional Bitwarden tests (disabled by default)
 #[cfg(feature = "tests_bitwarden")]
 pub mod test_bitwarden;
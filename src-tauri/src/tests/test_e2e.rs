@@ -0,0 +1,73 @@
+#![cfg(test)]
+
+//! Pełny przebieg na lokalnej stronie testowej: analiza strony przez CDP, generowanie DSL,
+//! wykonanie natywnym interpreterem i sprawdzenie formularza, który dotarł do strony.
+//! Wymaga Chrome: `cargo test --features tests_e2e`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::sync::Notify;
+
+use crate::fixtures::{self, FixtureSite};
+use codialog_core::{cdp, executor, llm, pacing::PacingProfile, prompts::PromptSelection};
+use codialog_core::tagui::{BrowserMode, ExecutionStatus};
+use codialog_types::automation::GenerationStrategy;
+
+/// Uruchamia stronę testową na wolnym porcie; zwraca adres formularza zgłoszenia
+async fn serve_fixture_site(site: Arc<FixtureSite>) -> String {
+    let app: axum::Router = axum::Router::new().nest(fixtures::FIXTURES_PATH, fixtures::router(site));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}{}/apply", address, fixtures::FIXTURES_PATH)
+}
+
+#[tokio::test]
+async fn test_fixture_form_full_pipeline() {
+    let site = Arc::new(FixtureSite::new());
+    let url = serve_fixture_site(site.clone()).await;
+
+    // Analiza: HTML strony tak jak w /page/analyze
+    let html = cdp::get_page_html(&url).await.expect("fixture page should load in Chrome");
+    assert!(html.contains("data-step=\"2\""));
+
+    // Generowanie: heurystyka rozpoznaje kroki kreatora, więc test nie zależy od LLM
+    let user_data = json!({ "first_name": "Jan", "email": "jan@example.com", "phone": "600100200", "country": "Poland" });
+    let generated = llm::generate_dsl_with_strategies(
+        &html,
+        &user_data,
+        &PromptSelection::default().with_page_url(Some(&url)),
+        Some(&[GenerationStrategy::Enhanced]),
+        None,
+    )
+    .await;
+    assert_eq!(generated.generation.strategy, Some(GenerationStrategy::Enhanced));
+    assert!(generated.script.contains("click \"#next-1\""), "script: {}", generated.script);
+
+    // Wysłanie formularza zostaje po stronie użytkownika - dopisuje je jak po przejrzeniu skryptu
+    let script = format!("{}\nclick \"#submit\"\nwaitfor \"#thanks\" timeout 10", generated.script.trim_end());
+    let result = executor::execute_script(
+        &script,
+        Some(&url),
+        Arc::new(Notify::new()),
+        None,
+        BrowserMode::Headless,
+        PacingProfile::Fast,
+        Some(Duration::from_secs(60)),
+        None,
+    )
+    .await;
+    assert_eq!(result.status, ExecutionStatus::Succeeded, "run failed: {:?}\n{}", result.error, result.stdout);
+
+    let submissions = site.submissions();
+    assert_eq!(submissions.len(), 1);
+    let fields = &submissions[0].fields;
+    assert_eq!(fields.get("first_name").map(String::as_str), Some("Jan"));
+    assert_eq!(fields.get("email").map(String::as_str), Some("jan@example.com"));
+    assert_eq!(fields.get("phone").map(String::as_str), Some("600100200"));
+    assert_eq!(fields.get("country").map(String::as_str), Some("pl"));
+}
@@ -220,8 +220,8 @@ impl FormAnalyzerTrait for MockFormAnalyzer {
         let checkbox_sequence = generate_checkbox_sequence(&analyzer);
         assert!(!checkbox_sequence.is_empty(), "Should generate checkbox sequence for form with checkboxes");
         
-        assert!(checkbox_sequence.iter().any(|action| action.contains("click")), 
-               "Should contain click commands for checkboxes");
+        assert!(checkbox_sequence.iter().any(|action| action.starts_with("check")), 
+               "Should contain check commands for checkboxes");
     }
 
     #[test]
@@ -0,0 +1,184 @@
+//! Odstępy między wysłaniami formularzy na tej samej witrynie, żeby automatyzacja nie wpadała
+//! w limity zapytań i nie obciążała stron. Przebieg „wysyła” formularz, gdy skrypt coś klika
+//! lub naciska klawisz; przebiegi tylko czytające stronę nie czekają.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::{cdp, tagui};
+
+/// Domyślny minimalny odstęp między wysłaniami na jednej witrynie (SUBMIT_MIN_INTERVAL_SECS)
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Odstępy: domyślny i nadpisane dla witryn (także ich subdomen)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrottlePolicy {
+    pub default_interval: Duration,
+    pub site_intervals: Vec<(String, Duration)>,
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self { default_interval: DEFAULT_MIN_INTERVAL, site_intervals: Vec::new() }
+    }
+}
+
+impl ThrottlePolicy {
+    /// Lista `witryna=sekundy` oddzielona przecinkami (SUBMIT_SITE_INTERVALS), np. "linkedin.com=60,workday.com=30"
+    pub fn parse_site_intervals(spec: &str) -> Result<Vec<(String, Duration)>, String> {
+        let mut intervals = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (site, secs) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected site=seconds, got '{}'", entry))?;
+            let site = site.trim().to_lowercase();
+            let site = site.strip_prefix("www.").unwrap_or(&site).to_string();
+            if site.is_empty() || site.contains('/') {
+                return Err(format!("invalid site in '{}'", entry));
+            }
+            let secs: u64 = secs.trim().parse().map_err(|_| format!("invalid number of seconds in '{}'", entry))?;
+            intervals.push((site, Duration::from_secs(secs)));
+        }
+        Ok(intervals)
+    }
+
+    /// Odstęp dla witryny: najdłuższa pasująca nazwa z listy, inaczej domyślny
+    pub fn interval(&self, site: &str) -> Duration {
+        self.site_intervals
+            .iter()
+            .filter(|(name, _)| site == name || site.ends_with(&format!(".{}", name)))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, interval)| *interval)
+            .unwrap_or(self.default_interval)
+    }
+
+    /// Najdłuższy skonfigurowany odstęp - starsze wysłania nie mają już znaczenia
+    pub fn max_interval(&self) -> Duration {
+        self.site_intervals.iter().map(|(_, interval)| *interval).fold(self.default_interval, Duration::max)
+    }
+}
+
+/// Komendy, które mogą coś wysłać na stronie
+pub const SUBMISSION_COMMANDS: &[&str] = &["click", "press"];
+
+/// Czy skrypt wysyła coś na stronie (klika lub naciska klawisz)
+pub fn is_submission(script: &str) -> bool {
+    tagui::parse_dsl_script(script)
+        .map(|commands| commands.iter().any(|command| SUBMISSION_COMMANDS.contains(&command.name.as_str())))
+        .unwrap_or(false)
+}
+
+/// Witryna, której dotyczy odstęp przebiegu: host strony startowej, jeśli skrypt coś wysyła
+pub fn submission_site(script: &str, start_url: Option<&str>) -> Option<String> {
+    start_url.and_then(cdp::site_of).filter(|_| is_submission(script))
+}
+
+/// Ostatnie wysłania na witrynach w tym procesie (oraz zgłoszone z kolejki zadań)
+#[derive(Debug, Default)]
+pub struct SubmissionThrottle {
+    policy: ThrottlePolicy,
+    last: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl SubmissionThrottle {
+    pub fn new(policy: ThrottlePolicy) -> Self {
+        Self { policy, last: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn policy(&self) -> &ThrottlePolicy {
+        &self.policy
+    }
+
+    /// Ile jeszcze czekać z wysłaniem na witrynie
+    pub fn remaining(&self, site: &str, now: DateTime<Utc>) -> Duration {
+        let last = self.last.lock().unwrap().get(site).copied();
+        remaining_after(last, self.policy.interval(site), now)
+    }
+
+    /// Zajmuje witrynę na teraz, jeśli odstęp już minął; `false` - trzeba dalej czekać
+    pub fn try_reserve(&self, site: &str, now: DateTime<Utc>) -> bool {
+        let mut last = self.last.lock().unwrap();
+        if !remaining_after(last.get(site).copied(), self.policy.interval(site), now).is_zero() {
+            return false;
+        }
+        last.insert(site.to_string(), now);
+        true
+    }
+
+    /// Wysłanie wykonane gdzie indziej (np. zadanie pobrane przez inny worker); zostaje nowsze
+    pub fn observe(&self, site: &str, at: DateTime<Utc>) {
+        let mut last = self.last.lock().unwrap();
+        let entry = last.entry(site.to_string()).or_insert(at);
+        if at > *entry {
+            *entry = at;
+        }
+    }
+
+    /// Witryny, na których odstęp jeszcze trwa; wpisy starsze od najdłuższego odstępu są usuwane
+    pub fn throttled_sites(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut last = self.last.lock().unwrap();
+        let horizon = chrono::Duration::from_std(self.policy.max_interval()).unwrap_or_default();
+        last.retain(|_, at| now - *at < horizon);
+        let sites: Vec<String> = last
+            .iter()
+            .filter(|(site, at)| !remaining_after(Some(**at), self.policy.interval(site), now).is_zero())
+            .map(|(site, _)| site.clone())
+            .collect();
+        if !sites.is_empty() {
+            debug!(sites = ?sites, "Sites waiting for the submission interval");
+        }
+        sites
+    }
+}
+
+fn remaining_after(last: Option<DateTime<Utc>>, interval: Duration, now: DateTime<Utc>) -> Duration {
+    let Some(last) = last else {
+        return Duration::ZERO;
+    };
+    let elapsed = (now - last).to_std().unwrap_or(Duration::ZERO);
+    interval.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_spaces_submissions_per_site() {
+        let intervals = ThrottlePolicy::parse_site_intervals(" www.LinkedIn.com=60, jobs.example.com=0 ").unwrap();
+        assert!(ThrottlePolicy::parse_site_intervals("linkedin.com").is_err());
+        assert!(ThrottlePolicy::parse_site_intervals("linkedin.com=soon").is_err());
+        let policy = ThrottlePolicy { default_interval: Duration::from_secs(10), site_intervals: intervals };
+        assert_eq!(policy.interval("linkedin.com"), Duration::from_secs(60));
+        assert_eq!(policy.interval("uk.linkedin.com"), Duration::from_secs(60));
+        assert_eq!(policy.interval("jobs.example.com"), Duration::ZERO);
+        assert_eq!(policy.interval("notlinkedin.com"), Duration::from_secs(10));
+        assert_eq!(policy.max_interval(), Duration::from_secs(60));
+
+        assert_eq!(submission_site("type \"#q\" \"x\"\nclick \"#go\"", Some("https://www.LinkedIn.com/jobs")).as_deref(), Some("linkedin.com"));
+        assert_eq!(submission_site("wait 1\nhover \"#menu\"", Some("https://linkedin.com")), None);
+        assert_eq!(submission_site("click \"#go\"", None), None);
+
+        let throttle = SubmissionThrottle::new(policy);
+        let start: DateTime<Utc> = "2026-03-02T09:00:00Z".parse().unwrap();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+        assert!(throttle.try_reserve("linkedin.com", at(0)));
+        assert!(!throttle.try_reserve("linkedin.com", at(30)));
+        assert_eq!(throttle.remaining("linkedin.com", at(30)), Duration::from_secs(30));
+        // Inne witryny mają własne odstępy
+        assert!(throttle.try_reserve("example.org", at(30)));
+        assert!(throttle.try_reserve("jobs.example.com", at(30)) && throttle.try_reserve("jobs.example.com", at(30)));
+
+        // Starsze zgłoszenie nie cofa ostatniego wysłania
+        throttle.observe("example.org", at(35));
+        throttle.observe("example.org", at(1));
+        let mut sites = throttle.throttled_sites(at(40));
+        sites.sort();
+        assert_eq!(sites, vec!["example.org", "linkedin.com"]);
+        assert_eq!(throttle.throttled_sites(at(50)), vec!["linkedin.com"]);
+        assert!(throttle.try_reserve("linkedin.com", at(60)));
+    }
+}
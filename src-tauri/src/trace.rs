@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::tagui::{self, DslCommand, OutputLine, OutputStream, StepStatus};
+
+/// Maksymalna liczba linii wyjścia zapamiętywanych na potrzeby śladu jednego przebiegu
+pub const MAX_TRACE_LINES: usize = 10_000;
+
+/// Rodzaj odcinka na osi czasu przebiegu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceKind {
+    /// Oczekiwanie w kolejce na wolny slot TagUI
+    Queue,
+    /// Start TagUI i przeglądarki przed pierwszą komendą
+    Startup,
+    Step,
+    /// Komenda `wait` lub `waitfor` ze skryptu
+    Wait,
+    /// Backoff adnotacji `retry` przed właściwą komendą
+    Retry,
+    Screenshot,
+    /// Browser/DevTools messages printed by TagUI (connection, tab, websocket)
+    Cdp,
+    Error,
+}
+
+/// Pojedynczy odcinek osi czasu; offsety w ms liczone od `queued_at` śladu
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEvent {
+    pub kind: TraceKind,
+    pub label: String,
+    /// 1-based line of the DSL command the event belongs to
+    pub line: Option<usize>,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StepStatus>,
+}
+
+/// Oś czasu przebiegu do wykresu Gantta; `totals_ms` sumuje czas odcinków wg rodzaju
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub run_id: String,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub total_ms: i64,
+    pub events: Vec<TraceEvent>,
+    pub totals_ms: BTreeMap<TraceKind, i64>,
+    /// Wyjście przekroczyło `MAX_TRACE_LINES` - końcówka przebiegu nie ma odcinków
+    pub truncated: bool,
+}
+
+/// Czas przebiegu zarejestrowany przez `RunManager`
+pub struct RunTiming<'a> {
+    pub run_id: &'a str,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub steps: &'a [tagui::StepResult],
+    pub truncated: bool,
+}
+
+/// Builds the timeline from TagUI output lines timestamped as they were printed.
+/// TagUI echoes each step when it starts, so a step lasts until the next echoed step.
+pub fn build_trace(script: &str, timing: &RunTiming, lines: &[OutputLine]) -> ExecutionTrace {
+    let offset = |at: DateTime<Utc>| (at - timing.queued_at).num_milliseconds().max(0);
+    let finished_ms = timing.finished_at.map(offset).unwrap_or_else(|| lines.last().map(|line| offset(line.at)).unwrap_or(0));
+    let mut events = Vec::new();
+
+    if let Some(started_at) = timing.started_at {
+        events.push(TraceEvent {
+            kind: TraceKind::Queue,
+            label: "Waiting for a TagUI slot".to_string(),
+            line: None,
+            start_ms: 0,
+            end_ms: offset(started_at),
+            status: None,
+        });
+    }
+
+    let steps = trace_steps(script);
+    let statuses: HashMap<usize, StepStatus> = timing.steps.iter().map(|step| (step.line, step.status)).collect();
+    let mut cursor = 0;
+    // Odcinek trwa do początku następnego, więc koniec uzupełniamy przy kolejnym echu
+    let mut open: Option<usize> = None;
+    let mut first_step_seen = false;
+
+    for output in lines {
+        let text = output.line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let at = offset(output.at);
+
+        let event = if output.stream == OutputStream::Stdout && tagui::is_command_echo(text) {
+            let first_word = text.split_whitespace().next().unwrap_or_default();
+            if first_word == "snap" {
+                Some((TraceKind::Screenshot, text.rsplit('/').next().unwrap_or(text).to_string(), None))
+            } else if first_word == "wait" && steps.get(cursor).is_some_and(|(_, _, parsed)| parsed.retry.is_some()) {
+                Some((TraceKind::Retry, text.to_string(), steps.get(cursor).map(|(line, _, _)| *line)))
+            } else if let Some(index) = match_step(&steps, cursor, text) {
+                cursor = index + 1;
+                let (line, command, parsed) = &steps[index];
+                let kind = if matches!(parsed.name.as_str(), "wait" | "waitfor") { TraceKind::Wait } else { TraceKind::Step };
+                Some((kind, command.clone(), Some(*line)))
+            } else {
+                Some((TraceKind::Step, text.to_string(), None))
+            }
+        } else if is_browser_message(text) {
+            Some((TraceKind::Cdp, text.to_string(), None))
+        } else if text.starts_with("ERROR") {
+            Some((TraceKind::Error, text.to_string(), None))
+        } else {
+            None
+        };
+
+        let Some((kind, label, line)) = event else {
+            continue;
+        };
+        let spans = matches!(kind, TraceKind::Step | TraceKind::Wait | TraceKind::Retry | TraceKind::Screenshot);
+        if spans {
+            close(&mut events, open.take(), at);
+        }
+        if spans && !first_step_seen {
+            first_step_seen = true;
+            events.push(TraceEvent {
+                kind: TraceKind::Startup,
+                label: "TagUI and browser startup".to_string(),
+                line: None,
+                start_ms: timing.started_at.map(offset).unwrap_or(0).min(at),
+                end_ms: at,
+                status: None,
+            });
+        }
+
+        events.push(TraceEvent {
+            kind,
+            label,
+            line,
+            start_ms: at,
+            end_ms: at,
+            status: line.filter(|_| kind == TraceKind::Step || kind == TraceKind::Wait).and_then(|line| statuses.get(&line).copied()),
+        });
+        if spans {
+            open = Some(events.len() - 1);
+        }
+    }
+    close(&mut events, open, finished_ms);
+
+    let mut totals_ms = BTreeMap::new();
+    for event in &events {
+        *totals_ms.entry(event.kind).or_insert(0) += event.end_ms - event.start_ms;
+    }
+
+    ExecutionTrace {
+        run_id: timing.run_id.to_string(),
+        queued_at: timing.queued_at,
+        started_at: timing.started_at,
+        finished_at: timing.finished_at,
+        total_ms: finished_ms,
+        events,
+        totals_ms,
+        truncated: timing.truncated,
+    }
+}
+
+fn close(events: &mut [TraceEvent], index: Option<usize>, end_ms: i64) {
+    if let Some(event) = index.and_then(|index| events.get_mut(index)) {
+        event.end_ms = end_ms.max(event.start_ms);
+    }
+}
+
+/// Wykonywane komendy skryptu: (linia, tekst, komenda) bez nagłówków bloków
+fn trace_steps(script: &str) -> Vec<(usize, String, DslCommand)> {
+    let parsed: HashMap<usize, DslCommand> = tagui::parse_dsl_script(script)
+        .map(|commands| commands.into_iter().map(|command| (command.line, command)).collect())
+        .unwrap_or_default();
+
+    tagui::script_commands(script)
+        .into_iter()
+        .filter_map(|(line, command)| {
+            let parsed = parsed.get(&line)?;
+            (!tagui::DSL_BLOCK_KEYWORDS.contains(&parsed.name.as_str())).then(|| (line, command, parsed.clone()))
+        })
+        .collect()
+}
+
+/// Szuka echa komendy od bieżącej pozycji, a potem od początku (kolejna iteracja pętli)
+fn match_step(steps: &[(usize, String, DslCommand)], cursor: usize, echo: &str) -> Option<usize> {
+    let matches = |index: &usize| {
+        let (_, command, parsed) = &steps[*index];
+        echo.contains(tagui::command_echo(parsed, command).as_str())
+    };
+
+    (cursor..steps.len()).chain(0..cursor.min(steps.len())).find(matches)
+}
+
+fn is_browser_message(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    ["chrome", "devtools", "websocket", "cdp", "browser"].iter().any(|word| lower.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_build_trace_timeline() {
+        let queued_at = Utc::now();
+        let at = |ms: i64| queued_at + Duration::milliseconds(ms);
+        let line = |ms: i64, text: &str| OutputLine {
+            run_id: "run-1".to_string(),
+            stream: OutputStream::Stdout,
+            line: text.to_string(),
+            at: at(ms),
+        };
+        let script = "click \"#open\"\nwait 5\nclick \"#submit\" retry 2 interval 1";
+        let output = vec![
+            line(1_000, "START - automation started - Chrome connected"),
+            line(3_000, "click \"#open\""),
+            line(3_500, "snap page to /tmp/run-1/step-001.png"),
+            line(4_000, "wait 5"),
+            line(9_000, "wait 1"),
+            line(10_000, "click \"#submit\""),
+        ];
+        let steps = [tagui::StepResult { line: 3, command: "click \"#submit\"".to_string(), status: StepStatus::Succeeded }];
+        let timing = RunTiming {
+            run_id: "run-1",
+            queued_at,
+            started_at: Some(at(500)),
+            finished_at: Some(at(12_000)),
+            steps: &steps,
+            truncated: false,
+        };
+
+        let trace = build_trace(script, &timing, &output);
+        let kinds: Vec<TraceKind> = trace.events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, vec![
+            TraceKind::Queue,
+            TraceKind::Cdp,
+            TraceKind::Startup,
+            TraceKind::Step,
+            TraceKind::Screenshot,
+            TraceKind::Wait,
+            TraceKind::Retry,
+            TraceKind::Step,
+        ]);
+
+        let wait = &trace.events[5];
+        assert_eq!((wait.line, wait.start_ms, wait.end_ms), (Some(2), 4_000, 9_000));
+        assert_eq!(trace.events[6].line, Some(3));
+        assert_eq!(trace.events[7].end_ms, 12_000);
+        assert_eq!(trace.events[7].status, Some(StepStatus::Succeeded));
+        assert_eq!(trace.totals_ms[&TraceKind::Startup], 2_500);
+        assert_eq!(trace.total_ms, 12_000);
+    }
+}
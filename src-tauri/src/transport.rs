@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, Context, bail};
+use std::path::Path;
+use tracing::info;
+
+/// Sposób wystawienia lokalnego API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiTransport {
+    /// Port TCP osiągalny dla każdego lokalnego procesu
+    Tcp,
+    /// Unix socket (named pipe on Windows) reachable only by the current user (desktop deployments)
+    Unix,
+}
+
+impl ApiTransport {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "unix" | "socket" => ApiTransport::Unix,
+            _ => ApiTransport::Tcp,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTransport::Tcp => "tcp",
+            ApiTransport::Unix => "unix",
+        }
+    }
+}
+
+/// Odpowiedź API przekazywana do frontendu Tauri
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiReply {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// Serves the router on a unix socket with `0600` permissions until `shutdown` resolves,
+/// then waits for in-flight requests like `axum::serve(..).with_graceful_shutdown`.
+/// `ready` runs once the socket is bound and accepts connections.
+///
+/// The socket is bound inside a fresh `0700` directory and only renamed to `socket_path`
+/// once it is `0600`, so no other user can connect in between. Permissions of directories
+/// that already exist are left untouched; missing ones are created as `0700`.
+#[cfg(unix)]
+pub async fn serve_unix(
+    socket_path: &Path,
+    app: axum::Router,
+    ready: impl FnOnce(),
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let parent = socket_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if !parent.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)
+            .with_context(|| format!("Failed to create socket directory {}", parent.display()))?;
+    }
+
+    let staging = tempfile::Builder::new()
+        .prefix(".codialog-api-")
+        .permissions(std::fs::Permissions::from_mode(0o700))
+        .tempdir_in(parent)
+        .with_context(|| format!("Failed to create a private staging directory in {}", parent.display()))?;
+    let staged_socket = staging.path().join("api.sock");
+    let listener = tokio::net::UnixListener::bind(&staged_socket)
+        .with_context(|| format!("Failed to bind unix socket {}", staged_socket.display()))?;
+    std::fs::set_permissions(&staged_socket, std::fs::Permissions::from_mode(0o600))?;
+    // Rename podmienia też pozostałość po poprzednim procesie
+    std::fs::rename(&staged_socket, socket_path)
+        .with_context(|| format!("Failed to move unix socket to {}", socket_path.display()))?;
+    drop(staging);
+    info!("HTTP server listening on unix socket {}", socket_path.display());
+    ready();
+
+    let graceful = hyper_util::server::graceful::GracefulShutdown::new();
+    tokio::pin!(shutdown);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Failed to accept unix socket connection: {}", e);
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        serve_connection(&graceful, &app, stream);
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    let _ = std::fs::remove_file(socket_path);
+    Ok(())
+}
+
+/// Named pipe used on Windows for `API_TRANSPORT=unix`: `API_SOCKET_PATH` may name the pipe
+/// directly (`\\.\pipe\...`), otherwise its file name is used under `\\.\pipe\`.
+pub fn pipe_name(socket_path: &Path) -> String {
+    let raw = socket_path.to_string_lossy();
+    if raw.starts_with(r"\\.\pipe\") {
+        return raw.to_string();
+    }
+    let name = socket_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "codialog-api".to_string());
+    format!(r"\\.\pipe\{}", name)
+}
+
+/// Serves the router on a named pipe until `shutdown` resolves (Windows counterpart of `serve_unix`).
+/// The pipe keeps the default security descriptor, which only lets the creating user, SYSTEM
+/// and administrators open it for writing; remote clients are rejected and a pipe already
+/// created by another process makes startup fail instead of being shared.
+#[cfg(windows)]
+pub async fn serve_pipe(
+    socket_path: &Path,
+    app: axum::Router,
+    ready: impl FnOnce(),
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = pipe_name(socket_path);
+    let create = |first: bool| {
+        ServerOptions::new()
+            .first_pipe_instance(first)
+            .reject_remote_clients(true)
+            .create(&name)
+            .with_context(|| format!("Failed to create named pipe {}", name))
+    };
+    let mut server = create(true)?;
+    info!("HTTP server listening on named pipe {}", name);
+    ready();
+
+    let graceful = hyper_util::server::graceful::GracefulShutdown::new();
+    tokio::pin!(shutdown);
+    loop {
+        let connected = tokio::select! {
+            connected = server.connect() => connected,
+            _ = &mut shutdown => break,
+        };
+        if let Err(e) = connected {
+            tracing::warn!("Failed to accept named pipe connection: {}", e);
+            server = create(false)?;
+            continue;
+        }
+        // Nowa instancja musi istnieć zanim obecna zostanie oddana połączeniu
+        let stream = std::mem::replace(&mut server, create(false)?);
+        serve_connection(&graceful, &app, stream);
+    }
+
+    drop(server);
+    graceful.shutdown().await;
+    Ok(())
+}
+
+/// Obsługuje jedno połączenie HTTP/1.1 z gniazda albo potoku
+#[cfg(any(unix, windows))]
+fn serve_connection<S>(graceful: &hyper_util::server::graceful::GracefulShutdown, app: &axum::Router, stream: S)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    use hyper::body::Incoming;
+    use hyper_util::rt::TokioIo;
+    use tower::Service;
+
+    let router = app.clone();
+    let service = hyper::service::service_fn(move |request: hyper::Request<Incoming>| router.clone().call(request));
+    let connection = hyper::server::conn::http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+    let connection = graceful.watch(connection);
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::debug!("Local API connection closed with error: {}", e);
+        }
+    });
+}
+
+/// Wysyła jedno żądanie HTTP/1.1 przez gniazdo unix albo (na Windows) potok nazwany
+/// (używane przez frontend Tauri)
+pub async fn request_local(
+    socket_path: &Path,
+    method: &str,
+    path: &str,
+    body: Option<String>,
+    headers: &[(&str, &str)],
+) -> Result<ApiReply> {
+    if !path.starts_with('/') {
+        bail!("API path must start with '/': {}", path);
+    }
+
+    #[cfg(unix)]
+    {
+        let stream = tokio::net::UnixStream::connect(socket_path)
+            .await
+            .with_context(|| format!("Failed to connect to {}", socket_path.display()))?;
+        send_request(stream, method, path, body, headers).await
+    }
+    #[cfg(windows)]
+    {
+        use tokio::net::windows::named_pipe::ClientOptions;
+        const ERROR_PIPE_BUSY: i32 = 231;
+
+        let name = pipe_name(socket_path);
+        let mut attempts = 0;
+        let stream = loop {
+            match ClientOptions::new().open(&name) {
+                Ok(stream) => break stream,
+                // Wszystkie instancje zajęte: serwer zaraz utworzy następną
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < 50 => {
+                    attempts += 1;
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to connect to {}", name)),
+            }
+        };
+        send_request(stream, method, path, body, headers).await
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (socket_path, method, body, headers);
+        bail!("Local socket transport is not available on this platform")
+    }
+}
+
+#[cfg(any(unix, windows))]
+async fn send_request<S>(
+    stream: S,
+    method: &str,
+    path: &str,
+    body: Option<String>,
+    headers: &[(&str, &str)],
+) -> Result<ApiReply>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+    use hyper_util::rt::TokioIo;
+
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    let mut request = hyper::Request::builder()
+        .method(method.to_uppercase().as_str())
+        .uri(path)
+        .header(hyper::header::HOST, "localhost");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    if body.is_some() {
+        request = request.header(hyper::header::CONTENT_TYPE, "application/json");
+    }
+    let request = request.body(Full::new(Bytes::from(body.unwrap_or_default())))?;
+
+    let response = sender.send_request(request).await.context("API request over the local socket failed")?;
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let body = response.into_body().collect().await?.to_bytes();
+
+    Ok(ApiReply { status, content_type, body: String::from_utf8_lossy(&body).to_string() })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use axum::routing::{get, post};
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn test_unix_socket_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("run").join("api.sock");
+        let app = axum::Router::new()
+            .route("/health", get(|| async { axum::Json(serde_json::json!({"status": "healthy"})) }))
+            .route("/echo", post(|headers: axum::http::HeaderMap, body: String| async move {
+                let nonce = headers.get("x-instance-nonce").and_then(|v| v.to_str().ok()).unwrap_or("-").to_string();
+                format!("{} {}", nonce, body)
+            }));
+
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let (ready, bound) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn({
+            let socket_path = socket_path.clone();
+            async move { serve_unix(&socket_path, app, move || { let _ = ready.send(()); }, async { let _ = stopped.await; }).await }
+        });
+        bound.await.unwrap();
+
+        let mode = std::fs::metadata(&socket_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0, "socket must not be accessible to other users");
+        let mode = std::fs::metadata(socket_path.parent().unwrap()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700, "a missing socket directory is created private");
+
+        let reply = request_local(&socket_path, "get", "/health", None, &[]).await.unwrap();
+        assert_eq!(reply.status, 200);
+        assert!(reply.body.contains("healthy"));
+        let reply = request_local(&socket_path, "POST", "/echo", Some("{\"a\":1}".to_string()), &[("x-instance-nonce", "abc")]).await.unwrap();
+        assert_eq!(reply.body, "abc {\"a\":1}");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!socket_path.exists());
+        assert_eq!(ApiTransport::parse("UNIX"), ApiTransport::Unix);
+        assert_eq!(pipe_name(Path::new("data/codialog-api.sock")), r"\\.\pipe\codialog-api.sock");
+    }
+
+    #[tokio::test]
+    async fn test_serve_unix_leaves_existing_directory_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let socket_path = dir.path().join("api.sock");
+        std::fs::write(&socket_path, "stale").unwrap();
+
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let (ready, bound) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn({
+            let socket_path = socket_path.clone();
+            async move { serve_unix(&socket_path, axum::Router::new(), move || { let _ = ready.send(()); }, async { let _ = stopped.await; }).await }
+        });
+        bound.await.unwrap();
+
+        assert_eq!(std::fs::metadata(dir.path()).unwrap().permissions().mode() & 0o777, 0o755);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1, "staging directory is removed");
+        assert_eq!(request_local(&socket_path, "GET", "/missing", None, &[]).await.unwrap().status, 404);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+//! Nadzór komponentów w trakcie działania: serwer HTTP, harmonogram, przeglądarka i Redis są sprawdzane
+//! co `WATCHDOG_INTERVAL_SECS`. Po `WATCHDOG_FAILURE_THRESHOLD` nieudanych sprawdzeniach z rzędu komponent
+//! jest restartowany w miejscu, a incydent trafia do historii i logu systemowego - bez restartu całej aplikacji.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Domyślny odstęp między sprawdzeniami (WATCHDOG_INTERVAL_SECS)
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Domyślna liczba nieudanych sprawdzeń z rzędu przed restartem (WATCHDOG_FAILURE_THRESHOLD)
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Przeglądarka jest uruchamiana tylko co tyle cykli - sprawdzenie kosztuje start Chromium
+pub const BROWSER_CHECK_EVERY: u32 = 10;
+
+/// Limit czasu pojedynczego sprawdzenia
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Ile ostatnich incydentów trzymamy w pamięci
+const MAX_INCIDENTS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Component {
+    HttpServer,
+    Scheduler,
+    Browser,
+    Redis,
+}
+
+impl Component {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Component::HttpServer => "http_server",
+            Component::Scheduler => "scheduler",
+            Component::Browser => "browser",
+            Component::Redis => "redis",
+        }
+    }
+}
+
+/// Stan komponentu widziany przez nadzorcę
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentHealth {
+    pub component: Component,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub restarts: u32,
+}
+
+impl ComponentHealth {
+    fn new(component: Component) -> Self {
+        Self { component, healthy: true, consecutive_failures: 0, last_error: None, last_checked_at: None, restarts: 0 }
+    }
+}
+
+/// Restart lub zgłoszona awaria komponentu po serii nieudanych sprawdzeń
+#[derive(Debug, Clone, Serialize)]
+pub struct Incident {
+    pub component: Component,
+    pub failures: u32,
+    pub error: String,
+    /// Co nadzorca zrobił, np. "restarted scheduler task"
+    pub action: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchdogStatus {
+    pub components: Vec<ComponentHealth>,
+    /// Od najnowszego
+    pub incidents: Vec<Incident>,
+}
+
+/// Liczniki nieudanych sprawdzeń i historia incydentów
+pub struct Watchdog {
+    failure_threshold: u32,
+    components: Mutex<HashMap<Component, ComponentHealth>>,
+    incidents: Mutex<VecDeque<Incident>>,
+}
+
+impl Watchdog {
+    pub fn new(failure_threshold: u32) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            components: Mutex::new(HashMap::new()),
+            incidents: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Zapisuje wynik sprawdzenia; zwraca błąd do zgłoszenia w incydencie, gdy seria porażek osiągnęła
+    /// próg i komponent trzeba zrestartować. Licznik jest wtedy zerowany, więc kolejny restart wymaga nowej serii.
+    pub fn observe(&self, component: Component, outcome: Result<(), String>) -> Option<String> {
+        let mut components = self.components.lock().unwrap();
+        let health = components.entry(component).or_insert_with(|| ComponentHealth::new(component));
+        health.last_checked_at = Some(Utc::now());
+        match outcome {
+            Ok(()) => {
+                if !health.healthy {
+                    info!(component = component.as_str(), "Component recovered");
+                }
+                health.healthy = true;
+                health.consecutive_failures = 0;
+                None
+            }
+            Err(error) => {
+                health.healthy = false;
+                health.consecutive_failures += 1;
+                health.last_error = Some(error.clone());
+                warn!(component = component.as_str(), failures = health.consecutive_failures, "Health check failed: {}", error);
+                if health.consecutive_failures < self.failure_threshold {
+                    return None;
+                }
+                health.consecutive_failures = 0;
+                Some(error)
+            }
+        }
+    }
+
+    /// Zapisuje restart komponentu w historii
+    pub fn record_restart(&self, component: Component, error: String, action: &str) -> Incident {
+        if let Some(health) = self.components.lock().unwrap().get_mut(&component) {
+            health.restarts += 1;
+        }
+        self.record_incident(component, error, action)
+    }
+
+    /// Zapisuje w historii awarię zgłoszoną bez restartu komponentu
+    pub fn record_incident(&self, component: Component, error: String, action: &str) -> Incident {
+        let incident = Incident { component, failures: self.failure_threshold, error, action: action.to_string(), at: Utc::now() };
+        let mut incidents = self.incidents.lock().unwrap();
+        if incidents.len() == MAX_INCIDENTS {
+            incidents.pop_back();
+        }
+        incidents.push_front(incident.clone());
+        incident
+    }
+
+    pub fn status(&self) -> WatchdogStatus {
+        let mut components: Vec<ComponentHealth> = self.components.lock().unwrap().values().cloned().collect();
+        components.sort_by_key(|health| health.component.as_str());
+        WatchdogStatus { components, incidents: self.incidents.lock().unwrap().iter().cloned().collect() }
+    }
+}
+
+/// Sprawdzenie z limitem `CHECK_TIMEOUT`; błąd jako tekst dla licznika i incydentu
+pub async fn probe<E: Display>(check: impl Future<Output = Result<(), E>>) -> Result<(), String> {
+    match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(outcome) => outcome.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("Timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    }
+}
+
+/// Zadanie w tle, które nadzorca może zatrzymać i uruchomić od nowa tą samą funkcją
+pub struct Supervised {
+    spawn: Box<dyn Fn() -> JoinHandle<()> + Send + Sync>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Supervised {
+    pub fn start(spawn: impl Fn() -> JoinHandle<()> + Send + Sync + 'static) -> Self {
+        let handle = spawn();
+        Self { spawn: Box::new(spawn), handle: Mutex::new(Some(handle)) }
+    }
+
+    /// Czy zadanie jeszcze działa (nie zakończyło się ani nie spanikowało)
+    pub fn is_running(&self) -> bool {
+        self.handle.lock().unwrap().as_ref().map(|handle| !handle.is_finished()).unwrap_or(false)
+    }
+
+    /// Przerywa bieżące zadanie i uruchamia nowe
+    pub fn restart(&self) {
+        let mut handle = self.handle.lock().unwrap();
+        if let Some(previous) = handle.take() {
+            previous.abort();
+        }
+        *handle = Some((self.spawn)());
+    }
+
+    /// Czeka na zakończenie bieżącego zadania, np. serwera HTTP po drenowaniu
+    pub async fn join(&self) {
+        let handle = self.handle.lock().unwrap().take();
+        if let Some(handle) = handle {
+            if let Err(e) = handle.await {
+                if !e.is_cancelled() {
+                    warn!("Supervised task failed: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_restart_after_threshold_and_supervised_task() {
+        let watchdog = Watchdog::new(3);
+        assert_eq!(watchdog.observe(Component::Redis, Err("connection refused".to_string())), None);
+        assert_eq!(watchdog.observe(Component::Redis, Err("connection refused".to_string())), None);
+        // Udane sprawdzenie przerywa serię
+        assert_eq!(watchdog.observe(Component::Redis, Ok(())), None);
+        for _ in 0..2 {
+            assert_eq!(watchdog.observe(Component::Redis, Err("timeout".to_string())), None);
+        }
+        assert_eq!(watchdog.observe(Component::Redis, Err("timeout".to_string())).as_deref(), Some("timeout"));
+        assert_eq!(watchdog.observe(Component::Redis, Err("timeout".to_string())), None);
+
+        watchdog.record_restart(Component::Redis, "timeout".to_string(), "reconnected");
+        let status = watchdog.status();
+        assert_eq!(status.components[0].restarts, 1);
+        assert!(!status.components[0].healthy);
+        assert_eq!(status.incidents[0].action, "reconnected");
+
+        let task = Supervised::start(|| tokio::spawn(async {}));
+        task.join().await;
+        assert!(!task.is_running());
+        let task = Supervised::start(|| tokio::spawn(std::future::pending()));
+        assert!(task.is_running());
+        task.restart();
+        assert!(task.is_running());
+    }
+}
@@ -1,4 +1,41 @@
 const API_URL = 'http://localhost:4000';
+// Próg pewności dopasowania pola, poniżej którego użytkownik potwierdza wypełnienie (jak LOW_CONFIDENCE w API)
+const LOW_CONFIDENCE = 0.7;
+
+// Transport API: 'tcp' (zwykły fetch) lub 'unix' (gniazdo przez komendę Tauri api_request)
+let apiTransport = null;
+// Nonce tej instancji aplikacji, wymagany przez endpointy sesji i Bitwarden (X-Instance-Nonce)
+let instanceNonce;
+
+async function apiFetch(path, options = {}) {
+    if (apiTransport === null) {
+        apiTransport = window.__TAURI__
+            ? await window.__TAURI__.core.invoke('api_transport').catch(() => 'tcp')
+            : 'tcp';
+    }
+    if (apiTransport !== 'unix') {
+        if (instanceNonce === undefined) {
+            instanceNonce = window.__TAURI__
+                ? await window.__TAURI__.core.invoke('instance_nonce').catch(() => null)
+                : null;
+        }
+        const headers = new Headers(options.headers || {});
+        if (instanceNonce) {
+            headers.set('X-Instance-Nonce', instanceNonce);
+        }
+        return fetch(`${API_URL}${path}`, { ...options, headers });
+    }
+
+    const reply = await window.__TAURI__.core.invoke('api_request', {
+        method: options.method || 'GET',
+        path,
+        body: options.body || null
+    });
+    return new Response(reply.body, {
+        status: reply.status,
+        headers: reply.content_type ? { 'Content-Type': reply.content_type } : {}
+    });
+}
 
 // Global state management
 let appState = {
@@ -39,8 +76,8 @@ async function initializeApp() {
         // Check backend connection
         await checkBackendConnection();
         
-        // Load user session if exists
-        await loadUserSession();
+        // Backendy (baza, Redis, Bitwarden) startują w tle - sesja wczytuje się, gdy baza jest gotowa
+        watchStartup();
         
         // Load settings
         loadSettings();
@@ -180,7 +217,7 @@ function showTab(tabId) {
 // Check if backend is running
 async function checkBackendConnection() {
     try {
-        const response = await fetch(`${API_URL}/health`);
+        const response = await apiFetch('/health');
         if (response.ok) {
             showStatus('✅ Połączenie z backendem nawiązane', 'success');
         } else {
@@ -191,10 +228,43 @@ async function checkBackendConnection() {
     }
 }
 
+// Postęp startu backendów z /setup/status; funkcje włączają się, gdy ich backend jest gotowy
+const STARTUP_POLL_MS = 2000;
+
+async function watchStartup() {
+    const announced = new Set();
+    while (true) {
+        try {
+            const response = await apiFetch('/setup/status');
+            if (response.ok) {
+                const { startup } = await response.json();
+                for (const backend of startup.backends) {
+                    if (backend.state === 'ready' && !announced.has(backend.backend)) {
+                        announced.add(backend.backend);
+                        if (backend.backend === 'database') {
+                            await loadUserSession();
+                        }
+                        showNotification(`✅ ${backend.backend} gotowy`, 'success');
+                    } else if (backend.state === 'failed' && !announced.has(`${backend.backend}:failed`)) {
+                        announced.add(`${backend.backend}:failed`);
+                        showNotification(`⚠️ ${backend.backend}: ${backend.error}`, 'warning');
+                    }
+                }
+                if (startup.complete) {
+                    return;
+                }
+            }
+        } catch (error) {
+            console.error('Startup status error:', error);
+        }
+        await new Promise(resolve => setTimeout(resolve, STARTUP_POLL_MS));
+    }
+}
+
 // Session Management Functions
 async function loadUserSession() {
     try {
-        const response = await fetch(`${API_URL}/session/get`, {
+        const response = await apiFetch('/session/get', {
             credentials: 'include'
         });
         
@@ -219,7 +289,7 @@ async function saveUserSession() {
     
     try {
         const userData = collectUserData();
-        const response = await fetch(`${API_URL}/session/create`, {
+        const response = await apiFetch('/session/create', {
             method: 'POST',
             headers: { 'Content-Type': 'application/json' },
             credentials: 'include',
@@ -252,7 +322,7 @@ async function handleBitwardenLogin() {
     showStatus('🔑 Logowanie do Bitwarden...', 'info');
     
     try {
-        const response = await fetch(`${API_URL}/bitwarden/login`, {
+        const response = await apiFetch('/bitwarden/login', {
             method: 'POST',
             headers: { 'Content-Type': 'application/json' },
             body: JSON.stringify({ email, password })
@@ -287,7 +357,7 @@ async function handleBitwardenUnlock() {
     showStatus('🔓 Odblokowywanie sejfu...', 'info');
     
     try {
-        const response = await fetch(`${API_URL}/bitwarden/unlock`, {
+        const response = await apiFetch('/bitwarden/unlock', {
             method: 'POST',
             headers: { 'Content-Type': 'application/json' },
             body: JSON.stringify({ master_password: masterPassword })
@@ -358,7 +428,7 @@ async function refreshBitwardenCredentials() {
     showStatus('🔄 Pobieranie danych uwierzytelniających...', 'info');
     
     try {
-        const response = await fetch(`${API_URL}/bitwarden/credentials`);
+        const response = await apiFetch('/bitwarden/credentials');
         
         if (response.ok) {
             const data = await response.json();
@@ -556,7 +626,7 @@ async function analyzePage() {
         updateProgress(50);
         
         // Fetch HTML through CDP
-        const response = await fetch(`${API_URL}/page/analyze`);
+        const response = await apiFetch('/page/analyze');
         
         if (!response.ok) {
             throw new Error(`HTTP ${response.status}: ${response.statusText}`);
@@ -590,7 +660,7 @@ async function refreshLogs() {
     showStatus('📋 Pobieranie logów...', 'info');
     
     try {
-        const response = await fetch(`${API_URL}/logs/get`);
+        const response = await apiFetch('/logs/get');
         
         if (response.ok) {
             const data = await response.json();
@@ -612,7 +682,7 @@ async function refreshLogs() {
 async function clearLogs() {
     if (confirm('🗑️ Czy na pewno chcesz wyczyścić wszystkie logi?')) {
         try {
-            const response = await fetch(`${API_URL}/logs/clear`, { method: 'POST' });
+            const response = await apiFetch('/logs/clear', { method: 'POST' });
             
             if (response.ok) {
                 appState.logs = [];
@@ -735,6 +805,35 @@ function updateLogStatistics() {
     }
 }
 
+// Czyta odpowiedź SSE (event/data) i wywołuje onEvent dla każdego zdarzenia
+async function readServerEvents(response, onEvent) {
+    const reader = response.body.getReader();
+    const decoder = new TextDecoder();
+    let buffer = '';
+    
+    const dispatch = (block) => {
+        let event = 'message';
+        const data = [];
+        for (const line of block.split('\n')) {
+            if (line.startsWith('event:')) event = line.slice(6).trim();
+            else if (line.startsWith('data:')) data.push(line.slice(5).replace(/^ /, ''));
+        }
+        if (data.length > 0) onEvent(event, JSON.parse(data.join('\n')));
+    };
+    
+    for (;;) {
+        const { value, done } = await reader.read();
+        if (done) break;
+        buffer += decoder.decode(value, { stream: true }).replace(/\r\n/g, '\n');
+        let end;
+        while ((end = buffer.indexOf('\n\n')) >= 0) {
+            dispatch(buffer.slice(0, end));
+            buffer = buffer.slice(end + 2);
+        }
+    }
+    if (buffer.trim()) dispatch(buffer);
+}
+
 // Generate DSL script
 async function generateDSL() {
     if (!appState.currentPageHTML) {
@@ -753,7 +852,7 @@ async function generateDSL() {
     showStatus('⚡ Generuję skrypt DSL...', 'info');
     
     try {
-        const response = await fetch(`${API_URL}/dsl/generate`, {
+        const response = await apiFetch('/dsl/generate/stream', {
             method: 'POST',
             headers: { 
                 'Content-Type': 'application/json' 
@@ -768,16 +867,42 @@ async function generateDSL() {
             throw new Error(`HTTP ${response.status}: ${response.statusText}`);
         }
         
-        updateProgress(75);
-        
-        const data = await response.json();
-        const script = data.script || '';
-        
+        // Skrypt rośnie w polu edycji w miarę, jak model zwraca kolejne komendy
         const dslScript = document.getElementById('dsl-script');
+        if (dslScript) dslScript.value = '';
+        let lines = 0;
+        let script = null;
+        let mapping = [];
+        await readServerEvents(response, (event, data) => {
+            if (event === 'line') {
+                lines += 1;
+                if (dslScript) dslScript.value += (dslScript.value ? '\n' : '') + data.line;
+                updateProgress(Math.min(90, 25 + lines * 5));
+            } else if (event === 'done') {
+                script = data.script || '';
+                mapping = data.mapping || [];
+            } else if (event === 'error') {
+                throw new Error(data.error);
+            }
+        });
+        if (script === null) {
+            throw new Error('Generowanie przerwane przed zakończeniem');
+        }
+        
+        // Ostateczna wersja zawiera poprawki (np. waitfor po nawigacji)
         if (dslScript) dslScript.value = script;
         
         updateProgress(100);
-        showStatus('✅ Skrypt DSL wygenerowany pomyślnie', 'success');
+        // Niepewne dopasowania pól (poniżej 70%) użytkownik potwierdza przed uruchomieniem
+        const uncertain = mapping.filter(field => field.confidence < LOW_CONFIDENCE);
+        if (uncertain.length > 0) {
+            const fields = uncertain
+                .map(field => `${field.key} → ${field.label || field.selector} (${Math.round(field.confidence * 100)}%)`)
+                .join(', ');
+            showStatus(`⚠️ Sprawdź dopasowanie pól: ${fields}`, 'warning');
+        } else {
+            showStatus('✅ Skrypt DSL wygenerowany pomyślnie', 'success');
+        }
         
         // Enable run button
         const runBtn = document.getElementById('run-btn');
@@ -815,7 +940,7 @@ async function runAutomation() {
     showStatus('🚀 Uruchamiam automatyzację...', 'info');
     
     try {
-        const response = await fetch(`${API_URL}/rpa/run`, {
+        const response = await apiFetch('/rpa/run', {
             method: 'POST',
             headers: { 
                 'Content-Type': 'application/json' 